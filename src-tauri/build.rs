@@ -1,3 +1,152 @@
+/// アプリのコマンド。ここに挙げたコマンドは、`permissions/` の権限を与えたウィンドウからしか呼べない
+const COMMANDS: &[&str] = &[
+    "get_audio_sessions",
+    "get_sessions_lite",
+    "get_session",
+    "get_session_icon",
+    "set_session_volume",
+    "set_session_mute",
+    "send_media_command",
+    "set_audio_routing",
+    "force_audio_routing",
+    "get_audio_devices",
+    "set_default_device",
+    "get_capture_devices",
+    "get_legacy_streams",
+    "set_default_capture_device",
+    "get_virtual_cables",
+    "route_app_through_cable",
+    "monitor_virtual_cable",
+    "get_voicemeeter",
+    "set_voicemeeter_enabled",
+    "set_voicemeeter_gain",
+    "set_voicemeeter_mute",
+    "start_input_meter",
+    "stop_input_meter",
+    "get_input_gain",
+    "set_input_gain",
+    "set_input_boost",
+    "get_capture_effects",
+    "set_capture_effect",
+    "get_microphone_users",
+    "get_call_panel",
+    "set_call_volume",
+    "is_elevated_helper_running",
+    "start_elevated_helper",
+    "stop_elevated_helper",
+    "get_sound_schemes",
+    "set_sound_scheme",
+    "play_system_sound",
+    "open_sound_settings",
+    "get_usage_stats",
+    "get_volume_history",
+    "clear_volume_history",
+    "export_mixer_state",
+    "get_volume_limit",
+    "set_volume_limit",
+    "request_volume_limit_override",
+    "confirm_volume_limit_override",
+    "end_volume_limit_override",
+    "get_device_pairs",
+    "add_device_pair",
+    "remove_device_pair",
+    "get_app_rules",
+    "add_app_rule",
+    "remove_app_rule",
+    "get_profiles",
+    "save_profile",
+    "apply_profile",
+    "remove_profile",
+    "set_startup_profile",
+    "get_backup_config",
+    "set_backup_config",
+    "back_up_settings",
+    "get_settings_backups",
+    "restore_settings_backup",
+    "undo",
+    "redo",
+    "get_undo_status",
+    "subscribe_events",
+    "unsubscribe_events",
+    "get_event_subscriptions",
+    "get_api_version",
+    "set_event_schema",
+    "watch_sessions",
+    "set_bluetooth_profile",
+    "probe_device_formats",
+    "play_tone",
+    "play_signal",
+    "start_metronome",
+    "set_metronome_tempo",
+    "play_file",
+    "seek_playback",
+    "play_synced",
+    "set_playback_delay",
+    "start_burn_in",
+    "get_burn_in",
+    "stop_burn_in",
+    "measure_frequency_response",
+    "stop_playback",
+    "start_mirror",
+    "set_mirror_delay",
+    "stop_mirror",
+    "get_mirrors",
+    "start_boost",
+    "set_boost_gain",
+    "stop_boost",
+    "get_boosts",
+    "start_file_capture",
+    "stop_file_capture",
+    "start_network_stream",
+    "stop_network_stream",
+    "get_network_stream",
+    "get_master_volume",
+    "refresh_audio_state",
+    "get_audio_summary",
+    "set_master_volume",
+    "set_master_volume_db",
+    "set_master_mute",
+    "step_master_volume",
+    "get_hidden_apps",
+    "hide_app",
+    "unhide_app",
+    "get_pinned_apps",
+    "pin_app",
+    "unpin_app",
+    "get_device_change_toast",
+    "set_device_change_toast",
+    "get_pause_on_unplug",
+    "set_pause_on_unplug",
+    "get_flyout_placement",
+    "set_flyout_placement",
+    "get_app_overrides",
+    "set_app_override",
+    "is_auto_launch_enabled",
+    "toggle_auto_launch",
+    "set_tactical_mode",
+    "get_backdrop",
+    "get_locale",
+    "check_for_update",
+    "install_update",
+    "get_hud_mode",
+    "set_hud_mode",
+    "get_volume_stepping",
+    "set_volume_stepping",
+    "get_flyout_shortcut",
+    "set_flyout_shortcut",
+    "get_media_key_target",
+    "set_media_key_target",
+    "get_hotkeys",
+    "add_hotkey",
+    "remove_hotkey",
+    "check_hotkey_conflicts",
+    "get_pending_crash_report",
+    "open_crash_report",
+    "dismiss_crash_report",
+    "report_frontend_crash",
+];
+
 fn main() {
-    tauri_build::build()
+    tauri_build::try_build(tauri_build::Attributes::new().app_manifest(tauri_build::AppManifest::new().commands(COMMANDS)))
+        .expect("failed to run tauri-build");
 }
@@ -0,0 +1,31 @@
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose, Engine as _};
+
+/// 色として数えない彩度と明るさ。白・黒・灰色の縁取りや影でアクセントが濁らないようにする
+const MIN_SATURATION: f32 = 0.25;
+const MIN_BRIGHTNESS: u8 = 48;
+
+/// アイコン (PNG の base64) で最も多く使われている色を `#rrggbb` で返します。
+/// 色を似たもの同士にまとめて最も画素の多いまとまりの平均を取ります。色のない（灰色だけの）アイコンは `None` です。
+pub fn accent_color(icon_base64: &str) -> Option<String> {
+    let bytes = general_purpose::STANDARD.decode(icon_base64).ok()?;
+    let image = image::load_from_memory(&bytes).ok()?.to_rgba8();
+    // 各チャンネルの上位 3 ビットでまとめ、まとまりごとに画素数と色の合計を数える
+    let mut buckets: BTreeMap<(u8, u8, u8), (u32, [u32; 3])> = BTreeMap::new();
+    for pixel in image.pixels() {
+        let [r, g, b, a] = pixel.0;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        if a < 128 || max < MIN_BRIGHTNESS || ((max - min) as f32 / max as f32) < MIN_SATURATION {
+            continue;
+        }
+        let (count, sum) = buckets.entry((r >> 5, g >> 5, b >> 5)).or_default();
+        *count += 1;
+        sum[0] += r as u32;
+        sum[1] += g as u32;
+        sum[2] += b as u32;
+    }
+    let (count, sum) = buckets.into_values().max_by_key(|(count, _)| *count)?;
+    Some(format!("#{:02x}{:02x}{:02x}", sum[0] / count, sum[1] / count, sum[2] / count))
+}
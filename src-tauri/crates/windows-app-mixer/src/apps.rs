@@ -0,0 +1,44 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::icon_file;
+use crate::AppOverride;
+
+/// 非表示設定と表示名・アイコンの上書き。どのバックエンドでも同じ規則で適用します。
+#[derive(Debug, Default)]
+pub struct AppPresentation {
+    hidden_apps: HashSet<String>,
+    app_overrides: HashMap<String, AppOverride>,
+    override_icon_cache: HashMap<String, Option<String>>,
+}
+
+impl AppPresentation {
+    pub fn set_hidden_apps(&mut self, names: Vec<String>) {
+        self.hidden_apps = names.into_iter().collect();
+    }
+
+    pub fn set_app_overrides(&mut self, overrides: HashMap<String, AppOverride>) {
+        self.app_overrides = overrides;
+        self.override_icon_cache.clear();
+    }
+
+    /// `exe_key` は大文字の実行ファイル名です。
+    pub fn is_hidden(&self, exe_key: &str) -> bool {
+        self.hidden_apps.contains(exe_key)
+    }
+
+    /// 上書き設定があればその表示名を、なければ `fallback`（バックエンドが調べた製品名か実行ファイル名）を返します。
+    pub fn display_name(&self, exe_key: &str, fallback: &str) -> String {
+        self.app_overrides.get(exe_key)
+            .and_then(|o| o.display_name.clone())
+            .unwrap_or_else(|| fallback.to_string())
+    }
+
+    /// 上書き設定で指定された画像ファイルのアイコンを返します。
+    pub fn override_icon(&mut self, exe_key: &str) -> Option<String> {
+        let path = self.app_overrides.get(exe_key)?.icon_path.clone()?;
+        self.override_icon_cache
+            .entry(path)
+            .or_insert_with_key(|p| icon_file::load_icon_file_base64(p))
+            .clone()
+    }
+}
@@ -0,0 +1,204 @@
+//! 音声ファイル（WAV / FLAC / Ogg Vorbis / MP3）の再生。symphonia でデコードしたものを
+//! ジェネレーターの信号源として流すので、出力先やフォーマットの選び方はトーンと同じです。
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::{Time, TimeBase};
+
+use crate::generator::SignalSource;
+use crate::{AudioError, DeviceFormat};
+
+const NO_SEEK: u64 = u64::MAX;
+
+/// 再生中に頼めるシーク。出力スレッドと共有し、次にバッファを埋めるときに移ります
+#[derive(Debug, Clone)]
+pub struct SeekRequest(Arc<AtomicU64>);
+
+impl Default for SeekRequest {
+    fn default() -> Self {
+        Self(Arc::new(AtomicU64::new(NO_SEEK)))
+    }
+}
+
+impl SeekRequest {
+    pub fn request(&self, position_ms: u64) {
+        self.0.store(position_ms.min(NO_SEEK - 1), Ordering::Relaxed);
+    }
+
+    fn take(&self) -> Option<u64> {
+        Some(self.0.swap(NO_SEEK, Ordering::Relaxed)).filter(|&ms| ms != NO_SEEK)
+    }
+}
+
+/// デコードしながら鳴らす音声ファイル
+pub struct AudioFileSource {
+    reader: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    time_base: Option<TimeBase>,
+    sample_rate: u32,
+    channels: usize,
+    /// ファイル全体のフレーム数。ヘッダーに書かれていない形式では `None`
+    total_frames: Option<u64>,
+    /// デコード済みでまだ鳴らしていないサンプル（インターリーブ）
+    pending: VecDeque<f32>,
+    /// 鳴らしたところまでのフレーム数（シーク先を含む）
+    position: u64,
+    /// シーク直後に、求めた位置まで読み捨てるフレーム数
+    skip: u64,
+    seek: SeekRequest,
+    finished: bool,
+}
+
+impl AudioFileSource {
+    pub fn open(path: &str, seek: SeekRequest) -> Result<Self, AudioError> {
+        let file = File::open(path).map_err(|e| AudioError::Backend(format!("Failed to open {}: {}", path, e)))?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = Path::new(path).extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
+        let unsupported = |e: DecodeError| AudioError::Backend(format!("Unsupported audio file {}: {}", path, e));
+        let probed = symphonia::default::get_probe()
+            .format(&hint, stream, &FormatOptions { enable_gapless: true, ..Default::default() }, &MetadataOptions::default())
+            .map_err(unsupported)?;
+        let reader = probed.format;
+        let track = reader.tracks().iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| AudioError::Backend(format!("No audio track in {}", path)))?;
+        let (track_id, params) = (track.id, track.codec_params.clone());
+        let (Some(sample_rate), Some(channels)) = (params.sample_rate, params.channels.map(|c| c.count())) else {
+            return Err(AudioError::Backend(format!("Unknown sample rate or channel layout in {}", path)));
+        };
+        let decoder = symphonia::default::get_codecs().make(&params, &DecoderOptions::default()).map_err(unsupported)?;
+        Ok(Self {
+            reader,
+            decoder,
+            track_id,
+            time_base: params.time_base,
+            sample_rate,
+            channels,
+            total_frames: params.n_frames,
+            pending: VecDeque::new(),
+            position: 0,
+            skip: 0,
+            seek,
+            finished: false,
+        })
+    }
+
+    /// ファイルのままのサンプルレートとチャンネル数の 32bit float。共有モードなら OS がデバイスに合わせて変換します
+    pub fn format(&self) -> DeviceFormat {
+        DeviceFormat { sample_rate: self.sample_rate, bits_per_sample: 32, container_bits: 32, float: true, channels: self.channels as u16 }
+    }
+
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.total_frames.map(|frames| self.frames_to_ms(frames))
+    }
+
+    pub fn position_ms(&self) -> u64 {
+        self.frames_to_ms(self.position)
+    }
+
+    fn frames_to_ms(&self, frames: u64) -> u64 {
+        frames * 1000 / u64::from(self.sample_rate)
+    }
+
+    /// タイムスタンプ（トラックのタイムベース）をフレーム数にします。
+    fn ts_to_frames(&self, ts: u64) -> u64 {
+        match self.time_base {
+            Some(base) => {
+                let time = base.calc_time(ts);
+                time.seconds * u64::from(self.sample_rate) + (time.frac * f64::from(self.sample_rate)) as u64
+            }
+            None => ts,
+        }
+    }
+
+    fn seek_to(&mut self, position_ms: u64) {
+        let position_ms = self.duration_ms().map_or(position_ms, |d| position_ms.min(d));
+        let time = Time::new(position_ms / 1000, (position_ms % 1000) as f64 / 1000.0);
+        match self.reader.seek(SeekMode::Accurate, SeekTo::Time { time, track_id: Some(self.track_id) }) {
+            Ok(seeked) => {
+                self.decoder.reset();
+                self.pending.clear();
+                self.position = self.ts_to_frames(seeked.required_ts);
+                self.skip = self.position.saturating_sub(self.ts_to_frames(seeked.actual_ts));
+                self.finished = false;
+            }
+            // ファイルの終わりより後ろなど。止めずにそのまま鳴らし続ける
+            Err(e) => eprintln!("Failed to seek: {}", e),
+        }
+    }
+
+    /// 次のパケットをデコードして `pending` に足します。終わりに達したら `false` を返します。
+    fn decode_next(&mut self) -> bool {
+        loop {
+            let packet = match self.reader.next_packet() {
+                Ok(packet) => packet,
+                Err(_) => return false,
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            match self.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+                    buffer.copy_interleaved_ref(decoded);
+                    let samples = buffer.samples();
+                    let skip = (self.skip as usize * self.channels).min(samples.len());
+                    self.skip -= (skip / self.channels) as u64;
+                    self.pending.extend(&samples[skip..]);
+                    return true;
+                }
+                // 壊れたパケットは飛ばす
+                Err(DecodeError::DecodeError(_)) => continue,
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+impl SignalSource for AudioFileSource {
+    fn fill(&mut self, out: &mut [f32], channels: usize) -> bool {
+        if let Some(position_ms) = self.seek.take() {
+            self.seek_to(position_ms);
+        }
+        let channels = channels.max(1);
+        for frame in out.chunks_mut(channels) {
+            while self.pending.len() < self.channels && !self.finished {
+                self.finished = !self.decode_next();
+            }
+            if self.pending.len() < self.channels {
+                frame.fill(0.0);
+                continue;
+            }
+            if channels == self.channels {
+                for (out, sample) in frame.iter_mut().zip(self.pending.drain(..self.channels)) {
+                    *out = sample;
+                }
+            } else {
+                // チャンネル数が違えば、モノラルにまとめてすべてのチャンネルへ
+                let mono = self.pending.drain(..self.channels).sum::<f32>() / self.channels as f32;
+                frame.fill(mono);
+            }
+            self.position += 1;
+        }
+        !(self.finished && self.pending.len() < self.channels)
+    }
+
+    fn progress_ms(&self) -> Option<(u64, Option<u64>)> {
+        Some((self.position_ms(), self.duration_ms()))
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::{
+    AppOverride, AudioDeviceInfo, AudioError, AudioNotification, AudioSessionInfo, BluetoothProfile, CaptureEffect, CaptureEffectKind,
+    BoostRequest, DefaultEndpoints, DeviceFormat, DeviceRole, FileCaptureRequest, InputGain, InputPeak, LegacyStream, MasterVolume, MediaCommand, MicrophoneUser, MirrorRequest, MixerEventSink, NetworkStream, NetworkStreamRequest, Notifier, PeakLevel, PlaybackRequest, Recording, SessionsDiff, SyncedPlaybackRequest,
+};
+
+/// プラットフォームごとのオーディオ実装。セッション、デバイス、出力先の切り替え、通知を扱います。
+/// 実装はオーディオスレッド上で生成・使用されるため、`Send` である必要はありません。
+pub trait AudioBackend {
+    /// ログや診断に使うバックエンド名
+    fn name(&self) -> &'static str;
+
+    /// アイコンを含むセッション一覧を返します。
+    fn get_sessions(&mut self) -> Result<Vec<AudioSessionInfo>, AudioError>;
+
+    /// アイコンを含まない軽量なセッション一覧を返します。
+    fn get_sessions_lite(&mut self) -> Result<Vec<AudioSessionInfo>, AudioError> {
+        let mut sessions = self.get_sessions()?;
+        for session in &mut sessions {
+            session.icon_base64 = None;
+        }
+        Ok(sessions)
+    }
+
+    /// 指定した PID のセッションを 1 件だけ返します。複数の出力先にセッションがある場合は最初のものです。
+    fn get_session(&mut self, pid: u32) -> Result<AudioSessionInfo, AudioError> {
+        self.get_sessions()?
+            .into_iter()
+            .find(|s| s.process_id == pid)
+            .ok_or(AudioError::SessionNotFound(pid))
+    }
+
+    /// 指定した PID のアイコンだけを取得します。
+    fn get_session_icon(&mut self, pid: u32) -> Result<Option<String>, AudioError>;
+
+    /// 前回の通知以降のセッション一覧の差分を返します。変更がなければ `None` です。
+    fn take_sessions_diff(&mut self) -> Result<Option<SessionsDiff>, AudioError>;
+
+    fn get_peak_levels(&mut self) -> Result<Vec<PeakLevel>, AudioError>;
+
+    fn set_session_volume(&mut self, pid: u32, volume: f32) -> Result<(), AudioError>;
+
+    fn set_session_mute(&mut self, pid: u32, mute: bool) -> Result<(), AudioError>;
+
+    /// `instance_id` のセッションだけの音量を変更します。同じプロセスの他のセッションは変わりません。
+    fn set_instance_volume(&mut self, instance_id: &str, volume: f32) -> Result<(), AudioError>;
+
+    fn set_instance_mute(&mut self, instance_id: &str, mute: bool) -> Result<(), AudioError>;
+
+    fn get_audio_devices(&mut self) -> Result<Vec<AudioDeviceInfo>, AudioError>;
+
+    /// プロセスの出力先デバイスを切り替えます。
+    fn set_audio_routing(&mut self, pid: u32, device_id: &str) -> Result<(), AudioError>;
+
+    /// `set_audio_routing` で設定した出力先を解除し、プロセスを既定のデバイスに従わせます。
+    #[allow(unused_variables)]
+    fn clear_audio_routing(&mut self, pid: u32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Per-app routing"))
+    }
+
+    /// `set_audio_routing` で設定した出力先に、プロセスが再生中のストリームをすぐ移すよう促します。
+    /// 移るかどうかはアプリ次第なので、結果はセッションの `route_pending` で確かめてください。
+    #[allow(unused_variables)]
+    fn force_route_migration(&mut self, pid: u32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Forcing route migration"))
+    }
+
+    /// プロセスのアプリにメディアの操作を送ります。`now_playing` のあるセッションだけが受け付けます。
+    #[allow(unused_variables)]
+    fn send_media_command(&mut self, pid: u32, command: MediaCommand) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Media controls"))
+    }
+
+    /// 既定の出力デバイス全体の音量とミュート状態を返します。
+    fn get_master_volume(&mut self) -> Result<MasterVolume, AudioError> {
+        Err(AudioError::Unsupported("Master volume"))
+    }
+
+    #[allow(unused_variables)]
+    fn set_master_volume(&mut self, volume: f32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Master volume"))
+    }
+
+    /// マスター音量を dB で設定します。範囲の外の値は範囲に収めます。
+    #[allow(unused_variables)]
+    fn set_master_volume_db(&mut self, db: f32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Master volume in dB"))
+    }
+
+    #[allow(unused_variables)]
+    fn set_master_mute(&mut self, mute: bool) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Master mute"))
+    }
+
+    /// 既定かどうかに関係なく、指定したデバイス全体の音量とミュート状態を返します。入力デバイスなら録音レベルです。
+    #[allow(unused_variables)]
+    fn get_device_volume(&mut self, device_id: &str) -> Result<MasterVolume, AudioError> {
+        Err(AudioError::Unsupported("Device volume"))
+    }
+
+    #[allow(unused_variables)]
+    fn set_device_volume(&mut self, device_id: &str, volume: f32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Device volume"))
+    }
+
+    #[allow(unused_variables)]
+    fn set_device_mute(&mut self, device_id: &str, mute: bool) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Device mute"))
+    }
+
+    /// 指定した出力デバイスの音量・ミュートの変化を [`crate::MixerEvent::DeviceVolumeChanged`] で通知します。
+    /// 前回の指定を置き換えます。デバイスが抜き差しされても監視を続けます。
+    #[allow(unused_variables)]
+    fn watch_device_volumes(&mut self, device_ids: Vec<String>) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Device volume notifications"))
+    }
+
+    /// システムの既定の出力デバイスを切り替えます。
+    #[allow(unused_variables)]
+    fn set_default_device(&mut self, device_id: &str) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Switching the default device"))
+    }
+
+    /// 入力デバイス（マイク）の一覧
+    fn get_capture_devices(&mut self) -> Result<Vec<AudioDeviceInfo>, AudioError> {
+        Err(AudioError::Unsupported("Listing capture devices"))
+    }
+
+    /// システムの既定の入力デバイスを切り替えます。`role` を省略するとすべての役割で切り替えます。
+    #[allow(unused_variables)]
+    fn set_default_capture_device(&mut self, device_id: &str, role: Option<DeviceRole>) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Switching the default capture device"))
+    }
+
+    /// 入力デバイスの音を `output_device_id` の出力デバイスでそのまま聴けるようにします（Windows の「このデバイスを聴く」）。
+    /// `None` でやめます。設定は OS に保存され、このアプリを閉じても続きます。
+    #[allow(unused_variables)]
+    fn set_input_monitor(&mut self, capture_device_id: &str, output_device_id: Option<&str>) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Listening to a capture device"))
+    }
+
+    /// 入力デバイスのレベルの計測を始めます。`device_id` を省略すると既定の入力デバイスを追いかけます。
+    /// 計測中のものがあれば置き換えます。
+    #[allow(unused_variables)]
+    fn start_input_meter(&mut self, device_id: Option<&str>) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Input level metering"))
+    }
+
+    /// 入力レベルの計測を止めます。計測していなければ何もしません。
+    fn stop_input_meter(&mut self) {}
+
+    /// 計測中の入力デバイスのピーク値。計測していなければ `None` です。
+    fn get_input_peak(&mut self) -> Option<InputPeak> {
+        None
+    }
+
+    /// 入力デバイスの音量とマイクブーストを dB で返します。
+    #[allow(unused_variables)]
+    fn get_input_gain(&mut self, device_id: &str) -> Result<InputGain, AudioError> {
+        Err(AudioError::Unsupported("Input gain"))
+    }
+
+    /// 入力デバイスの音量を dB で設定します。範囲外の値は範囲内に収めます。
+    #[allow(unused_variables)]
+    fn set_input_gain(&mut self, device_id: &str, db: f32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Input gain"))
+    }
+
+    /// ハードウェアのマイクブーストを dB で設定します。すべてのチャンネルに同じ値を設定します。
+    #[allow(unused_variables)]
+    fn set_input_boost(&mut self, device_id: &str, db: f32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Microphone boost"))
+    }
+
+    /// 入力デバイスのドライバーが公開しているエコーキャンセル・ノイズ抑制・自動ゲイン調整。
+    /// 公開していない処理は含まれません。
+    #[allow(unused_variables)]
+    fn get_capture_effects(&mut self, device_id: &str) -> Result<Vec<CaptureEffect>, AudioError> {
+        Err(AudioError::Unsupported("Capture effects"))
+    }
+
+    #[allow(unused_variables)]
+    fn set_capture_effect(&mut self, device_id: &str, kind: CaptureEffectKind, enabled: bool) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Capture effects"))
+    }
+
+    /// セッションを持たない古い API（DirectSound / MME）で再生しているらしいプロセス。
+    /// 「システム音」にまとめられたり、どこにも表示されなかったりする音の出どころを示すための推測です。
+    fn get_legacy_streams(&mut self) -> Result<Vec<LegacyStream>, AudioError> {
+        Err(AudioError::Unsupported("Detecting legacy audio streams"))
+    }
+
+    /// 録音中のキャプチャーセッションを持つアプリ。非表示の設定にかかわらずすべて返します。
+    fn get_microphone_users(&mut self) -> Result<Vec<MicrophoneUser>, AudioError> {
+        Err(AudioError::Unsupported("Listing microphone users"))
+    }
+
+    /// `role` の既定の出力・入力デバイス。役割ごとの既定がないプラットフォームでは、ふだんの既定のデバイスを返します。
+    #[allow(unused_variables)]
+    fn get_default_endpoints(&mut self, role: DeviceRole) -> Result<DefaultEndpoints, AudioError> {
+        let output = self.get_audio_devices()?.into_iter().find(|d| d.is_default).map(|d| d.id);
+        // 入力デバイスを列挙できないプラットフォームでは入力はないものとする
+        let input = self.get_capture_devices().ok().and_then(|devices| devices.into_iter().find(|d| d.is_default)).map(|d| d.id);
+        Ok(DefaultEndpoints { output, input })
+    }
+
+    /// 通信用の既定の出力デバイスの音量と、既定のマイクの録音レベルをまとめて設定します。
+    fn set_communications_volume(&mut self, volume: f32) -> Result<(), AudioError> {
+        let endpoints = self.get_default_endpoints(DeviceRole::Communications)?;
+        for device_id in endpoints.output.iter().chain(&endpoints.input) {
+            self.set_device_volume(device_id, volume)?;
+        }
+        Ok(())
+    }
+
+    /// Bluetooth 機器のプロファイルを切り替えます。切り替えると出力デバイスが作り直されることがあります。
+    #[allow(unused_variables)]
+    fn set_bluetooth_profile(&mut self, device_id: &str, profile: BluetoothProfile) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Switching Bluetooth profiles"))
+    }
+
+    /// デバイスが排他モードでそのまま受け付けるフォーマットを調べます。
+    #[allow(unused_variables)]
+    fn probe_device_formats(&mut self, device_id: &str) -> Result<Vec<DeviceFormat>, AudioError> {
+        Err(AudioError::Unsupported("Exclusive-mode format probing"))
+    }
+
+    /// ジェネレーターの再生を始めます。再生中のものがあれば止めて置き換えます。
+    #[allow(unused_variables)]
+    fn start_playback(&mut self, request: PlaybackRequest) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Tone playback"))
+    }
+
+    /// 同じ信号を複数のデバイスで同時に鳴らし始めます。再生中のものがあれば止めて置き換えます。
+    #[allow(unused_variables)]
+    fn start_synced_playback(&mut self, request: SyncedPlaybackRequest) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Synchronized playback"))
+    }
+
+    /// 同期再生中のデバイスの遅延を、止めずに変えます。
+    #[allow(unused_variables)]
+    fn set_playback_delay(&mut self, device_id: &str, delay_ms: u32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Synchronized playback"))
+    }
+
+    /// ジェネレーターの再生を止めます。再生していなければ何もしません。
+    fn stop_playback(&mut self) {}
+
+    /// 再生中のメトロノームのテンポを変えます。
+    #[allow(unused_variables)]
+    fn set_metronome_tempo(&mut self, bpm: f32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Metronome"))
+    }
+
+    /// 再生中の音声ファイルの再生位置を `position_ms` に移します。
+    #[allow(unused_variables)]
+    fn seek_playback(&mut self, position_ms: u64) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Seeking"))
+    }
+
+    /// 入力デバイスの録音を始めます。`device_id` を省略すると既定の入力デバイスを録ります。
+    /// `max_ms` を超えた分は捨てます。録音中のものがあれば置き換えます。
+    #[allow(unused_variables)]
+    fn start_recording(&mut self, device_id: Option<&str>, max_ms: u32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Recording"))
+    }
+
+    /// 録音を止めて、録った音を返します。
+    fn stop_recording(&mut self) -> Result<Recording, AudioError> {
+        Err(AudioError::Unsupported("Recording"))
+    }
+
+    /// システムの音（出力デバイスの音かアプリの音）をファイルに録り始めます。録音中のものがあれば止めて置き換えます。
+    /// 止まると [`crate::MixerEvent::FileCaptureStopped`] が届きます。
+    #[allow(unused_variables)]
+    fn start_file_capture(&mut self, request: FileCaptureRequest) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Recording to a file"))
+    }
+
+    /// ファイルへの録音を止めて、作ったファイルを返します。
+    fn stop_file_capture(&mut self) -> Result<Vec<String>, AudioError> {
+        Err(AudioError::Unsupported("Recording to a file"))
+    }
+
+    /// システムの音（出力デバイスの音かアプリの音）を HTTP で LAN に配信し始めます。配信中のものがあれば止めて置き換えます。
+    /// 止まると [`crate::MixerEvent::NetworkStreamStopped`] が届きます。
+    #[allow(unused_variables)]
+    fn start_network_stream(&mut self, request: NetworkStreamRequest) -> Result<NetworkStream, AudioError> {
+        Err(AudioError::Unsupported("Streaming to the network"))
+    }
+
+    fn stop_network_stream(&mut self) {}
+
+    /// 動いている配信
+    fn get_network_stream(&mut self) -> Option<NetworkStream> {
+        None
+    }
+
+    /// アプリの音を取り込んで別の出力デバイスにも流します。同じアプリのミラーがあれば置き換えます。
+    #[allow(unused_variables)]
+    fn start_mirror(&mut self, request: MirrorRequest) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Mirroring app audio"))
+    }
+
+    /// 動いているミラーの遅延を、止めずに変えます。
+    #[allow(unused_variables)]
+    fn set_mirror_delay(&mut self, pid: u32, delay_ms: u32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Mirroring app audio"))
+    }
+
+    fn stop_mirror(&mut self, _pid: u32) {}
+
+    /// 動いているミラー
+    fn get_mirrors(&mut self) -> Vec<MirrorRequest> {
+        Vec::new()
+    }
+
+    /// アプリの音を、今の出力デバイスで 100% より大きくします。同じアプリのブーストがあれば置き換えます。
+    #[allow(unused_variables)]
+    fn start_boost(&mut self, request: BoostRequest) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Boosting app audio"))
+    }
+
+    /// 動いているブーストの量を、止めずに変えます。
+    #[allow(unused_variables)]
+    fn set_boost_gain(&mut self, pid: u32, gain_db: f32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Boosting app audio"))
+    }
+
+    fn stop_boost(&mut self, _pid: u32) {}
+
+    /// 動いているブースト
+    fn get_boosts(&mut self) -> Vec<BoostRequest> {
+        Vec::new()
+    }
+
+    /// 非表示にする実行ファイル名（大文字）を設定します。
+    fn set_hidden_apps(&mut self, names: Vec<String>);
+
+    /// 実行ファイル名（大文字）ごとの表示名・アイコン上書きを設定します。
+    fn set_app_overrides(&mut self, overrides: HashMap<String, AppOverride>);
+
+    /// 権限が足りずに開けないプロセスの実行ファイルのパスを PID ごとに教えます。セッションの名前とアイコンに使います。
+    fn set_process_paths(&mut self, _paths: HashMap<u32, String>) {}
+
+    /// アイコンのディスクキャッシュの保存先を設定します。`None` ならメモリのみを使います。
+    fn set_icon_cache_dir(&mut self, _dir: Option<PathBuf>) {}
+
+    /// セッション・アイコンなどのキャッシュをすべて捨て、次の取得で列挙し直させます。
+    /// キャッシュと OS の状態が食い違ったときの最後の手段です。
+    fn clear_caches(&mut self) {}
+
+    /// OS のコールバックからの通知をキャッシュに反映します。
+    fn handle_notification(&mut self, _notification: AudioNotification) {}
+
+    /// 実行ファイルが更新されたアプリのアイコンと表示名を読み直し、セッションの変化として通知させます。
+    /// ワーカーがティックごとに呼ぶため、確認の間隔はバックエンドで空けてください。
+    fn check_app_updates(&mut self) {}
+
+    /// OS のオーディオサービスとの接続が保たれているかを返します。
+    /// `false` を返すとワーカーはバックエンドを破棄して作り直します。頻繁に呼ばれるため軽量にしてください。
+    fn check_health(&mut self) -> bool {
+        true
+    }
+}
+
+/// 実行中のプラットフォームのバックエンドを生成します。オーディオスレッド上で呼んでください。
+#[allow(unused_variables)]
+pub fn create_platform_backend(
+    notifier: Notifier,
+    sink: Arc<dyn MixerEventSink>,
+) -> Result<Box<dyn AudioBackend>, AudioError> {
+    #[cfg(windows)]
+    return Ok(Box::new(crate::wasapi::WasapiBackend::new(notifier, sink)?));
+    #[cfg(target_os = "macos")]
+    return Ok(Box::new(crate::coreaudio::CoreAudioBackend::new(notifier, sink)?));
+    #[cfg(target_os = "linux")]
+    return Ok(Box::new(crate::pulse::PulseBackend::new(notifier, sink)?));
+    #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+    Err(AudioError::Unsupported("Audio backend"))
+}
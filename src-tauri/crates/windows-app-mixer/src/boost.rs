@@ -0,0 +1,55 @@
+//! アプリの音のブースト（100% を超える音量）の信号処理。プロセスループバックで取り込める音はセッションの音量と
+//! ミュートを通った後のものなので、元の音を消して置き換えることはできません。元の音はそのまま鳴らし、
+//! 足りない分だけを同じ出力デバイスに重ねて流します。合計が上限を超えないよう、合計に対してリミッターをかけます。
+
+use std::time::Duration;
+
+/// 元の音と重ねた音を合わせた振幅の上限 (-1 dBFS)
+pub const CEILING: f32 = 0.891;
+
+/// 抑えた音量を戻す速さ。抑えた分が 1/e になるまでの時間
+const RELEASE: Duration = Duration::from_millis(100);
+
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// 取り込んだ音から、元の音に重ねる分を作ります。定位が変わらないよう、すべてのチャンネルを同じだけ抑えます。
+/// 抑えは振幅が上限を超えるサンプルですぐにかけ、その後ゆっくり戻します。
+pub struct BoostLimiter {
+    gain: f32,
+    channels: usize,
+    /// いまの抑え (0.0〜1.0)。1.0 なら抑えていない
+    reduction: f32,
+    /// 1 フレームごとに抑えの残りに掛ける係数
+    release: f32,
+}
+
+impl BoostLimiter {
+    pub fn new(gain_db: f32, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            gain: db_to_gain(gain_db),
+            channels: usize::from(channels).max(1),
+            reduction: 1.0,
+            release: (-1.0 / (RELEASE.as_secs_f32() * sample_rate as f32)).exp(),
+        }
+    }
+
+    pub fn set_gain_db(&mut self, gain_db: f32) {
+        self.gain = db_to_gain(gain_db);
+    }
+
+    /// インターリーブされた取り込んだ音を、元の音に重ねる分に書き換えます。
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_mut(self.channels) {
+            let peak = frame.iter().fold(0.0f32, |peak, s| peak.max(s.abs())) * self.gain;
+            let target = if peak > CEILING { CEILING / peak } else { 1.0 };
+            self.reduction = (1.0 - (1.0 - self.reduction) * self.release).min(target);
+            // 元の音だけで上限を超えているところには何も足さない
+            let extra = (self.gain * self.reduction - 1.0).max(0.0);
+            for sample in frame {
+                *sample *= extra;
+            }
+        }
+    }
+}
@@ -0,0 +1,553 @@
+//! システムの音をファイルに録るときのエンコードと分割。音の取り込み（出力デバイスのループバックやアプリ単体の取り込み）は
+//! バックエンドが受け持ち、ここでは届いた 32bit float を WAV / FLAC / Ogg Opus にして書き出します。
+//! 長く録るときは、大きさか長さでファイルを分けられます。
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flacenc::bitsink::MemSink;
+use flacenc::component::{BitRepr, StreamInfo};
+use flacenc::config;
+use flacenc::error::{Verified, Verify};
+use flacenc::source::{Fill, FrameBuf};
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+use opus_rs::{Application, OpusEncoder};
+
+use crate::AudioError;
+
+/// `sample_rate` を省略したときのサンプルレート (Hz)
+pub const DEFAULT_CAPTURE_SAMPLE_RATE: u32 = 48_000;
+const MIN_SAMPLE_RATE: u32 = 8000;
+const MAX_SAMPLE_RATE: u32 = 192_000;
+/// Opus で使えるサンプルレート (Hz)
+const OPUS_SAMPLE_RATES: [u32; 5] = [8000, 12_000, 16_000, 24_000, 48_000];
+const MIN_OPUS_KBPS: u32 = 6;
+const MAX_OPUS_KBPS: u32 = 510;
+/// 分割の大きさと長さの下限。小さすぎるとファイルが増えすぎる
+const MIN_ROTATION_BYTES: u64 = 1 << 20;
+const MIN_ROTATION_MS: u64 = 1000;
+/// WAV のヘッダーの長さ。データの長さは 32bit なので、WAV はこれを含めて 4 GiB で必ず分ける
+const WAV_HEADER_BYTES: u64 = 44;
+const WAV_MAX_BYTES: u64 = u32::MAX as u64;
+/// FLAC の 1 フレームのサンプル数
+const FLAC_BLOCK_SIZE: usize = 4096;
+/// `fLaC` と STREAMINFO のブロックヘッダーの長さ。閉じるときにこの後ろの STREAMINFO を書き直す
+const FLAC_STREAM_INFO_OFFSET: u64 = 8;
+/// Opus の 1 パケットの長さ (ms)
+const OPUS_FRAME_MS: usize = 20;
+/// デコーダーが先頭で捨てるサンプル数（48 kHz 換算）。エンコーダーの先読みの分
+const OPUS_PRE_SKIP: u16 = 312;
+/// Opus のパケットの最大長
+const OPUS_MAX_PACKET: usize = 4000;
+
+/// 録る音
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum CaptureSource {
+    /// 出力デバイスに流れているすべての音。`None` なら既定の出力デバイス
+    Device(Option<String>),
+    /// アプリ（子プロセスを含む）の音だけ
+    App(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum FileEncoding {
+    /// 16 / 24bit の整数か、32bit の float
+    Wav { bits_per_sample: u16 },
+    /// 16 / 24bit
+    Flac { bits_per_sample: u16 },
+    OggOpus { bitrate_kbps: u32 },
+}
+
+impl FileEncoding {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Wav { .. } => "wav",
+            Self::Flac { .. } => "flac",
+            Self::OggOpus { .. } => "opus",
+        }
+    }
+}
+
+/// ファイルを分ける条件。どちらかに達したら次のファイルに移ります
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[serde(default)]
+pub struct Rotation {
+    pub max_bytes: Option<u64>,
+    pub max_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct FileCaptureRequest {
+    pub source: CaptureSource,
+    /// 書き出すフォルダー。なければ作ります
+    pub directory: String,
+    /// ファイル名の頭。`{file_prefix}-001.flac` のように番号を付けます
+    pub file_prefix: String,
+    pub encoding: FileEncoding,
+    /// `None` なら [`DEFAULT_CAPTURE_SAMPLE_RATE`]。取り込んだ音は OS がこのレートに変換します
+    pub sample_rate: Option<u32>,
+    /// 1 か 2
+    pub channels: u16,
+    #[serde(default)]
+    pub rotation: Rotation,
+}
+
+impl FileCaptureRequest {
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate.unwrap_or(DEFAULT_CAPTURE_SAMPLE_RATE)
+    }
+
+    pub fn validate(&self) -> Result<(), AudioError> {
+        let invalid = |message: String| Err(AudioError::Backend(message));
+        if self.directory.is_empty() {
+            return invalid("No folder to save recordings in".to_string());
+        }
+        if self.file_prefix.is_empty() || self.file_prefix.contains(['/', '\\', ':']) {
+            return invalid(format!("Invalid file name: {:?}", self.file_prefix));
+        }
+        let sample_rate = self.sample_rate();
+        validate_format(sample_rate, self.channels)?;
+        match self.encoding {
+            FileEncoding::Wav { bits_per_sample: 16 | 24 | 32 } | FileEncoding::Flac { bits_per_sample: 16 | 24 } => {}
+            FileEncoding::Wav { bits_per_sample } | FileEncoding::Flac { bits_per_sample } => {
+                return invalid(format!("Unsupported bit depth for {}: {}", self.encoding.extension(), bits_per_sample));
+            }
+            FileEncoding::OggOpus { bitrate_kbps } => {
+                if !OPUS_SAMPLE_RATES.contains(&sample_rate) {
+                    return invalid(format!("Opus cannot record at {} Hz", sample_rate));
+                }
+                if !(MIN_OPUS_KBPS..=MAX_OPUS_KBPS).contains(&bitrate_kbps) {
+                    return invalid(format!("Bitrate must be between {} and {} kbps: {}", MIN_OPUS_KBPS, MAX_OPUS_KBPS, bitrate_kbps));
+                }
+            }
+        }
+        if self.rotation.max_bytes.is_some_and(|b| b < MIN_ROTATION_BYTES) {
+            return invalid(format!("Files must be allowed to grow to at least {} bytes", MIN_ROTATION_BYTES));
+        }
+        if self.rotation.max_ms.is_some_and(|ms| ms < MIN_ROTATION_MS) {
+            return invalid(format!("Files must be at least {} ms long", MIN_ROTATION_MS));
+        }
+        Ok(())
+    }
+}
+
+/// 取り込むときのサンプルレートとチャンネル数を確かめます。
+pub(crate) fn validate_format(sample_rate: u32, channels: u16) -> Result<(), AudioError> {
+    if !(1..=2).contains(&channels) {
+        return Err(AudioError::Backend(format!("Channels must be 1 or 2: {}", channels)));
+    }
+    if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&sample_rate) {
+        return Err(AudioError::Backend(format!("Sample rate must be between {} and {} Hz: {}", MIN_SAMPLE_RATE, MAX_SAMPLE_RATE, sample_rate)));
+    }
+    Ok(())
+}
+
+/// WAV のヘッダー。32bit は float (WAVE_FORMAT_IEEE_FLOAT)、それ以外は整数 (WAVE_FORMAT_PCM)
+pub(crate) fn wav_header(sample_rate: u32, channels: u16, bits_per_sample: u16, data_len: u32) -> Vec<u8> {
+    let block_align = channels * bits_per_sample / 8;
+    let format_tag: u16 = if bits_per_sample == 32 { 3 } else { 1 };
+    let mut header = Vec::with_capacity(WAV_HEADER_BYTES as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&data_len.saturating_add(36).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&format_tag.to_le_bytes());
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&bits_per_sample.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+/// 書いたバイト数を数える `Write`
+struct Counted<W> {
+    inner: W,
+    bytes: u64,
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn counted(file: File) -> Counted<BufWriter<File>> {
+    Counted { inner: BufWriter::new(file), bytes: 0 }
+}
+
+/// 1 ファイル分のエンコーダー
+trait Encoder {
+    /// インターリーブのサンプルを足します。
+    fn write(&mut self, samples: &[f32]) -> io::Result<()>;
+    /// ここまでに書いたバイト数
+    fn bytes(&self) -> u64;
+    /// 残りを書き出し、ヘッダーの長さを埋めます。
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// `bits` ビットの符号付き整数にします。
+pub(crate) fn to_int(sample: f32, bits: u16) -> i32 {
+    let max = ((1i64 << (bits - 1)) - 1) as f32;
+    (sample.clamp(-1.0, 1.0) * max).round() as i32
+}
+
+struct WavEncoder {
+    out: Counted<BufWriter<File>>,
+    sample_rate: u32,
+    channels: u16,
+    bits_per_sample: u16,
+}
+
+impl WavEncoder {
+    fn create(file: File, sample_rate: u32, channels: u16, bits_per_sample: u16) -> io::Result<Self> {
+        let mut encoder = Self { out: counted(file), sample_rate, channels, bits_per_sample };
+        encoder.out.write_all(&wav_header(sample_rate, channels, bits_per_sample, 0))?;
+        Ok(encoder)
+    }
+}
+
+impl Encoder for WavEncoder {
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        let bytes = usize::from(self.bits_per_sample / 8);
+        let mut data = Vec::with_capacity(samples.len() * bytes);
+        for &sample in samples {
+            match self.bits_per_sample {
+                32 => data.extend_from_slice(&sample.to_le_bytes()),
+                bits => data.extend_from_slice(&to_int(sample, bits).to_le_bytes()[..bytes]),
+            }
+        }
+        self.out.write_all(&data)
+    }
+
+    fn bytes(&self) -> u64 {
+        self.out.bytes
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        let data_len = (self.out.bytes - WAV_HEADER_BYTES).min(u64::from(u32::MAX)) as u32;
+        let header = wav_header(self.sample_rate, self.channels, self.bits_per_sample, data_len);
+        let out = &mut self.out.inner;
+        out.seek(SeekFrom::Start(0))?;
+        out.write_all(&header)?;
+        out.flush()
+    }
+}
+
+struct FlacEncoder {
+    out: Counted<BufWriter<File>>,
+    config: Verified<config::Encoder>,
+    stream_info: StreamInfo,
+    framebuf: FrameBuf,
+    bits_per_sample: u16,
+    channels: usize,
+    /// まだ 1 フレームに満たないサンプル
+    pending: Vec<i32>,
+    frame_number: usize,
+}
+
+fn flac_error(e: impl std::fmt::Debug) -> io::Error {
+    io::Error::other(format!("FLAC encoder error: {:?}", e))
+}
+
+impl FlacEncoder {
+    fn create(file: File, sample_rate: u32, channels: u16, bits_per_sample: u16) -> io::Result<Self> {
+        let channels = usize::from(channels);
+        let mut stream_info = StreamInfo::new(sample_rate as usize, channels, usize::from(bits_per_sample)).map_err(flac_error)?;
+        stream_info.set_block_sizes(FLAC_BLOCK_SIZE, FLAC_BLOCK_SIZE).map_err(flac_error)?;
+        let mut encoder = Self {
+            out: counted(file),
+            config: config::Encoder::default().into_verified().map_err(flac_error)?,
+            stream_info,
+            framebuf: FrameBuf::with_size(channels, FLAC_BLOCK_SIZE).map_err(flac_error)?,
+            bits_per_sample,
+            channels,
+            pending: Vec::new(),
+            frame_number: 0,
+        };
+        encoder.out.write_all(b"fLaC")?;
+        // 最後のメタデータブロック、種類 0 (STREAMINFO)、長さ 34 バイト
+        encoder.out.write_all(&[0x80, 0, 0, 34])?;
+        let info = encoder.stream_info_bytes()?;
+        encoder.out.write_all(&info)?;
+        Ok(encoder)
+    }
+
+    fn stream_info_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut sink = MemSink::<u8>::with_capacity(self.stream_info.count_bits());
+        self.stream_info.write(&mut sink).map_err(flac_error)?;
+        Ok(sink.into_inner())
+    }
+
+    fn encode_frame(&mut self, samples: &[i32]) -> io::Result<()> {
+        self.framebuf.fill_interleaved(samples).map_err(flac_error)?;
+        let frame = flacenc::encode_fixed_size_frame(&self.config, &self.framebuf, self.frame_number, &self.stream_info).map_err(flac_error)?;
+        self.stream_info.update_frame_info(&frame);
+        let mut sink = MemSink::<u8>::with_capacity(frame.count_bits());
+        frame.write(&mut sink).map_err(flac_error)?;
+        self.out.write_all(sink.as_slice())?;
+        self.frame_number += 1;
+        Ok(())
+    }
+}
+
+impl Encoder for FlacEncoder {
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        let bits = self.bits_per_sample;
+        self.pending.extend(samples.iter().map(|&s| to_int(s, bits)));
+        let block = FLAC_BLOCK_SIZE * self.channels;
+        let full = self.pending.len() / block * block;
+        let pending = std::mem::take(&mut self.pending);
+        for chunk in pending[..full].chunks(block) {
+            self.encode_frame(chunk)?;
+        }
+        self.pending = pending[full..].to_vec();
+        Ok(())
+    }
+
+    fn bytes(&self) -> u64 {
+        self.out.bytes
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        let rest = std::mem::take(&mut self.pending);
+        if rest.len() >= self.channels {
+            self.encode_frame(&rest[..rest.len() / self.channels * self.channels])?;
+        }
+        // 固定長のブロックでは最後の短いブロックを最小値に数えない（参照エンコーダーと同じ）。
+        // 数えると可変長のストリームとみなすデコーダーがある
+        let block = self.stream_info.total_samples().clamp(16, FLAC_BLOCK_SIZE);
+        self.stream_info.set_block_sizes(block, block).map_err(flac_error)?;
+        let info = self.stream_info_bytes()?;
+        let out = &mut self.out.inner;
+        out.seek(SeekFrom::Start(FLAC_STREAM_INFO_OFFSET))?;
+        out.write_all(&info)?;
+        out.flush()
+    }
+}
+
+struct OpusFileEncoder {
+    out: PacketWriter<'static, Counted<BufWriter<File>>>,
+    encoder: OpusEncoder,
+    serial: u32,
+    channels: usize,
+    /// 1 パケットのフレーム数
+    frame_size: usize,
+    /// 48 kHz に換算するときの倍率
+    scale: u64,
+    pending: Vec<f32>,
+    /// 受け取ったフレーム数とエンコードしたフレーム数（最後のパケットの無音の詰め物を含む）
+    received: u64,
+    encoded: u64,
+    /// 最後のパケットにだけストリームの終わりの印を付けるため、1 つ遅らせて書く
+    held: Option<(Vec<u8>, u64)>,
+}
+
+impl OpusFileEncoder {
+    fn create(file: File, sample_rate: u32, channels: u16, bitrate_kbps: u32) -> io::Result<Self> {
+        let mut encoder = OpusEncoder::new(sample_rate as i32, usize::from(channels), Application::Audio).map_err(io::Error::other)?;
+        encoder.bitrate_bps = (bitrate_kbps * 1000) as i32;
+        let serial = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or_default();
+        let mut out = PacketWriter::new(counted(file));
+
+        // RFC 7845 の識別ヘッダー（チャンネルマッピング 0）とコメントヘッダー
+        let mut head = b"OpusHead".to_vec();
+        head.push(1);
+        head.push(channels as u8);
+        head.extend_from_slice(&OPUS_PRE_SKIP.to_le_bytes());
+        head.extend_from_slice(&sample_rate.to_le_bytes());
+        head.extend_from_slice(&0i16.to_le_bytes());
+        head.push(0);
+        out.write_packet(head, serial, PacketWriteEndInfo::EndPage, 0)?;
+        let vendor = env!("CARGO_PKG_NAME").as_bytes();
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        tags.extend_from_slice(vendor);
+        tags.extend_from_slice(&0u32.to_le_bytes());
+        out.write_packet(tags, serial, PacketWriteEndInfo::EndPage, 0)?;
+
+        Ok(Self {
+            out,
+            encoder,
+            serial,
+            channels: usize::from(channels),
+            frame_size: sample_rate as usize * OPUS_FRAME_MS / 1000,
+            scale: u64::from(48_000 / sample_rate),
+            pending: Vec::new(),
+            received: 0,
+            encoded: 0,
+            held: None,
+        })
+    }
+
+    fn encode_frame(&mut self, samples: &[f32]) -> io::Result<()> {
+        let mut packet = vec![0u8; OPUS_MAX_PACKET];
+        let len = self.encoder.encode(samples, self.frame_size, &mut packet).map_err(io::Error::other)?;
+        packet.truncate(len);
+        self.encoded += self.frame_size as u64;
+        let granule = u64::from(OPUS_PRE_SKIP) + self.encoded * self.scale;
+        if let Some((held, granule)) = self.held.replace((packet, granule)) {
+            self.out.write_packet(held, self.serial, PacketWriteEndInfo::NormalPacket, granule)?;
+        }
+        Ok(())
+    }
+}
+
+impl Encoder for OpusFileEncoder {
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        self.received += (samples.len() / self.channels) as u64;
+        self.pending.extend_from_slice(samples);
+        let block = self.frame_size * self.channels;
+        let full = self.pending.len() / block * block;
+        let pending = std::mem::take(&mut self.pending);
+        for chunk in pending[..full].chunks(block) {
+            self.encode_frame(chunk)?;
+        }
+        self.pending = pending[full..].to_vec();
+        Ok(())
+    }
+
+    fn bytes(&self) -> u64 {
+        self.out.inner().bytes
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        if !self.pending.is_empty() || self.held.is_none() {
+            let mut last = std::mem::take(&mut self.pending);
+            last.resize(self.frame_size * self.channels, 0.0);
+            self.encode_frame(&last)?;
+        }
+        if let Some((packet, _)) = self.held.take() {
+            // 詰め物の無音は最後のグラニュール位置で切り落とされる
+            let granule = u64::from(OPUS_PRE_SKIP) + self.received * self.scale;
+            self.out.write_packet(packet, self.serial, PacketWriteEndInfo::EndStream, granule)?;
+        }
+        self.out.inner_mut().flush()
+    }
+}
+
+/// 録った音をファイルに書き出し、[`Rotation`] の条件に達するごとに次のファイルへ移ります。
+/// Drop でも書きかけのファイルを閉じますが、エラーを知るには [`FileWriter::finish`] を使います。
+pub struct FileWriter {
+    request: FileCaptureRequest,
+    encoder: Option<Box<dyn Encoder>>,
+    files: Vec<String>,
+    /// いまのファイルに書いたフレーム数
+    frames: u64,
+}
+
+impl Drop for FileWriter {
+    fn drop(&mut self) {
+        if let Some(encoder) = self.encoder.take() {
+            let _ = encoder.finish();
+        }
+    }
+}
+
+impl FileWriter {
+    /// フォルダーを作り、最初のファイルを開きます。
+    pub fn create(request: FileCaptureRequest) -> Result<Self, AudioError> {
+        request.validate()?;
+        fs::create_dir_all(&request.directory)
+            .map_err(|e| AudioError::Backend(format!("Failed to create {}: {}", request.directory, e)))?;
+        let mut writer = Self { request, encoder: None, files: Vec::new(), frames: 0 };
+        writer.open_next()?;
+        Ok(writer)
+    }
+
+    /// これまでに作ったファイル
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    fn write_error(&self, e: io::Error) -> AudioError {
+        AudioError::Backend(format!("Failed to write {}: {}", self.files.last().map_or("", String::as_str), e))
+    }
+
+    /// 番号を進めて次のファイルを開きます。同じ名前のファイルがあれば上書きせずに番号を飛ばします。
+    fn open_next(&mut self) -> Result<(), AudioError> {
+        let directory = Path::new(&self.request.directory);
+        let mut number = self.files.len() + 1;
+        let (path, file) = loop {
+            let path: PathBuf = directory.join(format!("{}-{:03}.{}", self.request.file_prefix, number, self.request.encoding.extension()));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => number += 1,
+                Err(e) => return Err(AudioError::Backend(format!("Failed to create {}: {}", path.display(), e))),
+            }
+        };
+        self.files.push(path.to_string_lossy().into_owned());
+        let (sample_rate, channels) = (self.request.sample_rate(), self.request.channels);
+        let encoder: io::Result<Box<dyn Encoder>> = match self.request.encoding {
+            FileEncoding::Wav { bits_per_sample } => WavEncoder::create(file, sample_rate, channels, bits_per_sample).map(|e| Box::new(e) as _),
+            FileEncoding::Flac { bits_per_sample } => FlacEncoder::create(file, sample_rate, channels, bits_per_sample).map(|e| Box::new(e) as _),
+            FileEncoding::OggOpus { bitrate_kbps } => OpusFileEncoder::create(file, sample_rate, channels, bitrate_kbps).map(|e| Box::new(e) as _),
+        };
+        self.encoder = Some(encoder.map_err(|e| self.write_error(e))?);
+        self.frames = 0;
+        Ok(())
+    }
+
+    /// いまのファイルにあと何フレーム書けるか。長さの上限がなければ `None`
+    fn frames_left(&self) -> Option<u64> {
+        let max_ms = self.request.rotation.max_ms?;
+        Some((max_ms * u64::from(self.request.sample_rate()) / 1000).saturating_sub(self.frames))
+    }
+
+    fn is_full(&self) -> bool {
+        let max_bytes = match self.request.encoding {
+            FileEncoding::Wav { .. } => Some(self.request.rotation.max_bytes.map_or(WAV_MAX_BYTES, |b| b.min(WAV_MAX_BYTES))),
+            _ => self.request.rotation.max_bytes,
+        };
+        let bytes = self.encoder.as_ref().map_or(0, |e| e.bytes());
+        self.frames_left() == Some(0) || max_bytes.is_some_and(|max| bytes >= max)
+    }
+
+    /// インターリーブのサンプルを書きます。長さで分けるときは、ちょうどその位置で次のファイルに移ります。
+    pub fn write(&mut self, mut samples: &[f32]) -> Result<(), AudioError> {
+        let channels = usize::from(self.request.channels);
+        while samples.len() >= channels {
+            if self.is_full() {
+                self.close_current()?;
+                self.open_next()?;
+            }
+            let frames = (samples.len() / channels) as u64;
+            let frames = self.frames_left().map_or(frames, |left| frames.min(left));
+            let (now, rest) = samples.split_at(frames as usize * channels);
+            let Some(encoder) = self.encoder.as_mut() else { break };
+            if let Err(e) = encoder.write(now) {
+                return Err(self.write_error(e));
+            }
+            self.frames += frames;
+            samples = rest;
+        }
+        Ok(())
+    }
+
+    fn close_current(&mut self) -> Result<(), AudioError> {
+        match self.encoder.take() {
+            Some(encoder) => encoder.finish().map_err(|e| self.write_error(e)),
+            None => Ok(()),
+        }
+    }
+
+    /// 書きかけのファイルを閉じて、作ったファイルを返します。
+    pub fn finish(mut self) -> Result<Vec<String>, AudioError> {
+        self.close_current()?;
+        Ok(std::mem::take(&mut self.files))
+    }
+}
@@ -0,0 +1,103 @@
+//! CoreAudio / CoreFoundation の必要な部分だけを宣言した FFI。
+
+#![allow(non_upper_case_globals, non_snake_case)]
+
+use std::ffi::{c_char, c_void};
+
+pub type AudioObjectID = u32;
+pub type OSStatus = i32;
+pub type CFStringRef = *const c_void;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct AudioObjectPropertyAddress {
+    pub mSelector: u32,
+    pub mScope: u32,
+    pub mElement: u32,
+}
+
+pub type AudioObjectPropertyListenerProc = unsafe extern "C" fn(
+    object_id: AudioObjectID,
+    number_addresses: u32,
+    addresses: *const AudioObjectPropertyAddress,
+    client_data: *mut c_void,
+) -> OSStatus;
+
+const fn fourcc(code: &[u8; 4]) -> u32 {
+    u32::from_be_bytes(*code)
+}
+
+pub const kAudioObjectSystemObject: AudioObjectID = 1;
+pub const kAudioObjectPropertyElementMain: u32 = 0;
+pub const kAudioObjectPropertyScopeGlobal: u32 = fourcc(b"glob");
+pub const kAudioObjectPropertyScopeOutput: u32 = fourcc(b"outp");
+pub const kAudioObjectPropertyScopeInput: u32 = fourcc(b"inpt");
+
+pub const kAudioObjectPropertyName: u32 = fourcc(b"lnam");
+pub const kAudioHardwarePropertyDevices: u32 = fourcc(b"dev#");
+pub const kAudioHardwarePropertyDefaultOutputDevice: u32 = fourcc(b"dOut");
+pub const kAudioHardwarePropertyDefaultInputDevice: u32 = fourcc(b"dIn ");
+/// macOS 14.2 以降で利用可能なプロセスオブジェクトの一覧
+pub const kAudioHardwarePropertyProcessObjectList: u32 = fourcc(b"prs#");
+pub const kAudioDevicePropertyDeviceUID: u32 = fourcc(b"uid ");
+pub const kAudioDevicePropertyStreams: u32 = fourcc(b"stm#");
+pub const kAudioDevicePropertyJackIsConnected: u32 = fourcc(b"jack");
+pub const kAudioProcessPropertyPID: u32 = fourcc(b"ppid");
+pub const kAudioProcessPropertyDevices: u32 = fourcc(b"pdv#");
+pub const kAudioProcessPropertyIsRunningOutput: u32 = fourcc(b"piro");
+
+pub const kCFStringEncodingUTF8: u32 = 0x0800_0100;
+
+#[link(name = "CoreAudio", kind = "framework")]
+extern "C" {
+    pub fn AudioObjectGetPropertyDataSize(
+        object_id: AudioObjectID,
+        address: *const AudioObjectPropertyAddress,
+        qualifier_size: u32,
+        qualifier: *const c_void,
+        out_size: *mut u32,
+    ) -> OSStatus;
+
+    pub fn AudioObjectGetPropertyData(
+        object_id: AudioObjectID,
+        address: *const AudioObjectPropertyAddress,
+        qualifier_size: u32,
+        qualifier: *const c_void,
+        io_size: *mut u32,
+        out_data: *mut c_void,
+    ) -> OSStatus;
+
+    pub fn AudioObjectSetPropertyData(
+        object_id: AudioObjectID,
+        address: *const AudioObjectPropertyAddress,
+        qualifier_size: u32,
+        qualifier: *const c_void,
+        data_size: u32,
+        data: *const c_void,
+    ) -> OSStatus;
+
+    pub fn AudioObjectAddPropertyListener(
+        object_id: AudioObjectID,
+        address: *const AudioObjectPropertyAddress,
+        listener: AudioObjectPropertyListenerProc,
+        client_data: *mut c_void,
+    ) -> OSStatus;
+
+    pub fn AudioObjectRemovePropertyListener(
+        object_id: AudioObjectID,
+        address: *const AudioObjectPropertyAddress,
+        listener: AudioObjectPropertyListenerProc,
+        client_data: *mut c_void,
+    ) -> OSStatus;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    pub fn CFStringGetCString(string: CFStringRef, buffer: *mut c_char, buffer_size: isize, encoding: u32) -> u8;
+    pub fn CFRelease(cf: *const c_void);
+}
+
+extern "C" {
+    /// libproc（libSystem に含まれる）
+    pub fn proc_pidpath(pid: i32, buffer: *mut c_void, buffer_size: u32) -> i32;
+}
@@ -0,0 +1,405 @@
+mod ffi;
+
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ffi::*;
+
+use crate::accent;
+use crate::apps::AppPresentation;
+use crate::diff::SessionPublisher;
+use crate::{
+    AppOverride, AudioBackend, AudioDeviceInfo, AudioError, AudioNotification, AudioSessionInfo,
+    DeviceRole, MixerEvent, MixerEventSink, Notifier, PeakLevel, SessionsDiff,
+};
+
+/// 出力中かどうかの変化は通知されないため、この間隔でプロセス一覧を読み直す
+const RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 変更を監視するシステムオブジェクトのプロパティ
+const SYSTEM_LISTENERS: [u32; 3] = [
+    kAudioHardwarePropertyDevices,
+    kAudioHardwarePropertyDefaultOutputDevice,
+    kAudioHardwarePropertyProcessObjectList,
+];
+
+/// CoreAudio によるバックエンド。
+/// macOS には公開されたアプリごとの音量 API がないため、セッションの一覧と
+/// デバイスの列挙のみを提供し、音量・ミュート・出力先の変更は `Unsupported` を返します。
+pub struct CoreAudioBackend {
+    sink: Arc<dyn MixerEventSink>,
+    apps: AppPresentation,
+    session_cache: Vec<AudioSessionInfo>,
+    cache_dirty: bool,
+    last_scan: Option<Instant>,
+    publisher: SessionPublisher,
+    /// リスナーに渡した `Notifier`。Drop で登録を解除してから解放する
+    listener_data: *mut Notifier,
+}
+
+impl Drop for CoreAudioBackend {
+    fn drop(&mut self) {
+        for selector in SYSTEM_LISTENERS {
+            unsafe {
+                AudioObjectRemovePropertyListener(
+                    kAudioObjectSystemObject,
+                    &global_address(selector),
+                    system_listener,
+                    self.listener_data as *mut c_void,
+                );
+            }
+        }
+        drop(unsafe { Box::from_raw(self.listener_data) });
+    }
+}
+
+impl CoreAudioBackend {
+    pub fn new(notifier: Notifier, sink: Arc<dyn MixerEventSink>) -> Result<Self, AudioError> {
+        // 既定の出力デバイスが取れない環境ではバックエンドとして使えない
+        default_output_device()?;
+        let listener_data = Box::into_raw(Box::new(notifier));
+        for selector in SYSTEM_LISTENERS {
+            unsafe {
+                AudioObjectAddPropertyListener(
+                    kAudioObjectSystemObject,
+                    &global_address(selector),
+                    system_listener,
+                    listener_data as *mut c_void,
+                );
+            }
+        }
+        Ok(Self {
+            sink,
+            apps: AppPresentation::default(),
+            session_cache: Vec::new(),
+            cache_dirty: true,
+            last_scan: None,
+            publisher: SessionPublisher::default(),
+            listener_data,
+        })
+    }
+
+    fn needs_rescan(&self) -> bool {
+        self.cache_dirty || self.last_scan.is_none_or(|t| t.elapsed() >= RESCAN_INTERVAL)
+    }
+
+    fn refresh_if_needed(&mut self) -> Result<(), AudioError> {
+        if self.needs_rescan() {
+            self.rescan_sessions()?;
+        }
+        Ok(())
+    }
+
+    fn rescan_sessions(&mut self) -> Result<(), AudioError> {
+        let default_uid = device_uid(default_output_device()?).unwrap_or_default();
+        let mut previous: HashMap<u32, Option<String>> = self.session_cache
+            .drain(..)
+            .map(|s| (s.process_id, s.icon_base64))
+            .collect();
+        let mut sessions = Vec::new();
+
+        // macOS 14.2 より前ではプロセス一覧が取れないため、セッションは空になる
+        let processes = get_array::<AudioObjectID>(kAudioObjectSystemObject, global_address(kAudioHardwarePropertyProcessObjectList))
+            .unwrap_or_default();
+        for process in processes {
+            let running = get_value::<u32>(process, global_address(kAudioProcessPropertyIsRunningOutput)).unwrap_or(0);
+            if running == 0 { continue; }
+            let Ok(pid) = get_value::<i32>(process, global_address(kAudioProcessPropertyPID)) else { continue };
+            let pid = pid as u32;
+
+            let exe_name = process_path(pid)
+                .as_deref()
+                .and_then(|p| Path::new(p).file_name())
+                .and_then(|n| n.to_str())
+                .map(|n| n.to_uppercase())
+                .unwrap_or_else(|| format!("PROCESS {}", pid));
+            if self.apps.is_hidden(&exe_name) { continue; }
+
+            let device_id = get_array::<AudioObjectID>(process, output_address(kAudioProcessPropertyDevices))
+                .ok()
+                .and_then(|devices| devices.first().copied())
+                .and_then(device_uid)
+                .unwrap_or_else(|| default_uid.clone());
+
+            let icon_base64 = previous.remove(&pid).flatten().or_else(|| self.apps.override_icon(&exe_name));
+            sessions.push(AudioSessionInfo {
+                process_id: pid,
+                process_name: self.apps.display_name(&exe_name, &exe_name),
+                accent_color: icon_base64.as_deref().and_then(accent::accent_color),
+                icon_base64,
+                exe_name,
+                volume: 1.0,
+                is_muted: false,
+                peak_level: 0.0,
+                instance_id: crate::session_key(pid, &device_id),
+                device_id,
+                // 出力中のプロセスだけを列挙しているため、常に再生中
+                state: crate::SessionState::Active,
+                persisted_device_id: None,
+                route_pending: false,
+                now_playing: None,
+            });
+        }
+
+        self.session_cache = sessions;
+        self.cache_dirty = false;
+        self.last_scan = Some(Instant::now());
+        Ok(())
+    }
+}
+
+impl AudioBackend for CoreAudioBackend {
+    fn name(&self) -> &'static str {
+        "coreaudio"
+    }
+
+    fn clear_caches(&mut self) {
+        self.session_cache.clear();
+        self.cache_dirty = true;
+    }
+
+    fn set_hidden_apps(&mut self, names: Vec<String>) {
+        self.apps.set_hidden_apps(names);
+        self.session_cache.clear();
+        self.cache_dirty = true;
+    }
+
+    fn set_app_overrides(&mut self, overrides: HashMap<String, AppOverride>) {
+        self.apps.set_app_overrides(overrides);
+        self.session_cache.clear();
+        self.cache_dirty = true;
+    }
+
+    fn get_sessions(&mut self) -> Result<Vec<AudioSessionInfo>, AudioError> {
+        self.refresh_if_needed()?;
+        Ok(self.session_cache.clone())
+    }
+
+    fn get_session_icon(&mut self, pid: u32) -> Result<Option<String>, AudioError> {
+        self.refresh_if_needed()?;
+        let session = self.session_cache.iter().find(|s| s.process_id == pid).ok_or(AudioError::SessionNotFound(pid))?;
+        Ok(session.icon_base64.clone())
+    }
+
+    fn take_sessions_diff(&mut self) -> Result<Option<SessionsDiff>, AudioError> {
+        if !self.needs_rescan() {
+            return Ok(None);
+        }
+        self.rescan_sessions()?;
+        Ok(self.publisher.publish(&self.session_cache))
+    }
+
+    fn get_peak_levels(&mut self) -> Result<Vec<PeakLevel>, AudioError> {
+        // アプリごとのメーターは公開されていない
+        Ok(Vec::new())
+    }
+
+    fn set_session_volume(&mut self, _pid: u32, _volume: f32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Per-app volume"))
+    }
+
+    fn set_session_mute(&mut self, _pid: u32, _mute: bool) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Per-app mute"))
+    }
+
+    fn set_instance_volume(&mut self, _instance_id: &str, _volume: f32) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Per-app volume"))
+    }
+
+    fn set_instance_mute(&mut self, _instance_id: &str, _mute: bool) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Per-app mute"))
+    }
+
+    fn get_audio_devices(&mut self) -> Result<Vec<AudioDeviceInfo>, AudioError> {
+        devices(kAudioObjectPropertyScopeOutput, kAudioHardwarePropertyDefaultOutputDevice)
+    }
+
+    fn set_default_device(&mut self, device_id: &str) -> Result<(), AudioError> {
+        set_default(kAudioHardwarePropertyDefaultOutputDevice, device_id)
+    }
+
+    fn get_capture_devices(&mut self) -> Result<Vec<AudioDeviceInfo>, AudioError> {
+        devices(kAudioObjectPropertyScopeInput, kAudioHardwarePropertyDefaultInputDevice)
+    }
+
+    /// macOS には役割ごとの既定がないので、`role` は無視します。
+    fn set_default_capture_device(&mut self, device_id: &str, _role: Option<DeviceRole>) -> Result<(), AudioError> {
+        set_default(kAudioHardwarePropertyDefaultInputDevice, device_id)
+    }
+
+    fn set_audio_routing(&mut self, _pid: u32, _device_id: &str) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported("Per-app routing"))
+    }
+
+    fn handle_notification(&mut self, notification: AudioNotification) {
+        match notification {
+            AudioNotification::DevicesChanged => {
+                self.cache_dirty = true;
+                self.sink.emit(MixerEvent::DevicesChanged);
+            }
+            _ => self.cache_dirty = true,
+        }
+    }
+}
+
+/// システムオブジェクトのプロパティ変化をオーディオスレッドへ転送します。CoreAudio のスレッドから呼ばれます。
+unsafe extern "C" fn system_listener(
+    _object_id: AudioObjectID,
+    number_addresses: u32,
+    addresses: *const AudioObjectPropertyAddress,
+    client_data: *mut c_void,
+) -> OSStatus {
+    let notifier = &*(client_data as *const Notifier);
+    for address in std::slice::from_raw_parts(addresses, number_addresses as usize) {
+        if address.mSelector == kAudioHardwarePropertyProcessObjectList {
+            notifier.notify(AudioNotification::SessionCreated);
+        } else {
+            notifier.notify(AudioNotification::DevicesChanged);
+        }
+    }
+    0
+}
+
+fn global_address(selector: u32) -> AudioObjectPropertyAddress {
+    AudioObjectPropertyAddress {
+        mSelector: selector,
+        mScope: kAudioObjectPropertyScopeGlobal,
+        mElement: kAudioObjectPropertyElementMain,
+    }
+}
+
+fn output_address(selector: u32) -> AudioObjectPropertyAddress {
+    scoped_address(selector, kAudioObjectPropertyScopeOutput)
+}
+
+fn scoped_address(selector: u32, scope: u32) -> AudioObjectPropertyAddress {
+    AudioObjectPropertyAddress {
+        mSelector: selector,
+        mScope: scope,
+        mElement: kAudioObjectPropertyElementMain,
+    }
+}
+
+fn check(status: OSStatus, what: &str) -> Result<(), AudioError> {
+    if status == 0 {
+        Ok(())
+    } else {
+        Err(AudioError::Backend(format!("CoreAudio {} failed (OSStatus {})", what, status)))
+    }
+}
+
+fn get_value<T: Copy + Default>(object: AudioObjectID, address: AudioObjectPropertyAddress) -> Result<T, AudioError> {
+    let mut value = T::default();
+    let mut size = std::mem::size_of::<T>() as u32;
+    let status = unsafe {
+        AudioObjectGetPropertyData(object, &address, 0, ptr::null(), &mut size, &mut value as *mut T as *mut c_void)
+    };
+    check(status, "property read")?;
+    Ok(value)
+}
+
+fn get_array<T: Copy + Default>(object: AudioObjectID, address: AudioObjectPropertyAddress) -> Result<Vec<T>, AudioError> {
+    let mut size = 0u32;
+    check(unsafe { AudioObjectGetPropertyDataSize(object, &address, 0, ptr::null(), &mut size) }, "property size")?;
+    let mut values = vec![T::default(); size as usize / std::mem::size_of::<T>()];
+    if values.is_empty() {
+        return Ok(values);
+    }
+    let status = unsafe {
+        AudioObjectGetPropertyData(object, &address, 0, ptr::null(), &mut size, values.as_mut_ptr() as *mut c_void)
+    };
+    check(status, "property read")?;
+    values.truncate(size as usize / std::mem::size_of::<T>());
+    Ok(values)
+}
+
+fn get_string(object: AudioObjectID, address: AudioObjectPropertyAddress) -> Option<String> {
+    let mut string: CFStringRef = ptr::null();
+    let mut size = std::mem::size_of::<CFStringRef>() as u32;
+    let status = unsafe {
+        AudioObjectGetPropertyData(object, &address, 0, ptr::null(), &mut size, &mut string as *mut CFStringRef as *mut c_void)
+    };
+    if status != 0 || string.is_null() {
+        return None;
+    }
+    let mut buffer = [0u8; 512];
+    let ok = unsafe {
+        let ok = CFStringGetCString(string, buffer.as_mut_ptr() as *mut _, buffer.len() as isize, kCFStringEncodingUTF8);
+        CFRelease(string);
+        ok
+    };
+    if ok == 0 {
+        return None;
+    }
+    let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    String::from_utf8(buffer[..len].to_vec()).ok()
+}
+
+/// `scope` 側（出力・入力）のストリームを持つデバイスの一覧。`default_selector` で既定のデバイスを判定します。
+fn devices(scope: u32, default_selector: u32) -> Result<Vec<AudioDeviceInfo>, AudioError> {
+    let default_device = get_value::<AudioObjectID>(kAudioObjectSystemObject, global_address(default_selector))?;
+    let devices = get_array::<AudioObjectID>(kAudioObjectSystemObject, global_address(kAudioHardwarePropertyDevices))?;
+    Ok(devices.into_iter()
+        .filter(|&device| has_streams(device, scope))
+        .filter_map(|device| {
+            Some(AudioDeviceInfo {
+                id: device_uid(device)?,
+                name: get_string(device, global_address(kAudioObjectPropertyName)).unwrap_or_else(|| "Unknown Device".to_string()),
+                is_default: device == default_device,
+                // 端子を持たないデバイスはこのプロパティを提供しない
+                jack_connected: get_value::<u32>(device, scoped_address(kAudioDevicePropertyJackIsConnected, scope)).ok().map(|c| c != 0),
+                bluetooth: None,
+            })
+        })
+        .collect())
+}
+
+/// UID で指定したデバイスを `selector` の既定のデバイスにします。
+fn set_default(selector: u32, device_id: &str) -> Result<(), AudioError> {
+    let devices = get_array::<AudioObjectID>(kAudioObjectSystemObject, global_address(kAudioHardwarePropertyDevices))?;
+    let device = devices.into_iter()
+        .find(|&d| device_uid(d).as_deref() == Some(device_id))
+        .ok_or_else(|| AudioError::Backend(format!("Unknown device {}", device_id)))?;
+    let address = global_address(selector);
+    let status = unsafe {
+        AudioObjectSetPropertyData(
+            kAudioObjectSystemObject,
+            &address,
+            0,
+            ptr::null(),
+            std::mem::size_of::<AudioObjectID>() as u32,
+            &device as *const AudioObjectID as *const c_void,
+        )
+    };
+    check(status, "default device change")
+}
+
+fn default_output_device() -> Result<AudioObjectID, AudioError> {
+    get_value::<AudioObjectID>(kAudioObjectSystemObject, global_address(kAudioHardwarePropertyDefaultOutputDevice))
+}
+
+fn device_uid(device: AudioObjectID) -> Option<String> {
+    get_string(device, global_address(kAudioDevicePropertyDeviceUID))
+}
+
+fn has_streams(device: AudioObjectID, scope: u32) -> bool {
+    let mut size = 0u32;
+    let status = unsafe {
+        AudioObjectGetPropertyDataSize(device, &scoped_address(kAudioDevicePropertyStreams, scope), 0, ptr::null(), &mut size)
+    };
+    status == 0 && size > 0
+}
+
+fn process_path(pid: u32) -> Option<String> {
+    // PROC_PIDPATHINFO_MAXSIZE
+    let mut buffer = [0u8; 4096];
+    let len = unsafe { proc_pidpath(pid as i32, buffer.as_mut_ptr() as *mut c_void, buffer.len() as u32) };
+    if len <= 0 {
+        return None;
+    }
+    String::from_utf8(buffer[..len as usize].to_vec()).ok()
+}
@@ -0,0 +1,42 @@
+use std::collections::HashMap;
+
+use crate::{AudioSessionInfo, RemovedSession, SessionsDiff};
+
+/// 最後に `sessions-changed` で通知したセッション一覧を保持し、次の通知との差分を計算します。
+#[derive(Debug, Default)]
+pub struct SessionPublisher {
+    published: HashMap<String, AudioSessionInfo>,
+}
+
+impl SessionPublisher {
+    /// `current` を通知済みとして記録し、前回からの差分を返します。変更がなければ `None` です。
+    pub fn publish(&mut self, current: &[AudioSessionInfo]) -> Option<SessionsDiff> {
+        let current: HashMap<String, AudioSessionInfo> = current.iter()
+            .map(|s| (s.instance_id.clone(), s.clone()))
+            .collect();
+        let mut diff = SessionsDiff::default();
+        for (key, session) in &current {
+            match self.published.get(key) {
+                None => diff.added.push(session.clone()),
+                Some(previous) if previous != session => diff.updated.push(session.clone()),
+                Some(_) => {}
+            }
+        }
+        for (key, session) in &self.published {
+            if !current.contains_key(key) {
+                diff.removed.push(RemovedSession {
+                    process_id: session.process_id,
+                    device_id: session.device_id.clone(),
+                    instance_id: session.instance_id.clone(),
+                });
+            }
+        }
+        self.published = current;
+
+        if diff.added.is_empty() && diff.updated.is_empty() && diff.removed.is_empty() {
+            None
+        } else {
+            Some(diff)
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::fmt;
+
+/// オーディオ層のエラー。OS API の失敗に加えて、アプリ側で判定するエラーを表します。
+#[derive(Debug, Clone)]
+pub enum AudioError {
+    /// 指定された PID のセッションが見つからない
+    SessionNotFound(u32),
+    /// 指定された識別子のセッションが見つからない
+    InstanceNotFound(String),
+    /// このバックエンドでは提供されていない操作
+    Unsupported(&'static str),
+    /// バックエンド固有の失敗
+    Backend(String),
+    #[cfg(windows)]
+    Windows(windows::core::Error),
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioError::SessionNotFound(pid) => write!(f, "Session not found for PID {}", pid),
+            AudioError::InstanceNotFound(id) => write!(f, "Session not found: {}", id),
+            AudioError::Unsupported(what) => write!(f, "{} is not supported on this platform", what),
+            AudioError::Backend(message) => write!(f, "{}", message),
+            #[cfg(windows)]
+            AudioError::Windows(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+#[cfg(windows)]
+impl From<windows::core::Error> for AudioError {
+    fn from(e: windows::core::Error) -> Self {
+        AudioError::Windows(e)
+    }
+}
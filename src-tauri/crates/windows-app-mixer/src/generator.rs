@@ -0,0 +1,746 @@
+//! テストトーン（和音や DTMF を含む）、スイープ、メトロノーム、スピーカーのエージング用のノイズ、音声ファイルを出力デバイスへ再生するジェネレーターの共通部品。
+//! 実際の出力はバックエンドの [`crate::AudioBackend::start_playback`] が担います。
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::audio_file::{AudioFileSource, SeekRequest};
+use crate::{AudioError, DeviceFormat};
+
+/// トーンの波形
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum Waveform {
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+    WhiteNoise,
+    PinkNoise,
+}
+
+impl Waveform {
+    pub fn is_noise(self) -> bool {
+        matches!(self, Waveform::WhiteNoise | Waveform::PinkNoise)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct ToneRequest {
+    pub waveform: Waveform,
+    /// 周波数 (Hz)。ノイズでは使いません
+    pub frequency: f32,
+    /// 0.0〜1.0 の振幅
+    pub amplitude: f32,
+    /// 再生時間（ミリ秒）。`None` なら止めるまで再生します
+    pub duration_ms: Option<u64>,
+}
+
+impl ToneRequest {
+    pub fn validate(&self) -> Result<(), AudioError> {
+        if !(0.0..=1.0).contains(&self.amplitude) {
+            return Err(AudioError::Backend(format!("Amplitude must be between 0 and 1: {}", self.amplitude)));
+        }
+        if !self.waveform.is_noise() && (self.frequency <= 0.0 || !self.frequency.is_finite()) {
+            return Err(AudioError::Backend(format!("Invalid frequency: {}", self.frequency)));
+        }
+        Ok(())
+    }
+}
+
+/// 和音の 1 つの音
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct ChordTone {
+    /// 周波数 (Hz)
+    pub frequency: f32,
+    /// 0.0〜1.0 の振幅。和音全体の合計も 1.0 以下にします
+    pub amplitude: f32,
+}
+
+/// DTMF の行 (低群) と列 (高群) の周波数 (Hz)
+const DTMF_ROWS: [f32; 4] = [697.0, 770.0, 852.0, 941.0];
+const DTMF_COLUMNS: [f32; 4] = [1209.0, 1336.0, 1477.0, 1633.0];
+const DTMF_KEYS: [&str; 4] = ["123A", "456B", "789C", "*0#D"];
+
+/// DTMF の数字（`0`〜`9`、`*`、`#`、`A`〜`D`）を鳴らす 2 つの周波数
+pub fn dtmf_frequencies(digit: char) -> Option<(f32, f32)> {
+    let digit = digit.to_ascii_uppercase();
+    DTMF_KEYS.iter().enumerate().find_map(|(row, keys)| {
+        keys.find(digit).map(|column| (DTMF_ROWS[row], DTMF_COLUMNS[column]))
+    })
+}
+
+/// ジェネレーターの出力をまとめるセッションの GUID。アプリのほかの音とは別のセッションとしてミキサーに並び、
+/// ほかのアプリと同じように音量・ミュート・出力先を変えられます
+pub const GENERATOR_SESSION_GUID: u128 = 0x6f1c_2a7e_93d4_4b5a_8e21_c0f7_5d3b_9a64;
+/// ジェネレーターのセッションの表示名。実行ファイル名の代わりに、非表示や表示名の上書きの設定のキーにもなります
+pub const GENERATOR_SESSION_NAME: &str = "Tone Generator";
+
+/// メトロノームのテンポの範囲 (BPM)
+pub const MIN_BPM: f32 = 20.0;
+pub const MAX_BPM: f32 = 400.0;
+const MAX_BEATS_PER_BAR: u32 = 16;
+
+/// 1 拍のクリックの長さと周波数 (Hz)。小節の頭（アクセント）は高い音にする
+const CLICK_MS: u32 = 30;
+const CLICK_FREQUENCY: f32 = 1000.0;
+const ACCENT_FREQUENCY: f32 = 1500.0;
+/// アクセントを付けるとき、小節の頭以外の拍の音量
+const WEAK_BEAT_GAIN: f32 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct Metronome {
+    pub bpm: f32,
+    pub beats_per_bar: u32,
+    /// 小節の頭の拍を高く大きな音にする
+    pub accent: bool,
+    /// 0.0〜1.0 の振幅
+    pub amplitude: f32,
+}
+
+impl Metronome {
+    pub fn validate(&self) -> Result<(), AudioError> {
+        validate_bpm(self.bpm)?;
+        if !(1..=MAX_BEATS_PER_BAR).contains(&self.beats_per_bar) {
+            return Err(AudioError::Backend(format!("Beats per bar must be between 1 and {}: {}", MAX_BEATS_PER_BAR, self.beats_per_bar)));
+        }
+        if !(0.0..=1.0).contains(&self.amplitude) {
+            return Err(AudioError::Backend(format!("Amplitude must be between 0 and 1: {}", self.amplitude)));
+        }
+        Ok(())
+    }
+}
+
+pub fn validate_bpm(bpm: f32) -> Result<(), AudioError> {
+    if !(MIN_BPM..=MAX_BPM).contains(&bpm) {
+        return Err(AudioError::Backend(format!("Tempo must be between {} and {} BPM: {}", MIN_BPM, MAX_BPM, bpm)));
+    }
+    Ok(())
+}
+
+/// スイープの周波数の範囲 (Hz) と長さ (ms)
+pub const MIN_SWEEP_HZ: f32 = 10.0;
+pub const MAX_SWEEP_HZ: f32 = 24_000.0;
+const MIN_SWEEP_MS: u64 = 100;
+const MAX_SWEEP_MS: u64 = 60_000;
+/// スイープの始めと終わりのフェード (ms)。プチッという音を防ぐ
+const SWEEP_FADE_MS: f64 = 5.0;
+
+/// `start_hz` から `end_hz` まで、周波数を指数的に（1 オクターブあたり同じ時間で）上げていく正弦波
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct Sweep {
+    pub start_hz: f32,
+    pub end_hz: f32,
+    /// 0.0〜1.0 の振幅
+    pub amplitude: f32,
+    pub duration_ms: u64,
+}
+
+impl Sweep {
+    pub fn validate(&self) -> Result<(), AudioError> {
+        if !(MIN_SWEEP_HZ <= self.start_hz && self.start_hz < self.end_hz && self.end_hz <= MAX_SWEEP_HZ) {
+            return Err(AudioError::Backend(format!("A sweep must rise within {} to {} Hz: {} to {} Hz", MIN_SWEEP_HZ, MAX_SWEEP_HZ, self.start_hz, self.end_hz)));
+        }
+        if !(0.0..=1.0).contains(&self.amplitude) {
+            return Err(AudioError::Backend(format!("Amplitude must be between 0 and 1: {}", self.amplitude)));
+        }
+        if !(MIN_SWEEP_MS..=MAX_SWEEP_MS).contains(&self.duration_ms) {
+            return Err(AudioError::Backend(format!("Sweep duration must be between {} and {} ms: {}", MIN_SWEEP_MS, MAX_SWEEP_MS, self.duration_ms)));
+        }
+        Ok(())
+    }
+
+    /// スイープが `frequency` に達するまでの時間（秒）
+    pub fn time_at(&self, frequency: f32) -> f64 {
+        let span = (f64::from(self.end_hz) / f64::from(self.start_hz)).ln();
+        let ratio = (f64::from(frequency.clamp(self.start_hz, self.end_hz)) / f64::from(self.start_hz)).ln();
+        self.duration_ms as f64 / 1000.0 * ratio / span
+    }
+}
+
+/// エージングで出せる振幅の上限。設定に関わらず、これを超える音は出しません
+pub const MAX_BURN_IN_AMPLITUDE: f32 = 0.5;
+/// エージングの長さの上限 (ms)
+pub const MAX_BURN_IN_MS: u64 = 72 * 60 * 60 * 1000;
+
+/// スピーカーのエージング。ノイズを決まった時間鳴らして自動で止めます
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct BurnIn {
+    /// `WhiteNoise` か `PinkNoise`
+    pub waveform: Waveform,
+    /// 0.0〜[`MAX_BURN_IN_AMPLITUDE`] の振幅
+    pub amplitude: f32,
+    pub duration_ms: u64,
+    /// 始めと終わりに音量を徐々に上げ下げする時間（ミリ秒）。0 なら上げ下げしない
+    pub ramp_ms: u64,
+}
+
+impl BurnIn {
+    pub fn validate(&self) -> Result<(), AudioError> {
+        if !self.waveform.is_noise() {
+            return Err(AudioError::Backend(format!("Burn-in plays noise only: {:?}", self.waveform)));
+        }
+        if !(0.0..=MAX_BURN_IN_AMPLITUDE).contains(&self.amplitude) {
+            return Err(AudioError::Backend(format!("Burn-in amplitude must be between 0 and {}: {}", MAX_BURN_IN_AMPLITUDE, self.amplitude)));
+        }
+        if !(1..=MAX_BURN_IN_MS).contains(&self.duration_ms) {
+            return Err(AudioError::Backend(format!("Burn-in duration must be between 1 ms and {} hours: {} ms", MAX_BURN_IN_MS / 3_600_000, self.duration_ms)));
+        }
+        if self.ramp_ms.saturating_mul(2) > self.duration_ms {
+            return Err(AudioError::Backend(format!("The ramps must fit in the duration: {} ms", self.ramp_ms)));
+        }
+        Ok(())
+    }
+
+    /// 始めてから `elapsed_ms` たったときの振幅。上げ下げの途中なら直線的に変わります
+    pub fn level_at(&self, elapsed_ms: u64) -> f32 {
+        if elapsed_ms >= self.duration_ms {
+            return 0.0;
+        }
+        let remaining = self.duration_ms - elapsed_ms;
+        let gain = match self.ramp_ms {
+            0 => 1.0,
+            ramp => (elapsed_ms.min(remaining) as f64 / ramp as f64).min(1.0),
+        };
+        (f64::from(self.amplitude.min(MAX_BURN_IN_AMPLITUDE)) * gain) as f32
+    }
+}
+
+/// 再生中に変えられるテンポ。出力スレッドと共有します
+#[derive(Debug, Clone)]
+pub struct Tempo(Arc<AtomicU32>);
+
+impl Tempo {
+    pub fn new(bpm: f32) -> Self {
+        Self(Arc::new(AtomicU32::new(bpm.to_bits())))
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, bpm: f32) {
+        self.0.store(bpm.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// 再生する信号
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum Signal {
+    Tone(ToneRequest),
+    /// 同じ波形の複数の音を同時に鳴らします
+    Chord {
+        waveform: Waveform,
+        tones: Vec<ChordTone>,
+        /// 再生時間（ミリ秒）。`None` なら止めるまで再生します
+        duration_ms: Option<u64>,
+    },
+    /// DTMF の数字を順に鳴らします。各数字は 2 つの正弦波を `amplitude` の半分ずつで重ねたもの
+    Dtmf {
+        digits: String,
+        amplitude: f32,
+        /// 1 つの数字を鳴らす時間（ミリ秒）
+        tone_ms: u64,
+        /// 数字の間の無音（ミリ秒）
+        gap_ms: u64,
+    },
+    /// 周波数を上げていく正弦波。周波数特性の測定に使います
+    Sweep(Sweep),
+    /// 止めるまで拍を刻みます。テンポは再生中に変えられます
+    Metronome(Metronome),
+    /// 決まった時間ノイズを鳴らし、終わったら止まります
+    BurnIn(BurnIn),
+    /// 音声ファイル（WAV / FLAC / Ogg Vorbis / MP3）を鳴らします。再生中にシークできます
+    File { path: String },
+}
+
+impl Signal {
+    pub fn validate(&self) -> Result<(), AudioError> {
+        match self {
+            Signal::Tone(tone) => tone.validate(),
+            Signal::Chord { waveform, tones, .. } => {
+                if tones.is_empty() {
+                    return Err(AudioError::Backend("A chord needs at least one tone".to_string()));
+                }
+                for tone in tones {
+                    ToneRequest { waveform: *waveform, frequency: tone.frequency, amplitude: tone.amplitude, duration_ms: None }.validate()?;
+                }
+                let total: f32 = tones.iter().map(|t| t.amplitude).sum();
+                if total > 1.0 {
+                    return Err(AudioError::Backend(format!("The amplitudes of a chord must add up to 1 or less: {}", total)));
+                }
+                Ok(())
+            }
+            Signal::Dtmf { digits, amplitude, tone_ms, .. } => {
+                if !(0.0..=1.0).contains(amplitude) {
+                    return Err(AudioError::Backend(format!("Amplitude must be between 0 and 1: {}", amplitude)));
+                }
+                if digits.is_empty() || *tone_ms == 0 {
+                    return Err(AudioError::Backend("Nothing to play".to_string()));
+                }
+                match digits.chars().find(|&d| dtmf_frequencies(d).is_none()) {
+                    Some(digit) => Err(AudioError::Backend(format!("Not a DTMF digit: {}", digit))),
+                    None => Ok(()),
+                }
+            }
+            Signal::Sweep(sweep) => sweep.validate(),
+            Signal::Metronome(metronome) => metronome.validate(),
+            Signal::BurnIn(burn_in) => burn_in.validate(),
+            Signal::File { path } => {
+                if path.is_empty() {
+                    return Err(AudioError::Backend("Nothing to play".to_string()));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// 信号源を作ります。メトロノームは `tempo` を読み続けるので、再生中にテンポを変えられます。
+    pub fn source(self, sample_rate: u32, tempo: Tempo) -> Box<dyn SignalSource> {
+        match self {
+            Signal::Metronome(metronome) => Box::new(MetronomeSource::new(metronome, sample_rate, tempo)),
+            Signal::BurnIn(burn_in) => Box::new(BurnInSource::new(burn_in, sample_rate)),
+            Signal::Sweep(sweep) => Box::new(SweepSource::new(sweep, sample_rate)),
+            // 開けなければ何も鳴らさずに終わる。先に AudioFileSource::open で確かめておくこと
+            Signal::File { path } => match AudioFileSource::open(&path, SeekRequest::default()) {
+                Ok(file) => Box::new(file),
+                Err(_) => Box::new(ToneSource { segments: VecDeque::new() }),
+            },
+            signal => Box::new(ToneSource::new(signal, sample_rate)),
+        }
+    }
+}
+
+/// 出力のバッファの長さの範囲 (ms)
+pub const MIN_BUFFER_MS: u32 = 3;
+pub const MAX_BUFFER_MS: u32 = 500;
+const MIN_SAMPLE_RATE: u32 = 8_000;
+const MAX_SAMPLE_RATE: u32 = 384_000;
+const MAX_CHANNELS: u16 = 8;
+
+/// ジェネレーターの出力のしかた
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[serde(default)]
+pub struct OutputFormat {
+    /// 出力するフォーマット。`None` ならデバイスのミックスフォーマット。
+    /// 共有モードでは OS がミックスフォーマットに変換します
+    pub format: Option<DeviceFormat>,
+    /// `format` のまま排他モードで出力し、OS のミキサーとリサンプラーを通しません
+    pub exclusive: bool,
+    /// バッファの長さ (ms)。`None` なら共有モードでは 50ms、排他モードではデバイスの既定の周期
+    pub buffer_ms: Option<u32>,
+}
+
+impl OutputFormat {
+    pub fn validate(&self) -> Result<(), AudioError> {
+        if let Some(ms) = self.buffer_ms {
+            if !(MIN_BUFFER_MS..=MAX_BUFFER_MS).contains(&ms) {
+                return Err(AudioError::Backend(format!("Buffer must be between {} and {} ms: {}", MIN_BUFFER_MS, MAX_BUFFER_MS, ms)));
+            }
+        }
+        let Some(format) = &self.format else {
+            return match self.exclusive {
+                true => Err(AudioError::Backend("Exclusive mode needs a format".to_string())),
+                false => Ok(()),
+            };
+        };
+        let sample_type_ok = match format.float {
+            true => format.bits_per_sample == 32 && format.container_bits == 32,
+            false => [16, 24, 32].contains(&format.bits_per_sample)
+                && [16, 24, 32].contains(&format.container_bits)
+                && format.bits_per_sample <= format.container_bits,
+        };
+        if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&format.sample_rate) || !sample_type_ok || !(1..=MAX_CHANNELS).contains(&format.channels) {
+            return Err(AudioError::Backend(format!("Unsupported output format: {:?}", format)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PlaybackRequest {
+    /// 出力先のデバイス。`None` なら既定のデバイス
+    pub device_id: Option<String>,
+    pub signal: Signal,
+    pub output: OutputFormat,
+}
+
+impl PlaybackRequest {
+    pub fn validate(&self) -> Result<(), AudioError> {
+        self.signal.validate()?;
+        self.output.validate()
+    }
+}
+
+/// 同期再生で出力を遅らせる時間の上限 (ms)
+pub const MAX_PLAYBACK_DELAY_MS: u32 = 2000;
+
+/// 同期再生の出力先の 1 つ
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct SyncedOutput {
+    pub device_id: String,
+    /// このデバイスだけ遅らせる時間 (ms)。Bluetooth のスピーカーとの遅れの違いを揃えるために使います
+    pub delay_ms: u32,
+}
+
+/// 同じ信号を複数のデバイスで同時に鳴らす再生
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SyncedPlaybackRequest {
+    pub signal: Signal,
+    pub outputs: Vec<SyncedOutput>,
+    /// すべての出力先で使う出力のしかた
+    pub output: OutputFormat,
+}
+
+impl SyncedPlaybackRequest {
+    pub fn validate(&self) -> Result<(), AudioError> {
+        self.signal.validate()?;
+        self.output.validate()?;
+        if self.outputs.len() < 2 {
+            return Err(AudioError::Backend("Synchronized playback needs at least two devices".to_string()));
+        }
+        for (i, output) in self.outputs.iter().enumerate() {
+            validate_delay(output.delay_ms)?;
+            if self.outputs[..i].iter().any(|o| o.device_id == output.device_id) {
+                return Err(AudioError::Backend(format!("{} is listed more than once", output.device_id)));
+            }
+        }
+        Ok(())
+    }
+
+    /// 出力先ごとの再生
+    pub fn requests(&self) -> Vec<PlaybackRequest> {
+        self.outputs.iter()
+            .map(|o| PlaybackRequest { device_id: Some(o.device_id.clone()), signal: self.signal.clone(), output: self.output })
+            .collect()
+    }
+}
+
+pub fn validate_delay(delay_ms: u32) -> Result<(), AudioError> {
+    if delay_ms > MAX_PLAYBACK_DELAY_MS {
+        return Err(AudioError::Backend(format!("Delay must be at most {} ms: {}", MAX_PLAYBACK_DELAY_MS, delay_ms)));
+    }
+    Ok(())
+}
+
+/// インターリーブされた f32 のフレームを生成する信号源
+pub trait SignalSource: Send {
+    /// `out` を `channels` チャンネルのフレームで埋めます。終わりに達したら残りを無音にして `false` を返します。
+    fn fill(&mut self, out: &mut [f32], channels: usize) -> bool;
+
+    /// 鳴らしたところまでの時間と全体の長さ (ms)。ファイルのように位置のあるものだけが返します
+    fn progress_ms(&self) -> Option<(u64, Option<u64>)> {
+        None
+    }
+}
+
+struct Oscillator {
+    waveform: Waveform,
+    /// 1 サンプルで進む位相
+    step: f64,
+    amplitude: f32,
+    /// 0.0〜1.0 の位相
+    phase: f64,
+    noise_state: u32,
+    /// ピンクノイズのフィルターの状態
+    pink: [f64; 3],
+}
+
+impl Oscillator {
+    fn new(waveform: Waveform, frequency: f32, amplitude: f32, sample_rate: f64) -> Self {
+        Self { waveform, step: f64::from(frequency) / sample_rate, amplitude, phase: 0.0, noise_state: 0x1234_5678, pink: [0.0; 3] }
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        let p = self.phase;
+        let value = match self.waveform {
+            Waveform::Sine => (p * std::f64::consts::TAU).sin(),
+            Waveform::Square => if p < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Triangle => 1.0 - 4.0 * (p - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * p - 1.0,
+            Waveform::WhiteNoise => self.white(),
+            Waveform::PinkNoise => {
+                // 白色ノイズを 3 つの 1 次フィルターに通して -3 dB/oct に近づける (Paul Kellett の簡易版)
+                let white = self.white();
+                let [b0, b1, b2] = &mut self.pink;
+                *b0 = 0.99765 * *b0 + white * 0.0990460;
+                *b1 = 0.96300 * *b1 + white * 0.2965164;
+                *b2 = 0.57000 * *b2 + white * 1.0526913;
+                ((*b0 + *b1 + *b2 + white * 0.1848) * 0.25).clamp(-1.0, 1.0)
+            }
+        };
+        self.phase = (p + self.step).fract();
+        value as f32 * self.amplitude
+    }
+
+    fn white(&mut self) -> f64 {
+        // xorshift32
+        let mut x = self.noise_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.noise_state = x;
+        f64::from(x) / f64::from(u32::MAX) * 2.0 - 1.0
+    }
+}
+
+/// 同じ音を鳴らし続ける区間。発振器がなければ無音
+struct Segment {
+    oscillators: Vec<Oscillator>,
+    /// 残りのフレーム数。`None` なら止めるまで続く
+    remaining: Option<u64>,
+}
+
+/// [`Signal`] を区間の並びにして順に鳴らす信号源
+pub struct ToneSource {
+    segments: VecDeque<Segment>,
+}
+
+impl ToneSource {
+    pub fn new(signal: Signal, sample_rate: u32) -> Self {
+        let rate = f64::from(sample_rate);
+        let frames = |ms: u64| ms * u64::from(sample_rate) / 1000;
+        let segments = match signal {
+            Signal::Tone(tone) => vec![Segment {
+                oscillators: vec![Oscillator::new(tone.waveform, tone.frequency, tone.amplitude, rate)],
+                remaining: tone.duration_ms.map(frames),
+            }],
+            Signal::Chord { waveform, tones, duration_ms } => vec![Segment {
+                oscillators: tones.iter().map(|t| Oscillator::new(waveform, t.frequency, t.amplitude, rate)).collect(),
+                remaining: duration_ms.map(frames),
+            }],
+            // スイープ・メトロノーム・エージングはそれぞれの信号源が鳴らす
+            Signal::Sweep(_) | Signal::Metronome(_) | Signal::BurnIn(_) | Signal::File { .. } => Vec::new(),
+            Signal::Dtmf { digits, amplitude, tone_ms, gap_ms } => {
+                let mut segments = Vec::new();
+                for (low, high) in digits.chars().filter_map(dtmf_frequencies) {
+                    if !segments.is_empty() && gap_ms > 0 {
+                        segments.push(Segment { oscillators: Vec::new(), remaining: Some(frames(gap_ms)) });
+                    }
+                    let oscillators = [low, high].iter().map(|&f| Oscillator::new(Waveform::Sine, f, amplitude / 2.0, rate)).collect();
+                    segments.push(Segment { oscillators, remaining: Some(frames(tone_ms)) });
+                }
+                segments
+            }
+        };
+        Self { segments: segments.into() }
+    }
+
+    /// 終わった区間を取り除きます。
+    fn skip_finished(&mut self) {
+        while self.segments.front().is_some_and(|s| s.remaining == Some(0)) {
+            self.segments.pop_front();
+        }
+    }
+}
+
+impl SignalSource for ToneSource {
+    fn fill(&mut self, out: &mut [f32], channels: usize) -> bool {
+        for frame in out.chunks_mut(channels.max(1)) {
+            self.skip_finished();
+            let sample = match self.segments.front_mut() {
+                Some(segment) => {
+                    if let Some(n) = &mut segment.remaining {
+                        *n -= 1;
+                    }
+                    segment.oscillators.iter_mut().map(Oscillator::next_sample).sum()
+                }
+                None => 0.0,
+            };
+            frame.fill(sample);
+        }
+        self.skip_finished();
+        !self.segments.is_empty()
+    }
+}
+
+/// 拍の頭ごとに短いクリックを鳴らす信号源
+pub struct MetronomeSource {
+    metronome: Metronome,
+    tempo: Tempo,
+    sample_rate: f64,
+    /// 今の拍の頭からのフレーム数
+    position: f64,
+    /// 小節の中の今の拍 (0 が頭)
+    beat: u32,
+}
+
+impl MetronomeSource {
+    pub fn new(metronome: Metronome, sample_rate: u32, tempo: Tempo) -> Self {
+        Self { metronome, tempo, sample_rate: f64::from(sample_rate), position: 0.0, beat: 0 }
+    }
+
+    fn next_sample(&mut self, frames_per_beat: f64) -> f32 {
+        if self.position >= frames_per_beat {
+            // テンポを上げた直後は、拍の長さを超えて進んでいることがある
+            self.position = (self.position - frames_per_beat) % frames_per_beat;
+            self.beat = (self.beat + 1) % self.metronome.beats_per_bar;
+        }
+        let click_frames = self.sample_rate * f64::from(CLICK_MS) / 1000.0;
+        let sample = if self.position < click_frames {
+            let (frequency, gain) = match (self.metronome.accent, self.beat) {
+                (true, 0) => (ACCENT_FREQUENCY, 1.0),
+                (true, _) => (CLICK_FREQUENCY, WEAK_BEAT_GAIN),
+                (false, _) => (CLICK_FREQUENCY, 1.0),
+            };
+            // 鳴らし始めから直線的に減衰させる
+            let envelope = 1.0 - self.position / click_frames;
+            let wave = (self.position * f64::from(frequency) / self.sample_rate * std::f64::consts::TAU).sin();
+            (wave * envelope) as f32 * gain * self.metronome.amplitude
+        } else {
+            0.0
+        };
+        self.position += 1.0;
+        sample
+    }
+}
+
+impl SignalSource for MetronomeSource {
+    fn fill(&mut self, out: &mut [f32], channels: usize) -> bool {
+        let bpm = self.tempo.get().clamp(MIN_BPM, MAX_BPM);
+        let frames_per_beat = self.sample_rate * 60.0 / f64::from(bpm);
+        for frame in out.chunks_mut(channels.max(1)) {
+            frame.fill(self.next_sample(frames_per_beat));
+        }
+        true
+    }
+}
+
+/// [`Sweep`] を鳴らす信号源。位相は開始からのフレーム数で毎回計算するので、長くてもずれません
+pub struct SweepSource {
+    sweep: Sweep,
+    sample_rate: f64,
+    /// 鳴らしたフレーム数
+    position: u64,
+}
+
+impl SweepSource {
+    pub fn new(sweep: Sweep, sample_rate: u32) -> Self {
+        Self { sweep, sample_rate: f64::from(sample_rate), position: 0 }
+    }
+
+    fn total_frames(&self) -> u64 {
+        (self.sweep.duration_ms as f64 * self.sample_rate / 1000.0) as u64
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        let duration = self.sweep.duration_ms as f64 / 1000.0;
+        let t = self.position as f64 / self.sample_rate;
+        let start = f64::from(self.sweep.start_hz);
+        let span = (f64::from(self.sweep.end_hz) / start).ln();
+        let phase = std::f64::consts::TAU * start * duration / span * ((t / duration * span).exp() - 1.0);
+        let fade = (t.min(duration - t) * 1000.0 / SWEEP_FADE_MS).clamp(0.0, 1.0);
+        self.position += 1;
+        (phase.sin() * fade) as f32 * self.sweep.amplitude
+    }
+}
+
+impl SignalSource for SweepSource {
+    fn fill(&mut self, out: &mut [f32], channels: usize) -> bool {
+        let total = self.total_frames();
+        for frame in out.chunks_mut(channels.max(1)) {
+            let sample = if self.position < total { self.next_sample() } else { 0.0 };
+            frame.fill(sample);
+        }
+        self.position < total
+    }
+}
+
+/// エージングのノイズを鳴らす信号源。どの時点でも [`MAX_BURN_IN_AMPLITUDE`] を超えないよう切り詰めます
+pub struct BurnInSource {
+    burn_in: BurnIn,
+    noise: Oscillator,
+    sample_rate: u64,
+    /// 鳴らしたフレーム数
+    position: u64,
+}
+
+impl BurnInSource {
+    pub fn new(burn_in: BurnIn, sample_rate: u32) -> Self {
+        let noise = Oscillator::new(burn_in.waveform, 0.0, 1.0, f64::from(sample_rate));
+        Self { burn_in, noise, sample_rate: u64::from(sample_rate), position: 0 }
+    }
+}
+
+impl SignalSource for BurnInSource {
+    fn fill(&mut self, out: &mut [f32], channels: usize) -> bool {
+        let total = self.burn_in.duration_ms * self.sample_rate / 1000;
+        for frame in out.chunks_mut(channels.max(1)) {
+            let sample = if self.position < total {
+                let level = self.burn_in.level_at(self.position * 1000 / self.sample_rate);
+                self.position += 1;
+                (self.noise.next_sample() * level).clamp(-MAX_BURN_IN_AMPLITUDE, MAX_BURN_IN_AMPLITUDE)
+            } else {
+                0.0
+            };
+            frame.fill(sample);
+        }
+        self.position < total
+    }
+}
+
+/// 信号の前に無音を入れて遅らせる信号源。再生中に遅延を延ばせば無音を足し、縮めればその分の信号を読み飛ばします。
+pub struct DelayedSource {
+    inner: Box<dyn SignalSource>,
+    delay_ms: Arc<AtomicU32>,
+    sample_rate: u64,
+    /// 今までに入れた（読み飛ばした分を差し引いた）無音のフレーム数
+    applied: u64,
+    scratch: Vec<f32>,
+}
+
+impl DelayedSource {
+    pub fn new(inner: Box<dyn SignalSource>, sample_rate: u32, delay_ms: Arc<AtomicU32>) -> Self {
+        Self { inner, delay_ms, sample_rate: u64::from(sample_rate), applied: 0, scratch: Vec::new() }
+    }
+}
+
+impl SignalSource for DelayedSource {
+    fn fill(&mut self, out: &mut [f32], channels: usize) -> bool {
+        let channels = channels.max(1);
+        let target = u64::from(self.delay_ms.load(Ordering::Relaxed)) * self.sample_rate / 1000;
+        if target < self.applied {
+            // 遅延を縮めた分の信号を捨てる
+            let skip = (self.applied - target) as usize * channels;
+            self.scratch.resize(skip, 0.0);
+            self.inner.fill(&mut self.scratch, channels);
+            self.applied = target;
+        }
+        let silence = (((target - self.applied) as usize) * channels).min(out.len());
+        out[..silence].fill(0.0);
+        self.applied += (silence / channels) as u64;
+        if silence == out.len() {
+            return true;
+        }
+        self.inner.fill(&mut out[silence..], channels)
+    }
+
+    fn progress_ms(&self) -> Option<(u64, Option<u64>)> {
+        self.inner.progress_ms()
+    }
+}
+
+/// -1.0〜1.0 のサンプルを出力フォーマットのバイト列（リトルエンディアン）に変換します。
+/// 整数形式では有効ビットをコンテナの上位に詰めます。
+pub fn encode_samples(samples: &[f32], format: &DeviceFormat, out: &mut [u8]) {
+    let bytes = usize::from(format.container_bits / 8);
+    for (sample, dst) in samples.iter().zip(out.chunks_exact_mut(bytes)) {
+        let sample = sample.clamp(-1.0, 1.0);
+        if format.float {
+            dst.copy_from_slice(&sample.to_le_bytes()[..bytes]);
+        } else {
+            let max = ((1i64 << (format.bits_per_sample - 1)) - 1) as f32;
+            let value = ((sample * max).round() as i64) << (format.container_bits - format.bits_per_sample);
+            dst.copy_from_slice(&value.to_le_bytes()[..bytes]);
+        }
+    }
+}
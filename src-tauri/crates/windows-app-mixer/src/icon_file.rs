@@ -0,0 +1,14 @@
+use base64::{engine::general_purpose, Engine as _};
+use image::ImageFormat;
+use std::io::Cursor;
+
+use crate::ICON_SIZE;
+
+/// ユーザー指定の画像ファイルを `ICON_SIZE` 四方の PNG (base64) に変換します。
+pub fn load_icon_file_base64(path: &str) -> Option<String> {
+    let img = image::open(path).ok()?.thumbnail(ICON_SIZE, ICON_SIZE).to_rgba8();
+    let mut image_data = Vec::new();
+    let mut cursor = Cursor::new(&mut image_data);
+    img.write_to(&mut cursor, ImageFormat::Png).ok()?;
+    Some(general_purpose::STANDARD.encode(image_data))
+}
@@ -0,0 +1,347 @@
+//! アプリごとのオーディオセッション（音量・ミュート・出力先）を操作するライブラリ。
+//! プラットフォームごとの実装（WASAPI / CoreAudio / PipeWire・PulseAudio）は [`AudioBackend`] の背後にあり、UI には依存しません。
+//! イベントは [`MixerEventSink`] を通じて通知されます。
+
+// 対応バックエンドのないプラットフォームでは共通部品の一部が使われない
+#![cfg_attr(not(any(windows, target_os = "macos", target_os = "linux")), allow(dead_code))]
+
+mod accent;
+mod apps;
+mod backend;
+mod diff;
+mod error;
+mod icon_file;
+mod notification;
+mod sink;
+mod worker;
+
+pub mod audio_file;
+pub mod boost;
+pub mod capture_file;
+pub mod generator;
+pub mod measurement;
+pub mod mock;
+pub mod process_watch;
+pub mod stream_server;
+pub mod virtual_cable;
+
+#[cfg(windows)]
+mod wasapi;
+#[cfg(target_os = "macos")]
+mod coreaudio;
+#[cfg(target_os = "linux")]
+mod pulse;
+
+pub use backend::{create_platform_backend, AudioBackend};
+pub use capture_file::{CaptureSource, FileCaptureRequest, FileEncoding, Rotation, DEFAULT_CAPTURE_SAMPLE_RATE};
+pub use error::AudioError;
+pub use generator::{
+    BurnIn, ChordTone, Metronome, OutputFormat, PlaybackRequest, Signal, Sweep, SyncedOutput, SyncedPlaybackRequest, ToneRequest,
+    Waveform, GENERATOR_SESSION_GUID, GENERATOR_SESSION_NAME, MAX_BPM, MAX_BURN_IN_AMPLITUDE, MAX_BURN_IN_MS, MAX_PLAYBACK_DELAY_MS, MAX_SWEEP_HZ, MIN_BPM, MIN_SWEEP_HZ,
+};
+pub use measurement::{BandLevel, FrequencyResponse, MeasurementRequest, Recording};
+pub use notification::{AudioNotification, ChangeOrigin, SessionState};
+pub use sink::{InputPeak, MixerEvent, MixerEventSink, PeakLevel};
+pub use stream_server::{NetworkStream, NetworkStreamRequest};
+pub use virtual_cable::{VirtualCable, VirtualCableKind};
+pub use worker::{AudioWorker, CallKind, Notifier};
+
+#[cfg(windows)]
+pub use wasapi::WasapiBackend;
+#[cfg(target_os = "macos")]
+pub use coreaudio::CoreAudioBackend;
+#[cfg(target_os = "linux")]
+pub use pulse::PulseBackend;
+
+/// セッションのアイコンの一辺の長さ (px)。高 DPI の画面で 32px の枠に表示してもぼやけない大きさにする
+pub const ICON_SIZE: u32 = 64;
+
+/// 実行ファイルごとの表示名・アイコンの上書き
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, PartialEq)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[serde(default)]
+pub struct AppOverride {
+    pub display_name: Option<String>,
+    pub icon_path: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct AudioSessionInfo {
+    pub process_id: u32,
+    pub process_name: String,
+    /// 上書き設定のキーとなる実行ファイル名（表示名の変更に影響されない）
+    pub exe_name: String,
+    pub volume: f32,
+    pub is_muted: bool,
+    pub peak_level: f32,
+    /// `ICON_SIZE` 四方の PNG (base64)
+    pub icon_base64: Option<String>,
+    /// アイコンで最も多く使われている色 (`#rrggbb`)。アイコンがないか、色のないアイコンなら `None`
+    pub accent_color: Option<String>,
+    pub device_id: String,
+    /// セッションの識別子。同じプロセス・同じ出力先に複数のセッションがあっても区別できます
+    pub instance_id: String,
+    /// 音を出している間は `Active`。セッションがあっても再生していなければ `Inactive` です
+    pub state: SessionState,
+    /// アプリに永続的に設定された出力先。未設定なら `None`
+    pub persisted_device_id: Option<String>,
+    /// 出力先を設定したが、まだ `device_id` に反映されていない（多くはアプリの再起動が必要）
+    pub route_pending: bool,
+    /// OS のメディアコントロールに出しているアプリの再生中の曲。対応していないアプリやプラットフォームでは `None`
+    pub now_playing: Option<NowPlaying>,
+}
+
+/// メディアの再生状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+/// アプリが OS のメディアコントロールに出している再生中の曲と、受け付ける操作
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct NowPlaying {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub status: PlaybackStatus,
+    pub can_play: bool,
+    pub can_pause: bool,
+    pub can_next: bool,
+    pub can_previous: bool,
+}
+
+/// アプリへ送るメディアの操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum MediaCommand {
+    Play,
+    Pause,
+    TogglePlayPause,
+    Next,
+    Previous,
+}
+
+impl AudioSessionInfo {
+    /// アイコンと、そこから求めたアクセントカラーを設定します。
+    pub(crate) fn set_icon(&mut self, icon_base64: Option<String>) {
+        self.accent_color = icon_base64.as_deref().and_then(accent::accent_color);
+        self.icon_base64 = icon_base64;
+    }
+}
+
+/// `sessions-changed` イベントのペイロード。前回の通知からの差分だけを含みます。
+#[derive(Debug, serde::Serialize, Clone, Default)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct SessionsDiff {
+    pub added: Vec<AudioSessionInfo>,
+    pub updated: Vec<AudioSessionInfo>,
+    pub removed: Vec<RemovedSession>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct RemovedSession {
+    pub process_id: u32,
+    pub device_id: String,
+    pub instance_id: String,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct AudioDeviceInfo {
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+    /// 端子に何かが接続されているか。端子の状態を報告しないデバイスでは `None`
+    pub jack_connected: Option<bool>,
+    /// Bluetooth 機器の場合だけ `Some`
+    pub bluetooth: Option<BluetoothInfo>,
+}
+
+/// 既定の出力デバイス全体の音量
+#[derive(Debug, serde::Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct MasterVolume {
+    pub device_id: String,
+    pub volume: f32,
+    pub is_muted: bool,
+    /// dB で表した音量と範囲。dB で扱えないバックエンドでは `None` です
+    pub level: Option<GainLevel>,
+}
+
+/// ある役割の既定の出力・入力デバイスの ID。なければ `None` です
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct DefaultEndpoints {
+    pub output: Option<String>,
+    pub input: Option<String>,
+}
+
+/// dB で表したゲインと、設定できる範囲・刻み
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct GainLevel {
+    pub db: f32,
+    pub min_db: f32,
+    pub max_db: f32,
+    pub step_db: f32,
+}
+
+/// 入力デバイスのゲイン。`boost` はハードウェアのマイクブーストで、持たないデバイスでは `None` です
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct InputGain {
+    pub device_id: String,
+    pub volume: GainLevel,
+    pub boost: Option<GainLevel>,
+}
+
+/// 入力デバイスの音声処理のうち、切り替えを扱うもの
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum CaptureEffectKind {
+    EchoCancellation,
+    NoiseSuppression,
+    AutomaticGainControl,
+}
+
+/// ドライバー（APO）が公開している音声処理とその状態
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct CaptureEffect {
+    pub kind: CaptureEffectKind,
+    pub enabled: bool,
+    /// ドライバーが切り替えを許している
+    pub can_toggle: bool,
+}
+
+/// マイクを使っているアプリ
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct MicrophoneUser {
+    /// OS の同意ストアからしか分からない場合は `None`
+    pub pid: Option<u32>,
+    pub exe_name: String,
+    pub process_name: String,
+    /// 録音しているデバイス。同意ストアからは分からない
+    pub device_id: Option<String>,
+}
+
+/// セッションを持たないことがある古い再生 API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum LegacyAudioApi {
+    DirectSound,
+    /// waveOut など Windows のマルチメディア API
+    Mme,
+}
+
+/// 自分のセッションが見当たらないのに、古い API で音を出しているらしいプロセス。
+/// 読み込んでいるモジュールから推測するだけなので、実際には鳴っていないこともあります
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct LegacyStream {
+    pub pid: u32,
+    pub exe_name: String,
+    pub api: LegacyAudioApi,
+    /// システム音のセッションが鳴っている。このプロセスの音がそちらにまとめられている可能性がある
+    pub system_sounds_active: bool,
+}
+
+/// 出力のサンプルフォーマット
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct DeviceFormat {
+    pub sample_rate: u32,
+    /// 1 サンプルの有効ビット数
+    pub bits_per_sample: u16,
+    /// 1 サンプルが占めるビット数。24bit を 32bit に詰める場合は 32
+    pub container_bits: u16,
+    /// 浮動小数点形式か
+    pub float: bool,
+    pub channels: u16,
+}
+
+/// ミラーの遅延の上限 (ms)
+pub const MAX_MIRROR_DELAY_MS: u32 = 2000;
+
+/// アプリ（子プロセスを含む）の音を、元の出力先とは別のデバイスにも流すミラー
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct MirrorRequest {
+    pub pid: u32,
+    /// 複製を流す出力デバイス
+    pub device_id: String,
+    /// 複製を遅らせる時間 (ms)。出力先ごとの遅れの違いを揃えるために使います
+    pub delay_ms: u32,
+}
+
+impl MirrorRequest {
+    pub fn validate(&self) -> Result<(), AudioError> {
+        if self.delay_ms > MAX_MIRROR_DELAY_MS {
+            return Err(AudioError::Backend(format!("Delay must be at most {} ms: {}", MAX_MIRROR_DELAY_MS, self.delay_ms)));
+        }
+        Ok(())
+    }
+}
+
+/// ブーストで上げられる量の上限 (dB)
+pub const MAX_BOOST_DB: f32 = 10.0;
+
+/// アプリ（子プロセスを含む）の音を 100% より大きくするブースト。声の小さい通話相手や音の小さい動画向け
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct BoostRequest {
+    pub pid: u32,
+    /// 上げる量 (dB)。0 より大きく [`MAX_BOOST_DB`] 以下
+    pub gain_db: f32,
+}
+
+impl BoostRequest {
+    pub fn validate(&self) -> Result<(), AudioError> {
+        if !(self.gain_db > 0.0 && self.gain_db <= MAX_BOOST_DB) {
+            return Err(AudioError::Backend(format!("Boost must be above 0 and at most {} dB: {}", MAX_BOOST_DB, self.gain_db)));
+        }
+        Ok(())
+    }
+}
+
+/// 既定のデバイスの役割。Windows では役割ごとに別のデバイスを既定にできます
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum DeviceRole {
+    /// ゲームやシステム音など一般の用途
+    Console,
+    /// 音楽・動画の再生や録音
+    Multimedia,
+    /// 通話アプリ
+    Communications,
+}
+
+/// Bluetooth のオーディオプロファイル
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum BluetoothProfile {
+    /// 高音質な再生専用のプロファイル
+    A2dp,
+    /// マイクが使える通話用のプロファイル (HFP/HSP)。再生音質は大きく下がります
+    HandsFree,
+}
+
+#[derive(Debug, serde::Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct BluetoothInfo {
+    /// バッテリー残量 (0〜100)。機器や OS が報告しなければ `None`
+    pub battery_percent: Option<u8>,
+    /// 現在のプロファイル。判別できなければ `None`
+    pub profile: Option<BluetoothProfile>,
+}
+
+/// セッションを一意に識別するキー。同じプロセスでもデバイスごとに別のセッションになります。
+pub fn session_key(pid: u32, device_id: &str) -> String {
+    format!("{}-{}", pid, device_id)
+}
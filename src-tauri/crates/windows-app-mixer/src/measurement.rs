@@ -0,0 +1,127 @@
+//! スイープとマイクの録音から大まかな周波数特性を求める測定。録音はバックエンドの
+//! [`crate::AudioBackend::start_recording`] が担い、ここでは録った音を帯域ごとのレベルにします。
+//! スイープは各周波数を決まった時刻に鳴らすので、その時刻の録音の大きさがその帯域の大きさになります。
+
+use crate::generator::{SignalSource, Sweep, SweepSource};
+use crate::AudioError;
+
+const MAX_BANDS_PER_OCTAVE: u32 = 24;
+/// 出力から録音までの遅れとして探す最長時間 (ms)
+pub const MAX_LATENCY_MS: u32 = 1000;
+/// 遅れを求めるときに照らし合わせるスイープの区間の長さ (ms) と、その中心の周波数 (Hz)。
+/// たいていのマイクでよく拾える周波数を選ぶ
+const ALIGN_WINDOW_MS: f64 = 50.0;
+const ALIGN_FREQUENCY: f32 = 1000.0;
+/// 録音の最大値がこれ (dBFS) より小さければ、スイープを拾えなかったとみなす
+const MIN_PEAK_DB: f32 = -60.0;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct MeasurementRequest {
+    /// スイープを鳴らすデバイス。`None` なら既定の出力デバイス
+    pub output_device_id: Option<String>,
+    /// 録音するマイク。`None` なら既定の入力デバイス
+    pub input_device_id: Option<String>,
+    pub sweep: Sweep,
+    /// 1 オクターブをいくつの帯域に分けるか
+    pub bands_per_octave: u32,
+}
+
+impl MeasurementRequest {
+    pub fn validate(&self) -> Result<(), AudioError> {
+        self.sweep.validate()?;
+        if !(1..=MAX_BANDS_PER_OCTAVE).contains(&self.bands_per_octave) {
+            return Err(AudioError::Backend(format!("Bands per octave must be between 1 and {}: {}", MAX_BANDS_PER_OCTAVE, self.bands_per_octave)));
+        }
+        Ok(())
+    }
+}
+
+/// マイクで録った音。モノラルにまとめてあります
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recording {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct BandLevel {
+    /// 帯域の中心周波数 (Hz)
+    pub frequency: f32,
+    /// 鳴らしたスイープに対するレベル (dB)。そのまま返ってくれば 0
+    pub level_db: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct FrequencyResponse {
+    pub bands: Vec<BandLevel>,
+    /// 出力から録音までの遅れ (ms)
+    pub latency_ms: f32,
+    /// 録音の最大値 (dBFS)
+    pub peak_db: f32,
+}
+
+fn to_db(value: f64) -> f32 {
+    (20.0 * value.max(1e-10).log10()) as f32
+}
+
+/// 録音の中でスイープが始まる位置（フレーム）。[`ALIGN_FREQUENCY`] あたりの区間の相互相関が最大になるところを探します。
+fn find_latency(sweep: &Sweep, reference: &[f32], recording: &Recording) -> usize {
+    let rate = f64::from(recording.sample_rate);
+    let half = (ALIGN_WINDOW_MS / 2000.0 * rate) as usize;
+    let center = (sweep.time_at(ALIGN_FREQUENCY) * rate) as usize;
+    let start = center.saturating_sub(half).min(reference.len());
+    let segment = &reference[start..(center + half).min(reference.len())];
+    let max_lag = (u64::from(MAX_LATENCY_MS) * u64::from(recording.sample_rate) / 1000) as usize;
+    let (mut best_lag, mut best) = (0, f64::MIN);
+    for lag in 0..=max_lag {
+        let Some(window) = recording.samples.get(start + lag..start + lag + segment.len()) else { break };
+        let correlation: f64 = segment.iter().zip(window).map(|(a, b)| f64::from(*a) * f64::from(*b)).sum();
+        if correlation.abs() > best {
+            best = correlation.abs();
+            best_lag = lag;
+        }
+    }
+    best_lag
+}
+
+/// `sweep` を鳴らしながら録った `recording` から、帯域ごとのレベルを求めます。
+pub fn analyze(sweep: &Sweep, bands_per_octave: u32, recording: &Recording) -> Result<FrequencyResponse, AudioError> {
+    let peak = recording.samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    let peak_db = to_db(f64::from(peak));
+    if recording.sample_rate == 0 || peak_db < MIN_PEAK_DB {
+        return Err(AudioError::Backend("The microphone did not pick up the sweep".to_string()));
+    }
+    let rate = f64::from(recording.sample_rate);
+    let mut reference = vec![0.0f32; (sweep.duration_ms as f64 * rate / 1000.0) as usize];
+    SweepSource::new(*sweep, recording.sample_rate).fill(&mut reference, 1);
+    let latency = find_latency(sweep, &reference, recording);
+
+    let half_band = 2f32.powf(0.5 / bands_per_octave as f32);
+    // 振幅 A の正弦波の実効値は A / √2
+    let expected = f64::from(sweep.amplitude) / std::f64::consts::SQRT_2;
+    let mut bands = Vec::new();
+    let mut k = 0;
+    loop {
+        let frequency = sweep.start_hz * 2f32.powf(k as f32 / bands_per_octave as f32);
+        if frequency > sweep.end_hz {
+            break;
+        }
+        k += 1;
+        // 録音のサンプルレートの半分を超える帯域は測れない
+        if f64::from(frequency) >= rate / 2.0 {
+            break;
+        }
+        let from = latency + (sweep.time_at(frequency / half_band) * rate) as usize;
+        let to = (latency + (sweep.time_at(frequency * half_band) * rate) as usize).min(recording.samples.len());
+        if from >= to {
+            continue;
+        }
+        let window = &recording.samples[from..to];
+        let rms = (window.iter().map(|s| f64::from(*s).powi(2)).sum::<f64>() / window.len() as f64).sqrt();
+        bands.push(BandLevel { frequency, level_db: to_db(rms / expected) });
+    }
+    Ok(FrequencyResponse { bands, latency_ms: (latency as f64 * 1000.0 / rate) as f32, peak_db })
+}
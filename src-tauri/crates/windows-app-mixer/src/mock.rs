@@ -0,0 +1,1234 @@
+//! 実機を使わずにミキサーを動かすためのモックバックエンド。
+//! [`MockAudioHandle`] でセッション・デバイス・OS 側の変化を台本どおりに与え、
+//! バックエンドへの書き込みや再列挙の回数を確認できます。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::apps::AppPresentation;
+use crate::diff::SessionPublisher;
+use crate::generator;
+use crate::stream_server;
+use crate::{
+    session_key, AppOverride, AudioBackend, AudioDeviceInfo, AudioError, AudioNotification, ChangeOrigin, AudioSessionInfo,
+    BluetoothProfile, BoostRequest, CaptureEffect, CaptureEffectKind, CaptureSource, DeviceFormat, DeviceRole, FileCaptureRequest, GainLevel, InputGain, InputPeak, LegacyAudioApi, LegacyStream, MasterVolume, MediaCommand, MicrophoneUser, MirrorRequest, MixerEvent, MixerEventSink, NetworkStream, NetworkStreamRequest, Notifier, NowPlaying, PeakLevel, PlaybackRequest, PlaybackStatus, SessionState,
+    Recording, SessionsDiff, Signal, SyncedPlaybackRequest, GENERATOR_SESSION_GUID, GENERATOR_SESSION_NAME,
+};
+
+/// 出力デバイスの dB の範囲と刻み。モックでは音量のスカラー値をこの範囲に直線で割り当てます
+pub const VOLUME_RANGE: (f32, f32, f32) = (-65.25, 0.0, 0.03125);
+
+/// モックの出力デバイスが報告する dB の音量
+pub fn volume_level(volume: f32) -> GainLevel {
+    let (min_db, max_db, step_db) = VOLUME_RANGE;
+    GainLevel { db: min_db + (max_db - min_db) * volume, min_db, max_db, step_db }
+}
+
+/// バックエンドに対して行われた書き込み操作
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockCall {
+    SetVolume { pid: u32, volume: f32 },
+    SetMute { pid: u32, mute: bool },
+    SetInstanceVolume { instance_id: String, volume: f32 },
+    SetInstanceMute { instance_id: String, mute: bool },
+    SetRouting { pid: u32, device_id: String },
+    ClearRouting(u32),
+    ForceRouteMigration(u32),
+    SendMediaCommand { pid: u32, command: MediaCommand },
+    SetDefaultDevice { device_id: String },
+    SetDefaultCaptureDevice { device_id: String, role: Option<DeviceRole> },
+    SetInputMonitor { capture_device_id: String, output_device_id: Option<String> },
+    SetBluetoothProfile { device_id: String, profile: BluetoothProfile },
+    StartPlayback(PlaybackRequest),
+    StartSyncedPlayback(SyncedPlaybackRequest),
+    SetPlaybackDelay { device_id: String, delay_ms: u32 },
+    StopPlayback,
+    SetMetronomeTempo(f32),
+    SeekPlayback(u64),
+    StartRecording { device_id: Option<String>, max_ms: u32 },
+    StopRecording,
+    StartInputMeter { device_id: Option<String> },
+    StopInputMeter,
+    SetInputGain { device_id: String, db: f32 },
+    SetInputBoost { device_id: String, db: f32 },
+    SetCaptureEffect { device_id: String, kind: CaptureEffectKind, enabled: bool },
+    SetMasterVolume(f32),
+    SetMasterVolumeDb(f32),
+    SetMasterMute(bool),
+    SetDeviceVolume { device_id: String, volume: f32 },
+    SetDeviceMute { device_id: String, mute: bool },
+    StartFileCapture(FileCaptureRequest),
+    StopFileCapture,
+    StartNetworkStream(NetworkStreamRequest),
+    StopNetworkStream,
+    StartMirror(MirrorRequest),
+    SetMirrorDelay { pid: u32, delay_ms: u32 },
+    StopMirror(u32),
+    StartBoost(BoostRequest),
+    SetBoostGain { pid: u32, gain_db: f32 },
+    StopBoost(u32),
+}
+
+#[derive(Default)]
+struct MockState {
+    /// OS 側にある（と見なす）セッション。非表示設定などの適用前の状態
+    sessions: Vec<AudioSessionInfo>,
+    devices: Vec<AudioDeviceInfo>,
+    capture_devices: Vec<AudioDeviceInfo>,
+    peaks: HashMap<u32, f32>,
+    /// 入力デバイスごとのピーク値
+    input_peaks: HashMap<String, f32>,
+    /// 計測中の入力デバイス。内側の `None` は既定のデバイスを追いかける
+    input_meter: Option<Option<String>>,
+    /// 入力デバイスごとの音量とマイクブースト
+    input_gains: HashMap<String, InputGain>,
+    /// 録音中のアプリ
+    microphone_users: Vec<MicrophoneUser>,
+    /// 古い再生 API のモジュールを読み込んでいるプロセス
+    legacy_audio: HashMap<u32, (String, LegacyAudioApi)>,
+    /// 入力デバイスごとにドライバーが公開している音声処理
+    capture_effects: HashMap<String, Vec<CaptureEffect>>,
+    calls: Vec<MockCall>,
+    scans: usize,
+    notifier: Option<Notifier>,
+    /// オーディオサービスとの接続が切れている（バックエンドの作り直しで復帰する）
+    lost: bool,
+    /// プロセスごとに永続的に設定された出力先
+    routes: HashMap<u32, String>,
+    /// `force_route_migration` でも出力先を移さない（再起動が必要な）プロセス
+    restart_required: HashSet<u32>,
+    /// デバイスごとに排他モードで受け付けるフォーマット
+    formats: HashMap<String, Vec<DeviceFormat>>,
+    /// 出力デバイスごとの全体の音量とミュート。既定のデバイスのものがマスター音量になる
+    endpoint_volumes: HashMap<String, (f32, bool)>,
+    /// 音量の変化を通知する出力デバイス
+    watched_devices: Vec<String>,
+    /// PID ごとの動いているミラー
+    mirrors: HashMap<u32, MirrorRequest>,
+    /// PID ごとの動いているブースト
+    boosts: HashMap<u32, BoostRequest>,
+    /// 再生中のジェネレーター
+    playback: Option<PlaybackRequest>,
+    /// 再生中の同期再生
+    synced_playback: Option<SyncedPlaybackRequest>,
+    /// 録音中の入力デバイス。内側の `None` は既定のデバイス
+    recording: Option<Option<String>>,
+    /// 録音を止めたときに返す音
+    recorded: Recording,
+    /// ファイルへの録音
+    file_capture: Option<FileCaptureRequest>,
+    /// LAN への配信
+    network_stream: Option<NetworkStreamRequest>,
+    /// 大文字の実行ファイル名ごとの、アプリが更新された回数
+    app_versions: HashMap<String, u32>,
+}
+
+impl MockState {
+    fn network_stream_info(&self) -> Option<NetworkStream> {
+        let request = self.network_stream.clone()?;
+        let url = format!("http://127.0.0.1:{}{}", request.port, stream_server::STREAM_PATH);
+        Some(NetworkStream { request, url, listeners: 0 })
+    }
+
+    /// 取り込む出力デバイスかアプリがあるか
+    fn check_capture_source(&self, source: &CaptureSource) -> Result<(), AudioError> {
+        match source {
+            CaptureSource::Device(Some(device_id)) if !self.devices.iter().any(|d| &d.id == device_id) => {
+                Err(AudioError::Backend(format!("Unknown device {}", device_id)))
+            }
+            CaptureSource::Device(None) => self.default_output().map(|_| ()),
+            CaptureSource::App(pid) if !self.sessions.iter().any(|s| s.process_id == *pid) => Err(AudioError::SessionNotFound(*pid)),
+            _ => Ok(()),
+        }
+    }
+
+    fn default_output(&self) -> Result<String, AudioError> {
+        self.devices.iter()
+            .find(|d| d.is_default)
+            .map(|d| d.id.clone())
+            .ok_or_else(|| AudioError::Backend("No default output device".to_string()))
+    }
+
+    fn endpoint_volume(&mut self, device_id: &str) -> Result<&mut (f32, bool), AudioError> {
+        if !self.devices.iter().chain(&self.capture_devices).any(|d| d.id == device_id) {
+            return Err(AudioError::Backend(format!("Unknown device {}", device_id)));
+        }
+        Ok(self.endpoint_volumes.entry(device_id.to_string()).or_default())
+    }
+
+    /// 出力デバイスの音量が変わったときに OS が送る通知
+    fn endpoint_notifications(&self, device_id: &str, origin: ChangeOrigin) -> Vec<AudioNotification> {
+        let mut notifications = Vec::new();
+        if self.default_output().ok().as_deref() == Some(device_id) {
+            notifications.push(AudioNotification::MasterVolumeChanged { origin });
+        }
+        if self.watched_devices.iter().any(|d| d == device_id) {
+            notifications.push(AudioNotification::DeviceVolumeChanged { device_id: device_id.to_string(), origin });
+        }
+        notifications
+    }
+}
+
+/// モックの状態を操作するハンドル。テストとオーディオスレッドの間で共有されます。
+#[derive(Clone, Default)]
+pub struct MockAudioHandle {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockAudioHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// [`crate::AudioWorker::spawn_with`] に渡すファクトリ
+    pub fn factory(&self) -> impl Fn(Notifier, Arc<dyn MixerEventSink>) -> Result<Box<dyn AudioBackend>, AudioError> + Send + 'static {
+        let handle = self.clone();
+        move |notifier, sink| Ok(Box::new(handle.backend(notifier, sink)))
+    }
+
+    /// このハンドルの状態を使うバックエンドを作ります。
+    pub fn backend(&self, notifier: Notifier, sink: Arc<dyn MixerEventSink>) -> MockAudioBackend {
+        let app_versions = {
+            let mut state = self.lock();
+            state.notifier = Some(notifier);
+            state.lost = false;
+            state.app_versions.clone()
+        };
+        MockAudioBackend {
+            handle: self.clone(),
+            sink,
+            apps: AppPresentation::default(),
+            session_cache: Vec::new(),
+            cache_dirty: true,
+            sessions_changed: false,
+            publisher: SessionPublisher::default(),
+            app_versions,
+        }
+    }
+
+    /// テスト用のセッション情報を組み立てます。
+    pub fn session_info(pid: u32, exe_name: &str, device_id: &str) -> AudioSessionInfo {
+        AudioSessionInfo {
+            process_id: pid,
+            process_name: exe_name.to_string(),
+            exe_name: exe_name.to_string(),
+            volume: 1.0,
+            is_muted: false,
+            peak_level: 0.0,
+            icon_base64: None,
+            accent_color: None,
+            device_id: device_id.to_string(),
+            instance_id: session_key(pid, device_id),
+            state: SessionState::Active,
+            persisted_device_id: None,
+            route_pending: false,
+            now_playing: None,
+        }
+    }
+
+    /// セッションを追加し、OS と同じくセッション生成を通知します。
+    pub fn add_session(&self, session: AudioSessionInfo) {
+        self.lock().sessions.push(session);
+        self.notify(AudioNotification::SessionCreated);
+    }
+
+    /// セッションを削除し、切断を通知します。
+    pub fn remove_session(&self, pid: u32) {
+        let removed: Vec<String> = {
+            let mut state = self.lock();
+            let keys = state.sessions.iter()
+                .filter(|s| s.process_id == pid)
+                .map(|s| s.instance_id.clone())
+                .collect();
+            state.sessions.retain(|s| s.process_id != pid);
+            keys
+        };
+        for session_key in removed {
+            self.notify(AudioNotification::SessionDisconnected { session_key });
+        }
+    }
+
+    /// 他のアプリや OS の音量ミキサーによる変更を再現します。
+    pub fn external_volume_change(&self, pid: u32, volume: f32, muted: bool) {
+        let keys: Vec<String> = {
+            let mut state = self.lock();
+            state.sessions.iter_mut()
+                .filter(|s| s.process_id == pid)
+                .map(|s| {
+                    s.volume = volume;
+                    s.is_muted = muted;
+                    s.instance_id.clone()
+                })
+                .collect()
+        };
+        for session_key in keys {
+            self.notify(AudioNotification::VolumeChanged { session_key, volume, muted, origin: ChangeOrigin::External });
+        }
+    }
+
+    /// 別スレッドでのアイコンの抽出が終わったことを再現します。モックでは exe 名をパスとして扱います。
+    pub fn icon_loaded(&self, exe_name: &str, icon_base64: &str) {
+        self.notify(AudioNotification::IconLoaded { exe_path: exe_name.to_string(), mtime: 0, icon_base64: Some(icon_base64.to_string()) });
+    }
+
+    /// アプリの更新で exe のアイコンが変わったことを再現します。バックエンドは次のティックで気付きます。
+    pub fn update_app(&self, exe_name: &str, icon_base64: Option<&str>) {
+        let mut state = self.lock();
+        for session in state.sessions.iter_mut().filter(|s| s.exe_name.eq_ignore_ascii_case(exe_name)) {
+            session.icon_base64 = icon_base64.map(str::to_string);
+        }
+        *state.app_versions.entry(exe_name.to_uppercase()).or_default() += 1;
+    }
+
+    /// アプリがメディアコントロールに出す曲を変え、変化を通知します。`None` ならメディアコントロールから消えたことになります。
+    pub fn set_now_playing(&self, pid: u32, now_playing: Option<NowPlaying>) {
+        for session in self.lock().sessions.iter_mut().filter(|s| s.process_id == pid) {
+            session.now_playing = now_playing.clone();
+        }
+        self.notify(AudioNotification::NowPlayingChanged);
+    }
+
+    /// セッションの状態を変更し、状態変化を通知します。
+    pub fn state_change(&self, pid: u32, state: SessionState) {
+        let keys: Vec<String> = self.lock().sessions.iter_mut()
+            .filter(|s| s.process_id == pid)
+            .map(|s| {
+                s.state = state;
+                s.instance_id.clone()
+            })
+            .collect();
+        for session_key in keys {
+            self.notify(AudioNotification::StateChanged { session_key, state });
+        }
+    }
+
+    /// デバイス一覧を置き換え、デバイスの変更を通知します。
+    pub fn set_devices(&self, devices: Vec<AudioDeviceInfo>) {
+        self.lock().devices = devices;
+        self.notify(AudioNotification::DevicesChanged);
+    }
+
+    /// 入力デバイスの一覧を置き換え、デバイスの変更を通知します。
+    pub fn set_capture_devices(&self, devices: Vec<AudioDeviceInfo>) {
+        self.lock().capture_devices = devices;
+        self.notify(AudioNotification::DevicesChanged);
+    }
+
+    /// オーディオサービスの再起動を再現します。以後、バックエンドは作り直されるまで不調を報告します。
+    pub fn disconnect(&self) {
+        self.lock().lost = true;
+    }
+
+    /// セッションを移動せずに出力先だけを永続化します。再起動するまで反映されないアプリを再現します。
+    pub fn persist_route(&self, pid: u32, device_id: &str) {
+        self.lock().routes.insert(pid, device_id.to_string());
+        self.notify(AudioNotification::SessionCreated);
+    }
+
+    /// `force_route_migration` で促しても出力先を移さないアプリにします。
+    pub fn require_restart(&self, pid: u32) {
+        self.lock().restart_required.insert(pid);
+    }
+
+    /// デバイスが排他モードで受け付けるフォーマットを設定します。
+    pub fn set_device_formats(&self, device_id: &str, formats: Vec<DeviceFormat>) {
+        self.lock().formats.insert(device_id.to_string(), formats);
+    }
+
+    /// OS の音量ミキサーやキーボードの音量キーによるマスター音量の変更を再現します。
+    pub fn external_master_change(&self, volume: f32, muted: bool) {
+        let device_id = self.lock().default_output();
+        match device_id {
+            Ok(device_id) => self.external_device_change(&device_id, volume, muted),
+            Err(_) => self.notify(AudioNotification::MasterVolumeChanged { origin: ChangeOrigin::External }),
+        }
+    }
+
+    /// 既定かどうかを問わず、ほかのアプリやデバイスの音量ボタンによる出力デバイスの音量の変更を再現します。
+    pub fn external_device_change(&self, device_id: &str, volume: f32, muted: bool) {
+        let notifications = {
+            let mut state = self.lock();
+            state.endpoint_volumes.insert(device_id.to_string(), (volume, muted));
+            state.endpoint_notifications(device_id, ChangeOrigin::External)
+        };
+        for notification in notifications {
+            self.notify(notification);
+        }
+    }
+
+    pub fn set_peak(&self, pid: u32, peak: f32) {
+        self.lock().peaks.insert(pid, peak);
+    }
+
+    pub fn set_input_peak(&self, device_id: &str, peak: f32) {
+        self.lock().input_peaks.insert(device_id.to_string(), peak);
+    }
+
+    /// 録音を止めたときに返す音（マイクが拾ったことにする音）を設定します。
+    pub fn set_recorded(&self, recording: Recording) {
+        self.lock().recorded = recording;
+    }
+
+    /// 入力デバイスの音量とマイクブーストの初期値・範囲を設定します。
+    pub fn set_input_gain(&self, gain: InputGain) {
+        self.lock().input_gains.insert(gain.device_id.clone(), gain);
+    }
+
+    /// 入力デバイスのドライバーが公開している音声処理を設定します。
+    pub fn set_capture_effects(&self, device_id: &str, effects: Vec<CaptureEffect>) {
+        self.lock().capture_effects.insert(device_id.to_string(), effects);
+    }
+
+    /// 録音中のアプリを置き換えます。OS と同じく通知はしません。
+    /// プロセスが古い再生 API のモジュールを読み込んでいることにします。
+    pub fn load_legacy_audio(&self, pid: u32, exe_name: &str, api: LegacyAudioApi) {
+        self.lock().legacy_audio.insert(pid, (exe_name.to_string(), api));
+    }
+
+    pub fn set_microphone_users(&self, users: Vec<MicrophoneUser>) {
+        self.lock().microphone_users = users;
+    }
+
+    /// OS 側のセッションの現在の状態
+    pub fn session(&self, pid: u32) -> Option<AudioSessionInfo> {
+        self.lock().sessions.iter().find(|s| s.process_id == pid).cloned()
+    }
+
+    /// これまでの書き込み操作
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.lock().calls.clone()
+    }
+
+    /// セッションを再列挙した回数
+    pub fn scan_count(&self) -> usize {
+        self.lock().scans
+    }
+
+    /// ジェネレーターの再生に合わせて、このプロセスの「Tone Generator」セッションを出力先ごとに置き換えます。
+    fn replace_generator_sessions(&self, device_ids: &[String]) {
+        let pid = std::process::id();
+        let removed: Vec<String> = {
+            let mut state = self.lock();
+            let (generator, others) = std::mem::take(&mut state.sessions)
+                .into_iter()
+                .partition(|s| s.process_id == pid && s.exe_name == GENERATOR_SESSION_NAME);
+            state.sessions = others;
+            for device_id in device_ids {
+                let mut session = Self::session_info(pid, GENERATOR_SESSION_NAME, device_id);
+                session.instance_id = format!("{}-{:032x}", session.instance_id, GENERATOR_SESSION_GUID);
+                state.sessions.push(session);
+            }
+            generator.into_iter().map(|s: AudioSessionInfo| s.instance_id).collect()
+        };
+        for session_key in removed {
+            self.notify(AudioNotification::SessionDisconnected { session_key });
+        }
+        if !device_ids.is_empty() {
+            self.notify(AudioNotification::SessionCreated);
+        }
+    }
+
+    fn notify(&self, notification: AudioNotification) {
+        let notifier = self.lock().notifier.clone();
+        if let Some(notifier) = notifier {
+            notifier.notify(notification);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// [`MockAudioHandle`] の状態を OS のオーディオとして扱うバックエンド。
+/// キャッシュと通知の扱いは WASAPI バックエンドと同じです。
+pub struct MockAudioBackend {
+    handle: MockAudioHandle,
+    sink: Arc<dyn MixerEventSink>,
+    apps: AppPresentation,
+    session_cache: Vec<AudioSessionInfo>,
+    cache_dirty: bool,
+    sessions_changed: bool,
+    publisher: SessionPublisher,
+    /// 最後に確かめたアプリの更新回数
+    app_versions: HashMap<String, u32>,
+}
+
+impl MockAudioBackend {
+    fn invalidate_sessions(&mut self) {
+        self.session_cache.clear();
+        self.cache_dirty = true;
+    }
+
+    fn rescan_sessions(&mut self) {
+        let (sessions, routes) = {
+            let mut state = self.handle.lock();
+            state.scans += 1;
+            (state.sessions.clone(), state.routes.clone())
+        };
+        self.session_cache = sessions.into_iter()
+            .filter_map(|mut session| {
+                let exe_key = session.exe_name.to_uppercase();
+                if self.apps.is_hidden(&exe_key) {
+                    return None;
+                }
+                session.process_name = self.apps.display_name(&exe_key, &session.exe_name);
+                let icon_base64 = session.icon_base64.take().or_else(|| self.apps.override_icon(&exe_key));
+                session.set_icon(icon_base64);
+                session.persisted_device_id = routes.get(&session.process_id).cloned();
+                session.route_pending = session.persisted_device_id.as_ref().is_some_and(|d| *d != session.device_id);
+                Some(session)
+            })
+            .collect();
+        self.cache_dirty = false;
+        self.sessions_changed = true;
+    }
+
+    fn refresh_if_needed(&mut self) {
+        if self.cache_dirty {
+            self.rescan_sessions();
+        }
+    }
+
+    fn apply_to_session<F>(&mut self, pid: u32, call: MockCall, action: F) -> Result<(), AudioError>
+    where
+        F: Fn(&mut AudioSessionInfo),
+    {
+        self.apply_where(|s| s.process_id == pid, || AudioError::SessionNotFound(pid), call, action)
+    }
+
+    fn apply_to_instance<F>(&mut self, instance_id: &str, call: MockCall, action: F) -> Result<(), AudioError>
+    where
+        F: Fn(&mut AudioSessionInfo),
+    {
+        self.apply_where(|s| s.instance_id == instance_id, || AudioError::InstanceNotFound(instance_id.to_string()), call, action)
+    }
+
+    fn apply_where<M, F>(&mut self, matches: M, not_found: impl Fn() -> AudioError, call: MockCall, action: F) -> Result<(), AudioError>
+    where
+        M: Fn(&AudioSessionInfo) -> bool,
+        F: Fn(&mut AudioSessionInfo),
+    {
+        self.refresh_if_needed();
+        if !self.session_cache.iter().any(&matches) {
+            self.rescan_sessions();
+        }
+        if !self.session_cache.iter().any(&matches) {
+            return Err(not_found());
+        }
+        let notifications: Vec<AudioNotification> = {
+            let mut state = self.handle.lock();
+            state.calls.push(call);
+            state.sessions.iter_mut()
+                .filter(|s| matches(s))
+                .map(|s| {
+                    action(s);
+                    AudioNotification::VolumeChanged {
+                        session_key: s.instance_id.clone(),
+                        volume: s.volume,
+                        muted: s.is_muted,
+                        origin: ChangeOrigin::Mixer,
+                    }
+                })
+                .collect()
+        };
+        // 実機と同じく、自分による変更もコールバックで返ってくる
+        for notification in notifications {
+            self.handle.notify(notification);
+        }
+        Ok(())
+    }
+}
+
+impl AudioBackend for MockAudioBackend {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn clear_caches(&mut self) {
+        self.invalidate_sessions();
+    }
+
+    fn set_hidden_apps(&mut self, names: Vec<String>) {
+        self.apps.set_hidden_apps(names);
+        self.invalidate_sessions();
+    }
+
+    fn set_app_overrides(&mut self, overrides: HashMap<String, AppOverride>) {
+        self.apps.set_app_overrides(overrides);
+        self.invalidate_sessions();
+    }
+
+    fn get_sessions(&mut self) -> Result<Vec<AudioSessionInfo>, AudioError> {
+        self.refresh_if_needed();
+        let peaks = self.handle.lock().peaks.clone();
+        let mut sessions = self.session_cache.clone();
+        for session in &mut sessions {
+            session.peak_level = peaks.get(&session.process_id).copied().unwrap_or(0.0);
+        }
+        Ok(sessions)
+    }
+
+    fn get_session_icon(&mut self, pid: u32) -> Result<Option<String>, AudioError> {
+        self.refresh_if_needed();
+        let session = self.session_cache.iter().find(|s| s.process_id == pid).ok_or(AudioError::SessionNotFound(pid))?;
+        Ok(session.icon_base64.clone())
+    }
+
+    fn take_sessions_diff(&mut self) -> Result<Option<SessionsDiff>, AudioError> {
+        self.refresh_if_needed();
+        if !self.sessions_changed {
+            return Ok(None);
+        }
+        self.sessions_changed = false;
+        Ok(self.publisher.publish(&self.session_cache))
+    }
+
+    fn get_peak_levels(&mut self) -> Result<Vec<PeakLevel>, AudioError> {
+        let peaks = self.handle.lock().peaks.clone();
+        Ok(self.session_cache.iter()
+            .map(|s| PeakLevel { pid: s.process_id, peak: peaks.get(&s.process_id).copied().unwrap_or(0.0) })
+            .collect())
+    }
+
+    fn set_session_volume(&mut self, pid: u32, volume: f32) -> Result<(), AudioError> {
+        self.apply_to_session(pid, MockCall::SetVolume { pid, volume }, |s| s.volume = volume)
+    }
+
+    fn set_session_mute(&mut self, pid: u32, mute: bool) -> Result<(), AudioError> {
+        self.apply_to_session(pid, MockCall::SetMute { pid, mute }, |s| s.is_muted = mute)
+    }
+
+    fn set_instance_volume(&mut self, instance_id: &str, volume: f32) -> Result<(), AudioError> {
+        let call = MockCall::SetInstanceVolume { instance_id: instance_id.to_string(), volume };
+        self.apply_to_instance(instance_id, call, |s| s.volume = volume)
+    }
+
+    fn set_instance_mute(&mut self, instance_id: &str, mute: bool) -> Result<(), AudioError> {
+        let call = MockCall::SetInstanceMute { instance_id: instance_id.to_string(), mute };
+        self.apply_to_instance(instance_id, call, |s| s.is_muted = mute)
+    }
+
+    fn get_audio_devices(&mut self) -> Result<Vec<AudioDeviceInfo>, AudioError> {
+        Ok(self.handle.lock().devices.clone())
+    }
+
+    fn set_audio_routing(&mut self, pid: u32, device_id: &str) -> Result<(), AudioError> {
+        if !self.handle.lock().devices.iter().any(|d| d.id == device_id) {
+            return Err(AudioError::Backend(format!("Unknown device {}", device_id)));
+        }
+        let device = device_id.to_string();
+        self.apply_to_session(pid, MockCall::SetRouting { pid, device_id: device.clone() }, |s| {
+            s.device_id = device.clone();
+            // 実機では出力先が変わると別のセッションになる
+            s.instance_id = session_key(s.process_id, &device);
+        })?;
+        self.handle.lock().routes.insert(pid, device);
+        self.invalidate_sessions();
+        Ok(())
+    }
+
+    fn clear_audio_routing(&mut self, pid: u32) -> Result<(), AudioError> {
+        let device = self.handle.lock().default_output()?;
+        self.apply_to_session(pid, MockCall::ClearRouting(pid), |s| {
+            s.device_id = device.clone();
+            s.instance_id = session_key(s.process_id, &device);
+        })?;
+        self.handle.lock().routes.remove(&pid);
+        self.invalidate_sessions();
+        Ok(())
+    }
+
+    /// 再起動が必要なアプリでなければ、永続化した出力先へセッションを移します。
+    fn force_route_migration(&mut self, pid: u32) -> Result<(), AudioError> {
+        let (route, restart_required) = {
+            let state = self.handle.lock();
+            (state.routes.get(&pid).cloned(), state.restart_required.contains(&pid))
+        };
+        let route = route.ok_or_else(|| AudioError::Backend(format!("No output device is set for PID {}", pid)))?;
+        self.apply_to_session(pid, MockCall::ForceRouteMigration(pid), |s| {
+            if !restart_required {
+                s.device_id = route.clone();
+                s.instance_id = session_key(s.process_id, &route);
+            }
+        })?;
+        self.invalidate_sessions();
+        Ok(())
+    }
+
+    fn set_default_device(&mut self, device_id: &str) -> Result<(), AudioError> {
+        {
+            let mut state = self.handle.lock();
+            if !state.devices.iter().any(|d| d.id == device_id) {
+                return Err(AudioError::Backend(format!("Unknown device {}", device_id)));
+            }
+            for device in &mut state.devices {
+                device.is_default = device.id == device_id;
+            }
+            state.calls.push(MockCall::SetDefaultDevice { device_id: device_id.to_string() });
+        }
+        self.handle_notification(AudioNotification::DevicesChanged);
+        Ok(())
+    }
+
+    fn get_capture_devices(&mut self) -> Result<Vec<AudioDeviceInfo>, AudioError> {
+        Ok(self.handle.lock().capture_devices.clone())
+    }
+
+    /// モックは役割を区別せず、どの役割でも同じデバイスを既定にします。
+    fn set_default_capture_device(&mut self, device_id: &str, role: Option<DeviceRole>) -> Result<(), AudioError> {
+        {
+            let mut state = self.handle.lock();
+            if !state.capture_devices.iter().any(|d| d.id == device_id) {
+                return Err(AudioError::Backend(format!("Unknown device {}", device_id)));
+            }
+            for device in &mut state.capture_devices {
+                device.is_default = device.id == device_id;
+            }
+            state.calls.push(MockCall::SetDefaultCaptureDevice { device_id: device_id.to_string(), role });
+        }
+        self.handle_notification(AudioNotification::DevicesChanged);
+        Ok(())
+    }
+
+    fn send_media_command(&mut self, pid: u32, command: MediaCommand) -> Result<(), AudioError> {
+        {
+            let mut state = self.handle.lock();
+            let mut sessions = state.sessions.iter_mut().filter(|s| s.process_id == pid).peekable();
+            if sessions.peek().is_none() {
+                return Err(AudioError::SessionNotFound(pid));
+            }
+            let mut found = false;
+            for now_playing in sessions.filter_map(|s| s.now_playing.as_mut()) {
+                found = true;
+                now_playing.status = match (command, now_playing.status) {
+                    (MediaCommand::Play, _) | (MediaCommand::TogglePlayPause, PlaybackStatus::Paused | PlaybackStatus::Stopped) => PlaybackStatus::Playing,
+                    (MediaCommand::Pause, _) | (MediaCommand::TogglePlayPause, PlaybackStatus::Playing) => PlaybackStatus::Paused,
+                    (_, status) => status,
+                };
+            }
+            if !found {
+                return Err(AudioError::Backend(format!("No media session for PID {}", pid)));
+            }
+            state.calls.push(MockCall::SendMediaCommand { pid, command });
+        }
+        self.handle.notify(AudioNotification::NowPlayingChanged);
+        Ok(())
+    }
+
+    fn set_input_monitor(&mut self, capture_device_id: &str, output_device_id: Option<&str>) -> Result<(), AudioError> {
+        let mut state = self.handle.lock();
+        if !state.capture_devices.iter().any(|d| d.id == capture_device_id) {
+            return Err(AudioError::Backend(format!("Unknown capture device {}", capture_device_id)));
+        }
+        if let Some(output_device_id) = output_device_id.filter(|id| !state.devices.iter().any(|d| d.id == *id)) {
+            return Err(AudioError::Backend(format!("Unknown device {}", output_device_id)));
+        }
+        state.calls.push(MockCall::SetInputMonitor {
+            capture_device_id: capture_device_id.to_string(),
+            output_device_id: output_device_id.map(str::to_string),
+        });
+        Ok(())
+    }
+
+    fn start_input_meter(&mut self, device_id: Option<&str>) -> Result<(), AudioError> {
+        let mut state = self.handle.lock();
+        let known = match device_id {
+            Some(id) => state.capture_devices.iter().any(|d| d.id == id),
+            None => state.capture_devices.iter().any(|d| d.is_default),
+        };
+        if !known {
+            return Err(AudioError::Backend(format!("Unknown capture device {}", device_id.unwrap_or("<default>"))));
+        }
+        state.input_meter = Some(device_id.map(str::to_string));
+        state.calls.push(MockCall::StartInputMeter { device_id: device_id.map(str::to_string) });
+        Ok(())
+    }
+
+    fn start_recording(&mut self, device_id: Option<&str>, max_ms: u32) -> Result<(), AudioError> {
+        let mut state = self.handle.lock();
+        let known = match device_id {
+            Some(id) => state.capture_devices.iter().any(|d| d.id == id),
+            None => state.capture_devices.iter().any(|d| d.is_default),
+        };
+        if !known {
+            return Err(AudioError::Backend(format!("Unknown capture device {}", device_id.unwrap_or("<default>"))));
+        }
+        state.recording = Some(device_id.map(str::to_string));
+        state.calls.push(MockCall::StartRecording { device_id: device_id.map(str::to_string), max_ms });
+        Ok(())
+    }
+
+    fn stop_recording(&mut self) -> Result<Recording, AudioError> {
+        let mut state = self.handle.lock();
+        if state.recording.take().is_none() {
+            return Err(AudioError::Backend("Not recording".to_string()));
+        }
+        state.calls.push(MockCall::StopRecording);
+        Ok(state.recorded.clone())
+    }
+
+    fn stop_input_meter(&mut self) {
+        let mut state = self.handle.lock();
+        if state.input_meter.take().is_some() {
+            state.calls.push(MockCall::StopInputMeter);
+        }
+    }
+
+    fn get_input_peak(&mut self) -> Option<InputPeak> {
+        let state = self.handle.lock();
+        let device_id = match state.input_meter.as_ref()? {
+            Some(id) => id.clone(),
+            None => state.capture_devices.iter().find(|d| d.is_default)?.id.clone(),
+        };
+        let peak = state.input_peaks.get(&device_id).copied().unwrap_or(0.0);
+        Some(InputPeak { device_id, peak })
+    }
+
+    fn get_input_gain(&mut self, device_id: &str) -> Result<InputGain, AudioError> {
+        self.handle.lock().input_gains.get(device_id).cloned()
+            .ok_or_else(|| AudioError::Backend(format!("Unknown capture device {}", device_id)))
+    }
+
+    fn set_input_gain(&mut self, device_id: &str, db: f32) -> Result<(), AudioError> {
+        let mut state = self.handle.lock();
+        let gain = state.input_gains.get_mut(device_id)
+            .ok_or_else(|| AudioError::Backend(format!("Unknown capture device {}", device_id)))?;
+        gain.volume.db = db.clamp(gain.volume.min_db, gain.volume.max_db);
+        let db = gain.volume.db;
+        state.calls.push(MockCall::SetInputGain { device_id: device_id.to_string(), db });
+        Ok(())
+    }
+
+    fn set_input_boost(&mut self, device_id: &str, db: f32) -> Result<(), AudioError> {
+        let mut state = self.handle.lock();
+        let boost = state.input_gains.get_mut(device_id)
+            .and_then(|g| g.boost.as_mut())
+            .ok_or_else(|| AudioError::Backend("This device has no microphone boost".to_string()))?;
+        boost.db = db.clamp(boost.min_db, boost.max_db);
+        let db = boost.db;
+        state.calls.push(MockCall::SetInputBoost { device_id: device_id.to_string(), db });
+        Ok(())
+    }
+
+    fn get_capture_effects(&mut self, device_id: &str) -> Result<Vec<CaptureEffect>, AudioError> {
+        Ok(self.handle.lock().capture_effects.get(device_id).cloned().unwrap_or_default())
+    }
+
+    fn set_capture_effect(&mut self, device_id: &str, kind: CaptureEffectKind, enabled: bool) -> Result<(), AudioError> {
+        let mut state = self.handle.lock();
+        let effect = state.capture_effects.get_mut(device_id)
+            .and_then(|effects| effects.iter_mut().find(|e| e.kind == kind))
+            .ok_or_else(|| AudioError::Backend(format!("{:?} is not available on {}", kind, device_id)))?;
+        if !effect.can_toggle {
+            return Err(AudioError::Backend(format!("The driver does not allow switching {:?}", kind)));
+        }
+        effect.enabled = enabled;
+        state.calls.push(MockCall::SetCaptureEffect { device_id: device_id.to_string(), kind, enabled });
+        Ok(())
+    }
+
+    /// 読み込んでいるプロセスのうち、セッションを持たないものを返します。
+    fn get_legacy_streams(&mut self) -> Result<Vec<LegacyStream>, AudioError> {
+        let state = self.handle.lock();
+        let system_sounds_active = state.sessions.iter().any(|s| s.process_id == 0 && s.state == SessionState::Active);
+        let mut streams: Vec<LegacyStream> = state.legacy_audio.iter()
+            .filter(|(pid, _)| !state.sessions.iter().any(|s| s.process_id == **pid))
+            .map(|(pid, (exe_name, api))| LegacyStream { pid: *pid, exe_name: exe_name.clone(), api: *api, system_sounds_active })
+            .collect();
+        streams.sort_by_key(|s| s.pid);
+        Ok(streams)
+    }
+
+    fn get_microphone_users(&mut self) -> Result<Vec<MicrophoneUser>, AudioError> {
+        Ok(self.handle.lock().microphone_users.clone())
+    }
+
+    fn set_bluetooth_profile(&mut self, device_id: &str, profile: BluetoothProfile) -> Result<(), AudioError> {
+        {
+            let mut state = self.handle.lock();
+            let bluetooth = state.devices.iter_mut()
+                .find(|d| d.id == device_id)
+                .and_then(|d| d.bluetooth.as_mut())
+                .ok_or_else(|| AudioError::Backend(format!("Not a Bluetooth device: {}", device_id)))?;
+            bluetooth.profile = Some(profile);
+            state.calls.push(MockCall::SetBluetoothProfile { device_id: device_id.to_string(), profile });
+        }
+        self.handle_notification(AudioNotification::DevicesChanged);
+        Ok(())
+    }
+
+    fn probe_device_formats(&mut self, device_id: &str) -> Result<Vec<DeviceFormat>, AudioError> {
+        let state = self.handle.lock();
+        if !state.devices.iter().any(|d| d.id == device_id) {
+            return Err(AudioError::Backend(format!("Unknown device {}", device_id)));
+        }
+        Ok(state.formats.get(device_id).cloned().unwrap_or_default())
+    }
+
+    /// 排他モードでは、[`MockAudioHandle::set_device_formats`] で設定したフォーマットだけを受け付けます。
+    fn start_playback(&mut self, request: PlaybackRequest) -> Result<(), AudioError> {
+        request.validate()?;
+        let mut state = self.handle.lock();
+        let device_id = match &request.device_id {
+            Some(id) => id.clone(),
+            None => state.devices.iter().find(|d| d.is_default).map(|d| d.id.clone()).unwrap_or_default(),
+        };
+        if !state.devices.iter().any(|d| d.id == device_id) {
+            return Err(AudioError::Backend(format!("Unknown device {}", device_id)));
+        }
+        if let (true, Some(format)) = (request.output.exclusive, &request.output.format) {
+            if !state.formats.get(&device_id).is_some_and(|f| f.contains(format)) {
+                return Err(AudioError::Backend(format!("{} does not accept {:?} in exclusive mode", device_id, format)));
+            }
+        }
+        state.synced_playback = None;
+        state.playback = Some(request.clone());
+        state.calls.push(MockCall::StartPlayback(request));
+        drop(state);
+        self.handle.replace_generator_sessions(&[device_id]);
+        Ok(())
+    }
+
+    fn start_synced_playback(&mut self, request: SyncedPlaybackRequest) -> Result<(), AudioError> {
+        request.validate()?;
+        let mut state = self.handle.lock();
+        if let Some(output) = request.outputs.iter().find(|o| !state.devices.iter().any(|d| d.id == o.device_id)) {
+            return Err(AudioError::Backend(format!("Unknown device {}", output.device_id)));
+        }
+        state.playback = None;
+        state.synced_playback = Some(request.clone());
+        state.calls.push(MockCall::StartSyncedPlayback(request.clone()));
+        drop(state);
+        let device_ids: Vec<String> = request.outputs.into_iter().map(|o| o.device_id).collect();
+        self.handle.replace_generator_sessions(&device_ids);
+        Ok(())
+    }
+
+    fn set_playback_delay(&mut self, device_id: &str, delay_ms: u32) -> Result<(), AudioError> {
+        generator::validate_delay(delay_ms)?;
+        let mut state = self.handle.lock();
+        let output = state.synced_playback.as_mut()
+            .and_then(|p| p.outputs.iter_mut().find(|o| o.device_id == device_id))
+            .ok_or_else(|| AudioError::Backend(format!("Nothing is playing on {}", device_id)))?;
+        output.delay_ms = delay_ms;
+        state.calls.push(MockCall::SetPlaybackDelay { device_id: device_id.to_string(), delay_ms });
+        Ok(())
+    }
+
+    fn stop_playback(&mut self) {
+        let mut state = self.handle.lock();
+        state.playback = None;
+        state.synced_playback = None;
+        state.calls.push(MockCall::StopPlayback);
+        drop(state);
+        self.handle.replace_generator_sessions(&[]);
+    }
+
+    fn set_metronome_tempo(&mut self, bpm: f32) -> Result<(), AudioError> {
+        generator::validate_bpm(bpm)?;
+        let mut state = self.handle.lock();
+        let signal = match &mut state.synced_playback {
+            Some(synced) => Some(&mut synced.signal),
+            None => state.playback.as_mut().map(|p| &mut p.signal),
+        };
+        match signal {
+            Some(Signal::Metronome(metronome)) => metronome.bpm = bpm,
+            _ => return Err(AudioError::Backend("The metronome is not running".to_string())),
+        }
+        state.calls.push(MockCall::SetMetronomeTempo(bpm));
+        Ok(())
+    }
+
+    fn seek_playback(&mut self, position_ms: u64) -> Result<(), AudioError> {
+        let mut state = self.handle.lock();
+        let signal = match &state.synced_playback {
+            Some(synced) => Some(&synced.signal),
+            None => state.playback.as_ref().map(|p| &p.signal),
+        };
+        if !matches!(signal, Some(Signal::File { .. })) {
+            return Err(AudioError::Backend("No audio file is playing".to_string()));
+        }
+        state.calls.push(MockCall::SeekPlayback(position_ms));
+        Ok(())
+    }
+
+    fn start_file_capture(&mut self, request: FileCaptureRequest) -> Result<(), AudioError> {
+        request.validate()?;
+        let mut state = self.handle.lock();
+        state.check_capture_source(&request.source)?;
+        state.file_capture = Some(request.clone());
+        state.calls.push(MockCall::StartFileCapture(request));
+        Ok(())
+    }
+
+    fn stop_file_capture(&mut self) -> Result<Vec<String>, AudioError> {
+        {
+            let mut state = self.handle.lock();
+            if state.file_capture.take().is_none() {
+                return Err(AudioError::Backend("Not recording to a file".to_string()));
+            }
+            state.calls.push(MockCall::StopFileCapture);
+        }
+        // 音は取り込まないので、ファイルは作らない
+        self.sink.emit(MixerEvent::FileCaptureStopped { files: Vec::new(), error: None });
+        Ok(Vec::new())
+    }
+
+    /// ポートは開かず、頼まれたポートで配信しているものとして扱います。
+    fn start_network_stream(&mut self, request: NetworkStreamRequest) -> Result<NetworkStream, AudioError> {
+        request.validate()?;
+        let mut state = self.handle.lock();
+        state.check_capture_source(&request.source)?;
+        state.network_stream = Some(request.clone());
+        state.calls.push(MockCall::StartNetworkStream(request));
+        Ok(state.network_stream_info().expect("just started"))
+    }
+
+    fn stop_network_stream(&mut self) {
+        let stopped = {
+            let mut state = self.handle.lock();
+            state.calls.push(MockCall::StopNetworkStream);
+            state.network_stream.take().is_some()
+        };
+        if stopped {
+            self.sink.emit(MixerEvent::NetworkStreamStopped { error: None });
+        }
+    }
+
+    fn get_network_stream(&mut self) -> Option<NetworkStream> {
+        self.handle.lock().network_stream_info()
+    }
+
+    fn start_mirror(&mut self, request: MirrorRequest) -> Result<(), AudioError> {
+        request.validate()?;
+        let mut state = self.handle.lock();
+        if !state.sessions.iter().any(|s| s.process_id == request.pid) {
+            return Err(AudioError::SessionNotFound(request.pid));
+        }
+        if !state.devices.iter().any(|d| d.id == request.device_id) {
+            return Err(AudioError::Backend(format!("Unknown device {}", request.device_id)));
+        }
+        state.mirrors.insert(request.pid, request.clone());
+        state.calls.push(MockCall::StartMirror(request));
+        Ok(())
+    }
+
+    fn set_mirror_delay(&mut self, pid: u32, delay_ms: u32) -> Result<(), AudioError> {
+        let mut state = self.handle.lock();
+        let mirror = state.mirrors.get_mut(&pid)
+            .ok_or_else(|| AudioError::Backend(format!("No mirror is running for PID {}", pid)))?;
+        MirrorRequest { delay_ms, ..mirror.clone() }.validate()?;
+        mirror.delay_ms = delay_ms;
+        state.calls.push(MockCall::SetMirrorDelay { pid, delay_ms });
+        Ok(())
+    }
+
+    fn stop_mirror(&mut self, pid: u32) {
+        let stopped = {
+            let mut state = self.handle.lock();
+            state.calls.push(MockCall::StopMirror(pid));
+            state.mirrors.remove(&pid).is_some()
+        };
+        if stopped {
+            self.sink.emit(MixerEvent::MirrorStopped { pid, error: None });
+        }
+    }
+
+    fn get_mirrors(&mut self) -> Vec<MirrorRequest> {
+        self.handle.lock().mirrors.values().cloned().collect()
+    }
+
+    fn start_boost(&mut self, request: BoostRequest) -> Result<(), AudioError> {
+        request.validate()?;
+        let mut state = self.handle.lock();
+        if !state.sessions.iter().any(|s| s.process_id == request.pid) {
+            return Err(AudioError::SessionNotFound(request.pid));
+        }
+        state.boosts.insert(request.pid, request.clone());
+        state.calls.push(MockCall::StartBoost(request));
+        Ok(())
+    }
+
+    fn set_boost_gain(&mut self, pid: u32, gain_db: f32) -> Result<(), AudioError> {
+        let mut state = self.handle.lock();
+        let boost = state.boosts.get_mut(&pid)
+            .ok_or_else(|| AudioError::Backend(format!("No boost is running for PID {}", pid)))?;
+        BoostRequest { pid, gain_db }.validate()?;
+        boost.gain_db = gain_db;
+        state.calls.push(MockCall::SetBoostGain { pid, gain_db });
+        Ok(())
+    }
+
+    fn stop_boost(&mut self, pid: u32) {
+        let stopped = {
+            let mut state = self.handle.lock();
+            state.calls.push(MockCall::StopBoost(pid));
+            state.boosts.remove(&pid).is_some()
+        };
+        if stopped {
+            self.sink.emit(MixerEvent::BoostStopped { pid, error: None });
+        }
+    }
+
+    fn get_boosts(&mut self) -> Vec<BoostRequest> {
+        self.handle.lock().boosts.values().cloned().collect()
+    }
+
+    fn get_master_volume(&mut self) -> Result<MasterVolume, AudioError> {
+        let device_id = self.handle.lock().default_output()?;
+        self.get_device_volume(&device_id)
+    }
+
+    fn set_master_volume(&mut self, volume: f32) -> Result<(), AudioError> {
+        let notifications = {
+            let mut state = self.handle.lock();
+            let device_id = state.default_output()?;
+            state.endpoint_volume(&device_id)?.0 = volume.clamp(0.0, 1.0);
+            state.calls.push(MockCall::SetMasterVolume(volume));
+            state.endpoint_notifications(&device_id, ChangeOrigin::Mixer)
+        };
+        for notification in notifications {
+            self.handle_notification(notification);
+        }
+        Ok(())
+    }
+
+    fn set_master_volume_db(&mut self, db: f32) -> Result<(), AudioError> {
+        let (min_db, max_db, _) = VOLUME_RANGE;
+        let notifications = {
+            let mut state = self.handle.lock();
+            let device_id = state.default_output()?;
+            state.endpoint_volume(&device_id)?.0 = (db.clamp(min_db, max_db) - min_db) / (max_db - min_db);
+            state.calls.push(MockCall::SetMasterVolumeDb(db));
+            state.endpoint_notifications(&device_id, ChangeOrigin::Mixer)
+        };
+        for notification in notifications {
+            self.handle_notification(notification);
+        }
+        Ok(())
+    }
+
+    fn set_master_mute(&mut self, mute: bool) -> Result<(), AudioError> {
+        let notifications = {
+            let mut state = self.handle.lock();
+            let device_id = state.default_output()?;
+            state.endpoint_volume(&device_id)?.1 = mute;
+            state.calls.push(MockCall::SetMasterMute(mute));
+            state.endpoint_notifications(&device_id, ChangeOrigin::Mixer)
+        };
+        for notification in notifications {
+            self.handle_notification(notification);
+        }
+        Ok(())
+    }
+
+    fn get_device_volume(&mut self, device_id: &str) -> Result<MasterVolume, AudioError> {
+        let (volume, is_muted) = *self.handle.lock().endpoint_volume(device_id)?;
+        Ok(MasterVolume { device_id: device_id.to_string(), volume, is_muted, level: Some(volume_level(volume)) })
+    }
+
+    fn set_device_volume(&mut self, device_id: &str, volume: f32) -> Result<(), AudioError> {
+        let notifications = {
+            let mut state = self.handle.lock();
+            state.endpoint_volume(device_id)?.0 = volume.clamp(0.0, 1.0);
+            state.calls.push(MockCall::SetDeviceVolume { device_id: device_id.to_string(), volume });
+            state.endpoint_notifications(device_id, ChangeOrigin::Mixer)
+        };
+        for notification in notifications {
+            self.handle_notification(notification);
+        }
+        Ok(())
+    }
+
+    fn set_device_mute(&mut self, device_id: &str, mute: bool) -> Result<(), AudioError> {
+        let notifications = {
+            let mut state = self.handle.lock();
+            state.endpoint_volume(device_id)?.1 = mute;
+            state.calls.push(MockCall::SetDeviceMute { device_id: device_id.to_string(), mute });
+            state.endpoint_notifications(device_id, ChangeOrigin::Mixer)
+        };
+        for notification in notifications {
+            self.handle_notification(notification);
+        }
+        Ok(())
+    }
+
+    fn watch_device_volumes(&mut self, device_ids: Vec<String>) -> Result<(), AudioError> {
+        self.handle.lock().watched_devices = device_ids;
+        Ok(())
+    }
+
+    fn handle_notification(&mut self, notification: AudioNotification) {
+        match notification {
+            AudioNotification::VolumeChanged { session_key, volume, muted, origin } => {
+                let updated = self.session_cache.iter_mut()
+                    .find(|s| s.instance_id == session_key)
+                    .map(|session| {
+                        session.volume = volume;
+                        session.is_muted = muted;
+                        session.process_id
+                    });
+                if let Some(pid) = updated {
+                    self.sessions_changed = true;
+                    self.sink.emit(MixerEvent::VolumeChanged { pid, volume, muted, origin });
+                }
+            }
+            AudioNotification::StateChanged { session_key, state } => {
+                let pid = self.session_cache.iter_mut()
+                    .find(|s| s.instance_id == session_key)
+                    .map(|s| {
+                        s.state = state;
+                        s.process_id
+                    })
+                    .unwrap_or(0);
+                if state == SessionState::Expired {
+                    self.session_cache.retain(|s| s.instance_id != session_key);
+                }
+                self.sessions_changed = true;
+                self.sink.emit(MixerEvent::SessionStateChanged { pid, state: format!("{:?}", state) });
+            }
+            AudioNotification::SessionDisconnected { session_key } => {
+                self.session_cache.retain(|s| s.instance_id != session_key);
+                self.sessions_changed = true;
+            }
+            AudioNotification::SessionCreated | AudioNotification::NowPlayingChanged => {
+                self.cache_dirty = true;
+            }
+            AudioNotification::IconLoaded { exe_path, icon_base64: Some(icon), .. } => {
+                for session in self.session_cache.iter_mut().filter(|s| s.icon_base64.is_none() && s.exe_name.eq_ignore_ascii_case(&exe_path)) {
+                    session.set_icon(Some(icon.clone()));
+                    self.sessions_changed = true;
+                    let accent_color = session.accent_color.clone();
+                    self.sink.emit(MixerEvent::SessionIconReady { pid: session.process_id, icon_base64: icon.clone(), accent_color });
+                }
+            }
+            AudioNotification::IconLoaded { .. } => {}
+            AudioNotification::DevicesChanged => {
+                self.cache_dirty = true;
+                self.sink.emit(MixerEvent::DevicesChanged);
+            }
+            AudioNotification::MasterVolumeChanged { origin } => {
+                if let Ok(master) = self.get_master_volume() {
+                    self.sink.emit(MixerEvent::MasterVolumeChanged(master, origin));
+                }
+            }
+            AudioNotification::DeviceVolumeChanged { device_id, origin } => {
+                if let Ok(volume) = self.get_device_volume(&device_id) {
+                    self.sink.emit(MixerEvent::DeviceVolumeChanged(volume, origin));
+                }
+            }
+        }
+    }
+    fn check_app_updates(&mut self) {
+        let app_versions = self.handle.lock().app_versions.clone();
+        if app_versions != self.app_versions {
+            self.app_versions = app_versions;
+            self.cache_dirty = true;
+        }
+    }
+
+    fn check_health(&mut self) -> bool {
+        !self.handle.lock().lost
+    }
+}
@@ -0,0 +1,38 @@
+/// セッションの状態。各プラットフォームの状態をこの 3 つに揃えます。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum SessionState {
+    Active,
+    Inactive,
+    Expired,
+}
+
+/// 音量・ミュートを変えたのが誰か
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum ChangeOrigin {
+    /// このミキサー（を通したユーザーの操作）
+    Mixer,
+    /// アプリ自身、OS の音量ミキサー、音量キーなど、ほかのプロセス
+    External,
+    /// バックエンドが区別できない
+    Unknown,
+}
+
+/// OS のコールバックからオーディオスレッドへ送られる通知。`session_key` は `AudioSessionInfo::instance_id` です。
+#[derive(Debug, Clone)]
+pub enum AudioNotification {
+    SessionCreated,
+    VolumeChanged { session_key: String, volume: f32, muted: bool, origin: ChangeOrigin },
+    StateChanged { session_key: String, state: SessionState },
+    SessionDisconnected { session_key: String },
+    DevicesChanged,
+    /// 既定の出力デバイスの音量・ミュートが変化した
+    MasterVolumeChanged { origin: ChangeOrigin },
+    /// 監視している出力デバイスの音量・ミュートが変化した
+    DeviceVolumeChanged { device_id: String, origin: ChangeOrigin },
+    /// 別スレッドで exe のアイコンを抽出し終えた。`mtime` は抽出したときの exe の更新日時
+    IconLoaded { exe_path: String, mtime: u64, icon_base64: Option<String> },
+    /// アプリがメディアコントロールに出している曲や再生状態が変わった
+    NowPlayingChanged,
+}
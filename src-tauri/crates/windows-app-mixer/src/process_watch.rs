@@ -0,0 +1,138 @@
+//! プロセスの起動と終了の監視。WMI のイベントは WMI サービスへの接続が重く、環境によっては遅れて届くので、
+//! プロセスの一覧を定期的に取って前回との差分を [`MixerEvent::ProcessStarted`]・[`MixerEvent::ProcessExited`] として通知します。
+//! 変化があった直後は短い間隔で、何も起きなければ間隔を延ばしていきます。
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::{MixerEvent, MixerEventSink};
+
+/// 変化があった直後の間隔。起動したアプリが音を出し始める前に気付けるよう短くする
+pub const MIN_INTERVAL: Duration = Duration::from_millis(250);
+/// 何も起きていないときの間隔
+pub const MAX_INTERVAL: Duration = Duration::from_secs(2);
+
+/// プロセスの一覧の 1 件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessEntry {
+    pub pid: u32,
+    pub exe_name: String,
+}
+
+/// 次に一覧を取るまでの間隔。変化があれば最短に戻し、なければ倍にしていきます。
+pub fn next_interval(current: Duration, changed: bool) -> Duration {
+    if changed { MIN_INTERVAL } else { (current * 2).min(MAX_INTERVAL) }
+}
+
+/// 前回と今回の一覧の差分。PID が使い回されて実行ファイルが変わっていれば、終了と起動の両方を返します。
+pub fn diff(previous: &HashMap<u32, String>, current: &HashMap<u32, String>) -> Vec<MixerEvent> {
+    let mut exited: Vec<(u32, &String)> = previous.iter()
+        .filter(|(pid, exe_name)| current.get(pid) != Some(exe_name))
+        .map(|(pid, exe_name)| (*pid, exe_name))
+        .collect();
+    let mut started: Vec<(u32, &String)> = current.iter()
+        .filter(|(pid, exe_name)| previous.get(pid) != Some(exe_name))
+        .map(|(pid, exe_name)| (*pid, exe_name))
+        .collect();
+    exited.sort_unstable();
+    started.sort_unstable();
+    exited.into_iter()
+        .map(|(pid, exe_name)| MixerEvent::ProcessExited { pid, exe_name: exe_name.clone() })
+        .chain(started.into_iter().map(|(pid, exe_name)| MixerEvent::ProcessStarted { pid, exe_name: exe_name.clone() }))
+        .collect()
+}
+
+fn to_map(entries: Vec<ProcessEntry>) -> HashMap<u32, String> {
+    entries.into_iter().map(|e| (e.pid, e.exe_name)).collect()
+}
+
+#[cfg(windows)]
+fn snapshot() -> Option<Vec<ProcessEntry>> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+    };
+
+    unsafe {
+        let handle = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0).ok()?;
+        let mut entry = PROCESSENTRY32W { dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32, ..Default::default() };
+        let mut entries = Vec::new();
+        let mut more = Process32FirstW(handle, &mut entry).is_ok();
+        while more {
+            let len = entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len());
+            entries.push(ProcessEntry { pid: entry.th32ProcessID, exe_name: String::from_utf16_lossy(&entry.szExeFile[..len]) });
+            more = Process32NextW(handle, &mut entry).is_ok();
+        }
+        let _ = CloseHandle(handle);
+        Some(entries)
+    }
+}
+
+/// `/proc` の数字のディレクトリがプロセス。ほかのユーザーのプロセスは実行ファイルを読めないので、`comm` の名前を使う
+#[cfg(target_os = "linux")]
+fn snapshot() -> Option<Vec<ProcessEntry>> {
+    let entries = std::fs::read_dir("/proc").ok()?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let exe_name = std::fs::read_link(entry.path().join("exe")).ok()
+                .and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+                .or_else(|| std::fs::read_to_string(entry.path().join("comm")).ok().map(|name| name.trim_end().to_string()))?;
+            Some(ProcessEntry { pid, exe_name })
+        })
+        .collect();
+    Some(entries)
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn snapshot() -> Option<Vec<ProcessEntry>> {
+    None
+}
+
+/// 監視のスレッド。drop すると止まります。
+pub struct ProcessWatcher {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for ProcessWatcher {
+    fn drop(&mut self) {
+        // 送信側を閉じると、待っているスレッドがすぐに抜ける
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl ProcessWatcher {
+    /// このプラットフォームのプロセスの一覧で監視を始めます。一覧を取れないプラットフォームでは `None` を返します。
+    pub fn spawn(sink: impl MixerEventSink) -> Option<Self> {
+        snapshot()?;
+        Some(Self::spawn_with(snapshot, sink))
+    }
+
+    /// `snapshot` で一覧を取って監視します。最初に取れた一覧は基準にするだけで通知しません。`None` を返した回は飛ばします。
+    pub fn spawn_with(mut snapshot: impl FnMut() -> Option<Vec<ProcessEntry>> + Send + 'static, sink: impl MixerEventSink) -> Self {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = std::thread::Builder::new()
+            .name("process-watch".into())
+            .spawn(move || {
+                let mut previous = snapshot().map(to_map);
+                let mut interval = MIN_INTERVAL;
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    let Some(current) = snapshot().map(to_map) else { continue };
+                    let events = previous.as_ref().map(|previous| diff(previous, &current)).unwrap_or_default();
+                    interval = next_interval(interval, !events.is_empty());
+                    for event in events {
+                        sink.emit(event);
+                    }
+                    previous = Some(current);
+                }
+            })
+            .ok();
+        Self { stop: Some(stop), thread }
+    }
+}
@@ -0,0 +1,464 @@
+mod pactl;
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pactl::{CardInfo, SinkInput, SinkInfo};
+
+use crate::accent;
+use crate::apps::AppPresentation;
+use crate::diff::SessionPublisher;
+use crate::{
+    AppOverride, AudioBackend, AudioDeviceInfo, AudioError, AudioNotification, ChangeOrigin, AudioSessionInfo,
+    BluetoothInfo, BluetoothProfile, DeviceRole, GainLevel, InputGain, MasterVolume, MicrophoneUser, MixerEvent, MixerEventSink, Notifier, PeakLevel, SessionState, SessionsDiff,
+};
+
+/// 音量変更などで大量に届く change イベントを、この間隔より細かく再列挙しない
+const MIN_RESCAN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 音量として報告する下限 (dB)。これ以下はミュートと同じ 0 にする
+const MIN_DB: f32 = -60.0;
+
+/// カードのプロファイル名（例: `a2dp-sink-aac`、`headset-head-unit`）から種類を判別します。
+fn profile_kind(name: &str) -> Option<BluetoothProfile> {
+    if name.starts_with("a2dp") {
+        Some(BluetoothProfile::A2dp)
+    } else if name.starts_with("headset") || name.starts_with("handsfree") {
+        Some(BluetoothProfile::HandsFree)
+    } else {
+        None
+    }
+}
+
+/// Bluetooth の sink が属するカード
+fn bluetooth_card<'a>(cards: &'a [CardInfo], sink: &SinkInfo) -> Option<&'a CardInfo> {
+    let address = sink.bluetooth_address()?;
+    cards.iter().find(|c| c.bluetooth_address().as_ref() == Some(&address))
+}
+
+/// sink・source の一覧をデバイス情報にします。
+fn device_infos(endpoints: Vec<SinkInfo>, default_name: &str) -> Result<Vec<AudioDeviceInfo>, AudioError> {
+    let cards = pactl::list::<CardInfo>("cards")?;
+    Ok(endpoints
+        .into_iter()
+        .map(|s| AudioDeviceInfo {
+            is_default: s.name == default_name,
+            jack_connected: s.jack_connected(),
+            bluetooth: s.bluetooth_address().map(|_| {
+                let card = bluetooth_card(&cards, &s);
+                BluetoothInfo {
+                    battery_percent: card.and_then(CardInfo::battery_percent),
+                    profile: card.and_then(|c| c.active_profile.as_deref()).and_then(profile_kind),
+                }
+            }),
+            name: s.description.unwrap_or_else(|| s.name.clone()),
+            id: s.name,
+        })
+        .collect())
+}
+
+/// PipeWire (pipewire-pulse) / PulseAudio によるバックエンド。
+/// `pactl` を通じて sink-input をセッション、sink をデバイスとして扱います。
+pub struct PulseBackend {
+    sink: Arc<dyn MixerEventSink>,
+    apps: AppPresentation,
+    session_cache: Vec<AudioSessionInfo>,
+    cache_dirty: bool,
+    last_scan: Option<Instant>,
+    publisher: SessionPublisher,
+    /// PID からセッションの識別子と sink-input の番号を引くための索引
+    session_index: HashMap<u32, Vec<(String, u32)>>,
+    /// 最後に通知したマスター音量。sink の change は既定以外の sink でも届くため、変化したときだけ通知する
+    last_master: Option<MasterVolume>,
+    /// `pactl subscribe` の子プロセス。Drop で終了させる
+    subscriber: Option<Child>,
+}
+
+impl Drop for PulseBackend {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.subscriber.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl PulseBackend {
+    pub fn new(notifier: Notifier, sink: Arc<dyn MixerEventSink>) -> Result<Self, AudioError> {
+        // サーバーに接続できなければバックエンドとして使えない
+        pactl::run(&["info"])?;
+        Ok(Self {
+            sink,
+            apps: AppPresentation::default(),
+            session_cache: Vec::new(),
+            cache_dirty: true,
+            last_scan: None,
+            publisher: SessionPublisher::default(),
+            session_index: HashMap::new(),
+            last_master: None,
+            subscriber: spawn_subscriber(notifier),
+        })
+    }
+
+    /// `pactl subscribe` からは誰が変えたか分からないので、変更元は常に `Unknown` です。
+    fn publish_master(&mut self) {
+        if let Ok(master) = self.get_master_volume() {
+            if self.last_master.as_ref() != Some(&master) {
+                self.last_master = Some(master.clone());
+                self.sink.emit(MixerEvent::MasterVolumeChanged(master, ChangeOrigin::Unknown));
+            }
+        }
+    }
+
+    fn invalidate_sessions(&mut self) {
+        self.session_cache.clear();
+        self.cache_dirty = true;
+    }
+
+    fn rescan_sessions(&mut self) -> Result<(), AudioError> {
+        let sinks: HashMap<u32, String> = pactl::list::<SinkInfo>("sinks")?
+            .into_iter()
+            .map(|s| (s.index, s.name))
+            .collect();
+        let mut previous: HashMap<u32, Option<String>> = self.session_cache
+            .drain(..)
+            .map(|s| (s.process_id, s.icon_base64))
+            .collect();
+        let mut sessions: Vec<AudioSessionInfo> = Vec::new();
+        let mut session_index: HashMap<u32, Vec<(String, u32)>> = HashMap::new();
+
+        for input in pactl::list::<SinkInput>("sink-inputs")? {
+            let Some(pid) = input.process_id() else { continue };
+            let exe_name = input.exe_name();
+            let exe_key = exe_name.to_uppercase();
+            if self.apps.is_hidden(&exe_key) { continue; }
+
+            let device_id = sinks.get(&input.sink).cloned().unwrap_or_default();
+            // 同じプロセス・同じ出力先のストリームは 1 つのセッションとして扱う
+            let instance_id = crate::session_key(pid, &device_id);
+            session_index.entry(pid).or_default().push((instance_id.clone(), input.index));
+
+            if let Some(session) = sessions.iter_mut().find(|s| s.instance_id == instance_id) {
+                // どれか 1 つでも再生中なら再生中とみなす
+                if !input.corked {
+                    session.state = SessionState::Active;
+                }
+                continue;
+            }
+            let icon_base64 = previous.remove(&pid).flatten().or_else(|| self.apps.override_icon(&exe_key));
+            sessions.push(AudioSessionInfo {
+                process_id: pid,
+                process_name: self.apps.display_name(&exe_key, &exe_name),
+                exe_name,
+                volume: input.volume.average(),
+                is_muted: input.mute,
+                peak_level: 0.0,
+                accent_color: icon_base64.as_deref().and_then(accent::accent_color),
+                icon_base64,
+                device_id,
+                instance_id,
+                state: if input.corked { SessionState::Inactive } else { SessionState::Active },
+                // move-sink-input はその場で移動するので、保留中の出力先はない
+                persisted_device_id: None,
+                route_pending: false,
+                now_playing: None,
+            });
+        }
+
+        self.session_cache = sessions;
+        self.session_index = session_index;
+        self.cache_dirty = false;
+        self.last_scan = Some(Instant::now());
+        Ok(())
+    }
+
+    fn refresh_if_needed(&mut self) -> Result<(), AudioError> {
+        if self.cache_dirty {
+            self.rescan_sessions()?;
+        }
+        Ok(())
+    }
+
+    /// 索引から PID の sink-input を引いて操作します。見つからない場合だけ再列挙します。
+    fn apply_to_session<F>(&mut self, pid: u32, action: F) -> Result<(), AudioError>
+    where
+        F: Fn(u32) -> Result<(), AudioError>,
+    {
+        if self.cache_dirty || !self.session_index.contains_key(&pid) {
+            self.rescan_sessions()?;
+        }
+        let inputs = self.session_index.get(&pid).ok_or(AudioError::SessionNotFound(pid))?;
+        let result = inputs.iter().try_for_each(|&(_, index)| action(index));
+        if result.is_err() {
+            // ストリームが終了している可能性があるので、次回は再列挙させる
+            self.cache_dirty = true;
+        }
+        result
+    }
+
+    /// 識別子が一致するセッションの sink-input だけを操作します。
+    fn apply_to_instance<F>(&mut self, instance_id: &str, action: F) -> Result<(), AudioError>
+    where
+        F: Fn(u32) -> Result<(), AudioError>,
+    {
+        let find = |index: &HashMap<u32, Vec<(String, u32)>>| -> Vec<u32> {
+            index.values().flatten().filter(|(id, _)| id == instance_id).map(|&(_, i)| i).collect()
+        };
+        let mut inputs = find(&self.session_index);
+        if self.cache_dirty || inputs.is_empty() {
+            self.rescan_sessions()?;
+            inputs = find(&self.session_index);
+        }
+        if inputs.is_empty() {
+            return Err(AudioError::InstanceNotFound(instance_id.to_string()));
+        }
+        let result = inputs.into_iter().try_for_each(action);
+        if result.is_err() {
+            self.cache_dirty = true;
+        }
+        result
+    }
+}
+
+impl AudioBackend for PulseBackend {
+    fn name(&self) -> &'static str {
+        "pulse"
+    }
+
+    fn clear_caches(&mut self) {
+        self.invalidate_sessions();
+    }
+
+    fn set_hidden_apps(&mut self, names: Vec<String>) {
+        self.apps.set_hidden_apps(names);
+        self.invalidate_sessions();
+    }
+
+    fn set_app_overrides(&mut self, overrides: HashMap<String, AppOverride>) {
+        self.apps.set_app_overrides(overrides);
+        self.invalidate_sessions();
+    }
+
+    fn get_sessions(&mut self) -> Result<Vec<AudioSessionInfo>, AudioError> {
+        self.refresh_if_needed()?;
+        Ok(self.session_cache.clone())
+    }
+
+    fn get_session_icon(&mut self, pid: u32) -> Result<Option<String>, AudioError> {
+        self.refresh_if_needed()?;
+        let session = self.session_cache.iter().find(|s| s.process_id == pid).ok_or(AudioError::SessionNotFound(pid))?;
+        Ok(session.icon_base64.clone())
+    }
+
+    fn take_sessions_diff(&mut self) -> Result<Option<SessionsDiff>, AudioError> {
+        let recent = self.last_scan.is_some_and(|t| t.elapsed() < MIN_RESCAN_INTERVAL);
+        if !self.cache_dirty || recent {
+            return Ok(None);
+        }
+        self.rescan_sessions()?;
+        Ok(self.publisher.publish(&self.session_cache))
+    }
+
+    fn get_peak_levels(&mut self) -> Result<Vec<PeakLevel>, AudioError> {
+        // ストリームごとのメーターは pactl からは取得できない
+        Ok(Vec::new())
+    }
+
+    fn set_session_volume(&mut self, pid: u32, volume: f32) -> Result<(), AudioError> {
+        let raw = pactl::raw_volume(volume).to_string();
+        self.apply_to_session(pid, |index| pactl::run(&["set-sink-input-volume", &index.to_string(), &raw]).map(|_| ()))
+    }
+
+    fn set_session_mute(&mut self, pid: u32, mute: bool) -> Result<(), AudioError> {
+        let flag = if mute { "1" } else { "0" };
+        self.apply_to_session(pid, |index| pactl::run(&["set-sink-input-mute", &index.to_string(), flag]).map(|_| ()))
+    }
+
+    fn set_instance_volume(&mut self, instance_id: &str, volume: f32) -> Result<(), AudioError> {
+        let raw = pactl::raw_volume(volume).to_string();
+        self.apply_to_instance(instance_id, |index| pactl::run(&["set-sink-input-volume", &index.to_string(), &raw]).map(|_| ()))
+    }
+
+    fn set_instance_mute(&mut self, instance_id: &str, mute: bool) -> Result<(), AudioError> {
+        let flag = if mute { "1" } else { "0" };
+        self.apply_to_instance(instance_id, |index| pactl::run(&["set-sink-input-mute", &index.to_string(), flag]).map(|_| ()))
+    }
+
+    fn get_audio_devices(&mut self) -> Result<Vec<AudioDeviceInfo>, AudioError> {
+        let default_sink = pactl::run(&["get-default-sink"])?.trim().to_string();
+        device_infos(pactl::list::<SinkInfo>("sinks")?, &default_sink)
+    }
+
+    /// モニター以外の source を入力デバイスとして返します。
+    fn get_capture_devices(&mut self) -> Result<Vec<AudioDeviceInfo>, AudioError> {
+        let default_source = pactl::run(&["get-default-source"])?.trim().to_string();
+        let sources = pactl::list::<SinkInfo>("sources")?.into_iter().filter(|s| !s.is_monitor()).collect();
+        device_infos(sources, &default_source)
+    }
+
+    /// PulseAudio には役割ごとの既定がないので、`role` は無視します。
+    fn set_default_capture_device(&mut self, device_id: &str, _role: Option<DeviceRole>) -> Result<(), AudioError> {
+        pactl::run(&["set-default-source", device_id])?;
+        Ok(())
+    }
+
+    /// ソフトウェア音量だけを扱います。ハードウェアのブーストは ALSA のミキサー側にあり、pactl からは見えません。
+    fn get_input_gain(&mut self, device_id: &str) -> Result<InputGain, AudioError> {
+        let source = pactl::list::<SinkInfo>("sources")?
+            .into_iter()
+            .find(|s| s.name == device_id)
+            .ok_or_else(|| AudioError::Backend(format!("Unknown source {}", device_id)))?;
+        let volume = GainLevel {
+            db: source.volume.average_db().max(MIN_DB),
+            min_db: MIN_DB,
+            max_db: pactl::volume_db(pactl::VOLUME_UI_MAX),
+            step_db: 0.0,
+        };
+        Ok(InputGain { device_id: source.name, volume, boost: None })
+    }
+
+    fn set_input_gain(&mut self, device_id: &str, db: f32) -> Result<(), AudioError> {
+        let raw = if db <= MIN_DB { 0 } else { pactl::raw_volume_db(db) };
+        pactl::run(&["set-source-volume", device_id, &raw.to_string()])?;
+        Ok(())
+    }
+
+    /// 一時停止していない source-output を録音中とみなします。モニターからの録音（画面録画など）は含めません。
+    fn get_microphone_users(&mut self) -> Result<Vec<MicrophoneUser>, AudioError> {
+        let sources: HashMap<u32, SinkInfo> = pactl::list::<SinkInfo>("sources")?
+            .into_iter()
+            .filter(|s| !s.is_monitor())
+            .map(|s| (s.index, s))
+            .collect();
+        let mut users: Vec<MicrophoneUser> = Vec::new();
+        for output in pactl::list::<SinkInput>("source-outputs")? {
+            let Some(source) = sources.get(&output.sink) else { continue };
+            if output.corked {
+                continue;
+            }
+            let pid = output.process_id();
+            if users.iter().any(|u| u.pid == pid && u.device_id.as_deref() == Some(source.name.as_str())) {
+                continue;
+            }
+            let exe_name = output.exe_name();
+            users.push(MicrophoneUser {
+                pid,
+                process_name: self.apps.display_name(&exe_name.to_uppercase(), &exe_name),
+                exe_name,
+                device_id: Some(source.name.clone()),
+            });
+        }
+        Ok(users)
+    }
+
+    fn get_master_volume(&mut self) -> Result<MasterVolume, AudioError> {
+        let default_sink = pactl::run(&["get-default-sink"])?.trim().to_string();
+        let sink = pactl::list::<SinkInfo>("sinks")?
+            .into_iter()
+            .find(|s| s.name == default_sink)
+            .ok_or_else(|| AudioError::Backend("No default sink".to_string()))?;
+        // スカラー値と同じく 100% を上限にする
+        let level = GainLevel { db: sink.volume.average_db().clamp(MIN_DB, 0.0), min_db: MIN_DB, max_db: 0.0, step_db: 0.0 };
+        Ok(MasterVolume { volume: sink.volume.average(), is_muted: sink.mute, device_id: sink.name, level: Some(level) })
+    }
+
+    fn set_master_volume(&mut self, volume: f32) -> Result<(), AudioError> {
+        pactl::run(&["set-sink-volume", "@DEFAULT_SINK@", &pactl::raw_volume(volume).to_string()])?;
+        Ok(())
+    }
+
+    fn set_master_volume_db(&mut self, db: f32) -> Result<(), AudioError> {
+        let raw = if db <= MIN_DB { 0 } else { pactl::raw_volume_db(db.min(0.0)) };
+        pactl::run(&["set-sink-volume", "@DEFAULT_SINK@", &raw.to_string()])?;
+        Ok(())
+    }
+
+    fn set_master_mute(&mut self, mute: bool) -> Result<(), AudioError> {
+        pactl::run(&["set-sink-mute", "@DEFAULT_SINK@", if mute { "1" } else { "0" }])?;
+        Ok(())
+    }
+
+    fn set_default_device(&mut self, device_id: &str) -> Result<(), AudioError> {
+        pactl::run(&["set-default-sink", device_id])?;
+        Ok(())
+    }
+
+    /// sink が属するカードのプロファイルを切り替えます。同じ種類が複数あれば優先度の高いもの（高音質なコーデック）を選びます。
+    fn set_bluetooth_profile(&mut self, device_id: &str, profile: BluetoothProfile) -> Result<(), AudioError> {
+        let sink = pactl::list::<SinkInfo>("sinks")?
+            .into_iter()
+            .find(|s| s.name == device_id)
+            .ok_or_else(|| AudioError::Backend(format!("Unknown device {}", device_id)))?;
+        let cards = pactl::list::<CardInfo>("cards")?;
+        let card = bluetooth_card(&cards, &sink)
+            .ok_or_else(|| AudioError::Backend(format!("Not a Bluetooth device: {}", device_id)))?;
+        let target = card.profiles.iter()
+            .filter(|(name, p)| p.available && profile_kind(name) == Some(profile))
+            .max_by_key(|(_, p)| p.priority)
+            .map(|(name, _)| name.as_str())
+            .ok_or_else(|| AudioError::Backend(format!("{} does not offer the {:?} profile", device_id, profile)))?;
+        pactl::run(&["set-card-profile", &card.name, target])?;
+        self.cache_dirty = true;
+        Ok(())
+    }
+
+    /// プロセスのすべての sink-input を指定した sink へ移動します。
+    fn set_audio_routing(&mut self, pid: u32, device_id: &str) -> Result<(), AudioError> {
+        self.apply_to_session(pid, |index| pactl::run(&["move-sink-input", &index.to_string(), device_id]).map(|_| ()))?;
+        self.cache_dirty = true;
+        Ok(())
+    }
+
+    /// 出力先を永続化しないので、既定の出力デバイスへ移すだけです。
+    fn clear_audio_routing(&mut self, pid: u32) -> Result<(), AudioError> {
+        let default_sink = pactl::run(&["get-default-sink"])?.trim().to_string();
+        self.set_audio_routing(pid, &default_sink)
+    }
+
+    fn handle_notification(&mut self, notification: AudioNotification) {
+        match notification {
+            AudioNotification::DevicesChanged => {
+                self.cache_dirty = true;
+                self.sink.emit(MixerEvent::DevicesChanged);
+                self.publish_master();
+            }
+            AudioNotification::MasterVolumeChanged { .. } => self.publish_master(),
+            _ => self.cache_dirty = true,
+        }
+    }
+
+    /// `pactl subscribe` はサーバーが終了すると一緒に終了するので、それを切断とみなします。
+    fn check_health(&mut self) -> bool {
+        match &mut self.subscriber {
+            Some(child) => !matches!(child.try_wait(), Ok(Some(_))),
+            None => true,
+        }
+    }
+}
+
+/// `pactl subscribe` を起動し、出力を読むスレッドからオーディオスレッドへ通知を転送します。
+fn spawn_subscriber(notifier: Notifier) -> Option<Child> {
+    let mut child = pactl::command(&["subscribe"]).stdout(Stdio::piped()).spawn().ok()?;
+    let stdout = child.stdout.take()?;
+    thread::Builder::new()
+        .name("pactl-subscribe".into())
+        .spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                // 例: "Event 'change' on sink-input #42"
+                if line.contains(" on sink-input ") {
+                    notifier.notify(AudioNotification::SessionCreated);
+                } else if line.contains(" on server") || (line.contains(" on sink ") && !line.contains("'change'")) {
+                    notifier.notify(AudioNotification::DevicesChanged);
+                } else if line.contains(" on sink ") {
+                    // sink の change はマスター音量の変化なので、デバイス一覧の再取得は不要
+                    notifier.notify(AudioNotification::MasterVolumeChanged { origin: ChangeOrigin::Unknown });
+                }
+            }
+        })
+        .ok()?;
+    Some(child)
+}
@@ -0,0 +1,221 @@
+//! `pactl` コマンドの呼び出しと JSON 出力（pactl 16 以降）の解析。
+
+use std::collections::HashMap;
+use std::process::Command;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::AudioError;
+
+/// PulseAudio の 100% に相当する生の音量値 (PA_VOLUME_NORM)
+const VOLUME_NORM: f32 = 65536.0;
+/// pavucontrol などが音量の上限として扱う値 (PA_VOLUME_UI_MAX、約 153%)
+pub const VOLUME_UI_MAX: u32 = 99957;
+
+/// sink（出力）の情報。source（入力）も同じ形式で返ってくるので、これで読みます。
+#[derive(Debug, Deserialize)]
+pub struct SinkInfo {
+    pub index: u32,
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub properties: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub mute: bool,
+    #[serde(default)]
+    pub volume: ChannelVolumes,
+    #[serde(default)]
+    pub ports: Vec<PortInfo>,
+    #[serde(default)]
+    pub active_port: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PortInfo {
+    pub name: String,
+    /// `available`、`not available`、`availability unknown` のいずれか
+    #[serde(default)]
+    pub availability: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CardInfo {
+    pub name: String,
+    #[serde(default)]
+    pub active_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: HashMap<String, CardProfile>,
+    #[serde(default)]
+    pub properties: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CardProfile {
+    #[serde(default)]
+    pub priority: u32,
+    #[serde(default = "default_available")]
+    pub available: bool,
+}
+
+fn default_available() -> bool {
+    true
+}
+
+fn property(properties: &HashMap<String, serde_json::Value>, key: &str) -> Option<String> {
+    match properties.get(key)? {
+        serde_json::Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// PipeWire と PulseAudio で名前の異なる、Bluetooth 機器のアドレス
+fn bluetooth_address(properties: &HashMap<String, serde_json::Value>) -> Option<String> {
+    if property(properties, "device.bus").as_deref() != Some("bluetooth") {
+        return None;
+    }
+    property(properties, "api.bluez5.address").or_else(|| property(properties, "device.string"))
+}
+
+/// `80%` や `80` のようなバッテリー残量の表記を読み取ります。
+fn battery_percent(properties: &HashMap<String, serde_json::Value>) -> Option<u8> {
+    let value = property(properties, "bluetooth.battery").or_else(|| property(properties, "api.bluez5.battery"))?;
+    value.trim().trim_end_matches('%').parse::<u8>().ok().map(|p| p.min(100))
+}
+
+impl SinkInfo {
+    pub fn bluetooth_address(&self) -> Option<String> {
+        bluetooth_address(&self.properties)
+    }
+
+    /// sink の出力をそのまま録音するための source（モニター）か
+    pub fn is_monitor(&self) -> bool {
+        property(&self.properties, "device.class").as_deref() == Some("monitor")
+    }
+
+    /// 選択中のポートの端子に接続があるか。ジャック検出のないポートでは `None` です。
+    pub fn jack_connected(&self) -> Option<bool> {
+        let active = self.active_port.as_ref()?;
+        let port = self.ports.iter().find(|p| &p.name == active)?;
+        match port.availability.as_str() {
+            "available" => Some(true),
+            "not available" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl CardInfo {
+    pub fn bluetooth_address(&self) -> Option<String> {
+        bluetooth_address(&self.properties)
+    }
+
+    pub fn battery_percent(&self) -> Option<u8> {
+        battery_percent(&self.properties)
+    }
+}
+
+/// source-output（録音ストリーム）にも使います。その場合 `sink` は録音元の source です。
+#[derive(Debug, Deserialize)]
+pub struct SinkInput {
+    pub index: u32,
+    #[serde(alias = "source")]
+    pub sink: u32,
+    #[serde(default)]
+    pub mute: bool,
+    /// 再生が一時停止されている
+    #[serde(default)]
+    pub corked: bool,
+    #[serde(default)]
+    pub volume: ChannelVolumes,
+    #[serde(default)]
+    pub properties: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct ChannelVolumes(HashMap<String, ChannelVolume>);
+
+#[derive(Debug, Deserialize)]
+pub struct ChannelVolume {
+    pub value: u32,
+}
+
+impl ChannelVolumes {
+    /// 全チャンネルの平均を 0.0〜1.0 のスカラー値で返します。
+    pub fn average(&self) -> f32 {
+        if self.0.is_empty() {
+            return 1.0;
+        }
+        let sum: f32 = self.0.values().map(|c| c.value as f32 / VOLUME_NORM).sum();
+        (sum / self.0.len() as f32).min(1.0)
+    }
+
+    /// 全チャンネルの平均を dB で返します。100% を超える音量もそのまま返します。
+    pub fn average_db(&self) -> f32 {
+        if self.0.is_empty() {
+            return 0.0;
+        }
+        let sum: u64 = self.0.values().map(|c| u64::from(c.value)).sum();
+        volume_db((sum / self.0.len() as u64) as u32)
+    }
+}
+
+impl SinkInput {
+    fn property(&self, key: &str) -> Option<String> {
+        property(&self.properties, key)
+    }
+
+    pub fn process_id(&self) -> Option<u32> {
+        self.property("application.process.id")?.parse().ok()
+    }
+
+    /// 上書き設定のキーとなる実行ファイル名。取れなければアプリケーション名を使います。
+    pub fn exe_name(&self) -> String {
+        self.property("application.process.binary")
+            .or_else(|| self.property("application.name"))
+            .map(|n| n.to_uppercase())
+            .unwrap_or_else(|| format!("STREAM {}", self.index))
+    }
+}
+
+/// 0.0〜1.0 のスカラー値を pactl に渡す生の音量値へ変換します。
+pub fn raw_volume(volume: f32) -> u32 {
+    (volume.clamp(0.0, 1.0) * VOLUME_NORM).round() as u32
+}
+
+/// PulseAudio のソフトウェア音量は 3 乗のカーブなので、dB は 60·log10(v/NORM) になります (pa_sw_volume_to_dB)。
+pub fn volume_db(raw: u32) -> f32 {
+    if raw == 0 {
+        return f32::NEG_INFINITY;
+    }
+    60.0 * (raw as f32 / VOLUME_NORM).log10()
+}
+
+pub fn raw_volume_db(db: f32) -> u32 {
+    (VOLUME_NORM * 10f32.powf(db / 60.0)).round().min(VOLUME_UI_MAX as f32) as u32
+}
+
+pub fn command(args: &[&str]) -> Command {
+    let mut command = Command::new("pactl");
+    command.args(args).env("LC_ALL", "C");
+    command
+}
+
+/// pactl を実行し、標準出力を返します。
+pub fn run(args: &[&str]) -> Result<String, AudioError> {
+    let output = command(args)
+        .output()
+        .map_err(|e| AudioError::Backend(format!("Failed to run pactl: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AudioError::Backend(format!("pactl {} failed: {}", args.join(" "), stderr.trim())));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `pactl --format=json list <kind>` の結果を読み込みます。
+pub fn list<T: DeserializeOwned>(kind: &str) -> Result<Vec<T>, AudioError> {
+    let json = run(&["--format=json", "list", kind])?;
+    serde_json::from_str(&json).map_err(|e| AudioError::Backend(format!("Unexpected pactl output: {}", e)))
+}
@@ -0,0 +1,72 @@
+use serde::Serialize;
+
+use crate::{ChangeOrigin, MasterVolume, SessionsDiff};
+
+/// ミキサーから外部（UI、CLI、テストなど）へ通知されるイベント
+#[derive(Debug, Clone, Serialize)]
+pub enum MixerEvent {
+    /// セッションの音量・ミュートが変化した
+    VolumeChanged { pid: u32, volume: f32, muted: bool, origin: ChangeOrigin },
+    /// セッションの状態（Active / Inactive / Expired）が変化した
+    SessionStateChanged { pid: u32, state: String },
+    /// エンドポイントの追加・削除・既定デバイス変更
+    DevicesChanged,
+    /// 既定の出力デバイスの音量・ミュートが変化した。2 つ目は変更元
+    MasterVolumeChanged(MasterVolume, ChangeOrigin),
+    /// `watch_device_volumes` で監視している出力デバイスの音量・ミュートが変化した。2 つ目は変更元
+    DeviceVolumeChanged(MasterVolume, ChangeOrigin),
+    /// 定期的なピーク値
+    Peaks(Vec<PeakLevel>),
+    /// 計測中の入力デバイスの定期的なピーク値
+    InputPeak(InputPeak),
+    /// 一覧を返した後に、セッションのアイコンを抽出できた
+    SessionIconReady { pid: u32, icon_base64: String, accent_color: Option<String> },
+    /// 前回通知からのセッション一覧の差分
+    SessionsChanged(SessionsDiff),
+    /// オーディオサービスとの接続が失われ、バックエンドを作り直した
+    BackendReconnected,
+    /// ジェネレーターの再生が終わった。`error` は失敗して止まった場合の理由
+    PlaybackStopped { error: Option<String> },
+    /// 音声ファイルの再生位置 (ms) の定期的な通知。`duration_ms` は長さの分からない形式では `None`
+    PlaybackProgress { position_ms: u64, duration_ms: Option<u64> },
+    /// アプリの音のミラーが止まった。`error` は失敗して止まった場合の理由
+    MirrorStopped { pid: u32, error: Option<String> },
+    /// アプリの音のブーストが止まった。`error` は失敗して止まった場合の理由
+    BoostStopped { pid: u32, error: Option<String> },
+    /// システムの音のファイルへの録音が止まった。`files` は作ったファイル、`error` は失敗して止まった場合の理由
+    FileCaptureStopped { files: Vec<String>, error: Option<String> },
+    /// LAN への配信が止まった。`error` は失敗して止まった場合の理由
+    NetworkStreamStopped { error: Option<String> },
+    /// プロセスが起動した（[`crate::process_watch::ProcessWatcher`] が通知する）
+    ProcessStarted { pid: u32, exe_name: String },
+    /// プロセスが終了した
+    ProcessExited { pid: u32, exe_name: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct PeakLevel {
+    pub pid: u32,
+    pub peak: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct InputPeak {
+    pub device_id: String,
+    pub peak: f32,
+}
+
+/// イベントの受け取り先。オーディオスレッドおよび COM スレッドから呼ばれます。
+pub trait MixerEventSink: Send + Sync + 'static {
+    fn emit(&self, event: MixerEvent);
+}
+
+impl<F> MixerEventSink for F
+where
+    F: Fn(MixerEvent) + Send + Sync + 'static,
+{
+    fn emit(&self, event: MixerEvent) {
+        self(event)
+    }
+}
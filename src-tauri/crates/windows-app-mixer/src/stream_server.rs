@@ -0,0 +1,198 @@
+//! 取り込んだ音を LAN に流す HTTP サーバー。終わりのない 16bit PCM の WAV として送るので、
+//! ほかの PC やスマートフォンのブラウザー・VLC などで `http://<PC のアドレス>:<ポート>/stream.wav` を開くだけで聴けます。
+//! 音の取り込みはバックエンドが受け持ち、ここでは [`StreamServer::push`] で渡された音を聴いている全員に配ります。
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::capture_file::{self, CaptureSource, DEFAULT_CAPTURE_SAMPLE_RATE};
+use crate::AudioError;
+
+/// 同時に聴ける数
+const MAX_LISTENERS: usize = 8;
+/// 聴き手ごとに送らずにためておける塊の数。受け取りが遅い相手の分は、これを超えたら捨てて遅れを詰める
+const LISTENER_QUEUE: usize = 32;
+/// 接続を待つ間隔。停止要求に気付くための上限
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+/// リクエストを読む・音を書くのを待つ最長時間
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(5);
+/// 送るサンプルのビット数
+const BITS_PER_SAMPLE: u16 = 16;
+pub const STREAM_PATH: &str = "/stream.wav";
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct NetworkStreamRequest {
+    pub source: CaptureSource,
+    /// 待ち受けるポート。0 なら空いているポートを使います
+    pub port: u16,
+    /// LAN のほかの機器からの接続を受け付ける。`false` ならこの PC からだけ
+    pub allow_lan: bool,
+    /// `None` なら [`DEFAULT_CAPTURE_SAMPLE_RATE`]
+    pub sample_rate: Option<u32>,
+    /// 1 か 2
+    pub channels: u16,
+}
+
+impl NetworkStreamRequest {
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate.unwrap_or(DEFAULT_CAPTURE_SAMPLE_RATE)
+    }
+
+    pub fn validate(&self) -> Result<(), AudioError> {
+        capture_file::validate_format(self.sample_rate(), self.channels)
+    }
+}
+
+/// 動いている配信
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct NetworkStream {
+    pub request: NetworkStreamRequest,
+    /// 聴くための URL。LAN に公開しているときは LAN 側のアドレス
+    pub url: String,
+    /// いま聴いている数
+    pub listeners: usize,
+}
+
+/// LAN 側のこの PC のアドレス。経路を調べるだけで、パケットは送りません。
+fn lan_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 0, 2, 1), 9)).ok()?;
+    socket.local_addr().ok().map(|a| a.ip()).filter(|ip| !ip.is_unspecified())
+}
+
+type Listeners = Arc<Mutex<Vec<SyncSender<Arc<[u8]>>>>>;
+
+/// 音を配る HTTP サーバー。Drop で待ち受けをやめ、聴いている全員との接続を切ります。
+pub struct StreamServer {
+    request: NetworkStreamRequest,
+    address: SocketAddr,
+    listeners: Listeners,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for StreamServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        if let Ok(mut listeners) = self.listeners.lock() {
+            listeners.clear();
+        }
+    }
+}
+
+impl StreamServer {
+    /// ポートを開き、接続を待ち始めます。
+    pub fn start(request: NetworkStreamRequest) -> Result<Self, AudioError> {
+        request.validate()?;
+        let ip = if request.allow_lan { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+        let listener = TcpListener::bind((ip, request.port))
+            .and_then(|l| l.set_nonblocking(true).map(|()| l))
+            .map_err(|e| AudioError::Backend(format!("Failed to listen on port {}: {}", request.port, e)))?;
+        let address = listener.local_addr().map_err(|e| AudioError::Backend(e.to_string()))?;
+        let listeners: Listeners = Arc::default();
+        let stop = Arc::new(AtomicBool::new(false));
+        let header = capture_file::wav_header(request.sample_rate(), request.channels, BITS_PER_SAMPLE, u32::MAX - 36);
+        let thread = {
+            let (listeners, stop) = (listeners.clone(), stop.clone());
+            thread::Builder::new()
+                .name("stream-server".into())
+                .spawn(move || accept_loop(listener, header, listeners, stop))
+                .map_err(|e| AudioError::Backend(format!("Failed to start stream server: {}", e)))?
+        };
+        Ok(Self { request, address, listeners, stop, thread: Some(thread) })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.address.port()
+    }
+
+    pub fn info(&self) -> NetworkStream {
+        let host = if self.request.allow_lan { lan_address().unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST)) } else { IpAddr::V4(Ipv4Addr::LOCALHOST) };
+        NetworkStream {
+            request: self.request.clone(),
+            url: format!("http://{}{}", SocketAddr::new(host, self.port()), STREAM_PATH),
+            listeners: self.listeners.lock().map(|l| l.len()).unwrap_or(0),
+        }
+    }
+
+    /// インターリーブのサンプルを聴いている全員に送ります。接続が切れた相手はここで外します。
+    pub fn push(&self, samples: &[f32]) {
+        let Ok(mut listeners) = self.listeners.lock() else { return };
+        if listeners.is_empty() {
+            return;
+        }
+        let bytes: Arc<[u8]> = samples.iter().flat_map(|&s| (capture_file::to_int(s, BITS_PER_SAMPLE) as i16).to_le_bytes()).collect();
+        listeners.retain(|listener| !matches!(listener.try_send(bytes.clone()), Err(TrySendError::Disconnected(_))));
+    }
+}
+
+fn accept_loop(listener: TcpListener, header: Vec<u8>, listeners: Listeners, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let (header, listeners) = (header.clone(), listeners.clone());
+                let _ = thread::Builder::new()
+                    .name("stream-listener".into())
+                    .spawn(move || serve(stream, &header, &listeners));
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_INTERVAL),
+            Err(e) => {
+                eprintln!("Stream server stopped accepting: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// 1 つの接続を受け持ちます。`GET /stream.wav` なら WAV のヘッダーに続けて音を送り続けます。
+fn serve(mut stream: TcpStream, header: &[u8], listeners: &Listeners) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(SOCKET_TIMEOUT))?;
+    stream.set_write_timeout(Some(SOCKET_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // 残りのヘッダーは読み捨てる
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    if method != "GET" && method != "HEAD" {
+        return stream.write_all(b"HTTP/1.1 405 Method Not Allowed\r\nAllow: GET, HEAD\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    }
+    if path.split('?').next() != Some(STREAM_PATH) {
+        return stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    }
+    let (tx, rx) = mpsc::sync_channel(LISTENER_QUEUE);
+    {
+        let mut listeners = listeners.lock().map_err(|_| io::Error::other("listeners lock poisoned"))?;
+        if listeners.len() >= MAX_LISTENERS {
+            return stream.write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+        }
+        if method == "GET" {
+            listeners.push(tx);
+        }
+    }
+    stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: audio/wav\r\nCache-Control: no-cache, no-store\r\nConnection: close\r\n\r\n")?;
+    if method == "HEAD" {
+        return Ok(());
+    }
+    stream.write_all(header)?;
+    // 送り手（サーバー）がなくなるか、相手が切断するまで送り続ける
+    while let Ok(bytes) = rx.recv() {
+        stream.write_all(&bytes)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! 仮想オーディオケーブル（VB-Audio の VB-Cable・VoiceMeeter）の見分け。どちらも再生デバイス（"CABLE Input" など）に
+//! 流した音が、対になる録音デバイス（"CABLE Output" など）から出てくるので、名前から両者を組にします。
+
+use crate::AudioDeviceInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub enum VirtualCableKind {
+    /// VB-Cable（A+B、Hi-Fi Cable を含む）
+    VbCable,
+    VoiceMeeter,
+}
+
+/// 見つかった仮想ケーブル
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+pub struct VirtualCable {
+    pub kind: VirtualCableKind,
+    /// 再生デバイスの名前
+    pub name: String,
+    /// アプリの出力先にする再生デバイス
+    pub playback_device_id: String,
+    /// 流した音が出てくる録音デバイス。無効にされているなどで見つからなければ `None`
+    pub recording_device_id: Option<String>,
+}
+
+fn kind_of(name: &str) -> Option<VirtualCableKind> {
+    let name = name.to_lowercase();
+    if name.contains("voicemeeter") {
+        Some(VirtualCableKind::VoiceMeeter)
+    } else if name.contains("vb-audio") && name.contains("cable") {
+        Some(VirtualCableKind::VbCable)
+    } else {
+        None
+    }
+}
+
+/// 再生デバイスの名前を、対になる録音デバイスの名前に変えます（"CABLE Input (…)" → "cable output (…)"）。
+fn recording_name(playback_name: &str) -> String {
+    playback_name.to_lowercase().replacen("input", "output", 1)
+}
+
+/// 再生デバイスと録音デバイスの一覧から仮想ケーブルを探します。
+pub fn detect(playback: &[AudioDeviceInfo], recording: &[AudioDeviceInfo]) -> Vec<VirtualCable> {
+    playback.iter()
+        .filter_map(|device| {
+            let kind = kind_of(&device.name)?;
+            let pair = recording_name(&device.name);
+            let recording_device_id = recording.iter().find(|r| r.name.to_lowercase() == pair).map(|r| r.id.clone());
+            Some(VirtualCable { kind, name: device.name.clone(), playback_device_id: device.id.clone(), recording_device_id })
+        })
+        .collect()
+}
@@ -0,0 +1,103 @@
+//! Bluetooth のオーディオ機器。Windows は A2DP と通話用 (HFP) を同じコンテナ ID を持つ別々のエンドポイントとして公開し、
+//! バッテリー残量はエンドポイントではなく、同じコンテナに属する Bluetooth のデバイスノードのプロパティに載ります。
+
+use std::collections::HashMap;
+
+use windows::core::{w, GUID, HSTRING, PCWSTR};
+use windows::Win32::Devices::DeviceAndDriverInstallation::{
+    SetupDiDestroyDeviceInfoList, SetupDiEnumDeviceInfo, SetupDiGetClassDevsW, SetupDiGetDevicePropertyW, DIGCF_ALLCLASSES,
+    DIGCF_PRESENT, HDEVINFO, SP_DEVINFO_DATA,
+};
+use windows::Win32::Devices::Properties::{
+    DEVPKEY_Device_ContainerId, DEVPKEY_Device_EnumeratorName, DEVPROPKEY, DEVPROPTYPE, DEVPROP_TYPE_BYTE, DEVPROP_TYPE_GUID,
+};
+use windows::Win32::Media::Audio::{eRender, IMMDeviceEnumerator, DEVICE_STATE_ACTIVE};
+use windows::Win32::System::Com::StructuredStorage::PropVariantToGUID;
+use windows::Win32::System::Com::{CoTaskMemFree, STGM_READ};
+use windows::Win32::UI::Shell::PropertiesSystem::{IPropertyStore, PROPERTYKEY};
+
+use crate::{AudioError, BluetoothProfile};
+
+/// 機器が報告するバッテリー残量 (DEVPKEY_Bluetooth_Battery)。SDK のヘッダーにないので値を直接書く
+const DEVPKEY_BLUETOOTH_BATTERY: DEVPROPKEY =
+    DEVPROPKEY { fmtid: GUID::from_u128(0x104ea319_6ee2_4701_bd47_8ddbf425bbe5), pid: 2 };
+
+/// バッテリー残量を載せるデバイスノードの列挙子。クラシックの機器は BTHENUM・BTHHFENUM、LE Audio の機器は BTHLEDEVICE
+const BATTERY_ENUMERATORS: [PCWSTR; 3] = [w!("BTHENUM"), w!("BTHHFENUM"), w!("BTHLEDEVICE")];
+
+fn property_key(key: DEVPROPKEY) -> PROPERTYKEY {
+    PROPERTYKEY { fmtid: key.fmtid, pid: key.pid }
+}
+
+/// エンドポイントの列挙子の名前から、それが公開しているプロファイルを判別します。Bluetooth でなければ `None`
+pub fn profile(store: &IPropertyStore) -> Option<BluetoothProfile> {
+    let enumerator = unsafe { store.GetValue(&property_key(DEVPKEY_Device_EnumeratorName)) }.ok()?.to_string();
+    match enumerator.to_uppercase().as_str() {
+        "BTHENUM" => Some(BluetoothProfile::A2dp),
+        "BTHHFENUM" => Some(BluetoothProfile::HandsFree),
+        _ => None,
+    }
+}
+
+/// エンドポイントが属する物理的な機器の ID
+pub fn container_id(store: &IPropertyStore) -> Option<GUID> {
+    unsafe {
+        let value = store.GetValue(&property_key(DEVPKEY_Device_ContainerId)).ok()?;
+        PropVariantToGUID(&value).ok()
+    }
+}
+
+/// デバイスノードのプロパティを、型が `expected` のときだけ読みます。
+unsafe fn device_property<T: Copy + Default>(set: HDEVINFO, data: &SP_DEVINFO_DATA, key: &DEVPROPKEY, expected: DEVPROPTYPE) -> Option<T> {
+    let mut kind = DEVPROPTYPE::default();
+    let mut value = T::default();
+    let buffer = std::slice::from_raw_parts_mut(&mut value as *mut T as *mut u8, std::mem::size_of::<T>());
+    SetupDiGetDevicePropertyW(set, data, key, &mut kind, Some(buffer), None, 0).ok()?;
+    (kind == expected).then_some(value)
+}
+
+/// 接続中の Bluetooth のデバイスノードから、コンテナ ID ごとのバッテリー残量 (0〜100) を集めます。
+pub fn batteries() -> HashMap<GUID, u8> {
+    let mut batteries = HashMap::new();
+    for enumerator in BATTERY_ENUMERATORS {
+        unsafe {
+            let Ok(set) = SetupDiGetClassDevsW(None, enumerator, None, DIGCF_ALLCLASSES | DIGCF_PRESENT) else { continue };
+            let mut data = SP_DEVINFO_DATA { cbSize: std::mem::size_of::<SP_DEVINFO_DATA>() as u32, ..Default::default() };
+            let mut index = 0;
+            while SetupDiEnumDeviceInfo(set, index, &mut data).is_ok() {
+                index += 1;
+                let Some(percent) = device_property::<u8>(set, &data, &DEVPKEY_BLUETOOTH_BATTERY, DEVPROP_TYPE_BYTE) else { continue };
+                let Some(container) = device_property::<GUID>(set, &data, &DEVPKEY_Device_ContainerId, DEVPROP_TYPE_GUID) else { continue };
+                batteries.insert(container, percent.min(100));
+            }
+            let _ = SetupDiDestroyDeviceInfoList(set);
+        }
+    }
+    batteries
+}
+
+/// `device_id` と同じ機器で、`profile` を公開している出力エンドポイントの ID を探します。
+pub unsafe fn endpoint_for_profile(
+    enumerator: &IMMDeviceEnumerator,
+    device_id: &str,
+    profile: BluetoothProfile,
+) -> std::result::Result<String, AudioError> {
+    let store = enumerator.GetDevice(&HSTRING::from(device_id))?.OpenPropertyStore(STGM_READ)?;
+    let not_bluetooth = || AudioError::Backend(format!("Not a Bluetooth device: {}", device_id));
+    if self::profile(&store).ok_or_else(not_bluetooth)? == profile {
+        return Ok(device_id.to_string());
+    }
+    let container = container_id(&store).ok_or_else(not_bluetooth)?;
+    let collection = enumerator.EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE)?;
+    for i in 0..collection.GetCount()? {
+        let device = collection.Item(i)?;
+        let Ok(store) = device.OpenPropertyStore(STGM_READ) else { continue };
+        if self::profile(&store) == Some(profile) && container_id(&store) == Some(container) {
+            let id_pwstr = device.GetId()?;
+            let id = id_pwstr.to_string().unwrap_or_default();
+            CoTaskMemFree(Some(id_pwstr.as_ptr() as _));
+            return Ok(id);
+        }
+    }
+    Err(AudioError::Backend(format!("{} does not offer the {:?} profile", device_id, profile)))
+}
@@ -0,0 +1,134 @@
+//! アプリの音のブースト。ミラーと同じくプロセスループバックでアプリの音を取り込み、[`BoostLimiter`] で作った
+//! 足りない分を、アプリと同じ出力デバイスに重ねて流します。重ねる音は元の音よりわずかに遅れるので、
+//! 遅れを増やさないよう、出力しきれない分は待たずに捨てます。
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+
+use windows::core::Result;
+use windows::Win32::Foundation::WAIT_OBJECT_0;
+use windows::Win32::System::Threading::WaitForSingleObject;
+
+use super::com;
+use super::mirror::{Streams, WAIT_TIMEOUT_MS};
+use crate::boost::BoostLimiter;
+use crate::{AudioError, BoostRequest, MixerEvent, MixerEventSink};
+
+/// 動いているブースト。Drop で止め、スレッドの終了を待ちます。
+pub struct Boost {
+    pub request: BoostRequest,
+    /// `f32` のビット列
+    gain_db: Arc<AtomicU32>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Boost {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Boost {
+    /// `device_id` はアプリがいま鳴らしている出力デバイス。取り込みと出力を開けたかどうかが分かるまで待ちます。
+    pub fn start(request: BoostRequest, device_id: String, sink: Arc<dyn MixerEventSink>) -> std::result::Result<Self, AudioError> {
+        request.validate()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let gain_db = Arc::new(AtomicU32::new(request.gain_db.to_bits()));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread = {
+            let (pid, stop, gain_db) = (request.pid, stop.clone(), gain_db.clone());
+            thread::Builder::new()
+                .name("app-boost".into())
+                .spawn(move || boost_thread(pid, device_id, gain_db, stop, ready_tx, sink))
+                .map_err(|e| AudioError::Backend(format!("Failed to start boost thread: {}", e)))?
+        };
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self { request, gain_db, stop, thread: Some(thread) }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err(AudioError::Backend("Boost thread exited unexpectedly".to_string())),
+        }
+    }
+
+    pub fn set_gain(&mut self, gain_db: f32) {
+        self.request.gain_db = gain_db;
+        self.gain_db.store(gain_db.to_bits(), Ordering::Relaxed);
+    }
+
+    /// アプリが終了したか失敗して、スレッドが止まっている
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(|t| t.is_finished())
+    }
+}
+
+fn boost_thread(
+    pid: u32,
+    device_id: String,
+    gain_db: Arc<AtomicU32>,
+    stop: Arc<AtomicBool>,
+    ready: mpsc::Sender<std::result::Result<(), AudioError>>,
+    sink: Arc<dyn MixerEventSink>,
+) {
+    let _com = com::init_mta();
+    let streams = match unsafe { Streams::open(pid, &device_id) } {
+        Ok(streams) if streams.format.float && streams.format.container_bits == 32 => streams,
+        Ok(_) => {
+            let _ = ready.send(Err(AudioError::Backend("Boost needs a 32-bit float mix format".to_string())));
+            return;
+        }
+        Err(e) => {
+            let _ = ready.send(Err(e.into()));
+            return;
+        }
+    };
+    let _ = ready.send(Ok(()));
+    let result = unsafe { run(&streams, &gain_db, &stop) };
+    sink.emit(MixerEvent::BoostStopped { pid, error: result.err().map(|e| e.to_string()) });
+}
+
+/// 停止を要求されるかアプリが終了するまで、取り込んだ音から足りない分を作って出力します。
+unsafe fn run(streams: &Streams, gain_db: &AtomicU32, stop: &AtomicBool) -> Result<()> {
+    let frame_bytes = usize::from(streams.format.channels) * 4;
+    let render_frames = streams.render_client.GetBufferSize()? as usize;
+    let mut current_gain = f32::from_bits(gain_db.load(Ordering::Relaxed));
+    let mut limiter = BoostLimiter::new(current_gain, streams.format.sample_rate, streams.format.channels);
+    let mut queue: VecDeque<u8> = VecDeque::new();
+    streams.capture_client.Start()?;
+    streams.render_client.Start()?;
+    while !stop.load(Ordering::Relaxed) {
+        WaitForSingleObject(streams.event, WAIT_TIMEOUT_MS);
+        if WaitForSingleObject(streams.process, 0) == WAIT_OBJECT_0 {
+            break;
+        }
+        streams.read(&mut queue, frame_bytes)?;
+        let gain = f32::from_bits(gain_db.load(Ordering::Relaxed));
+        if gain != current_gain {
+            limiter.set_gain_db(gain);
+            current_gain = gain;
+        }
+
+        let free = render_frames - streams.render_client.GetCurrentPadding()? as usize;
+        let queued = queue.len() / frame_bytes;
+        if queued > free {
+            queue.drain(..(queued - free) * frame_bytes);
+        }
+        let frames = queue.len() / frame_bytes;
+        if frames > 0 {
+            let bytes: Vec<u8> = queue.drain(..frames * frame_bytes).collect();
+            let mut samples: Vec<f32> = bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+            limiter.process(&mut samples);
+            let buffer = streams.render.GetBuffer(frames as u32)?;
+            std::slice::from_raw_parts_mut(buffer as *mut f32, samples.len()).copy_from_slice(&samples);
+            streams.render.ReleaseBuffer(frames as u32, 0)?;
+        }
+    }
+    Ok(())
+}
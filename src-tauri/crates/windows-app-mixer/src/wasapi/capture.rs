@@ -0,0 +1,454 @@
+//! マイクの入力レベルの計測と録音。エンドポイントのメーターは誰かが録音していないと 0 のままなので、
+//! 計測している間は共有モードのキャプチャーストリームを開いておきます（データは読み捨てます）。
+//! 録音は専用のスレッドで、モノラルの 32bit float に OS が変換したものを受け取ります。
+//! システムの音のファイルへの録音と LAN への配信も同じように、ループバックで取り込んだものを [`FileWriter`] や [`StreamServer`] に渡します。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use windows::core::{Result, HSTRING};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::Media::Audio::Endpoints::IAudioMeterInformation;
+use windows::Win32::Media::Audio::{
+    eCapture, eConsole, eRender, IAudioCaptureClient, IAudioClient, IMMDeviceEnumerator, MMDeviceEnumerator, AUDCLNT_BUFFERFLAGS_SILENT,
+    AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM, AUDCLNT_STREAMFLAGS_EVENTCALLBACK, AUDCLNT_STREAMFLAGS_LOOPBACK,
+    AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY,
+};
+use windows::Win32::System::Com::{CoCreateInstance, CoTaskMemFree, CLSCTX_ALL};
+use windows::Win32::System::Threading::{CreateEventW, OpenProcess, WaitForSingleObject, PROCESS_SYNCHRONIZE};
+
+use super::{com, format, mirror};
+use crate::capture_file::FileWriter;
+use crate::stream_server::StreamServer;
+use crate::{AudioError, CaptureSource, DeviceFormat, FileCaptureRequest, MixerEvent, MixerEventSink, NetworkStream, NetworkStreamRequest, Recording};
+
+/// キャプチャーストリームのバッファの長さ (100ns 単位)
+const CAPTURE_BUFFER_DURATION: i64 = 1_000_000;
+/// 録音を待つ最長時間 (ms)。停止要求に気付くための上限
+const WAIT_TIMEOUT_MS: u32 = 200;
+/// ループバックでパケットが途切れてから、無音を書き足し始めるまでの余裕 (ms)
+const SILENCE_SLACK_MS: u64 = 100;
+
+pub struct InputMeter {
+    /// 計測しているデバイスの ID
+    pub device_id: String,
+    /// 既定の入力デバイスを追いかける（既定が変わったら開き直す）
+    pub follows_default: bool,
+    meter: IAudioMeterInformation,
+    client: IAudioClient,
+}
+
+impl Drop for InputMeter {
+    fn drop(&mut self) {
+        unsafe { let _ = self.client.Stop(); }
+    }
+}
+
+impl InputMeter {
+    /// `device_id` を省略すると既定の入力デバイスを計測します。
+    pub unsafe fn open(enumerator: &IMMDeviceEnumerator, device_id: Option<&str>) -> Result<Self> {
+        let device = match device_id {
+            Some(id) => enumerator.GetDevice(&HSTRING::from(id))?,
+            None => enumerator.GetDefaultAudioEndpoint(eCapture, eConsole)?,
+        };
+        let id_pwstr = device.GetId()?;
+        let id = id_pwstr.to_string().unwrap_or_default();
+        CoTaskMemFree(Some(id_pwstr.as_ptr() as _));
+
+        let meter: IAudioMeterInformation = device.Activate(CLSCTX_ALL, None)?;
+        let client: IAudioClient = device.Activate(CLSCTX_ALL, None)?;
+        let (mix, mix_mask) = format::mix_format(&client)?;
+        let wave = format::wave_format(&mix, mix_mask);
+        client.Initialize(AUDCLNT_SHAREMODE_SHARED, 0, CAPTURE_BUFFER_DURATION, 0, &wave.Format, None)?;
+        client.Start()?;
+        Ok(Self { device_id: id, follows_default: device_id.is_none(), meter, client })
+    }
+
+    pub unsafe fn peak(&self) -> Result<f32> {
+        self.meter.GetPeakValue()
+    }
+}
+
+/// 録音中のマイク。[`Recorder::finish`] か Drop で止め、スレッドの終了を待ちます。
+pub struct Recorder {
+    samples: Arc<Mutex<Vec<f32>>>,
+    sample_rate: u32,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Recorder {
+    /// 録音のスレッドを起動し、デバイスを開けたかどうかが分かるまで待ちます。`device_id` を省略すると既定の入力デバイスを録ります。
+    pub fn start(device_id: Option<String>, max_ms: u32) -> std::result::Result<Self, AudioError> {
+        let samples = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread = {
+            let (samples, stop) = (samples.clone(), stop.clone());
+            thread::Builder::new()
+                .name("microphone-recorder".into())
+                .spawn(move || record_thread(device_id, max_ms, samples, stop, ready_tx))
+                .map_err(|e| AudioError::Backend(format!("Failed to start recording thread: {}", e)))?
+        };
+        match ready_rx.recv() {
+            Ok(Ok(sample_rate)) => Ok(Self { samples, sample_rate, stop, thread: Some(thread) }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err(AudioError::Backend("Recording thread exited unexpectedly".to_string())),
+        }
+    }
+
+    /// 録音を止めて、録った音を返します。
+    pub fn finish(mut self) -> Recording {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let samples = self.samples.lock().map(|mut s| std::mem::take(&mut *s)).unwrap_or_default();
+        Recording { samples, sample_rate: self.sample_rate }
+    }
+}
+
+fn record_thread(
+    device_id: Option<String>,
+    max_ms: u32,
+    samples: Arc<Mutex<Vec<f32>>>,
+    stop: Arc<AtomicBool>,
+    ready: mpsc::Sender<std::result::Result<u32, AudioError>>,
+) {
+    let _com = com::init_mta();
+    let stream = match unsafe { CaptureStream::open(device_id.as_deref()) } {
+        Ok(stream) => stream,
+        Err(e) => {
+            let _ = ready.send(Err(e.into()));
+            return;
+        }
+    };
+    let sample_rate = stream.sample_rate;
+    let _ = ready.send(Ok(sample_rate));
+    let max_frames = (u64::from(max_ms) * u64::from(sample_rate) / 1000) as usize;
+    if let Err(e) = unsafe { stream.run(&samples, max_frames, &stop) } {
+        eprintln!("Recording stopped: {}", e);
+    }
+}
+
+struct CaptureStream {
+    client: IAudioClient,
+    capture: IAudioCaptureClient,
+    event: HANDLE,
+    sample_rate: u32,
+}
+
+impl Drop for CaptureStream {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.client.Stop();
+            let _ = CloseHandle(self.event);
+        }
+    }
+}
+
+impl CaptureStream {
+    unsafe fn open(device_id: Option<&str>) -> Result<Self> {
+        let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let device = match device_id {
+            Some(id) => enumerator.GetDevice(&HSTRING::from(id))?,
+            None => enumerator.GetDefaultAudioEndpoint(eCapture, eConsole)?,
+        };
+        let client: IAudioClient = device.Activate(CLSCTX_ALL, None)?;
+        let (mix, _) = format::mix_format(&client)?;
+        // サンプルレートはそのままに、モノラルの float へは OS に変換させる
+        let mono = DeviceFormat { bits_per_sample: 32, container_bits: 32, float: true, channels: 1, ..mix };
+        let wave = format::wave_format(&mono, 0);
+        let flags = AUDCLNT_STREAMFLAGS_EVENTCALLBACK | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM | AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY;
+        client.Initialize(AUDCLNT_SHAREMODE_SHARED, flags, CAPTURE_BUFFER_DURATION, 0, &wave.Format, None)?;
+        let event = CreateEventW(None, false, false, None)?;
+        let stream = Self { capture: client.GetService()?, client, event, sample_rate: mix.sample_rate };
+        stream.client.SetEventHandle(stream.event)?;
+        Ok(stream)
+    }
+
+    /// 停止を要求されるまで、届いたパケットを `samples` に足します。`max_frames` を超えた分は捨てます。
+    unsafe fn run(&self, samples: &Mutex<Vec<f32>>, max_frames: usize, stop: &AtomicBool) -> Result<()> {
+        self.client.Start()?;
+        while !stop.load(Ordering::Relaxed) {
+            WaitForSingleObject(self.event, WAIT_TIMEOUT_MS);
+            while self.capture.GetNextPacketSize()? > 0 {
+                let mut data = std::ptr::null_mut();
+                let mut frames = 0;
+                let mut flags = 0;
+                self.capture.GetBuffer(&mut data, &mut frames, &mut flags, None, None)?;
+                if let Ok(mut samples) = samples.lock() {
+                    let count = (frames as usize).min(max_frames.saturating_sub(samples.len()));
+                    if flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 != 0 || data.is_null() {
+                        samples.extend(std::iter::repeat_n(0.0, count));
+                    } else {
+                        samples.extend_from_slice(&std::slice::from_raw_parts(data as *const f32, frames as usize)[..count]);
+                    }
+                }
+                self.capture.ReleaseBuffer(frames)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// システムの音をファイルに録っている取り込み。[`FileCapture::finish`] か Drop で止め、スレッドの終了を待ちます。
+pub struct FileCapture {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<std::result::Result<Vec<String>, AudioError>>>,
+}
+
+impl Drop for FileCapture {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl FileCapture {
+    /// 取り込みのスレッドを起動し、取り込みと最初のファイルを開けたかどうかが分かるまで待ちます。
+    pub fn start(request: FileCaptureRequest, sink: Arc<dyn MixerEventSink>) -> std::result::Result<Self, AudioError> {
+        request.validate()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread = {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("file-capture".into())
+                .spawn(move || file_capture_thread(request, stop, ready_tx, sink))
+                .map_err(|e| AudioError::Backend(format!("Failed to start capture thread: {}", e)))?
+        };
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self { stop, thread: Some(thread) }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err(AudioError::Backend("Capture thread exited unexpectedly".to_string())),
+        }
+    }
+
+    /// 取り込みを止めてファイルを閉じ、作ったファイルを返します。
+    pub fn finish(mut self) -> std::result::Result<Vec<String>, AudioError> {
+        self.stop.store(true, Ordering::Relaxed);
+        match self.thread.take().map(|t| t.join()) {
+            Some(Ok(result)) => result,
+            _ => Err(AudioError::Backend("Capture thread exited unexpectedly".to_string())),
+        }
+    }
+}
+
+fn file_capture_thread(
+    request: FileCaptureRequest,
+    stop: Arc<AtomicBool>,
+    ready: mpsc::Sender<std::result::Result<(), AudioError>>,
+    sink: Arc<dyn MixerEventSink>,
+) -> std::result::Result<Vec<String>, AudioError> {
+    let _com = com::init_mta();
+    let opened = unsafe { LoopbackStream::open(&request.source, request.sample_rate(), request.channels) }
+        .map_err(AudioError::from)
+        .and_then(|stream| Ok((stream, FileWriter::create(request.clone())?)));
+    let (stream, mut writer) = match opened {
+        Ok(opened) => opened,
+        Err(e) => {
+            let _ = ready.send(Err(e.clone()));
+            return Err(e);
+        }
+    };
+    let _ = ready.send(Ok(()));
+    let result = unsafe { stream.run(request.sample_rate(), &stop, |samples| writer.write(samples)) };
+    let files = writer.files().to_vec();
+    let result = result.and_then(|()| writer.finish());
+    sink.emit(MixerEvent::FileCaptureStopped { files, error: result.as_ref().err().map(|e| e.to_string()) });
+    result
+}
+
+/// LAN に配信している取り込み。Drop で止め、スレッドの終了を待ってから待ち受けをやめます。
+pub struct NetworkCapture {
+    server: Arc<StreamServer>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for NetworkCapture {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl NetworkCapture {
+    /// ポートを開いてから取り込みのスレッドを起動し、取り込みを開けたかどうかが分かるまで待ちます。
+    pub fn start(request: NetworkStreamRequest, sink: Arc<dyn MixerEventSink>) -> std::result::Result<Self, AudioError> {
+        let server = Arc::new(StreamServer::start(request.clone())?);
+        let stop = Arc::new(AtomicBool::new(false));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread = {
+            let (server, stop) = (server.clone(), stop.clone());
+            thread::Builder::new()
+                .name("network-capture".into())
+                .spawn(move || network_capture_thread(request, server, stop, ready_tx, sink))
+                .map_err(|e| AudioError::Backend(format!("Failed to start capture thread: {}", e)))?
+        };
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self { server, stop, thread: Some(thread) }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err(AudioError::Backend("Capture thread exited unexpectedly".to_string())),
+        }
+    }
+
+    pub fn info(&self) -> NetworkStream {
+        self.server.info()
+    }
+
+    /// アプリが終了したか失敗して、スレッドが止まっている
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(|t| t.is_finished())
+    }
+}
+
+fn network_capture_thread(
+    request: NetworkStreamRequest,
+    server: Arc<StreamServer>,
+    stop: Arc<AtomicBool>,
+    ready: mpsc::Sender<std::result::Result<(), AudioError>>,
+    sink: Arc<dyn MixerEventSink>,
+) {
+    let _com = com::init_mta();
+    let stream = match unsafe { LoopbackStream::open(&request.source, request.sample_rate(), request.channels) } {
+        Ok(stream) => stream,
+        Err(e) => {
+            let _ = ready.send(Err(e.into()));
+            return;
+        }
+    };
+    let _ = ready.send(Ok(()));
+    let result = unsafe {
+        stream.run(request.sample_rate(), &stop, |samples| {
+            server.push(samples);
+            Ok(())
+        })
+    };
+    sink.emit(MixerEvent::NetworkStreamStopped { error: result.err().map(|e| e.to_string()) });
+}
+
+struct LoopbackStream {
+    client: IAudioClient,
+    capture: IAudioCaptureClient,
+    event: HANDLE,
+    /// アプリの音を録るときの、終了を待つためのプロセスハンドル
+    process: Option<HANDLE>,
+    channels: usize,
+}
+
+impl Drop for LoopbackStream {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.client.Stop();
+            let _ = CloseHandle(self.event);
+            if let Some(process) = self.process {
+                let _ = CloseHandle(process);
+            }
+        }
+    }
+}
+
+impl LoopbackStream {
+    /// 頼まれたサンプルレートとチャンネル数の 32bit float で取り込みます。変換は OS に任せます。
+    unsafe fn open(source: &CaptureSource, sample_rate: u32, channels: u16) -> Result<Self> {
+        let (client, process) = match source {
+            CaptureSource::Device(device_id) => {
+                let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+                let device = match device_id {
+                    Some(id) => enumerator.GetDevice(&HSTRING::from(id.as_str()))?,
+                    None => enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?,
+                };
+                (device.Activate::<IAudioClient>(CLSCTX_ALL, None)?, None)
+            }
+            CaptureSource::App(pid) => (mirror::activate_process_loopback(*pid)?, Some(OpenProcess(PROCESS_SYNCHRONIZE, false, *pid)?)),
+        };
+        let format = DeviceFormat { sample_rate, bits_per_sample: 32, container_bits: 32, float: true, channels };
+        let mask = if channels == 2 { format::STEREO_MASK } else { 0 };
+        let wave = format::wave_format(&format, mask);
+        let flags = AUDCLNT_STREAMFLAGS_LOOPBACK | AUDCLNT_STREAMFLAGS_EVENTCALLBACK | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM | AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY;
+        let opened = client.Initialize(AUDCLNT_SHAREMODE_SHARED, flags, CAPTURE_BUFFER_DURATION, 0, &wave.Format, None)
+            .and_then(|()| CreateEventW(None, false, false, None));
+        let event = match opened {
+            Ok(event) => event,
+            Err(e) => {
+                if let Some(process) = process {
+                    let _ = CloseHandle(process);
+                }
+                return Err(e);
+            }
+        };
+        let stream = Self { capture: client.GetService()?, client, event, process, channels: usize::from(channels) };
+        stream.client.SetEventHandle(stream.event)?;
+        Ok(stream)
+    }
+
+    /// 停止を要求されるかアプリが終了するまで、取り込んだ音を `write` に渡します。
+    /// ループバックは何も鳴っていない間パケットが届かないので、その間は経過時間の分だけ無音を足して長さを保ちます。
+    unsafe fn run(
+        &self,
+        sample_rate: u32,
+        stop: &AtomicBool,
+        mut write: impl FnMut(&[f32]) -> std::result::Result<(), AudioError>,
+    ) -> std::result::Result<(), AudioError> {
+        let started = Instant::now();
+        let slack = SILENCE_SLACK_MS * u64::from(sample_rate) / 1000;
+        let mut written: u64 = 0;
+        let mut samples = Vec::new();
+        self.client.Start()?;
+        while !stop.load(Ordering::Relaxed) {
+            WaitForSingleObject(self.event, WAIT_TIMEOUT_MS);
+            if self.process.is_some_and(|process| WaitForSingleObject(process, 0) == WAIT_OBJECT_0) {
+                break;
+            }
+            while self.capture.GetNextPacketSize()? > 0 {
+                let mut data = std::ptr::null_mut();
+                let mut frames = 0;
+                let mut flags = 0;
+                self.capture.GetBuffer(&mut data, &mut frames, &mut flags, None, None)?;
+                let len = frames as usize * self.channels;
+                if flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 != 0 || data.is_null() {
+                    samples.extend(std::iter::repeat_n(0.0, len));
+                } else {
+                    samples.extend_from_slice(std::slice::from_raw_parts(data as *const f32, len));
+                }
+                self.capture.ReleaseBuffer(frames)?;
+            }
+            if samples.is_empty() {
+                let expected = started.elapsed().as_millis() as u64 * u64::from(sample_rate) / 1000;
+                if expected > written + slack {
+                    samples.resize((expected - written) as usize * self.channels, 0.0);
+                }
+            }
+            written += (samples.len() / self.channels) as u64;
+            write(&samples)?;
+            samples.clear();
+        }
+        Ok(())
+    }
+}
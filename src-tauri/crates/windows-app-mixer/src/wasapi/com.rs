@@ -0,0 +1,21 @@
+use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED};
+
+/// `init_mta` が成功したときだけ、Drop で対になる `CoUninitialize` を呼びます。
+/// COM オブジェクトをすべて解放した後に drop されるよう、所有する構造体の最後のフィールドに置いてください。
+pub struct ComGuard {
+    initialized: bool,
+}
+
+impl Drop for ComGuard {
+    fn drop(&mut self) {
+        if self.initialized {
+            unsafe { CoUninitialize() };
+        }
+    }
+}
+
+/// オーディオ操作に必要な Multi-Threaded Apartment (MTA) を初期化します。
+pub fn init_mta() -> ComGuard {
+    let initialized = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) }.is_ok();
+    ComGuard { initialized }
+}
@@ -0,0 +1,76 @@
+//! 入力デバイスの音声処理 (APO) のうち、エコーキャンセル・ノイズ抑制・自動ゲイン調整の状態。
+//! `IAudioEffectsManager` は Windows 11 22H2 以降で、効果を公開する APO を使うデバイスでだけ取得できます。
+//! 効果はストリームの処理モードごとにあるため、通話アプリと同じ通信用のストリームを開いて調べます。
+
+use windows::core::{Result, GUID, HSTRING};
+use windows::Win32::Media::Audio::{
+    AudioCategory_Communications, AudioClientProperties, IAudioClient2, IAudioEffectsManager, IMMDeviceEnumerator,
+    AUDCLNT_SHAREMODE_SHARED, AUDIO_EFFECT, AUDIO_EFFECT_STATE_OFF, AUDIO_EFFECT_STATE_ON,
+};
+use windows::Win32::Media::KernelStreaming::{
+    AUDIO_EFFECT_TYPE_ACOUSTIC_ECHO_CANCELLATION, AUDIO_EFFECT_TYPE_AUTOMATIC_GAIN_CONTROL, AUDIO_EFFECT_TYPE_NOISE_SUPPRESSION,
+};
+use windows::Win32::System::Com::{CoTaskMemFree, CLSCTX_ALL};
+
+use super::format;
+use crate::{CaptureEffect, CaptureEffectKind};
+
+/// 効果を調べるだけのストリームのバッファの長さ (100ns 単位)
+const BUFFER_DURATION: i64 = 1_000_000;
+
+fn effect_id(kind: CaptureEffectKind) -> GUID {
+    match kind {
+        CaptureEffectKind::EchoCancellation => AUDIO_EFFECT_TYPE_ACOUSTIC_ECHO_CANCELLATION,
+        CaptureEffectKind::NoiseSuppression => AUDIO_EFFECT_TYPE_NOISE_SUPPRESSION,
+        CaptureEffectKind::AutomaticGainControl => AUDIO_EFFECT_TYPE_AUTOMATIC_GAIN_CONTROL,
+    }
+}
+
+fn effect_kind(id: GUID) -> Option<CaptureEffectKind> {
+    [CaptureEffectKind::EchoCancellation, CaptureEffectKind::NoiseSuppression, CaptureEffectKind::AutomaticGainControl]
+        .into_iter()
+        .find(|kind| effect_id(*kind) == id)
+}
+
+/// 通信用のキャプチャーストリームを初期化し、その効果の管理インターフェースを返します。
+/// ストリームは開始しないので、マイクの使用中表示は出ません。
+unsafe fn effects_manager(enumerator: &IMMDeviceEnumerator, device_id: &str) -> Result<IAudioEffectsManager> {
+    let device = enumerator.GetDevice(&HSTRING::from(device_id))?;
+    let client: IAudioClient2 = device.Activate(CLSCTX_ALL, None)?;
+    let properties = AudioClientProperties {
+        cbSize: std::mem::size_of::<AudioClientProperties>() as u32,
+        eCategory: AudioCategory_Communications,
+        ..Default::default()
+    };
+    client.SetClientProperties(&properties)?;
+    let (mix, mix_mask) = format::mix_format(&client)?;
+    let wave = format::wave_format(&mix, mix_mask);
+    client.Initialize(AUDCLNT_SHAREMODE_SHARED, 0, BUFFER_DURATION, 0, &wave.Format, None)?;
+    client.GetService()
+}
+
+pub unsafe fn list(enumerator: &IMMDeviceEnumerator, device_id: &str) -> Result<Vec<CaptureEffect>> {
+    let manager = effects_manager(enumerator, device_id)?;
+    let mut effects: *mut AUDIO_EFFECT = std::ptr::null_mut();
+    let mut count = 0u32;
+    manager.GetAudioEffects(&mut effects, &mut count)?;
+    if effects.is_null() {
+        return Ok(Vec::new());
+    }
+    let list = std::slice::from_raw_parts(effects, count as usize)
+        .iter()
+        .filter_map(|effect| Some(CaptureEffect {
+            kind: effect_kind(effect.id)?,
+            enabled: effect.state == AUDIO_EFFECT_STATE_ON,
+            can_toggle: effect.canSetState.as_bool(),
+        }))
+        .collect();
+    CoTaskMemFree(Some(effects as _));
+    Ok(list)
+}
+
+pub unsafe fn set(enumerator: &IMMDeviceEnumerator, device_id: &str, kind: CaptureEffectKind, enabled: bool) -> Result<()> {
+    let manager = effects_manager(enumerator, device_id)?;
+    let state = if enabled { AUDIO_EFFECT_STATE_ON } else { AUDIO_EFFECT_STATE_OFF };
+    manager.SetAudioEffectState(effect_id(kind), state)
+}
@@ -0,0 +1,157 @@
+use windows::core::{GUID, PCWSTR};
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::Media::Audio::{
+    AudioSessionDisconnectReason, AudioSessionState, AudioSessionStateActive,
+    AudioSessionStateExpired, EDataFlow, ERole, IAudioSessionControl,
+    IAudioSessionEvents, IAudioSessionEvents_Impl, IAudioSessionNotification,
+    IAudioSessionNotification_Impl, IMMNotificationClient, IMMNotificationClient_Impl, DEVICE_STATE,
+};
+use windows::Win32::Media::Audio::Endpoints::{
+    IAudioEndpointVolumeCallback, IAudioEndpointVolumeCallback_Impl,
+};
+use windows::Win32::Media::Audio::AUDIO_VOLUME_NOTIFICATION_DATA;
+use windows::Win32::UI::Shell::PropertiesSystem::PROPERTYKEY;
+
+use crate::{AudioNotification, ChangeOrigin, Notifier, SessionState};
+
+/// このミキサーが音量を変えるときに渡すイベントコンテキスト。通知でこれが返ってきたら自分の変更です
+pub const EVENT_CONTEXT: GUID = GUID::from_u128(0x5f0c2a4e_8d1b_4c3f_9a6e_2b7d1e4f8c90);
+
+fn change_origin(context: *const GUID) -> ChangeOrigin {
+    if !context.is_null() && unsafe { *context } == EVENT_CONTEXT {
+        ChangeOrigin::Mixer
+    } else {
+        ChangeOrigin::External
+    }
+}
+
+pub fn session_state(state: AudioSessionState) -> SessionState {
+    if state == AudioSessionStateActive {
+        SessionState::Active
+    } else if state == AudioSessionStateExpired {
+        SessionState::Expired
+    } else {
+        SessionState::Inactive
+    }
+}
+
+#[windows_core::implement(IAudioSessionEvents)]
+pub struct SessionEventsListener {
+    pub notifier: Notifier,
+    pub session_key: String,
+}
+
+impl IAudioSessionEvents_Impl for SessionEventsListener_Impl {
+    fn OnDisplayNameChanged(&self, _newdisplayname: &PCWSTR, _eventcontext: *const GUID) -> windows::core::Result<()> {
+        Ok(())
+    }
+    fn OnIconPathChanged(&self, _newiconpath: &PCWSTR, _eventcontext: *const GUID) -> windows::core::Result<()> {
+        Ok(())
+    }
+    fn OnSimpleVolumeChanged(&self, newvolume: f32, newmute: BOOL, eventcontext: *const GUID) -> windows::core::Result<()> {
+        self.notifier.notify(AudioNotification::VolumeChanged {
+            session_key: self.session_key.clone(),
+            volume: newvolume,
+            muted: newmute.as_bool(),
+            origin: change_origin(eventcontext),
+        });
+        Ok(())
+    }
+    fn OnChannelVolumeChanged(&self, _channelcount: u32, _newchannelvolumearray: *const f32, _changedchannel: u32, _eventcontext: *const GUID) -> windows::core::Result<()> {
+        Ok(())
+    }
+    fn OnGroupingParamChanged(&self, _newgroupingparam: *const GUID, _eventcontext: *const GUID) -> windows::core::Result<()> {
+        Ok(())
+    }
+    fn OnStateChanged(&self, newstate: AudioSessionState) -> windows::core::Result<()> {
+        self.notifier.notify(AudioNotification::StateChanged {
+            session_key: self.session_key.clone(),
+            state: session_state(newstate),
+        });
+        Ok(())
+    }
+    fn OnSessionDisconnected(&self, _disconnectreason: AudioSessionDisconnectReason) -> windows::core::Result<()> {
+        self.notifier.notify(AudioNotification::SessionDisconnected {
+            session_key: self.session_key.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// デバイスごとのセッション生成通知
+#[windows_core::implement(IAudioSessionNotification)]
+pub struct SessionCreatedListener {
+    pub notifier: Notifier,
+}
+
+impl IAudioSessionNotification_Impl for SessionCreatedListener_Impl {
+    fn OnSessionCreated(&self, _newsession: Option<&IAudioSessionControl>) -> windows::core::Result<()> {
+        self.notifier.notify(AudioNotification::SessionCreated);
+        Ok(())
+    }
+}
+
+/// エンドポイントの追加・削除・既定デバイス変更の通知
+#[windows_core::implement(IMMNotificationClient)]
+pub struct DeviceEventsListener {
+    pub notifier: Notifier,
+}
+
+impl IMMNotificationClient_Impl for DeviceEventsListener_Impl {
+    fn OnDeviceStateChanged(&self, _pwstrdeviceid: &PCWSTR, _dwnewstate: DEVICE_STATE) -> windows::core::Result<()> {
+        self.notifier.notify(AudioNotification::DevicesChanged);
+        Ok(())
+    }
+    fn OnDeviceAdded(&self, _pwstrdeviceid: &PCWSTR) -> windows::core::Result<()> {
+        self.notifier.notify(AudioNotification::DevicesChanged);
+        Ok(())
+    }
+    fn OnDeviceRemoved(&self, _pwstrdeviceid: &PCWSTR) -> windows::core::Result<()> {
+        self.notifier.notify(AudioNotification::DevicesChanged);
+        Ok(())
+    }
+    fn OnDefaultDeviceChanged(&self, _flow: EDataFlow, _role: ERole, _pwstrdefaultdeviceid: &PCWSTR) -> windows::core::Result<()> {
+        self.notifier.notify(AudioNotification::DevicesChanged);
+        Ok(())
+    }
+    fn OnPropertyValueChanged(&self, _pwstrdeviceid: &PCWSTR, _key: &PROPERTYKEY) -> windows::core::Result<()> {
+        Ok(())
+    }
+}
+
+/// 既定の出力デバイス全体の音量・ミュートの変化の通知
+#[windows_core::implement(IAudioEndpointVolumeCallback)]
+pub struct MasterVolumeListener {
+    pub notifier: Notifier,
+}
+
+impl IAudioEndpointVolumeCallback_Impl for MasterVolumeListener_Impl {
+    fn OnNotify(&self, pnotify: *mut AUDIO_VOLUME_NOTIFICATION_DATA) -> windows::core::Result<()> {
+        let origin = if pnotify.is_null() {
+            ChangeOrigin::Unknown
+        } else {
+            change_origin(unsafe { &(*pnotify).guidEventContext })
+        };
+        self.notifier.notify(AudioNotification::MasterVolumeChanged { origin });
+        Ok(())
+    }
+}
+
+/// 監視を頼まれた出力デバイス（既定かどうかを問わない）の音量・ミュートの変化の通知
+#[windows_core::implement(IAudioEndpointVolumeCallback)]
+pub struct DeviceVolumeListener {
+    pub notifier: Notifier,
+    pub device_id: String,
+}
+
+impl IAudioEndpointVolumeCallback_Impl for DeviceVolumeListener_Impl {
+    fn OnNotify(&self, pnotify: *mut AUDIO_VOLUME_NOTIFICATION_DATA) -> windows::core::Result<()> {
+        let origin = if pnotify.is_null() {
+            ChangeOrigin::Unknown
+        } else {
+            change_origin(unsafe { &(*pnotify).guidEventContext })
+        };
+        self.notifier.notify(AudioNotification::DeviceVolumeChanged { device_id: self.device_id.clone(), origin });
+        Ok(())
+    }
+}
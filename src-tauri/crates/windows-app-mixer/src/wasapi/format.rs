@@ -0,0 +1,82 @@
+//! 排他モードで使えるサンプルフォーマットの調査と、`WAVEFORMATEXTENSIBLE` の組み立て。
+
+use windows::core::Result;
+use windows::Win32::Media::Audio::{IAudioClient, IMMDevice, AUDCLNT_SHAREMODE_EXCLUSIVE, WAVEFORMATEX, WAVEFORMATEXTENSIBLE, WAVEFORMATEXTENSIBLE_0};
+use windows::Win32::Media::KernelStreaming::{KSDATAFORMAT_SUBTYPE_PCM, WAVE_FORMAT_EXTENSIBLE};
+use windows::Win32::Media::Multimedia::{KSDATAFORMAT_SUBTYPE_IEEE_FLOAT, WAVE_FORMAT_IEEE_FLOAT};
+use windows::Win32::System::Com::{CoTaskMemFree, CLSCTX_ALL};
+
+use crate::DeviceFormat;
+
+/// 調べるサンプルレート
+const SAMPLE_RATES: [u32; 8] = [44_100, 48_000, 88_200, 96_000, 176_400, 192_000, 352_800, 384_000];
+
+/// 調べるサンプル形式（有効ビット数、コンテナのビット数、浮動小数点か）
+const SAMPLE_TYPES: [(u16, u16, bool); 5] = [(16, 16, false), (24, 24, false), (24, 32, false), (32, 32, false), (32, 32, true)];
+
+/// ステレオの既定のチャンネルマスク (SPEAKER_FRONT_LEFT | SPEAKER_FRONT_RIGHT)
+pub(super) const STEREO_MASK: u32 = 0x3;
+
+/// デバイスの共有モードのミックスフォーマットと同じチャンネル構成で、排他モードで受け付けられるフォーマットを列挙します。
+pub fn probe(device: &IMMDevice) -> Result<Vec<DeviceFormat>> {
+    unsafe {
+        let client: IAudioClient = device.Activate(CLSCTX_ALL, None)?;
+        let (mix, channel_mask) = mix_format(&client)?;
+        let channels = mix.channels;
+        let mut supported = Vec::new();
+        for sample_rate in SAMPLE_RATES {
+            for (bits_per_sample, container_bits, float) in SAMPLE_TYPES {
+                let format = DeviceFormat { sample_rate, bits_per_sample, container_bits, float, channels };
+                let wave = wave_format(&format, channel_mask);
+                let hr = client.IsFormatSupported(AUDCLNT_SHAREMODE_EXCLUSIVE, &wave.Format, None);
+                // 排他モードでは S_OK だけが「そのまま使える」を意味する
+                if hr.0 == 0 {
+                    supported.push(format);
+                }
+            }
+        }
+        Ok(supported)
+    }
+}
+
+/// 共有モードのミックスフォーマットとチャンネルマスク
+pub unsafe fn mix_format(client: &IAudioClient) -> Result<(DeviceFormat, u32)> {
+    let mix = client.GetMixFormat()?;
+    let base = &*mix;
+    let mut format = DeviceFormat {
+        sample_rate: base.nSamplesPerSec,
+        bits_per_sample: base.wBitsPerSample,
+        container_bits: base.wBitsPerSample,
+        float: u32::from(base.wFormatTag) == WAVE_FORMAT_IEEE_FLOAT,
+        channels: base.nChannels,
+    };
+    let mut mask = STEREO_MASK;
+    if u32::from(base.wFormatTag) == WAVE_FORMAT_EXTENSIBLE {
+        // パックされた構造体なので、フィールドは参照せずにコピーして読む
+        let extensible = std::ptr::read_unaligned(mix as *const WAVEFORMATEXTENSIBLE);
+        let sub_format = extensible.SubFormat;
+        mask = extensible.dwChannelMask;
+        format.bits_per_sample = extensible.Samples.wValidBitsPerSample;
+        format.float = sub_format == KSDATAFORMAT_SUBTYPE_IEEE_FLOAT;
+    }
+    CoTaskMemFree(Some(mix as _));
+    Ok((format, mask))
+}
+
+pub fn wave_format(format: &DeviceFormat, channel_mask: u32) -> WAVEFORMATEXTENSIBLE {
+    let block_align = format.channels * format.container_bits / 8;
+    WAVEFORMATEXTENSIBLE {
+        Format: WAVEFORMATEX {
+            wFormatTag: WAVE_FORMAT_EXTENSIBLE as u16,
+            nChannels: format.channels,
+            nSamplesPerSec: format.sample_rate,
+            nAvgBytesPerSec: format.sample_rate * u32::from(block_align),
+            nBlockAlign: block_align,
+            wBitsPerSample: format.container_bits,
+            cbSize: (std::mem::size_of::<WAVEFORMATEXTENSIBLE>() - std::mem::size_of::<WAVEFORMATEX>()) as u16,
+        },
+        Samples: WAVEFORMATEXTENSIBLE_0 { wValidBitsPerSample: format.bits_per_sample },
+        dwChannelMask: channel_mask,
+        SubFormat: if format.float { KSDATAFORMAT_SUBTYPE_IEEE_FLOAT } else { KSDATAFORMAT_SUBTYPE_PCM },
+    }
+}
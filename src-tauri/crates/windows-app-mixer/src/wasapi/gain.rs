@@ -0,0 +1,91 @@
+//! 入力デバイスの音量とマイクブースト。ブーストはエンドポイントの音量とは別に、
+//! アダプターのトポロジーにあるボリュームのパーツとして公開されています。
+
+use std::collections::HashSet;
+
+use windows::core::{Interface, Result, GUID};
+use windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolume;
+use windows::Win32::Media::Audio::{IAudioVolumeLevel, IDeviceTopology, IMMDevice, IPart, Subunit};
+use windows::Win32::System::Com::{CoTaskMemFree, CLSCTX_ALL};
+
+use crate::GainLevel;
+
+/// たどるパーツの上限。ループのある（壊れた）トポロジーでも止まるようにする
+const MAX_PARTS: usize = 64;
+
+pub unsafe fn volume_level(device: &IMMDevice) -> Result<GainLevel> {
+    endpoint_level(&device.Activate(CLSCTX_ALL, None)?)
+}
+
+pub unsafe fn set_volume_level(device: &IMMDevice, db: f32) -> Result<()> {
+    set_endpoint_level(&device.Activate(CLSCTX_ALL, None)?, db, std::ptr::null())
+}
+
+pub unsafe fn endpoint_level(endpoint: &IAudioEndpointVolume) -> Result<GainLevel> {
+    let (mut min_db, mut max_db, mut step_db) = (0.0, 0.0, 0.0);
+    endpoint.GetVolumeRange(&mut min_db, &mut max_db, &mut step_db)?;
+    Ok(GainLevel { db: endpoint.GetMasterVolumeLevel()?, min_db, max_db, step_db })
+}
+
+/// `context` は音量の変化の通知に載る値で、自分の変更かどうかの判別に使います。
+pub unsafe fn set_endpoint_level(endpoint: &IAudioEndpointVolume, db: f32, context: *const GUID) -> Result<()> {
+    let (mut min_db, mut max_db, mut step_db) = (0.0, 0.0, 0.0);
+    endpoint.GetVolumeRange(&mut min_db, &mut max_db, &mut step_db)?;
+    endpoint.SetMasterVolumeLevel(db.clamp(min_db, max_db), context)
+}
+
+/// マイクブーストのパーツ。見つからなければ `None` です。
+pub unsafe fn boost_control(device: &IMMDevice) -> Result<Option<IAudioVolumeLevel>> {
+    let topology: IDeviceTopology = device.Activate(CLSCTX_ALL, None)?;
+    // エンドポイント側のコネクタの接続先から、マイクの端子に向かって上流をたどる
+    let connector = topology.GetConnector(0)?;
+    let start: IPart = connector.GetConnectedTo()?.cast()?;
+    let mut pending = vec![start];
+    let mut visited = HashSet::new();
+    while let Some(part) = pending.pop() {
+        if visited.len() >= MAX_PARTS || !visited.insert(part.GetGlobalId().map(|id| pwstr_string(id)).unwrap_or_default()) {
+            continue;
+        }
+        if part.GetPartType()? == Subunit && is_boost(&part) {
+            let mut raw = std::ptr::null_mut();
+            if part.Activate(CLSCTX_ALL.0, &IAudioVolumeLevel::IID, Some(&mut raw)).is_ok() {
+                return Ok(Some(IAudioVolumeLevel::from_raw(raw)));
+            }
+        }
+        // 上流のないパーツ（端子）ではエラーになる
+        if let Ok(incoming) = part.EnumPartsIncoming() {
+            for i in 0..incoming.GetCount()? {
+                pending.push(incoming.GetPart(i)?);
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// ドライバーはブーストのパーツに "Microphone Boost" や "Mic Boost" といった名前を付けます。
+unsafe fn is_boost(part: &IPart) -> bool {
+    part.GetName().map(|name| pwstr_string(name).to_lowercase().contains("boost")).unwrap_or(false)
+}
+
+unsafe fn pwstr_string(value: windows::core::PWSTR) -> String {
+    let text = value.to_string().unwrap_or_default();
+    CoTaskMemFree(Some(value.as_ptr() as _));
+    text
+}
+
+/// チャンネルごとのレベルのうち、最初のチャンネルの値を代表として返します。
+pub unsafe fn boost_level(boost: &IAudioVolumeLevel) -> Result<GainLevel> {
+    let (mut min_db, mut max_db, mut step_db) = (0.0, 0.0, 0.0);
+    boost.GetLevelRange(0, &mut min_db, &mut max_db, &mut step_db)?;
+    Ok(GainLevel { db: boost.GetLevel(0)?, min_db, max_db, step_db })
+}
+
+pub unsafe fn set_boost_level(boost: &IAudioVolumeLevel, db: f32) -> Result<()> {
+    let range = boost_level(boost)?;
+    // ブーストは 10dB 刻みなど段階的なことが多く、刻みに合わない値は受け付けられない
+    let mut db = db.clamp(range.min_db, range.max_db);
+    if range.step_db > 0.0 {
+        db = range.min_db + ((db - range.min_db) / range.step_db).round() * range.step_db;
+    }
+    boost.SetLevelUniform(db.min(range.max_db), None)
+}
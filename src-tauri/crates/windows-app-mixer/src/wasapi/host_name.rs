@@ -0,0 +1,44 @@
+//! スクリプトや Java のアプリを動かす汎用のホストの表示名。実行ファイルの製品名は「Java(TM) Platform SE binary」
+//! のようにホストのものになってしまうので、代わりにメインウィンドウのタイトル（「Minecraft」など）を使います。
+
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM, TRUE};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetWindow, GetWindowTextW, GetWindowThreadProcessId, IsWindowVisible, GW_OWNER,
+};
+
+/// 大文字の実行ファイル名
+const GENERIC_HOSTS: [&str; 8] = [
+    "JAVAW.EXE", "JAVA.EXE", "PYTHONW.EXE", "PYTHON.EXE", "WSCRIPT.EXE", "CSCRIPT.EXE", "MSHTA.EXE", "RUNDLL32.EXE",
+];
+
+pub fn is_generic_host(exe_key: &str) -> bool {
+    GENERIC_HOSTS.contains(&exe_key)
+}
+
+unsafe extern "system" fn find_main_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let (pid, title) = &mut *(lparam.0 as *mut (u32, Option<String>));
+    let mut owner = 0;
+    GetWindowThreadProcessId(hwnd, Some(&mut owner));
+    // 所有者のいない表示されたウィンドウ（ダイアログやツールウィンドウではないもの）をメインウィンドウとみなす
+    if owner != *pid || !IsWindowVisible(hwnd).as_bool() || GetWindow(hwnd, GW_OWNER).is_ok_and(|o| !o.is_invalid()) {
+        return TRUE;
+    }
+    let mut buffer = [0u16; 256];
+    let len = GetWindowTextW(hwnd, &mut buffer);
+    let text = String::from_utf16_lossy(&buffer[..len.max(0) as usize]);
+    if text.trim().is_empty() {
+        return TRUE;
+    }
+    *title = Some(text.trim().to_string());
+    BOOL(0)
+}
+
+/// プロセスのメインウィンドウのタイトル。ウィンドウを持たなければ `None` です。
+pub fn main_window_title(pid: u32) -> Option<String> {
+    let mut state = (pid, None);
+    unsafe {
+        // 見つけたところで列挙を止めるので、エラーが返る
+        let _ = EnumWindows(Some(find_main_window), LPARAM(&mut state as *mut (u32, Option<String>) as isize));
+    }
+    state.1
+}
@@ -0,0 +1,149 @@
+use std::path::Path;
+use windows::Win32::Foundation::{MAX_PATH, HANDLE};
+use windows::Win32::System::Threading::{OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION};
+use windows::Win32::UI::Shell::{SHDefExtractIconW, SHGetFileInfoW, SHGFI_ICON, SHGFI_LARGEICON, SHFILEINFOW};
+use windows::Win32::UI::WindowsAndMessaging::{DestroyIcon, HICON, GetIconInfo, ICONINFO};
+use windows::Win32::Graphics::Gdi::{
+    GetDC, ReleaseDC, CreateCompatibleDC, SelectObject, DeleteDC, 
+    DeleteObject, GetDIBits, GetObjectW, BITMAP, BITMAPINFO, BITMAPINFOHEADER, DIB_RGB_COLORS, RGBQUAD
+};
+use base64::{engine::general_purpose, Engine as _};
+use image::imageops::{self, FilterType};
+use image::{RgbaImage, ImageFormat};
+use std::io::Cursor;
+
+use crate::ICON_SIZE;
+
+/// プロセスの実行ファイルのフルパスを取得します。
+pub fn get_process_path(pid: u32) -> Option<String> {
+    unsafe {
+        let handle: HANDLE = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; MAX_PATH as usize * 2];
+        let mut len = (MAX_PATH * 2) as u32;
+        let res = QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, windows::core::PWSTR(buffer.as_mut_ptr()), &mut len);
+        let _ = windows::Win32::Foundation::CloseHandle(handle);
+        if res.is_err() { return None; }
+        String::from_utf16(&buffer[..len as usize]).ok()
+    }
+}
+
+pub fn exe_name_from_path(path: &str) -> Option<String> {
+    Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|s| s.to_string().to_uppercase())
+}
+
+/// 実行ファイルのアイコンを `ICON_SIZE` 四方の PNG (base64) で返します。
+/// 実行ファイルに含まれる画像から大きさの合うものを選び、なければシェルの 32px のアイコンを拡大します。
+pub fn extract_icon_base64(exe_path: &str) -> Option<String> {
+    unsafe {
+        let path_wstr: Vec<u16> = exe_path.encode_utf16().chain(std::iter::once(0)).collect();
+
+        // 下位ワードが大きいアイコンの大きさ。256px などの画像を持つ exe では、縮小したものが返る
+        let mut large = HICON::default();
+        let res = SHDefExtractIconW(windows::core::PCWSTR(path_wstr.as_ptr()), 0, 0, Some(&mut large), None, ICON_SIZE);
+        if res.is_ok() && !large.is_invalid() {
+            let base64 = hicon_to_base64(large);
+            let _ = DestroyIcon(large);
+            if base64.is_some() {
+                return base64;
+            }
+        }
+
+        let mut shfi: SHFILEINFOW = std::mem::zeroed();
+        let res = SHGetFileInfoW(
+            windows::core::PCWSTR(path_wstr.as_ptr()),
+            windows::Win32::Storage::FileSystem::FILE_FLAGS_AND_ATTRIBUTES(0),
+            Some(&mut shfi),
+            std::mem::size_of::<SHFILEINFOW>() as u32,
+            SHGFI_ICON | SHGFI_LARGEICON
+        );
+
+        if res == 0 || shfi.hIcon.is_invalid() { return None; }
+
+        let base64 = hicon_to_base64(shfi.hIcon);
+        let _ = DestroyIcon(shfi.hIcon);
+        base64
+    }
+}
+
+/// BGRA の画素にマスクからアルファを設定します。マスクの白い画素は透明、黒い画素は不透明です。
+/// マスクを読めなければすべて不透明にします。
+fn apply_mask(pixels: &mut [u8], mask: Option<&[u8]>) {
+    match mask {
+        Some(mask) => {
+            for (pixel, bit) in pixels.chunks_exact_mut(4).zip(mask.chunks_exact(4)) {
+                pixel[3] = if bit[0] == 0 { 255 } else { 0 };
+            }
+        }
+        None => pixels.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 255),
+    }
+}
+
+#[allow(non_snake_case)]
+unsafe fn hicon_to_base64(hicon: HICON) -> Option<String> {
+    let mut icon_info = ICONINFO::default();
+    if GetIconInfo(hicon, &mut icon_info).is_err() { return None; }
+
+    let hdc_screen = GetDC(None);
+    let hdc_mem = CreateCompatibleDC(hdc_screen);
+    let h_old_obj = SelectObject(hdc_mem, icon_info.hbmColor);
+
+    let mut bitmap = BITMAP::default();
+    GetObjectW(icon_info.hbmColor, std::mem::size_of::<BITMAP>() as i32, Some(&mut bitmap as *mut _ as *mut _));
+    let width = bitmap.bmWidth.max(0) as u32;
+    let height = bitmap.bmHeight.max(0) as u32;
+
+    let mut bmi = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width as i32,
+            biHeight: -(height as i32), // Top-down
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: 0, // BI_RGB
+            ..Default::default()
+        },
+        bmiColors: [RGBQUAD::default(); 1],
+    };
+
+    let mut buffer: Vec<u8> = vec![0; (width * height * 4) as usize];
+    let lines = GetDIBits(hdc_mem, icon_info.hbmColor, 0, height, Some(buffer.as_mut_ptr() as *mut _), &mut bmi, DIB_RGB_COLORS);
+
+    // 古い形式のアイコンは色にアルファを持たず（すべて 0）、透明な部分をマスクで表す。
+    // そのまま使うと全体が透明か黒い四角になるので、マスクからアルファを作る
+    if lines != 0 && buffer.chunks_exact(4).all(|p| p[3] == 0) {
+        let mut mask: Vec<u8> = vec![0; buffer.len()];
+        let mut mask_bmi = bmi;
+        let mask_lines = GetDIBits(hdc_mem, icon_info.hbmMask, 0, height, Some(mask.as_mut_ptr() as *mut _), &mut mask_bmi, DIB_RGB_COLORS);
+        apply_mask(&mut buffer, (mask_lines != 0).then_some(mask.as_slice()));
+    }
+
+    // Cleanup
+    SelectObject(hdc_mem, h_old_obj);
+    let _ = DeleteDC(hdc_mem);
+    ReleaseDC(None, hdc_screen);
+    let _ = DeleteObject(icon_info.hbmColor);
+    let _ = DeleteObject(icon_info.hbmMask);
+
+    if lines == 0 { return None; }
+
+    // BGRA to RGBA
+    for i in (0..buffer.len()).step_by(4) {
+        let b = buffer[i];
+        let r = buffer[i + 2];
+        buffer[i] = r;
+        buffer[i + 2] = b;
+    }
+
+    let mut img = RgbaImage::from_raw(width, height, buffer)?;
+    if width != ICON_SIZE || height != ICON_SIZE {
+        img = imageops::resize(&img, ICON_SIZE, ICON_SIZE, FilterType::Lanczos3);
+    }
+    let mut image_data = Vec::new();
+    let mut cursor = Cursor::new(&mut image_data);
+    img.write_to(&mut cursor, ImageFormat::Png).ok()?;
+
+    Some(general_purpose::STANDARD.encode(image_data))
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use super::icon;
+use crate::ICON_SIZE;
+
+/// 抽出方法を変えたら上げる。マスクから透明な部分を作る前のアイコンは黒い四角が残っている
+const FORMAT_VERSION: u32 = 2;
+
+/// 実行ファイルのパスと更新日時をキーにしたアイコンキャッシュ（メモリ + ディスク）。
+/// 同じバージョンの exe からは一度しかアイコンを抽出しません。
+#[derive(Debug, Default)]
+pub struct IconCache {
+    dir: Option<PathBuf>,
+    entries: HashMap<String, CachedIcon>,
+}
+
+/// `IconCache::lookup` の結果
+#[derive(Debug)]
+pub enum Lookup {
+    /// 抽出済み。`None` は exe にアイコンがなかった
+    Cached(Option<String>),
+    Missing(IconJob),
+}
+
+/// まだメモリにないアイコンの読み込み。ディスクキャッシュになければ exe から抽出して書き込みます。
+#[derive(Debug)]
+pub struct IconJob {
+    pub exe_path: String,
+    pub mtime: u64,
+    disk_path: Option<PathBuf>,
+}
+
+impl IconJob {
+    pub fn load(&self) -> Option<String> {
+        self.disk_path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .or_else(|| {
+                let extracted = icon::extract_icon_base64(&self.exe_path)?;
+                if let Some(p) = &self.disk_path {
+                    if let Some(dir) = p.parent() {
+                        let _ = fs::create_dir_all(dir);
+                    }
+                    let _ = fs::write(p, &extracted);
+                }
+                Some(extracted)
+            })
+    }
+}
+
+#[derive(Debug)]
+struct CachedIcon {
+    mtime: u64,
+    icon_base64: Option<String>,
+}
+
+impl IconCache {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self { dir, entries: HashMap::new() }
+    }
+
+    /// メモリにあるアイコンを返します。なければ、別スレッドで `IconJob::load` するための情報を返します。
+    pub fn lookup(&mut self, exe_path: &str) -> Lookup {
+        let key = exe_path.to_lowercase();
+        let mtime = file_mtime(exe_path);
+
+        if let Some(entry) = self.entries.get(&key) {
+            if entry.mtime == mtime {
+                return Lookup::Cached(entry.icon_base64.clone());
+            }
+            // exe が更新されたので古いディスクキャッシュを削除
+            if let Some(old) = self.disk_path(&key, entry.mtime) {
+                let _ = fs::remove_file(old);
+            }
+        }
+
+        Lookup::Missing(IconJob { exe_path: exe_path.to_string(), mtime, disk_path: self.disk_path(&key, mtime) })
+    }
+
+    /// `IconJob::load` の結果をメモリに記録します。
+    pub fn insert(&mut self, exe_path: &str, mtime: u64, icon_base64: Option<String>) {
+        self.entries.insert(exe_path.to_lowercase(), CachedIcon { mtime, icon_base64 });
+    }
+
+    pub fn get_or_extract(&mut self, exe_path: &str) -> Option<String> {
+        match self.lookup(exe_path) {
+            Lookup::Cached(icon_base64) => icon_base64,
+            Lookup::Missing(job) => {
+                let icon_base64 = job.load();
+                self.insert(exe_path, job.mtime, icon_base64.clone());
+                icon_base64
+            }
+        }
+    }
+
+    /// メモリとディスクのキャッシュをすべて削除します。次の取得で抽出し直します。
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        let Some(entries) = self.dir.as_ref().and_then(|dir| fs::read_dir(dir).ok()) else { return };
+        for entry in entries.flatten() {
+            if entry.path().extension().is_some_and(|e| e == "b64") {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    fn disk_path(&self, key: &str, mtime: u64) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        // 大きさや抽出方法を変えたら以前のキャッシュを使わないよう、キーに含める
+        Some(dir.join(format!("{:016x}.b64", fnv1a(format!("{}|{}|{}|{}", key, mtime, ICON_SIZE, FORMAT_VERSION).as_bytes()))))
+    }
+}
+
+pub fn file_mtime(path: &str) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// ファイル名用の安定したハッシュ（std の Hasher は実装が変わり得るため使わない）
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
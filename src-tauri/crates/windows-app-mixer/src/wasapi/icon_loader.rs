@@ -0,0 +1,56 @@
+//! exe からのアイコンの抽出を行うスレッドプール。セッション一覧はアイコンを待たずに返し、
+//! 抽出できたものから `AudioNotification::IconLoaded` でオーディオスレッドへ届けます。
+
+use std::collections::HashSet;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::com;
+use super::icon_cache::IconJob;
+use crate::{AudioNotification, Notifier};
+
+/// 抽出するスレッドの数。ディスクとシェルの呼び出しが主なので、少なくて足りる
+const THREADS: usize = 2;
+
+/// 抽出を頼まれているアイコン。同じ exe を重ねて頼まないよう、届くまで覚えておきます。
+/// drop するとキューが閉じ、スレッドは途中のアイコンを抽出し終えてから終了します。
+pub struct IconLoader {
+    tx: Option<Sender<IconJob>>,
+    pending: HashSet<String>,
+}
+
+impl IconLoader {
+    pub fn new(notifier: Notifier) -> Self {
+        let (tx, rx) = mpsc::channel::<IconJob>();
+        let rx = Arc::new(Mutex::new(rx));
+        let mut started = false;
+        for i in 0..THREADS {
+            let rx = rx.clone();
+            let notifier = notifier.clone();
+            let spawned = thread::Builder::new().name(format!("icon-loader-{}", i)).spawn(move || {
+                let _com = com::init_mta();
+                // 待っている間だけロックを持つ
+                while let Some(job) = rx.lock().ok().and_then(|rx| rx.recv().ok()) {
+                    let icon_base64 = job.load();
+                    notifier.notify(AudioNotification::IconLoaded { exe_path: job.exe_path, mtime: job.mtime, icon_base64 });
+                }
+            });
+            started |= spawned.is_ok();
+        }
+        // スレッドを作れなければ、アイコンなしで動かす
+        Self { tx: started.then_some(tx), pending: HashSet::new() }
+    }
+
+    pub fn queue(&mut self, job: IconJob) {
+        let Some(tx) = &self.tx else { return };
+        if self.pending.insert(job.exe_path.to_lowercase()) && tx.send(job).is_err() {
+            self.tx = None;
+        }
+    }
+
+    /// `IconLoaded` を受け取ったら呼びます。
+    pub fn finish(&mut self, exe_path: &str) {
+        self.pending.remove(&exe_path.to_lowercase());
+    }
+}
@@ -0,0 +1,35 @@
+//! デバイストポロジーをたどり、エンドポイントの端子（ジャック）に何かが接続されているかを調べます。
+
+use windows::core::{Interface, Result};
+use windows::Win32::Media::Audio::{IDeviceTopology, IMMDevice, IPart};
+use windows::Win32::Media::KernelStreaming::{IKsJackDescription, KSJACK_DESCRIPTION};
+use windows::Win32::System::Com::CLSCTX_ALL;
+
+/// 端子のいずれかに接続があれば `true` を返します。
+/// 端子の状態を報告しないデバイス（USB や Bluetooth、HDMI の一部など）では `None` です。
+pub fn jack_connected(device: &IMMDevice) -> Option<bool> {
+    unsafe { query_jacks(device).ok().flatten() }
+}
+
+unsafe fn query_jacks(device: &IMMDevice) -> Result<Option<bool>> {
+    let topology: IDeviceTopology = device.Activate(CLSCTX_ALL, None)?;
+    // エンドポイント側のコネクタの接続先が、アダプター側の端子を表すパーツ
+    let connector = topology.GetConnector(0)?;
+    let part: IPart = connector.GetConnectedTo()?.cast()?;
+
+    let mut raw = std::ptr::null_mut();
+    part.Activate(CLSCTX_ALL.0, &IKsJackDescription::IID, Some(&mut raw))?;
+    let jacks = IKsJackDescription::from_raw(raw);
+
+    let count = jacks.GetJackCount()?;
+    if count == 0 {
+        return Ok(None);
+    }
+    let mut connected = false;
+    for i in 0..count {
+        let mut description: KSJACK_DESCRIPTION = std::mem::zeroed();
+        jacks.GetJackDescription(i, &mut description)?;
+        connected |= description.IsConnected.as_bool();
+    }
+    Ok(Some(connected))
+}
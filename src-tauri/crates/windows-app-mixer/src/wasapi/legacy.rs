@@ -0,0 +1,72 @@
+//! セッションを持たない（または「システム音」にまとめられる）古い API での再生の推測。
+//! DirectSound は dsound.dll を、MME の waveOut は wdmaud.drv を読み込むので、それらを読み込んでいて
+//! 自分のセッションを持たないプロセスを探します。音を出す古いゲームはウィンドウを持つので、
+//! 全プロセスのモジュールを調べる代わりに、表示されているウィンドウを持つプロセスだけを調べます。
+//! 開けないプロセス（ほかのユーザーや管理者のもの）は調べられません。
+
+use std::collections::HashSet;
+
+use windows::core::Result;
+use windows::Win32::Foundation::{CloseHandle, BOOL, HWND, LPARAM, TRUE};
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, Module32FirstW, Module32NextW, MODULEENTRY32W, TH32CS_SNAPMODULE, TH32CS_SNAPMODULE32,
+};
+use windows::Win32::UI::WindowsAndMessaging::{EnumWindows, GetWindowThreadProcessId, IsWindowVisible};
+
+use crate::LegacyAudioApi;
+
+unsafe extern "system" fn collect_pid(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let pids = &mut *(lparam.0 as *mut HashSet<u32>);
+    if IsWindowVisible(hwnd).as_bool() {
+        let mut pid = 0;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        pids.insert(pid);
+    }
+    TRUE
+}
+
+/// 表示されているウィンドウを持つプロセス
+unsafe fn windowed_processes() -> Result<HashSet<u32>> {
+    let mut pids = HashSet::new();
+    EnumWindows(Some(collect_pid), LPARAM(&mut pids as *mut HashSet<u32> as isize))?;
+    Ok(pids)
+}
+
+fn module_name(entry: &MODULEENTRY32W) -> String {
+    let len = entry.szModule.iter().position(|c| *c == 0).unwrap_or(entry.szModule.len());
+    String::from_utf16_lossy(&entry.szModule[..len])
+}
+
+/// プロセスの実行ファイル名と、読み込んでいる古い再生 API。どちらも読み込んでいなければ `None` です。
+unsafe fn legacy_api(pid: u32) -> Option<(String, LegacyAudioApi)> {
+    let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPMODULE | TH32CS_SNAPMODULE32, pid).ok()?;
+    let mut entry = MODULEENTRY32W { dwSize: std::mem::size_of::<MODULEENTRY32W>() as u32, ..Default::default() };
+    // 最初のモジュールは実行ファイル自身
+    let mut exe_name = None;
+    let mut api = None;
+    let mut next = Module32FirstW(snapshot, &mut entry);
+    while next.is_ok() {
+        let name = module_name(&entry);
+        match name.to_lowercase().as_str() {
+            "dsound.dll" => api = Some(LegacyAudioApi::DirectSound),
+            "wdmaud.drv" if api.is_none() => api = Some(LegacyAudioApi::Mme),
+            _ => {}
+        }
+        exe_name.get_or_insert(name);
+        next = Module32NextW(snapshot, &mut entry);
+    }
+    let _ = CloseHandle(snapshot);
+    Some((exe_name?, api?))
+}
+
+/// `with_sessions` 以外のプロセスから、古い再生 API を読み込んでいるものを探します。
+pub unsafe fn find(with_sessions: &HashSet<u32>) -> Result<Vec<(u32, String, LegacyAudioApi)>> {
+    let own_pid = std::process::id();
+    let mut found: Vec<(u32, String, LegacyAudioApi)> = windowed_processes()?
+        .into_iter()
+        .filter(|pid| *pid != 0 && *pid != own_pid && !with_sessions.contains(pid))
+        .filter_map(|pid| legacy_api(pid).map(|(exe_name, api)| (pid, exe_name, api)))
+        .collect();
+    found.sort_by_key(|(pid, _, _)| *pid);
+    Ok(found)
+}
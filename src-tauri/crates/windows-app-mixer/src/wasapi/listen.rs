@@ -0,0 +1,38 @@
+//! 入力デバイスの「このデバイスを聴く」。サウンドのコントロールパネルと同じく、録音エンドポイントの
+//! プロパティ（聴くかどうかと、聴く出力デバイス）を IPolicyConfig 経由で書き換えます。OS が音を流すので、
+//! このアプリのスレッドは使わず、閉じても続きます。
+
+use windows::core::{Result, GUID, HSTRING, PROPVARIANT};
+use windows::Win32::UI::Shell::PropertiesSystem::PROPERTYKEY;
+
+use super::policy_config::IPolicyConfig;
+
+/// 聴く出力デバイスの ID (VT_LPWSTR)。空なら既定の出力デバイス
+const PKEY_LISTEN_DEVICE: PROPERTYKEY = PROPERTYKEY { fmtid: GUID::from_u128(0x24dbb0fc_9311_4b3d_9cf0_18ff155639d4), pid: 0 };
+/// 聴くかどうか (VT_BOOL)
+const PKEY_LISTEN_ENABLED: PROPERTYKEY = PROPERTYKEY { fmtid: GUID::from_u128(0x24dbb0fc_9311_4b3d_9cf0_18ff155639d4), pid: 1 };
+/// PROPVARIANT の型 VT_LPWSTR
+const VT_LPWSTR: u16 = 31;
+
+/// VT_LPWSTR の PROPVARIANT と同じ配置。`windows::core::PROPVARIANT` には文字列を指すだけのものを作る手段がないので、
+/// 呼び出し側が持つ文字列を指すものを自前で組み立てる
+#[repr(C)]
+struct StringVariant {
+    vt: u16,
+    reserved: [u16; 3],
+    data: *const u16,
+    /// PROPVARIANT の大きさに合わせる
+    padding: usize,
+}
+
+/// `output_device_id` で `capture_device_id` を聴くようにします。`None` ならやめます。
+pub unsafe fn set(config: &IPolicyConfig, capture_device_id: &str, output_device_id: Option<&str>) -> Result<()> {
+    let capture_device_id = HSTRING::from(capture_device_id);
+    if let Some(output_device_id) = output_device_id {
+        let output_device_id = HSTRING::from(output_device_id);
+        let variant = StringVariant { vt: VT_LPWSTR, reserved: [0; 3], data: output_device_id.as_ptr(), padding: 0 };
+        config.set_property_value(&capture_device_id, &PKEY_LISTEN_DEVICE, &variant as *const StringVariant as *const PROPVARIANT)?;
+    }
+    let enabled = PROPVARIANT::from(output_device_id.is_some());
+    config.set_property_value(&capture_device_id, &PKEY_LISTEN_ENABLED, &enabled)
+}
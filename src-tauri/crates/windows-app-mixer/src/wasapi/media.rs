@@ -0,0 +1,176 @@
+//! OS のメディアコントロール（SMTC）。アプリが出している再生中の曲を読み、再生・一時停止・曲送りを送ります。
+//! SMTC のセッションはプロセスではなくアプリ ID で表されるので、実行ファイル名と突き合わせてオーディオセッションに結び付けます。
+
+use std::collections::HashMap;
+
+use windows::core::Result;
+use windows::Foundation::{EventRegistrationToken, TypedEventHandler};
+use windows::Media::Control::{
+    GlobalSystemMediaTransportControlsSession as Session, GlobalSystemMediaTransportControlsSessionManager as SessionManager,
+    GlobalSystemMediaTransportControlsSessionPlaybackStatus as Status,
+};
+
+use crate::{AudioNotification, MediaCommand, NowPlaying, Notifier, PlaybackStatus};
+
+/// 変化の通知を登録したセッションと、登録の解除に使うトークン（曲の変化・再生状態の変化）
+struct Subscription {
+    session: Session,
+    properties: EventRegistrationToken,
+    playback: EventRegistrationToken,
+}
+
+pub struct MediaSessions {
+    notifier: Notifier,
+    /// 古い Windows などで取得できなければ `None`。取得を試すのは最初の 1 回だけ
+    manager: Option<Option<(SessionManager, EventRegistrationToken)>>,
+    /// アプリ ID ごとの通知の登録
+    subscriptions: HashMap<String, Subscription>,
+    /// アプリ ID ごとの再生中の曲
+    now_playing: Vec<(String, NowPlaying)>,
+}
+
+impl Drop for MediaSessions {
+    fn drop(&mut self) {
+        for (_, subscription) in self.subscriptions.drain() {
+            unsubscribe(&subscription);
+        }
+        if let Some(Some((manager, token))) = self.manager.take() {
+            let _ = manager.RemoveSessionsChanged(token);
+        }
+    }
+}
+
+fn unsubscribe(subscription: &Subscription) {
+    let _ = subscription.session.RemoveMediaPropertiesChanged(subscription.properties);
+    let _ = subscription.session.RemovePlaybackInfoChanged(subscription.playback);
+}
+
+/// SMTC のアプリ ID が実行ファイルのものか。デスクトップアプリは "Spotify.exe" や "Chrome"、
+/// ストアアプリは "SpotifyAB.SpotifyMusic_zpdnekdrzrea0!Spotify" のような形をしています。
+fn matches(app_id: &str, exe_name: &str) -> bool {
+    let app_id = app_id.to_lowercase();
+    let exe_name = exe_name.to_lowercase();
+    let stem = exe_name.strip_suffix(".exe").unwrap_or(&exe_name);
+    if stem.is_empty() {
+        return false;
+    }
+    app_id == exe_name
+        || app_id == stem
+        || app_id.rsplit('\\').next() == Some(exe_name.as_str())
+        || app_id.split(['!', '_', '.']).any(|part| part == stem)
+}
+
+fn playback_status(status: Status) -> PlaybackStatus {
+    match status {
+        Status::Playing => PlaybackStatus::Playing,
+        Status::Paused => PlaybackStatus::Paused,
+        _ => PlaybackStatus::Stopped,
+    }
+}
+
+fn read(session: &Session) -> Result<NowPlaying> {
+    let properties = session.TryGetMediaPropertiesAsync()?.get()?;
+    let playback = session.GetPlaybackInfo()?;
+    let controls = playback.Controls()?;
+    Ok(NowPlaying {
+        title: properties.Title()?.to_string(),
+        artist: properties.Artist()?.to_string(),
+        album: properties.AlbumTitle()?.to_string(),
+        status: playback_status(playback.PlaybackStatus()?),
+        can_play: controls.IsPlayEnabled()?,
+        can_pause: controls.IsPauseEnabled()?,
+        can_next: controls.IsNextEnabled()?,
+        can_previous: controls.IsPreviousEnabled()?,
+    })
+}
+
+impl MediaSessions {
+    pub fn new(notifier: Notifier) -> Self {
+        Self { notifier, manager: None, subscriptions: HashMap::new(), now_playing: Vec::new() }
+    }
+
+    fn manager(&mut self) -> Option<&SessionManager> {
+        if self.manager.is_none() {
+            let notifier = self.notifier.clone();
+            let manager = (|| -> Result<(SessionManager, EventRegistrationToken)> {
+                let manager = SessionManager::RequestAsync()?.get()?;
+                let token = manager.SessionsChanged(&TypedEventHandler::new(move |_, _| {
+                    notifier.notify(AudioNotification::NowPlayingChanged);
+                    Ok(())
+                }))?;
+                Ok((manager, token))
+            })();
+            self.manager = Some(manager.ok());
+        }
+        self.manager.as_ref().and_then(|m| m.as_ref()).map(|(manager, _)| manager)
+    }
+
+    /// メディアコントロールに出ているアプリを読み直し、曲や再生状態の変化の通知を登録し直します。
+    pub fn refresh(&mut self) {
+        let Some(manager) = self.manager() else { return };
+        let sessions: Vec<Session> = manager.GetSessions().map(|s| s.into_iter().collect()).unwrap_or_default();
+        let mut now_playing = Vec::new();
+        let mut subscriptions = HashMap::new();
+        for session in sessions {
+            let Ok(app_id) = session.SourceAppUserModelId().map(|id| id.to_string()) else { continue };
+            if let Ok(info) = read(&session) {
+                now_playing.push((app_id.clone(), info));
+            }
+            let subscription = match self.subscriptions.remove(&app_id) {
+                Some(subscription) => subscription,
+                None => match self.subscribe(session) {
+                    Ok(subscription) => subscription,
+                    Err(_) => continue,
+                },
+            };
+            subscriptions.insert(app_id, subscription);
+        }
+        // メディアコントロールから消えたアプリの登録を解除する
+        for (_, subscription) in self.subscriptions.drain() {
+            unsubscribe(&subscription);
+        }
+        self.subscriptions = subscriptions;
+        self.now_playing = now_playing;
+    }
+
+    fn subscribe(&self, session: Session) -> Result<Subscription> {
+        let notifier = self.notifier.clone();
+        let properties = session.MediaPropertiesChanged(&TypedEventHandler::new(move |_, _| {
+            notifier.notify(AudioNotification::NowPlayingChanged);
+            Ok(())
+        }))?;
+        let notifier = self.notifier.clone();
+        let playback = session.PlaybackInfoChanged(&TypedEventHandler::new(move |_, _| {
+            notifier.notify(AudioNotification::NowPlayingChanged);
+            Ok(())
+        }));
+        match playback {
+            Ok(playback) => Ok(Subscription { session, properties, playback }),
+            Err(e) => {
+                let _ = session.RemoveMediaPropertiesChanged(properties);
+                Err(e)
+            }
+        }
+    }
+
+    /// 実行ファイルのアプリが出している再生中の曲
+    pub fn now_playing(&self, exe_name: &str) -> Option<NowPlaying> {
+        self.now_playing.iter().find(|(app_id, _)| matches(app_id, exe_name)).map(|(_, info)| info.clone())
+    }
+
+    /// 実行ファイルのアプリに操作を送ります。メディアコントロールに出ていなければ `Ok(false)` を返します。
+    pub fn send(&self, exe_name: &str, command: MediaCommand) -> Result<bool> {
+        let Some((_, subscription)) = self.subscriptions.iter().find(|(app_id, _)| matches(app_id, exe_name)) else {
+            return Ok(false);
+        };
+        let session = &subscription.session;
+        let operation = match command {
+            MediaCommand::Play => session.TryPlayAsync()?,
+            MediaCommand::Pause => session.TryPauseAsync()?,
+            MediaCommand::TogglePlayPause => session.TryTogglePlayPauseAsync()?,
+            MediaCommand::Next => session.TrySkipNextAsync()?,
+            MediaCommand::Previous => session.TrySkipPreviousAsync()?,
+        };
+        operation.get()
+    }
+}
@@ -0,0 +1,274 @@
+//! アプリの音のミラー。プロセスループバック（Windows 10 2004 以降）でアプリと子プロセスの音だけを取り込み、
+//! 遅延を挟んで別の出力デバイスに流します。取り込みは出力先のミックスフォーマットで行うため、変換は OS に任せます。
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+
+use windows::core::{implement, Interface, Result, HRESULT, HSTRING, PROPVARIANT};
+use windows::Win32::Foundation::{CloseHandle, E_NOINTERFACE, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::Media::Audio::{
+    ActivateAudioInterfaceAsync, IActivateAudioInterfaceAsyncOperation, IActivateAudioInterfaceCompletionHandler,
+    IActivateAudioInterfaceCompletionHandler_Impl, IAudioCaptureClient, IAudioClient, IAudioRenderClient, IMMDeviceEnumerator,
+    MMDeviceEnumerator, AUDCLNT_BUFFERFLAGS_SILENT, AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM,
+    AUDCLNT_STREAMFLAGS_EVENTCALLBACK, AUDCLNT_STREAMFLAGS_LOOPBACK, AUDIOCLIENT_ACTIVATION_PARAMS, AUDIOCLIENT_ACTIVATION_PARAMS_0,
+    AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK, AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS, PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE,
+    VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+};
+use windows::Win32::System::Com::{CoCreateInstance, IAgileObject, IAgileObject_Impl, CLSCTX_ALL};
+use windows::Win32::System::Threading::{CreateEventW, OpenProcess, SetEvent, WaitForSingleObject, PROCESS_SYNCHRONIZE};
+
+use super::{com, format};
+use crate::{AudioError, DeviceFormat, MirrorRequest, MixerEvent, MixerEventSink};
+
+/// 取り込み・出力のバッファの長さ (100ns 単位、100ms)
+const BUFFER_DURATION: i64 = 1_000_000;
+/// 取り込みを待つ最長時間 (ms)。停止要求やプロセスの終了に気付くための上限
+pub(super) const WAIT_TIMEOUT_MS: u32 = 200;
+/// 非同期のアクティベートを待つ最長時間 (ms)
+const ACTIVATE_TIMEOUT_MS: u32 = 5000;
+/// PROPVARIANT の型 VT_BLOB
+const VT_BLOB: u16 = 65;
+
+/// VT_BLOB の PROPVARIANT と同じ配置。`windows::core::PROPVARIANT` は Drop で中身を解放してしまうため、
+/// 呼び出し側が持つデータを指すものは自前で組み立てる
+#[repr(C)]
+struct BlobVariant {
+    vt: u16,
+    reserved: [u16; 3],
+    size: u32,
+    data: *const u8,
+}
+
+/// 動いているミラー。Drop で止め、スレッドの終了を待ちます。
+pub struct Mirror {
+    pub request: MirrorRequest,
+    delay_ms: Arc<AtomicU32>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Mirror {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Mirror {
+    /// ミラーのスレッドを起動し、取り込みと出力を開けたかどうかが分かるまで待ちます。
+    pub fn start(request: MirrorRequest, sink: Arc<dyn MixerEventSink>) -> std::result::Result<Self, AudioError> {
+        request.validate()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let delay_ms = Arc::new(AtomicU32::new(request.delay_ms));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread = {
+            let (request, stop, delay_ms) = (request.clone(), stop.clone(), delay_ms.clone());
+            thread::Builder::new()
+                .name("app-mirror".into())
+                .spawn(move || mirror_thread(request, delay_ms, stop, ready_tx, sink))
+                .map_err(|e| AudioError::Backend(format!("Failed to start mirror thread: {}", e)))?
+        };
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self { request, delay_ms, stop, thread: Some(thread) }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err(AudioError::Backend("Mirror thread exited unexpectedly".to_string())),
+        }
+    }
+
+    pub fn set_delay(&mut self, delay_ms: u32) {
+        self.request.delay_ms = delay_ms;
+        self.delay_ms.store(delay_ms, Ordering::Relaxed);
+    }
+
+    /// アプリが終了したか失敗して、スレッドが止まっている
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(|t| t.is_finished())
+    }
+}
+
+fn mirror_thread(
+    request: MirrorRequest,
+    delay_ms: Arc<AtomicU32>,
+    stop: Arc<AtomicBool>,
+    ready: mpsc::Sender<std::result::Result<(), AudioError>>,
+    sink: Arc<dyn MixerEventSink>,
+) {
+    let _com = com::init_mta();
+    let streams = match unsafe { Streams::open(request.pid, &request.device_id) } {
+        Ok(streams) => streams,
+        Err(e) => {
+            let _ = ready.send(Err(e.into()));
+            return;
+        }
+    };
+    let _ = ready.send(Ok(()));
+    let result = unsafe { streams.run(&delay_ms, &stop) };
+    sink.emit(MixerEvent::MirrorStopped { pid: request.pid, error: result.err().map(|e| e.to_string()) });
+}
+
+#[implement(IActivateAudioInterfaceCompletionHandler, IAgileObject)]
+struct ActivationHandler {
+    done: HANDLE,
+}
+
+impl IActivateAudioInterfaceCompletionHandler_Impl for ActivationHandler_Impl {
+    fn ActivateCompleted(&self, _operation: Option<&IActivateAudioInterfaceAsyncOperation>) -> Result<()> {
+        unsafe { SetEvent(self.done) }
+    }
+}
+
+impl IAgileObject_Impl for ActivationHandler_Impl {}
+
+/// `pid` とその子プロセスの音だけを取り込む `IAudioClient` を作ります。
+pub(super) unsafe fn activate_process_loopback(pid: u32) -> Result<IAudioClient> {
+    let params = AUDIOCLIENT_ACTIVATION_PARAMS {
+        ActivationType: AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK,
+        Anonymous: AUDIOCLIENT_ACTIVATION_PARAMS_0 {
+            ProcessLoopbackParams: AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS {
+                TargetProcessId: pid,
+                ProcessLoopbackMode: PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE,
+            },
+        },
+    };
+    let variant = BlobVariant {
+        vt: VT_BLOB,
+        reserved: [0; 3],
+        size: std::mem::size_of::<AUDIOCLIENT_ACTIVATION_PARAMS>() as u32,
+        data: &params as *const _ as *const u8,
+    };
+    let done = CreateEventW(None, false, false, None)?;
+    let handler: IActivateAudioInterfaceCompletionHandler = ActivationHandler { done }.into();
+    let result = ActivateAudioInterfaceAsync(
+        VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+        &IAudioClient::IID,
+        Some(&variant as *const BlobVariant as *const PROPVARIANT),
+        &handler,
+    ).and_then(|operation| {
+        WaitForSingleObject(done, ACTIVATE_TIMEOUT_MS);
+        let mut hr = HRESULT(0);
+        let mut activated = None;
+        operation.GetActivateResult(&mut hr, &mut activated)?;
+        hr.ok()?;
+        activated.ok_or_else(|| windows::core::Error::from(E_NOINTERFACE))?.cast()
+    });
+    let _ = CloseHandle(done);
+    result
+}
+
+/// アプリの音の取り込みと、出力デバイスへの出力。ブーストでも使う
+pub(super) struct Streams {
+    pub(super) capture_client: IAudioClient,
+    capture: IAudioCaptureClient,
+    pub(super) render_client: IAudioClient,
+    pub(super) render: IAudioRenderClient,
+    pub(super) event: HANDLE,
+    /// 終了を待つためのアプリのプロセスハンドル
+    pub(super) process: HANDLE,
+    pub(super) format: DeviceFormat,
+}
+
+impl Drop for Streams {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.capture_client.Stop();
+            let _ = self.render_client.Stop();
+            let _ = CloseHandle(self.event);
+            let _ = CloseHandle(self.process);
+        }
+    }
+}
+
+impl Streams {
+    pub(super) unsafe fn open(pid: u32, device_id: &str) -> Result<Self> {
+        let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let device = enumerator.GetDevice(&HSTRING::from(device_id))?;
+        let render_client: IAudioClient = device.Activate(CLSCTX_ALL, None)?;
+        let (format, mask) = format::mix_format(&render_client)?;
+        let wave = format::wave_format(&format, mask);
+        render_client.Initialize(AUDCLNT_SHAREMODE_SHARED, 0, BUFFER_DURATION, 0, &wave.Format, None)?;
+
+        // プロセスループバックはミックスフォーマットを返さないので、出力先のフォーマットで取り込む
+        let capture_client = activate_process_loopback(pid)?;
+        capture_client.Initialize(
+            AUDCLNT_SHAREMODE_SHARED,
+            AUDCLNT_STREAMFLAGS_LOOPBACK | AUDCLNT_STREAMFLAGS_EVENTCALLBACK | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM,
+            BUFFER_DURATION,
+            0,
+            &wave.Format,
+            None,
+        )?;
+        let process = OpenProcess(PROCESS_SYNCHRONIZE, false, pid)?;
+        let event = CreateEventW(None, false, false, None)?;
+        let streams = Self {
+            capture: capture_client.GetService()?,
+            render: render_client.GetService()?,
+            capture_client,
+            render_client,
+            event,
+            process,
+            format,
+        };
+        streams.capture_client.SetEventHandle(streams.event)?;
+        Ok(streams)
+    }
+
+    /// 停止を要求されるかアプリが終了するまで、取り込んだ音を遅延分ためてから出力します。
+    unsafe fn run(&self, delay_ms: &AtomicU32, stop: &AtomicBool) -> Result<()> {
+        let frame_bytes = usize::from(self.format.channels) * usize::from(self.format.container_bits / 8);
+        let render_frames = self.render_client.GetBufferSize()? as usize;
+        let mut queue: VecDeque<u8> = VecDeque::new();
+        self.capture_client.Start()?;
+        self.render_client.Start()?;
+        while !stop.load(Ordering::Relaxed) {
+            WaitForSingleObject(self.event, WAIT_TIMEOUT_MS);
+            if WaitForSingleObject(self.process, 0) == WAIT_OBJECT_0 {
+                break;
+            }
+            self.read(&mut queue, frame_bytes)?;
+
+            let delay_frames = delay_ms.load(Ordering::Relaxed) as usize * self.format.sample_rate as usize / 1000;
+            let queued = queue.len() / frame_bytes;
+            // 遅延を縮めたときは、出力しきれない古い分を捨てて追いつく
+            if queued > delay_frames + render_frames {
+                queue.drain(..(queued - delay_frames - render_frames) * frame_bytes);
+            }
+            let queued = queue.len() / frame_bytes;
+            let free = render_frames - self.render_client.GetCurrentPadding()? as usize;
+            let frames = free.min(queued.saturating_sub(delay_frames));
+            if frames > 0 {
+                let buffer = self.render.GetBuffer(frames as u32)?;
+                let out = std::slice::from_raw_parts_mut(buffer, frames * frame_bytes);
+                for (dst, src) in out.iter_mut().zip(queue.drain(..frames * frame_bytes)) {
+                    *dst = src;
+                }
+                self.render.ReleaseBuffer(frames as u32, 0)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 届いているパケットをすべて `queue` に移します。無音のパケットは 0 で埋めます。
+    pub(super) unsafe fn read(&self, queue: &mut VecDeque<u8>, frame_bytes: usize) -> Result<()> {
+        while self.capture.GetNextPacketSize()? > 0 {
+            let mut data = std::ptr::null_mut();
+            let mut frames = 0;
+            let mut flags = 0;
+            self.capture.GetBuffer(&mut data, &mut frames, &mut flags, None, None)?;
+            let len = frames as usize * frame_bytes;
+            if flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 != 0 || data.is_null() {
+                queue.extend(std::iter::repeat_n(0, len));
+            } else {
+                queue.extend(std::slice::from_raw_parts(data, len));
+            }
+            self.capture.ReleaseBuffer(frames)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1439 @@
+mod bluetooth;
+mod boost;
+mod capture;
+mod com;
+mod effects;
+mod events;
+mod format;
+mod gain;
+mod host_name;
+mod icon;
+mod icon_cache;
+mod icon_loader;
+mod jack;
+mod legacy;
+mod listen;
+mod media;
+mod mirror;
+mod policy_config;
+mod policy_v2;
+mod render;
+mod version_info;
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use windows::core::{Interface, Result, GUID, HRESULT, HSTRING};
+use windows::Win32::Media::Audio::{
+    eRender, IMMDeviceEnumerator, MMDeviceEnumerator, DEVICE_STATE, DEVICE_STATE_ACTIVE, DEVICE_STATE_UNPLUGGED,
+    IAudioSessionManager2, IAudioSessionControl2, IAudioSessionEvents, IAudioSessionNotification,
+    IMMNotificationClient, ISimpleAudioVolume,
+    eCapture, eConsole, eMultimedia, eCommunications, EDataFlow, ERole
+};
+use windows::Win32::Media::Audio::Endpoints::{IAudioEndpointVolume, IAudioEndpointVolumeCallback, IAudioMeterInformation};
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_ALL, CoTaskMemFree};
+use windows::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use icon_cache::{IconCache, Lookup};
+use icon_loader::IconLoader;
+use events::{session_state, DeviceEventsListener, DeviceVolumeListener, MasterVolumeListener, SessionCreatedListener, SessionEventsListener, EVENT_CONTEXT};
+
+use crate::accent;
+use crate::apps::AppPresentation;
+use crate::diff::SessionPublisher;
+use crate::{
+    session_key, AppOverride, AudioBackend, AudioDeviceInfo, AudioError, AudioNotification, ChangeOrigin,
+    AudioSessionInfo, BluetoothInfo, BluetoothProfile, BoostRequest, CaptureEffect, CaptureEffectKind, DefaultEndpoints, DeviceFormat, DeviceRole, FileCaptureRequest, InputGain, InputPeak, LegacyStream, MasterVolume, MediaCommand, MicrophoneUser, MirrorRequest, MixerEvent, MixerEventSink, NetworkStream, NetworkStreamRequest, Notifier, PeakLevel, PlaybackRequest, Recording, SessionState, SessionsDiff, SyncedPlaybackRequest, GENERATOR_SESSION_GUID, GENERATOR_SESSION_NAME,
+};
+
+/// オーディオサービスの再起動などで、既存の COM オブジェクトが使えなくなったことを示すエラー
+const SERVICE_LOST_ERRORS: [HRESULT; 5] = [
+    HRESULT(0x8889_0004_u32 as i32), // AUDCLNT_E_DEVICE_INVALIDATED
+    HRESULT(0x8889_0010_u32 as i32), // AUDCLNT_E_SERVICE_NOT_RUNNING
+    HRESULT(0x8001_0108_u32 as i32), // RPC_E_DISCONNECTED
+    HRESULT(0x8007_06BA_u32 as i32), // RPC_S_SERVER_UNAVAILABLE
+    HRESULT(0x8007_06BE_u32 as i32), // RPC_S_CALL_FAILED
+];
+
+/// ポリシー API が返すデバイスインターフェースのパスから、エンドポイントの ID を取り出します。
+fn endpoint_id_from_path(path: &str) -> Option<String> {
+    if path.is_empty() {
+        return None;
+    }
+    // 例: \\?\SWD#MMDEVAPI#{0.0.0.00000000}.{guid}#{e6327cad-dcec-4949-ae8a-991e976a79d2}
+    match path.split_once("MMDEVAPI#") {
+        Some((_, rest)) => rest.split('#').next().map(str::to_string),
+        None => Some(path.to_string()),
+    }
+}
+
+fn erole(role: DeviceRole) -> ERole {
+    match role {
+        DeviceRole::Console => eConsole,
+        DeviceRole::Multimedia => eMultimedia,
+        DeviceRole::Communications => eCommunications,
+    }
+}
+
+/// 接続の確認のためにオーディオサービスへ問い合わせる間隔
+const HEALTH_PROBE_INTERVAL: Duration = Duration::from_secs(1);
+/// 実行ファイルの更新日時を確かめる間隔
+const APP_UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// セッションのインスタンス識別子を取得します。
+fn instance_identifier(control: &IAudioSessionControl2) -> Option<String> {
+    unsafe {
+        let id = control.GetSessionInstanceIdentifier().ok()?;
+        let value = id.to_string().ok();
+        CoTaskMemFree(Some(id.as_ptr() as _));
+        value
+    }
+}
+
+/// このプロセスのジェネレーターが開いたセッションか
+fn is_generator_session(control: &IAudioSessionControl2) -> bool {
+    unsafe { control.GetGroupingParam() }.is_ok_and(|guid| guid == GUID::from_u128(GENERATOR_SESSION_GUID))
+}
+
+/// WASAPI によるバックエンド。すべての COM オブジェクトはオーディオスレッド上でのみ扱います。
+pub struct WasapiBackend {
+    device_enumerator: IMMDeviceEnumerator,
+    sink: Arc<dyn MixerEventSink>,
+    process_handles: HashMap<u32, HANDLE>,
+    /// インスタンス識別子ごとの PID とメーター
+    meter_cache: HashMap<String, (u32, IAudioMeterInformation)>,
+    apps: AppPresentation,
+    icon_cache: IconCache,
+    /// キャッシュになかったアイコンを抽出するスレッド
+    icon_loader: IconLoader,
+    /// 小文字の実行ファイルのパスごとの、バージョン情報の製品名
+    product_names: HashMap<String, Option<String>>,
+    /// 小文字の実行ファイルのパスごとの、最後に見た更新日時。アプリの更新に気付くために使う
+    exe_mtimes: HashMap<String, u64>,
+    last_app_check: Instant,
+    exe_paths: HashMap<u32, String>,
+    /// 自分では開けないプロセス（管理者として動いているものなど）の実行ファイルのパス
+    process_paths: HashMap<u32, String>,
+    /// アプリがメディアコントロールに出している再生中の曲
+    media: media::MediaSessions,
+    notifier: Notifier,
+    /// 通知で更新されるセッション一覧。`cache_dirty` のときだけ再列挙する
+    session_cache: Vec<AudioSessionInfo>,
+    cache_dirty: bool,
+    /// 最後に `sessions-changed` で通知した状態と、それ以降に変更があったか
+    publisher: SessionPublisher,
+    sessions_changed: bool,
+    session_listeners: HashMap<String, (IAudioSessionControl2, IAudioSessionEvents)>,
+    /// PID からキャッシュ済みセッションの音量インターフェースを引くための索引
+    session_index: HashMap<u32, Vec<(String, ISimpleAudioVolume)>>,
+    session_managers: HashMap<String, (IAudioSessionManager2, IAudioSessionNotification)>,
+    device_listener: Option<IMMNotificationClient>,
+    /// 出力先の切り替えに使う非公開インターフェース。初回の切り替え時に生成する
+    policy_config: Option<policy_v2::IAudioPolicyConfig>,
+    /// オーディオサービスとの接続を失ったことを検出した
+    lost: bool,
+    last_probe: Instant,
+    /// 既定の出力デバイスの音量コントロールと、登録した変化の通知、デバイス ID
+    master: Option<(IAudioEndpointVolume, IAudioEndpointVolumeCallback, String)>,
+    /// 最後に通知したマスター音量
+    last_master: Option<MasterVolume>,
+    /// 監視を頼まれた出力デバイスと、いま登録できている変化の通知
+    watched_devices: Vec<String>,
+    device_watchers: HashMap<String, (IAudioEndpointVolume, IAudioEndpointVolumeCallback)>,
+    /// 再生中のジェネレーター。同期再生ではデバイスごとに 1 つ
+    playback: Vec<render::Playback>,
+    /// 計測中の入力デバイス
+    input_meter: Option<capture::InputMeter>,
+    recorder: Option<capture::Recorder>,
+    /// システムの音のファイルへの録音
+    file_capture: Option<capture::FileCapture>,
+    /// LAN への配信
+    network_stream: Option<capture::NetworkCapture>,
+    /// PID ごとの動いているミラー
+    mirrors: HashMap<u32, mirror::Mirror>,
+    /// PID ごとの動いているブースト
+    boosts: HashMap<u32, boost::Boost>,
+    /// 他のフィールドの COM オブジェクトがすべて解放された後に COM を終了させるため、最後に置く
+    _com: com::ComGuard,
+}
+
+impl Drop for WasapiBackend {
+    /// コールバックがスレッド終了後に呼ばれないよう、登録したすべての通知を解除します。
+    fn drop(&mut self) {
+        self.playback.clear();
+        self.input_meter = None;
+        self.recorder = None;
+        self.file_capture = None;
+        self.network_stream = None;
+        self.mirrors.clear();
+        self.boosts.clear();
+        if let Some((endpoint, listener, _)) = self.master.take() {
+            unsafe { let _ = endpoint.UnregisterControlChangeNotify(&listener); }
+        }
+        self.unwatch_devices();
+        for (_, (control, listener)) in self.session_listeners.drain() {
+            unsafe { let _ = control.UnregisterAudioSessionNotification(&listener); }
+        }
+        for (_, (manager, listener)) in self.session_managers.drain() {
+            unsafe { let _ = manager.UnregisterSessionNotification(&listener); }
+        }
+        if let Some(listener) = self.device_listener.take() {
+            unsafe { let _ = self.device_enumerator.UnregisterEndpointNotificationCallback(&listener); }
+        }
+        for (_, handle) in self.process_handles.drain() {
+            unsafe { let _ = CloseHandle(handle); }
+        }
+    }
+}
+
+impl WasapiBackend {
+    pub fn new(notifier: Notifier, sink: Arc<dyn MixerEventSink>) -> Result<Self> {
+        let com = com::init_mta();
+        let device_enumerator: IMMDeviceEnumerator = unsafe {
+            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?
+        };
+        let device_listener: IMMNotificationClient = DeviceEventsListener { notifier: notifier.clone() }.into();
+        let device_listener = unsafe {
+            device_enumerator.RegisterEndpointNotificationCallback(&device_listener).ok().map(|_| device_listener)
+        };
+        let mut backend = Self {
+            device_enumerator,
+            sink,
+            process_handles: HashMap::new(),
+            meter_cache: HashMap::new(),
+            apps: AppPresentation::default(),
+            icon_cache: IconCache::default(),
+            icon_loader: IconLoader::new(notifier.clone()),
+            product_names: HashMap::new(),
+            exe_mtimes: HashMap::new(),
+            last_app_check: Instant::now(),
+            exe_paths: HashMap::new(),
+            process_paths: HashMap::new(),
+            media: media::MediaSessions::new(notifier.clone()),
+            notifier,
+            session_cache: Vec::new(),
+            cache_dirty: true,
+            publisher: SessionPublisher::default(),
+            sessions_changed: false,
+            session_listeners: HashMap::new(),
+            session_index: HashMap::new(),
+            session_managers: HashMap::new(),
+            device_listener,
+            policy_config: None,
+            lost: false,
+            last_probe: Instant::now(),
+            master: None,
+            last_master: None,
+            watched_devices: Vec::new(),
+            device_watchers: HashMap::new(),
+            playback: Vec::new(),
+            input_meter: None,
+            recorder: None,
+            file_capture: None,
+            network_stream: None,
+            mirrors: HashMap::new(),
+            boosts: HashMap::new(),
+            _com: com,
+        };
+        backend.watch_master();
+        backend.media.refresh();
+        Ok(backend)
+    }
+
+    /// 既定の出力デバイスの音量の変化を監視します。既定のデバイスが変わったら呼び直してください。
+    fn watch_master(&mut self) {
+        if let Some((endpoint, listener, _)) = self.master.take() {
+            unsafe { let _ = endpoint.UnregisterControlChangeNotify(&listener); }
+        }
+        let master = unsafe {
+            (|| -> Result<(IAudioEndpointVolume, IAudioEndpointVolumeCallback, String)> {
+                let device = self.device_enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?;
+                let id_pwstr = device.GetId()?;
+                let id = id_pwstr.to_string().unwrap_or_default();
+                CoTaskMemFree(Some(id_pwstr.as_ptr() as _));
+                let endpoint: IAudioEndpointVolume = device.Activate(CLSCTX_ALL, None)?;
+                let listener: IAudioEndpointVolumeCallback = MasterVolumeListener { notifier: self.notifier.clone() }.into();
+                endpoint.RegisterControlChangeNotify(&listener)?;
+                Ok((endpoint, listener, id))
+            })()
+        };
+        let master = self.watch(master);
+        self.master = master.ok();
+    }
+
+    fn unwatch_devices(&mut self) {
+        for (_, (endpoint, listener)) in self.device_watchers.drain() {
+            unsafe { let _ = endpoint.UnregisterControlChangeNotify(&listener); }
+        }
+    }
+
+    /// `watched_devices` の音量の変化の通知を登録し直します。いま接続されていないデバイスは、
+    /// 次にデバイスが変化したときにもう一度試します。
+    fn rewatch_devices(&mut self) {
+        self.unwatch_devices();
+        for device_id in self.watched_devices.clone() {
+            let Ok(endpoint) = self.device_endpoint(&device_id) else { continue };
+            let listener: IAudioEndpointVolumeCallback = DeviceVolumeListener { notifier: self.notifier.clone(), device_id: device_id.clone() }.into();
+            if unsafe { endpoint.RegisterControlChangeNotify(&listener) }.is_ok() {
+                self.device_watchers.insert(device_id, (endpoint, listener));
+            }
+        }
+    }
+
+    /// マスター音量が前回の通知から変わっていれば通知します。
+    fn publish_master(&mut self, origin: ChangeOrigin) {
+        if let Ok(master) = self.get_master_volume() {
+            if self.last_master.as_ref() != Some(&master) {
+                self.last_master = Some(master.clone());
+                self.sink.emit(MixerEvent::MasterVolumeChanged(master, origin));
+            }
+        }
+    }
+
+    fn master_endpoint(&mut self) -> std::result::Result<IAudioEndpointVolume, AudioError> {
+        if self.master.is_none() {
+            self.watch_master();
+        }
+        self.master.as_ref()
+            .map(|(endpoint, _, _)| endpoint.clone())
+            .ok_or_else(|| AudioError::Backend("No default output device".to_string()))
+    }
+
+    /// 既定でないデバイスも含め、出力デバイスの音量を操作するインターフェース
+    fn device_endpoint(&mut self, device_id: &str) -> std::result::Result<IAudioEndpointVolume, AudioError> {
+        let endpoint = unsafe {
+            self.device_enumerator.GetDevice(&HSTRING::from(device_id))
+                .and_then(|device| device.Activate::<IAudioEndpointVolume>(CLSCTX_ALL, None))
+        };
+        Ok(self.watch(endpoint)?)
+    }
+
+    /// オーディオサービスとの接続が失われたことを示すエラーなら記録します。
+    fn watch<T>(&mut self, result: Result<T>) -> Result<T> {
+        if let Err(e) = &result {
+            if SERVICE_LOST_ERRORS.contains(&e.code()) {
+                self.lost = true;
+            }
+        }
+        result
+    }
+
+    /// キャッシュを破棄し、次回の取得時に全セッションを再列挙させます。
+    fn invalidate_sessions(&mut self) {
+        self.session_cache.clear();
+        self.cache_dirty = true;
+    }
+
+    fn sessions_with_peaks(&self) -> Vec<AudioSessionInfo> {
+        let mut sessions = self.session_cache.clone();
+        for session in &mut sessions {
+            if let Some((_, meter)) = self.meter_cache.get(&session.instance_id) {
+                session.peak_level = unsafe { meter.GetPeakValue() }.unwrap_or(0.0);
+            }
+        }
+        sessions
+    }
+
+    fn remove_session(&mut self, key: &str) {
+        self.session_cache.retain(|s| s.instance_id != key);
+        self.sessions_changed = true;
+        self.meter_cache.remove(key);
+        for controls in self.session_index.values_mut() {
+            controls.retain(|(k, _)| k != key);
+        }
+        self.session_index.retain(|_, controls| !controls.is_empty());
+        if let Some((control, listener)) = self.session_listeners.remove(key) {
+            unsafe { let _ = control.UnregisterAudioSessionNotification(&listener); }
+        }
+    }
+
+    fn register_session_listener(&mut self, key: &str, control: &IAudioSessionControl2) {
+        if self.session_listeners.contains_key(key) { return; }
+        let listener: IAudioSessionEvents = SessionEventsListener {
+            notifier: self.notifier.clone(),
+            session_key: key.to_string(),
+        }.into();
+        if unsafe { control.RegisterAudioSessionNotification(&listener) }.is_ok() {
+            self.session_listeners.insert(key.to_string(), (control.clone(), listener));
+        }
+    }
+
+    fn register_session_manager(&mut self, device_id: &str, session_manager: &IAudioSessionManager2) {
+        if self.session_managers.contains_key(device_id) { return; }
+        let listener: IAudioSessionNotification = SessionCreatedListener { notifier: self.notifier.clone() }.into();
+        if unsafe { session_manager.RegisterSessionNotification(&listener) }.is_ok() {
+            self.session_managers.insert(device_id.to_string(), (session_manager.clone(), listener));
+        }
+    }
+
+    fn rescan_sessions(&mut self) -> Result<()> {
+        let result = self.enumerate_sessions();
+        self.watch(result)
+    }
+
+    fn enumerate_sessions(&mut self) -> Result<()> {
+        let mut previous: HashMap<String, AudioSessionInfo> = self.session_cache
+            .drain(..)
+            .map(|s| (s.instance_id.clone(), s))
+            .collect();
+        let mut sessions = Vec::new();
+        let mut session_index: HashMap<u32, Vec<(String, ISimpleAudioVolume)>> = HashMap::new();
+        let mut active_devices = HashSet::new();
+        let mut active_session_keys = HashSet::new();
+        let mut active_pids = HashSet::new();
+        let mut routes: HashMap<u32, Option<String>> = HashMap::new();
+
+        unsafe {
+            // 何もつながっていない端子も「未接続」として表示するため、抜かれたエンドポイントも列挙する
+            let states = DEVICE_STATE(DEVICE_STATE_ACTIVE.0 | DEVICE_STATE_UNPLUGGED.0);
+            let collection = self.device_enumerator.EnumAudioEndpoints(eRender, states)?;
+            let count = collection.GetCount()?;
+
+            for i in 0..count {
+                let device = collection.Item(i)?;
+                let id_pwstr = device.GetId()?;
+                let device_id = id_pwstr.to_string().unwrap_or_default();
+                CoTaskMemFree(Some(id_pwstr.as_ptr() as _));
+                active_devices.insert(device_id.clone());
+
+                if let Ok(session_manager) = device.Activate::<IAudioSessionManager2>(CLSCTX_ALL, None) {
+                    self.register_session_manager(&device_id, &session_manager);
+                    if let Ok(enumerator) = session_manager.GetSessionEnumerator() {
+                        let session_count = enumerator.GetCount()?;
+                        for j in 0..session_count {
+                            let session = enumerator.GetSession(j)?;
+                            if let Ok(control2) = session.cast::<IAudioSessionControl2>() {
+                                let pid = control2.GetProcessId().unwrap_or(0);
+                                let instance_id = instance_identifier(&control2)
+                                    .unwrap_or_else(|| session_key(pid, &device_id));
+                                active_session_keys.insert(instance_id.clone());
+
+                                if pid != 0 {
+                                    if !self.is_process_alive(pid) { continue; }
+                                    active_pids.insert(pid);
+                                }
+
+                                if let (Ok(vol), Ok(meter)) = (session.cast::<ISimpleAudioVolume>(), session.cast::<IAudioMeterInformation>()) {
+                                    let exe_path = if pid == 0 { None } else { self.process_path(pid) };
+                                    let generator = pid == std::process::id() && is_generator_session(&control2);
+                                    let exe_name = if pid == 0 {
+                                        "System Sounds".to_string()
+                                    } else if generator {
+                                        GENERATOR_SESSION_NAME.to_string()
+                                    } else {
+                                        exe_path.as_deref()
+                                            .and_then(icon::exe_name_from_path)
+                                            .unwrap_or_else(|| format!("PROCESS {}", pid))
+                                    };
+                                    let exe_key = exe_name.to_uppercase();
+                                    if self.apps.is_hidden(&exe_key) { continue; }
+
+                                    let volume = vol.GetMasterVolume().unwrap_or(1.0);
+                                    let muted = vol.GetMute().map(|m| m.as_bool()).unwrap_or(false);
+
+                                    self.register_session_listener(&instance_id, &control2);
+                                    session_index.entry(pid).or_default().push((instance_id.clone(), vol.clone()));
+                                    let (icon_base64, accent_color) = previous.remove(&instance_id)
+                                        .map(|s| (s.icon_base64, s.accent_color))
+                                        .unwrap_or_default();
+                                    self.meter_cache.insert(instance_id.clone(), (pid, meter));
+
+                                    let process_name = if generator {
+                                        self.apps.display_name(&exe_key, &exe_name)
+                                    } else {
+                                        self.display_name(pid, exe_path.as_deref(), &exe_name)
+                                    };
+                                    if let Some(path) = exe_path {
+                                        self.exe_mtimes.entry(path.to_lowercase()).or_insert_with(|| icon_cache::file_mtime(&path));
+                                        self.exe_paths.insert(pid, path);
+                                    }
+
+                                    // アイコンはここでは抽出せず、必要になった時点で ensure_icons が補う
+                                    let persisted_device_id = match routes.get(&pid) {
+                                        Some(route) => route.clone(),
+                                        None => {
+                                            let route = if pid == 0 { None } else { self.persisted_route(pid) };
+                                            routes.insert(pid, route.clone());
+                                            route
+                                        }
+                                    };
+
+                                    sessions.push(AudioSessionInfo {
+                                        process_id: pid,
+                                        process_name,
+                                        exe_name,
+                                        volume,
+                                        is_muted: muted,
+                                        // ピーク値は取得時に毎回読み直すため、キャッシュには保持しない
+                                        peak_level: 0.0,
+                                        icon_base64,
+                                        accent_color,
+                                        device_id: device_id.clone(),
+                                        instance_id,
+                                        state: control2.GetState().map(session_state).unwrap_or(SessionState::Inactive),
+                                        route_pending: persisted_device_id.as_ref().is_some_and(|d| *d != device_id),
+                                        persisted_device_id,
+                                        now_playing: None,
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        self.process_handles.retain(|pid, _| active_pids.contains(pid));
+        self.exe_paths.retain(|pid, _| active_pids.contains(pid));
+        let live_paths: HashSet<String> = self.exe_paths.values().map(|p| p.to_lowercase()).collect();
+        self.exe_mtimes.retain(|path, _| live_paths.contains(path));
+        self.meter_cache.retain(|key, _| active_session_keys.contains(key));
+
+        let stale_keys: Vec<String> = self.session_listeners.keys()
+            .filter(|k| !active_session_keys.contains(*k))
+            .cloned()
+            .collect();
+        for key in stale_keys {
+            self.remove_session(&key);
+        }
+        self.session_managers.retain(|device_id, (manager, listener)| {
+            let keep = active_devices.contains(device_id);
+            if !keep {
+                unsafe { let _ = manager.UnregisterSessionNotification(&*listener); }
+            }
+            keep
+        });
+
+        self.session_cache = sessions;
+        self.session_index = session_index;
+        self.cache_dirty = false;
+        self.sessions_changed = true;
+        self.apply_now_playing();
+        Ok(())
+    }
+
+    /// メディアコントロールの再生中の曲をセッションに反映します。
+    fn apply_now_playing(&mut self) {
+        for session in &mut self.session_cache {
+            let now_playing = self.media.now_playing(&session.exe_name);
+            if session.now_playing != now_playing {
+                session.now_playing = now_playing;
+                self.sessions_changed = true;
+            }
+        }
+    }
+
+    /// アイコン未取得のキャッシュ項目について、上書き設定またはキャッシュからアイコンを取得します。
+    /// キャッシュにない exe のアイコンは抽出を頼むだけで待ちません。
+    fn ensure_icons(&mut self) {
+        for i in 0..self.session_cache.len() {
+            if self.session_cache[i].icon_base64.is_some() { continue; }
+            let pid = self.session_cache[i].process_id;
+            let exe_key = self.session_cache[i].exe_name.to_uppercase();
+            if let Some(icon) = self.resolve_icon(pid, &exe_key) {
+                self.session_cache[i].set_icon(Some(icon));
+                self.sessions_changed = true;
+            }
+        }
+    }
+
+    /// 実行ファイルのパス。開けなければ外から教えられたパスを使います。
+    fn process_path(&self, pid: u32) -> Option<String> {
+        icon::get_process_path(pid).or_else(|| self.process_paths.get(&pid).cloned())
+    }
+
+    /// 上書き設定のアイコンか、抽出済みのアイコンを返します。まだなら `icon_loader` に抽出を頼み、
+    /// できあがったら `IconLoaded` の通知で受け取ります。
+    /// 上書き設定の表示名、exe のバージョン情報の製品名、実行ファイル名の順に表示名を決めます。
+    /// 汎用のホスト（javaw.exe など）では、製品名の代わりにメインウィンドウのタイトルを使います。
+    fn display_name(&mut self, pid: u32, exe_path: Option<&str>, exe_name: &str) -> String {
+        let exe_key = exe_name.to_uppercase();
+        let name = if host_name::is_generic_host(&exe_key) {
+            host_name::main_window_title(pid)
+        } else {
+            exe_path.and_then(|path| {
+                self.product_names.entry(path.to_lowercase()).or_insert_with(|| version_info::product_name(path)).clone()
+            })
+        };
+        self.apps.display_name(&exe_key, name.as_deref().unwrap_or(exe_name))
+    }
+
+    fn resolve_icon(&mut self, pid: u32, exe_key: &str) -> Option<String> {
+        if let Some(icon) = self.apps.override_icon(exe_key) {
+            return Some(icon);
+        }
+        let exe_path = self.exe_paths.get(&pid)?.clone();
+        match self.icon_cache.lookup(&exe_path) {
+            Lookup::Cached(icon) => icon,
+            Lookup::Missing(job) => {
+                self.icon_loader.queue(job);
+                None
+            }
+        }
+    }
+
+    /// 更新された exe の製品名とアイコンを捨てて、そのセッションの表示名を決め直します。
+    /// アイコンは次の `ensure_icons` で新しい exe から抽出し直します。
+    fn app_updated(&mut self, exe_key: &str) {
+        self.product_names.remove(exe_key);
+        for i in 0..self.session_cache.len() {
+            let pid = self.session_cache[i].process_id;
+            let Some(exe_path) = self.exe_paths.get(&pid).filter(|p| p.to_lowercase() == exe_key).cloned() else { continue };
+            if self.session_cache[i].exe_name == GENERATOR_SESSION_NAME { continue; }
+            let exe_name = self.session_cache[i].exe_name.clone();
+            let process_name = self.display_name(pid, Some(&exe_path), &exe_name);
+            let session = &mut self.session_cache[i];
+            session.process_name = process_name;
+            session.set_icon(None);
+            self.sessions_changed = true;
+        }
+    }
+
+    /// 抽出できたアイコンを、その exe のアイコンがまだないセッションに付けて通知します。
+    fn icon_loaded(&mut self, exe_path: String, mtime: u64, icon_base64: Option<String>) {
+        self.icon_loader.finish(&exe_path);
+        self.icon_cache.insert(&exe_path, mtime, icon_base64.clone());
+        let Some(icon) = icon_base64 else { return };
+        let accent_color = accent::accent_color(&icon);
+        let exe_key = exe_path.to_lowercase();
+        let mut ready = Vec::new();
+        for session in &mut self.session_cache {
+            let pid = session.process_id;
+            if session.icon_base64.is_none() && self.exe_paths.get(&pid).is_some_and(|p| p.to_lowercase() == exe_key) {
+                session.icon_base64 = Some(icon.clone());
+                session.accent_color = accent_color.clone();
+                ready.push(pid);
+            }
+        }
+        for pid in ready {
+            self.sessions_changed = true;
+            self.sink.emit(MixerEvent::SessionIconReady { pid, icon_base64: icon.clone(), accent_color: accent_color.clone() });
+        }
+    }
+
+    fn is_process_alive(&mut self, pid: u32) -> bool {
+        if let Some(&handle) = self.process_handles.get(&pid) {
+            let mut exit_code = 0u32;
+            unsafe {
+                if windows::Win32::System::Threading::GetExitCodeProcess(handle, &mut exit_code).is_ok() {
+                    if exit_code == 259 { return true; }
+                }
+            }
+            unsafe { let _ = CloseHandle(handle); }
+            self.process_handles.remove(&pid);
+        }
+        unsafe {
+            match OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) {
+                Ok(handle) => {
+                    self.process_handles.insert(pid, handle);
+                    true
+                }
+                Err(_) => false,
+            }
+        }
+    }
+
+    /// 索引から PID のセッションを引いて操作します。見つからない場合だけ再列挙します。
+    fn apply_to_session<F>(&mut self, target_pid: u32, action: F) -> std::result::Result<(), AudioError>
+    where
+        F: Fn(&ISimpleAudioVolume) -> Result<()>,
+    {
+        if self.cache_dirty || !self.session_index.contains_key(&target_pid) {
+            self.rescan_sessions()?;
+        }
+        let controls = self.session_index.get(&target_pid).ok_or(AudioError::SessionNotFound(target_pid))?;
+        let result = controls.iter().try_for_each(|(_, sv)| action(sv));
+        if result.is_err() {
+            // セッションが無効になっている可能性があるので、次回は再列挙させる
+            self.cache_dirty = true;
+        }
+        Ok(self.watch(result)?)
+    }
+
+    /// 識別子が一致するセッションだけを操作します。見つからない場合だけ再列挙します。
+    fn apply_to_instance<F>(&mut self, instance_id: &str, action: F) -> std::result::Result<(), AudioError>
+    where
+        F: Fn(&ISimpleAudioVolume) -> Result<()>,
+    {
+        let find = |index: &HashMap<u32, Vec<(String, ISimpleAudioVolume)>>| {
+            index.values().flatten().find(|(id, _)| id == instance_id).map(|(_, sv)| sv.clone())
+        };
+        let mut control = find(&self.session_index);
+        if self.cache_dirty || control.is_none() {
+            self.rescan_sessions()?;
+            control = find(&self.session_index);
+        }
+        let control = control.ok_or_else(|| AudioError::InstanceNotFound(instance_id.to_string()))?;
+        let result = action(&control);
+        if result.is_err() {
+            self.cache_dirty = true;
+        }
+        Ok(self.watch(result)?)
+    }
+
+    /// 出力先の切り替えに使う非公開インターフェース。初回に生成してキャッシュします。
+    fn policy_config(&mut self) -> Result<policy_v2::IAudioPolicyConfig> {
+        if let Some(config) = &self.policy_config {
+            return Ok(config.clone());
+        }
+        let config = policy_v2::AudioPolicyConfigFactory::new();
+        let config = self.watch(config)?;
+        self.policy_config = Some(config.clone());
+        Ok(config)
+    }
+
+    /// プロセスに永続的に設定されている出力先。取得できない場合も `None` です。
+    fn persisted_route(&mut self, pid: u32) -> Option<String> {
+        let config = self.policy_config().ok()?;
+        let endpoint = unsafe { config.get_persisted_default_endpoint(pid, eMultimedia) }.ok()?;
+        endpoint_id_from_path(&endpoint.to_string())
+    }
+
+    /// `flow` の向き（出力 `eRender`・入力 `eCapture`）のエンドポイントを列挙します。
+    fn enumerate_devices(&self, flow: EDataFlow) -> Result<Vec<AudioDeviceInfo>> {
+        let mut devices = Vec::new();
+        unsafe {
+            use windows::Win32::Devices::Properties::DEVPKEY_Device_FriendlyName;
+            use windows::Win32::UI::Shell::PropertiesSystem::PROPERTYKEY;
+            use windows::Win32::System::Com::STGM_READ;
+
+            // 何もつながっていない端子も「未接続」として表示するため、抜かれたエンドポイントも列挙する
+            let states = DEVICE_STATE(DEVICE_STATE_ACTIVE.0 | DEVICE_STATE_UNPLUGGED.0);
+            let collection = self.device_enumerator.EnumAudioEndpoints(flow, states)?;
+            // マイクが一つもない場合などは既定のデバイスがない
+            let default_id = match self.device_enumerator.GetDefaultAudioEndpoint(flow, eConsole) {
+                Ok(default_device) => {
+                    let default_id_pwstr = default_device.GetId()?;
+                    let default_id = default_id_pwstr.to_string().unwrap_or_default();
+                    CoTaskMemFree(Some(default_id_pwstr.as_ptr() as _));
+                    default_id
+                }
+                Err(_) => String::new(),
+            };
+            // Bluetooth の機器があるときだけ、バッテリー残量をまとめて読む
+            let mut batteries = None;
+
+            for i in 0..collection.GetCount()? {
+                let device = collection.Item(i)?;
+                let id_pwstr = device.GetId()?;
+                let id = id_pwstr.to_string().unwrap_or_default();
+                CoTaskMemFree(Some(id_pwstr.as_ptr() as _));
+                
+                let is_default = id == default_id;
+                let jack_connected = match device.GetState() {
+                    Ok(state) if state == DEVICE_STATE_UNPLUGGED => Some(false),
+                    _ => jack::jack_connected(&device),
+                };
+
+                if let Ok(store) = device.OpenPropertyStore(STGM_READ) {
+                    let prop_key = PROPERTYKEY {
+                        fmtid: DEVPKEY_Device_FriendlyName.fmtid,
+                        pid: DEVPKEY_Device_FriendlyName.pid,
+                    };
+                    let name = store.GetValue(&prop_key).map(|v| v.to_string()).unwrap_or_else(|_| "Unknown Device".to_string());
+                    let bluetooth = bluetooth::profile(&store).map(|profile| BluetoothInfo {
+                        battery_percent: bluetooth::container_id(&store)
+                            .and_then(|container| batteries.get_or_insert_with(bluetooth::batteries).get(&container).copied()),
+                        profile: Some(profile),
+                    });
+                    devices.push(AudioDeviceInfo { id, name, is_default, jack_connected, bluetooth });
+                }
+            }
+        }
+        Ok(devices)
+    }
+}
+
+impl AudioBackend for WasapiBackend {
+    fn name(&self) -> &'static str {
+        "wasapi"
+    }
+
+    fn set_icon_cache_dir(&mut self, dir: Option<PathBuf>) {
+        self.icon_cache = IconCache::new(dir);
+    }
+
+    /// 通知の登録もやり直すので、取りこぼした通知があっても以後は届きます。
+    fn clear_caches(&mut self) {
+        self.invalidate_sessions();
+        self.meter_cache.clear();
+        self.exe_paths.clear();
+        self.icon_cache.clear();
+        self.product_names.clear();
+        self.exe_mtimes.clear();
+        self.watch_master();
+        self.rewatch_devices();
+    }
+
+    fn set_hidden_apps(&mut self, names: Vec<String>) {
+        self.apps.set_hidden_apps(names);
+        self.invalidate_sessions();
+    }
+
+    fn set_app_overrides(&mut self, overrides: HashMap<String, AppOverride>) {
+        self.apps.set_app_overrides(overrides);
+        self.invalidate_sessions();
+    }
+
+    fn set_process_paths(&mut self, paths: HashMap<u32, String>) {
+        if paths != self.process_paths {
+            self.process_paths = paths;
+            self.invalidate_sessions();
+        }
+    }
+
+    /// キャッシュ済みのセッション一覧を返します。ピーク値だけは毎回取り直します。
+    fn get_sessions(&mut self) -> std::result::Result<Vec<AudioSessionInfo>, AudioError> {
+        if self.cache_dirty {
+            self.rescan_sessions()?;
+        }
+        self.ensure_icons();
+        Ok(self.sessions_with_peaks())
+    }
+
+    fn get_sessions_lite(&mut self) -> std::result::Result<Vec<AudioSessionInfo>, AudioError> {
+        if self.cache_dirty {
+            self.rescan_sessions()?;
+        }
+        let mut sessions = self.sessions_with_peaks();
+        for session in &mut sessions {
+            session.icon_base64 = None;
+        }
+        Ok(sessions)
+    }
+
+    /// キャッシュを経由せず、音量・ミュート・ピーク値をセッションから直接読み直します。
+    fn get_session(&mut self, pid: u32) -> std::result::Result<AudioSessionInfo, AudioError> {
+        if self.cache_dirty || !self.session_index.contains_key(&pid) {
+            self.rescan_sessions()?;
+        }
+        let (key, volume) = self.session_index.get(&pid)
+            .and_then(|controls| controls.first())
+            .cloned()
+            .ok_or(AudioError::SessionNotFound(pid))?;
+        let mut session = self.session_cache.iter()
+            .find(|s| s.instance_id == key)
+            .cloned()
+            .ok_or(AudioError::SessionNotFound(pid))?;
+        let fresh = unsafe { volume.GetMasterVolume().and_then(|v| Ok((v, volume.GetMute()?.as_bool()))) };
+        let (level, muted) = self.watch(fresh)?;
+        session.volume = level;
+        session.is_muted = muted;
+        if let Some((control, _)) = self.session_listeners.get(&key) {
+            if let Ok(state) = unsafe { control.GetState() } {
+                session.state = session_state(state);
+            }
+        }
+        if let Some((_, meter)) = self.meter_cache.get(&key) {
+            session.peak_level = unsafe { meter.GetPeakValue() }.unwrap_or(0.0);
+        }
+        if session.icon_base64.is_none() {
+            session.set_icon(self.resolve_icon(pid, &session.exe_name.to_uppercase()));
+        }
+        Ok(session)
+    }
+
+    fn get_session_icon(&mut self, pid: u32) -> std::result::Result<Option<String>, AudioError> {
+        if self.cache_dirty {
+            self.rescan_sessions()?;
+        }
+        let session = self.session_cache.iter().find(|s| s.process_id == pid).ok_or(AudioError::SessionNotFound(pid))?;
+        if session.icon_base64.is_some() {
+            return Ok(session.icon_base64.clone());
+        }
+        // 明示的に頼まれたアイコンは、抽出を待って返す
+        if let Some(icon) = self.apps.override_icon(&session.exe_name.to_uppercase()) {
+            return Ok(Some(icon));
+        }
+        let Some(exe_path) = self.exe_paths.get(&pid).cloned() else { return Ok(None) };
+        Ok(self.icon_cache.get_or_extract(&exe_path))
+    }
+
+    fn take_sessions_diff(&mut self) -> std::result::Result<Option<SessionsDiff>, AudioError> {
+        if self.cache_dirty {
+            self.rescan_sessions()?;
+        }
+        if !self.sessions_changed {
+            return Ok(None);
+        }
+        self.ensure_icons();
+        self.sessions_changed = false;
+        Ok(self.publisher.publish(&self.session_cache))
+    }
+
+    fn get_master_volume(&mut self) -> std::result::Result<MasterVolume, AudioError> {
+        let endpoint = self.master_endpoint()?;
+        let device_id = self.master.as_ref().map(|(_, _, id)| id.clone()).unwrap_or_default();
+        let state = unsafe { endpoint.GetMasterVolumeLevelScalar().and_then(|v| Ok((v, endpoint.GetMute()?.as_bool()))) };
+        let (volume, is_muted) = self.watch(state)?;
+        let level = unsafe { gain::endpoint_level(&endpoint) }.ok();
+        Ok(MasterVolume { device_id, volume, is_muted, level })
+    }
+
+    fn set_master_volume(&mut self, volume: f32) -> std::result::Result<(), AudioError> {
+        let endpoint = self.master_endpoint()?;
+        let result = unsafe { endpoint.SetMasterVolumeLevelScalar(volume.clamp(0.0, 1.0), &EVENT_CONTEXT) };
+        Ok(self.watch(result)?)
+    }
+
+    fn set_master_volume_db(&mut self, db: f32) -> std::result::Result<(), AudioError> {
+        let endpoint = self.master_endpoint()?;
+        let result = unsafe { gain::set_endpoint_level(&endpoint, db, &EVENT_CONTEXT) };
+        Ok(self.watch(result)?)
+    }
+
+    fn set_master_mute(&mut self, mute: bool) -> std::result::Result<(), AudioError> {
+        let endpoint = self.master_endpoint()?;
+        let result = unsafe { endpoint.SetMute(mute, &EVENT_CONTEXT) };
+        Ok(self.watch(result)?)
+    }
+
+    fn get_device_volume(&mut self, device_id: &str) -> std::result::Result<MasterVolume, AudioError> {
+        let endpoint = self.device_endpoint(device_id)?;
+        let state = unsafe { endpoint.GetMasterVolumeLevelScalar().and_then(|v| Ok((v, endpoint.GetMute()?.as_bool()))) };
+        let (volume, is_muted) = self.watch(state)?;
+        let level = unsafe { gain::endpoint_level(&endpoint) }.ok();
+        Ok(MasterVolume { device_id: device_id.to_string(), volume, is_muted, level })
+    }
+
+    fn set_device_volume(&mut self, device_id: &str, volume: f32) -> std::result::Result<(), AudioError> {
+        let endpoint = self.device_endpoint(device_id)?;
+        let result = unsafe { endpoint.SetMasterVolumeLevelScalar(volume.clamp(0.0, 1.0), &EVENT_CONTEXT) };
+        Ok(self.watch(result)?)
+    }
+
+    fn set_device_mute(&mut self, device_id: &str, mute: bool) -> std::result::Result<(), AudioError> {
+        let endpoint = self.device_endpoint(device_id)?;
+        let result = unsafe { endpoint.SetMute(mute, &EVENT_CONTEXT) };
+        Ok(self.watch(result)?)
+    }
+
+    fn watch_device_volumes(&mut self, device_ids: Vec<String>) -> std::result::Result<(), AudioError> {
+        self.watched_devices = device_ids;
+        self.rewatch_devices();
+        Ok(())
+    }
+
+    /// 一般 (eConsole) とマルチメディア (eMultimedia) の既定を切り替えます。通話用の既定はそのままです。
+    fn set_default_device(&mut self, device_id: &str) -> std::result::Result<(), AudioError> {
+        let config = self.watch(policy_config::IPolicyConfig::new())?;
+        let device_id = HSTRING::from(device_id);
+        for role in [eConsole, eMultimedia] {
+            let result = unsafe { config.set_default_endpoint(&device_id, role) };
+            self.watch(result)?;
+        }
+        Ok(())
+    }
+
+    /// Windows はプロファイルごとに別のエンドポイントを公開するので、同じ機器で `profile` を公開しているエンドポイントを
+    /// すべての用途の既定にします。通話用のエンドポイントを使うアプリがなくなれば、機器は A2DP に戻ります。
+    fn set_bluetooth_profile(&mut self, device_id: &str, profile: BluetoothProfile) -> std::result::Result<(), AudioError> {
+        let target = unsafe { bluetooth::endpoint_for_profile(&self.device_enumerator, device_id, profile) }?;
+        let config = self.watch(policy_config::IPolicyConfig::new())?;
+        let target = HSTRING::from(target);
+        for role in [eConsole, eMultimedia, eCommunications] {
+            let result = unsafe { config.set_default_endpoint(&target, role) };
+            self.watch(result)?;
+        }
+        Ok(())
+    }
+
+    fn set_default_capture_device(&mut self, device_id: &str, role: Option<DeviceRole>) -> std::result::Result<(), AudioError> {
+        let config = self.watch(policy_config::IPolicyConfig::new())?;
+        let device_id = HSTRING::from(device_id);
+        let roles: Vec<ERole> = match role {
+            Some(role) => vec![erole(role)],
+            None => vec![eConsole, eMultimedia, eCommunications],
+        };
+        for role in roles {
+            let result = unsafe { config.set_default_endpoint(&device_id, role) };
+            self.watch(result)?;
+        }
+        Ok(())
+    }
+
+    fn set_input_monitor(&mut self, capture_device_id: &str, output_device_id: Option<&str>) -> std::result::Result<(), AudioError> {
+        let config = self.watch(policy_config::IPolicyConfig::new())?;
+        let result = unsafe { listen::set(&config, capture_device_id, output_device_id) };
+        self.watch(result)?;
+        Ok(())
+    }
+
+    fn get_input_gain(&mut self, device_id: &str) -> std::result::Result<InputGain, AudioError> {
+        let device = unsafe { self.device_enumerator.GetDevice(&HSTRING::from(device_id)) };
+        let device = self.watch(device)?;
+        let volume = unsafe { gain::volume_level(&device) };
+        let volume = self.watch(volume)?;
+        // トポロジーを公開しないドライバーもあるので、ブーストはたどれなければないものとする
+        let boost = unsafe { gain::boost_control(&device) }.ok().flatten()
+            .and_then(|boost| unsafe { gain::boost_level(&boost) }.ok());
+        Ok(InputGain { device_id: device_id.to_string(), volume, boost })
+    }
+
+    fn set_input_gain(&mut self, device_id: &str, db: f32) -> std::result::Result<(), AudioError> {
+        let device = unsafe { self.device_enumerator.GetDevice(&HSTRING::from(device_id)) };
+        let device = self.watch(device)?;
+        let result = unsafe { gain::set_volume_level(&device, db) };
+        Ok(self.watch(result)?)
+    }
+
+    fn set_input_boost(&mut self, device_id: &str, db: f32) -> std::result::Result<(), AudioError> {
+        let device = unsafe { self.device_enumerator.GetDevice(&HSTRING::from(device_id)) };
+        let device = self.watch(device)?;
+        let boost = unsafe { gain::boost_control(&device) };
+        let boost = self.watch(boost)?.ok_or_else(|| AudioError::Backend("This device has no microphone boost".to_string()))?;
+        let result = unsafe { gain::set_boost_level(&boost, db) };
+        Ok(self.watch(result)?)
+    }
+
+    fn get_default_endpoints(&mut self, role: DeviceRole) -> std::result::Result<DefaultEndpoints, AudioError> {
+        let role = erole(role);
+        let default_id = |flow: EDataFlow| unsafe {
+            let device = self.device_enumerator.GetDefaultAudioEndpoint(flow, role).ok()?;
+            let id_pwstr = device.GetId().ok()?;
+            let id = id_pwstr.to_string().ok();
+            CoTaskMemFree(Some(id_pwstr.as_ptr() as _));
+            id
+        };
+        Ok(DefaultEndpoints { output: default_id(eRender), input: default_id(eCapture) })
+    }
+
+    fn get_capture_effects(&mut self, device_id: &str) -> std::result::Result<Vec<CaptureEffect>, AudioError> {
+        let result = unsafe { effects::list(&self.device_enumerator, device_id) };
+        Ok(self.watch(result)?)
+    }
+
+    fn set_capture_effect(&mut self, device_id: &str, kind: CaptureEffectKind, enabled: bool) -> std::result::Result<(), AudioError> {
+        let result = unsafe { effects::set(&self.device_enumerator, device_id, kind, enabled) };
+        Ok(self.watch(result)?)
+    }
+
+    /// 一覧のセッション（非表示のアプリを含む）を持たないプロセスだけを調べます。
+    fn get_legacy_streams(&mut self) -> std::result::Result<Vec<LegacyStream>, AudioError> {
+        if self.cache_dirty {
+            self.rescan_sessions()?;
+        }
+        // 非表示のアプリは一覧から外れているので、セッションを見つけたときに開いたプロセスからも PID を集める
+        let with_sessions: HashSet<u32> = self.session_index.keys().chain(self.process_handles.keys()).copied().collect();
+        let system_sounds_active = self.session_cache.iter().any(|s| s.process_id == 0 && s.state == SessionState::Active);
+        let found = unsafe { legacy::find(&with_sessions) };
+        Ok(self.watch(found)?
+            .into_iter()
+            .map(|(pid, exe_name, api)| LegacyStream { pid, exe_name, api, system_sounds_active })
+            .collect())
+    }
+
+    /// 入力デバイスごとにセッションを列挙し、録音中 (Active) のものを返します。
+    /// セッションの通知は登録せず、呼ばれるたびに列挙し直します。
+    fn get_microphone_users(&mut self) -> std::result::Result<Vec<MicrophoneUser>, AudioError> {
+        let result = unsafe {
+            (|| -> Result<Vec<MicrophoneUser>> {
+                let mut users: Vec<MicrophoneUser> = Vec::new();
+                let collection = self.device_enumerator.EnumAudioEndpoints(eCapture, DEVICE_STATE_ACTIVE)?;
+                for i in 0..collection.GetCount()? {
+                    let device = collection.Item(i)?;
+                    let id_pwstr = device.GetId()?;
+                    let device_id = id_pwstr.to_string().unwrap_or_default();
+                    CoTaskMemFree(Some(id_pwstr.as_ptr() as _));
+                    let Ok(manager) = device.Activate::<IAudioSessionManager2>(CLSCTX_ALL, None) else { continue };
+                    let sessions = manager.GetSessionEnumerator()?;
+                    for j in 0..sessions.GetCount()? {
+                        let Ok(control) = sessions.GetSession(j)?.cast::<IAudioSessionControl2>() else { continue };
+                        if !control.GetState().is_ok_and(|state| session_state(state) == SessionState::Active) {
+                            continue;
+                        }
+                        let pid = control.GetProcessId().unwrap_or(0);
+                        if pid == 0 || users.iter().any(|u| u.pid == Some(pid) && u.device_id.as_deref() == Some(device_id.as_str())) {
+                            continue;
+                        }
+                        let exe_path = self.process_path(pid);
+                        let exe_name = exe_path.as_deref()
+                            .and_then(icon::exe_name_from_path)
+                            .unwrap_or_else(|| format!("PROCESS {}", pid));
+                        users.push(MicrophoneUser {
+                            pid: Some(pid),
+                            process_name: self.display_name(pid, exe_path.as_deref(), &exe_name),
+                            exe_name,
+                            device_id: Some(device_id.clone()),
+                        });
+                    }
+                }
+                Ok(users)
+            })()
+        };
+        Ok(self.watch(result)?)
+    }
+
+    fn probe_device_formats(&mut self, device_id: &str) -> std::result::Result<Vec<DeviceFormat>, AudioError> {
+        let device = unsafe { self.device_enumerator.GetDevice(&HSTRING::from(device_id)) };
+        let device = self.watch(device)?;
+        let formats = format::probe(&device);
+        Ok(self.watch(formats)?)
+    }
+
+    fn start_playback(&mut self, request: PlaybackRequest) -> std::result::Result<(), AudioError> {
+        // 同じデバイスを排他モードで開き直せるよう、先に止める
+        self.playback.clear();
+        self.playback.push(render::Playback::start(request, self.sink.clone())?);
+        Ok(())
+    }
+
+    fn start_synced_playback(&mut self, request: SyncedPlaybackRequest) -> std::result::Result<(), AudioError> {
+        self.playback.clear();
+        self.playback = render::Playback::start_synced(request, self.sink.clone())?;
+        Ok(())
+    }
+
+    fn set_playback_delay(&mut self, device_id: &str, delay_ms: u32) -> std::result::Result<(), AudioError> {
+        let playback = self.playback.iter()
+            .find(|p| p.device_id.as_deref() == Some(device_id))
+            .ok_or_else(|| AudioError::Backend(format!("Nothing is playing on {}", device_id)))?;
+        playback.set_delay(delay_ms)
+    }
+
+    fn stop_playback(&mut self) {
+        self.playback.clear();
+    }
+
+    fn set_metronome_tempo(&mut self, bpm: f32) -> std::result::Result<(), AudioError> {
+        if self.playback.is_empty() {
+            return Err(AudioError::Backend("The metronome is not running".to_string()));
+        }
+        self.playback.iter().try_for_each(|p| p.set_tempo(bpm))
+    }
+
+    fn seek_playback(&mut self, position_ms: u64) -> std::result::Result<(), AudioError> {
+        if self.playback.is_empty() {
+            return Err(AudioError::Backend("No audio file is playing".to_string()));
+        }
+        self.playback.iter().try_for_each(|p| p.seek(position_ms))
+    }
+
+    fn start_recording(&mut self, device_id: Option<&str>, max_ms: u32) -> std::result::Result<(), AudioError> {
+        self.recorder = None;
+        self.recorder = Some(capture::Recorder::start(device_id.map(str::to_string), max_ms)?);
+        Ok(())
+    }
+
+    fn stop_recording(&mut self) -> std::result::Result<Recording, AudioError> {
+        let recorder = self.recorder.take().ok_or_else(|| AudioError::Backend("Not recording".to_string()))?;
+        Ok(recorder.finish())
+    }
+
+    fn start_file_capture(&mut self, request: FileCaptureRequest) -> std::result::Result<(), AudioError> {
+        self.file_capture = None;
+        self.file_capture = Some(capture::FileCapture::start(request, self.sink.clone())?);
+        Ok(())
+    }
+
+    fn stop_file_capture(&mut self) -> std::result::Result<Vec<String>, AudioError> {
+        let capture = self.file_capture.take().ok_or_else(|| AudioError::Backend("Not recording to a file".to_string()))?;
+        capture.finish()
+    }
+
+    fn start_network_stream(&mut self, request: NetworkStreamRequest) -> std::result::Result<NetworkStream, AudioError> {
+        // 同じポートを開き直せるよう、先に止める
+        self.network_stream = None;
+        let capture = capture::NetworkCapture::start(request, self.sink.clone())?;
+        let info = capture.info();
+        self.network_stream = Some(capture);
+        Ok(info)
+    }
+
+    fn stop_network_stream(&mut self) {
+        self.network_stream = None;
+    }
+
+    fn get_network_stream(&mut self) -> Option<NetworkStream> {
+        if self.network_stream.as_ref().is_some_and(|c| c.is_finished()) {
+            self.network_stream = None;
+        }
+        self.network_stream.as_ref().map(|c| c.info())
+    }
+
+    fn start_mirror(&mut self, request: MirrorRequest) -> std::result::Result<(), AudioError> {
+        // 同じアプリの取り込みを二重に開かないよう、先に止める
+        self.mirrors.remove(&request.pid);
+        let pid = request.pid;
+        let mirror = mirror::Mirror::start(request, self.sink.clone())?;
+        self.mirrors.insert(pid, mirror);
+        Ok(())
+    }
+
+    fn set_mirror_delay(&mut self, pid: u32, delay_ms: u32) -> std::result::Result<(), AudioError> {
+        let mirror = self.mirrors.get_mut(&pid).ok_or_else(|| AudioError::Backend(format!("No mirror is running for PID {}", pid)))?;
+        MirrorRequest { delay_ms, ..mirror.request.clone() }.validate()?;
+        mirror.set_delay(delay_ms);
+        Ok(())
+    }
+
+    fn stop_mirror(&mut self, pid: u32) {
+        self.mirrors.remove(&pid);
+    }
+
+    fn get_mirrors(&mut self) -> Vec<MirrorRequest> {
+        self.mirrors.retain(|_, m| !m.is_finished());
+        self.mirrors.values().map(|m| m.request.clone()).collect()
+    }
+
+    fn start_boost(&mut self, request: BoostRequest) -> std::result::Result<(), AudioError> {
+        request.validate()?;
+        if self.cache_dirty {
+            self.rescan_sessions()?;
+        }
+        let device_id = self.session_cache.iter()
+            .find(|s| s.process_id == request.pid)
+            .map(|s| s.device_id.clone())
+            .ok_or(AudioError::SessionNotFound(request.pid))?;
+        // 同じアプリの取り込みを二重に開かないよう、先に止める
+        self.boosts.remove(&request.pid);
+        let pid = request.pid;
+        let boost = boost::Boost::start(request, device_id, self.sink.clone())?;
+        self.boosts.insert(pid, boost);
+        Ok(())
+    }
+
+    fn set_boost_gain(&mut self, pid: u32, gain_db: f32) -> std::result::Result<(), AudioError> {
+        let boost = self.boosts.get_mut(&pid).ok_or_else(|| AudioError::Backend(format!("No boost is running for PID {}", pid)))?;
+        BoostRequest { pid, gain_db }.validate()?;
+        boost.set_gain(gain_db);
+        Ok(())
+    }
+
+    fn stop_boost(&mut self, pid: u32) {
+        self.boosts.remove(&pid);
+    }
+
+    fn get_boosts(&mut self) -> Vec<BoostRequest> {
+        self.boosts.retain(|_, b| !b.is_finished());
+        self.boosts.values().map(|b| b.request.clone()).collect()
+    }
+
+    fn start_input_meter(&mut self, device_id: Option<&str>) -> std::result::Result<(), AudioError> {
+        self.input_meter = None;
+        let meter = unsafe { capture::InputMeter::open(&self.device_enumerator, device_id) };
+        self.input_meter = Some(self.watch(meter)?);
+        Ok(())
+    }
+
+    fn stop_input_meter(&mut self) {
+        self.input_meter = None;
+    }
+
+    fn get_input_peak(&mut self) -> Option<InputPeak> {
+        let meter = self.input_meter.as_ref()?;
+        let peak = unsafe { meter.peak() };
+        let device_id = meter.device_id.clone();
+        let peak = self.watch(peak).ok()?;
+        Some(InputPeak { device_id, peak })
+    }
+
+    fn handle_notification(&mut self, notification: AudioNotification) {
+        match notification {
+            AudioNotification::VolumeChanged { session_key, volume, muted, origin } => {
+                let updated = self.session_cache.iter_mut()
+                    .find(|s| s.instance_id == session_key)
+                    .map(|session| {
+                        session.volume = volume;
+                        session.is_muted = muted;
+                        session.process_id
+                    });
+                if let Some(pid) = updated {
+                    self.sessions_changed = true;
+                    self.sink.emit(MixerEvent::VolumeChanged { pid, volume, muted, origin });
+                }
+            }
+            AudioNotification::StateChanged { session_key, state } => {
+                let pid = self.session_cache.iter_mut()
+                    .find(|s| s.instance_id == session_key)
+                    .map(|s| {
+                        s.state = state;
+                        s.process_id
+                    })
+                    .unwrap_or(0);
+                if state == SessionState::Expired {
+                    self.remove_session(&session_key);
+                } else {
+                    self.sessions_changed = true;
+                }
+                self.sink.emit(MixerEvent::SessionStateChanged { pid, state: format!("{:?}", state) });
+            }
+            AudioNotification::SessionDisconnected { session_key } => {
+                self.remove_session(&session_key);
+            }
+            AudioNotification::IconLoaded { exe_path, mtime, icon_base64 } => {
+                self.icon_loaded(exe_path, mtime, icon_base64);
+            }
+            AudioNotification::SessionCreated => {
+                self.cache_dirty = true;
+            }
+            AudioNotification::DevicesChanged => {
+                self.cache_dirty = true;
+                self.sink.emit(MixerEvent::DevicesChanged);
+                self.watch_master();
+                self.rewatch_devices();
+                // 既定のデバイスが替わった。誰が替えたかは通知からは分からない
+                self.publish_master(ChangeOrigin::Unknown);
+                // 既定の入力デバイスが変わっていれば開き直す。外されたデバイスは計測をやめる
+                if let Some(meter) = &self.input_meter {
+                    let follows_default = meter.follows_default;
+                    let reopened = if follows_default {
+                        unsafe { capture::InputMeter::open(&self.device_enumerator, None) }.ok()
+                            .filter(|m| m.device_id != meter.device_id)
+                    } else {
+                        None
+                    };
+                    if let Some(reopened) = reopened {
+                        self.input_meter = Some(reopened);
+                    }
+                }
+            }
+            AudioNotification::MasterVolumeChanged { origin } => self.publish_master(origin),
+            AudioNotification::DeviceVolumeChanged { device_id, origin } => {
+                if let Ok(volume) = self.get_device_volume(&device_id) {
+                    self.sink.emit(MixerEvent::DeviceVolumeChanged(volume, origin));
+                }
+            }
+            AudioNotification::NowPlayingChanged => {
+                self.media.refresh();
+                self.apply_now_playing();
+            }
+        }
+    }
+
+    fn send_media_command(&mut self, pid: u32, command: MediaCommand) -> std::result::Result<(), AudioError> {
+        if self.cache_dirty {
+            self.rescan_sessions()?;
+        }
+        let session = self.session_cache.iter().find(|s| s.process_id == pid).ok_or(AudioError::SessionNotFound(pid))?;
+        if session.now_playing.is_none() {
+            return Err(AudioError::Backend(format!("No media session for PID {}", pid)));
+        }
+        if self.media.send(&session.exe_name, command)? {
+            Ok(())
+        } else {
+            Err(AudioError::Backend(format!("{} did not accept the media command", session.exe_name)))
+        }
+    }
+
+    fn set_session_volume(&mut self, pid: u32, volume: f32) -> std::result::Result<(), AudioError> {
+        self.apply_to_session(pid, |sv| unsafe { sv.SetMasterVolume(volume, &EVENT_CONTEXT) })
+    }
+
+    fn set_session_mute(&mut self, pid: u32, mute: bool) -> std::result::Result<(), AudioError> {
+        self.apply_to_session(pid, |sv| unsafe { sv.SetMute(mute, &EVENT_CONTEXT) })
+    }
+
+    fn set_instance_volume(&mut self, instance_id: &str, volume: f32) -> std::result::Result<(), AudioError> {
+        self.apply_to_instance(instance_id, |sv| unsafe { sv.SetMasterVolume(volume, &EVENT_CONTEXT) })
+    }
+
+    fn set_instance_mute(&mut self, instance_id: &str, mute: bool) -> std::result::Result<(), AudioError> {
+        self.apply_to_instance(instance_id, |sv| unsafe { sv.SetMute(mute, &EVENT_CONTEXT) })
+    }
+
+    fn set_audio_routing(&mut self, pid: u32, device_id: &str) -> std::result::Result<(), AudioError> {
+        let config = self.policy_config()?;
+        let endpoint_hstring = HSTRING::from(device_id);
+        unsafe {
+            // 3つの役割すべてに対して設定を行うことで、確実な切り替えを実現
+            let _ = config.set_persisted_default_endpoint(pid, eConsole, &endpoint_hstring);
+            let _ = config.set_persisted_default_endpoint(pid, eMultimedia, &endpoint_hstring);
+            let _ = config.set_persisted_default_endpoint(pid, eCommunications, &endpoint_hstring);
+        }
+        // 永続化した出力先と実際の出力先を取り直す
+        self.cache_dirty = true;
+        Ok(())
+    }
+
+    /// 空の ID を永続化すると、プロセスの出力先の設定が消えます。
+    fn clear_audio_routing(&mut self, pid: u32) -> std::result::Result<(), AudioError> {
+        let config = self.policy_config()?;
+        unsafe {
+            for role in [eConsole, eMultimedia, eCommunications] {
+                config.set_persisted_default_endpoint(pid, role, &HSTRING::new())?;
+            }
+        }
+        self.cache_dirty = true;
+        Ok(())
+    }
+
+    /// 既定の出力デバイスを役割ごとに出力先へ切り替えてすぐ戻し、既定のデバイスの変更を追いかけるアプリに
+    /// ストリームを開き直させます。開き直すときは永続化した出力先が使われます。
+    /// 切り替えている間、ほかのアプリの音も一瞬だけ出力先から鳴ります。
+    fn force_route_migration(&mut self, pid: u32) -> std::result::Result<(), AudioError> {
+        let target = self.persisted_route(pid)
+            .ok_or_else(|| AudioError::Backend(format!("No output device is set for PID {}", pid)))?;
+        let config = self.watch(policy_config::IPolicyConfig::new())?;
+        let target_id = HSTRING::from(target.as_str());
+        for role in [DeviceRole::Console, DeviceRole::Multimedia, DeviceRole::Communications] {
+            let Some(previous) = self.get_default_endpoints(role)?.output else { continue };
+            if previous == target {
+                continue;
+            }
+            let result = unsafe { config.set_default_endpoint(&target_id, erole(role)) };
+            self.watch(result)?;
+            let result = unsafe { config.set_default_endpoint(&HSTRING::from(previous), erole(role)) };
+            self.watch(result)?;
+        }
+        self.cache_dirty = true;
+        Ok(())
+    }
+
+    fn get_audio_devices(&mut self) -> std::result::Result<Vec<AudioDeviceInfo>, AudioError> {
+        let result = self.enumerate_devices(eRender);
+        Ok(self.watch(result)?)
+    }
+
+    fn get_capture_devices(&mut self) -> std::result::Result<Vec<AudioDeviceInfo>, AudioError> {
+        let result = self.enumerate_devices(eCapture);
+        Ok(self.watch(result)?)
+    }
+
+    fn get_peak_levels(&mut self) -> std::result::Result<Vec<PeakLevel>, AudioError> {
+        let mut peaks = Vec::new();
+        let mut lost = false;
+        for (pid, meter) in self.meter_cache.values() {
+            match unsafe { meter.GetPeakValue() } {
+                Ok(peak) => peaks.push(PeakLevel { pid: *pid, peak }),
+                Err(e) => lost |= SERVICE_LOST_ERRORS.contains(&e.code()),
+            }
+        }
+        self.lost |= lost;
+        Ok(peaks)
+    }
+
+    fn check_app_updates(&mut self) {
+        if self.last_app_check.elapsed() < APP_UPDATE_INTERVAL {
+            return;
+        }
+        self.last_app_check = Instant::now();
+        let updated: Vec<String> = self.exe_mtimes.iter_mut()
+            .filter_map(|(path, mtime)| {
+                let current = icon_cache::file_mtime(path);
+                // 更新の途中で exe が消えているあいだは、置き換わるのを待つ
+                (current != 0 && current != *mtime).then(|| {
+                    *mtime = current;
+                    path.clone()
+                })
+            })
+            .collect();
+        for path in updated {
+            self.app_updated(&path);
+        }
+    }
+
+    /// 検出済みのエラーに加え、一定間隔でセッションマネージャーに問い合わせて接続を確認します。
+    fn check_health(&mut self) -> bool {
+        if !self.lost && self.last_probe.elapsed() >= HEALTH_PROBE_INTERVAL {
+            self.last_probe = Instant::now();
+            let probe = match self.session_managers.values().next() {
+                Some((manager, _)) => unsafe { manager.GetSessionEnumerator() }.map(|_| ()),
+                None => unsafe { self.device_enumerator.EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE) }.map(|_| ()),
+            };
+            let _ = self.watch(probe);
+        }
+        !self.lost
+    }
+}
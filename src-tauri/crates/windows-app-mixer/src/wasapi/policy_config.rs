@@ -0,0 +1,51 @@
+use windows::core::{IUnknown, IUnknown_Vtbl, Interface, GUID, PCWSTR, HRESULT, PROPVARIANT};
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::Media::Audio::ERole;
+use windows::Win32::UI::Shell::PropertiesSystem::PROPERTYKEY;
+
+// 非公開インターフェース IPolicyConfig の定義
+// Windows Vista 以降のサウンド設定が既定のデバイスの切り替えに使っているもので、
+// VTable Index 12 が SetPropertyValue、13 が SetDefaultEndpoint に相当します。
+
+#[repr(C)]
+#[allow(non_snake_case)]
+pub struct IPolicyConfig_Vtbl {
+    pub base: IUnknown_Vtbl,
+    // GetMixFormat から GetPropertyValue まで
+    pub reserved: [usize; 9],
+    pub SetPropertyValue: unsafe extern "system" fn(this: *mut core::ffi::c_void, device_id: PCWSTR, fx_store: BOOL, key: *const PROPERTYKEY, value: *const PROPVARIANT) -> HRESULT,
+    pub SetDefaultEndpoint: unsafe extern "system" fn(this: *mut core::ffi::c_void, device_id: PCWSTR, role: ERole) -> HRESULT,
+}
+
+#[repr(transparent)]
+#[derive(Clone, PartialEq, Eq)]
+pub struct IPolicyConfig(IUnknown);
+
+unsafe impl Interface for IPolicyConfig {
+    type Vtable = IPolicyConfig_Vtbl;
+    const IID: GUID = GUID::from_u128(0xf8679f50_850a_41cf_9c72_430f290290c8); // IID_IPolicyConfig
+}
+
+impl IPolicyConfig {
+    pub fn new() -> windows::core::Result<Self> {
+        unsafe {
+            windows::Win32::System::Com::CoCreateInstance(
+                &GUID::from_u128(0x870af99c_171d_4f9e_af0d_e63df40c2bc9), // CLSID_PolicyConfigClient
+                None,
+                windows::Win32::System::Com::CLSCTX_ALL,
+            )
+        }
+    }
+
+    /// 役割 (`role`) ごとのシステムの既定のエンドポイントを変更します。
+    pub unsafe fn set_default_endpoint(&self, device_id: &windows::core::HSTRING, role: ERole) -> windows::core::Result<()> {
+        let vtbl = self.vtable();
+        (vtbl.SetDefaultEndpoint)(self.as_raw(), PCWSTR(device_id.as_ptr()), role).ok()
+    }
+
+    /// エンドポイントのプロパティを書き換えます。プロパティストアを直接開くのと違い、管理者権限がいりません。
+    pub unsafe fn set_property_value(&self, device_id: &windows::core::HSTRING, key: &PROPERTYKEY, value: *const PROPVARIANT) -> windows::core::Result<()> {
+        let vtbl = self.vtable();
+        (vtbl.SetPropertyValue)(self.as_raw(), PCWSTR(device_id.as_ptr()), false.into(), key, value).ok()
+    }
+}
@@ -15,6 +15,8 @@ pub struct IAudioPolicyConfig_Vtbl {
     // ... 中間のメソッド ...
     pub reserved: [usize; 21], // インデックス 25 までのパディング
     pub SetPersistedDefaultAudioEndpoint: unsafe extern "system" fn(this: *mut core::ffi::c_void, process_id: u32, role: ERole, endpoint_id: HSTRING) -> HRESULT,
+    // インデックス 26。Set と対になる、プロセスごとの設定の取得
+    pub GetPersistedDefaultAudioEndpointForProcess: unsafe extern "system" fn(this: *mut core::ffi::c_void, process_id: u32, role: ERole, endpoint_id: *mut HSTRING) -> HRESULT,
 }
 
 #[repr(transparent)]
@@ -32,6 +34,14 @@ impl IAudioPolicyConfig {
         let vtbl = self.vtable();
         (vtbl.SetPersistedDefaultAudioEndpoint)(core::mem::transmute_copy(self), process_id, role, endpoint_id.clone()).ok()
     }
+
+    /// 特定のプロセスに永続的に設定されている出力先を取得します。未設定なら空文字列です。
+    pub unsafe fn get_persisted_default_endpoint(&self, process_id: u32, role: ERole) -> windows::core::Result<HSTRING> {
+        let vtbl = self.vtable();
+        let mut endpoint_id = HSTRING::new();
+        (vtbl.GetPersistedDefaultAudioEndpointForProcess)(core::mem::transmute_copy(self), process_id, role, &mut endpoint_id).ok()?;
+        Ok(endpoint_id)
+    }
 }
 
 pub struct AudioPolicyConfigFactory;
@@ -47,6 +57,3 @@ impl AudioPolicyConfigFactory {
         }
     }
 }
-
-unsafe impl Send for IAudioPolicyConfig {}
-unsafe impl Sync for IAudioPolicyConfig {}
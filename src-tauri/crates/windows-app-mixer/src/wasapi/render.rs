@@ -0,0 +1,347 @@
+//! ジェネレーターの出力。専用のスレッドで `IAudioClient` をイベント駆動で動かします。
+//! 排他モードでは指定されたフォーマットのままデバイスへ渡すため、OS のミキサーとリサンプラーを通りません。
+//! 共有モードで指定されたフォーマットは、OS がミックスフォーマットに変換します。
+//! どのストリームも [`GENERATOR_SESSION_GUID`] のセッションに入れ、ミキサーに「Tone Generator」として並べます。
+//! 複数のデバイスで同時に鳴らすときは、すべてのデバイスを開いてバッファを埋めてから一斉に始めます。
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use windows::core::{Result, GUID, HSTRING};
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::Media::Audio::{
+    eConsole, eRender, IAudioClient, IAudioRenderClient, IAudioSessionControl, IMMDevice, IMMDeviceEnumerator, MMDeviceEnumerator,
+    AUDCLNT_E_BUFFER_SIZE_NOT_ALIGNED, AUDCLNT_SHAREMODE_EXCLUSIVE, AUDCLNT_SHAREMODE_SHARED,
+    AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM, AUDCLNT_STREAMFLAGS_EVENTCALLBACK, AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY,
+};
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_ALL};
+use windows::Win32::System::Threading::{CreateEventW, WaitForSingleObject};
+
+use super::{com, format};
+use crate::audio_file::{AudioFileSource, SeekRequest};
+use crate::generator::{
+    encode_samples, validate_bpm, validate_delay, DelayedSource, PlaybackRequest, Signal, SignalSource, SyncedPlaybackRequest, Tempo,
+    GENERATOR_SESSION_GUID, GENERATOR_SESSION_NAME,
+};
+use crate::{AudioError, DeviceFormat, MixerEvent, MixerEventSink};
+
+/// 共有モードで確保するバッファの長さ (100ns 単位、50ms)
+const SHARED_BUFFER_DURATION: i64 = 500_000;
+
+/// 音声ファイルの再生位置を知らせる間隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+const SESSION_GUID: GUID = GUID::from_u128(GENERATOR_SESSION_GUID);
+
+/// バッファの要求を待つ最長時間 (ms)。停止要求に気付くための上限
+const WAIT_TIMEOUT_MS: u32 = 200;
+
+/// 同期再生で、すべてのデバイスの準備ができるまで再生を始めさせない門
+#[derive(Default)]
+struct StartGate {
+    open: Mutex<bool>,
+    opened: Condvar,
+}
+
+impl StartGate {
+    fn open(&self) {
+        if let Ok(mut open) = self.open.lock() {
+            *open = true;
+            self.opened.notify_all();
+        }
+    }
+
+    fn wait(&self) {
+        let Ok(open) = self.open.lock() else { return };
+        let _open = self.opened.wait_while(open, |open| !*open);
+    }
+}
+
+/// 再生中のジェネレーター。Drop で再生を止め、スレッドの終了を待ちます。
+pub struct Playback {
+    /// 出力先。`None` なら既定のデバイス
+    pub device_id: Option<String>,
+    /// メトロノームでなければ `None`
+    tempo: Option<Tempo>,
+    /// 音声ファイルでなければ `None`
+    seek: Option<SeekRequest>,
+    delay_ms: Arc<AtomicU32>,
+    /// 同期再生のときだけ
+    gate: Option<Arc<StartGate>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Playback {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // 門の前で待っているスレッドを起こす
+        if let Some(gate) = &self.gate {
+            gate.open();
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Playback {
+    /// 出力スレッドを起動し、デバイスを開けたかどうかが分かるまで待ちます。
+    pub fn start(request: PlaybackRequest, sink: Arc<dyn MixerEventSink>) -> std::result::Result<Self, AudioError> {
+        request.validate()?;
+        Self::spawn(request, 0, None, sink)
+    }
+
+    /// 同じ信号をすべての出力先で開き、そろったところで一斉に鳴らし始めます。どれかを開けなければ、どれも鳴らしません。
+    pub fn start_synced(request: SyncedPlaybackRequest, sink: Arc<dyn MixerEventSink>) -> std::result::Result<Vec<Self>, AudioError> {
+        request.validate()?;
+        let gate = Arc::new(StartGate::default());
+        let mut playbacks = Vec::new();
+        for (playback, output) in request.requests().into_iter().zip(&request.outputs) {
+            // 失敗したら、開けた分は Drop で門を開けて止める
+            playbacks.push(Self::spawn(playback, output.delay_ms, Some(gate.clone()), sink.clone())?);
+        }
+        gate.open();
+        Ok(playbacks)
+    }
+
+    fn spawn(
+        request: PlaybackRequest,
+        delay_ms: u32,
+        gate: Option<Arc<StartGate>>,
+        sink: Arc<dyn MixerEventSink>,
+    ) -> std::result::Result<Self, AudioError> {
+        let tempo = match &request.signal {
+            Signal::Metronome(metronome) => Some(Tempo::new(metronome.bpm)),
+            _ => None,
+        };
+        let seek = matches!(request.signal, Signal::File { .. }).then(SeekRequest::default);
+        let device_id = request.device_id.clone();
+        let delay_ms = Arc::new(AtomicU32::new(delay_ms));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread = {
+            let tempo = tempo.clone().unwrap_or(Tempo::new(0.0));
+            let (seek, delay_ms, gate, stop) = (seek.clone().unwrap_or_default(), delay_ms.clone(), gate.clone(), stop.clone());
+            thread::Builder::new()
+                .name("generator-render".into())
+                .spawn(move || render_thread(request, tempo, seek, delay_ms, gate, stop, ready_tx, sink))
+                .map_err(|e| AudioError::Backend(format!("Failed to start playback thread: {}", e)))?
+        };
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self { device_id, tempo, seek, delay_ms, gate, stop, thread: Some(thread) }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err(AudioError::Backend("Playback thread exited unexpectedly".to_string())),
+        }
+    }
+
+    /// 遅延を、止めずに変えます。
+    pub fn set_delay(&self, delay_ms: u32) -> std::result::Result<(), AudioError> {
+        validate_delay(delay_ms)?;
+        self.delay_ms.store(delay_ms, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// メトロノームのテンポを、止めずに変えます。
+    pub fn set_tempo(&self, bpm: f32) -> std::result::Result<(), AudioError> {
+        let tempo = self.tempo.as_ref().ok_or_else(|| AudioError::Backend("The metronome is not running".to_string()))?;
+        validate_bpm(bpm)?;
+        tempo.set(bpm);
+        Ok(())
+    }
+
+    /// 音声ファイルの再生位置を移します。
+    pub fn seek(&self, position_ms: u64) -> std::result::Result<(), AudioError> {
+        let seek = self.seek.as_ref().ok_or_else(|| AudioError::Backend("No audio file is playing".to_string()))?;
+        seek.request(position_ms);
+        Ok(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_thread(
+    mut request: PlaybackRequest,
+    tempo: Tempo,
+    seek: SeekRequest,
+    delay_ms: Arc<AtomicU32>,
+    gate: Option<Arc<StartGate>>,
+    stop: Arc<AtomicBool>,
+    ready: mpsc::Sender<std::result::Result<(), AudioError>>,
+    sink: Arc<dyn MixerEventSink>,
+) {
+    let _com = com::init_mta();
+    // 音声ファイルは先に開いて、ファイルのままのフォーマットで出力する
+    let file = match &request.signal {
+        Signal::File { path } => match AudioFileSource::open(path, seek) {
+            Ok(file) => {
+                request.output.format = Some(file.format());
+                Some(file)
+            }
+            Err(e) => {
+                let _ = ready.send(Err(e));
+                return;
+            }
+        },
+        _ => None,
+    };
+    let stream = match unsafe { Stream::open(&request) } {
+        Ok(stream) => stream,
+        Err(e) => {
+            let _ = ready.send(Err(e));
+            return;
+        }
+    };
+    let _ = ready.send(Ok(()));
+    let sample_rate = stream.format.sample_rate;
+    let source: Box<dyn SignalSource> = match file {
+        Some(file) => Box::new(file),
+        None => request.signal.source(sample_rate, tempo),
+    };
+    let mut source = DelayedSource::new(source, sample_rate, delay_ms);
+    let result = unsafe { stream.run(&mut source, gate.as_deref(), &stop, &*sink) };
+    sink.emit(MixerEvent::PlaybackStopped { error: result.err().map(|e| e.to_string()) });
+}
+
+struct Stream {
+    client: IAudioClient,
+    render: IAudioRenderClient,
+    event: HANDLE,
+    format: DeviceFormat,
+    exclusive: bool,
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.client.Stop();
+            let _ = CloseHandle(self.event);
+        }
+    }
+}
+
+impl Stream {
+    unsafe fn open(request: &PlaybackRequest) -> std::result::Result<Self, AudioError> {
+        let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let device = match &request.device_id {
+            Some(id) => enumerator.GetDevice(&HSTRING::from(id.as_str()))?,
+            None => enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?,
+        };
+        let mut client: IAudioClient = device.Activate(CLSCTX_ALL, None)?;
+        let (mix, mix_mask) = format::mix_format(&client)?;
+
+        let output = request.output;
+        let format = output.format.unwrap_or(mix);
+        // チャンネル数がミックスフォーマットと違う場合は、スピーカー配置を指定しない
+        let mask = if format.channels == mix.channels { mix_mask } else { 0 };
+        let buffer = output.buffer_ms.map(|ms| i64::from(ms) * 10_000);
+        if output.exclusive {
+            if client.IsFormatSupported(AUDCLNT_SHAREMODE_EXCLUSIVE, &format::wave_format(&format, mask).Format, None).0 != 0 {
+                return Err(AudioError::Backend(format!("The device does not accept {:?} in exclusive mode", format)));
+            }
+            let mut minimum = 0i64;
+            client.GetDevicePeriod(None, Some(&mut minimum))?;
+            if buffer.is_some_and(|b| b < minimum) {
+                return Err(AudioError::Backend(format!("The device needs a buffer of at least {:.1} ms", minimum as f64 / 10_000.0)));
+            }
+            client = initialize_exclusive(&device, client, &format, mask, buffer)?;
+        } else {
+            let wave = format::wave_format(&format, mask);
+            // ミックスフォーマットでなければ OS に変換させる
+            let flags = match output.format {
+                Some(_) => AUDCLNT_STREAMFLAGS_EVENTCALLBACK | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM | AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY,
+                None => AUDCLNT_STREAMFLAGS_EVENTCALLBACK,
+            };
+            client.Initialize(AUDCLNT_SHAREMODE_SHARED, flags, buffer.unwrap_or(SHARED_BUFFER_DURATION), 0, &wave.Format, Some(&SESSION_GUID))?;
+        }
+
+        // 表示名はセッションに残るので、開くたびに付け直しても変わらない
+        let session: IAudioSessionControl = client.GetService()?;
+        let _ = session.SetDisplayName(&HSTRING::from(GENERATOR_SESSION_NAME), std::ptr::null());
+
+        let event = CreateEventW(None, false, false, None)?;
+        let stream = Self {
+            render: client.GetService()?,
+            client,
+            event,
+            format,
+            exclusive: output.exclusive,
+        };
+        stream.client.SetEventHandle(stream.event)?;
+        Ok(stream)
+    }
+
+    /// 信号源が終わるか停止を要求されるまで、デバイスの要求に応じてバッファを埋めます。
+    unsafe fn run(&self, source: &mut dyn SignalSource, gate: Option<&StartGate>, stop: &AtomicBool, sink: &dyn MixerEventSink) -> Result<()> {
+        let buffer_frames = self.client.GetBufferSize()?;
+        let channels = usize::from(self.format.channels);
+        let mut samples = vec![0f32; buffer_frames as usize * channels];
+        let mut bytes = vec![0u8; samples.len() * usize::from(self.format.container_bits / 8)];
+
+        // 開始直後の途切れを防ぐため、先にバッファ全体を埋めておく
+        let mut playing = self.write(source, buffer_frames, &mut samples, &mut bytes)?;
+        if let Some(gate) = gate {
+            gate.wait();
+            if stop.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+        }
+        self.client.Start()?;
+        let mut last_progress: Option<Instant> = None;
+        while playing && !stop.load(Ordering::Relaxed) {
+            if last_progress.is_none_or(|at| at.elapsed() >= PROGRESS_INTERVAL) {
+                if let Some((position_ms, duration_ms)) = source.progress_ms() {
+                    sink.emit(MixerEvent::PlaybackProgress { position_ms, duration_ms });
+                }
+                last_progress = Some(Instant::now());
+            }
+            WaitForSingleObject(self.event, WAIT_TIMEOUT_MS);
+            // 排他モードのイベント駆動では、毎回バッファ全体を渡す
+            let frames = if self.exclusive { buffer_frames } else { buffer_frames - self.client.GetCurrentPadding()? };
+            if frames > 0 {
+                playing = self.write(source, frames, &mut samples, &mut bytes)?;
+            }
+        }
+        if !playing {
+            // 書き込んだ分を鳴らし終えるまで待つ
+            let period_ms = u64::from(buffer_frames) * 1000 / u64::from(self.format.sample_rate);
+            thread::sleep(Duration::from_millis(period_ms));
+        }
+        Ok(())
+    }
+
+    unsafe fn write(&self, source: &mut dyn SignalSource, frames: u32, samples: &mut [f32], bytes: &mut [u8]) -> Result<bool> {
+        let channels = usize::from(self.format.channels);
+        let count = frames as usize * channels;
+        let more = source.fill(&mut samples[..count], channels);
+        let len = count * usize::from(self.format.container_bits / 8);
+        encode_samples(&samples[..count], &self.format, &mut bytes[..len]);
+        let buffer = self.render.GetBuffer(frames)?;
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, len);
+        self.render.ReleaseBuffer(frames, 0)?;
+        Ok(more)
+    }
+}
+
+/// 排他モードで、`buffer`（100ns 単位、`None` ならデバイスの既定の周期）を周期として初期化します。
+/// バッファサイズがデバイスの境界に揃っていないと言われた場合は、示されたサイズに合わせた周期で作り直します。
+unsafe fn initialize_exclusive(device: &IMMDevice, client: IAudioClient, format: &DeviceFormat, mask: u32, buffer: Option<i64>) -> Result<IAudioClient> {
+    let wave = format::wave_format(format, mask);
+    let mut period = 0i64;
+    client.GetDevicePeriod(Some(&mut period), None)?;
+    let period = buffer.unwrap_or(period);
+    match client.Initialize(AUDCLNT_SHAREMODE_EXCLUSIVE, AUDCLNT_STREAMFLAGS_EVENTCALLBACK, period, period, &wave.Format, Some(&SESSION_GUID)) {
+        Err(e) if e.code() == AUDCLNT_E_BUFFER_SIZE_NOT_ALIGNED => {
+            let frames = client.GetBufferSize()?;
+            let period = (10_000_000.0 * f64::from(frames) / f64::from(format.sample_rate)).round() as i64;
+            let client: IAudioClient = device.Activate(CLSCTX_ALL, None)?;
+            client.Initialize(AUDCLNT_SHAREMODE_EXCLUSIVE, AUDCLNT_STREAMFLAGS_EVENTCALLBACK, period, period, &wave.Format, Some(&SESSION_GUID))?;
+            Ok(client)
+        }
+        result => result.map(|_| client),
+    }
+}
@@ -0,0 +1,54 @@
+use std::ffi::c_void;
+
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Storage::FileSystem::{GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW};
+
+/// 翻訳表にない exe でも多くが持っている、英語 (米国)・Unicode のブロック
+const NEUTRAL_BLOCK: &str = "040904b0";
+
+/// exe のバージョン情報から表示名を取り出します。製品名 (ProductName) を、翻訳表のすべての言語と
+/// 英語のブロックで探し、どこにもなければファイルの説明 (FileDescription) で同じように探します。
+pub fn product_name(exe_path: &str) -> Option<String> {
+    let path = HSTRING::from(exe_path);
+    unsafe {
+        let size = GetFileVersionInfoSizeW(&path, None);
+        if size == 0 {
+            return None;
+        }
+        let mut data = vec![0u8; size as usize];
+        GetFileVersionInfoW(&path, 0, size, data.as_mut_ptr().cast()).ok()?;
+
+        // 翻訳表は (言語, コードページ) の WORD の組の並び
+        let mut blocks: Vec<String> = query(&data, "\\VarFileInfo\\Translation")
+            .map(|(value, len)| {
+                let pairs = std::slice::from_raw_parts(value as *const u16, len / 2);
+                pairs.chunks_exact(2).map(|p| format!("{:04x}{:04x}", p[0], p[1])).collect()
+            })
+            .unwrap_or_default();
+        if !blocks.iter().any(|b| b == NEUTRAL_BLOCK) {
+            blocks.push(NEUTRAL_BLOCK.to_string());
+        }
+        ["ProductName", "FileDescription"].iter().find_map(|key| {
+            blocks.iter().find_map(|block| string_value(&data, &format!("\\StringFileInfo\\{}\\{}", block, key)))
+        })
+    }
+}
+
+/// 値の先頭と、その長さ（翻訳表ではバイト数、文字列では終端を含む文字数）
+unsafe fn query(data: &[u8], sub_block: &str) -> Option<(*const c_void, usize)> {
+    let mut value = std::ptr::null_mut();
+    let mut len = 0u32;
+    let sub_block = HSTRING::from(sub_block);
+    if !VerQueryValueW(data.as_ptr().cast(), PCWSTR(sub_block.as_ptr()), &mut value, &mut len).as_bool() || value.is_null() {
+        return None;
+    }
+    Some((value as *const c_void, len as usize))
+}
+
+/// 空白だけの値はないものとして扱います。
+unsafe fn string_value(data: &[u8], sub_block: &str) -> Option<String> {
+    let (value, len) = query(data, sub_block)?;
+    let text = String::from_utf16_lossy(std::slice::from_raw_parts(value as *const u16, len));
+    let text = text.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
@@ -0,0 +1,421 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+use crate::backend::{create_platform_backend, AudioBackend};
+use crate::error::AudioError;
+use crate::notification::AudioNotification;
+use crate::sink::{MixerEvent, MixerEventSink};
+
+type Job = Box<dyn FnOnce(Option<&mut (dyn AudioBackend + 'static)>) + Send>;
+type InitHook = Box<dyn Fn(&mut dyn AudioBackend) + Send>;
+type BackendFactory = Box<dyn Fn(Notifier, Arc<dyn MixerEventSink>) -> Result<Box<dyn AudioBackend>, AudioError> + Send>;
+type Reply<T> = oneshot::Sender<Result<T, String>>;
+
+enum Message {
+    Job(Job),
+    /// `call_limited` から送られたジョブ。ほかのメッセージが残っていないときだけ実行する
+    LimitedJob(Job),
+    Notify(AudioNotification),
+    SetVolume { target: VolumeTarget, volume: f32, reply: Reply<()>, slot: QueueSlot },
+    /// バックエンドを解放し、待たずに作り直す
+    Restart,
+    /// バックエンドを解放してスレッドを終了する。解放が済んだら送り返す
+    Shutdown(Sender<()>),
+}
+
+/// 音量変更の対象。プロセスのすべてのセッションか、特定のセッションか
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum VolumeTarget {
+    Process(u32),
+    Instance(String),
+}
+
+/// 合流待ちの音量変更。期限までに届いた最後の値だけが適用される
+struct PendingVolume {
+    volume: f32,
+    deadline: Instant,
+    replies: Vec<Reply<()>>,
+}
+
+/// OS のコールバックからオーディオスレッドへ通知を送るためのハンドル
+#[derive(Clone)]
+pub struct Notifier(Sender<Message>);
+
+impl Notifier {
+    pub fn notify(&self, notification: AudioNotification) {
+        let _ = self.0.send(Message::Notify(notification));
+    }
+}
+
+/// `call_limited` で送る操作の種類。種類ごとに 1 秒あたりの回数を制限します
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CallKind {
+    /// セッションやデバイスの一覧を作り直す重い操作
+    Enumerate,
+    /// 音量などの読み取り
+    Read,
+    /// 音量・ミュート・出力先などの変更
+    Write,
+}
+
+impl CallKind {
+    /// 1 秒あたりに受け付ける回数。続けて受け付けられる回数（バースト）も同じ数にする
+    fn per_second(self) -> f64 {
+        match self {
+            CallKind::Enumerate => 5.0,
+            CallKind::Read => 50.0,
+            // スライダーのドラッグを受け付けられるだけの余裕を持たせる
+            CallKind::Write => 100.0,
+        }
+    }
+}
+
+/// 種類ごとの残りの回数
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// `call_limited` の制限の状態。ワーカーのクローン間で共有します
+#[derive(Default)]
+struct CallLimiter {
+    buckets: Mutex<HashMap<CallKind, TokenBucket>>,
+    /// 送ったがまだ実行されていないジョブの数
+    queued: Arc<AtomicUsize>,
+}
+
+impl CallLimiter {
+    fn try_acquire(&self, kind: CallKind) -> bool {
+        let Ok(mut buckets) = self.buckets.lock() else { return false };
+        let now = Instant::now();
+        let rate = kind.per_second();
+        let bucket = buckets.entry(kind).or_insert(TokenBucket { tokens: rate, refilled_at: now });
+        bucket.tokens = (bucket.tokens + now.duration_since(bucket.refilled_at).as_secs_f64() * rate).min(rate);
+        bucket.refilled_at = now;
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+/// ジョブが待ち行列に占める 1 枠。ジョブと一緒に drop され、実行されずに捨てられても枠を返します
+struct QueueSlot(Arc<AtomicUsize>);
+
+impl QueueSlot {
+    /// `queued` が `max` 未満なら 1 枠を取ります。
+    fn acquire(queued: &Arc<AtomicUsize>, max: usize) -> Result<Self, String> {
+        if queued.fetch_add(1, Ordering::SeqCst) >= max {
+            queued.fetch_sub(1, Ordering::SeqCst);
+            return Err("Audio worker is busy".to_string());
+        }
+        Ok(Self(queued.clone()))
+    }
+}
+
+impl Drop for QueueSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+const PEAK_INTERVAL: Duration = Duration::from_millis(16);
+/// スライダー操作の連続した音量変更を対象ごとにまとめる時間幅
+const VOLUME_COALESCE_WINDOW: Duration = Duration::from_millis(15);
+/// バックエンドの生成に失敗したとき、または接続を失ったときに次に作り直すまでの最短間隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+/// 終了時にバックエンドの解放を待つ上限。ドライバが応答しなくてもアプリの終了は止めない
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+/// `call_limited` で実行を待てるジョブの上限。超えた呼び出しはすぐに失敗させる
+const MAX_LIMITED_QUEUE: usize = 32;
+/// `call` と音量変更で実行を待てる数の上限。ドライバが応答しないあいだに呼び出しが溜まり続けないようにする
+const MAX_QUEUE: usize = 256;
+
+/// バックエンドを所有する専用オーディオスレッドへのハンドル。
+/// 処理はチャネル経由でこのスレッドに送られ、COM オブジェクトなどがスレッドをまたぐことはありません。
+#[derive(Clone)]
+pub struct AudioWorker {
+    tx: Sender<Message>,
+    /// `call` と音量変更で送ったがまだ済んでいない数
+    queued: Arc<AtomicUsize>,
+    limiter: Arc<CallLimiter>,
+}
+
+impl AudioWorker {
+    /// 実行中のプラットフォームのバックエンドでワーカースレッドを起動します。
+    /// イベントは `sink` に届き、`init` はバックエンドの生成直後に毎回呼ばれます。
+    pub fn spawn<S, F>(sink: S, init: F) -> Self
+    where
+        S: MixerEventSink,
+        F: Fn(&mut dyn AudioBackend) + Send + 'static,
+    {
+        Self::spawn_with(create_platform_backend, sink, init)
+    }
+
+    /// `factory` で生成したバックエンドでワーカースレッドを起動します。
+    /// `factory` はオーディオスレッド上で呼ばれ、失敗した場合は次のメッセージ処理時に再試行されます。
+    pub fn spawn_with<B, S, F>(factory: B, sink: S, init: F) -> Self
+    where
+        B: Fn(Notifier, Arc<dyn MixerEventSink>) -> Result<Box<dyn AudioBackend>, AudioError> + Send + 'static,
+        S: MixerEventSink,
+        F: Fn(&mut dyn AudioBackend) + Send + 'static,
+    {
+        let sink: Arc<dyn MixerEventSink> = Arc::new(sink);
+        let (tx, rx) = mpsc::channel::<Message>();
+        let notifier = Notifier(tx.clone());
+        thread::Builder::new()
+            .name("audio-worker".into())
+            .spawn(move || run(Box::new(factory), sink, rx, notifier, Box::new(init)))
+            .expect("failed to spawn audio worker");
+        Self { tx, queued: Arc::default(), limiter: Arc::default() }
+    }
+
+    /// オーディオスレッド上で `f` を実行し、結果を非同期に待ちます。
+    /// 呼び出し側のスレッドはブロックされないため、ドライバが応答しなくてもコマンド処理全体は止まりません。
+    /// 待ち行列が上限に達しているときは実行せずにすぐ失敗します。
+    pub async fn call<F, R>(&self, f: F) -> Result<R, String>
+    where
+        F: FnOnce(&mut dyn AudioBackend) -> Result<R, String> + Send + 'static,
+        R: Send + 'static,
+    {
+        let reply_rx = self.submit(f)?;
+        reply_rx.await.map_err(|_| "Audio worker stopped")?
+    }
+
+    /// `call` の同期版。非同期ランタイムを持たない呼び出し元（CLI など）向けです。
+    pub fn call_blocking<F, R>(&self, f: F) -> Result<R, String>
+    where
+        F: FnOnce(&mut dyn AudioBackend) -> Result<R, String> + Send + 'static,
+        R: Send + 'static,
+    {
+        let reply_rx = self.submit(f)?;
+        reply_rx.blocking_recv().map_err(|_| "Audio worker stopped")?
+    }
+
+    /// 外部の連携（API やスクリプト）から `call` する場合に使います。`kind` ごとの回数の上限と待ち行列の上限を超えると
+    /// 実行せずにすぐ失敗します。ジョブは UI などからの `call` がすべて済んでから実行されるため、
+    /// 連携が呼び出しを繰り返してもアプリ自身の操作は待たされません。
+    pub async fn call_limited<F, R>(&self, kind: CallKind, f: F) -> Result<R, String>
+    where
+        F: FnOnce(&mut dyn AudioBackend) -> Result<R, String> + Send + 'static,
+        R: Send + 'static,
+    {
+        if !self.limiter.try_acquire(kind) {
+            return Err(format!("Too many {:?} requests", kind));
+        }
+        let slot = QueueSlot::acquire(&self.limiter.queued, MAX_LIMITED_QUEUE)?;
+        let (job, reply_rx) = Self::job(f, slot);
+        self.tx.send(Message::LimitedJob(job)).map_err(|_| "Audio worker stopped")?;
+        reply_rx.await.map_err(|_| "Audio worker stopped")?
+    }
+
+    fn submit<F, R>(&self, f: F) -> Result<oneshot::Receiver<Result<R, String>>, String>
+    where
+        F: FnOnce(&mut dyn AudioBackend) -> Result<R, String> + Send + 'static,
+        R: Send + 'static,
+    {
+        let slot = QueueSlot::acquire(&self.queued, MAX_QUEUE)?;
+        let (job, reply_rx) = Self::job(f, slot);
+        self.tx.send(Message::Job(job)).map_err(|_| "Audio worker stopped")?;
+        Ok(reply_rx)
+    }
+
+    fn job<F, R>(f: F, slot: QueueSlot) -> (Job, oneshot::Receiver<Result<R, String>>)
+    where
+        F: FnOnce(&mut dyn AudioBackend) -> Result<R, String> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let job: Job = Box::new(move |backend| {
+            let result = match backend {
+                Some(m) => f(m),
+                None => Err("Audio backend unavailable".to_string()),
+            };
+            // 結果を受け取った呼び出し元がすぐ次を送っても数えられないよう、返す前に枠を空ける
+            drop(slot);
+            let _ = reply_tx.send(result);
+        });
+        (job, reply_rx)
+    }
+
+    /// 音量変更を送ります。同じ PID への変更は短時間のうちにまとめられ、最後の値だけが適用されます。
+    pub async fn set_session_volume(&self, pid: u32, volume: f32) -> Result<(), String> {
+        self.set_volume(VolumeTarget::Process(pid), volume).await
+    }
+
+    /// `instance_id` のセッションだけの音量変更を送ります。まとめ方は `set_session_volume` と同じです。
+    pub async fn set_instance_volume(&self, instance_id: String, volume: f32) -> Result<(), String> {
+        self.set_volume(VolumeTarget::Instance(instance_id), volume).await
+    }
+
+    async fn set_volume(&self, target: VolumeTarget, volume: f32) -> Result<(), String> {
+        let slot = QueueSlot::acquire(&self.queued, MAX_QUEUE)?;
+        let (reply, reply_rx) = oneshot::channel();
+        self.tx.send(Message::SetVolume { target, volume, reply, slot }).map_err(|_| "Audio worker stopped")?;
+        reply_rx.await.map_err(|_| "Audio worker stopped")?
+    }
+
+    /// バックエンドを作り直し、デバイスの列挙と通知の登録をやり直させます。作り直すと `BackendReconnected` が届きます。
+    /// スリープからの復帰のように、エンドポイントの ID や状態が通知なしに変わりうるときに使います。
+    pub fn restart(&self) {
+        let _ = self.tx.send(Message::Restart);
+    }
+
+    /// バックエンドを解放してワーカースレッドを終了させ、完了まで待ちます。
+    /// 通知の登録解除と COM の終了はワーカースレッド上で行われます。以降の呼び出しはすべて失敗します。
+    pub fn shutdown(&self) {
+        let (done_tx, done_rx) = mpsc::channel();
+        if self.tx.send(Message::Shutdown(done_tx)).is_ok() {
+            let _ = done_rx.recv_timeout(SHUTDOWN_TIMEOUT);
+        }
+    }
+}
+
+fn run(factory: BackendFactory, sink: Arc<dyn MixerEventSink>, rx: Receiver<Message>, notifier: Notifier, init: InitHook) {
+    let mut backend: Option<Box<dyn AudioBackend>> = None;
+    let mut next_attempt = Instant::now();
+    let mut created_at = Instant::now();
+    let mut reconnecting = false;
+    let mut pending_volumes: HashMap<VolumeTarget, PendingVolume> = HashMap::new();
+    let mut limited_jobs: VecDeque<Job> = VecDeque::new();
+    let mut next_tick = Instant::now() + PEAK_INTERVAL;
+
+    loop {
+        if backend.is_none() && Instant::now() >= next_attempt {
+            match factory(notifier.clone(), sink.clone()) {
+                Ok(mut b) => {
+                    init(b.as_mut());
+                    backend = Some(b);
+                    created_at = Instant::now();
+                    if reconnecting {
+                        reconnecting = false;
+                        sink.emit(MixerEvent::BackendReconnected);
+                    }
+                }
+                Err(_) => next_attempt = Instant::now() + RECONNECT_INTERVAL,
+            }
+        }
+
+        // 期限を過ぎていればジョブより先にティックを処理し、連続したコマンドで計測が止まらないようにする
+        let now = Instant::now();
+        let wake = pending_volumes.values().map(|p| p.deadline).fold(next_tick, Instant::min);
+        if now < wake {
+            // 制限付きのジョブは、ほかに届いているメッセージがないときだけ 1 つずつ実行する
+            let message = if limited_jobs.is_empty() {
+                rx.recv_timeout(wake - now)
+            } else {
+                match rx.try_recv() {
+                    Ok(message) => Ok(message),
+                    Err(TryRecvError::Empty) => {
+                        if let Some(job) = limited_jobs.pop_front() {
+                            job(backend.as_deref_mut());
+                            reconnecting |= drop_if_lost(&mut backend, created_at, &mut next_attempt);
+                        }
+                        continue;
+                    }
+                    Err(TryRecvError::Disconnected) => Err(RecvTimeoutError::Disconnected),
+                }
+            };
+            match message {
+                Ok(Message::Job(job)) => {
+                    job(backend.as_deref_mut());
+                    reconnecting |= drop_if_lost(&mut backend, created_at, &mut next_attempt);
+                    continue;
+                }
+                Ok(Message::LimitedJob(job)) => {
+                    limited_jobs.push_back(job);
+                    continue;
+                }
+                Ok(Message::Notify(notification)) => {
+                    if let Some(b) = backend.as_mut() {
+                        b.handle_notification(notification);
+                    }
+                    reconnecting |= drop_if_lost(&mut backend, created_at, &mut next_attempt);
+                    continue;
+                }
+                Ok(Message::SetVolume { target, volume, reply, slot }) => {
+                    // 合流すれば 1 回の変更で済むので、枠はここで返す
+                    drop(slot);
+                    let pending = pending_volumes.entry(target).or_insert_with(|| PendingVolume {
+                        volume,
+                        deadline: Instant::now() + VOLUME_COALESCE_WINDOW,
+                        replies: Vec::new(),
+                    });
+                    pending.volume = volume;
+                    pending.replies.push(reply);
+                    continue;
+                }
+                Ok(Message::Restart) => {
+                    reconnecting |= backend.take().is_some();
+                    next_attempt = Instant::now();
+                    continue;
+                }
+                Ok(Message::Shutdown(done)) => {
+                    drop(backend.take());
+                    let _ = done.send(());
+                    break;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        flush_volumes(backend.as_deref_mut(), &mut pending_volumes);
+        if Instant::now() < next_tick {
+            continue;
+        }
+
+        next_tick = Instant::now() + PEAK_INTERVAL;
+
+        let Some(b) = backend.as_mut() else { continue };
+        if let Ok(peaks) = b.get_peak_levels() {
+            sink.emit(MixerEvent::Peaks(peaks));
+        }
+        if let Some(peak) = b.get_input_peak() {
+            sink.emit(MixerEvent::InputPeak(peak));
+        }
+        b.check_app_updates();
+        if let Ok(Some(diff)) = b.take_sessions_diff() {
+            sink.emit(MixerEvent::SessionsChanged(diff));
+        }
+        reconnecting |= drop_if_lost(&mut backend, created_at, &mut next_attempt);
+    }
+}
+
+/// バックエンドがオーディオサービスとの接続を失っていれば破棄し、作り直しを予約します。
+/// サービスが止まったままのときに生成と破棄を繰り返さないよう、生成から一定時間は作り直しません。
+fn drop_if_lost(backend: &mut Option<Box<dyn AudioBackend>>, created_at: Instant, next_attempt: &mut Instant) -> bool {
+    if backend.as_mut().is_some_and(|b| !b.check_health()) {
+        *backend = None;
+        *next_attempt = created_at + RECONNECT_INTERVAL;
+        true
+    } else {
+        false
+    }
+}
+
+/// 期限に達した音量変更を適用し、待っているすべての呼び出し元に結果を返します。
+fn flush_volumes(mut backend: Option<&mut (dyn AudioBackend + 'static)>, pending_volumes: &mut HashMap<VolumeTarget, PendingVolume>) {
+    let now = Instant::now();
+    let due: Vec<VolumeTarget> = pending_volumes.iter()
+        .filter(|(_, p)| p.deadline <= now)
+        .map(|(target, _)| target.clone())
+        .collect();
+    for target in due {
+        let Some(pending) = pending_volumes.remove(&target) else { continue };
+        let result = match (backend.as_deref_mut(), &target) {
+            (Some(m), VolumeTarget::Process(pid)) => m.set_session_volume(*pid, pending.volume).map_err(|e| e.to_string()),
+            (Some(m), VolumeTarget::Instance(id)) => m.set_instance_volume(id, pending.volume).map_err(|e| e.to_string()),
+            (None, _) => Err("Audio backend unavailable".to_string()),
+        };
+        for reply in pending.replies {
+            let _ = reply.send(result.clone());
+        }
+    }
+}
@@ -0,0 +1,1758 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use windows_app_mixer::audio_file::{AudioFileSource, SeekRequest};
+use windows_app_mixer::boost::{self, BoostLimiter};
+use windows_app_mixer::capture_file::FileWriter;
+use windows_app_mixer::generator::{BurnInSource, DelayedSource, MetronomeSource, SignalSource, SweepSource, Tempo, ToneSource};
+use windows_app_mixer::measurement;
+use windows_app_mixer::process_watch::{self, ProcessEntry, ProcessWatcher};
+use windows_app_mixer::stream_server::{StreamServer, STREAM_PATH};
+use windows_app_mixer::virtual_cable::{self, VirtualCable, VirtualCableKind};
+use windows_app_mixer::mock::{self, MockAudioHandle, MockCall};
+use windows_app_mixer::{
+    AppOverride, AudioDeviceInfo, BoostRequest, BurnIn, CaptureSource, FileCaptureRequest, FileEncoding, Rotation, GENERATOR_SESSION_NAME, AudioError, AudioWorker, BluetoothInfo, CallKind, ChangeOrigin, BluetoothProfile, CaptureEffect, CaptureEffectKind, ChordTone, DeviceFormat, DeviceRole, GainLevel, InputGain, InputPeak, LegacyAudioApi, MasterVolume,
+    MeasurementRequest, MediaCommand, Metronome, MirrorRequest, MixerEvent, NetworkStream, NetworkStreamRequest, NowPlaying, OutputFormat, PlaybackRequest, PlaybackStatus, Recording, Signal, Sweep, ToneRequest, Waveform, ICON_SIZE, MAX_BOOST_DB, MAX_MIRROR_DELAY_MS,
+    SessionState, SessionsDiff, SyncedOutput, SyncedPlaybackRequest, MAX_BURN_IN_AMPLITUDE, MAX_BURN_IN_MS, MAX_PLAYBACK_DELAY_MS,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn spawn(mock: &MockAudioHandle) -> (AudioWorker, Receiver<MixerEvent>) {
+    let (tx, rx) = mpsc::channel();
+    let worker = AudioWorker::spawn_with(mock.factory(), move |event: MixerEvent| { let _ = tx.send(event); }, |_| {});
+    // バックエンドが生成されるまでは MockAudioHandle からの通知が届かない
+    worker.call_blocking(|_| Ok(())).unwrap();
+    (worker, rx)
+}
+
+/// 条件に合うイベントが届くまで待ちます。ピーク値のイベントは読み飛ばします。
+fn wait_for<T>(rx: &Receiver<MixerEvent>, mut matches: impl FnMut(MixerEvent) -> Option<T>) -> T {
+    let deadline = Instant::now() + TIMEOUT;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        match rx.recv_timeout(remaining) {
+            Ok(event) => {
+                if let Some(found) = matches(event) {
+                    return found;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+    panic!("expected event was not emitted");
+}
+
+fn wait_for_diff(rx: &Receiver<MixerEvent>, mut matches: impl FnMut(&SessionsDiff) -> bool) -> SessionsDiff {
+    wait_for(rx, |event| match event {
+        MixerEvent::SessionsChanged(diff) if matches(&diff) => Some(diff),
+        _ => None,
+    })
+}
+
+#[test]
+fn lists_sessions_with_hidden_apps_and_overrides_applied() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    mock.add_session(MockAudioHandle::session_info(20, "CHAT.EXE", "speakers"));
+    mock.add_session(MockAudioHandle::session_info(30, "UPDATER.EXE", "speakers"));
+    let (worker, _rx) = spawn(&mock);
+
+    let overrides = HashMap::from([(
+        "CHAT.EXE".to_string(),
+        AppOverride { display_name: Some("Voice Chat".to_string()), icon_path: None },
+    )]);
+    let sessions = worker.call_blocking(move |b| {
+        b.set_hidden_apps(vec!["UPDATER.EXE".to_string()]);
+        b.set_app_overrides(overrides);
+        b.get_sessions().map_err(|e| e.to_string())
+    }).unwrap();
+
+    let mut names: Vec<(u32, String)> = sessions.iter().map(|s| (s.process_id, s.process_name.clone())).collect();
+    names.sort();
+    assert_eq!(names, vec![(10, "GAME.EXE".to_string()), (20, "Voice Chat".to_string())]);
+    assert!(sessions.iter().all(|s| s.exe_name != "UPDATER.EXE"));
+}
+
+#[test]
+fn override_icons_are_scaled_to_the_icon_size() {
+    use base64::Engine as _;
+
+    let path = std::env::temp_dir().join(format!("mixer-icon-{}.png", std::process::id()));
+    image::RgbaImage::from_pixel(200, 100, image::Rgba([255, 0, 0, 255])).save(&path).unwrap();
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, _rx) = spawn(&mock);
+
+    let overrides = HashMap::from([(
+        "GAME.EXE".to_string(),
+        AppOverride { display_name: None, icon_path: Some(path.to_string_lossy().into_owned()) },
+    )]);
+    let sessions = worker.call_blocking(move |b| {
+        b.set_app_overrides(overrides);
+        b.get_sessions().map_err(|e| e.to_string())
+    }).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    let png = base64::engine::general_purpose::STANDARD.decode(sessions[0].icon_base64.as_ref().unwrap()).unwrap();
+    let icon = image::load_from_memory(&png).unwrap();
+    // 縦横比を保ったまま、長い辺を ICON_SIZE に合わせる
+    assert_eq!((icon.width(), icon.height()), (ICON_SIZE, ICON_SIZE / 2));
+}
+
+#[test]
+fn session_list_is_cached_until_a_session_is_created() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+
+    worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    wait_for_diff(&rx, |d| !d.added.is_empty());
+    let scans = mock.scan_count();
+    for _ in 0..5 {
+        worker.call_blocking(|b| b.get_sessions_lite().map_err(|e| e.to_string())).unwrap();
+    }
+    assert_eq!(mock.scan_count(), scans, "cached sessions should not be rescanned");
+
+    mock.add_session(MockAudioHandle::session_info(20, "CHAT.EXE", "speakers"));
+    let diff = wait_for_diff(&rx, |d| !d.added.is_empty());
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].process_id, 20);
+    assert!(mock.scan_count() > scans);
+}
+
+#[test]
+fn clearing_caches_rescans_sessions() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, _rx) = spawn(&mock);
+
+    worker.call_blocking(|b| b.get_sessions_lite().map_err(|e| e.to_string())).unwrap();
+    let scans = mock.scan_count();
+    worker.call_blocking(|b| b.get_sessions_lite().map_err(|e| e.to_string())).unwrap();
+    assert_eq!(mock.scan_count(), scans);
+
+    worker.call_blocking(|b| { b.clear_caches(); Ok(()) }).unwrap();
+    let sessions = worker.call_blocking(|b| b.get_sessions_lite().map_err(|e| e.to_string())).unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert!(mock.scan_count() > scans, "sessions should be rescanned after the caches are cleared");
+}
+
+#[test]
+fn external_volume_change_is_forwarded_as_events() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+    worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    wait_for_diff(&rx, |d| !d.added.is_empty());
+
+    mock.external_volume_change(10, 0.25, true);
+
+    let (pid, volume, muted, origin) = wait_for(&rx, |event| match event {
+        MixerEvent::VolumeChanged { pid, volume, muted, origin } => Some((pid, volume, muted, origin)),
+        _ => None,
+    });
+    assert_eq!((pid, volume, muted, origin), (10, 0.25, true, ChangeOrigin::External));
+    let diff = wait_for_diff(&rx, |d| !d.updated.is_empty());
+    assert_eq!(diff.updated[0].volume, 0.25);
+    assert!(diff.updated[0].is_muted);
+
+    // ミキサー自身による変更は区別される
+    worker.call_blocking(|b| b.set_session_mute(10, false).map_err(|e| e.to_string())).unwrap();
+    let origin = wait_for(&rx, |event| match event {
+        MixerEvent::VolumeChanged { pid: 10, muted: false, origin, .. } => Some(origin),
+        _ => None,
+    });
+    assert_eq!(origin, ChangeOrigin::Mixer);
+}
+
+#[test]
+fn icons_extracted_later_are_delivered_as_events() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+    let sessions = worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    assert_eq!(sessions[0].icon_base64, None);
+    wait_for_diff(&rx, |d| !d.added.is_empty());
+
+    mock.icon_loaded("game.exe", "aWNvbg==");
+
+    let (pid, icon) = wait_for(&rx, |event| match event {
+        MixerEvent::SessionIconReady { pid, icon_base64, .. } => Some((pid, icon_base64)),
+        _ => None,
+    });
+    assert_eq!((pid, icon.as_str()), (10, "aWNvbg=="));
+    let diff = wait_for_diff(&rx, |d| !d.updated.is_empty());
+    assert_eq!(diff.updated[0].icon_base64.as_deref(), Some("aWNvbg=="));
+}
+
+#[test]
+fn updated_apps_get_their_new_icon_without_touching_other_sessions() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    mock.add_session(MockAudioHandle::session_info(20, "CHAT.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+    worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    wait_for_diff(&rx, |d| !d.added.is_empty());
+
+    mock.update_app("game.exe", Some("bmV3"));
+
+    let diff = wait_for_diff(&rx, |d| !d.updated.is_empty());
+    assert_eq!(diff.updated.len(), 1);
+    assert_eq!((diff.updated[0].process_id, diff.updated[0].icon_base64.as_deref()), (10, Some("bmV3")));
+}
+
+#[test]
+fn accent_color_is_the_dominant_color_of_the_icon() {
+    use base64::Engine as _;
+
+    // 赤が多く、縁が白と灰色のアイコン
+    let image = image::RgbaImage::from_fn(8, 8, |x, y| match (x, y) {
+        (0, _) | (_, 0) => image::Rgba([255, 255, 255, 255]),
+        (7, _) | (_, 7) => image::Rgba([128, 128, 128, 255]),
+        (1, 1) => image::Rgba([0, 0, 255, 255]),
+        _ => image::Rgba([240, 16, 16, 255]),
+    });
+    let mut png = Vec::new();
+    image.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+    let icon = base64::engine::general_purpose::STANDARD.encode(png);
+
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    mock.add_session(MockAudioHandle::session_info(20, "CHAT.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+    worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    wait_for_diff(&rx, |d| !d.added.is_empty());
+
+    mock.icon_loaded("GAME.EXE", &icon);
+    let accent = wait_for(&rx, |event| match event {
+        MixerEvent::SessionIconReady { pid: 10, accent_color, .. } => Some(accent_color),
+        _ => None,
+    });
+    assert_eq!(accent.as_deref(), Some("#f01010"));
+
+    // 色のないアイコンにはアクセントカラーがない
+    let gray = image::RgbaImage::from_pixel(4, 4, image::Rgba([90, 90, 90, 255]));
+    let mut png = Vec::new();
+    gray.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+    mock.icon_loaded("CHAT.EXE", &base64::engine::general_purpose::STANDARD.encode(png));
+    let accent = wait_for(&rx, |event| match event {
+        MixerEvent::SessionIconReady { pid: 20, accent_color, .. } => Some(accent_color),
+        _ => None,
+    });
+    assert_eq!(accent, None);
+}
+
+#[test]
+fn inactive_sessions_stay_listed_with_their_state() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+    worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    wait_for_diff(&rx, |d| !d.added.is_empty());
+
+    mock.state_change(10, SessionState::Inactive);
+    let diff = wait_for_diff(&rx, |d| !d.updated.is_empty());
+    assert_eq!(diff.updated[0].state, SessionState::Inactive);
+
+    let session = worker.call_blocking(|b| b.get_session(10).map_err(|e| e.to_string())).unwrap();
+    assert_eq!(session.state, SessionState::Inactive);
+}
+
+#[test]
+fn removed_and_expired_sessions_appear_in_diff() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    mock.add_session(MockAudioHandle::session_info(20, "CHAT.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+    worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    wait_for_diff(&rx, |d| d.added.len() == 2);
+
+    mock.remove_session(10);
+    let diff = wait_for_diff(&rx, |d| !d.removed.is_empty());
+    assert_eq!(diff.removed[0].process_id, 10);
+
+    mock.state_change(20, SessionState::Expired);
+    let state = wait_for(&rx, |event| match event {
+        MixerEvent::SessionStateChanged { pid: 20, state } => Some(state),
+        _ => None,
+    });
+    assert_eq!(state, "Expired");
+    let diff = wait_for_diff(&rx, |d| !d.removed.is_empty());
+    assert_eq!(diff.removed[0].process_id, 20);
+}
+
+#[test]
+fn rapid_volume_changes_are_coalesced() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, _rx) = spawn(&mock);
+    worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let results = runtime.block_on(async {
+        tokio::join!(
+            worker.set_session_volume(10, 0.1),
+            worker.set_session_volume(10, 0.2),
+            worker.set_session_volume(10, 0.3),
+        )
+    });
+    assert!(results.0.is_ok() && results.1.is_ok() && results.2.is_ok());
+
+    let writes: Vec<MockCall> = mock.calls();
+    assert_eq!(writes, vec![MockCall::SetVolume { pid: 10, volume: 0.3 }]);
+    assert_eq!(mock.session(10).unwrap().volume, 0.3);
+}
+
+#[test]
+fn limited_calls_are_rejected_over_the_rate_limit() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, _rx) = spawn(&mock);
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let results: Vec<_> = (0..8)
+        .map(|_| runtime.block_on(worker.call_limited(CallKind::Enumerate, |b| b.get_sessions().map_err(|e| e.to_string()))))
+        .collect();
+    let accepted = results.iter().filter(|r| r.is_ok()).count();
+    assert!((5..8).contains(&accepted), "accepted {} enumerations", accepted);
+    assert!(results.last().unwrap().as_ref().is_err_and(|e| e.contains("Too many")));
+
+    // 別の種類の上限には影響しない
+    let volume = runtime.block_on(worker.call_limited(CallKind::Read, |b| b.get_session(10).map_err(|e| e.to_string()))).unwrap().volume;
+    assert_eq!(volume, 1.0);
+    // 制限のない呼び出しはそのまま通る
+    assert!(worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).is_ok());
+}
+
+#[test]
+fn calls_are_rejected_while_the_queue_is_full() {
+    let mock = MockAudioHandle::new();
+    let (worker, _rx) = spawn(&mock);
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async {
+        // 応答しないドライバの代わりに、最初のジョブでワーカーを止めておく
+        let blocked = worker.clone();
+        let mut tasks = vec![tokio::spawn(async move {
+            blocked.call(move |_| release_rx.recv().map_err(|e| e.to_string())).await
+        })];
+        // 待ち行列の上限 (256) まで溜める
+        for _ in 1..256 {
+            let worker = worker.clone();
+            tasks.push(tokio::spawn(async move { worker.call(|_| Ok(())).await }));
+        }
+        // 1 回譲っただけではすべてのタスクが動くとは限らないので、何度か譲る
+        for _ in 0..64 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(worker.call(|_| Ok(())).await, Err("Audio worker is busy".to_string()));
+        assert_eq!(worker.set_session_volume(10, 0.5).await, Err("Audio worker is busy".to_string()));
+
+        release_tx.send(()).unwrap();
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), Ok(()));
+        }
+        assert!(worker.call(|_| Ok(())).await.is_ok());
+    });
+}
+
+#[test]
+fn single_session_reflects_latest_volume_and_peak() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    mock.add_session(MockAudioHandle::session_info(20, "CHAT.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+    worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    wait_for_diff(&rx, |d| d.added.len() == 2);
+
+    mock.external_volume_change(20, 0.4, true);
+    wait_for(&rx, |event| matches!(event, MixerEvent::VolumeChanged { pid: 20, .. }).then_some(()));
+    mock.set_peak(20, 0.7);
+
+    let session = worker.call_blocking(|b| b.get_session(20).map_err(|e| e.to_string())).unwrap();
+    assert_eq!(session.process_id, 20);
+    assert_eq!((session.volume, session.is_muted, session.peak_level), (0.4, true, 0.7));
+
+    let missing = worker.call_blocking(|b| match b.get_session(99) {
+        Err(AudioError::SessionNotFound(pid)) => Ok(pid),
+        other => Err(format!("unexpected result: {:?}", other)),
+    });
+    assert_eq!(missing, Ok(99));
+}
+
+#[test]
+fn instances_of_one_process_are_controlled_independently() {
+    let mock = MockAudioHandle::new();
+    for tab in ["tab-1", "tab-2"] {
+        let mut session = MockAudioHandle::session_info(10, "BROWSER.EXE", "speakers");
+        session.instance_id = tab.to_string();
+        mock.add_session(session);
+    }
+    let (worker, rx) = spawn(&mock);
+    worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    wait_for_diff(&rx, |d| d.added.len() == 2);
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(worker.set_instance_volume("tab-2".to_string(), 0.5)).unwrap();
+    worker.call_blocking(|b| b.set_instance_mute("tab-1", true).map_err(|e| e.to_string())).unwrap();
+
+    let sessions = worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    let state = |id: &str| sessions.iter().find(|s| s.instance_id == id).map(|s| (s.volume, s.is_muted)).unwrap();
+    assert_eq!(state("tab-1"), (1.0, true));
+    assert_eq!(state("tab-2"), (0.5, false));
+
+    let missing = worker.call_blocking(|b| match b.set_instance_mute("tab-3", true) {
+        Err(AudioError::InstanceNotFound(id)) => Ok(id),
+        other => Err(format!("unexpected result: {:?}", other)),
+    });
+    assert_eq!(missing, Ok("tab-3".to_string()));
+}
+
+#[test]
+fn unknown_session_reports_not_found() {
+    let mock = MockAudioHandle::new();
+    let (worker, _rx) = spawn(&mock);
+
+    let result = worker.call_blocking(|b| match b.set_session_mute(99, true) {
+        Err(AudioError::SessionNotFound(pid)) => Ok(pid),
+        other => Err(format!("unexpected result: {:?}", other)),
+    });
+    assert_eq!(result, Ok(99));
+    assert!(mock.calls().is_empty());
+}
+
+#[test]
+fn routing_moves_session_and_device_changes_are_emitted() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+        AudioDeviceInfo { id: "headset".to_string(), name: "Headset".to_string(), is_default: false, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+
+    let devices = worker.call_blocking(|b| b.get_audio_devices().map_err(|e| e.to_string())).unwrap();
+    assert_eq!(devices.len(), 2);
+
+    worker.call_blocking(|b| b.set_audio_routing(10, "headset").map_err(|e| e.to_string())).unwrap();
+    let sessions = worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    assert_eq!(sessions[0].device_id, "headset");
+
+    let error = worker.call_blocking(|b| b.set_audio_routing(10, "missing").map_err(|e| e.to_string()));
+    assert!(error.is_err());
+
+    worker.call_blocking(|b| b.clear_audio_routing(10).map_err(|e| e.to_string())).unwrap();
+    let sessions = worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    assert_eq!(sessions[0].device_id, "speakers");
+    assert_eq!(sessions[0].persisted_device_id, None);
+    assert!(mock.calls().contains(&MockCall::ClearRouting(10)));
+}
+
+#[test]
+fn default_device_can_be_switched() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+        AudioDeviceInfo { id: "headset".to_string(), name: "Headset".to_string(), is_default: false, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+
+    worker.call_blocking(|b| b.set_default_device("headset").map_err(|e| e.to_string())).unwrap();
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+    let devices = worker.call_blocking(|b| b.get_audio_devices().map_err(|e| e.to_string())).unwrap();
+    let default: Vec<&str> = devices.iter().filter(|d| d.is_default).map(|d| d.id.as_str()).collect();
+    assert_eq!(default, vec!["headset"]);
+}
+
+#[test]
+fn default_capture_device_can_be_switched() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    mock.set_capture_devices(vec![
+        AudioDeviceInfo { id: "usb-mic".to_string(), name: "USB Mic".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+        AudioDeviceInfo { id: "headset-mic".to_string(), name: "Headset Mic".to_string(), is_default: false, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+
+    worker.call_blocking(|b| b.set_default_capture_device("headset-mic", Some(DeviceRole::Communications)).map_err(|e| e.to_string())).unwrap();
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+    let devices = worker.call_blocking(|b| b.get_capture_devices().map_err(|e| e.to_string())).unwrap();
+    let default: Vec<&str> = devices.iter().filter(|d| d.is_default).map(|d| d.id.as_str()).collect();
+    assert_eq!(default, vec!["headset-mic"]);
+    assert!(mock.calls().contains(&MockCall::SetDefaultCaptureDevice {
+        device_id: "headset-mic".to_string(),
+        role: Some(DeviceRole::Communications),
+    }));
+
+    // 出力デバイスの一覧には入らない
+    let outputs = worker.call_blocking(|b| b.get_audio_devices().map_err(|e| e.to_string())).unwrap();
+    assert!(outputs.is_empty());
+}
+
+#[test]
+fn input_meter_follows_the_default_capture_device() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    mock.set_capture_devices(vec![
+        AudioDeviceInfo { id: "usb-mic".to_string(), name: "USB Mic".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+        AudioDeviceInfo { id: "headset-mic".to_string(), name: "Headset Mic".to_string(), is_default: false, jack_connected: None, bluetooth: None },
+    ]);
+    mock.set_input_peak("usb-mic", 0.4);
+    mock.set_input_peak("headset-mic", 0.7);
+
+    worker.call_blocking(|b| b.start_input_meter(None).map_err(|e| e.to_string())).unwrap();
+    let peak = wait_for(&rx, |event| match event {
+        MixerEvent::InputPeak(peak) => Some(peak.clone()),
+        _ => None,
+    });
+    assert_eq!(peak, InputPeak { device_id: "usb-mic".to_string(), peak: 0.4 });
+
+    worker.call_blocking(|b| b.set_default_capture_device("headset-mic", None).map_err(|e| e.to_string())).unwrap();
+    wait_for(&rx, |event| match event {
+        MixerEvent::InputPeak(peak) if peak.device_id == "headset-mic" => Some(()),
+        _ => None,
+    });
+
+    worker.call_blocking(|b| {
+        b.stop_input_meter();
+        Ok::<_, String>(())
+    }).unwrap();
+    assert!(worker.call_blocking(|b| Ok::<_, String>(b.get_input_peak())).unwrap().is_none());
+    assert!(worker.call_blocking(|b| b.start_input_meter(Some("missing")).map_err(|e| e.to_string())).is_err());
+    assert_eq!(mock.calls().iter().filter(|c| matches!(c, MockCall::StartInputMeter { .. } | MockCall::StopInputMeter)).count(), 2);
+}
+
+#[test]
+fn input_gain_is_clamped_to_the_device_range() {
+    let mock = MockAudioHandle::new();
+    let (worker, _rx) = spawn(&mock);
+    let level = |db, min_db, max_db, step_db| GainLevel { db, min_db, max_db, step_db };
+    mock.set_input_gain(InputGain { device_id: "usb-mic".to_string(), volume: level(0.0, -96.0, 0.0, 0.5), boost: None });
+    mock.set_input_gain(InputGain {
+        device_id: "line-in".to_string(),
+        volume: level(-10.0, -46.5, 30.0, 1.5),
+        boost: Some(level(0.0, 0.0, 30.0, 10.0)),
+    });
+
+    worker.call_blocking(|b| b.set_input_gain("usb-mic", 12.0).map_err(|e| e.to_string())).unwrap();
+    worker.call_blocking(|b| b.set_input_boost("line-in", 20.0).map_err(|e| e.to_string())).unwrap();
+    assert!(worker.call_blocking(|b| b.set_input_boost("usb-mic", 10.0).map_err(|e| e.to_string())).is_err());
+
+    let usb = worker.call_blocking(|b| b.get_input_gain("usb-mic").map_err(|e| e.to_string())).unwrap();
+    assert_eq!(usb.volume.db, 0.0);
+    let line = worker.call_blocking(|b| b.get_input_gain("line-in").map_err(|e| e.to_string())).unwrap();
+    assert_eq!(line.boost.map(|b| b.db), Some(20.0));
+    assert_eq!(line.volume.db, -10.0);
+    assert_eq!(mock.calls(), vec![
+        MockCall::SetInputGain { device_id: "usb-mic".to_string(), db: 0.0 },
+        MockCall::SetInputBoost { device_id: "line-in".to_string(), db: 20.0 },
+    ]);
+}
+
+#[test]
+fn capture_effects_can_only_be_switched_when_the_driver_allows() {
+    let mock = MockAudioHandle::new();
+    let (worker, _rx) = spawn(&mock);
+    mock.set_capture_effects("usb-mic", vec![
+        CaptureEffect { kind: CaptureEffectKind::NoiseSuppression, enabled: true, can_toggle: true },
+        CaptureEffect { kind: CaptureEffectKind::EchoCancellation, enabled: true, can_toggle: false },
+    ]);
+
+    worker.call_blocking(|b| b.set_capture_effect("usb-mic", CaptureEffectKind::NoiseSuppression, false).map_err(|e| e.to_string())).unwrap();
+    assert!(worker.call_blocking(|b| b.set_capture_effect("usb-mic", CaptureEffectKind::EchoCancellation, false).map_err(|e| e.to_string())).is_err());
+    assert!(worker.call_blocking(|b| b.set_capture_effect("usb-mic", CaptureEffectKind::AutomaticGainControl, true).map_err(|e| e.to_string())).is_err());
+
+    let effects = worker.call_blocking(|b| b.get_capture_effects("usb-mic").map_err(|e| e.to_string())).unwrap();
+    let enabled: Vec<(CaptureEffectKind, bool)> = effects.iter().map(|e| (e.kind, e.enabled)).collect();
+    assert_eq!(enabled, vec![(CaptureEffectKind::NoiseSuppression, false), (CaptureEffectKind::EchoCancellation, true)]);
+    assert_eq!(mock.calls(), vec![MockCall::SetCaptureEffect {
+        device_id: "usb-mic".to_string(),
+        kind: CaptureEffectKind::NoiseSuppression,
+        enabled: false,
+    }]);
+}
+
+#[test]
+fn master_volume_changes_are_published() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+
+    mock.external_master_change(0.5, true);
+    let (master, origin) = wait_for(&rx, |event| match event {
+        MixerEvent::MasterVolumeChanged(master, origin) => Some((master, origin)),
+        _ => None,
+    });
+    assert_eq!(master, MasterVolume { device_id: "speakers".to_string(), volume: 0.5, is_muted: true, level: Some(mock::volume_level(0.5)) });
+    assert_eq!(origin, ChangeOrigin::External);
+
+    worker.call_blocking(|b| b.set_master_mute(false).map_err(|e| e.to_string())).unwrap();
+    let master = worker.call_blocking(|b| b.get_master_volume().map_err(|e| e.to_string())).unwrap();
+    assert!(!master.is_muted);
+    assert!(mock.calls().contains(&MockCall::SetMasterMute(false)));
+}
+
+#[test]
+fn master_volume_is_reported_and_set_in_db() {
+    let mock = MockAudioHandle::new();
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    let (worker, _rx) = spawn(&mock);
+    mock.external_master_change(0.5, false);
+
+    let master = worker.call_blocking(|b| b.get_master_volume().map_err(|e| e.to_string())).unwrap();
+    let (min_db, max_db, step_db) = mock::VOLUME_RANGE;
+    assert_eq!(master.level, Some(GainLevel { db: -32.625, min_db, max_db, step_db }));
+
+    let master = worker.call_blocking(|b| {
+        b.set_master_volume_db(-16.3125).map_err(|e| e.to_string())?;
+        b.get_master_volume().map_err(|e| e.to_string())
+    }).unwrap();
+    assert_eq!((master.volume, master.level.map(|l| l.db)), (0.75, Some(-16.3125)));
+    assert!(mock.calls().contains(&MockCall::SetMasterVolumeDb(-16.3125)));
+
+    // 範囲の外は範囲に収める
+    let master = worker.call_blocking(|b| {
+        b.set_master_volume_db(6.0).map_err(|e| e.to_string())?;
+        b.get_master_volume().map_err(|e| e.to_string())
+    }).unwrap();
+    assert_eq!(master.volume, 1.0);
+}
+
+#[test]
+fn each_output_device_keeps_its_own_volume() {
+    let mock = MockAudioHandle::new();
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+        AudioDeviceInfo { id: "headset".to_string(), name: "Headset".to_string(), is_default: false, jack_connected: None, bluetooth: None },
+    ]);
+    let (worker, _rx) = spawn(&mock);
+
+    worker.call_blocking(|b| {
+        b.set_master_volume(0.8).map_err(|e| e.to_string())?;
+        b.set_device_volume("headset", 0.2).map_err(|e| e.to_string())
+    }).unwrap();
+    let headset = worker.call_blocking(|b| b.get_device_volume("headset").map_err(|e| e.to_string())).unwrap();
+    assert_eq!(headset.volume, 0.2);
+
+    // 既定を切り替えると、マスター音量はそのデバイスの音量になる
+    let master = worker.call_blocking(|b| {
+        b.set_default_device("headset").map_err(|e| e.to_string())?;
+        b.get_master_volume().map_err(|e| e.to_string())
+    }).unwrap();
+    assert_eq!(master, MasterVolume { device_id: "headset".to_string(), volume: 0.2, is_muted: false, level: Some(mock::volume_level(0.2)) });
+    assert!(worker.call_blocking(|b| b.get_device_volume("missing").map_err(|e| e.to_string())).is_err());
+}
+
+#[test]
+fn call_volume_sets_the_communications_devices_and_call_apps() {
+    let mock = MockAudioHandle::new();
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    mock.set_capture_devices(vec![
+        AudioDeviceInfo { id: "usb-mic".to_string(), name: "USB Mic".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    mock.add_session(MockAudioHandle::session_info(10, "DISCORD.EXE", "speakers"));
+    let (worker, _rx) = spawn(&mock);
+
+    worker.call_blocking(|b| {
+        b.set_session_volume(10, 0.4).map_err(|e| e.to_string())?;
+        b.set_communications_volume(0.4).map_err(|e| e.to_string())
+    }).unwrap();
+    let (session, output, input) = worker.call_blocking(|b| {
+        let session = b.get_session(10).map_err(|e| e.to_string())?;
+        let output = b.get_device_volume("speakers").map_err(|e| e.to_string())?;
+        let input = b.get_device_volume("usb-mic").map_err(|e| e.to_string())?;
+        Ok((session, output, input))
+    }).unwrap();
+    assert_eq!(session.volume, 0.4);
+    assert_eq!(output.volume, 0.4);
+    assert_eq!(input.volume, 0.4);
+}
+
+#[test]
+fn watched_device_volume_changes_are_published() {
+    let mock = MockAudioHandle::new();
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+        AudioDeviceInfo { id: "zone-a".to_string(), name: "Zone A".to_string(), is_default: false, jack_connected: None, bluetooth: None },
+        AudioDeviceInfo { id: "zone-b".to_string(), name: "Zone B".to_string(), is_default: false, jack_connected: None, bluetooth: None },
+    ]);
+    let (worker, rx) = spawn(&mock);
+    worker.call_blocking(|b| b.watch_device_volumes(vec!["zone-a".to_string()]).map_err(|e| e.to_string())).unwrap();
+
+    // 監視していないデバイスの変化は通知されない
+    mock.external_device_change("zone-b", 0.3, false);
+    mock.external_device_change("zone-a", 0.6, true);
+    let (volume, origin) = wait_for(&rx, |event| match event {
+        MixerEvent::DeviceVolumeChanged(volume, origin) => Some((volume, origin)),
+        _ => None,
+    });
+    assert_eq!(volume, MasterVolume { device_id: "zone-a".to_string(), volume: 0.6, is_muted: true, level: Some(mock::volume_level(0.6)) });
+    assert_eq!(origin, ChangeOrigin::External);
+
+    worker.call_blocking(|b| b.set_device_mute("zone-a", false).map_err(|e| e.to_string())).unwrap();
+    let (volume, origin) = wait_for(&rx, |event| match event {
+        MixerEvent::DeviceVolumeChanged(volume, origin) => Some((volume, origin)),
+        _ => None,
+    });
+    assert!(!volume.is_muted);
+    assert_eq!(origin, ChangeOrigin::Mixer);
+    assert!(mock.calls().contains(&MockCall::SetDeviceMute { device_id: "zone-a".to_string(), mute: false }));
+}
+
+#[test]
+fn app_audio_can_be_mirrored_with_adjustable_delay() {
+    let mock = MockAudioHandle::new();
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+        AudioDeviceInfo { id: "headset".to_string(), name: "Headset".to_string(), is_default: false, jack_connected: None, bluetooth: None },
+    ]);
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+
+    let request = MirrorRequest { pid: 10, device_id: "headset".to_string(), delay_ms: 40 };
+    let start = request.clone();
+    worker.call_blocking(move |b| b.start_mirror(start).map_err(|e| e.to_string())).unwrap();
+    worker.call_blocking(|b| b.set_mirror_delay(10, 120).map_err(|e| e.to_string())).unwrap();
+    let mirrors = worker.call_blocking(|b| Ok(b.get_mirrors())).unwrap();
+    assert_eq!(mirrors, vec![MirrorRequest { delay_ms: 120, ..request }]);
+
+    let too_long = worker.call_blocking(|b| b.set_mirror_delay(10, MAX_MIRROR_DELAY_MS + 1).map_err(|e| e.to_string()));
+    assert!(too_long.is_err());
+    let unknown = MirrorRequest { pid: 99, device_id: "headset".to_string(), delay_ms: 0 };
+    assert!(worker.call_blocking(move |b| b.start_mirror(unknown).map_err(|e| e.to_string())).is_err());
+
+    worker.call_blocking(|b| { b.stop_mirror(10); Ok(()) }).unwrap();
+    let stopped = wait_for(&rx, |event| match event {
+        MixerEvent::MirrorStopped { pid, error } => Some((pid, error)),
+        _ => None,
+    });
+    assert_eq!(stopped, (10, None));
+    assert!(worker.call_blocking(|b| Ok(b.get_mirrors())).unwrap().is_empty());
+}
+
+#[test]
+fn app_audio_can_be_boosted_with_adjustable_gain() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "CHAT.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+
+    worker.call_blocking(|b| b.start_boost(BoostRequest { pid: 10, gain_db: 6.0 }).map_err(|e| e.to_string())).unwrap();
+    worker.call_blocking(|b| b.set_boost_gain(10, 9.0).map_err(|e| e.to_string())).unwrap();
+    let boosts = worker.call_blocking(|b| Ok(b.get_boosts())).unwrap();
+    assert_eq!(boosts, vec![BoostRequest { pid: 10, gain_db: 9.0 }]);
+
+    for gain_db in [0.0, -3.0, MAX_BOOST_DB + 0.5, f32::NAN] {
+        assert!(worker.call_blocking(move |b| b.set_boost_gain(10, gain_db).map_err(|e| e.to_string())).is_err());
+    }
+    assert!(worker.call_blocking(|b| b.start_boost(BoostRequest { pid: 99, gain_db: 3.0 }).map_err(|e| e.to_string())).is_err());
+
+    worker.call_blocking(|b| { b.stop_boost(10); Ok(()) }).unwrap();
+    let stopped = wait_for(&rx, |event| match event {
+        MixerEvent::BoostStopped { pid, error } => Some((pid, error)),
+        _ => None,
+    });
+    assert_eq!(stopped, (10, None));
+    assert!(worker.call_blocking(|b| Ok(b.get_boosts())).unwrap().is_empty());
+}
+
+#[test]
+fn boost_limiter_adds_the_missing_gain_and_keeps_the_sum_under_the_ceiling() {
+    // 上限に届かない音は、上げた量だけ大きくなる
+    let mut limiter = BoostLimiter::new(6.0, 48_000, 2);
+    let quiet: Vec<f32> = (0..960).map(|i| 0.1 * (i as f32 * 0.05).sin()).collect();
+    let mut extra = quiet.clone();
+    limiter.process(&mut extra);
+    let gain = boost::db_to_gain(6.0);
+    assert!(quiet.iter().zip(&extra).all(|(original, extra)| (original + extra - original * gain).abs() < 1e-6));
+
+    // 大きい音は、元の音と合わせても上限を超えない
+    let mut limiter = BoostLimiter::new(MAX_BOOST_DB, 48_000, 2);
+    let loud: Vec<f32> = (0..4800).map(|i| 0.8 * (i as f32 * 0.05).sin()).collect();
+    let mut extra = loud.clone();
+    limiter.process(&mut extra);
+    assert!(loud.iter().zip(&extra).all(|(original, extra)| (original + extra).abs() <= boost::CEILING + 1e-6));
+    assert!(extra.iter().any(|e| e.abs() > 0.01));
+
+    // 元の音だけで上限を超えるところには何も足さない
+    let mut extra = vec![0.95, -0.95];
+    limiter.process(&mut extra);
+    assert_eq!(extra, vec![0.0, 0.0]);
+}
+
+#[test]
+fn bluetooth_profile_can_be_switched() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    let bluetooth = BluetoothInfo { battery_percent: Some(80), profile: Some(BluetoothProfile::HandsFree) };
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+        AudioDeviceInfo { id: "buds".to_string(), name: "Buds".to_string(), is_default: false, jack_connected: None, bluetooth: Some(bluetooth) },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+
+    worker.call_blocking(|b| b.set_bluetooth_profile("buds", BluetoothProfile::A2dp).map_err(|e| e.to_string())).unwrap();
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+    let devices = worker.call_blocking(|b| b.get_audio_devices().map_err(|e| e.to_string())).unwrap();
+    let buds = devices.iter().find(|d| d.id == "buds").unwrap().bluetooth.clone().unwrap();
+    assert_eq!(buds.profile, Some(BluetoothProfile::A2dp));
+    assert_eq!(buds.battery_percent, Some(80));
+
+    let error = worker.call_blocking(|b| b.set_bluetooth_profile("speakers", BluetoothProfile::A2dp).map_err(|e| e.to_string()));
+    assert!(error.is_err());
+}
+
+#[test]
+fn exclusive_formats_are_probed_per_device() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "dac".to_string(), name: "DAC".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+    let hires = DeviceFormat { sample_rate: 192_000, bits_per_sample: 24, container_bits: 32, float: false, channels: 2 };
+    mock.set_device_formats("dac", vec![hires]);
+
+    let formats = worker.call_blocking(|b| b.probe_device_formats("dac").map_err(|e| e.to_string())).unwrap();
+    assert_eq!(formats, vec![hires]);
+
+    let error = worker.call_blocking(|b| b.probe_device_formats("missing").map_err(|e| e.to_string()));
+    assert!(error.is_err());
+}
+
+#[test]
+fn exclusive_playback_requires_a_supported_format() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "dac".to_string(), name: "DAC".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+    let hires = DeviceFormat { sample_rate: 96_000, bits_per_sample: 24, container_bits: 32, float: false, channels: 2 };
+    mock.set_device_formats("dac", vec![hires]);
+    let tone = ToneRequest { waveform: Waveform::Sine, frequency: 1000.0, amplitude: 0.5, duration_ms: Some(500) };
+
+    let cd = DeviceFormat { sample_rate: 44_100, bits_per_sample: 16, container_bits: 16, ..hires };
+    let exclusive = |format| OutputFormat { format: Some(format), exclusive: true, buffer_ms: None };
+    let request = PlaybackRequest { device_id: None, signal: Signal::Tone(tone.clone()), output: exclusive(cd) };
+    assert!(worker.call_blocking(move |b| b.start_playback(request).map_err(|e| e.to_string())).is_err());
+
+    // 共有モードなら OS が変換するので、デバイスが受け付けないフォーマットでもよい
+    let request = PlaybackRequest { device_id: None, signal: Signal::Tone(tone.clone()), output: OutputFormat { exclusive: false, ..exclusive(cd) } };
+    let shared = request.clone();
+    worker.call_blocking(move |b| b.start_playback(request).map_err(|e| e.to_string())).unwrap();
+
+    let request = PlaybackRequest { device_id: Some("dac".to_string()), signal: Signal::Tone(tone), output: exclusive(hires) };
+    let expected = request.clone();
+    worker.call_blocking(move |b| b.start_playback(request).map_err(|e| e.to_string())).unwrap();
+    worker.call_blocking(|b| { b.stop_playback(); Ok(()) }).unwrap();
+    assert_eq!(mock.calls(), vec![MockCall::StartPlayback(shared), MockCall::StartPlayback(expected), MockCall::StopPlayback]);
+}
+
+#[test]
+fn output_format_and_buffer_are_validated() {
+    let format = DeviceFormat { sample_rate: 48_000, bits_per_sample: 24, container_bits: 32, float: false, channels: 2 };
+    let output = OutputFormat { format: Some(format), exclusive: true, buffer_ms: Some(10) };
+    assert!(output.validate().is_ok());
+    assert!(OutputFormat::default().validate().is_ok());
+    // 排他モードにはフォーマットが要る
+    assert!(OutputFormat { format: None, ..output }.validate().is_err());
+    assert!(OutputFormat { buffer_ms: Some(1), ..output }.validate().is_err());
+    assert!(OutputFormat { buffer_ms: Some(1000), ..output }.validate().is_err());
+    for bad in [
+        DeviceFormat { sample_rate: 1_000, ..format },
+        DeviceFormat { bits_per_sample: 32, container_bits: 24, ..format },
+        DeviceFormat { bits_per_sample: 24, container_bits: 32, float: true, ..format },
+        DeviceFormat { channels: 0, ..format },
+    ] {
+        assert!(OutputFormat { format: Some(bad), ..output }.validate().is_err(), "{:?}", bad);
+    }
+}
+
+#[test]
+fn chords_and_dtmf_are_validated_before_playback() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+    let play = |signal: Signal| {
+        let request = PlaybackRequest { device_id: None, signal, output: OutputFormat::default() };
+        worker.call_blocking(move |b| b.start_playback(request).map_err(|e| e.to_string()))
+    };
+    let chord = |amplitudes: [f32; 3]| Signal::Chord {
+        waveform: Waveform::Sine,
+        tones: [261.63, 329.63, 392.0].iter().zip(amplitudes).map(|(&frequency, amplitude)| ChordTone { frequency, amplitude }).collect(),
+        duration_ms: Some(1000),
+    };
+    let dtmf = |digits: &str| Signal::Dtmf { digits: digits.to_string(), amplitude: 0.5, tone_ms: 100, gap_ms: 50 };
+
+    assert!(play(chord([0.3, 0.3, 0.3])).is_ok());
+    // 合計が 1 を超えると割れる
+    assert!(play(chord([0.5, 0.5, 0.5])).is_err());
+    assert!(play(Signal::Chord { waveform: Waveform::Sine, tones: Vec::new(), duration_ms: None }).is_err());
+    assert!(play(dtmf("0123456789*#abcd")).is_ok());
+    assert!(play(dtmf("12-3")).is_err());
+    assert!(play(dtmf("")).is_err());
+}
+
+#[test]
+fn dtmf_digits_are_separated_by_silence() {
+    // 8 kHz で 10 ms の音と 5 ms の無音: 音 80 + 無音 40 + 音 80 フレーム
+    let mut source = ToneSource::new(Signal::Dtmf { digits: "1#".to_string(), amplitude: 1.0, tone_ms: 10, gap_ms: 5 }, 8000);
+    let mut out = vec![1.0f32; 300];
+    assert!(!source.fill(&mut out, 1));
+    assert!(out[..80].iter().any(|s| s.abs() > 0.1));
+    assert!(out[..80].iter().all(|s| s.abs() <= 1.0));
+    assert!(out[80..120].iter().all(|&s| s == 0.0));
+    assert!(out[120..200].iter().any(|s| s.abs() > 0.1));
+    assert!(out[200..].iter().all(|&s| s == 0.0));
+}
+
+#[test]
+fn metronome_tempo_changes_while_it_plays() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+    let set_tempo = |bpm: f32| worker.call_blocking(move |b| b.set_metronome_tempo(bpm).map_err(|e| e.to_string()));
+    assert!(set_tempo(120.0).is_err());
+
+    let metronome = Metronome { bpm: 100.0, beats_per_bar: 4, accent: true, amplitude: 0.8 };
+    let request = PlaybackRequest { device_id: None, signal: Signal::Metronome(metronome), output: OutputFormat::default() };
+    let started = request.clone();
+    worker.call_blocking(move |b| b.start_playback(request).map_err(|e| e.to_string())).unwrap();
+    set_tempo(140.0).unwrap();
+    assert!(set_tempo(1000.0).is_err());
+    worker.call_blocking(|b| { b.stop_playback(); Ok(()) }).unwrap();
+    assert!(set_tempo(120.0).is_err());
+    assert_eq!(mock.calls(), vec![MockCall::StartPlayback(started), MockCall::SetMetronomeTempo(140.0), MockCall::StopPlayback]);
+}
+
+#[test]
+fn metronome_clicks_on_each_beat_and_accents_the_bar() {
+    // 8 kHz・120 BPM なら 4000 フレームごとに拍
+    let metronome = Metronome { bpm: 120.0, beats_per_bar: 2, accent: true, amplitude: 1.0 };
+    let tempo = Tempo::new(metronome.bpm);
+    let mut source = MetronomeSource::new(metronome, 8000, tempo.clone());
+    let mut out = vec![0.0f32; 12_000];
+    assert!(source.fill(&mut out, 1));
+    let loudest = |range: std::ops::Range<usize>| out[range].iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    assert!(loudest(0..240) > 0.5);
+    assert_eq!(loudest(240..4000), 0.0);
+    // 小節の頭以外は小さい
+    assert!(loudest(4000..4240) < loudest(0..240));
+    assert!(loudest(8000..8240) > loudest(4000..4240));
+
+    // テンポを倍にすると 2000 フレームごとになる
+    tempo.set(240.0);
+    let mut out = vec![0.0f32; 4000];
+    source.fill(&mut out, 1);
+    assert!(out[..240].iter().any(|s| s.abs() > 0.1));
+    assert!(out[240..2000].iter().all(|&s| s == 0.0));
+}
+
+#[test]
+fn persisted_route_not_yet_applied_is_flagged() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+    worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    wait_for_diff(&rx, |d| !d.added.is_empty());
+
+    mock.persist_route(10, "headset");
+    let diff = wait_for_diff(&rx, |d| !d.updated.is_empty());
+    assert_eq!(diff.updated[0].device_id, "speakers");
+    assert_eq!(diff.updated[0].persisted_device_id.as_deref(), Some("headset"));
+    assert!(diff.updated[0].route_pending);
+
+    mock.persist_route(10, "speakers");
+    let diff = wait_for_diff(&rx, |d| !d.updated.is_empty());
+    assert!(!diff.updated[0].route_pending);
+}
+
+#[test]
+fn legacy_streams_are_reported_only_for_processes_without_sessions() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    mock.load_legacy_audio(10, "GAME.EXE", LegacyAudioApi::DirectSound);
+    mock.load_legacy_audio(40, "OLDGAME.EXE", LegacyAudioApi::Mme);
+    let (worker, _rx) = spawn(&mock);
+
+    let streams = worker.call_blocking(|b| b.get_legacy_streams().map_err(|e| e.to_string())).unwrap();
+    assert_eq!(streams.len(), 1);
+    assert_eq!((streams[0].pid, streams[0].exe_name.as_str(), streams[0].api), (40, "OLDGAME.EXE", LegacyAudioApi::Mme));
+    assert!(!streams[0].system_sounds_active);
+
+    // 古いゲームの音がシステム音にまとめられている場合
+    mock.add_session(MockAudioHandle::session_info(0, "System Sounds", "speakers"));
+    let streams = worker.call_blocking(|b| b.get_legacy_streams().map_err(|e| e.to_string())).unwrap();
+    assert!(streams[0].system_sounds_active);
+}
+
+#[test]
+fn forced_route_migration_moves_sessions_that_follow_it() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    mock.add_session(MockAudioHandle::session_info(20, "LEGACY.EXE", "speakers"));
+    mock.require_restart(20);
+    let (worker, _rx) = spawn(&mock);
+    mock.persist_route(10, "headset");
+    mock.persist_route(20, "headset");
+
+    let pending = |pid: u32| worker.call_blocking(move |b| b.get_session(pid).map_err(|e| e.to_string())).unwrap().route_pending;
+    assert!(pending(10) && pending(20));
+    worker.call_blocking(|b| b.force_route_migration(10).map_err(|e| e.to_string())).unwrap();
+    worker.call_blocking(|b| b.force_route_migration(20).map_err(|e| e.to_string())).unwrap();
+    assert!(!pending(10));
+    assert_eq!(mock.session(10).unwrap().device_id, "headset");
+    assert!(pending(20), "apps that only pick up routes on restart stay pending");
+    assert!(mock.calls().contains(&MockCall::ForceRouteMigration(20)));
+
+    let result = worker.call_blocking(|b| b.force_route_migration(30).map_err(|e| e.to_string()));
+    assert!(result.is_err(), "no route was set for the process");
+}
+
+#[test]
+fn commands_fail_cleanly_when_backend_cannot_start() {
+    let worker = AudioWorker::spawn_with(
+        |_, _| Err(AudioError::Unsupported("Audio backend")),
+        |_: MixerEvent| {},
+        |_| {},
+    );
+    let result = worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string()));
+    assert_eq!(result, Err("Audio backend unavailable".to_string()));
+}
+
+#[test]
+fn limited_calls_release_their_queue_slot_when_backend_cannot_start() {
+    let worker = AudioWorker::spawn_with(
+        |_, _| Err(AudioError::Unsupported("Audio backend")),
+        |_: MixerEvent| {},
+        |_| {},
+    );
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    // 待ち行列の上限 (32) より多く呼んでも、実行されなかったジョブの枠は返されている
+    for _ in 0..40 {
+        let result = runtime.block_on(worker.call_limited(CallKind::Read, |b| b.get_sessions().map_err(|e| e.to_string())));
+        assert_eq!(result.map(|_| ()), Err("Audio backend unavailable".to_string()));
+    }
+}
+
+#[test]
+fn backend_is_rebuilt_after_the_audio_service_restarts() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (tx, rx) = mpsc::channel();
+    let worker = AudioWorker::spawn_with(
+        mock.factory(),
+        move |event: MixerEvent| { let _ = tx.send(event); },
+        |b| b.set_hidden_apps(vec!["UPDATER.EXE".to_string()]),
+    );
+    worker.call_blocking(|_| Ok(())).unwrap();
+
+    mock.disconnect();
+    // 作り直しは前回の生成から一定時間空けて行われる
+    let deadline = Instant::now() + RECONNECT_TIMEOUT;
+    loop {
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(MixerEvent::BackendReconnected) => break,
+            Ok(_) => {}
+            Err(_) => panic!("backend was not rebuilt"),
+        }
+    }
+
+    mock.add_session(MockAudioHandle::session_info(30, "UPDATER.EXE", "speakers"));
+    let sessions = worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    let pids: Vec<u32> = sessions.iter().map(|s| s.process_id).collect();
+    assert_eq!(pids, vec![10], "init hook should be applied to the rebuilt backend");
+}
+
+#[test]
+fn restart_rebuilds_the_backend_without_waiting() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (tx, rx) = mpsc::channel();
+    let created = Arc::new(AtomicUsize::new(0));
+    let counter = created.clone();
+    let worker = AudioWorker::spawn_with(
+        mock.factory(),
+        move |event: MixerEvent| { let _ = tx.send(event); },
+        move |_| { counter.fetch_add(1, Ordering::SeqCst); },
+    );
+    worker.call_blocking(|_| Ok(())).unwrap();
+
+    worker.restart();
+    // 作り直しの間隔を待たずに、次の呼び出しは新しいバックエンドで処理される
+    let sessions = worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    assert_eq!(created.load(Ordering::SeqCst), 2);
+    assert_eq!(sessions.len(), 1);
+    wait_for(&rx, |event| matches!(event, MixerEvent::BackendReconnected).then_some(()));
+}
+
+#[test]
+fn shutdown_stops_worker_and_rejects_later_calls() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, _rx) = spawn(&mock);
+
+    worker.shutdown();
+
+    let result = worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string()));
+    assert_eq!(result, Err("Audio worker stopped".to_string()));
+}
+
+#[test]
+fn synced_playback_needs_two_known_devices_and_adjusts_delays() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+        AudioDeviceInfo { id: "headphones".to_string(), name: "Headphones".to_string(), is_default: false, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+    let tone = Signal::Tone(ToneRequest { frequency: 440.0, amplitude: 0.5, waveform: Waveform::Sine, duration_ms: None });
+    let outputs = |ids: &[&str]| ids.iter().map(|id| SyncedOutput { device_id: id.to_string(), delay_ms: 0 }).collect::<Vec<_>>();
+    let start = |outputs: Vec<SyncedOutput>| {
+        let request = SyncedPlaybackRequest { signal: tone.clone(), outputs, output: OutputFormat::default() };
+        worker.call_blocking(move |b| b.start_synced_playback(request).map_err(|e| e.to_string()))
+    };
+    let set_delay = |device_id: &'static str, delay_ms: u32| {
+        worker.call_blocking(move |b| b.set_playback_delay(device_id, delay_ms).map_err(|e| e.to_string()))
+    };
+    assert!(start(outputs(&["speakers"])).is_err());
+    assert!(start(outputs(&["speakers", "speakers"])).is_err());
+    assert!(start(outputs(&["speakers", "missing"])).is_err());
+    assert!(set_delay("speakers", 10).is_err());
+
+    start(outputs(&["speakers", "headphones"])).unwrap();
+    set_delay("headphones", 120).unwrap();
+    assert!(set_delay("headphones", MAX_PLAYBACK_DELAY_MS + 1).is_err());
+    assert!(set_delay("missing", 10).is_err());
+    worker.call_blocking(|b| { b.stop_playback(); Ok(()) }).unwrap();
+    assert!(set_delay("headphones", 0).is_err());
+    assert_eq!(mock.calls(), vec![
+        MockCall::StartSyncedPlayback(SyncedPlaybackRequest { signal: tone.clone(), outputs: outputs(&["speakers", "headphones"]), output: OutputFormat::default() }),
+        MockCall::SetPlaybackDelay { device_id: "headphones".to_string(), delay_ms: 120 },
+        MockCall::StopPlayback,
+    ]);
+}
+
+#[test]
+fn delayed_source_inserts_and_skips_silence_when_the_delay_changes() {
+    // 8 kHz なら 1 ms が 8 フレーム
+    let tone = ToneRequest { frequency: 1000.0, amplitude: 1.0, waveform: Waveform::Square, duration_ms: None };
+    let delay = Arc::new(AtomicU32::new(10));
+    let mut source = DelayedSource::new(Box::new(ToneSource::new(Signal::Tone(tone), 8000)), 8000, delay.clone());
+    let mut out = vec![1.0f32; 160];
+    assert!(source.fill(&mut out, 2));
+    assert!(out[..160].iter().all(|&s| s == 0.0));
+    let mut out = vec![0.0f32; 40];
+    source.fill(&mut out, 2);
+    assert!(out.iter().all(|s| s.abs() > 0.5));
+
+    // 遅らせると、その分だけ無音が入る
+    delay.store(15, Ordering::Relaxed);
+    let mut out = vec![1.0f32; 100];
+    source.fill(&mut out, 2);
+    assert!(out[..80].iter().all(|&s| s == 0.0));
+    assert!(out[80..].iter().all(|s| s.abs() > 0.5));
+
+    // 縮めると無音は入らず、信号が読み飛ばされる
+    delay.store(0, Ordering::Relaxed);
+    let mut out = vec![0.0f32; 40];
+    source.fill(&mut out, 2);
+    assert!(out.iter().all(|s| s.abs() > 0.5));
+}
+
+#[test]
+fn burn_in_ramps_stays_under_the_cap_and_stops_by_itself() {
+    let burn_in = BurnIn { waveform: Waveform::PinkNoise, amplitude: MAX_BURN_IN_AMPLITUDE, duration_ms: 1000, ramp_ms: 250 };
+    assert!(burn_in.validate().is_ok());
+    for bad in [
+        BurnIn { waveform: Waveform::Sine, ..burn_in },
+        BurnIn { amplitude: MAX_BURN_IN_AMPLITUDE + 0.1, ..burn_in },
+        BurnIn { duration_ms: 0, ..burn_in },
+        BurnIn { duration_ms: MAX_BURN_IN_MS + 1, ..burn_in },
+        BurnIn { ramp_ms: 600, ..burn_in },
+    ] {
+        assert!(bad.validate().is_err(), "{:?}", bad);
+    }
+    assert_eq!(burn_in.level_at(0), 0.0);
+    assert_eq!(burn_in.level_at(125), MAX_BURN_IN_AMPLITUDE / 2.0);
+    assert_eq!(burn_in.level_at(500), MAX_BURN_IN_AMPLITUDE);
+    assert_eq!(burn_in.level_at(1000), 0.0);
+
+    // 8 kHz で 1 秒 = 8000 フレーム
+    let mut source = BurnInSource::new(burn_in, 8000);
+    let mut out = vec![0.0f32; 4000];
+    assert!(source.fill(&mut out, 1));
+    let loudest = |samples: &[f32]| samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    assert!(loudest(&out) <= MAX_BURN_IN_AMPLITUDE);
+    assert!(loudest(&out[..200]) < loudest(&out[2000..]));
+    let mut out = vec![1.0f32; 4000];
+    assert!(!source.fill(&mut out, 1));
+    assert!(loudest(&out) <= MAX_BURN_IN_AMPLITUDE);
+    let mut out = vec![1.0f32; 100];
+    assert!(!source.fill(&mut out, 1));
+    assert!(out.iter().all(|&s| s == 0.0));
+}
+
+#[test]
+fn sweep_recording_is_turned_into_band_levels() {
+    // 8 kHz で 2 秒のスイープを 125 ms 遅れで録り、後半（約 550 Hz 以上）は半分の大きさで届いたことにする
+    let sweep = Sweep { start_hz: 100.0, end_hz: 3000.0, amplitude: 0.5, duration_ms: 2000 };
+    let mut played = vec![0.0f32; 16_000];
+    assert!(!SweepSource::new(sweep, 8000).fill(&mut played, 1));
+    let mut samples = vec![0.0f32; 1000];
+    samples.extend(played.iter().enumerate().map(|(i, s)| if i < 8000 { *s } else { s * 0.5 }));
+    samples.extend(vec![0.0f32; 1000]);
+    let response = measurement::analyze(&sweep, 3, &Recording { samples, sample_rate: 8000 }).unwrap();
+
+    assert!((response.latency_ms - 125.0).abs() < 1.0, "{}", response.latency_ms);
+    let first = response.bands.first().unwrap();
+    let last = response.bands.last().unwrap();
+    assert_eq!(first.frequency, 100.0);
+    assert!(last.frequency <= 3000.0 && last.frequency > 2000.0);
+    assert!(first.level_db.abs() < 1.0, "{:?}", first);
+    assert!((last.level_db + 6.0).abs() < 1.0, "{:?}", last);
+
+    let silence = Recording { samples: vec![0.0; 20_000], sample_rate: 8000 };
+    assert!(measurement::analyze(&sweep, 3, &silence).is_err());
+}
+
+#[test]
+fn recording_uses_known_capture_devices() {
+    let mock = MockAudioHandle::new();
+    let (worker, _rx) = spawn(&mock);
+    mock.set_capture_devices(vec![
+        AudioDeviceInfo { id: "mic".to_string(), name: "Microphone".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    let recorded = Recording { samples: vec![0.25; 480], sample_rate: 48_000 };
+    mock.set_recorded(recorded.clone());
+    let request = MeasurementRequest {
+        output_device_id: None,
+        input_device_id: Some("mic".to_string()),
+        sweep: Sweep { start_hz: 20.0, end_hz: 20_000.0, amplitude: 0.5, duration_ms: 5000 },
+        bands_per_octave: 3,
+    };
+    assert!(request.validate().is_ok());
+    assert!(MeasurementRequest { bands_per_octave: 0, ..request.clone() }.validate().is_err());
+    assert!(MeasurementRequest { sweep: Sweep { start_hz: 1000.0, end_hz: 500.0, ..request.sweep }, ..request.clone() }.validate().is_err());
+
+    assert!(worker.call_blocking(|b| b.stop_recording().map(|_| ()).map_err(|e| e.to_string())).is_err());
+    assert!(worker.call_blocking(|b| b.start_recording(Some("missing"), 1000).map_err(|e| e.to_string())).is_err());
+    worker.call_blocking(|b| b.start_recording(Some("mic"), 6000).map_err(|e| e.to_string())).unwrap();
+    let recording = worker.call_blocking(|b| b.stop_recording().map_err(|e| e.to_string())).unwrap();
+    assert_eq!(recording, recorded);
+    assert_eq!(mock.calls(), vec![
+        MockCall::StartRecording { device_id: Some("mic".to_string()), max_ms: 6000 },
+        MockCall::StopRecording,
+    ]);
+}
+
+#[test]
+fn generator_shows_up_as_its_own_session() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "GAME.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+    let tone = ToneRequest { frequency: 440.0, amplitude: 0.5, waveform: Waveform::Sine, duration_ms: None };
+    let request = PlaybackRequest { device_id: None, signal: Signal::Tone(tone), output: OutputFormat::default() };
+    worker.call_blocking(move |b| b.start_playback(request).map_err(|e| e.to_string())).unwrap();
+
+    let sessions = worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    let generator = sessions.iter().find(|s| s.process_name == GENERATOR_SESSION_NAME).expect("generator session").clone();
+    wait_for_diff(&rx, |diff| diff.added.iter().any(|s| s.instance_id == generator.instance_id));
+    assert_eq!(generator.process_id, std::process::id());
+    assert_eq!(generator.device_id, "speakers");
+    // アプリのほかのセッションと区別して音量を変えられる
+    let instance_id = generator.instance_id.clone();
+    worker.call_blocking(move |b| b.set_instance_volume(&instance_id, 0.3).map_err(|e| e.to_string())).unwrap();
+    let sessions = worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    assert_eq!(sessions.iter().find(|s| s.instance_id == generator.instance_id).unwrap().volume, 0.3);
+    assert_eq!(sessions.iter().find(|s| s.process_id == 10).unwrap().volume, 1.0);
+
+    worker.call_blocking(|b| { b.stop_playback(); Ok(()) }).unwrap();
+    wait_for_diff(&rx, |diff| diff.removed.iter().any(|s| s.instance_id == generator.instance_id));
+    let sessions = worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    assert!(sessions.iter().all(|s| s.process_name != GENERATOR_SESSION_NAME));
+}
+
+/// 16bit PCM モノラルの WAV を書き出します。中身はサンプル番号を 1/1000 にしたランプ
+fn write_wav(path: &std::path::Path, sample_rate: u32, frames: u32) {
+    let data_len = frames * 2;
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for i in 0..frames {
+        bytes.extend_from_slice(&((i % 1000) as i16 * 16).to_le_bytes());
+    }
+    std::fs::write(path, bytes).unwrap();
+}
+
+#[test]
+fn audio_file_plays_to_the_end_and_seeks() {
+    let path = std::env::temp_dir().join(format!("sound-generator-test-{}.wav", std::process::id()));
+    write_wav(&path, 8000, 8000);
+    let seek = SeekRequest::default();
+    let mut source = AudioFileSource::open(path.to_str().unwrap(), seek.clone()).unwrap();
+    assert_eq!(source.format(), DeviceFormat { sample_rate: 8000, bits_per_sample: 32, container_bits: 32, float: true, channels: 1 });
+    assert_eq!(source.duration_ms(), Some(1000));
+
+    let mut out = vec![0.0f32; 2000];
+    assert!(source.fill(&mut out, 1));
+    assert_eq!(source.progress_ms(), Some((250, Some(1000))));
+    assert_eq!(out[10], 160.0 / 32768.0);
+    // ステレオの出力には同じ音を両方のチャンネルへ
+    let mut stereo = vec![0.0f32; 4];
+    assert!(source.fill(&mut stereo, 2));
+    assert_eq!(stereo[0], stereo[1]);
+
+    seek.request(500);
+    let mut out = vec![0.0f32; 1000];
+    assert!(source.fill(&mut out, 1));
+    assert_eq!(source.position_ms(), 625);
+    assert_eq!(out[0], 0.0);
+    // 残りは 3000 フレーム。鳴らし終えたら止まり、足りない分は無音
+    let mut out = vec![1.0f32; 4000];
+    assert!(!source.fill(&mut out, 1));
+    assert!(out[3000..].iter().all(|&s| s == 0.0));
+    assert!(out[..3000].iter().any(|&s| s != 0.0));
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(AudioFileSource::open(path.to_str().unwrap(), SeekRequest::default()).is_err());
+    assert!(Signal::File { path: String::new() }.validate().is_err());
+}
+
+#[test]
+fn seeking_needs_a_playing_audio_file() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+    assert!(worker.call_blocking(|b| b.seek_playback(500).map_err(|e| e.to_string())).is_err());
+    let request = PlaybackRequest { device_id: None, signal: Signal::File { path: "music.flac".to_string() }, output: OutputFormat::default() };
+    worker.call_blocking(move |b| b.start_playback(request).map_err(|e| e.to_string())).unwrap();
+    worker.call_blocking(|b| b.seek_playback(500).map_err(|e| e.to_string())).unwrap();
+    assert!(mock.calls().contains(&MockCall::SeekPlayback(500)));
+}
+
+/// テストごとに空のフォルダーを用意します。
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("sound-generator-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn file_capture(dir: &std::path::Path, encoding: FileEncoding) -> FileCaptureRequest {
+    FileCaptureRequest {
+        source: CaptureSource::Device(None),
+        directory: dir.to_string_lossy().into_owned(),
+        file_prefix: "capture".to_string(),
+        encoding,
+        sample_rate: Some(8000),
+        channels: 2,
+        rotation: Rotation::default(),
+    }
+}
+
+/// 440 Hz の正弦波をステレオで `frames` フレーム分
+fn stereo_sine(frames: usize) -> Vec<f32> {
+    (0..frames).flat_map(|i| {
+        let s = 0.5 * (i as f32 * 440.0 * std::f32::consts::TAU / 8000.0).sin();
+        [s, -s]
+    }).collect()
+}
+
+#[test]
+fn file_capture_writes_wav_and_flac_that_decode_back() {
+    let dir = temp_dir("capture-formats");
+    let samples = stereo_sine(12_000);
+    for encoding in [FileEncoding::Wav { bits_per_sample: 24 }, FileEncoding::Wav { bits_per_sample: 32 }, FileEncoding::Flac { bits_per_sample: 16 }] {
+        let mut writer = FileWriter::create(file_capture(&dir, encoding)).unwrap();
+        // 届く単位はまちまちなので、半端な長さに分けて書く
+        for chunk in samples.chunks(1234 * 2) {
+            writer.write(chunk).unwrap();
+        }
+        let files = writer.finish().unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].ends_with(&format!("capture-001.{}", encoding.extension())), "{}", files[0]);
+
+        let mut source = AudioFileSource::open(&files[0], SeekRequest::default()).unwrap();
+        assert_eq!(source.format().sample_rate, 8000);
+        assert_eq!(source.format().channels, 2);
+        assert_eq!(source.duration_ms(), Some(1500));
+        let mut decoded = vec![0.0f32; samples.len()];
+        source.fill(&mut decoded, 2);
+        let error = decoded.iter().zip(&samples).fold(0.0f32, |max, (a, b)| max.max((a - b).abs()));
+        assert!(error < 1e-3, "{:?}: {}", encoding, error);
+        std::fs::remove_file(&files[0]).unwrap();
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_capture_writes_ogg_opus() {
+    let dir = temp_dir("capture-opus");
+    let request = FileCaptureRequest { sample_rate: Some(48_000), ..file_capture(&dir, FileEncoding::OggOpus { bitrate_kbps: 96 }) };
+    assert!(FileCaptureRequest { sample_rate: Some(44_100), ..request.clone() }.validate().is_err());
+    let mut writer = FileWriter::create(request).unwrap();
+    writer.write(&vec![0.25f32; 48_000 * 2]).unwrap();
+    let files = writer.finish().unwrap();
+    let bytes = std::fs::read(&files[0]).unwrap();
+    assert!(files[0].ends_with("capture-001.opus"));
+    assert_eq!(&bytes[..4], b"OggS");
+    assert_eq!(&bytes[28..36], b"OpusHead");
+    assert_eq!(bytes[37], 2);
+    // 最後のページにはストリームの終わりの印（ヘッダー種別 0x04）が付き、グラニュール位置は先読み分 + 1 秒
+    let last = bytes.windows(4).rposition(|w| w == b"OggS").unwrap();
+    assert_eq!(bytes[last + 5] & 0x04, 0x04);
+    assert_eq!(u64::from_le_bytes(bytes[last + 6..last + 14].try_into().unwrap()), 312 + 48_000);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_capture_rotates_by_duration_and_keeps_existing_files() {
+    let dir = temp_dir("capture-rotation");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("capture-001.wav"), b"keep").unwrap();
+    let request = FileCaptureRequest {
+        rotation: Rotation { max_bytes: None, max_ms: Some(1000) },
+        ..file_capture(&dir, FileEncoding::Wav { bits_per_sample: 16 })
+    };
+    let mut writer = FileWriter::create(request).unwrap();
+    writer.write(&stereo_sine(20_000)).unwrap();
+    let files = writer.finish().unwrap();
+    let names: Vec<_> = files.iter().map(|f| std::path::Path::new(f).file_name().unwrap().to_string_lossy().into_owned()).collect();
+    assert_eq!(names, ["capture-002.wav", "capture-003.wav", "capture-004.wav"]);
+    let durations: Vec<_> = files.iter()
+        .map(|f| AudioFileSource::open(f, SeekRequest::default()).unwrap().duration_ms())
+        .collect();
+    assert_eq!(durations, [Some(1000), Some(1000), Some(500)]);
+    assert_eq!(std::fs::read(dir.join("capture-001.wav")).unwrap(), b"keep");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_capture_requests_are_validated() {
+    let dir = temp_dir("capture-validate");
+    let request = file_capture(&dir, FileEncoding::Flac { bits_per_sample: 24 });
+    assert!(request.validate().is_ok());
+    for bad in [
+        FileCaptureRequest { encoding: FileEncoding::Flac { bits_per_sample: 32 }, ..request.clone() },
+        FileCaptureRequest { encoding: FileEncoding::Wav { bits_per_sample: 8 }, ..request.clone() },
+        FileCaptureRequest { encoding: FileEncoding::OggOpus { bitrate_kbps: 1000 }, sample_rate: Some(48_000), ..request.clone() },
+        FileCaptureRequest { channels: 6, ..request.clone() },
+        FileCaptureRequest { sample_rate: Some(1000), ..request.clone() },
+        FileCaptureRequest { file_prefix: "../escape".to_string(), ..request.clone() },
+        FileCaptureRequest { rotation: Rotation { max_bytes: Some(1000), max_ms: None }, ..request.clone() },
+        FileCaptureRequest { rotation: Rotation { max_bytes: None, max_ms: Some(10) }, ..request.clone() },
+    ] {
+        assert!(bad.validate().is_err(), "{:?}", bad);
+    }
+}
+
+#[test]
+fn file_capture_needs_a_known_source() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    let dir = temp_dir("capture-mock");
+    let request = FileCaptureRequest { source: CaptureSource::Device(Some("missing".to_string())), ..file_capture(&dir, FileEncoding::Wav { bits_per_sample: 16 }) };
+    assert!(worker.call_blocking(move |b| b.start_file_capture(request).map_err(|e| e.to_string())).is_err());
+    let request = FileCaptureRequest { source: CaptureSource::App(4242), ..file_capture(&dir, FileEncoding::Wav { bits_per_sample: 16 }) };
+    assert!(worker.call_blocking(move |b| b.start_file_capture(request).map_err(|e| e.to_string())).is_err());
+    assert!(worker.call_blocking(|b| b.stop_file_capture().map_err(|e| e.to_string())).is_err());
+
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+    let request = file_capture(&dir, FileEncoding::Wav { bits_per_sample: 16 });
+    let expected = request.clone();
+    worker.call_blocking(move |b| b.start_file_capture(request).map_err(|e| e.to_string())).unwrap();
+    worker.call_blocking(|b| b.stop_file_capture().map_err(|e| e.to_string())).unwrap();
+    wait_for(&rx, |event| matches!(event, MixerEvent::FileCaptureStopped { error: None, .. }).then_some(()));
+    let calls = mock.calls();
+    assert!(calls.contains(&MockCall::StartFileCapture(expected)));
+    assert!(calls.contains(&MockCall::StopFileCapture));
+}
+
+fn network_stream(port: u16) -> NetworkStreamRequest {
+    NetworkStreamRequest { source: CaptureSource::Device(None), port, allow_lan: false, sample_rate: Some(8000), channels: 2 }
+}
+
+/// サーバーに 1 行のリクエストを送り、応答の先頭 `len` バイトを読みます。
+fn http_request(port: u16, request_line: &str, len: usize) -> (std::net::TcpStream, Vec<u8>) {
+    use std::io::{Read, Write};
+    let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+    stream.write_all(format!("{}\r\nHost: localhost\r\n\r\n", request_line).as_bytes()).unwrap();
+    let mut bytes = vec![0u8; len];
+    let mut read = 0;
+    while read < len {
+        match stream.read(&mut bytes[read..]).unwrap() {
+            0 => break,
+            n => read += n,
+        }
+    }
+    bytes.truncate(read);
+    (stream, bytes)
+}
+
+#[test]
+fn stream_server_sends_pcm_wav_to_listeners() {
+    let server = StreamServer::start(network_stream(0)).unwrap();
+    let port = server.port();
+    let info = server.info();
+    assert_eq!(info.url, format!("http://127.0.0.1:{}{}", port, STREAM_PATH));
+    assert_eq!(info.listeners, 0);
+
+    let (_, not_found) = http_request(port, "GET /other HTTP/1.1", 64);
+    assert!(String::from_utf8_lossy(&not_found).starts_with("HTTP/1.1 404"));
+    let (_, not_allowed) = http_request(port, "POST /stream.wav HTTP/1.1", 64);
+    assert!(String::from_utf8_lossy(&not_allowed).starts_with("HTTP/1.1 405"));
+
+    let reader = std::thread::spawn(move || {
+        let head = "HTTP/1.1 200 OK\r\nContent-Type: audio/wav\r\nCache-Control: no-cache, no-store\r\nConnection: close\r\n\r\n";
+        let (_, bytes) = http_request(port, "GET /stream.wav HTTP/1.1", head.len() + 44 + 8);
+        assert_eq!(&bytes[..head.len()], head.as_bytes());
+        bytes[head.len()..].to_vec()
+    });
+    let started = Instant::now();
+    while server.info().listeners == 0 {
+        assert!(started.elapsed() < TIMEOUT, "listener never connected");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    server.push(&[0.5, -0.5, 1.0, -1.0]);
+    let wav = reader.join().unwrap();
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u16::from_le_bytes([wav[22], wav[23]]), 2);
+    assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 8000);
+    assert_eq!(u16::from_le_bytes([wav[34], wav[35]]), 16);
+    let samples: Vec<i16> = wav[44..].chunks(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+    assert_eq!(samples, [16384, -16384, 32767, -32767]);
+
+    // 切断した相手は次に送るときに外れる
+    let started = Instant::now();
+    while server.info().listeners > 0 {
+        assert!(started.elapsed() < TIMEOUT, "listener was never removed");
+        server.push(&[0.0; 2]);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn network_stream_needs_a_known_source() {
+    let mock = MockAudioHandle::new();
+    let (worker, rx) = spawn(&mock);
+    let request = NetworkStreamRequest { source: CaptureSource::App(4242), ..network_stream(8080) };
+    assert!(worker.call_blocking(move |b| b.start_network_stream(request).map_err(|e| e.to_string())).is_err());
+    let request = NetworkStreamRequest { channels: 3, ..network_stream(8080) };
+    assert!(worker.call_blocking(move |b| b.start_network_stream(request).map_err(|e| e.to_string())).is_err());
+    assert_eq!(worker.call_blocking(|b| Ok(b.get_network_stream())).unwrap(), None);
+
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "speakers".to_string(), name: "Speakers".to_string(), is_default: true, jack_connected: None, bluetooth: None },
+    ]);
+    wait_for(&rx, |event| matches!(event, MixerEvent::DevicesChanged).then_some(()));
+    let request = network_stream(8080);
+    let expected = NetworkStream { request: request.clone(), url: format!("http://127.0.0.1:8080{}", STREAM_PATH), listeners: 0 };
+    let started = worker.call_blocking(move |b| b.start_network_stream(request).map_err(|e| e.to_string())).unwrap();
+    assert_eq!(started, expected);
+    assert_eq!(worker.call_blocking(|b| Ok(b.get_network_stream())).unwrap(), Some(expected.clone()));
+    worker.call_blocking(|b| {
+        b.stop_network_stream();
+        Ok(())
+    }).unwrap();
+    wait_for(&rx, |event| matches!(event, MixerEvent::NetworkStreamStopped { error: None }).then_some(()));
+    assert_eq!(worker.call_blocking(|b| Ok(b.get_network_stream())).unwrap(), None);
+    let calls = mock.calls();
+    assert!(calls.contains(&MockCall::StartNetworkStream(expected.request)));
+    assert!(calls.contains(&MockCall::StopNetworkStream));
+}
+
+fn device(id: &str, name: &str) -> AudioDeviceInfo {
+    AudioDeviceInfo { id: id.to_string(), name: name.to_string(), is_default: false, jack_connected: None, bluetooth: None }
+}
+
+#[test]
+fn virtual_cables_pair_playback_and_recording_sides() {
+    let playback = [
+        device("speakers", "Speakers (Realtek(R) Audio)"),
+        device("cable-in", "CABLE Input (VB-Audio Virtual Cable)"),
+        device("hifi-in", "Hi-Fi Cable Input (VB-Audio Hi-Fi Cable)"),
+        device("vm-in", "VoiceMeeter Input (VB-Audio VoiceMeeter VAIO)"),
+    ];
+    let recording = [
+        device("mic", "Microphone (Realtek(R) Audio)"),
+        device("cable-out", "CABLE Output (VB-Audio Virtual Cable)"),
+        device("vm-out", "VoiceMeeter Output (VB-Audio VoiceMeeter VAIO)"),
+    ];
+    let cable = |kind, name: &str, playback: &str, recording: Option<&str>| VirtualCable {
+        kind,
+        name: name.to_string(),
+        playback_device_id: playback.to_string(),
+        recording_device_id: recording.map(str::to_string),
+    };
+    assert_eq!(virtual_cable::detect(&playback, &recording), [
+        cable(VirtualCableKind::VbCable, "CABLE Input (VB-Audio Virtual Cable)", "cable-in", Some("cable-out")),
+        // 録音側が無効にされている
+        cable(VirtualCableKind::VbCable, "Hi-Fi Cable Input (VB-Audio Hi-Fi Cable)", "hifi-in", None),
+        cable(VirtualCableKind::VoiceMeeter, "VoiceMeeter Input (VB-Audio VoiceMeeter VAIO)", "vm-in", Some("vm-out")),
+    ]);
+}
+
+#[test]
+fn input_monitor_needs_known_devices() {
+    let mock = MockAudioHandle::new();
+    mock.set_devices(vec![device("speakers", "Speakers")]);
+    mock.set_capture_devices(vec![device("cable-out", "CABLE Output (VB-Audio Virtual Cable)")]);
+    let (worker, _rx) = spawn(&mock);
+    assert!(worker.call_blocking(|b| b.set_input_monitor("missing", Some("speakers")).map_err(|e| e.to_string())).is_err());
+    assert!(worker.call_blocking(|b| b.set_input_monitor("cable-out", Some("missing")).map_err(|e| e.to_string())).is_err());
+    worker.call_blocking(|b| b.set_input_monitor("cable-out", Some("speakers")).map_err(|e| e.to_string())).unwrap();
+    worker.call_blocking(|b| b.set_input_monitor("cable-out", None).map_err(|e| e.to_string())).unwrap();
+    let calls = mock.calls();
+    assert!(calls.contains(&MockCall::SetInputMonitor { capture_device_id: "cable-out".to_string(), output_device_id: Some("speakers".to_string()) }));
+    assert!(calls.contains(&MockCall::SetInputMonitor { capture_device_id: "cable-out".to_string(), output_device_id: None }));
+}
+
+fn now_playing(title: &str, status: PlaybackStatus) -> NowPlaying {
+    NowPlaying {
+        title: title.to_string(),
+        artist: "Artist".to_string(),
+        album: "Album".to_string(),
+        status,
+        can_play: true,
+        can_pause: true,
+        can_next: true,
+        can_previous: true,
+    }
+}
+
+#[test]
+fn now_playing_changes_are_published_with_the_session() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "SPOTIFY.EXE", "speakers"));
+    let (worker, rx) = spawn(&mock);
+    let sessions = worker.call_blocking(|b| b.get_sessions().map_err(|e| e.to_string())).unwrap();
+    assert_eq!(sessions[0].now_playing, None);
+    wait_for_diff(&rx, |d| !d.added.is_empty());
+
+    mock.set_now_playing(10, Some(now_playing("Song", PlaybackStatus::Playing)));
+    let diff = wait_for_diff(&rx, |d| d.updated.iter().any(|s| s.now_playing.is_some()));
+    assert_eq!(diff.updated[0].now_playing, Some(now_playing("Song", PlaybackStatus::Playing)));
+
+    mock.set_now_playing(10, None);
+    wait_for_diff(&rx, |d| d.updated.iter().any(|s| s.process_id == 10 && s.now_playing.is_none()));
+}
+
+#[test]
+fn media_commands_go_only_to_apps_with_a_media_session() {
+    let mock = MockAudioHandle::new();
+    mock.add_session(MockAudioHandle::session_info(10, "SPOTIFY.EXE", "speakers"));
+    mock.add_session(MockAudioHandle::session_info(20, "GAME.EXE", "speakers"));
+    mock.set_now_playing(10, Some(now_playing("Song", PlaybackStatus::Playing)));
+    let (worker, _rx) = spawn(&mock);
+    assert!(matches!(worker.call_blocking(|b| Ok(b.send_media_command(30, MediaCommand::Play))).unwrap(), Err(AudioError::SessionNotFound(30))));
+    assert!(worker.call_blocking(|b| b.send_media_command(20, MediaCommand::Play).map_err(|e| e.to_string())).is_err());
+
+    worker.call_blocking(|b| b.send_media_command(10, MediaCommand::TogglePlayPause).map_err(|e| e.to_string())).unwrap();
+    assert_eq!(mock.session(10).and_then(|s| s.now_playing).map(|n| n.status), Some(PlaybackStatus::Paused));
+    worker.call_blocking(|b| b.send_media_command(10, MediaCommand::Next).map_err(|e| e.to_string())).unwrap();
+    assert_eq!(mock.calls(), vec![
+        MockCall::SendMediaCommand { pid: 10, command: MediaCommand::TogglePlayPause },
+        MockCall::SendMediaCommand { pid: 10, command: MediaCommand::Next },
+    ]);
+}
+
+fn process(pid: u32, exe_name: &str) -> ProcessEntry {
+    ProcessEntry { pid, exe_name: exe_name.to_string() }
+}
+
+#[test]
+fn process_watcher_reports_started_and_exited_processes() {
+    let processes = Arc::new(std::sync::Mutex::new(vec![process(4, "SYSTEM"), process(100, "explorer.exe")]));
+    let snapshots = Arc::new(AtomicUsize::new(0));
+    let (tx, rx) = mpsc::channel();
+    let snapshot = {
+        let (processes, snapshots) = (processes.clone(), snapshots.clone());
+        move || {
+            snapshots.fetch_add(1, Ordering::SeqCst);
+            Some(processes.lock().unwrap().clone())
+        }
+    };
+    let _watcher = ProcessWatcher::spawn_with(snapshot, move |event: MixerEvent| { let _ = tx.send(event); });
+    // 最初の一覧は基準になるだけなので、取られてから起動させる
+    while snapshots.load(Ordering::SeqCst) == 0 {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    processes.lock().unwrap().push(process(200, "spotify.exe"));
+    let started = wait_for(&rx, |event| match event {
+        MixerEvent::ProcessStarted { pid, exe_name } => Some((pid, exe_name)),
+        _ => None,
+    });
+    assert_eq!(started, (200, "spotify.exe".to_string()));
+
+    // PID が使い回されたら、終了と起動の両方を通知する
+    *processes.lock().unwrap() = vec![process(4, "SYSTEM"), process(100, "game.exe")];
+    let mut events = Vec::new();
+    while events.len() < 3 {
+        events.push(wait_for(&rx, |event| match event {
+            MixerEvent::ProcessStarted { pid, exe_name } => Some((true, pid, exe_name)),
+            MixerEvent::ProcessExited { pid, exe_name } => Some((false, pid, exe_name)),
+            _ => None,
+        }));
+    }
+    assert_eq!(events, vec![
+        (false, 100, "explorer.exe".to_string()),
+        (false, 200, "spotify.exe".to_string()),
+        (true, 100, "game.exe".to_string()),
+    ]);
+}
+
+#[test]
+fn process_watch_interval_backs_off_while_idle() {
+    let mut interval = process_watch::MIN_INTERVAL;
+    for _ in 0..10 {
+        interval = process_watch::next_interval(interval, false);
+    }
+    assert_eq!(interval, process_watch::MAX_INTERVAL);
+    assert_eq!(process_watch::next_interval(interval, true), process_watch::MIN_INTERVAL);
+    assert_eq!(process_watch::next_interval(process_watch::MIN_INTERVAL, false), process_watch::MIN_INTERVAL * 2);
+}
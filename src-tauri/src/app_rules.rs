@@ -0,0 +1,177 @@
+//! ウィンドウのタイトルやコマンドラインで選ぶアプリごとの設定。実行ファイル名だけでは区別できないもの
+//! （`chrome.exe --app=...` で開いたアプリと普段のブラウザーなど）に、別々の出力先や音量を割り当てます。
+//! ルールは新しいプロセスのセッションが現れたときに調べます。出力先はプロセスが起動した時点でも調べて、
+//! 音を出し始める前に切り替えておきます。
+
+use std::sync::Mutex;
+
+use regex::Regex;
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::broadcast;
+
+use crate::audio::{AudioSessionInfo, AudioWorker, MixerEvent};
+use crate::events::EventBus;
+use crate::i18n::{self, Text};
+use crate::settings::{normalize_exe_name, AppRule, RuleMatch, SettingsManager};
+
+/// ルールを調べるためのプロセスの情報。ルールがあるときだけ取得する
+struct ProcessInfo {
+    window_titles: Vec<String>,
+    command_line: Option<String>,
+}
+
+fn validate(rule: &AppRule) -> Result<(), String> {
+    match &rule.matches {
+        RuleMatch::WindowTitle(pattern) => Regex::new(pattern).map(|_| ()).map_err(|e| i18n::format(Text::InvalidRulePattern, &e.to_string())),
+        RuleMatch::CommandLine(text) if text.trim().is_empty() => Err(i18n::format(Text::InvalidRulePattern, text)),
+        RuleMatch::CommandLine(_) => Ok(()),
+    }
+}
+
+fn matches(rule: &AppRule, exe_name: &str, info: &ProcessInfo) -> bool {
+    if rule.exe_name.as_ref().is_some_and(|name| *name != normalize_exe_name(exe_name)) {
+        return false;
+    }
+    match &rule.matches {
+        RuleMatch::WindowTitle(pattern) => Regex::new(pattern).is_ok_and(|re| info.window_titles.iter().any(|t| re.is_match(t))),
+        RuleMatch::CommandLine(text) => info.command_line.as_ref().is_some_and(|c| c.to_lowercase().contains(&text.to_lowercase())),
+    }
+}
+
+/// 最初に当てはまったルールをセッションのプロセスに適用します。ルールで音量かミュートを決めた場合は `true` を返すので、
+/// 呼び出し元は覚えている音量で上書きしないでください。
+pub async fn apply(app: &AppHandle, session: &AudioSessionInfo) -> bool {
+    let rules = app.state::<Mutex<SettingsManager>>().lock().map(|s| s.settings().app_rules.clone()).unwrap_or_default();
+    if rules.is_empty() {
+        return false;
+    }
+    let pid = session.process_id;
+    let info = ProcessInfo { window_titles: window_titles(pid), command_line: command_line(pid) };
+    let Some(rule) = rules.into_iter().find(|rule| matches(rule, &session.exe_name, &info)) else { return false };
+    let worker = app.state::<AudioWorker>();
+    if let Some(device_id) = rule.device_id.filter(|d| *d != session.device_id) {
+        if let Err(e) = worker.call(move |m| m.set_audio_routing(pid, &device_id).map_err(i18n::audio_error)).await {
+            eprintln!("Failed to route PID {} by rule: {}", pid, e);
+        }
+    }
+    if let Some(volume) = rule.volume {
+        let _ = worker.set_session_volume(pid, volume).await;
+    }
+    if let Some(muted) = rule.muted {
+        let _ = worker.call(move |m| m.set_session_mute(pid, muted).map_err(i18n::audio_error)).await;
+    }
+    rule.volume.is_some() || rule.muted.is_some()
+}
+
+/// 起動したプロセスに当てはまるルールの出力先を設定します。まだウィンドウがないので、コマンドラインのルールだけが当てはまります。
+async fn route_process(app: &AppHandle, pid: u32, exe_name: &str) {
+    let rules = app.state::<Mutex<SettingsManager>>().lock().map(|s| s.settings().app_rules.clone()).unwrap_or_default();
+    if rules.is_empty() {
+        return;
+    }
+    let info = ProcessInfo { window_titles: Vec::new(), command_line: command_line(pid) };
+    let Some(device_id) = rules.into_iter().find(|rule| matches(rule, exe_name, &info)).and_then(|rule| rule.device_id) else { return };
+    let worker = app.state::<AudioWorker>();
+    if let Err(e) = worker.call(move |m| m.set_audio_routing(pid, &device_id).map_err(i18n::audio_error)).await {
+        eprintln!("Failed to route PID {} by rule: {}", pid, e);
+    }
+}
+
+/// プロセスの起動を受け取り、ルールの出力先を適用します。
+pub fn spawn_process_listener(app: AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(MixerEvent::ProcessStarted { pid, exe_name }) => route_process(&app, pid, &exe_name).await,
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// プロセスの表示されているトップレベルウィンドウのタイトル
+#[cfg(windows)]
+fn window_titles(pid: u32) -> Vec<String> {
+    use windows::Win32::Foundation::{BOOL, HWND, LPARAM, TRUE};
+    use windows::Win32::UI::WindowsAndMessaging::{EnumWindows, GetWindowTextW, GetWindowThreadProcessId, IsWindowVisible};
+
+    unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let (pid, titles) = &mut *(lparam.0 as *mut (u32, Vec<String>));
+        let mut owner = 0;
+        GetWindowThreadProcessId(hwnd, Some(&mut owner));
+        if owner == *pid && IsWindowVisible(hwnd).as_bool() {
+            let mut buffer = [0u16; 512];
+            let len = GetWindowTextW(hwnd, &mut buffer);
+            if len > 0 {
+                titles.push(String::from_utf16_lossy(&buffer[..len as usize]));
+            }
+        }
+        TRUE
+    }
+
+    let mut state = (pid, Vec::new());
+    unsafe {
+        let _ = EnumWindows(Some(collect), LPARAM(&mut state as *mut (u32, Vec<String>) as isize));
+    }
+    state.1
+}
+
+#[cfg(not(windows))]
+fn window_titles(_pid: u32) -> Vec<String> {
+    Vec::new()
+}
+
+/// プロセスのコマンドライン。管理者として動いているプロセスなど、開けなければ `None` です。
+#[cfg(windows)]
+fn command_line(pid: u32) -> Option<String> {
+    use windows::Wdk::System::Threading::{NtQueryInformationProcess, ProcessCommandLineInformation};
+    use windows::Win32::Foundation::{CloseHandle, UNICODE_STRING};
+    use windows::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut len = 0u32;
+        let _ = NtQueryInformationProcess(handle, ProcessCommandLineInformation, std::ptr::null_mut(), 0, &mut len);
+        // UNICODE_STRING の後ろに文字列が続く。ポインタを含むので 8 バイト境界に揃える
+        let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+        let status = NtQueryInformationProcess(handle, ProcessCommandLineInformation, buffer.as_mut_ptr().cast(), len, &mut len);
+        let _ = CloseHandle(handle);
+        if len == 0 || status.is_err() {
+            return None;
+        }
+        let text = &*(buffer.as_ptr() as *const UNICODE_STRING);
+        Some(String::from_utf16_lossy(std::slice::from_raw_parts(text.Buffer.0, text.Length as usize / 2)))
+    }
+}
+
+#[cfg(not(windows))]
+fn command_line(_pid: u32) -> Option<String> {
+    None
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_app_rules(settings: State<'_, Mutex<SettingsManager>>) -> Result<Vec<AppRule>, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().app_rules.clone())
+}
+
+/// ルールを最後に加えます。すでに動いているアプリには適用せず、次にセッションが現れたときから使います。
+#[tauri::command]
+#[specta::specta]
+pub fn add_app_rule(settings: State<'_, Mutex<SettingsManager>>, rule: AppRule) -> Result<Vec<AppRule>, String> {
+    validate(&rule)?;
+    let mut settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    settings.add_app_rule(rule)?;
+    Ok(settings.settings().app_rules.clone())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn remove_app_rule(settings: State<'_, Mutex<SettingsManager>>, index: usize) -> Result<Vec<AppRule>, String> {
+    let mut settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    settings.remove_app_rule(index)?;
+    Ok(settings.settings().app_rules.clone())
+}
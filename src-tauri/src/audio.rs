@@ -0,0 +1,24 @@
+pub use windows_app_mixer::*;
+use windows_app_mixer::mock::MockAudioHandle;
+
+/// UI の確認用に、いくつかのセッションとデバイスを持つモックを作ります。
+pub fn demo_mock() -> MockAudioHandle {
+    let mock = MockAudioHandle::new();
+    mock.set_devices(vec![
+        AudioDeviceInfo { id: "mock-speakers".to_string(), name: "Speakers (Mock)".to_string(), is_default: true, jack_connected: Some(true), bluetooth: None },
+        AudioDeviceInfo {
+            id: "mock-headset".to_string(),
+            name: "Headset (Mock)".to_string(),
+            is_default: false,
+            jack_connected: None,
+            bluetooth: Some(BluetoothInfo { battery_percent: Some(80), profile: Some(BluetoothProfile::HandsFree) }),
+        },
+    ]);
+    for (pid, exe_name, volume) in [(1001, "BROWSER.EXE", 0.8), (1002, "GAME.EXE", 0.5), (1003, "CHAT.EXE", 1.0)] {
+        let mut session = MockAudioHandle::session_info(pid, exe_name, "mock-speakers");
+        session.volume = volume;
+        mock.add_session(session);
+        mock.set_peak(pid, 0.3);
+    }
+    mock
+}
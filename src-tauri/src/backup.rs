@@ -0,0 +1,140 @@
+//! 設定ファイルの自動バックアップ。選んだフォルダ（OneDrive などの同期フォルダ）へ定期的に写して古いものを消し、
+//! 入れ直したあとでも振り分けのルールやプロファイルを一覧から選んで戻せるようにします。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use crate::i18n::{self, Text};
+use crate::settings::{BackupConfig, SettingsManager};
+use crate::settings_watch;
+
+const PREFIX: &str = "pulse-settings-";
+/// バックアップの時期が来たかを調べる間隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct BackupEntry {
+    pub file_name: String,
+    /// UNIX 時刻（秒）
+    pub created_at: u64,
+    pub size: u64,
+}
+
+/// 新しいものから順に並べます。
+fn list(dir: &Path) -> Vec<BackupEntry> {
+    let Ok(entries) = fs::read_dir(dir) else { return Vec::new() };
+    let mut backups: Vec<BackupEntry> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let created_at = file_name.strip_prefix(PREFIX)?.strip_suffix(".json")?.parse().ok()?;
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            Some(BackupEntry { file_name, created_at, size })
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+    backups
+}
+
+fn config(app: &AppHandle) -> Result<(BackupConfig, PathBuf), String> {
+    let settings = app.state::<Mutex<SettingsManager>>();
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    let config = settings.settings().backup.clone().ok_or(i18n::text(Text::BackupNotConfigured))?;
+    let path = settings.path().ok_or(i18n::text(Text::SettingsPathUnavailable))?.to_path_buf();
+    Ok((config, path))
+}
+
+/// 設定ファイルを写し、`keep` を超えた古いものを消します。前回のバックアップと中身が同じなら写しません。
+fn back_up(config: &BackupConfig, settings_path: &Path) -> Result<Option<BackupEntry>, String> {
+    let dir = Path::new(&config.dir);
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let contents = fs::read(settings_path).map_err(|e| e.to_string())?;
+    let backups = list(dir);
+    if backups.first().is_some_and(|latest| fs::read(dir.join(&latest.file_name)).is_ok_and(|c| c == contents)) {
+        return Ok(None);
+    }
+    let created_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let file_name = format!("{}{}.json", PREFIX, created_at);
+    fs::write(dir.join(&file_name), &contents).map_err(|e| e.to_string())?;
+    for old in list(dir).iter().skip(config.keep.max(1)) {
+        let _ = fs::remove_file(dir.join(&old.file_name));
+    }
+    Ok(Some(BackupEntry { file_name, created_at, size: contents.len() as u64 }))
+}
+
+/// 前回のバックアップから `interval_hours` がたっていれば写します。アプリを閉じている間に過ぎた分も、起動後の最初の確認で写します。
+pub fn spawn_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Ok((config, path)) = config(&app) {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+                let latest = list(Path::new(&config.dir)).first().map(|b| b.created_at).unwrap_or(0);
+                if now.saturating_sub(latest) >= config.interval_hours as u64 * 3600 {
+                    if let Err(e) = back_up(&config, &path) {
+                        eprintln!("Failed to back up the settings: {}", e);
+                    }
+                }
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_backup_config(settings: State<'_, Mutex<SettingsManager>>) -> Result<Option<BackupConfig>, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().backup.clone())
+}
+
+/// バックアップ先を選びます（`None` で止める）。フォルダがなければ作ります。
+#[tauri::command]
+#[specta::specta]
+pub fn set_backup_config(settings: State<'_, Mutex<SettingsManager>>, config: Option<BackupConfig>) -> Result<(), String> {
+    let config = config.map(|c| BackupConfig { interval_hours: c.interval_hours.max(1), keep: c.keep.max(1), ..c });
+    if let Some(config) = &config {
+        fs::create_dir_all(&config.dir).map_err(|e| e.to_string())?;
+    }
+    settings.lock().map_err(|_| i18n::text(Text::LockFailed))?.set_backup(config)
+}
+
+/// 今すぐ写します。前回と同じ中身なら何もせず `None` を返します。
+#[tauri::command]
+#[specta::specta]
+pub fn back_up_settings(app: AppHandle) -> Result<Option<BackupEntry>, String> {
+    let (config, path) = config(&app)?;
+    back_up(&config, &path)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_settings_backups(app: AppHandle) -> Result<Vec<BackupEntry>, String> {
+    let (config, _) = config(&app)?;
+    Ok(list(Path::new(&config.dir)))
+}
+
+/// バックアップ先のフォルダにあるバックアップで設定を置き換え、すぐに適用します。
+/// 戻す前の設定も、バックアップとして残します。
+#[tauri::command]
+#[specta::specta]
+pub async fn restore_settings_backup(app: AppHandle, file_name: String) -> Result<(), String> {
+    let (config, path) = config(&app)?;
+    // 一覧にあるファイルだけを受け付け、バックアップ先の外のファイルは読まない
+    if !list(Path::new(&config.dir)).iter().any(|b| b.file_name == file_name) {
+        return Err(i18n::format(Text::InvalidBackup, &file_name));
+    }
+    let contents = fs::read_to_string(Path::new(&config.dir).join(&file_name)).map_err(|e| e.to_string())?;
+    if !matches!(serde_json::from_str::<Value>(&contents), Ok(Value::Object(_))) {
+        return Err(i18n::format(Text::InvalidBackup, &file_name));
+    }
+    back_up(&config, &path)?;
+    fs::write(&path, contents).map_err(|e| e.to_string())?;
+    settings_watch::apply(&app).await;
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! スピーカーのエージング。ノイズを決まった時間鳴らす再生を 1 つだけ受け持ち、
+//! 進み具合を `burn-in-progress` イベントで知らせます。終わりの時刻が来ると信号源が止まるので、
+//! アプリを操作していなくても鳴り続けることはありません。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::audio::{self, AudioWorker};
+use crate::i18n::{self, Text};
+
+/// 進み具合を知らせる間隔
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+struct Job {
+    id: u64,
+    device_id: Option<String>,
+    burn_in: audio::BurnIn,
+    started: Instant,
+}
+
+/// 実行中のエージング。`app.manage` で登録します。
+#[derive(Debug, Default)]
+pub struct BurnInJobs {
+    current: Mutex<Option<Job>>,
+    next_id: AtomicU64,
+}
+
+#[derive(Debug, Clone, serde::Serialize, specta::Type)]
+pub struct BurnInProgress {
+    pub device_id: Option<String>,
+    pub elapsed_ms: u64,
+    pub duration_ms: u64,
+    /// いまの振幅
+    pub level: f32,
+    /// 時間が来たか、止められた
+    pub finished: bool,
+}
+
+fn progress(job: &Job, finished: bool) -> BurnInProgress {
+    let elapsed_ms = (job.started.elapsed().as_millis() as u64).min(job.burn_in.duration_ms);
+    BurnInProgress {
+        device_id: job.device_id.clone(),
+        elapsed_ms,
+        duration_ms: job.burn_in.duration_ms,
+        level: if finished { 0.0 } else { job.burn_in.level_at(elapsed_ms) },
+        finished,
+    }
+}
+
+/// エージングを終わったことにします。再生そのものは止めないので、ほかの再生に置き換えるときにも使えます。
+pub fn cancel(app: &AppHandle) {
+    let jobs = app.state::<BurnInJobs>();
+    let Ok(mut current) = jobs.current.lock() else { return };
+    if let Some(job) = current.take() {
+        let _ = app.emit("burn-in-progress", progress(&job, true));
+    }
+}
+
+/// 時間が来るか止められるまで、進み具合を知らせ続けます。
+fn spawn_progress(app: AppHandle, id: u64) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(PROGRESS_INTERVAL).await;
+            let jobs = app.state::<BurnInJobs>();
+            let Ok(mut current) = jobs.current.lock() else { return };
+            let Some(job) = current.as_ref().filter(|job| job.id == id) else { return };
+            let done = job.started.elapsed().as_millis() as u64 >= job.burn_in.duration_ms;
+            let _ = app.emit("burn-in-progress", progress(job, done));
+            if done {
+                *current = None;
+                return;
+            }
+        }
+    });
+}
+
+/// エージングを始めます。振幅は [`audio::MAX_BURN_IN_AMPLITUDE`] を超えられません。
+#[tauri::command]
+#[specta::specta]
+pub async fn start_burn_in(
+    app: AppHandle,
+    device_id: Option<String>,
+    burn_in: audio::BurnIn,
+    output: Option<audio::OutputFormat>,
+) -> Result<(), String> {
+    cancel(&app);
+    let request = audio::PlaybackRequest { device_id: device_id.clone(), signal: audio::Signal::BurnIn(burn_in), output: output.unwrap_or_default() };
+    app.state::<AudioWorker>().call(move |m| m.start_playback(request).map_err(i18n::audio_error)).await?;
+    let jobs = app.state::<BurnInJobs>();
+    let id = jobs.next_id.fetch_add(1, Ordering::Relaxed);
+    let job = Job { id, device_id, burn_in, started: Instant::now() };
+    let _ = app.emit("burn-in-progress", progress(&job, false));
+    *jobs.current.lock().map_err(|_| i18n::text(Text::LockFailed))? = Some(job);
+    spawn_progress(app.clone(), id);
+    Ok(())
+}
+
+/// 実行中のエージングの進み具合。動いていなければ `None`
+#[tauri::command]
+#[specta::specta]
+pub fn get_burn_in(jobs: State<'_, BurnInJobs>) -> Result<Option<BurnInProgress>, String> {
+    let current = jobs.current.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(current.as_ref().map(|job| progress(job, false)))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn stop_burn_in(app: AppHandle) -> Result<(), String> {
+    let running = app.state::<BurnInJobs>().current.lock().map_err(|_| i18n::text(Text::LockFailed))?.is_some();
+    if running {
+        cancel(&app);
+        app.state::<AudioWorker>().call(|m| {
+            m.stop_playback();
+            Ok(())
+        }).await?;
+    }
+    Ok(())
+}
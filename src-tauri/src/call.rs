@@ -0,0 +1,82 @@
+//! 通話に関わる音声をまとめて扱う通信パネル。通信用の既定の出力・マイクと、
+//! 通話アプリ（既知の VoIP アプリか、いまマイクを使っているアプリ）のセッションを組にします。
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::audio::{AudioDeviceInfo, AudioError, AudioSessionInfo, AudioWorker, DeviceRole};
+use crate::i18n;
+use crate::microphone::MicrophoneUsers;
+use crate::settings::normalize_exe_name;
+use crate::undo;
+
+/// 録音していなくても通話アプリとして扱う実行ファイル名（大文字）
+const VOIP_APPS: [&str; 10] = [
+    "MS-TEAMS.EXE",
+    "TEAMS.EXE",
+    "ZOOM.EXE",
+    "DISCORD.EXE",
+    "SLACK.EXE",
+    "SKYPE.EXE",
+    "CISCOCOLLABHOST.EXE",
+    "WEBEX.EXE",
+    "TELEGRAM.EXE",
+    "SIGNAL.EXE",
+];
+
+#[derive(Debug, Clone, serde::Serialize, specta::Type)]
+pub struct CallPanel {
+    /// 通信用の既定の出力デバイス
+    pub output: Option<AudioDeviceInfo>,
+    /// 通信用の既定のマイク
+    pub input: Option<AudioDeviceInfo>,
+    pub sessions: Vec<AudioSessionInfo>,
+    /// 通話アプリの音量の平均。通話アプリがなければ `None`
+    pub volume: Option<f32>,
+}
+
+async fn call_sessions(app: &AppHandle) -> Result<Vec<AudioSessionInfo>, String> {
+    let capturing: Vec<u32> = app.state::<MicrophoneUsers>().pids();
+    let worker = app.state::<AudioWorker>();
+    let sessions = worker.call(|m| m.get_sessions_lite().map_err(i18n::audio_error)).await?;
+    Ok(sessions
+        .into_iter()
+        .filter(|s| VOIP_APPS.contains(&normalize_exe_name(&s.exe_name).as_str()) || capturing.contains(&s.process_id))
+        .collect())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn get_call_panel(app: AppHandle, worker: State<'_, AudioWorker>) -> Result<CallPanel, String> {
+    let (endpoints, outputs, inputs) = worker.call(|m| {
+        let endpoints = m.get_default_endpoints(DeviceRole::Communications).map_err(i18n::audio_error)?;
+        let outputs = m.get_audio_devices().map_err(i18n::audio_error)?;
+        let inputs = m.get_capture_devices().unwrap_or_default();
+        Ok((endpoints, outputs, inputs))
+    }).await?;
+    let sessions = call_sessions(&app).await?;
+    let volume = (!sessions.is_empty()).then(|| sessions.iter().map(|s| s.volume).sum::<f32>() / sessions.len() as f32);
+    Ok(CallPanel {
+        output: outputs.into_iter().find(|d| Some(&d.id) == endpoints.output.as_ref()),
+        input: inputs.into_iter().find(|d| Some(&d.id) == endpoints.input.as_ref()),
+        sessions,
+        volume,
+    })
+}
+
+/// 通話アプリのセッションすべてと、通信用の既定の出力デバイス・マイクの音量をまとめて設定します。
+/// セッションは `set_session_volume` と同じく取り消せ、管理者として動いているアプリには昇格ヘルパーを使います。
+#[tauri::command]
+#[specta::specta]
+pub async fn set_call_volume(app: AppHandle, worker: State<'_, AudioWorker>, volume: f32) -> Result<(), String> {
+    let mut pids: Vec<u32> = call_sessions(&app).await?.into_iter().map(|s| s.process_id).collect();
+    pids.sort_unstable();
+    pids.dedup();
+    for pid in pids {
+        undo::change(&app, undo::Target::SessionVolume { pid, instance_id: None }, undo::Value::Volume(volume)).await?;
+    }
+    worker.call(move |m| match m.set_communications_volume(volume) {
+        // デバイスの音量を変えられないプラットフォームでは、セッションだけを変える
+        Err(AudioError::Unsupported(_)) => Ok(()),
+        result => result.map_err(i18n::audio_error),
+    }).await
+}
@@ -0,0 +1,125 @@
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fs;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::i18n::{self, Text};
+
+const CRASH_DIR: &str = "crashes";
+/// 次回起動時に案内するレポートのパスを書いておくファイル
+const PENDING_FILE: &str = "pending";
+/// レポートに含める直近のログの行数
+const LOG_CAPACITY: usize = 200;
+
+static RECENT_LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// クラッシュレポートに含める行を記録します。
+pub fn log_line(line: impl Into<String>) {
+    if let Ok(mut log) = RECENT_LOG.lock() {
+        if log.len() == LOG_CAPACITY {
+            log.pop_front();
+        }
+        log.push_back(format!("[{}] {}", timestamp(), line.into()));
+    }
+}
+
+fn timestamp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("{}.{:03}", now.as_secs(), now.subsec_millis())
+}
+
+#[derive(Debug)]
+pub struct CrashReports {
+    dir: PathBuf,
+}
+
+impl CrashReports {
+    /// アプリのデータディレクトリ配下にレポートを保存します。
+    pub fn new(app: &AppHandle) -> Self {
+        let dir = app.path().app_data_dir().unwrap_or_else(|_| std::env::temp_dir()).join(CRASH_DIR);
+        Self { dir }
+    }
+
+    /// パニック時にレポートを書き出すフックを登録します。既定のフック（標準エラーへの出力）も呼び出します。
+    pub fn install_panic_hook(&self) {
+        let dir = self.dir.clone();
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let backtrace = Backtrace::force_capture().to_string();
+            let _ = write_report(&dir, "panic", &info.to_string(), &backtrace);
+            default_hook(info);
+        }));
+    }
+
+    /// 前回の実行で書き出され、まだ案内していないレポート
+    pub fn pending(&self) -> Option<PathBuf> {
+        let path = PathBuf::from(fs::read_to_string(self.dir.join(PENDING_FILE)).ok()?.trim());
+        path.exists().then_some(path)
+    }
+
+    pub fn dismiss(&self) -> Result<(), String> {
+        match fs::remove_file(self.dir.join(PENDING_FILE)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn write_report(dir: &Path, kind: &str, message: &str, backtrace: &str) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("crash-{}.txt", timestamp().replace('.', "-")));
+    let thread = std::thread::current();
+    // パニックしたスレッドがログを書き込み中の場合もあるので、待たずに読む
+    let log: Vec<String> = RECENT_LOG.try_lock().map(|l| l.iter().cloned().collect()).unwrap_or_default();
+    let report = format!(
+        "Antigravity Pulse {} crash report\nkind: {}\nos: {} {}\nthread: {}\n\n{}\n\nbacktrace:\n{}\n\nrecent log:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        kind,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        thread.name().unwrap_or("<unnamed>"),
+        message,
+        backtrace,
+        log.join("\n"),
+    );
+    fs::write(&path, report)?;
+    fs::write(dir.join(PENDING_FILE), path.to_string_lossy().as_bytes())?;
+    Ok(path)
+}
+
+/// 前回の実行で書き出されたクラッシュレポートのパスを返します。
+#[tauri::command]
+#[specta::specta]
+pub fn get_pending_crash_report(reports: State<'_, CrashReports>) -> Option<String> {
+    reports.pending().map(|p| p.to_string_lossy().into_owned())
+}
+
+/// 案内中のクラッシュレポートを既定のアプリで開き、案内を終えます。
+#[tauri::command]
+#[specta::specta]
+pub fn open_crash_report(app: AppHandle, reports: State<'_, CrashReports>) -> Result<(), String> {
+    let path = reports.pending().ok_or(i18n::text(Text::NoCrashReport))?;
+    app.opener().open_path(path.to_string_lossy(), None::<&str>).map_err(|e| e.to_string())?;
+    reports.dismiss()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn dismiss_crash_report(reports: State<'_, CrashReports>) -> Result<(), String> {
+    reports.dismiss()
+}
+
+/// フロントエンドのエラーバウンダリで捕捉したエラーをレポートとして書き出します。
+#[tauri::command]
+#[specta::specta]
+pub fn report_frontend_crash(reports: State<'_, CrashReports>, message: String, stack: Option<String>) -> Result<String, String> {
+    let path = write_report(&reports.dir, "frontend", &message, stack.as_deref().unwrap_or("<none>"))
+        .map_err(|e| e.to_string())?;
+    Ok(path.to_string_lossy().into_owned())
+}
@@ -0,0 +1,70 @@
+//! 既定の出力デバイスの切り替え。前回大きな音量で使ったヘッドホンにいきなり大音量で切り替わらないよう、
+//! 新しいデバイスを小さい音量で始めて、約 1 秒かけて切り替える前のデバイスの音量まで上げます。
+
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::audio::AudioWorker;
+use crate::crash;
+use crate::i18n;
+
+/// 切り替えた直後の音量
+const START_VOLUME: f32 = 0.1;
+const RAMP_DURATION: Duration = Duration::from_secs(1);
+const RAMP_STEPS: u32 = 20;
+/// これ以上ずれていたら、途中でほかから音量が変えられたとみなして上げるのをやめる
+const TOLERANCE: f32 = 0.01;
+
+/// 既定の出力デバイスを切り替え、切り替える前のデバイスの音量まで徐々に上げます。
+/// デバイスの音量を扱えないバックエンドではそのまま切り替えます。
+pub async fn switch(app: &AppHandle, device_id: String) -> Result<(), String> {
+    let worker = app.state::<AudioWorker>();
+    let target = device_id.clone();
+    let ramp_to = worker.call(move |m| {
+        // 新しいデバイス自身が前回使っていた音量ではなく、いま聞いている音量に合わせる
+        let outgoing = m.get_master_volume().ok().filter(|v| v.device_id != target).map(|v| v.volume);
+        let own = m.get_device_volume(&target).ok().map(|v| v.volume);
+        let (Some(outgoing), Some(own)) = (outgoing, own) else {
+            return m.set_default_device(&target).map(|_| None).map_err(i18n::audio_error);
+        };
+        // 切り替えた瞬間に大きな音が出ないよう、切り替える前に下げておく
+        m.set_device_volume(&target, outgoing.min(START_VOLUME)).map_err(i18n::audio_error)?;
+        if let Err(e) = m.set_default_device(&target) {
+            let _ = m.set_device_volume(&target, own);
+            return Err(e.to_string());
+        }
+        Ok((outgoing > START_VOLUME).then_some(outgoing))
+    }).await?;
+    if let Some(volume) = ramp_to {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move { ramp(&app, device_id, volume).await });
+    }
+    Ok(())
+}
+
+async fn ramp(app: &AppHandle, device_id: String, target: f32) {
+    let worker = app.state::<AudioWorker>();
+    let mut current = START_VOLUME;
+    for step in 1..=RAMP_STEPS {
+        tokio::time::sleep(RAMP_DURATION / RAMP_STEPS).await;
+        let next = START_VOLUME + (target - START_VOLUME) * step as f32 / RAMP_STEPS as f32;
+        let id = device_id.clone();
+        let result = worker.call(move |m| {
+            let volume = m.get_device_volume(&id).map_err(i18n::audio_error)?.volume;
+            if (volume - current).abs() > TOLERANCE {
+                return Ok(false);
+            }
+            m.set_device_volume(&id, next).map_err(i18n::audio_error)?;
+            Ok(true)
+        }).await;
+        match result {
+            Ok(true) => current = next,
+            Ok(false) => break,
+            Err(e) => {
+                crash::log_line(format!("Failed to ramp the volume of {}: {}", device_id, e));
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! 音量を揃えておく出力デバイスの組（2 台のゾーンアンプなど）。組にしたデバイスの音量の変化を
+//! エンドポイントの通知で受け取り、どこから変えられても（音量キー、ほかのアプリ、このミキサー）もう一方を合わせます。
+//! 組が連なっていれば（A と B、B と C）、つながっているすべてのデバイスを揃えます。
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::broadcast;
+
+use crate::audio::{AudioWorker, ChangeOrigin, MasterVolume, MixerEvent};
+use crate::events::EventBus;
+use crate::i18n::{self, Text};
+use crate::settings::{DevicePair, SettingsManager};
+
+/// 浮動小数点の丸めで合わせ続けないための許容差
+const TOLERANCE: f32 = 0.005;
+/// 合わせるために変えたデバイスから返ってくる通知を、元の変更として扱わない時間
+const ECHO_WINDOW: Duration = Duration::from_millis(500);
+
+/// 合わせるために変えたデバイスと、その通知を無視する期限。`app.manage` で登録します。
+#[derive(Debug, Default)]
+pub struct PairSync(Mutex<HashMap<String, Instant>>);
+
+fn pairs(app: &AppHandle) -> Vec<DevicePair> {
+    app.state::<Mutex<SettingsManager>>().lock().map(|s| s.settings().device_pairs.clone()).unwrap_or_default()
+}
+
+/// `device_id` と組を通じてつながっている、ほかのすべてのデバイス
+fn linked_devices(pairs: &[DevicePair], device_id: &str) -> Vec<String> {
+    let mut linked = vec![device_id.to_string()];
+    let mut next = 0;
+    while next < linked.len() {
+        for pair in pairs {
+            for (a, b) in [(&pair.first, &pair.second), (&pair.second, &pair.first)] {
+                if *a == linked[next] && !linked.contains(b) {
+                    linked.push(b.clone());
+                }
+            }
+        }
+        next += 1;
+    }
+    linked.remove(0);
+    linked
+}
+
+/// 組に含まれるデバイスの音量の変化を通知するようバックエンドに頼みます。
+async fn watch(app: &AppHandle) -> Result<(), String> {
+    let mut device_ids: Vec<String> = pairs(app).into_iter().flat_map(|p| [p.first, p.second]).collect();
+    device_ids.sort();
+    device_ids.dedup();
+    app.state::<AudioWorker>().call(move |m| m.watch_device_volumes(device_ids).map_err(i18n::audio_error)).await
+}
+
+/// `source` とつながっているデバイスの音量とミュートを `source` に合わせます。
+async fn sync(app: &AppHandle, source: MasterVolume, origin: ChangeOrigin) {
+    let state = app.state::<PairSync>();
+    if origin == ChangeOrigin::Mixer {
+        // 合わせるために変えたデバイスからの通知。途中の状態（音量だけ変わった）で元のデバイスを戻さないよう無視する
+        let echo = state.0.lock().ok()
+            .and_then(|pending| pending.get(&source.device_id).copied())
+            .is_some_and(|until| Instant::now() < until);
+        if echo {
+            return;
+        }
+    }
+    let targets = linked_devices(&pairs(app), &source.device_id);
+    if targets.is_empty() {
+        return;
+    }
+    if let Ok(mut pending) = state.0.lock() {
+        let until = Instant::now() + ECHO_WINDOW;
+        pending.retain(|_, u| Instant::now() < *u);
+        for target in &targets {
+            pending.insert(target.clone(), until);
+        }
+    }
+    let device_id = source.device_id.clone();
+    let result = app.state::<AudioWorker>().call(move |m| {
+        for target in &targets {
+            // 外されているデバイスは飛ばす。つながったら通知が来た時点で揃う
+            let Ok(current) = m.get_device_volume(target) else { continue };
+            if (current.volume - source.volume).abs() > TOLERANCE {
+                m.set_device_volume(target, source.volume).map_err(i18n::audio_error)?;
+            }
+            if current.is_muted != source.is_muted {
+                m.set_device_mute(target, source.is_muted).map_err(i18n::audio_error)?;
+            }
+        }
+        Ok(())
+    }).await;
+    if let Err(e) = result {
+        eprintln!("Failed to sync the devices paired with {}: {}", device_id, e);
+    }
+}
+
+/// 起動時とバックエンドの作り直し後に監視を頼み直します。組がなければ、音量の通知を扱えないバックエンドでも
+/// エラーを出さずに済むよう何もしません。
+pub async fn rewatch(app: &AppHandle) {
+    if pairs(app).is_empty() {
+        return;
+    }
+    if let Err(e) = watch(app).await {
+        eprintln!("Failed to watch paired devices: {}", e);
+    }
+}
+
+/// 組にしたデバイスの音量の変化を見張り、つながっているデバイスを合わせます。
+pub fn spawn_sync(app: AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        rewatch(&app).await;
+        loop {
+            match rx.recv().await {
+                Ok(MixerEvent::DeviceVolumeChanged(volume, origin)) => sync(&app, volume, origin).await,
+                Ok(MixerEvent::BackendReconnected) => rewatch(&app).await,
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_device_pairs(settings: State<'_, Mutex<SettingsManager>>) -> Result<Vec<DevicePair>, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().device_pairs.clone())
+}
+
+/// 2 つのデバイスを組にし、`second` の音量とミュートを `first` に合わせます。
+#[tauri::command]
+#[specta::specta]
+pub async fn add_device_pair(app: AppHandle, first: String, second: String) -> Result<(), String> {
+    if first == second {
+        return Err(i18n::text(Text::DevicePairSameDevice).to_string());
+    }
+    let pair = DevicePair { first: first.clone(), second };
+    app.state::<Mutex<SettingsManager>>().lock().map_err(|_| i18n::text(Text::LockFailed))?.add_device_pair(pair)?;
+    watch(&app).await?;
+    let source = app.state::<AudioWorker>().call(move |m| m.get_device_volume(&first).map_err(i18n::audio_error)).await?;
+    sync(&app, source, ChangeOrigin::Unknown).await;
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn remove_device_pair(app: AppHandle, first: String, second: String) -> Result<(), String> {
+    let pair = DevicePair { first, second };
+    app.state::<Mutex<SettingsManager>>().lock().map_err(|_| i18n::text(Text::LockFailed))?.remove_device_pair(&pair)?;
+    watch(&app).await
+}
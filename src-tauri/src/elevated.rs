@@ -0,0 +1,428 @@
+//! 管理者として動いているアプリのセッションを扱うための昇格ヘルパー。
+//! 必要になったときだけ同じ実行ファイルを `--elevated-helper` 付きで管理者として起動し、名前付きパイプで要求を送ります。
+//! パイプは起動したユーザーだけが開けるようにし、両端で相手のプロセス ID を確かめます。
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::broadcast;
+
+use crate::audio::{AudioWorker, MixerEvent};
+use crate::events::EventBus;
+use crate::i18n::{self, Text};
+
+/// ヘルパーとして起動されたことを示す引数。続けてパイプ名と起動元の PID を渡します。
+const HELPER_ARG: &str = "--elevated-helper";
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Request {
+    /// 音声セッションを持つプロセスの実行ファイルのパス
+    ProcessPaths,
+    SetVolume { pid: u32, volume: f32 },
+    SetMute { pid: u32, muted: bool },
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+enum Response {
+    ProcessPaths(HashMap<u32, String>),
+    Done,
+    Error(String),
+}
+
+/// 1 行に 1 つの JSON で要求を送り、応答を受け取ります。
+struct HelperClient {
+    reader: BufReader<File>,
+    writer: File,
+}
+
+impl HelperClient {
+    fn new(pipe: File) -> Result<Self, String> {
+        let writer = pipe.try_clone().map_err(|e| e.to_string())?;
+        Ok(Self { reader: BufReader::new(pipe), writer })
+    }
+
+    fn request(&mut self, request: &Request) -> Result<Response, String> {
+        let mut line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+        line.clear();
+        if self.reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            return Err(i18n::text(Text::ElevatedHelperStopped).to_string());
+        }
+        serde_json::from_str(&line).map_err(|e| e.to_string())
+    }
+}
+
+/// 起動中のヘルパーへの接続。`app.manage` で登録します。
+#[derive(Default)]
+pub struct ElevatedHelper(Mutex<Option<HelperClient>>);
+
+impl ElevatedHelper {
+    fn is_running(&self) -> bool {
+        self.0.lock().is_ok_and(|client| client.is_some())
+    }
+
+    /// ヘルパーが動いていなければ `None`。パイプが切れていたら接続を捨てます。
+    fn request(&self, request: &Request) -> Option<Result<Response, String>> {
+        let mut client = self.0.lock().ok()?;
+        let result = client.as_mut()?.request(request);
+        if result.is_err() {
+            *client = None;
+        }
+        Some(result)
+    }
+}
+
+async fn send(app: &AppHandle, request: Request) -> Option<Result<Response, String>> {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || app.state::<ElevatedHelper>().request(&request))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// ヘルパーが見ている実行ファイルのパスをバックエンドに渡し、名前とアイコンを解決させます。
+async fn share_process_paths(app: &AppHandle) {
+    if let Some(Ok(Response::ProcessPaths(paths))) = send(app, Request::ProcessPaths).await {
+        let worker = app.state::<AudioWorker>();
+        let _ = worker.call(move |m| { m.set_process_paths(paths); Ok(()) }).await;
+    }
+}
+
+/// 自分で操作できなかったセッションの音量をヘルパー経由で設定します。ヘルパーがなければ `None`。
+pub async fn set_session_volume(app: &AppHandle, pid: u32, volume: f32) -> Option<Result<(), String>> {
+    Some(match send(app, Request::SetVolume { pid, volume }).await? {
+        Ok(Response::Error(e)) => Err(e),
+        result => result.map(|_| ()),
+    })
+}
+
+/// `set_session_volume` のミュート版です。
+pub async fn set_session_mute(app: &AppHandle, pid: u32, muted: bool) -> Option<Result<(), String>> {
+    Some(match send(app, Request::SetMute { pid, muted }).await? {
+        Ok(Response::Error(e)) => Err(e),
+        result => result.map(|_| ()),
+    })
+}
+
+/// ヘルパーの動作中は、新しいセッションやバックエンドの作り直しのたびにパスを渡し直します。
+pub fn spawn_path_sharer(app: AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(MixerEvent::SessionsChanged(diff)) if !diff.added.is_empty() => {}
+                Ok(MixerEvent::BackendReconnected) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+            if app.state::<ElevatedHelper>().is_running() {
+                share_process_paths(&app).await;
+            }
+        }
+    });
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn is_elevated_helper_running(helper: State<'_, ElevatedHelper>) -> bool {
+    helper.is_running()
+}
+
+/// ヘルパーを管理者として起動します。UAC の確認が出ます。すでに動いていれば何もしません。
+#[tauri::command]
+#[specta::specta]
+pub async fn start_elevated_helper(app: AppHandle) -> Result<(), String> {
+    if app.state::<ElevatedHelper>().is_running() {
+        return Ok(());
+    }
+    let client = tauri::async_runtime::spawn_blocking(launch).await.map_err(|e| e.to_string())??;
+    {
+        let helper = app.state::<ElevatedHelper>();
+        let mut current = helper.0.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+        *current = Some(client);
+    }
+    share_process_paths(&app).await;
+    let _ = app.emit("elevated-helper-changed", true);
+    Ok(())
+}
+
+/// パイプを閉じてヘルパーを終了させます。
+#[tauri::command]
+#[specta::specta]
+pub fn stop_elevated_helper(app: AppHandle, helper: State<'_, ElevatedHelper>) -> Result<(), String> {
+    let mut client = helper.0.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    if client.take().is_some() {
+        let _ = app.emit("elevated-helper-changed", false);
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn launch() -> Result<HelperClient, String> {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::os::windows::io::FromRawHandle;
+    use std::time::{Duration, Instant};
+
+    use windows::core::{w, HSTRING, PCWSTR};
+    use windows::Win32::Foundation::{CloseHandle, WAIT_OBJECT_0};
+    use windows::Win32::Storage::FileSystem::{
+        CreateFileW, FILE_GENERIC_READ, FILE_GENERIC_WRITE, FILE_SHARE_NONE, OPEN_EXISTING, SECURITY_IDENTIFICATION,
+        SECURITY_SQOS_PRESENT,
+    };
+    use windows::Win32::System::Pipes::GetNamedPipeServerProcessId;
+    use windows::Win32::System::Threading::{GetProcessId, WaitForSingleObject};
+    use windows::Win32::UI::Shell::{ShellExecuteExW, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW};
+    use windows::Win32::UI::WindowsAndMessaging::SW_HIDE;
+
+    /// UAC の確認を待つ時間も含めた、パイプが開けるようになるまでの上限
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let nonce = RandomState::new().build_hasher().finish();
+    let pipe_name = format!(r"\\.\pipe\antigravity-pulse-helper-{}-{:016x}", std::process::id(), nonce);
+    let file = HSTRING::from(exe.as_os_str());
+    let parameters = HSTRING::from(format!("{} {} {}", HELPER_ARG, pipe_name, std::process::id()));
+    let mut info = SHELLEXECUTEINFOW {
+        cbSize: std::mem::size_of::<SHELLEXECUTEINFOW>() as u32,
+        fMask: SEE_MASK_NOCLOSEPROCESS,
+        lpVerb: w!("runas"),
+        lpFile: PCWSTR(file.as_ptr()),
+        lpParameters: PCWSTR(parameters.as_ptr()),
+        nShow: SW_HIDE.0,
+        ..Default::default()
+    };
+    // UAC で断られると ERROR_CANCELLED で失敗する
+    unsafe { ShellExecuteExW(&mut info) }.map_err(|e| e.message())?;
+    let helper_pid = unsafe { GetProcessId(info.hProcess) };
+
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+    let pipe = loop {
+        // サーバーがクライアントの権限で動けないよう、識別レベルに制限して開く
+        let opened = unsafe {
+            CreateFileW(
+                &HSTRING::from(pipe_name.as_str()),
+                (FILE_GENERIC_READ | FILE_GENERIC_WRITE).0,
+                FILE_SHARE_NONE,
+                None,
+                OPEN_EXISTING,
+                SECURITY_SQOS_PRESENT | SECURITY_IDENTIFICATION,
+                None,
+            )
+        };
+        match opened {
+            Ok(pipe) => break Ok(pipe),
+            Err(e) if Instant::now() >= deadline || unsafe { WaitForSingleObject(info.hProcess, 0) } == WAIT_OBJECT_0 => {
+                break Err(e.message());
+            }
+            Err(_) => std::thread::sleep(Duration::from_millis(100)),
+        }
+    };
+    unsafe { let _ = CloseHandle(info.hProcess); }
+    let pipe = pipe?;
+    let file = unsafe { File::from_raw_handle(pipe.0) };
+
+    // 同じ名前のパイプを先に作った別のプロセスにつながっていないか確かめる
+    let mut server_pid = 0;
+    unsafe { GetNamedPipeServerProcessId(pipe, &mut server_pid) }.map_err(|e| e.message())?;
+    if server_pid != helper_pid {
+        return Err(i18n::text(Text::ElevatedHelperMismatch).to_string());
+    }
+    HelperClient::new(file)
+}
+
+#[cfg(not(windows))]
+fn launch() -> Result<HelperClient, String> {
+    Err(i18n::text(Text::ElevatedHelperUnsupported).to_string())
+}
+
+/// ヘルパーとして起動されていれば要求を処理して `true` を返します。通常の起動なら何もせず `false` です。
+pub fn run_helper_if_requested() -> bool {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(position) = args.iter().position(|a| a == HELPER_ARG) else { return false };
+    let (Some(pipe_name), Some(parent_pid)) = (args.get(position + 1), args.get(position + 2).and_then(|p| p.parse().ok())) else {
+        return true;
+    };
+    if let Err(e) = serve(pipe_name, parent_pid) {
+        eprintln!("Elevated helper stopped: {}", e);
+    }
+    true
+}
+
+#[cfg(windows)]
+fn serve(pipe_name: &str, parent_pid: u32) -> Result<(), String> {
+    use std::os::windows::io::FromRawHandle;
+
+    use windows::core::{HSTRING, PWSTR};
+    use windows::Win32::Foundation::{CloseHandle, LocalFree, ERROR_PIPE_CONNECTED, HANDLE, HLOCAL};
+    use windows::Win32::Security::Authorization::{
+        ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    };
+    use windows::Win32::Security::{GetTokenInformation, TokenUser, PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES, TOKEN_QUERY, TOKEN_USER};
+    use windows::Win32::Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX};
+    use windows::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, GetNamedPipeClientProcessId, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_TYPE_BYTE, PIPE_WAIT,
+    };
+    use windows::Win32::System::Threading::{
+        OpenProcess, OpenProcessToken, WaitForSingleObject, INFINITE, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_SYNCHRONIZE,
+    };
+
+    const BUFFER_SIZE: u32 = 4096;
+
+    let parent = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_SYNCHRONIZE, false, parent_pid) }
+        .map_err(|e| e.message())?;
+
+    // パイプを開けるのは起動元のユーザーだけにする（別の管理者アカウントで昇格した場合も起動元に合わせる）
+    let user_sid = unsafe {
+        let mut token = HANDLE::default();
+        OpenProcessToken(parent, TOKEN_QUERY, &mut token).map_err(|e| e.message())?;
+        // TOKEN_USER はポインタを含むので、その境界に揃ったバッファで受け取る
+        let mut buffer = vec![0u64; 32];
+        let mut len = 0;
+        let size = std::mem::size_of_val(buffer.as_slice()) as u32;
+        let result = GetTokenInformation(token, TokenUser, Some(buffer.as_mut_ptr() as _), size, &mut len);
+        let _ = CloseHandle(token);
+        result.map_err(|e| e.message())?;
+        let user = &*(buffer.as_ptr() as *const TOKEN_USER);
+        let mut sid = PWSTR::null();
+        ConvertSidToStringSidW(user.User.Sid, &mut sid).map_err(|e| e.message())?;
+        let text = sid.to_string().unwrap_or_default();
+        let _ = LocalFree(HLOCAL(sid.0 as _));
+        text
+    };
+    let mut descriptor = PSECURITY_DESCRIPTOR::default();
+    unsafe {
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            &HSTRING::from(format!("D:P(A;;GA;;;{})", user_sid)),
+            SDDL_REVISION_1,
+            &mut descriptor,
+            None,
+        )
+    }
+    .map_err(|e| e.message())?;
+    let attributes = SECURITY_ATTRIBUTES {
+        nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+        lpSecurityDescriptor: descriptor.0,
+        bInheritHandle: false.into(),
+    };
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            &HSTRING::from(pipe_name),
+            PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            1,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            Some(&attributes),
+        )
+    };
+    unsafe { let _ = LocalFree(HLOCAL(descriptor.0)); }
+    if pipe.is_invalid() {
+        return Err(windows::core::Error::from_win32().message());
+    }
+    let pipe_file = unsafe { File::from_raw_handle(pipe.0) };
+
+    // 起動元が終了したら、接続を待っている途中でもヘルパーを終える
+    let parent_handle = parent.0 as isize;
+    std::thread::spawn(move || {
+        unsafe { WaitForSingleObject(HANDLE(parent_handle as _), INFINITE) };
+        std::process::exit(0);
+    });
+
+    if let Err(e) = unsafe { ConnectNamedPipe(pipe, None) } {
+        if e.code() != ERROR_PIPE_CONNECTED.to_hresult() {
+            return Err(e.message());
+        }
+    }
+    let mut client_pid = 0;
+    unsafe { GetNamedPipeClientProcessId(pipe, &mut client_pid) }.map_err(|e| e.message())?;
+    if client_pid != parent_pid {
+        return Err(i18n::text(Text::ElevatedHelperMismatch).to_string());
+    }
+    handle_requests(pipe_file)
+}
+
+#[cfg(not(windows))]
+fn serve(_pipe_name: &str, _parent_pid: u32) -> Result<(), String> {
+    Err(i18n::text(Text::ElevatedHelperUnsupported).to_string())
+}
+
+/// 実行ファイルのフルパス。管理者として動いていれば、ほかの管理者のプロセスも開けます。
+#[cfg(windows)]
+pub fn process_path(pid: u32) -> Option<String> {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::{CloseHandle, MAX_PATH};
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; MAX_PATH as usize * 2];
+        let mut len = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut len);
+        let _ = CloseHandle(handle);
+        result.ok()?;
+        String::from_utf16(&buffer[..len as usize]).ok()
+    }
+}
+
+#[cfg(not(windows))]
+pub fn process_path(_pid: u32) -> Option<String> {
+    None
+}
+
+/// パイプが閉じられるまで要求を 1 つずつ処理します。操作はヘルパー自身のバックエンドで行います。
+#[cfg_attr(not(windows), allow(dead_code))]
+fn handle_requests(pipe: File) -> Result<(), String> {
+    let worker = AudioWorker::spawn(|_event: MixerEvent| {}, |_backend: &mut dyn crate::audio::AudioBackend| {});
+    let mut writer = pipe.try_clone().map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(pipe);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            break;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::ProcessPaths) => match worker.call_blocking(|m| m.get_sessions_lite().map_err(i18n::audio_error)) {
+                #[cfg(windows)]
+                Ok(sessions) => Response::ProcessPaths(
+                    sessions.iter()
+                        .filter(|s| s.process_id != 0)
+                        .filter_map(|s| Some((s.process_id, process_path(s.process_id)?)))
+                        .collect(),
+                ),
+                #[cfg(not(windows))]
+                Ok(_) => Response::ProcessPaths(HashMap::new()),
+                Err(e) => Response::Error(e),
+            },
+            Ok(Request::SetVolume { pid, volume }) => {
+                match worker.call_blocking(move |m| m.set_session_volume(pid, volume).map_err(i18n::audio_error)) {
+                    Ok(()) => Response::Done,
+                    Err(e) => Response::Error(e),
+                }
+            }
+            Ok(Request::SetMute { pid, muted }) => {
+                match worker.call_blocking(move |m| m.set_session_mute(pid, muted).map_err(i18n::audio_error)) {
+                    Ok(()) => Response::Done,
+                    Err(e) => Response::Error(e),
+                }
+            }
+            Err(e) => Response::Error(e.to_string()),
+        };
+        let mut reply = serde_json::to_string(&response).map_err(|e| e.to_string())?;
+        reply.push('\n');
+        writer.write_all(reply.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    worker.shutdown();
+    Ok(())
+}
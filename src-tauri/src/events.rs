@@ -0,0 +1,459 @@
+//! オーディオスレッド・COM コールバックから届くミキサーのイベントを、ディスパッチャーのタスクで配送する内部イベントバス。
+//! webview は `subscribe_events` で PID やイベントの種類を絞り込めます。絞り込んでいない webview にはすべて送ります。
+//! 外部の連携（Stream Deck プラグイン、CLI など）は `get_api_version` で形式の版を確かめ、`set_event_schema` で
+//! 版付きの形式を選べます。選ばなければ、これまでどおりペイロードだけの旧形式で送ります。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+use tauri_specta::Event;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::audio::{ChangeOrigin, InputPeak, MasterVolume, MixerEvent, MixerEventSink, PeakLevel, SessionsDiff};
+use crate::crash;
+use crate::i18n::{self, Text};
+
+/// webview へピーク値を送る最短間隔
+const PEAK_MIN_INTERVAL: Duration = Duration::from_millis(16);
+
+/// 購読者ごとに保持するイベント数。遅れた購読者は古いイベントを取りこぼします
+const SUBSCRIBER_CAPACITY: usize = 256;
+
+/// イベントとコマンドの形式の版。互換性のない変更をしたら上げ、古い版の形式も送れるようにしておく
+pub const API_VERSION: u32 = 2;
+/// 版を持たない旧形式。イベントのペイロードをそのまま送る
+const LEGACY_API_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct ApiVersion {
+    pub version: u32,
+    /// `set_event_schema` で選べる最も古い版
+    pub min_supported: u32,
+    pub app_version: &'static str,
+}
+
+/// イベントの配送先を管理します。`app.manage` で登録し、webview 以外の購読者は `subscribe` で受け取ります。
+pub struct EventBus {
+    tx: mpsc::UnboundedSender<MixerEvent>,
+    subscribers: broadcast::Sender<MixerEvent>,
+    /// webview のラベルごとの絞り込み
+    filters: Arc<Mutex<HashMap<String, EventFilter>>>,
+}
+
+/// webview へ送るイベントの種類。送出するイベント名と 1 対 1 に対応します。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, specta::Type)]
+pub enum EventCategory {
+    /// `volume-change`
+    Volume,
+    /// `session-state-change`
+    SessionState,
+    /// `sessions-changed`
+    Sessions,
+    /// `session-icon-ready`
+    SessionIcon,
+    /// `refresh-trigger`
+    Devices,
+    /// `master-volume-change`
+    MasterVolume,
+    /// `device-volume-change`
+    DeviceVolume,
+    /// `audio-pulse`
+    Peaks,
+    /// `input-pulse`
+    InputPeak,
+    /// `backend-reconnected`
+    Backend,
+    /// `playback-stopped`, `playback-progress`
+    Playback,
+    /// `mirror-stopped`
+    Mirror,
+    /// `boost-stopped`
+    Boost,
+    /// `file-capture-stopped`
+    FileCapture,
+    /// `network-stream-stopped`
+    NetworkStream,
+    /// `process-started`, `process-exited`
+    Process,
+}
+
+impl EventCategory {
+    const ALL: [EventCategory; 16] = [
+        Self::Volume, Self::SessionState, Self::Sessions, Self::SessionIcon, Self::Devices, Self::MasterVolume, Self::DeviceVolume,
+        Self::Peaks, Self::InputPeak, Self::Backend, Self::Playback, Self::Mirror, Self::Boost, Self::FileCapture, Self::NetworkStream,
+        Self::Process,
+    ];
+
+    fn of(event: &MixerEvent) -> Self {
+        match event {
+            MixerEvent::VolumeChanged { .. } => Self::Volume,
+            MixerEvent::SessionStateChanged { .. } => Self::SessionState,
+            MixerEvent::SessionsChanged(_) => Self::Sessions,
+            MixerEvent::SessionIconReady { .. } => Self::SessionIcon,
+            MixerEvent::DevicesChanged => Self::Devices,
+            MixerEvent::MasterVolumeChanged(..) => Self::MasterVolume,
+            MixerEvent::DeviceVolumeChanged(..) => Self::DeviceVolume,
+            MixerEvent::Peaks(_) => Self::Peaks,
+            MixerEvent::InputPeak(_) => Self::InputPeak,
+            MixerEvent::BackendReconnected => Self::Backend,
+            MixerEvent::PlaybackStopped { .. } | MixerEvent::PlaybackProgress { .. } => Self::Playback,
+            MixerEvent::MirrorStopped { .. } => Self::Mirror,
+            MixerEvent::BoostStopped { .. } => Self::Boost,
+            MixerEvent::FileCaptureStopped { .. } => Self::FileCapture,
+            MixerEvent::NetworkStreamStopped { .. } => Self::NetworkStream,
+            MixerEvent::ProcessStarted { .. } | MixerEvent::ProcessExited { .. } => Self::Process,
+        }
+    }
+}
+
+/// webview ごとの購読内容。`None` はすべてを表します。
+#[derive(Debug, Clone, Default, Serialize, specta::Type)]
+pub struct EventFilter {
+    pub categories: Option<HashSet<EventCategory>>,
+    /// PID を含むイベント（音量、状態、ピーク値、セッションの差分、ミラー、プロセスの起動と終了）だけに効きます
+    pub pids: Option<HashSet<u32>>,
+    /// 送る形式の版。`None` なら旧形式
+    pub schema_version: Option<u32>,
+}
+
+impl EventFilter {
+    /// 購読していないイベントなら `None`。ピーク値とセッションの差分は、購読している PID の分だけに絞ります。
+    fn apply(&self, event: &MixerEvent) -> Option<MixerEvent> {
+        if self.categories.as_ref().is_some_and(|c| !c.contains(&EventCategory::of(event))) {
+            return None;
+        }
+        let Some(pids) = &self.pids else { return Some(event.clone()) };
+        match event {
+            MixerEvent::VolumeChanged { pid, .. } | MixerEvent::SessionStateChanged { pid, .. } | MixerEvent::MirrorStopped { pid, .. } | MixerEvent::BoostStopped { pid, .. }
+            | MixerEvent::SessionIconReady { pid, .. } | MixerEvent::ProcessStarted { pid, .. } | MixerEvent::ProcessExited { pid, .. } => {
+                pids.contains(pid).then(|| event.clone())
+            }
+            MixerEvent::Peaks(peaks) => {
+                let peaks: Vec<_> = peaks.iter().filter(|p| pids.contains(&p.pid)).cloned().collect();
+                (!peaks.is_empty()).then_some(MixerEvent::Peaks(peaks))
+            }
+            MixerEvent::SessionsChanged(diff) => {
+                let mut diff = diff.clone();
+                diff.added.retain(|s| pids.contains(&s.process_id));
+                diff.updated.retain(|s| pids.contains(&s.process_id));
+                diff.removed.retain(|s| pids.contains(&s.process_id));
+                let empty = diff.added.is_empty() && diff.updated.is_empty() && diff.removed.is_empty();
+                (!empty).then_some(MixerEvent::SessionsChanged(diff))
+            }
+            _ => Some(event.clone()),
+        }
+    }
+}
+
+impl EventBus {
+    /// ディスパッチャーのタスクを起動します。
+    pub fn new(app_handle: AppHandle) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (subscribers, _) = broadcast::channel(SUBSCRIBER_CAPACITY);
+        let filters = Arc::new(Mutex::new(HashMap::new()));
+        tauri::async_runtime::spawn(dispatch(app_handle, rx, subscribers.clone(), filters.clone()));
+        Self { tx, subscribers, filters }
+    }
+
+    /// ワーカーに渡すシンク。送信はキューに積むだけなので、COM のコールバックを待たせません。
+    pub fn sink(&self) -> impl MixerEventSink {
+        let tx = self.tx.clone();
+        move |event: MixerEvent| {
+            let _ = tx.send(event);
+        }
+    }
+
+    /// webview 以外（HTTP、MIDI フィードバック、ログなど）の購読者を追加します。
+    pub fn subscribe(&self) -> broadcast::Receiver<MixerEvent> {
+        self.subscribers.subscribe()
+    }
+}
+
+async fn dispatch(
+    app_handle: AppHandle,
+    mut rx: mpsc::UnboundedReceiver<MixerEvent>,
+    subscribers: broadcast::Sender<MixerEvent>,
+    filters: Arc<Mutex<HashMap<String, EventFilter>>>,
+) {
+    let mut last_peaks: Option<Instant> = None;
+    while let Some(event) = rx.recv().await {
+        if let MixerEvent::Peaks(_) = &event {
+            if last_peaks.is_some_and(|t| t.elapsed() < PEAK_MIN_INTERVAL) {
+                continue;
+            }
+            last_peaks = Some(Instant::now());
+        }
+        // 購読者がいなければ送信は失敗するが、問題ない
+        let _ = subscribers.send(event.clone());
+        let filters = filters.lock().map(|f| f.clone()).unwrap_or_default();
+        if filters.is_empty() {
+            emit_to_webview(&app_handle, None, LEGACY_API_VERSION, event);
+            continue;
+        }
+        for label in app_handle.webview_windows().into_keys() {
+            let (event, version) = match filters.get(&label) {
+                Some(filter) => (filter.apply(&event), filter.schema_version.unwrap_or(LEGACY_API_VERSION)),
+                None => (Some(event.clone()), LEGACY_API_VERSION),
+            };
+            if let Some(event) = event {
+                emit_to_webview(&app_handle, Some(&label), version, event);
+            }
+        }
+    }
+}
+
+/// `label` を省略するとすべての webview に送ります。旧形式より新しい版では `{ schema_version, data }` で包みます。
+fn emit<E: Event + Serialize>(app_handle: &AppHandle, label: Option<&str>, version: u32, payload: E) -> tauri::Result<()> {
+    let payload = if version > LEGACY_API_VERSION {
+        serde_json::json!({ "schema_version": version, "data": payload })
+    } else {
+        serde_json::to_value(payload)?
+    };
+    match label {
+        Some(label) => app_handle.emit_to(label, E::NAME, payload),
+        None => app_handle.emit(E::NAME, payload),
+    }
+}
+
+// webview へ送るイベント。イベント名は型名をケバブケースにしたもの（`VolumeChange` なら `volume-change`）
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct VolumeChange {
+    pub pid: u32,
+    pub volume: f32,
+    pub muted: bool,
+    pub origin: ChangeOrigin,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct SessionStateChange {
+    pub pid: u32,
+    pub state: String,
+}
+
+/// デバイスの一覧が変わった。ペイロードはない
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct RefreshTrigger;
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct AudioPulse(pub Vec<PeakLevel>);
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct InputPulse(pub InputPeak);
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct SessionsChanged(pub SessionsDiff);
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct SessionIconReady {
+    pub pid: u32,
+    pub icon_base64: String,
+    pub accent_color: Option<String>,
+}
+
+/// オーディオサービスにつなぎ直した。ペイロードはない
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct BackendReconnected;
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct MasterVolumeChange(pub MasterVolume);
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct DeviceVolumeChange(pub MasterVolume);
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct PlaybackStopped {
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct PlaybackProgress {
+    pub position_ms: u64,
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct MirrorStopped {
+    pub pid: u32,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct BoostStopped {
+    pub pid: u32,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct FileCaptureStopped {
+    pub files: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct NetworkStreamStopped {
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct ProcessStarted {
+    pub pid: u32,
+    pub exe_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type, Event)]
+pub struct ProcessExited {
+    pub pid: u32,
+    pub exe_name: String,
+}
+
+/// `emit_to_webview` が送るイベントの一覧。フロントエンドの型はここから生成します。
+pub fn webview_events() -> tauri_specta::Events {
+    tauri_specta::collect_events![
+        VolumeChange,
+        SessionStateChange,
+        RefreshTrigger,
+        AudioPulse,
+        InputPulse,
+        SessionsChanged,
+        SessionIconReady,
+        BackendReconnected,
+        MasterVolumeChange,
+        DeviceVolumeChange,
+        PlaybackStopped,
+        PlaybackProgress,
+        MirrorStopped,
+        BoostStopped,
+        FileCaptureStopped,
+        NetworkStreamStopped,
+        ProcessStarted,
+        ProcessExited,
+    ]
+}
+
+/// ミキサーのイベントを従来どおりの名前で Tauri イベントとして送出します。
+fn emit_to_webview(app_handle: &AppHandle, label: Option<&str>, version: u32, event: MixerEvent) {
+    let _ = match event {
+        MixerEvent::VolumeChanged { pid, volume, muted, origin } => emit(app_handle, label, version, VolumeChange { pid, volume, muted, origin }),
+        MixerEvent::SessionStateChanged { pid, state } => emit(app_handle, label, version, SessionStateChange { pid, state }),
+        MixerEvent::DevicesChanged => emit(app_handle, label, version, RefreshTrigger),
+        MixerEvent::Peaks(peaks) => emit(app_handle, label, version, AudioPulse(peaks)),
+        MixerEvent::InputPeak(peak) => emit(app_handle, label, version, InputPulse(peak)),
+        MixerEvent::SessionsChanged(diff) => emit(app_handle, label, version, SessionsChanged(diff)),
+        MixerEvent::SessionIconReady { pid, icon_base64, accent_color } => {
+            emit(app_handle, label, version, SessionIconReady { pid, icon_base64, accent_color })
+        }
+        MixerEvent::BackendReconnected => emit(app_handle, label, version, BackendReconnected),
+        MixerEvent::MasterVolumeChanged(master, _) => emit(app_handle, label, version, MasterVolumeChange(master)),
+        MixerEvent::DeviceVolumeChanged(volume, _) => emit(app_handle, label, version, DeviceVolumeChange(volume)),
+        MixerEvent::PlaybackStopped { error } => emit(app_handle, label, version, PlaybackStopped { error }),
+        MixerEvent::PlaybackProgress { position_ms, duration_ms } => {
+            emit(app_handle, label, version, PlaybackProgress { position_ms, duration_ms })
+        }
+        MixerEvent::MirrorStopped { pid, error } => emit(app_handle, label, version, MirrorStopped { pid, error }),
+        MixerEvent::BoostStopped { pid, error } => emit(app_handle, label, version, BoostStopped { pid, error }),
+        MixerEvent::FileCaptureStopped { files, error } => emit(app_handle, label, version, FileCaptureStopped { files, error }),
+        MixerEvent::NetworkStreamStopped { error } => emit(app_handle, label, version, NetworkStreamStopped { error }),
+        MixerEvent::ProcessStarted { pid, exe_name } => emit(app_handle, label, version, ProcessStarted { pid, exe_name }),
+        MixerEvent::ProcessExited { pid, exe_name } => emit(app_handle, label, version, ProcessExited { pid, exe_name }),
+    };
+}
+
+/// ピーク値以外のイベントをクラッシュレポート用のログに記録します。
+/// 環境変数 `PULSE_LOG_EVENTS` が設定されていれば、標準エラーにも出力します。
+pub fn spawn_logger(bus: &EventBus) {
+    let echo = std::env::var("PULSE_LOG_EVENTS").is_ok();
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let line = match rx.recv().await {
+                Ok(MixerEvent::Peaks(_) | MixerEvent::InputPeak(_)) => continue,
+                // アイコンを含むため、件数だけを残す
+                Ok(MixerEvent::SessionsChanged(diff)) => format!(
+                    "[event] SessionsChanged added={} updated={} removed={}",
+                    diff.added.len(), diff.updated.len(), diff.removed.len()
+                ),
+                Ok(MixerEvent::SessionIconReady { pid, .. }) => format!("[event] SessionIconReady pid={}", pid),
+                Ok(event) => format!("[event] {:?}", event),
+                Err(broadcast::error::RecvError::Lagged(n)) => format!("[event] {} events dropped", n),
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if echo {
+                eprintln!("{}", line);
+            }
+            crash::log_line(line);
+        }
+    });
+}
+
+/// 呼び出した webview が受け取るイベントに `categories` と `pids` を加えます。初めて絞り込むと、
+/// 加えたもの以外は届かなくなります。省略した方はこれまでどおりです。
+#[tauri::command]
+#[specta::specta]
+pub fn subscribe_events(window: WebviewWindow, bus: State<'_, EventBus>, categories: Option<Vec<EventCategory>>, pids: Option<Vec<u32>>) -> Result<EventFilter, String> {
+    let mut filters = bus.filters.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    let filter = filters.entry(window.label().to_string()).or_default();
+    if let Some(categories) = categories {
+        filter.categories.get_or_insert_with(HashSet::new).extend(categories);
+    }
+    if let Some(pids) = pids {
+        filter.pids.get_or_insert_with(HashSet::new).extend(pids);
+    }
+    Ok(filter.clone())
+}
+
+/// 呼び出した webview の購読から `categories` と `pids` を外します。どちらも省略すると絞り込みをやめ、すべてを受け取ります。
+#[tauri::command]
+#[specta::specta]
+pub fn unsubscribe_events(window: WebviewWindow, bus: State<'_, EventBus>, categories: Option<Vec<EventCategory>>, pids: Option<Vec<u32>>) -> Result<EventFilter, String> {
+    let mut filters = bus.filters.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    let filter = filters.entry(window.label().to_string()).or_default();
+    if categories.is_none() && pids.is_none() {
+        filter.categories = None;
+        filter.pids = None;
+        return Ok(filter.clone());
+    }
+    if let Some(categories) = categories {
+        // すべてを受け取っている状態から外すときは、外したもの以外を購読している状態にする
+        let current = filter.categories.get_or_insert_with(|| EventCategory::ALL.into_iter().collect());
+        for category in categories {
+            current.remove(&category);
+        }
+    }
+    // PID はすべてを列挙できないので、絞り込んでいなければそのまま
+    if let (Some(pids), Some(current)) = (pids, filter.pids.as_mut()) {
+        for pid in pids {
+            current.remove(&pid);
+        }
+    }
+    Ok(filter.clone())
+}
+
+/// 呼び出した webview の購読内容。`None` はすべてを受け取っていることを表します。
+#[tauri::command]
+#[specta::specta]
+pub fn get_event_subscriptions(window: WebviewWindow, bus: State<'_, EventBus>) -> Result<EventFilter, String> {
+    let filters = bus.filters.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(filters.get(window.label()).cloned().unwrap_or_default())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_api_version() -> ApiVersion {
+    ApiVersion { version: API_VERSION, min_supported: LEGACY_API_VERSION, app_version: env!("CARGO_PKG_VERSION") }
+}
+
+/// 呼び出した webview に送るイベントの形式の版を選びます。連携側が扱える最も新しい版を渡してください。
+#[tauri::command]
+#[specta::specta]
+pub fn set_event_schema(window: WebviewWindow, bus: State<'_, EventBus>, version: u32) -> Result<ApiVersion, String> {
+    if !(LEGACY_API_VERSION..=API_VERSION).contains(&version) {
+        return Err(i18n::format(Text::ApiVersionUnsupported, &version.to_string()));
+    }
+    let mut filters = bus.filters.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    filters.entry(window.label().to_string()).or_default().schema_version = Some(version);
+    Ok(get_api_version())
+}
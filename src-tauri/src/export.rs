@@ -0,0 +1,132 @@
+//! ミキサーの状態（アプリ、音量、出力先、デバイス）のエクスポート。設定の記録や問い合わせの添付用に、
+//! CSV か JSON でダウンロードフォルダへ書き出します。
+
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::audio::{AudioDeviceInfo, AudioSessionInfo, AudioWorker, MasterVolume};
+use crate::i18n::{self, Text};
+
+#[derive(Debug, Clone, Copy, serde::Deserialize, specta::Type)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct MixerSnapshot {
+    /// UNIX 時刻（秒）
+    exported_at: u64,
+    version: &'static str,
+    master: Option<MasterVolume>,
+    devices: Vec<AudioDeviceInfo>,
+    capture_devices: Vec<AudioDeviceInfo>,
+    sessions: Vec<AudioSessionInfo>,
+}
+
+impl MixerSnapshot {
+    fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    /// 表計算ソフトで開けるよう、セッションとデバイスを `kind` 列で区別した 1 つの表にします。
+    fn to_csv(&self) -> String {
+        let mut rows = vec![csv_row(&["kind", "id", "name", "exe_name", "pid", "volume", "muted", "state", "device_id", "route", "default"])];
+        if let Some(master) = &self.master {
+            rows.push(csv_row(&["master", &master.device_id, "", "", "", &master.volume.to_string(), &master.is_muted.to_string(), "", &master.device_id, "", ""]));
+        }
+        for (kind, devices) in [("output", &self.devices), ("input", &self.capture_devices)] {
+            for device in devices {
+                rows.push(csv_row(&[kind, &device.id, &device.name, "", "", "", "", "", "", "", &device.is_default.to_string()]));
+            }
+        }
+        for session in &self.sessions {
+            rows.push(csv_row(&[
+                "session",
+                &session.instance_id,
+                &session.process_name,
+                &session.exe_name,
+                &session.process_id.to_string(),
+                &session.volume.to_string(),
+                &session.is_muted.to_string(),
+                &format!("{:?}", session.state),
+                &session.device_id,
+                session.persisted_device_id.as_deref().unwrap_or(""),
+                "",
+            ]));
+        }
+        rows.concat()
+    }
+}
+
+/// RFC 4180 の形式で 1 行にします。区切りや引用符、改行を含む値は引用符で囲む
+fn csv_row(fields: &[&str]) -> String {
+    let fields: Vec<String> = fields.iter()
+        .map(|f| if f.contains([',', '"', '\n', '\r']) { format!("\"{}\"", f.replace('"', "\"\"")) } else { f.to_string() })
+        .collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+async fn snapshot(worker: &AudioWorker) -> Result<MixerSnapshot, String> {
+    worker.call(|m| {
+        let mut sessions = m.get_sessions_lite().map_err(i18n::audio_error)?;
+        for session in &mut sessions {
+            session.icon_base64 = None;
+        }
+        Ok(MixerSnapshot {
+            exported_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            version: env!("CARGO_PKG_VERSION"),
+            master: m.get_master_volume().ok(),
+            devices: m.get_audio_devices().map_err(i18n::audio_error)?,
+            capture_devices: m.get_capture_devices().unwrap_or_default(),
+            sessions,
+        })
+    }).await
+}
+
+/// いまの状態をダウンロードフォルダ（なければドキュメントフォルダ）に書き出し、書き出したファイルのパスを返します。
+/// `file_name` はフォルダの外を指せないファイル名だけを受け付け、省略すると時刻から付けます。
+/// 同じ名前のファイルがあれば、`overwrite` のときだけ上書きします。
+#[tauri::command]
+#[specta::specta]
+pub async fn export_mixer_state(
+    app: AppHandle,
+    worker: State<'_, AudioWorker>,
+    format: ExportFormat,
+    file_name: Option<String>,
+    overwrite: bool,
+) -> Result<String, String> {
+    if let Some(name) = file_name.as_deref().filter(|name| !is_plain_file_name(name)) {
+        return Err(i18n::format(Text::ExportInvalidFileName, name));
+    }
+    let snapshot = snapshot(&worker).await?;
+    let (contents, extension) = match format {
+        ExportFormat::Csv => (snapshot.to_csv(), "csv"),
+        ExportFormat::Json => (snapshot.to_json()?, "json"),
+    };
+    let dir = app.path().download_dir().or_else(|_| app.path().document_dir()).map_err(|e| e.to_string())?;
+    let path = dir.join(file_name.unwrap_or_else(|| format!("pulse-mixer-{}.{}", snapshot.exported_at, extension)));
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        // 確かめてから書くあいだに作られたファイルも上書きしない
+        options.create_new(true);
+    }
+    let mut file = options.open(&path).map_err(|e| match e.kind() {
+        ErrorKind::AlreadyExists => i18n::format(Text::ExportFileExists, &path.to_string_lossy()),
+        _ => e.to_string(),
+    })?;
+    file.write_all(contents.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// ディレクトリを含まない、ふつうのファイル名か
+fn is_plain_file_name(name: &str) -> bool {
+    Path::new(name).file_name().is_some_and(|file_name| file_name == name) && !name.contains(['/', '\\', ':'])
+}
@@ -0,0 +1,115 @@
+//! 音量・ミュートの変更履歴。誰が（このミキサーか、ほかのプロセスか）何を変えたかを新しい順に一定件数だけ残し、
+//! 「勝手に音量が変わる」ときの原因探しに使えるようにします。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::broadcast;
+
+use crate::audio::{AudioWorker, ChangeOrigin, MixerEvent};
+use crate::events::EventBus;
+use crate::i18n::{self, Text};
+
+/// 残す件数。これを超えたら古いものから捨てる
+const CAPACITY: usize = 500;
+/// 同じ対象・同じ変更元の続けざまの変更（スライダーのドラッグなど）を 1 件にまとめる時間幅
+const MERGE_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, specta::Type)]
+#[serde(tag = "kind")]
+pub enum HistoryTarget {
+    Session { pid: u32, exe_name: String },
+    Master { device_id: String },
+}
+
+#[derive(Debug, Clone, serde::Serialize, specta::Type)]
+pub struct HistoryEntry {
+    /// UNIX 時刻（ミリ秒）
+    pub time: u64,
+    pub target: HistoryTarget,
+    pub volume: f32,
+    pub muted: bool,
+    pub origin: ChangeOrigin,
+}
+
+/// 変更履歴のリングバッファ。`app.manage` で登録します。
+#[derive(Default)]
+pub struct VolumeHistory(Mutex<VecDeque<HistoryEntry>>);
+
+impl VolumeHistory {
+    /// 追加した（またはまとめた）項目を返します。
+    fn push(&self, entry: HistoryEntry) -> HistoryEntry {
+        let mut entries = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(last) = entries.back_mut() {
+            let recent = entry.time.saturating_sub(last.time) < MERGE_WINDOW.as_millis() as u64;
+            if recent && last.target == entry.target && last.origin == entry.origin && last.muted == entry.muted {
+                *last = entry;
+                return last.clone();
+            }
+        }
+        if entries.len() == CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry.clone());
+        entry
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// 変更をバスから受け取って履歴に加え、"volume-history" で UI にも送ります。
+pub fn spawn_recorder(app: AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        let worker = app.state::<AudioWorker>();
+        let sessions = worker.call(|m| m.get_sessions_lite().map_err(i18n::audio_error)).await.unwrap_or_default();
+        let mut exe_names: HashMap<u32, String> = sessions.into_iter().map(|s| (s.process_id, s.exe_name)).collect();
+        loop {
+            let entry = match rx.recv().await {
+                Ok(MixerEvent::SessionsChanged(diff)) => {
+                    for session in diff.added.into_iter().chain(diff.updated) {
+                        exe_names.insert(session.process_id, session.exe_name);
+                    }
+                    continue;
+                }
+                Ok(MixerEvent::VolumeChanged { pid, volume, muted, origin }) => HistoryEntry {
+                    time: now_millis(),
+                    target: HistoryTarget::Session { pid, exe_name: exe_names.get(&pid).cloned().unwrap_or_default() },
+                    volume,
+                    muted,
+                    origin,
+                },
+                Ok(MixerEvent::MasterVolumeChanged(master, origin)) => HistoryEntry {
+                    time: now_millis(),
+                    target: HistoryTarget::Master { device_id: master.device_id },
+                    volume: master.volume,
+                    muted: master.is_muted,
+                    origin,
+                },
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let entry = app.state::<VolumeHistory>().push(entry);
+            let _ = app.emit("volume-history", entry);
+        }
+    });
+}
+
+/// 新しい順に最大 `limit` 件（省略時はすべて）
+#[tauri::command]
+#[specta::specta]
+pub fn get_volume_history(history: State<'_, VolumeHistory>, limit: Option<usize>) -> Result<Vec<HistoryEntry>, String> {
+    let entries = history.0.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(entries.iter().rev().take(limit.unwrap_or(usize::MAX)).cloned().collect())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn clear_volume_history(history: State<'_, VolumeHistory>) -> Result<(), String> {
+    history.0.lock().map_err(|_| i18n::text(Text::LockFailed))?.clear();
+    Ok(())
+}
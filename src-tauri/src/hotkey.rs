@@ -0,0 +1,446 @@
+//! グローバルショートカット。ミキサー（フライアウト）を開くショートカットは設定で変更できます。
+//! マスター音量の上げ下げとミュートは固定のショートカットで、変更すると HUD を表示します。
+//! ほかに、修飾キーの組み合わせや 2 回押しに、アプリ・デバイス・プロファイルへの操作を割り当てられます。
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Manager, State, Wry};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::audio::AudioWorker;
+use crate::i18n::{self, Text};
+use crate::settings::{HotkeyAction, HotkeyBinding, HotkeyTarget, Settings, SettingsManager};
+use crate::{default_device, hud, profiles, tray};
+
+pub const DEFAULT_FLYOUT_SHORTCUT: &str = "Ctrl+Shift+V";
+
+/// この時間内にもう一度押したら 2 回押しとみなす
+const DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(400);
+
+#[derive(Debug, Clone, Copy)]
+pub enum VolumeAction {
+    Up,
+    Down,
+    ToggleMute,
+}
+
+const VOLUME_SHORTCUTS: [(&str, VolumeAction); 3] = [
+    ("Ctrl+Shift+ArrowUp", VolumeAction::Up),
+    ("Ctrl+Shift+ArrowDown", VolumeAction::Down),
+    ("Ctrl+Shift+KeyM", VolumeAction::ToggleMute),
+];
+
+/// Windows が使っている主なショートカット。割り当てても Windows の機能が優先されるか、Windows の機能が使えなくなる
+const WINDOWS_SHORTCUTS: &[&str] = &[
+    "Super+KeyA", "Super+KeyD", "Super+KeyE", "Super+KeyG", "Super+KeyI", "Super+KeyK", "Super+KeyL", "Super+KeyM",
+    "Super+KeyN", "Super+KeyP", "Super+KeyR", "Super+KeyS", "Super+KeyV", "Super+KeyX", "Super+Tab", "Super+Period",
+    "Super+Comma", "Super+ArrowUp", "Super+ArrowDown", "Super+ArrowLeft", "Super+ArrowRight", "Super+Shift+KeyS",
+    "Super+Shift+KeyM", "Super+Ctrl+KeyD", "Super+Ctrl+ArrowLeft", "Super+Ctrl+ArrowRight", "Super+Ctrl+F4",
+    "Super+Alt+KeyR", "Super+Alt+PrintScreen", "Super+PrintScreen", "Alt+Tab", "Alt+F4", "Alt+Escape", "Alt+Space",
+    "Ctrl+Escape", "Ctrl+Shift+Escape", "Ctrl+Alt+Delete", "Ctrl+Alt+Tab", "PrintScreen", "Alt+PrintScreen",
+];
+
+/// 登録中のショートカット。`app.manage` で登録します。
+#[derive(Default)]
+pub struct Hotkeys {
+    flyout: Mutex<Option<Shortcut>>,
+    volume: Mutex<Vec<(Shortcut, VolumeAction)>>,
+    bindings: Mutex<Vec<(Shortcut, HotkeyBinding)>>,
+    /// ショートカットごとの 1 回目を押した時刻と世代。2 回目を待っている 1 回押しの割り当ては、世代が変わったら行わない
+    taps: Mutex<HashMap<u32, (Instant, u64)>>,
+    generation: AtomicU64,
+}
+
+/// ショートカットが重なっているもの
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, specta::Type)]
+#[serde(tag = "kind", content = "value")]
+pub enum HotkeyConflict {
+    /// Windows のショートカット。割り当てられても Windows の機能と重なる
+    Windows(&'static str),
+    /// ミキサーを開くショートカット
+    Flyout,
+    /// マスター音量の固定のショートカット
+    MasterVolume,
+    /// 割り当て済みのショートカット（`hotkeys` の番号）
+    Binding(usize),
+    /// ほかのアプリが登録している
+    OtherApp,
+}
+
+pub fn plugin() -> TauriPlugin<Wry> {
+    tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(|app, shortcut, event| {
+            if event.state() != ShortcutState::Pressed {
+                return;
+            }
+            let hotkeys = app.state::<Hotkeys>();
+            let flyout = *hotkeys.flyout.lock().unwrap();
+            if flyout.as_ref() == Some(shortcut) {
+                tray::toggle_flyout(app);
+                return;
+            }
+            let action = hotkeys.volume.lock().unwrap().iter().find(|(s, _)| s == shortcut).map(|(_, a)| *a);
+            if let Some(action) = action {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    let _ = step_master(&app, action).await;
+                });
+                return;
+            }
+            press_binding(app, shortcut);
+        })
+        .build()
+}
+
+/// 割り当てたショートカットが押されたときの処理。2 回押しの割り当てがあれば、1 回押しの割り当ては 2 回目が来ないと分かってから行います。
+fn press_binding(app: &AppHandle, shortcut: &Shortcut) {
+    let hotkeys = app.state::<Hotkeys>();
+    let (single, double) = {
+        let bindings = hotkeys.bindings.lock().unwrap();
+        let find = |double_tap: bool| bindings.iter().find(|(s, b)| s == shortcut && b.double_tap == double_tap).map(|(_, b)| b.clone());
+        (find(false), find(true))
+    };
+    if double.is_none() {
+        if let Some(single) = single {
+            spawn_run(app, single);
+        }
+        return;
+    }
+    let id = shortcut.id();
+    let now = Instant::now();
+    let mut taps = hotkeys.taps.lock().unwrap();
+    match taps.get(&id) {
+        Some(&(at, _)) if now.duration_since(at) <= DOUBLE_TAP_WINDOW => {
+            taps.remove(&id);
+            spawn_run(app, double.unwrap());
+        }
+        _ => {
+            let generation = hotkeys.generation.fetch_add(1, Ordering::SeqCst) + 1;
+            taps.insert(id, (now, generation));
+            let Some(single) = single else { return };
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(DOUBLE_TAP_WINDOW).await;
+                let hotkeys = app.state::<Hotkeys>();
+                let pending = {
+                    let mut taps = hotkeys.taps.lock().unwrap();
+                    let pending = taps.get(&id).is_some_and(|&(_, g)| g == generation);
+                    if pending {
+                        taps.remove(&id);
+                    }
+                    pending
+                };
+                if pending {
+                    if let Err(e) = run(&app, single).await {
+                        eprintln!("Failed to run a shortcut: {}", e);
+                    }
+                }
+            });
+        }
+    }
+}
+
+fn spawn_run(app: &AppHandle, binding: HotkeyBinding) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = run(&app, binding).await {
+            eprintln!("Failed to run a shortcut: {}", e);
+        }
+    });
+}
+
+fn volume_action(action: HotkeyAction) -> Option<VolumeAction> {
+    match action {
+        HotkeyAction::VolumeUp => Some(VolumeAction::Up),
+        HotkeyAction::VolumeDown => Some(VolumeAction::Down),
+        HotkeyAction::ToggleMute => Some(VolumeAction::ToggleMute),
+        HotkeyAction::Activate => None,
+    }
+}
+
+async fn run(app: &AppHandle, binding: HotkeyBinding) -> Result<(), String> {
+    let Some(action) = volume_action(binding.action) else {
+        return match binding.target {
+            HotkeyTarget::Device(device_id) => default_device::switch(app, device_id).await,
+            HotkeyTarget::Profile(name) => profiles::apply(app, &name).await,
+            _ => Ok(()),
+        };
+    };
+    let handled = match binding.target {
+        HotkeyTarget::Master | HotkeyTarget::Profile(_) => false,
+        HotkeyTarget::FocusedApp => match foreground_process() {
+            Some((pid, exe_name)) => step_app(app, Some(pid), exe_name, action).await?,
+            None => false,
+        },
+        HotkeyTarget::App(exe_name) => step_app(app, None, exe_name, action).await?,
+        HotkeyTarget::Device(device_id) => {
+            step_device(app, device_id, action).await?;
+            true
+        }
+    };
+    if handled { Ok(()) } else { step_master(app, action).await }
+}
+
+/// 前面のウィンドウのプロセスと実行ファイル名
+#[cfg(windows)]
+pub fn foreground_process() -> Option<(u32, String)> {
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    use crate::elevated;
+
+    let mut pid = 0;
+    unsafe { GetWindowThreadProcessId(GetForegroundWindow(), Some(&mut pid)) };
+    let path = elevated::process_path(pid)?;
+    let exe_name = std::path::Path::new(&path).file_name()?.to_string_lossy().into_owned();
+    Some((pid, exe_name))
+}
+
+#[cfg(not(windows))]
+pub fn foreground_process() -> Option<(u32, String)> {
+    None
+}
+
+/// `pid` のプロセスか、同じ実行ファイルのプロセスのセッションの音量を変えます。ブラウザーのように
+/// ウィンドウと音を出すプロセスが別のアプリもあるため、実行ファイル名でも探します。セッションがなければ `false` を返します。
+pub async fn step_app(app: &AppHandle, pid: Option<u32>, exe_name: String, action: VolumeAction) -> Result<bool, String> {
+    let stepping = hud::volume_stepping(app);
+    app.state::<AudioWorker>().call(move |m| {
+        let sessions = m.get_sessions_lite().map_err(i18n::audio_error)?;
+        let by_pid: Vec<_> = sessions.iter().filter(|s| Some(s.process_id) == pid).collect();
+        let targets = if by_pid.is_empty() {
+            sessions.iter().filter(|s| s.exe_name.eq_ignore_ascii_case(&exe_name)).collect()
+        } else {
+            by_pid
+        };
+        let Some(first) = targets.first() else { return Ok(false) };
+        let mut pids: Vec<u32> = targets.iter().map(|s| s.process_id).collect();
+        pids.sort_unstable();
+        pids.dedup();
+        match action {
+            VolumeAction::Up | VolumeAction::Down => {
+                let volume = hud::next_volume(stepping, first.volume, matches!(action, VolumeAction::Up));
+                for pid in pids {
+                    m.set_session_volume(pid, volume).map_err(i18n::audio_error)?;
+                }
+            }
+            VolumeAction::ToggleMute => {
+                let muted = targets.iter().any(|s| !s.is_muted);
+                for pid in pids {
+                    m.set_session_mute(pid, muted).map_err(i18n::audio_error)?;
+                }
+            }
+        }
+        Ok(true)
+    }).await
+}
+
+pub async fn step_master(app: &AppHandle, action: VolumeAction) -> Result<(), String> {
+    match action {
+        VolumeAction::Up => hud::step_master_volume(app, true).await,
+        VolumeAction::Down => hud::step_master_volume(app, false).await,
+        VolumeAction::ToggleMute => hud::toggle_master_mute(app).await,
+    }
+}
+
+/// 出力デバイスの音量を変え、HUD に表示します。
+async fn step_device(app: &AppHandle, device_id: String, action: VolumeAction) -> Result<(), String> {
+    let stepping = hud::volume_stepping(app);
+    let volume = app.state::<AudioWorker>().call(move |m| {
+        let current = m.get_device_volume(&device_id).map_err(i18n::audio_error)?;
+        match action {
+            VolumeAction::Up => m.set_device_volume(&device_id, hud::next_volume(stepping, current.volume, true)),
+            VolumeAction::Down => m.set_device_volume(&device_id, hud::next_volume(stepping, current.volume, false)),
+            VolumeAction::ToggleMute => m.set_device_mute(&device_id, !current.is_muted),
+        }.map_err(i18n::audio_error)?;
+        m.get_device_volume(&device_id).map_err(i18n::audio_error)
+    }).await?;
+    hud::show(app, &volume);
+    Ok(())
+}
+
+/// フライアウトを開くショートカットを登録し直します。空文字列なら何も登録しません。
+/// 他のアプリが使っているなどで登録できない場合は、元のショートカットのままにします。
+pub fn set_flyout(app: &AppHandle, shortcut: &str) -> Result<(), String> {
+    let next = match shortcut.trim() {
+        "" => None,
+        s => Some(Shortcut::from_str(s).map_err(|e| e.to_string())?),
+    };
+    let hotkeys = app.state::<Hotkeys>();
+    // 登録はメインスレッドで行われ、ハンドラーもロックを取るので、ロックを持ったまま登録しない
+    let current = *hotkeys.flyout.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    if current == next {
+        return Ok(());
+    }
+    let global_shortcut = app.global_shortcut();
+    if let Some(next) = next {
+        global_shortcut.register(next).map_err(|_| i18n::format(Text::ShortcutUnavailable, shortcut.trim()))?;
+    }
+    if let Some(current) = current {
+        let _ = global_shortcut.unregister(current);
+    }
+    *hotkeys.flyout.lock().map_err(|_| i18n::text(Text::LockFailed))? = next;
+    Ok(())
+}
+
+/// マスター音量のショートカットを登録します。登録できなかったものは飛ばします。
+pub fn register_volume_shortcuts(app: &AppHandle) {
+    let global_shortcut = app.global_shortcut();
+    let registered: Vec<(Shortcut, VolumeAction)> = VOLUME_SHORTCUTS.iter()
+        .filter_map(|(s, action)| {
+            let shortcut = Shortcut::from_str(s).ok()?;
+            match global_shortcut.register(shortcut) {
+                Ok(()) => Some((shortcut, *action)),
+                Err(e) => {
+                    eprintln!("Failed to register shortcut {}: {}", s, e);
+                    None
+                }
+            }
+        })
+        .collect();
+    *app.state::<Hotkeys>().volume.lock().unwrap() = registered;
+}
+
+/// 割り当てたショートカットを登録し直します。登録できなかったものは飛ばします。
+pub fn set_bindings(app: &AppHandle, bindings: &[HotkeyBinding]) {
+    let hotkeys = app.state::<Hotkeys>();
+    let current: Vec<Shortcut> = hotkeys.bindings.lock().unwrap().iter().map(|(s, _)| *s).collect();
+    let global_shortcut = app.global_shortcut();
+    let mut next: Vec<(Shortcut, HotkeyBinding)> = Vec::new();
+    for binding in bindings {
+        let shortcut = match Shortcut::from_str(&binding.shortcut) {
+            Ok(shortcut) => shortcut,
+            Err(e) => {
+                eprintln!("Failed to parse shortcut {}: {}", binding.shortcut, e);
+                continue;
+            }
+        };
+        // 1 回押しと 2 回押しで同じショートカットを使うことがあるので、登録は 1 度だけ
+        let registered = current.contains(&shortcut) || next.iter().any(|(s, _)| *s == shortcut);
+        if !registered {
+            if let Err(e) = global_shortcut.register(shortcut) {
+                eprintln!("Failed to register shortcut {}: {}", binding.shortcut, e);
+                continue;
+            }
+        }
+        next.push((shortcut, binding.clone()));
+    }
+    for shortcut in current {
+        if !next.iter().any(|(s, _)| *s == shortcut) {
+            let _ = global_shortcut.unregister(shortcut);
+        }
+    }
+    *hotkeys.bindings.lock().unwrap() = next;
+}
+
+/// 操作と対象の組み合わせを確かめます。デバイスとプロファイル以外は `Activate` できず、プロファイルは `Activate` しかできません。
+fn validate(settings: &Settings, binding: &HotkeyBinding) -> Result<(), String> {
+    Shortcut::from_str(binding.shortcut.trim()).map_err(|e| e.to_string())?;
+    match (&binding.target, binding.action) {
+        (HotkeyTarget::Profile(name), HotkeyAction::Activate) => {
+            if settings.profiles.iter().any(|p| &p.name == name) { Ok(()) } else { Err(i18n::format(Text::ProfileNotFound, name)) }
+        }
+        (HotkeyTarget::Device(_), _) => Ok(()),
+        (HotkeyTarget::Profile(_), _) | (_, HotkeyAction::Activate) => Err(i18n::text(Text::HotkeyInvalidAction).to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn conflicts(app: &AppHandle, shortcut: &str, double_tap: bool) -> Result<Vec<HotkeyConflict>, String> {
+    let target = Shortcut::from_str(shortcut.trim()).map_err(|e| e.to_string())?;
+    let mut found = Vec::new();
+    if cfg!(windows) {
+        found.extend(WINDOWS_SHORTCUTS.iter()
+            .filter(|s| Shortcut::from_str(s).is_ok_and(|s| s == target))
+            .map(|s| HotkeyConflict::Windows(s)));
+    }
+    let hotkeys = app.state::<Hotkeys>();
+    if *hotkeys.flyout.lock().map_err(|_| i18n::text(Text::LockFailed))? == Some(target) {
+        found.push(HotkeyConflict::Flyout);
+    }
+    if hotkeys.volume.lock().map_err(|_| i18n::text(Text::LockFailed))?.iter().any(|(s, _)| *s == target) {
+        found.push(HotkeyConflict::MasterVolume);
+    }
+    let bindings = app.state::<Mutex<SettingsManager>>().lock().map_err(|_| i18n::text(Text::LockFailed))?.settings().hotkeys.clone();
+    // 1 回押しと 2 回押しは同じショートカットで使い分けられる
+    found.extend(bindings.iter().enumerate()
+        .filter(|(_, b)| b.double_tap == double_tap && Shortcut::from_str(&b.shortcut).is_ok_and(|s| s == target))
+        .map(|(i, _)| HotkeyConflict::Binding(i)));
+    // ほかのアプリが使っているかは、登録してみないと分からない
+    let global_shortcut = app.global_shortcut();
+    if !global_shortcut.is_registered(target) {
+        match global_shortcut.register(target) {
+            Ok(()) => {
+                let _ = global_shortcut.unregister(target);
+            }
+            Err(_) => found.push(HotkeyConflict::OtherApp),
+        }
+    }
+    Ok(found)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_flyout_shortcut(settings: State<'_, Mutex<SettingsManager>>) -> Result<String, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().flyout_shortcut().to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_flyout_shortcut(app: AppHandle, settings: State<'_, Mutex<SettingsManager>>, shortcut: String) -> Result<(), String> {
+    set_flyout(&app, &shortcut)?;
+    settings.lock().map_err(|_| i18n::text(Text::LockFailed))?.set_flyout_shortcut(shortcut.trim())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_hotkeys(settings: State<'_, Mutex<SettingsManager>>) -> Result<Vec<HotkeyBinding>, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().hotkeys.clone())
+}
+
+/// ショートカットと重なっているもの。Windows のショートカットと重なっていても割り当てはできます。
+#[tauri::command]
+#[specta::specta]
+pub fn check_hotkey_conflicts(app: AppHandle, shortcut: String, double_tap: bool) -> Result<Vec<HotkeyConflict>, String> {
+    conflicts(&app, &shortcut, double_tap)
+}
+
+/// ショートカットを割り当てます。ミキサーのショートカットや割り当て済みのもの、ほかのアプリが使っているものとは重ねられません。
+#[tauri::command]
+#[specta::specta]
+pub fn add_hotkey(app: AppHandle, settings: State<'_, Mutex<SettingsManager>>, binding: HotkeyBinding) -> Result<(), String> {
+    validate(settings.lock().map_err(|_| i18n::text(Text::LockFailed))?.settings(), &binding)?;
+    for conflict in conflicts(&app, &binding.shortcut, binding.double_tap)? {
+        match conflict {
+            HotkeyConflict::Windows(_) => {}
+            HotkeyConflict::OtherApp => return Err(i18n::format(Text::ShortcutUnavailable, binding.shortcut.trim())),
+            _ => return Err(i18n::format(Text::HotkeyConflict, binding.shortcut.trim())),
+        }
+    }
+    let bindings = {
+        let mut settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+        settings.add_hotkey(binding)?;
+        settings.settings().hotkeys.clone()
+    };
+    set_bindings(&app, &bindings);
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn remove_hotkey(app: AppHandle, settings: State<'_, Mutex<SettingsManager>>, index: usize) -> Result<(), String> {
+    let bindings = {
+        let mut settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+        settings.remove_hotkey(index)?;
+        settings.settings().hotkeys.clone()
+    };
+    set_bindings(&app, &bindings);
+    Ok(())
+}
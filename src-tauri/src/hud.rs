@@ -0,0 +1,204 @@
+//! 音量を変えたときに画面下部に出す小さなオーバーレイ (HUD)。Windows の音量 OSD の代わりに使います。
+//! 表示内容は `hud` ウィンドウの webview が "hud-update" イベントから描画します。
+//! [`HudMode::ReplaceNative`] では Windows の音量フライアウトを隠し、代わりに外部からの変更も HUD で表示します。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, State};
+use tokio::sync::broadcast;
+
+use crate::audio::{AudioWorker, MasterVolume, MixerEvent};
+use crate::events::EventBus;
+use crate::i18n::{self, Text};
+use crate::settings::{HudMode, SettingsManager, VolumeStepping};
+
+const HUD_LABEL: &str = "hud";
+
+/// 最後の変更から HUD を隠すまでの時間
+const HUD_DURATION: Duration = Duration::from_millis(1500);
+
+/// `VolumeStepping::snap` で止める音量
+const SNAP_POINTS: [f32; 4] = [0.25, 0.5, 0.75, 1.0];
+
+/// 止めた点から次の 1 段で動けるよう、これより近い点では止めない（音量の読み取り値には誤差がある）
+const SNAP_TOLERANCE: f32 = 0.001;
+
+/// 表示のたびに増やし、隠すタイマーが古いものなら何もしないようにします。`app.manage` で登録します。
+#[derive(Default)]
+pub struct Hud {
+    generation: AtomicU64,
+}
+
+fn mode(app: &AppHandle) -> HudMode {
+    let settings = app.state::<Mutex<SettingsManager>>();
+    let mode = settings.lock().map(|s| s.settings().hud_mode);
+    mode.unwrap_or_default()
+}
+
+/// HUD を表示し、しばらく変更がなければ隠します。
+pub fn show(app: &AppHandle, master: &MasterVolume) {
+    if mode(app) == HudMode::Off {
+        return;
+    }
+    let Some(window) = app.get_webview_window(HUD_LABEL) else { return };
+    let _ = app.emit_to(HUD_LABEL, "hud-update", master);
+    if !window.is_visible().unwrap_or(false) {
+        // 画面下端から高さの 1/8 の位置、左右中央
+        if let Ok(Some(monitor)) = window.primary_monitor() {
+            let size = window.outer_size().unwrap_or_default();
+            let x = monitor.position().x + (monitor.size().width as i32 - size.width as i32) / 2;
+            let y = monitor.position().y + monitor.size().height as i32 * 7 / 8 - size.height as i32;
+            let _ = window.set_position(PhysicalPosition::new(x, y));
+        }
+        let _ = window.set_ignore_cursor_events(true);
+        let _ = window.show();
+    }
+
+    let generation = app.state::<Hud>().generation.fetch_add(1, Ordering::SeqCst) + 1;
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(HUD_DURATION).await;
+        if app.state::<Hud>().generation.load(Ordering::SeqCst) == generation {
+            if let Some(window) = app.get_webview_window(HUD_LABEL) {
+                let _ = window.hide();
+            }
+        }
+    });
+}
+
+pub fn volume_stepping(app: &AppHandle) -> VolumeStepping {
+    let settings = app.state::<Mutex<SettingsManager>>();
+    let stepping = settings.lock().map(|s| s.settings().volume_stepping);
+    stepping.unwrap_or_default()
+}
+
+/// `current` から刻み 1 段分上げた・下げた音量
+pub fn next_volume(stepping: VolumeStepping, current: f32, up: bool) -> f32 {
+    let step = stepping.step.amount();
+    let next = (if up { current + step } else { current - step }).clamp(0.0, 1.0);
+    if !stepping.snap {
+        return next;
+    }
+    let snap = if up {
+        SNAP_POINTS.iter().find(|&&p| p > current + SNAP_TOLERANCE && p < next)
+    } else {
+        SNAP_POINTS.iter().rev().find(|&&p| p < current - SNAP_TOLERANCE && p > next)
+    };
+    snap.copied().unwrap_or(next)
+}
+
+/// マスター音量を設定の刻みで 1 段変え、HUD に表示します。
+pub async fn step_master_volume(app: &AppHandle, up: bool) -> Result<(), String> {
+    let worker = app.state::<AudioWorker>();
+    let stepping = volume_stepping(app);
+    let master = worker.call(move |m| {
+        let current = m.get_master_volume().map_err(i18n::audio_error)?;
+        m.set_master_volume(next_volume(stepping, current.volume, up)).map_err(i18n::audio_error)?;
+        m.get_master_volume().map_err(i18n::audio_error)
+    }).await?;
+    show(app, &master);
+    Ok(())
+}
+
+/// マスター出力のミュートを切り替え、HUD に表示します。
+pub async fn toggle_master_mute(app: &AppHandle) -> Result<(), String> {
+    let worker = app.state::<AudioWorker>();
+    let master = worker.call(|m| {
+        let current = m.get_master_volume().map_err(i18n::audio_error)?;
+        m.set_master_mute(!current.is_muted).map_err(i18n::audio_error)?;
+        m.get_master_volume().map_err(i18n::audio_error)
+    }).await?;
+    show(app, &master);
+    Ok(())
+}
+
+/// 設定に合わせて Windows の音量フライアウトを隠す・戻します。
+pub fn apply_native_osd(app: &AppHandle) {
+    native_osd::set_hidden(mode(app) == HudMode::ReplaceNative);
+}
+
+/// マスター音量の変化を購読し、[`HudMode::ReplaceNative`] なら HUD を表示します。
+pub fn spawn_master_listener(app: AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        apply_native_osd(&app);
+        loop {
+            match rx.recv().await {
+                Ok(MixerEvent::MasterVolumeChanged(master, _)) => {
+                    if mode(&app) == HudMode::ReplaceNative {
+                        // フライアウトは最初の音量キーの操作やエクスプローラーの再起動で作り直されるので、毎回隠し直す
+                        native_osd::set_hidden(true);
+                        show(&app, &master);
+                    }
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_volume_stepping(settings: State<'_, Mutex<SettingsManager>>) -> Result<VolumeStepping, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().volume_stepping)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_volume_stepping(settings: State<'_, Mutex<SettingsManager>>, stepping: VolumeStepping) -> Result<(), String> {
+    settings.lock().map_err(|_| i18n::text(Text::LockFailed))?.set_volume_stepping(stepping)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_hud_mode(settings: State<'_, Mutex<SettingsManager>>) -> Result<HudMode, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().hud_mode)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_hud_mode(app: AppHandle, settings: State<'_, Mutex<SettingsManager>>, mode: HudMode) -> Result<(), String> {
+    settings.lock().map_err(|_| i18n::text(Text::LockFailed))?.set_hud_mode(mode)?;
+    apply_native_osd(&app);
+    Ok(())
+}
+
+/// Windows の音量フライアウト。エクスプローラーが作る `NativeHWNDHost` のうち、
+/// `DirectUIHWND` を子に持つウィンドウがそれで、最小化しておくと表示されなくなります。
+#[cfg(windows)]
+pub mod native_osd {
+    use windows::core::{w, PCWSTR};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::WindowsAndMessaging::{FindWindowExW, ShowWindow, SW_MINIMIZE, SW_RESTORE};
+
+    fn find() -> Option<HWND> {
+        let mut after = HWND::default();
+        unsafe {
+            while let Ok(host) = FindWindowExW(HWND::default(), after, w!("NativeHWNDHost"), PCWSTR::null()) {
+                if FindWindowExW(host, HWND::default(), w!("DirectUIHWND"), PCWSTR::null()).is_ok() {
+                    return Some(host);
+                }
+                after = host;
+            }
+        }
+        None
+    }
+
+    pub fn set_hidden(hidden: bool) {
+        if let Some(hwnd) = find() {
+            unsafe {
+                let _ = ShowWindow(hwnd, if hidden { SW_MINIMIZE } else { SW_RESTORE });
+            }
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub mod native_osd {
+    pub fn set_hidden(_hidden: bool) {}
+}
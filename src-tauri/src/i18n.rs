@@ -0,0 +1,238 @@
+//! バックエンドが表示する文字列（トレイメニュー、トースト、エラーメッセージ）の翻訳。
+//! 言語は起動時に OS の表示言語から決め、フロントエンドには `get_locale` で伝えます。
+
+use std::sync::OnceLock;
+
+use crate::audio::AudioError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, specta::Type)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    En,
+    Ja,
+}
+
+impl Locale {
+    /// "ja-JP" や "ja_JP.UTF-8" のような表記から判定します。対応していない言語は英語にします。
+    fn from_tag(tag: &str) -> Self {
+        if tag.to_ascii_lowercase().starts_with("ja") { Locale::Ja } else { Locale::En }
+    }
+}
+
+/// 翻訳する文字列。`{}` を含むものは [`format`] で埋めます。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Text {
+    OpenMixerWindow,
+    CheckForUpdates,
+    UpdaterDisabled,
+    NoUpdateAvailable,
+    OutputMuted,
+    MutedApps,
+    PlayingCount,
+    NowPlayingThrough,
+    Undo,
+    LockFailed,
+    SettingsPathUnavailable,
+    SettingsNewerVersion,
+    NoCrashReport,
+    #[cfg_attr(not(windows), allow(dead_code))]
+    InvalidExePath,
+    #[cfg_attr(windows, allow(dead_code))]
+    AutoLaunchUnsupported,
+    ShortcutUnavailable,
+    ElevatedHelperStopped,
+    #[cfg_attr(not(windows), allow(dead_code))]
+    ElevatedHelperMismatch,
+    #[cfg_attr(windows, allow(dead_code))]
+    ElevatedHelperUnsupported,
+    #[cfg_attr(windows, allow(dead_code))]
+    SoundSchemesUnsupported,
+    #[cfg_attr(windows, allow(dead_code))]
+    SoundSettingsUnsupported,
+    #[cfg_attr(not(windows), allow(dead_code))]
+    UnknownSoundScheme,
+    #[cfg_attr(not(windows), allow(dead_code))]
+    UnknownSystemSound,
+    VolumeLimitConfirmationInvalid,
+    DevicePairSameDevice,
+    ApiVersionUnsupported,
+    InvalidRulePattern,
+    ProfileNameEmpty,
+    ProfileNotFound,
+    BackupNotConfigured,
+    InvalidBackup,
+    VirtualCableNotFound,
+    VirtualCableNoRecordingDevice,
+    #[cfg_attr(not(windows), allow(dead_code))]
+    VoiceMeeterNotInstalled,
+    #[cfg_attr(windows, allow(dead_code))]
+    VoiceMeeterUnsupported,
+    VoiceMeeterNotConnected,
+    VoiceMeeterChannelNotFound,
+    #[cfg_attr(windows, allow(dead_code))]
+    MediaKeysUnsupported,
+    #[cfg_attr(not(windows), allow(dead_code))]
+    MediaKeysHookFailed,
+    HotkeyInvalidAction,
+    HotkeyConflict,
+    ExportInvalidFileName,
+    ExportFileExists,
+    SessionNotFound,
+    Unsupported,
+    CannotUndo,
+    #[cfg_attr(not(windows), allow(dead_code))]
+    VoiceMeeterLoginFailed,
+    #[cfg_attr(not(windows), allow(dead_code))]
+    VoiceMeeterReadFailed,
+    #[cfg_attr(not(windows), allow(dead_code))]
+    VoiceMeeterWriteFailed,
+}
+
+pub fn locale() -> Locale {
+    static LOCALE: OnceLock<Locale> = OnceLock::new();
+    *LOCALE.get_or_init(|| system_locale().map(|tag| Locale::from_tag(&tag)).unwrap_or(Locale::En))
+}
+
+#[cfg(windows)]
+fn system_locale() -> Option<String> {
+    use windows::Win32::Globalization::GetUserDefaultLocaleName;
+    // LOCALE_NAME_MAX_LENGTH
+    let mut buffer = [0u16; 85];
+    let len = unsafe { GetUserDefaultLocaleName(&mut buffer) };
+    (len > 1).then(|| String::from_utf16_lossy(&buffer[..len as usize - 1]))
+}
+
+#[cfg(not(windows))]
+fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+pub fn text(key: Text) -> &'static str {
+    match locale() {
+        Locale::En => match key {
+            Text::OpenMixerWindow => "Open mixer window",
+            Text::CheckForUpdates => "Check for updates",
+            Text::UpdaterDisabled => "Updates are not available in this build",
+            Text::NoUpdateAvailable => "No update available",
+            Text::OutputMuted => "Output is muted",
+            Text::MutedApps => "Muted: {}",
+            Text::PlayingCount => "{} playing",
+            Text::NowPlayingThrough => "Now playing through: {}",
+            Text::Undo => "Undo",
+            Text::LockFailed => "Lock failed",
+            Text::SettingsPathUnavailable => "Settings path unavailable",
+            Text::SettingsNewerVersion => "The settings were saved by a newer version of the app. Update the app to change them",
+            Text::NoCrashReport => "No crash report",
+            Text::InvalidExePath => "Invalid EXE path",
+            Text::AutoLaunchUnsupported => "Auto launch is only supported on Windows",
+            Text::ShortcutUnavailable => "{} is already used by another app",
+            Text::ElevatedHelperStopped => "The administrator helper has stopped",
+            Text::ElevatedHelperMismatch => "Connected to an unexpected process",
+            Text::ElevatedHelperUnsupported => "The administrator helper is only supported on Windows",
+            Text::SoundSchemesUnsupported => "Sound schemes are only supported on Windows",
+            Text::SoundSettingsUnsupported => "Opening sound settings is only supported on Windows",
+            Text::UnknownSoundScheme => "Unknown sound scheme: {}",
+            Text::UnknownSystemSound => "Unknown system sound: {}",
+            Text::VolumeLimitConfirmationInvalid => "The confirmation has expired. Try again",
+            Text::DevicePairSameDevice => "Choose two different devices to pair",
+            Text::ApiVersionUnsupported => "API version {} is not supported",
+            Text::InvalidRulePattern => "Invalid pattern: {}",
+            Text::ProfileNameEmpty => "Enter a profile name",
+            Text::ProfileNotFound => "Profile not found: {}",
+            Text::BackupNotConfigured => "Choose a backup folder first",
+            Text::InvalidBackup => "Not a settings backup: {}",
+            Text::VirtualCableNotFound => "Not a virtual cable: {}",
+            Text::VirtualCableNoRecordingDevice => "The recording side of {} is not available. Enable it in the sound settings",
+            Text::VoiceMeeterNotInstalled => "VoiceMeeter is not installed",
+            Text::VoiceMeeterUnsupported => "VoiceMeeter is only supported on Windows",
+            Text::VoiceMeeterNotConnected => "VoiceMeeter is not running",
+            Text::VoiceMeeterChannelNotFound => "This VoiceMeeter has no {}",
+            Text::MediaKeysUnsupported => "Redirecting the volume keys is only supported on Windows",
+            Text::MediaKeysHookFailed => "Could not capture the volume keys: {}",
+            Text::HotkeyInvalidAction => "This action can't be assigned to the selected target",
+            Text::HotkeyConflict => "{} is already assigned",
+            Text::ExportInvalidFileName => "Not a file name: {}",
+            Text::ExportFileExists => "{} already exists",
+            Text::SessionNotFound => "The app's audio session was not found: {}",
+            Text::Unsupported => "{} is not supported on this platform",
+            Text::CannotUndo => "This change can't be undone",
+            Text::VoiceMeeterLoginFailed => "Could not connect to VoiceMeeter ({})",
+            Text::VoiceMeeterReadFailed => "Could not read {} from VoiceMeeter",
+            Text::VoiceMeeterWriteFailed => "Could not change {} in VoiceMeeter",
+        },
+        Locale::Ja => match key {
+            Text::OpenMixerWindow => "ミキサーをウィンドウで開く",
+            Text::CheckForUpdates => "アップデートを確認",
+            Text::UpdaterDisabled => "このビルドではアップデートを利用できません",
+            Text::NoUpdateAvailable => "インストールできるアップデートはありません",
+            Text::OutputMuted => "出力がミュートされています",
+            Text::MutedApps => "ミュート中: {}",
+            Text::PlayingCount => "{} 件再生中",
+            Text::NowPlayingThrough => "出力先: {}",
+            Text::Undo => "元に戻す",
+            Text::LockFailed => "ロックを取得できませんでした",
+            Text::SettingsPathUnavailable => "設定ファイルの場所が分かりません",
+            Text::SettingsNewerVersion => "設定は新しいバージョンのアプリで保存されています。変更するにはアプリを更新してください",
+            Text::NoCrashReport => "クラッシュレポートはありません",
+            Text::InvalidExePath => "実行ファイルのパスが不正です",
+            Text::AutoLaunchUnsupported => "自動起動は Windows でのみ使用できます",
+            Text::ShortcutUnavailable => "{} は他のアプリが使用しています",
+            Text::ElevatedHelperStopped => "管理者ヘルパーが終了しました",
+            Text::ElevatedHelperMismatch => "想定していないプロセスに接続しました",
+            Text::ElevatedHelperUnsupported => "管理者ヘルパーは Windows でのみ使用できます",
+            Text::SoundSchemesUnsupported => "サウンドのテーマは Windows でのみ使用できます",
+            Text::SoundSettingsUnsupported => "サウンド設定を開けるのは Windows のみです",
+            Text::UnknownSoundScheme => "サウンドのテーマが見つかりません: {}",
+            Text::UnknownSystemSound => "システムの音が見つかりません: {}",
+            Text::VolumeLimitConfirmationInvalid => "確認の有効期限が切れました。もう一度やり直してください",
+            Text::DevicePairSameDevice => "組にするには別々のデバイスを選んでください",
+            Text::ApiVersionUnsupported => "API のバージョン {} には対応していません",
+            Text::InvalidRulePattern => "パターンが正しくありません: {}",
+            Text::ProfileNameEmpty => "プロファイル名を入力してください",
+            Text::ProfileNotFound => "プロファイルが見つかりません: {}",
+            Text::BackupNotConfigured => "先にバックアップ先のフォルダを選んでください",
+            Text::InvalidBackup => "設定のバックアップではありません: {}",
+            Text::VirtualCableNotFound => "仮想ケーブルではありません: {}",
+            Text::VirtualCableNoRecordingDevice => "{} の録音側のデバイスが見つかりません。サウンドの設定で有効にしてください",
+            Text::VoiceMeeterNotInstalled => "VoiceMeeter がインストールされていません",
+            Text::VoiceMeeterUnsupported => "VoiceMeeter は Windows でのみ使用できます",
+            Text::VoiceMeeterNotConnected => "VoiceMeeter が起動していません",
+            Text::VoiceMeeterChannelNotFound => "この VoiceMeeter には {} がありません",
+            Text::MediaKeysUnsupported => "音量キーの割り当ての変更は Windows でのみ使用できます",
+            Text::MediaKeysHookFailed => "音量キーを受け取れませんでした: {}",
+            Text::HotkeyInvalidAction => "この対象にはこの操作を割り当てられません",
+            Text::HotkeyConflict => "{} はすでに割り当てられています",
+            Text::ExportInvalidFileName => "ファイル名ではありません: {}",
+            Text::ExportFileExists => "{} はすでにあります",
+            Text::SessionNotFound => "アプリの音声セッションが見つかりません: {}",
+            Text::Unsupported => "{} はこのプラットフォームでは使用できません",
+            Text::CannotUndo => "この変更は元に戻せません",
+            Text::VoiceMeeterLoginFailed => "VoiceMeeter に接続できませんでした ({})",
+            Text::VoiceMeeterReadFailed => "VoiceMeeter から {} を読み取れませんでした",
+            Text::VoiceMeeterWriteFailed => "VoiceMeeter の {} を変更できませんでした",
+        },
+    }
+}
+
+/// `{}` を `arg` に置き換えた文字列
+pub fn format(key: Text, arg: &str) -> String {
+    text(key).replacen("{}", arg, 1)
+}
+
+/// オーディオ層のエラーを表示用の文字列にします。OS やバックエンドからのメッセージはそのまま使います。
+pub fn audio_error(e: AudioError) -> String {
+    match e {
+        AudioError::SessionNotFound(pid) => format(Text::SessionNotFound, &format!("PID {}", pid)),
+        AudioError::InstanceNotFound(id) => format(Text::SessionNotFound, &id),
+        AudioError::Unsupported(what) => format(Text::Unsupported, what),
+        e => e.to_string(),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_locale() -> Locale {
+    locale()
+}
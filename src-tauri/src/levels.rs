@@ -0,0 +1,99 @@
+//! 実行ファイルごとに最後の音量とミュートを覚え、そのアプリのセッションが新しく現れたら適用し直します。
+//! 前日にミュートしたアプリが、次に起動したときに大きな音を出さないようにするためです。
+//! 同じときに、ウィンドウのタイトルやコマンドラインで選ぶルール（`app_rules`）も適用します。
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+use tokio::sync::broadcast;
+
+use crate::app_rules;
+use crate::audio::{AudioSessionInfo, AudioWorker, MixerEvent};
+use crate::events::EventBus;
+use crate::i18n;
+use crate::settings::{normalize_exe_name, AppLevel, SettingsManager};
+
+/// 最後の変更からこの時間がたったら設定に保存する。スライダーの操作中に何度も書き込まないため
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// 覚えている音量・ミュートと違っていれば適用し直します。
+async fn restore(app: &AppHandle, session: &AudioSessionInfo) {
+    let level = {
+        let settings = app.state::<Mutex<SettingsManager>>();
+        let settings = settings.lock().ok();
+        settings.and_then(|s| s.settings().app_levels.get(&normalize_exe_name(&session.exe_name)).copied())
+    };
+    let Some(level) = level else { return };
+    let worker = app.state::<AudioWorker>();
+    let pid = session.process_id;
+    if (session.volume - level.volume).abs() > f32::EPSILON {
+        let _ = worker.set_session_volume(pid, level.volume).await;
+    }
+    if session.is_muted != level.muted {
+        let muted = level.muted;
+        let _ = worker.call(move |m| m.set_session_mute(pid, muted).map_err(i18n::audio_error)).await;
+    }
+}
+
+fn save(app: &AppHandle, levels: HashMap<String, AppLevel>) {
+    if let Ok(mut settings) = app.state::<Mutex<SettingsManager>>().lock() {
+        if let Err(e) = settings.remember_app_levels(levels) {
+            eprintln!("Failed to save app levels: {}", e);
+        }
+    }
+}
+
+/// 音量・ミュートの変化を覚え、新しいプロセスのセッションが現れたら覚えている状態に戻します。
+/// 起動時やバックエンドの作り直しで改めて通知されるセッションには適用しません。
+pub fn spawn_level_keeper(app: AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        let worker = app.state::<AudioWorker>();
+        let existing = worker.call(|m| m.get_sessions_lite().map_err(i18n::audio_error)).await.unwrap_or_default();
+        let mut exe_names: HashMap<u32, String> = existing
+            .iter()
+            .map(|s| (s.process_id, normalize_exe_name(&s.exe_name)))
+            .collect();
+        let mut pending: HashMap<String, AppLevel> = HashMap::new();
+        loop {
+            let event = if pending.is_empty() {
+                rx.recv().await
+            } else {
+                match tokio::time::timeout(SAVE_DELAY, rx.recv()).await {
+                    Ok(event) => event,
+                    Err(_) => {
+                        save(&app, std::mem::take(&mut pending));
+                        continue;
+                    }
+                }
+            };
+            match event {
+                Ok(MixerEvent::SessionsChanged(diff)) => {
+                    for session in &diff.added {
+                        // ルールで音量やミュートを決めたアプリには、覚えている状態を適用しない
+                        if exe_names.insert(session.process_id, normalize_exe_name(&session.exe_name)).is_none()
+                            && !app_rules::apply(&app, session).await
+                        {
+                            restore(&app, session).await;
+                        }
+                    }
+                    for removed in &diff.removed {
+                        exe_names.remove(&removed.process_id);
+                    }
+                }
+                Ok(MixerEvent::VolumeChanged { pid, volume, muted, .. }) => {
+                    if let Some(exe_name) = exe_names.get(&pid) {
+                        pending.insert(exe_name.clone(), AppLevel { volume, muted });
+                    }
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        if !pending.is_empty() {
+            save(&app, pending);
+        }
+    });
+}
@@ -1,57 +1,539 @@
+use std::collections::HashMap;
 use std::sync::Mutex;
 use tauri::{AppHandle, Manager, State};
-use tauri::tray::{TrayIconBuilder, TrayIconEvent, MouseButton, MouseButtonState};
 
+mod app_rules;
 mod audio;
+mod backup;
+mod burn_in;
+mod call;
+mod crash;
+mod default_device;
+mod device_pairs;
+mod elevated;
+mod events;
+mod export;
+mod history;
+mod hotkey;
+mod hud;
+mod i18n;
+mod levels;
+mod measurement;
+mod media_keys;
+mod microphone;
+mod power;
+mod profiles;
+mod refresh;
+mod route_migration;
+mod session_stream;
+mod settings;
+mod settings_watch;
+mod sound_scheme;
+mod sound_settings;
+mod summary;
+mod toast;
+mod tray;
+mod undo;
+mod unplug;
+mod updater;
+mod usage;
+mod virtual_cable;
+mod voicemeeter;
+mod volume_limit;
 mod window;
 
-use audio::{AudioManager, AudioSessionInfo};
+use audio::{AudioBackend, AudioSessionInfo, AudioWorker};
+use audio::AppOverride;
+use events::EventBus;
+use i18n::Text;
+use settings::{DeviceChangeToast, FlyoutPlacement, Settings, SettingsManager};
 use window::WindowManager;
 
-pub struct AudioState(Mutex<Option<AudioManager>>);
-
-impl AudioState {
-    fn with_manager<F, R>(&self, app_handle: &AppHandle, f: F) -> Result<R, String>
-    where
-        F: FnOnce(&mut AudioManager) -> Result<R, String>,
-    {
-        let mut guard = self.0.lock().map_err(|_| "Lock failed")?;
-        if guard.is_none() {
-            let _ = audio::com::init_mta();
-            let mut manager = AudioManager::new().map_err(|e| e.to_string())?;
-            manager.set_app_handle(app_handle.clone());
-            *guard = Some(manager);
-        }
-        f(guard.as_mut().unwrap())
-    }
+/// 設定のうちオーディオ層に関係するものをバックエンドへ反映します。
+fn apply_settings(backend: &mut dyn AudioBackend, settings: &Settings) {
+    backend.set_hidden_apps(settings.hidden_apps.clone());
+    backend.set_app_overrides(settings.app_overrides.clone());
+}
+
+async fn update_settings<F>(worker: &AudioWorker, settings: &Mutex<SettingsManager>, f: F) -> Result<(), String>
+where
+    F: FnOnce(&mut SettingsManager) -> Result<(), String>,
+{
+    let snapshot = {
+        let mut settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+        f(&mut settings)?;
+        settings.settings().clone()
+    };
+    worker.call(move |m| { apply_settings(m, &snapshot); Ok(()) }).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_audio_sessions(worker: State<'_, AudioWorker>) -> Result<Vec<AudioSessionInfo>, String> {
+    worker.call(move |m| m.get_sessions().map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_sessions_lite(worker: State<'_, AudioWorker>) -> Result<Vec<AudioSessionInfo>, String> {
+    worker.call(move |m| m.get_sessions_lite().map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_session(worker: State<'_, AudioWorker>, pid: u32) -> Result<AudioSessionInfo, String> {
+    worker.call(move |m| m.get_session(pid).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_session_icon(worker: State<'_, AudioWorker>, pid: u32) -> Result<Option<String>, String> {
+    worker.call(move |m| m.get_session_icon(pid).map_err(i18n::audio_error)).await
+}
+
+/// `instance_id` を指定した場合はそのセッションだけ、省略した場合はプロセスのすべてのセッションを変更します。
+/// 管理者として動いているアプリなどで失敗したら、昇格ヘルパーが動いていればそちらで設定し直します。
+#[tauri::command]
+#[specta::specta]
+async fn set_session_volume(app: AppHandle, pid: u32, volume: f32, instance_id: Option<String>) -> Result<(), String> {
+    undo::change(&app, undo::Target::SessionVolume { pid, instance_id }, undo::Value::Volume(volume)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_session_mute(app: AppHandle, pid: u32, mute: bool, instance_id: Option<String>) -> Result<(), String> {
+    undo::change(&app, undo::Target::SessionMute { pid, instance_id }, undo::Value::Muted(mute)).await
+}
+
+/// アプリに再生・一時停止・曲送りなどを送ります。セッションに `now_playing` のあるアプリだけが受け付けます。
+#[tauri::command]
+#[specta::specta]
+async fn send_media_command(worker: State<'_, AudioWorker>, pid: u32, command: audio::MediaCommand) -> Result<(), String> {
+    worker.call(move |m| m.send_media_command(pid, command).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_audio_routing(app: AppHandle, pid: u32, device_id: String) -> Result<(), String> {
+    undo::change(&app, undo::Target::Routing { pid }, undo::Value::Route(Some(device_id))).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_default_device(app: AppHandle, device_id: String) -> Result<(), String> {
+    undo::change(&app, undo::Target::DefaultDevice, undo::Value::Device(device_id)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_bluetooth_profile(worker: State<'_, AudioWorker>, device_id: String, profile: audio::BluetoothProfile) -> Result<(), String> {
+    worker.call(move |m| m.set_bluetooth_profile(&device_id, profile).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn probe_device_formats(worker: State<'_, AudioWorker>, device_id: String) -> Result<Vec<audio::DeviceFormat>, String> {
+    worker.call(move |m| m.probe_device_formats(&device_id).map_err(i18n::audio_error)).await
+}
+
+/// テストトーンを再生します。`output` でサンプルレート・ビット深度・バッファの長さと、排他モードにするかを選べます。
+/// 省略するとデバイスのミックスフォーマットで共有モードに出力します。
+#[tauri::command]
+#[specta::specta]
+async fn play_tone(
+    app: AppHandle,
+    worker: State<'_, AudioWorker>,
+    device_id: Option<String>,
+    tone: audio::ToneRequest,
+    output: Option<audio::OutputFormat>,
+) -> Result<(), String> {
+    let request = audio::PlaybackRequest { device_id, signal: audio::Signal::Tone(tone), output: output.unwrap_or_default() };
+    burn_in::cancel(&app);
+    worker.call(move |m| m.start_playback(request).map_err(i18n::audio_error)).await
+}
+
+/// 和音や DTMF の数字の並びを再生します。
+#[tauri::command]
+#[specta::specta]
+async fn play_signal(
+    app: AppHandle,
+    worker: State<'_, AudioWorker>,
+    device_id: Option<String>,
+    signal: audio::Signal,
+    output: Option<audio::OutputFormat>,
+) -> Result<(), String> {
+    let request = audio::PlaybackRequest { device_id, signal, output: output.unwrap_or_default() };
+    burn_in::cancel(&app);
+    worker.call(move |m| m.start_playback(request).map_err(i18n::audio_error)).await
+}
+
+/// メトロノームを始めます。止めるときは `stop_playback` を使います。
+#[tauri::command]
+#[specta::specta]
+async fn start_metronome(
+    app: AppHandle,
+    worker: State<'_, AudioWorker>,
+    device_id: Option<String>,
+    metronome: audio::Metronome,
+    output: Option<audio::OutputFormat>,
+) -> Result<(), String> {
+    let request = audio::PlaybackRequest { device_id, signal: audio::Signal::Metronome(metronome), output: output.unwrap_or_default() };
+    burn_in::cancel(&app);
+    worker.call(move |m| m.start_playback(request).map_err(i18n::audio_error)).await
+}
+
+/// 音声ファイル（WAV / FLAC / Ogg Vorbis / MP3）を再生します。再生位置は `playback-progress` イベントで届きます。
+#[tauri::command]
+#[specta::specta]
+async fn play_file(
+    app: AppHandle,
+    worker: State<'_, AudioWorker>,
+    device_id: Option<String>,
+    path: String,
+    output: Option<audio::OutputFormat>,
+) -> Result<(), String> {
+    let request = audio::PlaybackRequest { device_id, signal: audio::Signal::File { path }, output: output.unwrap_or_default() };
+    burn_in::cancel(&app);
+    worker.call(move |m| m.start_playback(request).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn seek_playback(worker: State<'_, AudioWorker>, position_ms: u64) -> Result<(), String> {
+    worker.call(move |m| m.seek_playback(position_ms).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_metronome_tempo(worker: State<'_, AudioWorker>, bpm: f32) -> Result<(), String> {
+    worker.call(move |m| m.set_metronome_tempo(bpm).map_err(i18n::audio_error)).await
+}
+
+/// 複数のデバイスで同じ信号を同時に鳴らします。デバイスごとの遅れは `set_playback_delay` で鳴らしながら合わせられます。
+#[tauri::command]
+#[specta::specta]
+async fn play_synced(
+    app: AppHandle,
+    worker: State<'_, AudioWorker>,
+    signal: audio::Signal,
+    outputs: Vec<audio::SyncedOutput>,
+    output: Option<audio::OutputFormat>,
+) -> Result<(), String> {
+    let request = audio::SyncedPlaybackRequest { signal, outputs, output: output.unwrap_or_default() };
+    burn_in::cancel(&app);
+    worker.call(move |m| m.start_synced_playback(request).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_playback_delay(worker: State<'_, AudioWorker>, device_id: String, delay_ms: u32) -> Result<(), String> {
+    worker.call(move |m| m.set_playback_delay(&device_id, delay_ms).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn stop_playback(app: AppHandle, worker: State<'_, AudioWorker>) -> Result<(), String> {
+    burn_in::cancel(&app);
+    worker.call(|m| {
+        m.stop_playback();
+        Ok(())
+    }).await
+}
+
+/// アプリの音を `device_id` にも流します。止まると `mirror-stopped` イベントが届きます。
+#[tauri::command]
+#[specta::specta]
+async fn start_mirror(worker: State<'_, AudioWorker>, pid: u32, device_id: String, delay_ms: u32) -> Result<(), String> {
+    let request = audio::MirrorRequest { pid, device_id, delay_ms };
+    worker.call(move |m| m.start_mirror(request).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_mirror_delay(worker: State<'_, AudioWorker>, pid: u32, delay_ms: u32) -> Result<(), String> {
+    worker.call(move |m| m.set_mirror_delay(pid, delay_ms).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn stop_mirror(worker: State<'_, AudioWorker>, pid: u32) -> Result<(), String> {
+    worker.call(move |m| {
+        m.stop_mirror(pid);
+        Ok(())
+    }).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_mirrors(worker: State<'_, AudioWorker>) -> Result<Vec<audio::MirrorRequest>, String> {
+    worker.call(|m| Ok(m.get_mirrors())).await
+}
+
+/// アプリの音を `gain_db` だけ大きくします。アプリが終わるか止めるまで続き、止まると `boost-stopped` イベントが届きます。
+#[tauri::command]
+#[specta::specta]
+async fn start_boost(worker: State<'_, AudioWorker>, pid: u32, gain_db: f32) -> Result<(), String> {
+    worker.call(move |m| m.start_boost(audio::BoostRequest { pid, gain_db }).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_boost_gain(worker: State<'_, AudioWorker>, pid: u32, gain_db: f32) -> Result<(), String> {
+    worker.call(move |m| m.set_boost_gain(pid, gain_db).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn stop_boost(worker: State<'_, AudioWorker>, pid: u32) -> Result<(), String> {
+    worker.call(move |m| {
+        m.stop_boost(pid);
+        Ok(())
+    }).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_boosts(worker: State<'_, AudioWorker>) -> Result<Vec<audio::BoostRequest>, String> {
+    worker.call(|m| Ok(m.get_boosts())).await
+}
+
+/// システムの音をファイルに録り始めます。止まると `file-capture-stopped` イベントが届きます。
+#[tauri::command]
+#[specta::specta]
+async fn start_file_capture(worker: State<'_, AudioWorker>, request: audio::FileCaptureRequest) -> Result<(), String> {
+    worker.call(move |m| m.start_file_capture(request).map_err(i18n::audio_error)).await
+}
+
+/// ファイルへの録音を止めて、作ったファイルのパスを返します。
+#[tauri::command]
+#[specta::specta]
+async fn stop_file_capture(worker: State<'_, AudioWorker>) -> Result<Vec<String>, String> {
+    worker.call(|m| m.stop_file_capture().map_err(i18n::audio_error)).await
+}
+
+/// 取り込んだ音を HTTP で配信し始めます。止まると `network-stream-stopped` イベントが届きます。
+#[tauri::command]
+#[specta::specta]
+async fn start_network_stream(worker: State<'_, AudioWorker>, request: audio::NetworkStreamRequest) -> Result<audio::NetworkStream, String> {
+    worker.call(move |m| m.start_network_stream(request).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn stop_network_stream(worker: State<'_, AudioWorker>) -> Result<(), String> {
+    worker.call(|m| {
+        m.stop_network_stream();
+        Ok(())
+    }).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_network_stream(worker: State<'_, AudioWorker>) -> Result<Option<audio::NetworkStream>, String> {
+    worker.call(|m| Ok(m.get_network_stream())).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_audio_devices(worker: State<'_, AudioWorker>) -> Result<Vec<audio::AudioDeviceInfo>, String> {
+    worker.call(move |m| m.get_audio_devices().map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_capture_devices(worker: State<'_, AudioWorker>) -> Result<Vec<audio::AudioDeviceInfo>, String> {
+    worker.call(|m| m.get_capture_devices().map_err(i18n::audio_error)).await
+}
+
+/// セッションを持たない古い API で再生しているらしいアプリ。ウィンドウを持つプロセスのモジュールを調べるので、一覧を開いたときだけ呼びます。
+#[tauri::command]
+#[specta::specta]
+async fn get_legacy_streams(worker: State<'_, AudioWorker>) -> Result<Vec<audio::LegacyStream>, String> {
+    worker.call(|m| m.get_legacy_streams().map_err(i18n::audio_error)).await
 }
 
+/// 既定のマイクを切り替えます。`role` を省略するとすべての役割で既定にします。
 #[tauri::command]
-fn get_audio_sessions(app: AppHandle, state: State<'_, AudioState>) -> Result<Vec<AudioSessionInfo>, String> {
-    state.with_manager(&app, |m| m.get_sessions().map_err(|e| e.to_string()))
+#[specta::specta]
+async fn set_default_capture_device(worker: State<'_, AudioWorker>, device_id: String, role: Option<audio::DeviceRole>) -> Result<(), String> {
+    worker.call(move |m| m.set_default_capture_device(&device_id, role).map_err(i18n::audio_error)).await
 }
 
+/// マイクの入力レベルの計測を始めます。結果は `input-pulse` イベントで届きます。
 #[tauri::command]
-fn set_session_volume(app: AppHandle, state: State<'_, AudioState>, pid: u32, volume: f32) -> Result<(), String> {
-    state.with_manager(&app, |m| m.set_session_volume(pid, volume).map_err(|e| e.to_string()))
+#[specta::specta]
+async fn start_input_meter(worker: State<'_, AudioWorker>, device_id: Option<String>) -> Result<(), String> {
+    worker.call(move |m| m.start_input_meter(device_id.as_deref()).map_err(i18n::audio_error)).await
 }
 
 #[tauri::command]
-fn set_session_mute(app: AppHandle, state: State<'_, AudioState>, pid: u32, mute: bool) -> Result<(), String> {
-    state.with_manager(&app, |m| m.set_session_mute(pid, mute).map_err(|e| e.to_string()))
+#[specta::specta]
+async fn stop_input_meter(worker: State<'_, AudioWorker>) -> Result<(), String> {
+    worker.call(|m| {
+        m.stop_input_meter();
+        Ok(())
+    }).await
 }
 
 #[tauri::command]
-fn set_audio_routing(app: AppHandle, state: State<'_, AudioState>, pid: u32, device_id: String) -> Result<(), String> {
-    state.with_manager(&app, |m| m.set_audio_routing(pid, &device_id).map_err(|e| e.to_string()))
+#[specta::specta]
+async fn get_input_gain(worker: State<'_, AudioWorker>, device_id: String) -> Result<audio::InputGain, String> {
+    worker.call(move |m| m.get_input_gain(&device_id).map_err(i18n::audio_error)).await
 }
 
 #[tauri::command]
-fn get_audio_devices(app: AppHandle, state: State<'_, AudioState>) -> Result<Vec<audio::AudioDeviceInfo>, String> {
-    state.with_manager(&app, |m| m.get_audio_devices().map_err(|e| e.to_string()))
+#[specta::specta]
+async fn set_input_gain(worker: State<'_, AudioWorker>, device_id: String, db: f32) -> Result<(), String> {
+    worker.call(move |m| m.set_input_gain(&device_id, db).map_err(i18n::audio_error)).await
 }
 
 #[tauri::command]
+#[specta::specta]
+async fn set_input_boost(worker: State<'_, AudioWorker>, device_id: String, db: f32) -> Result<(), String> {
+    worker.call(move |m| m.set_input_boost(&device_id, db).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_capture_effects(worker: State<'_, AudioWorker>, device_id: String) -> Result<Vec<audio::CaptureEffect>, String> {
+    worker.call(move |m| m.get_capture_effects(&device_id).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_capture_effect(worker: State<'_, AudioWorker>, device_id: String, kind: audio::CaptureEffectKind, enabled: bool) -> Result<(), String> {
+    worker.call(move |m| m.set_capture_effect(&device_id, kind, enabled).map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_master_volume(worker: State<'_, AudioWorker>) -> Result<audio::MasterVolume, String> {
+    worker.call(|m| m.get_master_volume().map_err(i18n::audio_error)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_master_volume(app: AppHandle, volume: f32) -> Result<(), String> {
+    undo::change(&app, undo::Target::MasterVolume, undo::Value::Volume(volume)).await
+}
+
+/// マスター音量を dB で設定します。設定できる範囲は `get_master_volume` の `level` にあります。
+#[tauri::command]
+#[specta::specta]
+async fn set_master_volume_db(app: AppHandle, db: f32) -> Result<(), String> {
+    let worker = app.state::<AudioWorker>();
+    undo::track(&app, undo::Target::MasterVolume, worker.call(move |m| m.set_master_volume_db(db).map_err(i18n::audio_error))).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_master_mute(app: AppHandle, mute: bool) -> Result<(), String> {
+    undo::change(&app, undo::Target::MasterMute, undo::Value::Muted(mute)).await
+}
+
+/// マスター音量を `delta` の向きに設定の刻みで 1 段変えて HUD を表示します。ミキサー上のホイール操作用
+#[tauri::command]
+#[specta::specta]
+async fn step_master_volume(app: tauri::AppHandle, delta: f32) -> Result<(), String> {
+    undo::track(&app, undo::Target::MasterVolume, hud::step_master_volume(&app, delta > 0.0)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+fn get_hidden_apps(settings: State<'_, Mutex<SettingsManager>>) -> Result<Vec<String>, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().hidden_apps.clone())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn hide_app(worker: State<'_, AudioWorker>, settings: State<'_, Mutex<SettingsManager>>, exe_name: String) -> Result<(), String> {
+    update_settings(&worker, &settings, |s| s.hide_app(&exe_name)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn unhide_app(worker: State<'_, AudioWorker>, settings: State<'_, Mutex<SettingsManager>>, exe_name: String) -> Result<(), String> {
+    update_settings(&worker, &settings, |s| s.unhide_app(&exe_name)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+fn get_pinned_apps(settings: State<'_, Mutex<SettingsManager>>) -> Result<Vec<String>, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().pinned_apps.clone())
+}
+
+/// ピン留めしたアプリはミュートされるとトレイアイコンにバッジが付きます。
+#[tauri::command]
+#[specta::specta]
+async fn pin_app(app: tauri::AppHandle, settings: State<'_, Mutex<SettingsManager>>, exe_name: String) -> Result<(), String> {
+    settings.lock().map_err(|_| i18n::text(Text::LockFailed))?.pin_app(&exe_name)?;
+    tray::refresh_badge(&app).await;
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn unpin_app(app: tauri::AppHandle, settings: State<'_, Mutex<SettingsManager>>, exe_name: String) -> Result<(), String> {
+    settings.lock().map_err(|_| i18n::text(Text::LockFailed))?.unpin_app(&exe_name)?;
+    tray::refresh_badge(&app).await;
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+fn get_device_change_toast(settings: State<'_, Mutex<SettingsManager>>) -> Result<DeviceChangeToast, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().device_change_toast)
+}
+
+#[tauri::command]
+#[specta::specta]
+fn set_device_change_toast(settings: State<'_, Mutex<SettingsManager>>, mode: DeviceChangeToast) -> Result<(), String> {
+    settings.lock().map_err(|_| i18n::text(Text::LockFailed))?.set_device_change_toast(mode)
+}
+
+#[tauri::command]
+#[specta::specta]
+fn get_flyout_placement(settings: State<'_, Mutex<SettingsManager>>) -> Result<FlyoutPlacement, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().flyout_placement)
+}
+
+/// 次にトレイから開いたときから使います。
+#[tauri::command]
+#[specta::specta]
+fn set_flyout_placement(settings: State<'_, Mutex<SettingsManager>>, placement: FlyoutPlacement) -> Result<(), String> {
+    settings.lock().map_err(|_| i18n::text(Text::LockFailed))?.set_flyout_placement(placement)
+}
+
+#[tauri::command]
+#[specta::specta]
+fn get_app_overrides(settings: State<'_, Mutex<SettingsManager>>) -> Result<HashMap<String, AppOverride>, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().app_overrides.clone())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_app_override(
+    worker: State<'_, AudioWorker>,
+    settings: State<'_, Mutex<SettingsManager>>,
+    exe_name: String,
+    display_name: Option<String>,
+    icon_path: Option<String>,
+) -> Result<(), String> {
+    let app_override = AppOverride {
+        display_name: display_name.filter(|n| !n.trim().is_empty()),
+        icon_path: icon_path.filter(|p| !p.trim().is_empty()),
+    };
+    update_settings(&worker, &settings, |s| s.set_app_override(&exe_name, app_override)).await
+}
+
+#[cfg(windows)]
+#[tauri::command]
+#[specta::specta]
 fn is_auto_launch_enabled() -> Result<bool, String> {
     use winreg::enums::*;
     use winreg::RegKey;
@@ -61,7 +543,9 @@ fn is_auto_launch_enabled() -> Result<bool, String> {
     Ok(!val.is_empty())
 }
 
+#[cfg(windows)]
 #[tauri::command]
+#[specta::specta]
 fn toggle_auto_launch(enable: bool) -> Result<(), String> {
     use winreg::enums::*;
     use winreg::RegKey;
@@ -70,7 +554,7 @@ fn toggle_auto_launch(enable: bool) -> Result<(), String> {
 
     if enable {
         let exe_path = std::env::current_exe().map_err(|e: std::io::Error| e.to_string())?;
-        let exe_str = exe_path.to_str().ok_or("Invalid EXE path")?;
+        let exe_str = exe_path.to_str().ok_or(i18n::text(Text::InvalidExePath))?;
         key.set_value("AntigravityPulse", &exe_str).map_err(|e: std::io::Error| e.to_string())?;
     } else {
         let _ = key.delete_value("AntigravityPulse");
@@ -78,7 +562,22 @@ fn toggle_auto_launch(enable: bool) -> Result<(), String> {
     Ok(())
 }
 
+#[cfg(not(windows))]
+#[tauri::command]
+#[specta::specta]
+fn is_auto_launch_enabled() -> Result<bool, String> {
+    Ok(false)
+}
+
+#[cfg(not(windows))]
+#[tauri::command]
+#[specta::specta]
+fn toggle_auto_launch(_enable: bool) -> Result<(), String> {
+    Err(i18n::text(Text::AutoLaunchUnsupported).to_string())
+}
+
 #[tauri::command]
+#[specta::specta]
 fn set_tactical_mode(window: tauri::WebviewWindow, enabled: bool) -> Result<(), String> {
     window.set_always_on_top(enabled).map_err(|e| e.to_string())?;
     let _opacity = if enabled { 0.7 } else { 1.0 };
@@ -87,56 +586,272 @@ fn set_tactical_mode(window: tauri::WebviewWindow, enabled: bool) -> Result<(),
     Ok(())
 }
 
+/// ウィンドウの背景に使っている効果。フロントエンドはこれに合わせて下地の色を変えます。
+#[tauri::command]
+#[specta::specta]
+fn get_backdrop(wm: State<'_, Mutex<WindowManager>>) -> Result<window::Backdrop, String> {
+    let wm = wm.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(wm.backdrop())
+}
+
+/// コマンドとイベントの一覧。フロントエンドの型 `src/bindings.ts` もここから生成します。
+fn bindings() -> tauri_specta::Builder<tauri::Wry> {
+    tauri_specta::Builder::<tauri::Wry>::new()
+        .error_handling(tauri_specta::ErrorHandlingMode::Throw)
+        .events(events::webview_events())
+        .commands(tauri_specta::collect_commands![
+            get_audio_sessions,
+            get_sessions_lite,
+            get_session,
+            get_session_icon,
+            set_session_volume,
+            set_session_mute,
+            send_media_command,
+            set_audio_routing,
+            route_migration::force_audio_routing,
+            get_audio_devices,
+            set_default_device,
+            get_capture_devices,
+            get_legacy_streams,
+            set_default_capture_device,
+            virtual_cable::get_virtual_cables,
+            virtual_cable::route_app_through_cable,
+            virtual_cable::monitor_virtual_cable,
+            voicemeeter::get_voicemeeter,
+            voicemeeter::set_voicemeeter_enabled,
+            voicemeeter::set_voicemeeter_gain,
+            voicemeeter::set_voicemeeter_mute,
+            start_input_meter,
+            stop_input_meter,
+            get_input_gain,
+            set_input_gain,
+            set_input_boost,
+            get_capture_effects,
+            set_capture_effect,
+            microphone::get_microphone_users,
+            call::get_call_panel,
+            call::set_call_volume,
+            elevated::is_elevated_helper_running,
+            elevated::start_elevated_helper,
+            elevated::stop_elevated_helper,
+            sound_scheme::get_sound_schemes,
+            sound_scheme::set_sound_scheme,
+            sound_scheme::play_system_sound,
+            sound_settings::open_sound_settings,
+            usage::get_usage_stats,
+            history::get_volume_history,
+            history::clear_volume_history,
+            export::export_mixer_state,
+            volume_limit::get_volume_limit,
+            volume_limit::set_volume_limit,
+            volume_limit::request_volume_limit_override,
+            volume_limit::confirm_volume_limit_override,
+            volume_limit::end_volume_limit_override,
+            device_pairs::get_device_pairs,
+            device_pairs::add_device_pair,
+            device_pairs::remove_device_pair,
+            app_rules::get_app_rules,
+            app_rules::add_app_rule,
+            app_rules::remove_app_rule,
+            profiles::get_profiles,
+            profiles::save_profile,
+            profiles::apply_profile,
+            profiles::remove_profile,
+            profiles::set_startup_profile,
+            backup::get_backup_config,
+            backup::set_backup_config,
+            backup::back_up_settings,
+            backup::get_settings_backups,
+            backup::restore_settings_backup,
+            undo::undo,
+            undo::redo,
+            undo::get_undo_status,
+            events::subscribe_events,
+            events::unsubscribe_events,
+            events::get_event_subscriptions,
+            events::get_api_version,
+            events::set_event_schema,
+            session_stream::watch_sessions,
+            set_bluetooth_profile,
+            probe_device_formats,
+            play_tone,
+            play_signal,
+            start_metronome,
+            set_metronome_tempo,
+            play_file,
+            seek_playback,
+            play_synced,
+            set_playback_delay,
+            burn_in::start_burn_in,
+            burn_in::get_burn_in,
+            burn_in::stop_burn_in,
+            measurement::measure_frequency_response,
+            stop_playback,
+            start_mirror,
+            set_mirror_delay,
+            stop_mirror,
+            get_mirrors,
+            start_boost,
+            set_boost_gain,
+            stop_boost,
+            get_boosts,
+            start_file_capture,
+            stop_file_capture,
+            start_network_stream,
+            stop_network_stream,
+            get_network_stream,
+            get_master_volume,
+            refresh::refresh_audio_state,
+            summary::get_audio_summary,
+            set_master_volume,
+            set_master_volume_db,
+            set_master_mute,
+            step_master_volume,
+            get_hidden_apps,
+            hide_app,
+            unhide_app,
+            get_pinned_apps,
+            pin_app,
+            unpin_app,
+            get_device_change_toast,
+            set_device_change_toast,
+            unplug::get_pause_on_unplug,
+            unplug::set_pause_on_unplug,
+            get_flyout_placement,
+            set_flyout_placement,
+            get_app_overrides,
+            set_app_override,
+            is_auto_launch_enabled,
+            toggle_auto_launch,
+            set_tactical_mode,
+            get_backdrop,
+            i18n::get_locale,
+            updater::check_for_update,
+            updater::install_update,
+            hud::get_hud_mode,
+            hud::set_hud_mode,
+            hud::get_volume_stepping,
+            hud::set_volume_stepping,
+            hotkey::get_flyout_shortcut,
+            hotkey::set_flyout_shortcut,
+            hotkey::get_hotkeys,
+            hotkey::add_hotkey,
+            hotkey::remove_hotkey,
+            hotkey::check_hotkey_conflicts,
+            media_keys::get_media_key_target,
+            media_keys::set_media_key_target,
+            crash::get_pending_crash_report,
+            crash::open_crash_report,
+            crash::dismiss_crash_report,
+            crash::report_frontend_crash
+        ])
+}
+
+/// `src/bindings.ts` を書き出します。デバッグビルドの起動時と `cargo test` で呼びます。
+fn export_bindings(bindings: &tauri_specta::Builder<tauri::Wry>) {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../src/bindings.ts");
+    let typescript = specta_typescript::Typescript::default()
+        .header("// tauri-specta が生成したファイルです。編集しないでください。")
+        .bigint(specta_typescript::BigIntExportBehavior::Number);
+    if let Err(e) = bindings.export(typescript, path) {
+        eprintln!("Failed to export the TypeScript bindings: {}", e);
+    }
+}
+
+/// ウェブビューのウィンドウを作らず、トレイ・ホットキー・ルール・外部 API だけで動かす起動オプション
+const HEADLESS_ARG: &str = "--headless";
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // 管理者ヘルパーとして起動されたときはウィンドウを作らず、パイプの要求だけを処理して終わる
+    if elevated::run_helper_if_requested() {
+        return;
+    }
+    let bindings = bindings();
+    #[cfg(debug_assertions)]
+    export_bindings(&bindings);
     tauri::Builder::default()
-        .plugin(tauri_plugin_global_shortcut::Builder::new()
-            .with_handler(move |app, _shortcut, event| {
-                use tauri_plugin_global_shortcut::ShortcutState;
-                if event.state() == ShortcutState::Pressed {
-                    let wm_state = app.state::<Mutex<WindowManager>>();
-                    let mut wm = wm_state.lock().unwrap();
-                    let mut point = windows::Win32::Foundation::POINT { x: 0, y: 0 };
-                    let pos = unsafe {
-                        if windows::Win32::UI::WindowsAndMessaging::GetCursorPos(&mut point).is_ok() {
-                            (point.x, point.y)
-                        } else {
-                            (0, 0)
-                        }
-                    };
-                    wm.toggle(app, pos);
-                }
-            })
-            .build()
-        )
-        .manage(AudioState(Mutex::new(None)))
+        .plugin(hotkey::plugin())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_opener::init())
         .manage(Mutex::new(WindowManager::default()))
+        .manage(updater::PendingUpdate::default())
+        .manage(hotkey::Hotkeys::default())
+        .manage(hud::Hud::default())
+        .manage(microphone::MicrophoneUsers::default())
+        .manage(elevated::ElevatedHelper::default())
+        .manage(history::VolumeHistory::default())
+        .manage(volume_limit::VolumeLimit::default())
+        .manage(device_pairs::PairSync::default())
+        .manage(undo::UndoStack::default())
+        .manage(session_stream::SessionStreams::default())
+        .manage(burn_in::BurnInJobs::default())
+        .manage(voicemeeter::VoiceMeeter::default())
         .setup(|app| {
-            use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
-            use std::str::FromStr;
-            let _ = app.global_shortcut().register(Shortcut::from_str("Super+Alt+A").unwrap());
-
-            let handle = app.handle().clone();
-            
-            TrayIconBuilder::new()
-                .icon(app.default_window_icon().unwrap().clone())
-                .on_tray_icon_event(|tray, event| {
-                    if let TrayIconEvent::Click { position, button, button_state, .. } = event {
-                        if button == MouseButton::Left && button_state == MouseButtonState::Up {
-                            let app = tray.app_handle();
-                            let wm_state = app.state::<Mutex<WindowManager>>();
-                            let mut wm = wm_state.lock().unwrap();
-                            wm.toggle(app, (position.x as i32, position.y as i32));
-                        }
-                    }
-                })
-                .build(app)?;
+            let crash_reports = crash::CrashReports::new(app.handle());
+            crash_reports.install_panic_hook();
+            app.manage(crash_reports);
 
-            if let Some(window) = app.get_webview_window("main") {
-                let wm_state = app.state::<Mutex<WindowManager>>();
-                let wm = wm_state.lock().unwrap();
-                wm.apply_visual_effects(&window);
+            app.manage(Mutex::new(SettingsManager::load(app.handle())));
+            app.manage(Mutex::new(usage::UsageStore::load(app.handle())));
+
+            let settings_handle = app.handle().clone();
+            let icon_cache_dir = app.path().app_cache_dir().ok().map(|d| d.join("icons"));
+            let init = move |m: &mut dyn AudioBackend| {
+                m.set_icon_cache_dir(icon_cache_dir.clone());
+                let settings = settings_handle.state::<Mutex<SettingsManager>>();
+                if let Ok(settings) = settings.lock() {
+                    apply_settings(m, settings.settings());
+                };
+            };
+            let bus = EventBus::new(app.handle().clone());
+            events::spawn_logger(&bus);
+            let sink = bus.sink();
+            app.manage(bus);
+            // テスト用：環境変数があれば実機の代わりにモックのセッションで起動
+            let worker = if std::env::var("PULSE_MOCK_AUDIO").is_ok() {
+                AudioWorker::spawn_with(audio::demo_mock().factory(), sink, init)
+            } else {
+                AudioWorker::spawn(sink, init)
+            };
+            app.manage(worker);
+            if let Some(watcher) = audio::process_watch::ProcessWatcher::spawn(app.state::<EventBus>().sink()) {
+                app.manage(watcher);
+            }
+
+            tray::create(app)?;
+            let flyout_shortcut = app.state::<Mutex<SettingsManager>>().lock().unwrap().settings().flyout_shortcut().to_string();
+            if let Err(e) = hotkey::set_flyout(app.handle(), &flyout_shortcut) {
+                eprintln!("Failed to register shortcut {}: {}", flyout_shortcut, e);
+            }
+            hotkey::register_volume_shortcuts(app.handle());
+            let hotkeys = app.state::<Mutex<SettingsManager>>().lock().unwrap().settings().hotkeys.clone();
+            hotkey::set_bindings(app.handle(), &hotkeys);
+            media_keys::spawn_handler(app.handle().clone());
+            hud::spawn_master_listener(app.handle().clone(), &app.state::<EventBus>());
+            tray::spawn_menu_updater(app.handle().clone(), &app.state::<EventBus>());
+            tray::spawn_badge_updater(app.handle().clone(), &app.state::<EventBus>());
+            toast::spawn_device_change_notifier(app.handle().clone(), &app.state::<EventBus>());
+            unplug::spawn_guard(app.handle().clone(), &app.state::<EventBus>());
+            app_rules::spawn_process_listener(app.handle().clone(), &app.state::<EventBus>());
+            elevated::spawn_path_sharer(app.handle().clone(), &app.state::<EventBus>());
+            history::spawn_recorder(app.handle().clone(), &app.state::<EventBus>());
+            volume_limit::spawn_enforcer(app.handle().clone(), &app.state::<EventBus>());
+            device_pairs::spawn_sync(app.handle().clone(), &app.state::<EventBus>());
+            levels::spawn_level_keeper(app.handle().clone(), &app.state::<EventBus>());
+            microphone::spawn_watcher(app.handle().clone());
+            voicemeeter::spawn_watcher(app.handle().clone());
+            power::spawn_power_listener(app.handle().clone());
+            usage::spawn_recorder(app.handle().clone());
+            settings_watch::spawn_watcher(app.handle().clone());
+            profiles::spawn_startup(app.handle().clone());
+            backup::spawn_scheduler(app.handle().clone());
 
+            // ヘッドレスでもトレイメニューからミキサーウィンドウは開ける
+            if !std::env::args().any(|a| a == HEADLESS_ARG) {
+                app.state::<Mutex<WindowManager>>().lock().unwrap().create_configured_windows(app.handle())?;
+            }
+            if let Some(window) = app.get_webview_window("main") {
                 // テスト用：環境変数があれば即座に中央に表示
                 if std::env::var("PULSE_TEST_MODE").is_ok() {
                     let _ = window.set_position(tauri::PhysicalPosition::new(200, 200));
@@ -146,43 +861,28 @@ pub fn run() {
                 }
             }
 
-            let handle_task = handle.clone();
-            std::thread::spawn(move || {
-                let mut session_refresh_counter = 0;
-                loop {
-                    std::thread::sleep(std::time::Duration::from_millis(16));
-                    session_refresh_counter += 1;
-                    
-                    let state = handle_task.state::<AudioState>();
-                    let _ = state.with_manager(&handle_task, |m| {
-                        use tauri::Emitter;
-                        if let Ok(peaks) = m.get_peak_levels() {
-                            let _ = handle_task.emit("audio-pulse", peaks);
-                        }
-                        
-                        if session_refresh_counter >= 120 {
-                            session_refresh_counter = 0;
-                            if let Ok(sessions) = m.get_sessions() {
-                                let _ = handle_task.emit("refresh-sessions", sessions);
-                            }
-                        }
-                        Ok(())
-                    });
-                }
-            });
-
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
-            get_audio_sessions,
-            set_session_volume,
-            set_session_mute,
-            set_audio_routing,
-            get_audio_devices,
-            is_auto_launch_enabled,
-            toggle_auto_launch,
-            set_tactical_mode
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .invoke_handler(bindings.invoke_handler())
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // コールバックを登録したままプロセスが終了しないよう、オーディオスレッドを先に止める
+            if let tauri::RunEvent::Exit = event {
+                if let Some(worker) = app.try_state::<AudioWorker>() {
+                    worker.shutdown();
+                }
+                // 隠したままにすると、アプリを終了しても Windows の音量フライアウトが出なくなる
+                hud::native_osd::set_hidden(false);
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    /// 生成した `src/bindings.ts` をコミットし忘れていないか、CI では実行後の差分で確かめます。
+    #[test]
+    fn export_bindings() {
+        super::export_bindings(&super::bindings());
+    }
 }
@@ -0,0 +1,41 @@
+//! スイープを鳴らしながらマイクで録り、大まかな周波数特性を返す測定。
+//! 部屋やヘッドホンの癖をざっと確かめるためのもので、マイク自体の特性は補正しません。
+
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::audio::{self, AudioWorker};
+use crate::burn_in;
+use crate::i18n;
+
+/// スイープが終わってから録音を止めるまでの余裕。出力から録音までの遅れの分を録り切る
+const TAIL: Duration = Duration::from_millis(audio::measurement::MAX_LATENCY_MS as u64);
+
+/// 測定の間（スイープの長さ + 1 秒ほど）待ってから結果を返します。ジェネレーターの再生中のものは止めます。
+#[tauri::command]
+#[specta::specta]
+pub async fn measure_frequency_response(app: AppHandle, request: audio::MeasurementRequest) -> Result<audio::FrequencyResponse, String> {
+    request.validate().map_err(i18n::audio_error)?;
+    burn_in::cancel(&app);
+    let worker = app.state::<AudioWorker>();
+    let sweep = request.sweep;
+    let max_ms = (sweep.duration_ms + TAIL.as_millis() as u64) as u32;
+    let input = request.input_device_id.clone();
+    worker.call(move |m| m.start_recording(input.as_deref(), max_ms).map_err(i18n::audio_error)).await?;
+    let playback = audio::PlaybackRequest { device_id: request.output_device_id.clone(), signal: audio::Signal::Sweep(sweep), output: audio::OutputFormat::default() };
+    if let Err(e) = worker.call(move |m| m.start_playback(playback).map_err(i18n::audio_error)).await {
+        let _ = worker.call(|m| m.stop_recording().map_err(i18n::audio_error)).await;
+        return Err(e);
+    }
+    tokio::time::sleep(Duration::from_millis(sweep.duration_ms) + TAIL).await;
+    let recording = worker.call(|m| {
+        m.stop_playback();
+        m.stop_recording().map_err(i18n::audio_error)
+    }).await?;
+    let bands_per_octave = request.bands_per_octave;
+    tauri::async_runtime::spawn_blocking(move || audio::measurement::analyze(&sweep, bands_per_octave, &recording))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(i18n::audio_error)
+}
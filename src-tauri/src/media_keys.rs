@@ -0,0 +1,171 @@
+//! キーボードの音量キー（音量アップ・ダウン・ミュート）の横取り。設定で対象を選ぶと低レベルキーボードフックで
+//! キーを OS に届く前に受け取り、マスター音量の代わりに前面のアプリや選んだアプリの音量を変えます。
+//! フックは最初に横取りを有効にしたときに入れ、無効にしたらキーをそのまま OS に渡します。
+
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::mpsc;
+
+use crate::hotkey::{self, VolumeAction};
+use crate::i18n::{self, Text};
+use crate::settings::{MediaKeyTarget, SettingsManager};
+
+#[cfg(windows)]
+mod hook {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Mutex, OnceLock};
+    use std::thread;
+
+    use tokio::sync::mpsc;
+    use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
+    use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        CallNextHookEx, GetMessageW, SetWindowsHookExW, HC_ACTION, KBDLLHOOKSTRUCT, MSG, WH_KEYBOARD_LL, WM_KEYDOWN, WM_SYSKEYDOWN,
+    };
+
+    use crate::hotkey::VolumeAction;
+    use crate::i18n::{self, Text};
+
+    const VK_VOLUME_MUTE: u32 = 0xAD;
+    const VK_VOLUME_DOWN: u32 = 0xAE;
+    const VK_VOLUME_UP: u32 = 0xAF;
+
+    static SENDER: OnceLock<mpsc::UnboundedSender<VolumeAction>> = OnceLock::new();
+    static INTERCEPT: AtomicBool = AtomicBool::new(false);
+    /// フックを入れたか。入れられなかったときは、次に横取りを有効にしたときにやり直す
+    static INSTALLED: Mutex<bool> = Mutex::new(false);
+
+    /// フックの呼び出しは入力全体を待たせるので、チャネルに送るだけにする
+    unsafe extern "system" fn keyboard_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        if code == HC_ACTION as i32 && INTERCEPT.load(Ordering::Relaxed) {
+            let key = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+            let action = match key.vkCode {
+                VK_VOLUME_UP => Some(VolumeAction::Up),
+                VK_VOLUME_DOWN => Some(VolumeAction::Down),
+                VK_VOLUME_MUTE => Some(VolumeAction::ToggleMute),
+                _ => None,
+            };
+            if let Some(action) = action {
+                // 押し続けたときの繰り返しも 1 回ずつ送る。離したときは何もせず、OS にも渡さない
+                if matches!(wparam.0 as u32, WM_KEYDOWN | WM_SYSKEYDOWN) {
+                    if let Some(sender) = SENDER.get() {
+                        let _ = sender.send(action);
+                    }
+                }
+                return LRESULT(1);
+            }
+        }
+        CallNextHookEx(None, code, wparam, lparam)
+    }
+
+    pub fn init(sender: mpsc::UnboundedSender<VolumeAction>) {
+        let _ = SENDER.set(sender);
+    }
+
+    /// 横取りするかを切り替えます。初めて横取りするときにフックを入れ、入れられなければエラーを返します。
+    pub fn set_intercept(intercept: bool) -> Result<(), String> {
+        if intercept {
+            install()?;
+        }
+        INTERCEPT.store(intercept, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn install() -> Result<(), String> {
+        let mut installed = INSTALLED.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+        if *installed {
+            return Ok(());
+        }
+        let (result_tx, result_rx) = std::sync::mpsc::channel();
+        // 低レベルフックは入れたスレッドのメッセージループで呼ばれるので、専用のスレッドを残しておく
+        thread::Builder::new()
+            .name("media-keys".into())
+            .spawn(move || unsafe {
+                let module = GetModuleHandleW(None).unwrap_or_default();
+                let hook = SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_proc), module, 0);
+                let ok = hook.is_ok();
+                let _ = result_tx.send(hook.map(|_| ()).map_err(|e| e.to_string()));
+                if ok {
+                    let mut message = MSG::default();
+                    while GetMessageW(&mut message, None, 0, 0).as_bool() {}
+                }
+            })
+            .map_err(|e| i18n::format(Text::MediaKeysHookFailed, &e.to_string()))?;
+        match result_rx.recv() {
+            Ok(Ok(())) => {
+                *installed = true;
+                Ok(())
+            }
+            Ok(Err(e)) => Err(i18n::format(Text::MediaKeysHookFailed, &e)),
+            Err(e) => Err(i18n::format(Text::MediaKeysHookFailed, &e.to_string())),
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod hook {
+    use tokio::sync::mpsc;
+
+    use crate::hotkey::VolumeAction;
+    use crate::i18n::{self, Text};
+
+    pub fn init(_sender: mpsc::UnboundedSender<VolumeAction>) {}
+
+    pub fn set_intercept(intercept: bool) -> Result<(), String> {
+        if intercept { Err(i18n::text(Text::MediaKeysUnsupported).to_string()) } else { Ok(()) }
+    }
+}
+
+fn target(app: &AppHandle) -> MediaKeyTarget {
+    app.state::<Mutex<SettingsManager>>().lock().map(|s| s.settings().media_keys.clone()).unwrap_or_default()
+}
+
+async fn handle(app: &AppHandle, action: VolumeAction) -> Result<(), String> {
+    let handled = match target(app) {
+        MediaKeyTarget::System | MediaKeyTarget::Master => false,
+        MediaKeyTarget::FocusedApp => match hotkey::foreground_process() {
+            Some((pid, exe_name)) => hotkey::step_app(app, Some(pid), exe_name, action).await?,
+            None => false,
+        },
+        MediaKeyTarget::App(exe_name) => hotkey::step_app(app, None, exe_name, action).await?,
+    };
+    if handled { Ok(()) } else { hotkey::step_master(app, action).await }
+}
+
+/// 設定に合わせて横取りを始める・やめます。
+pub fn reapply(app: &AppHandle) {
+    if let Err(e) = hook::set_intercept(target(app) != MediaKeyTarget::System) {
+        eprintln!("Failed to intercept media keys: {}", e);
+    }
+}
+
+/// 横取りしたキーを処理し、設定で選ばれていれば横取りを始めます。
+pub fn spawn_handler(app: AppHandle) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    hook::init(tx);
+    reapply(&app);
+    tauri::async_runtime::spawn(async move {
+        while let Some(action) = rx.recv().await {
+            if let Err(e) = handle(&app, action).await {
+                eprintln!("Failed to handle a media key: {}", e);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_media_key_target(settings: State<'_, Mutex<SettingsManager>>) -> Result<MediaKeyTarget, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().media_keys.clone())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_media_key_target(settings: State<'_, Mutex<SettingsManager>>, target: MediaKeyTarget) -> Result<(), String> {
+    // 保存できなかった設定でキーを横取りしないよう、先に保存する
+    let intercept = target != MediaKeyTarget::System;
+    settings.lock().map_err(|_| i18n::text(Text::LockFailed))?.set_media_keys(target)?;
+    hook::set_intercept(intercept)
+}
@@ -0,0 +1,116 @@
+//! マイクを使っているアプリの一覧（プライバシーダッシュボード）。
+//! 録音中のキャプチャーセッションに加え、Windows では同意ストア (CapabilityAccessManager) を読み、
+//! セッションを持たない形でマイクを開いているアプリも拾います。どちらも通知がないので定期的に調べます。
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::audio::{AudioWorker, MicrophoneUser};
+use crate::i18n::{self, Text};
+
+/// 一覧を調べ直す間隔
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 最後に調べたマイクの利用者。`app.manage` で登録します。
+#[derive(Default)]
+pub struct MicrophoneUsers(Mutex<Vec<MicrophoneUser>>);
+
+impl MicrophoneUsers {
+    /// 録音中のセッションを持つプロセス
+    pub fn pids(&self) -> Vec<u32> {
+        let users = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        users.iter().filter_map(|u| u.pid).collect()
+    }
+}
+
+/// "microphone-access" イベントの内容
+#[derive(Clone, serde::Serialize)]
+struct AccessChange<'a> {
+    user: &'a MicrophoneUser,
+    active: bool,
+}
+
+/// 同意ストアで「使用中」（最後の使用の終了時刻が 0）になっているアプリ
+#[cfg(windows)]
+fn consent_store_users() -> Vec<MicrophoneUser> {
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    const CONSENT_STORE: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\CapabilityAccessManager\\ConsentStore\\microphone";
+    let Ok(store) = RegKey::predef(HKEY_CURRENT_USER).open_subkey(CONSENT_STORE) else { return Vec::new() };
+    let in_use = |key: &RegKey| {
+        let start: u64 = key.get_value("LastUsedTimeStart").unwrap_or(0);
+        let stop: u64 = key.get_value("LastUsedTimeStop").unwrap_or(1);
+        start != 0 && stop == 0
+    };
+
+    let mut users = Vec::new();
+    // デスクトップアプリはパスの `\` を `#` に置き換えた名前で記録される
+    if let Ok(desktop) = store.open_subkey("NonPackaged") {
+        for name in desktop.enum_keys().flatten() {
+            if !desktop.open_subkey(&name).is_ok_and(|key| in_use(&key)) {
+                continue;
+            }
+            let exe_name = name.rsplit('#').next().unwrap_or(&name).to_string();
+            users.push(MicrophoneUser { pid: None, process_name: exe_name.clone(), exe_name, device_id: None });
+        }
+    }
+    // ストアアプリはパッケージファミリー名 (Name_PublisherId) で記録される
+    for name in store.enum_keys().flatten() {
+        if name == "NonPackaged" || !store.open_subkey(&name).is_ok_and(|key| in_use(&key)) {
+            continue;
+        }
+        let process_name = name.split('_').next().unwrap_or(&name).rsplit('.').next().unwrap_or(&name).to_string();
+        users.push(MicrophoneUser { pid: None, exe_name: name, process_name, device_id: None });
+    }
+    users
+}
+
+#[cfg(not(windows))]
+fn consent_store_users() -> Vec<MicrophoneUser> {
+    Vec::new()
+}
+
+async fn current_users(app: &AppHandle) -> Vec<MicrophoneUser> {
+    let worker = app.state::<AudioWorker>();
+    let mut users = worker.call(|m| m.get_microphone_users().map_err(i18n::audio_error)).await.unwrap_or_default();
+    // セッションから分かったものを優先し、同じ実行ファイルは重ねない
+    for user in consent_store_users() {
+        if !users.iter().any(|u| u.exe_name.eq_ignore_ascii_case(&user.exe_name)) {
+            users.push(user);
+        }
+    }
+    users
+}
+
+/// 一覧を定期的に調べ、アプリが録音を始めた・やめたときに "microphone-access" を送ります。
+pub fn spawn_watcher(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let users = current_users(&app).await;
+            let previous = {
+                let state = app.state::<MicrophoneUsers>();
+                let mut current = state.0.lock().unwrap_or_else(|e| e.into_inner());
+                std::mem::replace(&mut *current, users.clone())
+            };
+            let same = |a: &MicrophoneUser, b: &MicrophoneUser| a.pid == b.pid && a.exe_name == b.exe_name;
+            for user in users.iter().filter(|u| !previous.iter().any(|p| same(p, u))) {
+                let _ = app.emit("microphone-access", AccessChange { user, active: true });
+            }
+            for user in previous.iter().filter(|p| !users.iter().any(|u| same(p, u))) {
+                let _ = app.emit("microphone-access", AccessChange { user, active: false });
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+/// いまマイクを使っているアプリ
+#[tauri::command]
+#[specta::specta]
+pub fn get_microphone_users(users: State<'_, MicrophoneUsers>) -> Result<Vec<MicrophoneUser>, String> {
+    let users = users.0.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(users.clone())
+}
@@ -0,0 +1,102 @@
+//! スリープと復帰の通知。復帰するとエンドポイントの ID や状態が変わっていることが多いため、
+//! バックエンドを作り直してデバイスを列挙し直し、スリープ前のアプリごとの出力先を設定し直します。
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+
+use crate::audio::AudioWorker;
+use crate::i18n;
+
+/// 復帰してから作り直すまでの待ち時間。USB や Bluetooth のデバイスが戻ってくるのを待つ
+const RESUME_DELAY: Duration = Duration::from_secs(3);
+
+#[cfg_attr(not(windows), allow(dead_code))]
+enum PowerEvent {
+    Suspend,
+    Resume,
+}
+
+/// 電源の通知を `tx` に送るよう登録します。登録はアプリの終了まで残します。
+#[cfg(windows)]
+fn register(tx: mpsc::UnboundedSender<PowerEvent>) {
+    use windows::Win32::Foundation::{HANDLE, ERROR_SUCCESS};
+    use windows::Win32::System::Power::{PowerRegisterSuspendResumeNotification, DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS};
+    use windows::Win32::UI::WindowsAndMessaging::{DEVICE_NOTIFY_CALLBACK, PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND};
+
+    unsafe extern "system" fn callback(context: *const core::ffi::c_void, kind: u32, _setting: *const core::ffi::c_void) -> u32 {
+        let tx = &*(context as *const mpsc::UnboundedSender<PowerEvent>);
+        // 復帰の通知はユーザー操作の有無で 2 種類あるが、PBT_APMRESUMEAUTOMATIC は必ず届く
+        let event = match kind {
+            PBT_APMSUSPEND => Some(PowerEvent::Suspend),
+            PBT_APMRESUMEAUTOMATIC => Some(PowerEvent::Resume),
+            _ => None,
+        };
+        if let Some(event) = event {
+            let _ = tx.send(event);
+        }
+        ERROR_SUCCESS.0
+    }
+
+    let parameters = Box::leak(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+        Callback: Some(callback),
+        Context: Box::into_raw(Box::new(tx)) as _,
+    }));
+    let mut registration = std::ptr::null_mut();
+    let result = unsafe {
+        PowerRegisterSuspendResumeNotification(DEVICE_NOTIFY_CALLBACK, HANDLE(parameters as *mut _ as _), &mut registration)
+    };
+    if result != ERROR_SUCCESS {
+        eprintln!("Failed to register for power notifications: {:?}", result);
+    }
+}
+
+#[cfg(not(windows))]
+fn register(_tx: mpsc::UnboundedSender<PowerEvent>) {}
+
+/// アプリごとに永続化された出力先（PID とデバイス ID）
+async fn persisted_routes(app: &AppHandle) -> Vec<(u32, String)> {
+    let worker = app.state::<AudioWorker>();
+    let sessions = worker.call(|m| m.get_sessions_lite().map_err(i18n::audio_error)).await.unwrap_or_default();
+    sessions.into_iter()
+        .filter_map(|s| Some((s.process_id, s.persisted_device_id?)))
+        .collect()
+}
+
+async fn resume(app: &AppHandle, routes: Vec<(u32, String)>) {
+    tokio::time::sleep(RESUME_DELAY).await;
+    let worker = app.state::<AudioWorker>();
+    worker.restart();
+    // 作り直したバックエンドで、まだ存在するデバイスへの出力先が外れていれば設定し直す
+    let result = worker.call(move |m| {
+        let devices: HashSet<String> = m.get_audio_devices().map_err(i18n::audio_error)?.into_iter().map(|d| d.id).collect();
+        let sessions = m.get_sessions_lite().map_err(i18n::audio_error)?;
+        for (pid, device_id) in routes {
+            let lost = sessions.iter().any(|s| s.process_id == pid && s.persisted_device_id.as_ref() != Some(&device_id));
+            if lost && devices.contains(&device_id) {
+                m.set_audio_routing(pid, &device_id).map_err(i18n::audio_error)?;
+            }
+        }
+        Ok(())
+    }).await;
+    if let Err(e) = result {
+        eprintln!("Failed to restore routes after resume: {}", e);
+    }
+}
+
+/// スリープの直前に出力先を覚え、復帰したらバックエンドを作り直して設定し直します。
+pub fn spawn_power_listener(app: AppHandle) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    register(tx);
+    tauri::async_runtime::spawn(async move {
+        let mut routes = Vec::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                PowerEvent::Suspend => routes = persisted_routes(&app).await,
+                PowerEvent::Resume => resume(&app, std::mem::take(&mut routes)).await,
+            }
+        }
+    });
+}
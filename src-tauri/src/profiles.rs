@@ -0,0 +1,170 @@
+//! 名前を付けて保存したミキサーの状態（プロファイル）。起動時に適用するものを 1 つ選べるほか、
+//! 初めて起動したときにその時点の状態を「Default」として保存し、いつでも戻せる状態を用意します。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::audio::AudioWorker;
+use crate::default_device;
+use crate::i18n::{self, Text};
+use crate::settings::{normalize_exe_name, AppLevel, Profile, SettingsManager};
+
+/// 初回起動時に作るプロファイルの名前
+const DEFAULT_PROFILE: &str = "Default";
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct ProfileList {
+    pub profiles: Vec<Profile>,
+    pub startup_profile: Option<String>,
+}
+
+fn list(settings: &SettingsManager) -> ProfileList {
+    ProfileList {
+        profiles: settings.settings().profiles.clone(),
+        startup_profile: settings.settings().startup_profile.clone(),
+    }
+}
+
+/// 今のマスター音量・既定のデバイスと、動いているアプリの音量・ミュート・出力先を取り出します。
+async fn capture(app: &AppHandle, name: String) -> Result<Profile, String> {
+    app.state::<AudioWorker>().call(move |m| {
+        let sessions = m.get_sessions_lite().map_err(i18n::audio_error)?;
+        let master = m.get_master_volume().ok().map(|v| AppLevel { volume: v.volume, muted: v.is_muted });
+        let default_device = m.get_audio_devices().ok()
+            .and_then(|devices| devices.into_iter().find(|d| d.is_default))
+            .map(|d| d.id);
+        let mut apps = HashMap::new();
+        let mut routes = HashMap::new();
+        for session in sessions {
+            let exe_name = normalize_exe_name(&session.exe_name);
+            if let Some(device_id) = session.persisted_device_id {
+                routes.insert(exe_name.clone(), device_id);
+            }
+            apps.insert(exe_name, AppLevel { volume: session.volume, muted: session.is_muted });
+        }
+        Ok(Profile { name, master, default_device, apps, routes })
+    }).await
+}
+
+/// プロファイルを適用します。アプリの音量とミュートは覚えている状態にも加えるので、まだ起動していないアプリにも後で反映されます。
+pub async fn apply(app: &AppHandle, name: &str) -> Result<(), String> {
+    let profile = {
+        let settings = app.state::<Mutex<SettingsManager>>();
+        let mut settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+        let profile = settings.settings().profiles.iter().find(|p| p.name == name).cloned()
+            .ok_or_else(|| i18n::format(Text::ProfileNotFound, name))?;
+        settings.remember_app_levels(profile.apps.clone())?;
+        profile
+    };
+    let worker = app.state::<AudioWorker>();
+    let Profile { master, default_device, apps, routes, .. } = profile;
+    worker.call(move |m| {
+        let sessions = m.get_sessions_lite().map_err(i18n::audio_error)?;
+        let mut seen = HashSet::new();
+        for session in sessions.iter().filter(|s| seen.insert(s.process_id)) {
+            let exe_name = normalize_exe_name(&session.exe_name);
+            let pid = session.process_id;
+            if let Some(level) = apps.get(&exe_name) {
+                let _ = m.set_session_volume(pid, level.volume);
+                let _ = m.set_session_mute(pid, level.muted);
+            }
+            if let Some(device_id) = routes.get(&exe_name) {
+                if session.persisted_device_id.as_ref() != Some(device_id) {
+                    let _ = m.set_audio_routing(pid, device_id);
+                }
+            }
+        }
+        Ok(())
+    }).await?;
+    if let Some(device_id) = default_device {
+        let current = worker.call(|m| m.get_audio_devices().map_err(i18n::audio_error)).await?
+            .into_iter()
+            .find(|d| d.is_default)
+            .map(|d| d.id);
+        if current.as_ref() != Some(&device_id) {
+            default_device::switch(app, device_id).await?;
+        }
+    }
+    if let Some(level) = master {
+        worker.call(move |m| {
+            m.set_master_volume(level.volume).map_err(i18n::audio_error)?;
+            m.set_master_mute(level.muted).map_err(i18n::audio_error)
+        }).await?;
+    }
+    Ok(())
+}
+
+/// 初回起動ならその時点の状態を「Default」として保存し、そうでなければ起動時のプロファイルを適用します。
+pub fn spawn_startup(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let (first_run, startup_profile) = {
+            let settings = app.state::<Mutex<SettingsManager>>();
+            let Ok(settings) = settings.lock() else { return };
+            (settings.is_first_run() && settings.settings().profiles.is_empty(), settings.settings().startup_profile.clone())
+        };
+        if first_run {
+            match capture(&app, DEFAULT_PROFILE.to_string()).await {
+                Ok(profile) => {
+                    if let Ok(mut settings) = app.state::<Mutex<SettingsManager>>().lock() {
+                        if let Err(e) = settings.save_profile(profile) {
+                            eprintln!("Failed to save default profile: {}", e);
+                        }
+                    }
+                }
+                Err(e) => eprintln!("Failed to capture default profile: {}", e),
+            }
+        } else if let Some(name) = startup_profile {
+            if let Err(e) = apply(&app, &name).await {
+                eprintln!("Failed to apply startup profile: {}", e);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_profiles(settings: State<'_, Mutex<SettingsManager>>) -> Result<ProfileList, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(list(&settings))
+}
+
+/// 今の状態を名前を付けて保存します。同じ名前のプロファイルは上書きします。
+#[tauri::command]
+#[specta::specta]
+pub async fn save_profile(app: AppHandle, name: String) -> Result<ProfileList, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(i18n::text(Text::ProfileNameEmpty).to_string());
+    }
+    let profile = capture(&app, name).await?;
+    let settings = app.state::<Mutex<SettingsManager>>();
+    let mut settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    settings.save_profile(profile)?;
+    Ok(list(&settings))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn apply_profile(app: AppHandle, name: String) -> Result<(), String> {
+    apply(&app, &name).await
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn remove_profile(settings: State<'_, Mutex<SettingsManager>>, name: String) -> Result<ProfileList, String> {
+    let mut settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    settings.remove_profile(&name)?;
+    Ok(list(&settings))
+}
+
+/// 起動時に適用するプロファイルを選びます（`None` で適用しない）。
+#[tauri::command]
+#[specta::specta]
+pub fn set_startup_profile(settings: State<'_, Mutex<SettingsManager>>, name: Option<String>) -> Result<ProfileList, String> {
+    let mut settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    settings.set_startup_profile(name)?;
+    Ok(list(&settings))
+}
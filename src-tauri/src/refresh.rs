@@ -0,0 +1,36 @@
+//! 表示が実際の状態とずれたときの手動の再読み込み。バックエンドのキャッシュ（セッション、アイコン、
+//! デバイスの監視）を捨ててすべてを数え直し、全体を `audio-state-refreshed` イベントで送ります。
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::audio::{AudioDeviceInfo, AudioSessionInfo, AudioWorker, MasterVolume};
+use crate::i18n;
+
+/// オーディオの状態の全体
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct AudioState {
+    /// セッション一覧。まだ抽出していないアイコンは後から `session-icon-ready` で届く
+    pub sessions: Vec<AudioSessionInfo>,
+    pub devices: Vec<AudioDeviceInfo>,
+    pub capture_devices: Vec<AudioDeviceInfo>,
+    /// 既定の出力デバイスがない場合は `None`
+    pub master: Option<MasterVolume>,
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn refresh_audio_state(app: AppHandle, worker: State<'_, AudioWorker>) -> Result<AudioState, String> {
+    let state = worker.call(|m| {
+        m.clear_caches();
+        Ok(AudioState {
+            sessions: m.get_sessions().map_err(i18n::audio_error)?,
+            devices: m.get_audio_devices().map_err(i18n::audio_error)?,
+            // マイクを扱えないバックエンドでも出力側は返す
+            capture_devices: m.get_capture_devices().unwrap_or_default(),
+            master: m.get_master_volume().ok(),
+        })
+    }).await?;
+    let _ = app.emit("audio-state-refreshed", state.clone());
+    Ok(state)
+}
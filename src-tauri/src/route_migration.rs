@@ -0,0 +1,64 @@
+//! 出力先をすぐに反映させる切り替え。出力先を永続化しただけでは、多くのアプリは音声クライアントを
+//! 開き直すまで（多くは再起動するまで）元のデバイスで鳴り続けます。そのようなアプリにはバックエンドから
+//! ストリームを移すよう促し、それでも移らなければ再起動が必要だと返します。
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::audio::AudioWorker;
+use crate::i18n;
+use crate::undo;
+
+/// 切り替えが反映されたかを確かめ続ける時間。アプリがストリームを開き直すのを待つ
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(2);
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 出力先の切り替えの結果
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, specta::Type)]
+pub enum RouteMigration {
+    /// 永続化しただけで反映された
+    Applied,
+    /// ストリームを移すよう促して反映された
+    Migrated,
+    /// 設定は保存されたが、アプリを再起動するまで反映されない
+    RestartRequired,
+}
+
+/// プロセスのセッションのうち、まだ出力先が反映されていないものがあるか
+async fn pending(worker: &AudioWorker, pid: u32) -> Result<bool, String> {
+    let sessions = worker.call(|m| m.get_sessions_lite().map_err(i18n::audio_error)).await?;
+    Ok(sessions.iter().any(|s| s.process_id == pid && s.route_pending))
+}
+
+/// 出力先が反映されるまで、最大 `SETTLE_TIMEOUT` 待ちます。
+async fn settle(worker: &AudioWorker, pid: u32) -> Result<bool, String> {
+    let deadline = Instant::now() + SETTLE_TIMEOUT;
+    loop {
+        if !pending(worker, pid).await? {
+            return Ok(true);
+        }
+        if Instant::now() >= deadline {
+            return Ok(false);
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// `set_audio_routing` と同じく出力先を切り替え（取り消せる操作として記録し）、反映されなければストリームを移すよう促します。
+#[tauri::command]
+#[specta::specta]
+pub async fn force_audio_routing(app: AppHandle, pid: u32, device_id: String) -> Result<RouteMigration, String> {
+    undo::change(&app, undo::Target::Routing { pid }, undo::Value::Route(Some(device_id))).await?;
+    let worker = app.state::<AudioWorker>();
+    if !pending(&worker, pid).await? {
+        return Ok(RouteMigration::Applied);
+    }
+    // 促せないバックエンドやアプリでは、再起動が必要と返す
+    if let Err(e) = worker.call(move |m| m.force_route_migration(pid).map_err(i18n::audio_error)).await {
+        eprintln!("Failed to migrate the streams of PID {}: {}", pid, e);
+        return Ok(RouteMigration::RestartRequired);
+    }
+    Ok(if settle(&worker, pid).await? { RouteMigration::Migrated } else { RouteMigration::RestartRequired })
+}
@@ -0,0 +1,80 @@
+//! セッション一覧のストリーミング。`watch_sessions` に渡された Tauri チャネルへ、最初に全体を、
+//! その後は差分を、呼び出したウィンドウが表示されている間だけ送ります。
+//! 差分を取りこぼしたとき（購読が遅れた、バックエンドを作り直した）は全体を送り直して揃えます。
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::ipc::Channel;
+use tauri::{Manager, State, WebviewWindow};
+use tokio::sync::broadcast;
+
+use crate::audio::{AudioSessionInfo, AudioWorker, MixerEvent, SessionsDiff};
+use crate::events::EventBus;
+use crate::i18n::{self, Text};
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(tag = "kind", content = "data")]
+pub enum SessionStreamMessage {
+    /// アイコンを含むセッション一覧の全体。これまでの一覧を置き換える
+    Snapshot(Vec<AudioSessionInfo>),
+    /// 前回のメッセージからの差分
+    Diff(SessionsDiff),
+}
+
+/// ウィンドウのラベルごとの最新のストリームの番号。呼び直されたら古いストリームを止めるのに使います。`app.manage` で登録します。
+#[derive(Debug, Default)]
+pub struct SessionStreams(Mutex<HashMap<String, u64>>);
+
+impl SessionStreams {
+    fn is_current(&self, label: &str, generation: u64) -> bool {
+        self.0.lock().map(|s| s.get(label) == Some(&generation)).unwrap_or(false)
+    }
+}
+
+async fn snapshot(worker: &AudioWorker) -> Result<SessionStreamMessage, String> {
+    worker.call(|m| m.get_sessions().map_err(i18n::audio_error)).await.map(SessionStreamMessage::Snapshot)
+}
+
+/// セッション一覧の全体を送ってから、差分を送り続けます。ウィンドウが隠されるか閉じられると止まるので、
+/// 開き直したら呼び直してください。同じウィンドウで呼び直すと、前のストリームは止まります。
+#[tauri::command]
+#[specta::specta]
+pub async fn watch_sessions(
+    window: WebviewWindow,
+    bus: State<'_, EventBus>,
+    worker: State<'_, AudioWorker>,
+    channel: Channel<SessionStreamMessage>,
+) -> Result<(), String> {
+    let label = window.label().to_string();
+    let generation = {
+        let streams = window.state::<SessionStreams>();
+        let mut streams = streams.0.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+        let generation = streams.get(&label).map_or(0, |g| g + 1);
+        streams.insert(label.clone(), generation);
+        generation
+    };
+    // 全体を取得している間の差分を取りこぼさないよう、先に購読する
+    let mut rx = bus.subscribe();
+    channel.send(snapshot(&worker).await?).map_err(|e| e.to_string())?;
+    let worker = worker.inner().clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let message = match rx.recv().await {
+                Ok(MixerEvent::SessionsChanged(diff)) => SessionStreamMessage::Diff(diff),
+                Ok(MixerEvent::BackendReconnected) | Err(broadcast::error::RecvError::Lagged(_)) => match snapshot(&worker).await {
+                    Ok(message) => message,
+                    Err(_) => continue,
+                },
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let open = window.is_visible().unwrap_or(false) && window.state::<SessionStreams>().is_current(&label, generation);
+            if !open || channel.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
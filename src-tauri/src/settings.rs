@@ -0,0 +1,698 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager};
+use windows_app_mixer::AppOverride;
+
+use crate::hotkey::DEFAULT_FLYOUT_SHORTCUT;
+use crate::i18n::{self, Text};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// 設定ファイルの形式の版。項目の意味や形を変えたら上げ、`MIGRATIONS` に前の版から移す手順を加える。
+/// 項目を足すだけなら `#[serde(default)]` で読めるので上げなくてよい
+const SCHEMA_VERSION: u32 = 1;
+
+/// `MIGRATIONS[n]` は版 n の設定を版 n + 1 に書き換える。`schema_version` のない（版を付ける前の）ファイルは版 0
+const MIGRATIONS: [fn(&mut Map<String, Value>); SCHEMA_VERSION as usize] = [migrate_v0];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// ファイルの形式の版。読み込んだ後は常に `SCHEMA_VERSION`
+    pub schema_version: u32,
+    /// ミキサーに表示しない実行ファイル名（大文字で保持）
+    pub hidden_apps: Vec<String>,
+    /// 実行ファイル名ごとの表示名・アイコンの上書き
+    pub app_overrides: HashMap<String, AppOverride>,
+    /// ミュートされたらトレイアイコンで知らせる実行ファイル名（大文字で保持）
+    pub pinned_apps: Vec<String>,
+    /// 既定の出力デバイスが変わったときのトースト通知
+    pub device_change_toast: DeviceChangeToast,
+    /// 既定の出力デバイスが外れたら、再生中のメディアを一時停止し、切り替わった先のデバイスをしばらくミュートする
+    pub pause_on_unplug: bool,
+    /// ミキサーを開くグローバルショートカット。`None` なら既定のもの、空文字列なら無効
+    pub flyout_shortcut: Option<String>,
+    /// 音量 HUD の表示方法
+    pub hud_mode: HudMode,
+    /// ホットキー・ホイールで音量を変える刻み
+    pub volume_stepping: VolumeStepping,
+    /// 最後に閉じたときのミキサーウィンドウの位置と大きさ
+    pub mixer_geometry: Option<WindowGeometry>,
+    /// 実行ファイル名（大文字）ごとの最後の音量とミュート。セッションが現れたら適用し直す
+    pub app_levels: HashMap<String, AppLevel>,
+    /// 聴覚保護のためのマスター音量の上限（0.0〜1.0）。`None` なら上限なし
+    pub volume_limit: Option<f32>,
+    /// 音量とミュートを揃えておく出力デバイスの組
+    pub device_pairs: Vec<DevicePair>,
+    /// ウィンドウのタイトルやコマンドラインで選ぶアプリごとの設定。上から順に調べ、最初に合ったものを使う
+    pub app_rules: Vec<AppRule>,
+    /// トレイから開くミキサーの位置の補正。タスクバーの位置を正しく判定できない環境向け
+    pub flyout_placement: FlyoutPlacement,
+    /// 名前を付けて保存したミキサーの状態
+    pub profiles: Vec<Profile>,
+    /// 起動時に適用するプロファイルの名前
+    pub startup_profile: Option<String>,
+    /// 設定を定期的に写しておくフォルダ。`None` なら写さない
+    pub backup: Option<BackupConfig>,
+    /// VoiceMeeter のストリップとバスもミキサーから操作する
+    pub voicemeeter: bool,
+    /// キーボードの音量キーで変える対象
+    pub media_keys: MediaKeyTarget,
+    /// ユーザーが割り当てたショートカット
+    pub hotkeys: Vec<HotkeyBinding>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct AppLevel {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+/// 音量を揃えておく 2 つの出力デバイスの ID。どちらを変えても、もう一方が同じ音量になる
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub struct DevicePair {
+    pub first: String,
+    pub second: String,
+}
+
+impl DevicePair {
+    /// 順序を問わず同じ 2 つのデバイスの組か
+    pub fn same_devices(&self, other: &DevicePair) -> bool {
+        (self.first == other.first && self.second == other.second) || (self.first == other.second && self.second == other.first)
+    }
+}
+
+/// ルールが当てはまるプロセスの条件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub enum RuleMatch {
+    /// いずれかのウィンドウのタイトルに合う正規表現
+    WindowTitle(String),
+    /// コマンドラインに含まれる文字列。大文字と小文字は区別しない
+    CommandLine(String),
+}
+
+/// 条件に合うセッションが現れたときに適用する出力先・音量・ミュート。`None` の項目は変えない
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct AppRule {
+    pub matches: RuleMatch,
+    /// 実行ファイル名（大文字）で絞り込む。`None` ならすべてのアプリ
+    pub exe_name: Option<String>,
+    pub device_id: Option<String>,
+    pub volume: Option<f32>,
+    pub muted: Option<bool>,
+}
+
+/// 設定の自動バックアップ。OneDrive などの同期フォルダを選べば、入れ直しても設定を戻せる
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct BackupConfig {
+    pub dir: String,
+    /// 前回のバックアップからこの時間がたったら写す
+    pub interval_hours: u32,
+    /// 残しておくバックアップの数。古いものから消す
+    pub keep: usize,
+}
+
+/// 名前を付けて保存したミキサーの状態。`None` の項目と、含まれていないアプリは変えない
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct Profile {
+    pub name: String,
+    /// マスター音量とミュート
+    pub master: Option<AppLevel>,
+    pub default_device: Option<String>,
+    /// 実行ファイル名（大文字）ごとの音量とミュート
+    pub apps: HashMap<String, AppLevel>,
+    /// 実行ファイル名（大文字）ごとの出力先
+    pub routes: HashMap<String, String>,
+}
+
+/// トレイから開くミキサーを置く作業領域の角
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub enum ScreenCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// トレイから開くミキサーの位置の上書き（物理ピクセル）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(default)]
+pub struct FlyoutPlacement {
+    /// 決めた位置からずらす量。正の値で右・下へ動く
+    pub offset_x: i32,
+    pub offset_y: i32,
+    /// `Some` ならタスクバーの位置を判定せず、常にこの角に置く
+    pub corner: Option<ScreenCorner>,
+}
+
+/// ウィンドウの位置と大きさ（物理ピクセル）と、表示していたモニターの名前
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub monitor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub enum HudMode {
+    Off,
+    /// このアプリのホットキー・ホイールで音量を変えたときだけ表示する
+    #[default]
+    On,
+    /// Windows の音量フライアウトを隠し、外部からの音量の変更でも HUD を表示する
+    ReplaceNative,
+}
+
+/// ホットキー・ホイールで 1 回に変える音量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub enum VolumeStep {
+    Percent1,
+    Percent2,
+    #[default]
+    Percent5,
+}
+
+impl VolumeStep {
+    pub fn amount(self) -> f32 {
+        match self {
+            Self::Percent1 => 0.01,
+            Self::Percent2 => 0.02,
+            Self::Percent5 => 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(default)]
+pub struct VolumeStepping {
+    pub step: VolumeStep,
+    /// 25・50・75・100% をまたぐときは、いったんそこで止める
+    pub snap: bool,
+}
+
+/// 音量キー（音量アップ・ダウン・ミュート）で変える対象。`System` 以外では OS に届く前にキーを横取りする
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub enum MediaKeyTarget {
+    /// 横取りせず、OS がマスター音量を変える
+    #[default]
+    System,
+    /// このアプリがマスター音量を変え、HUD を表示する
+    Master,
+    /// 前面のウィンドウのアプリ。セッションがなければマスター
+    FocusedApp,
+    /// 実行ファイル名（大文字）で選んだアプリ。再生していなければマスター
+    App(String),
+}
+
+/// ショートカットで行う操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub enum HotkeyAction {
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    /// デバイスを既定にする、プロファイルを適用する
+    Activate,
+}
+
+/// ショートカットで操作する対象
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub enum HotkeyTarget {
+    Master,
+    /// 前面のウィンドウのアプリ。セッションがなければマスター
+    FocusedApp,
+    /// 実行ファイル名（大文字）で選んだアプリ。再生していなければマスター
+    App(String),
+    /// 出力デバイスの ID
+    Device(String),
+    /// プロファイルの名前。`Activate` だけに使える
+    Profile(String),
+}
+
+/// ショートカットと、それで行う操作
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub struct HotkeyBinding {
+    /// "Ctrl+Alt+KeyM" のような、修飾キー（いくつでも）とキーの組み合わせ
+    pub shortcut: String,
+    /// 素早く 2 回押したときだけ行う。同じショートカットの 1 回押しの割り当ては、2 回目を待ってから行う
+    #[serde(default)]
+    pub double_tap: bool,
+    pub action: HotkeyAction,
+    pub target: HotkeyTarget,
+}
+
+impl Settings {
+    pub fn flyout_shortcut(&self) -> &str {
+        self.flyout_shortcut.as_deref().unwrap_or(DEFAULT_FLYOUT_SHORTCUT)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub enum DeviceChangeToast {
+    Off,
+    On,
+    /// 元のデバイスへ戻す「元に戻す」ボタンを付ける
+    #[default]
+    WithUndo,
+}
+
+#[derive(Debug, Default)]
+pub struct SettingsManager {
+    path: Option<PathBuf>,
+    settings: Settings,
+    /// 起動したときに設定ファイルがなかった
+    first_run: bool,
+    /// 新しい版のアプリで保存されたファイル。知らない項目を消してしまわないよう保存しない
+    read_only: bool,
+}
+
+impl SettingsManager {
+    /// アプリの設定ディレクトリから設定を読み込みます。読めない場合は既定値を使用します。
+    /// 古い版のファイルは今の版に移して保存し直します。
+    /// 新しい版のファイルは読むだけで、保存しようとするとエラーにします。
+    pub fn load(app: &AppHandle) -> Self {
+        Self::open(app.path().app_config_dir().ok().map(|dir| dir.join(SETTINGS_FILE)))
+    }
+
+    fn open(path: Option<PathBuf>) -> Self {
+        let first_run = path.as_ref().is_some_and(|p| !p.exists());
+        let (settings, version) =
+            path.as_deref().and_then(|p| read(p, true)).unwrap_or((Settings::default(), SCHEMA_VERSION));
+        let manager = Self {
+            path,
+            settings: Settings { schema_version: SCHEMA_VERSION, ..settings },
+            first_run,
+            read_only: version > SCHEMA_VERSION,
+        };
+        if version < SCHEMA_VERSION {
+            if let Err(e) = manager.save() {
+                eprintln!("Failed to save the migrated settings: {}", e);
+            }
+        }
+        manager
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn is_first_run(&self) -> bool {
+        self.first_run
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// ファイルを読み直し、外から書き換えられていれば置き換えて `true` を返します。
+    /// 自分で保存しただけのときや、書きかけで読めないときは今の設定のままです。
+    /// 起動時の控えを上書きしないよう、ここでは控えを取らず、古い版のファイルも保存し直しません。
+    pub fn reload(&mut self) -> bool {
+        let Some((settings, version)) = self.path.as_deref().and_then(|p| read(p, false)) else { return false };
+        let settings = Settings { schema_version: SCHEMA_VERSION, ..settings };
+        self.read_only = version > SCHEMA_VERSION;
+        if settings == self.settings {
+            return false;
+        }
+        self.settings = settings;
+        true
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if self.read_only {
+            return Err(i18n::text(Text::SettingsNewerVersion).to_string());
+        }
+        let path = self.path.as_ref().ok_or(i18n::text(Text::SettingsPathUnavailable))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(&self.settings).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    pub fn hide_app(&mut self, exe_name: &str) -> Result<(), String> {
+        let name = normalize_exe_name(exe_name);
+        if !self.settings.hidden_apps.contains(&name) {
+            self.settings.hidden_apps.push(name);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn unhide_app(&mut self, exe_name: &str) -> Result<(), String> {
+        let name = normalize_exe_name(exe_name);
+        self.settings.hidden_apps.retain(|n| *n != name);
+        self.save()
+    }
+
+    pub fn pin_app(&mut self, exe_name: &str) -> Result<(), String> {
+        let name = normalize_exe_name(exe_name);
+        if !self.settings.pinned_apps.contains(&name) {
+            self.settings.pinned_apps.push(name);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn unpin_app(&mut self, exe_name: &str) -> Result<(), String> {
+        let name = normalize_exe_name(exe_name);
+        self.settings.pinned_apps.retain(|n| *n != name);
+        self.save()
+    }
+
+    pub fn set_device_change_toast(&mut self, mode: DeviceChangeToast) -> Result<(), String> {
+        self.settings.device_change_toast = mode;
+        self.save()
+    }
+
+    pub fn set_pause_on_unplug(&mut self, enabled: bool) -> Result<(), String> {
+        self.settings.pause_on_unplug = enabled;
+        self.save()
+    }
+
+    pub fn set_flyout_shortcut(&mut self, shortcut: &str) -> Result<(), String> {
+        self.settings.flyout_shortcut = Some(shortcut.to_string());
+        self.save()
+    }
+
+    pub fn set_flyout_placement(&mut self, placement: FlyoutPlacement) -> Result<(), String> {
+        self.settings.flyout_placement = placement;
+        self.save()
+    }
+
+    pub fn set_hud_mode(&mut self, mode: HudMode) -> Result<(), String> {
+        self.settings.hud_mode = mode;
+        self.save()
+    }
+
+    pub fn set_volume_stepping(&mut self, stepping: VolumeStepping) -> Result<(), String> {
+        self.settings.volume_stepping = stepping;
+        self.save()
+    }
+
+    pub fn set_volume_limit(&mut self, limit: Option<f32>) -> Result<(), String> {
+        self.settings.volume_limit = limit;
+        self.save()
+    }
+
+    pub fn set_media_keys(&mut self, target: MediaKeyTarget) -> Result<(), String> {
+        self.settings.media_keys = match target {
+            MediaKeyTarget::App(exe_name) => MediaKeyTarget::App(normalize_exe_name(&exe_name)),
+            target => target,
+        };
+        self.save()
+    }
+
+    pub fn add_hotkey(&mut self, mut binding: HotkeyBinding) -> Result<(), String> {
+        if let HotkeyTarget::App(exe_name) = &binding.target {
+            binding.target = HotkeyTarget::App(normalize_exe_name(exe_name));
+        }
+        binding.shortcut = binding.shortcut.trim().to_string();
+        self.settings.hotkeys.push(binding);
+        self.save()
+    }
+
+    pub fn remove_hotkey(&mut self, index: usize) -> Result<(), String> {
+        if index < self.settings.hotkeys.len() {
+            self.settings.hotkeys.remove(index);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn set_voicemeeter(&mut self, enabled: bool) -> Result<(), String> {
+        self.settings.voicemeeter = enabled;
+        self.save()
+    }
+
+    pub fn add_device_pair(&mut self, pair: DevicePair) -> Result<(), String> {
+        if !self.settings.device_pairs.iter().any(|p| p.same_devices(&pair)) {
+            self.settings.device_pairs.push(pair);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn remove_device_pair(&mut self, pair: &DevicePair) -> Result<(), String> {
+        self.settings.device_pairs.retain(|p| !p.same_devices(pair));
+        self.save()
+    }
+
+    pub fn add_app_rule(&mut self, mut rule: AppRule) -> Result<(), String> {
+        rule.exe_name = rule.exe_name.as_deref().map(normalize_exe_name);
+        self.settings.app_rules.push(rule);
+        self.save()
+    }
+
+    pub fn remove_app_rule(&mut self, index: usize) -> Result<(), String> {
+        if index < self.settings.app_rules.len() {
+            self.settings.app_rules.remove(index);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn set_backup(&mut self, backup: Option<BackupConfig>) -> Result<(), String> {
+        self.settings.backup = backup;
+        self.save()
+    }
+
+    /// 同じ名前のプロファイルがあれば置き換えます。
+    pub fn save_profile(&mut self, profile: Profile) -> Result<(), String> {
+        match self.settings.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.settings.profiles.push(profile),
+        }
+        self.save()
+    }
+
+    pub fn remove_profile(&mut self, name: &str) -> Result<(), String> {
+        self.settings.profiles.retain(|p| p.name != name);
+        if self.settings.startup_profile.as_deref() == Some(name) {
+            self.settings.startup_profile = None;
+        }
+        self.save()
+    }
+
+    pub fn set_startup_profile(&mut self, name: Option<String>) -> Result<(), String> {
+        if let Some(name) = &name {
+            if !self.settings.profiles.iter().any(|p| p.name == *name) {
+                return Err(i18n::format(Text::ProfileNotFound, name));
+            }
+        }
+        self.settings.startup_profile = name;
+        self.save()
+    }
+
+    pub fn set_mixer_geometry(&mut self, geometry: WindowGeometry) -> Result<(), String> {
+        if self.settings.mixer_geometry.as_ref() == Some(&geometry) {
+            return Ok(());
+        }
+        self.settings.mixer_geometry = Some(geometry);
+        self.save()
+    }
+
+    /// 覚えている音量とミュートをまとめて更新します。変化がなければ保存しません。
+    pub fn remember_app_levels(&mut self, levels: HashMap<String, AppLevel>) -> Result<(), String> {
+        let mut changed = false;
+        for (exe_name, level) in levels {
+            let previous = self.settings.app_levels.insert(normalize_exe_name(&exe_name), level);
+            changed |= previous != Some(level);
+        }
+        if changed {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn set_app_override(&mut self, exe_name: &str, app_override: AppOverride) -> Result<(), String> {
+        let name = normalize_exe_name(exe_name);
+        if app_override == AppOverride::default() {
+            self.settings.app_overrides.remove(&name);
+        } else {
+            self.settings.app_overrides.insert(name, app_override);
+        }
+        self.save()
+    }
+}
+
+/// 設定ファイルを読み、古い版なら今の版に移します。2 つ目はファイルの版。
+/// `backup` のときは、壊れたファイルや別の版のファイルを、既定値や今の版で上書きされても戻せるよう別名で残します。
+fn read(path: &Path, backup: bool) -> Option<(Settings, u32)> {
+    let backup = |suffix: &str| {
+        if backup {
+            self::backup(path, suffix);
+        }
+    };
+    let text = fs::read_to_string(path).ok()?;
+    let Ok(Value::Object(mut value)) = serde_json::from_str::<Value>(&text) else {
+        backup("invalid");
+        return None;
+    };
+    let version = value.get("schema_version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if version != SCHEMA_VERSION {
+        backup(&format!("v{}", version));
+    }
+    // 新しい版のファイル（新しいアプリで保存したもの）は、知っている項目だけを読む
+    for migrate in MIGRATIONS.iter().skip(version as usize) {
+        migrate(&mut value);
+    }
+    match serde_json::from_value(Value::Object(value)) {
+        Ok(settings) => Some((settings, version)),
+        Err(e) => {
+            eprintln!("Failed to read the settings: {}", e);
+            backup("invalid");
+            None
+        }
+    }
+}
+
+/// `settings.json` を `settings.<suffix>.json` として写します。最初の控えを残すため、既にあれば写しません。
+fn backup(path: &Path, suffix: &str) {
+    let target = path.with_extension(format!("{}.json", suffix));
+    if !target.exists() {
+        let _ = fs::copy(path, target);
+    }
+}
+
+/// 版 0 → 1: 手で編集されたファイルでも引けるよう、実行ファイル名を大文字に揃える
+fn migrate_v0(value: &mut Map<String, Value>) {
+    for key in ["hidden_apps", "pinned_apps"] {
+        if let Some(Value::Array(names)) = value.get_mut(key) {
+            for name in names.iter_mut() {
+                if let Value::String(s) = name {
+                    *s = normalize_exe_name(s);
+                }
+            }
+        }
+    }
+    for key in ["app_overrides", "app_levels"] {
+        if let Some(Value::Object(map)) = value.get_mut(key) {
+            *map = std::mem::take(map).into_iter().map(|(k, v)| (normalize_exe_name(&k), v)).collect();
+        }
+    }
+}
+
+/// `process_name` と同じ表記（大文字）に揃えます。
+pub fn normalize_exe_name(exe_name: &str) -> String {
+    exe_name.trim().to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// テストごとに空のフォルダーを用意します。
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sound-generator-settings-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn version_0_files_are_migrated_and_backed_up() {
+        let dir = temp_dir("v0");
+        let path = dir.join(SETTINGS_FILE);
+        let original = r#"{
+            "hidden_apps": ["chrome.exe", " Teams.exe "],
+            "pinned_apps": ["Spotify.exe"],
+            "app_overrides": { "Discord.exe": { "display_name": "Chat" } },
+            "app_levels": { "vlc.exe": { "volume": 0.5, "muted": true } }
+        }"#;
+        fs::write(&path, original).unwrap();
+
+        let (settings, version) = read(&path, true).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(settings.hidden_apps, vec!["CHROME.EXE", "TEAMS.EXE"]);
+        assert_eq!(settings.pinned_apps, vec!["SPOTIFY.EXE"]);
+        assert_eq!(settings.app_overrides["DISCORD.EXE"].display_name.as_deref(), Some("Chat"));
+        assert_eq!(settings.app_levels["VLC.EXE"], AppLevel { volume: 0.5, muted: true });
+        assert_eq!(fs::read_to_string(dir.join("settings.v0.json")).unwrap(), original);
+    }
+
+    #[test]
+    fn current_files_are_read_as_is() {
+        let dir = temp_dir("current");
+        let path = dir.join(SETTINGS_FILE);
+        fs::write(&path, format!(r#"{{ "schema_version": {}, "hidden_apps": ["CHROME.EXE"] }}"#, SCHEMA_VERSION)).unwrap();
+
+        let (settings, version) = read(&path, true).unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        assert_eq!(settings.hidden_apps, vec!["CHROME.EXE"]);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn newer_files_are_not_downgraded() {
+        let dir = temp_dir("newer");
+        let path = dir.join(SETTINGS_FILE);
+        let newer = SCHEMA_VERSION + 1;
+        let original = format!(r#"{{ "schema_version": {}, "hidden_apps": ["chrome.exe"], "added_later": true }}"#, newer);
+        fs::write(&path, &original).unwrap();
+
+        let (settings, version) = read(&path, true).unwrap();
+        assert_eq!(version, newer);
+        assert_eq!(settings.hidden_apps, vec!["chrome.exe"]);
+
+        let mut manager = SettingsManager::open(Some(path.clone()));
+        assert_eq!(manager.settings().hidden_apps, vec!["chrome.exe"]);
+        assert!(manager.hide_app("game.exe").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+        assert_eq!(fs::read_to_string(dir.join(format!("settings.v{}.json", newer))).unwrap(), original);
+    }
+
+    #[test]
+    fn version_0_files_are_saved_in_the_current_version_on_load() {
+        let dir = temp_dir("load");
+        let path = dir.join(SETTINGS_FILE);
+        fs::write(&path, r#"{ "hidden_apps": ["chrome.exe"] }"#).unwrap();
+
+        let mut manager = SettingsManager::open(Some(path.clone()));
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["schema_version"], SCHEMA_VERSION);
+        assert_eq!(saved["hidden_apps"], serde_json::json!(["CHROME.EXE"]));
+        assert!(manager.hide_app("game.exe").is_ok());
+    }
+
+    #[test]
+    fn broken_files_are_backed_up() {
+        let dir = temp_dir("broken");
+        let path = dir.join(SETTINGS_FILE);
+        fs::write(&path, "{ not json").unwrap();
+
+        assert!(read(&path, true).is_none());
+        assert_eq!(fs::read_to_string(dir.join("settings.invalid.json")).unwrap(), "{ not json");
+    }
+
+    #[test]
+    fn backups_are_not_overwritten() {
+        let dir = temp_dir("backups");
+        let path = dir.join(SETTINGS_FILE);
+        fs::write(&path, "{ not json").unwrap();
+        assert!(read(&path, true).is_none());
+
+        fs::write(&path, "{ still not json").unwrap();
+        assert!(read(&path, true).is_none());
+        assert_eq!(fs::read_to_string(dir.join("settings.invalid.json")).unwrap(), "{ not json");
+    }
+
+    #[test]
+    fn reloading_does_not_back_up() {
+        let dir = temp_dir("reload");
+        let path = dir.join(SETTINGS_FILE);
+        let mut manager = SettingsManager::open(Some(path.clone()));
+
+        fs::write(&path, "{ not json").unwrap();
+        assert!(!manager.reload());
+        fs::write(&path, r#"{ "hidden_apps": ["chrome.exe"] }"#).unwrap();
+        assert!(manager.reload());
+        assert_eq!(manager.settings().hidden_apps, vec!["CHROME.EXE"]);
+        assert!(!dir.join("settings.invalid.json").exists());
+        assert!(!dir.join("settings.v0.json").exists());
+    }
+}
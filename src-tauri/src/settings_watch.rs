@@ -0,0 +1,76 @@
+//! 設定ファイルの外からの編集の反映。JSON を手で書き換えても再起動せずに済むよう、ファイルを見張り、
+//! 変わっていれば読み直して適用し、`settings-changed` イベントで新しい設定を送ります。
+
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio::AudioWorker;
+use crate::settings::SettingsManager;
+use crate::{apply_settings, device_pairs, hotkey, hud, media_keys, tray, volume_limit};
+
+/// エディターは保存を何回かの書き込みに分けることがあるので、静かになるまで待ってから読む
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// 読み直した設定を、起動時に設定から決めているものすべてに適用し直します。
+pub async fn apply(app: &AppHandle) {
+    let snapshot = {
+        let settings = app.state::<Mutex<SettingsManager>>();
+        let Ok(mut settings) = settings.lock() else { return };
+        if !settings.reload() {
+            return;
+        }
+        settings.settings().clone()
+    };
+    let backend_settings = snapshot.clone();
+    let _ = app.state::<AudioWorker>().call(move |m| { apply_settings(m, &backend_settings); Ok(()) }).await;
+    if let Err(e) = hotkey::set_flyout(app, snapshot.flyout_shortcut()) {
+        eprintln!("Failed to register shortcut {}: {}", snapshot.flyout_shortcut(), e);
+    }
+    hotkey::set_bindings(app, &snapshot.hotkeys);
+    hud::apply_native_osd(app);
+    media_keys::reapply(app);
+    volume_limit::reapply(app).await;
+    device_pairs::rewatch(app).await;
+    tray::refresh_badge(app).await;
+    let _ = app.emit("settings-changed", snapshot);
+}
+
+/// 設定ファイルのあるディレクトリを見張ります。保存し直すエディターはファイルを置き換えるので、ファイルではなくディレクトリを見る
+pub fn spawn_watcher(app: AppHandle) {
+    let Some(path) = app.state::<Mutex<SettingsManager>>().lock().ok().and_then(|s| s.path().map(|p| p.to_path_buf())) else { return };
+    let (Some(dir), Some(file_name)) = (path.parent().map(|d| d.to_path_buf()), path.file_name().map(|f| f.to_os_string())) else { return };
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(tx) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("Failed to watch the settings file: {}", e);
+            return;
+        }
+    };
+    // まだ一度も保存していなければディレクトリがない
+    let _ = std::fs::create_dir_all(&dir);
+    if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+        eprintln!("Failed to watch the settings file: {}", e);
+        return;
+    }
+    std::thread::spawn(move || {
+        // スレッドが動いている間だけ見張る
+        let _watcher = watcher;
+        let touches_settings = |event: &notify::Result<notify::Event>| {
+            event.as_ref().is_ok_and(|e| {
+                matches!(e.kind, EventKind::Create(_) | EventKind::Modify(_)) && e.paths.iter().any(|p| p.file_name() == Some(&file_name))
+            })
+        };
+        while let Ok(event) = rx.recv() {
+            if !touches_settings(&event) {
+                continue;
+            }
+            while rx.recv_timeout(DEBOUNCE).is_ok() {}
+            tauri::async_runtime::block_on(apply(&app));
+        }
+    });
+}
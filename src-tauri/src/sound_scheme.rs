@@ -0,0 +1,139 @@
+//! Windows のサウンド設定（システムの効果音）のテーマ。`HKCU\AppEvents\Schemes` に保存されており、
+//! テーマを切り替えるには各イベントの `.Current` の値をテーマのものに書き換えます。
+
+/// サウンドのテーマ。`id` は ".Default" や ".None" のようなレジストリ上の名前
+#[derive(Debug, Clone, serde::Serialize, specta::Type)]
+pub struct SoundScheme {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, specta::Type)]
+pub struct SoundSchemes {
+    /// 使用中のテーマの ID
+    pub current: String,
+    pub schemes: Vec<SoundScheme>,
+}
+
+/// 効果音を鳴らさない「サウンドなし」のテーマ
+#[cfg_attr(not(windows), allow(dead_code))]
+const NO_SOUNDS: &str = ".None";
+
+#[cfg(windows)]
+mod imp {
+    use winreg::enums::{HKEY_CURRENT_USER, KEY_READ, KEY_WRITE};
+    use winreg::RegKey;
+
+    use super::{SoundScheme, SoundSchemes, NO_SOUNDS};
+    use crate::i18n::{self, Text};
+
+    const SCHEMES: &str = "AppEvents\\Schemes";
+
+    /// "@mmres.dll,-800" のような間接指定の表示名を解決します。
+    fn resolve_name(name: &str) -> String {
+        use windows::core::HSTRING;
+        use windows::Win32::UI::Shell::SHLoadIndirectString;
+
+        if !name.starts_with('@') {
+            return name.to_string();
+        }
+        let mut buffer = [0u16; 256];
+        match unsafe { SHLoadIndirectString(&HSTRING::from(name), &mut buffer, None) } {
+            Ok(()) => String::from_utf16_lossy(&buffer[..buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len())]),
+            Err(_) => name.to_string(),
+        }
+    }
+
+    pub fn list() -> Result<SoundSchemes, String> {
+        let schemes = RegKey::predef(HKEY_CURRENT_USER).open_subkey(SCHEMES).map_err(|e| e.to_string())?;
+        let current: String = schemes.get_value("").unwrap_or_default();
+        let names = schemes.open_subkey("Names").map_err(|e| e.to_string())?;
+        let schemes = names.enum_keys().flatten()
+            .map(|id| {
+                let name = names.open_subkey(&id)
+                    .and_then(|key| key.get_value::<String, _>(""))
+                    .map(|name| resolve_name(&name))
+                    .unwrap_or_else(|_| id.clone());
+                SoundScheme { id, name }
+            })
+            .collect();
+        Ok(SoundSchemes { current, schemes })
+    }
+
+    /// すべてのイベントの `.Current` をテーマの音に置き換えます。テーマに音がないイベントは無音にします。
+    pub fn set(id: &str) -> Result<(), String> {
+        let root = RegKey::predef(HKEY_CURRENT_USER);
+        let schemes = root.open_subkey_with_flags(SCHEMES, KEY_READ | KEY_WRITE).map_err(|e| e.to_string())?;
+        if id != NO_SOUNDS && schemes.open_subkey(format!("Names\\{}", id)).is_err() {
+            return Err(i18n::format(Text::UnknownSoundScheme, id));
+        }
+        let apps = schemes.open_subkey("Apps").map_err(|e| e.to_string())?;
+        for app in apps.enum_keys().flatten() {
+            let Ok(app_key) = apps.open_subkey(&app) else { continue };
+            for event in app_key.enum_keys().flatten() {
+                let Ok(event_key) = app_key.open_subkey_with_flags(&event, KEY_READ | KEY_WRITE) else { continue };
+                let sound: String = if id == NO_SOUNDS {
+                    String::new()
+                } else {
+                    event_key.open_subkey(id).and_then(|key| key.get_value("")).unwrap_or_default()
+                };
+                let (current, _) = event_key.create_subkey(".Current").map_err(|e| e.to_string())?;
+                current.set_value("", &sound).map_err(|e| e.to_string())?;
+            }
+        }
+        schemes.set_value("", &id).map_err(|e| e.to_string())
+    }
+
+    /// "SystemAsterisk" のようなイベント名の音を、いまのテーマで鳴らします。
+    pub fn play(event: &str) -> Result<(), String> {
+        use windows::core::HSTRING;
+        use windows::Win32::Media::Audio::{PlaySoundW, SND_ALIAS, SND_ASYNC, SND_NODEFAULT};
+
+        // 存在しない名前で既定の警告音が鳴らないよう SND_NODEFAULT を付ける
+        let played = unsafe { PlaySoundW(&HSTRING::from(event), None, SND_ALIAS | SND_ASYNC | SND_NODEFAULT) };
+        if played.as_bool() { Ok(()) } else { Err(i18n::format(Text::UnknownSystemSound, event)) }
+    }
+}
+
+#[cfg(windows)]
+#[tauri::command]
+#[specta::specta]
+pub fn get_sound_schemes() -> Result<SoundSchemes, String> {
+    imp::list()
+}
+
+/// テーマを切り替えます。`".None"` で効果音をすべて消します。
+#[cfg(windows)]
+#[tauri::command]
+#[specta::specta]
+pub fn set_sound_scheme(id: String) -> Result<(), String> {
+    imp::set(&id)
+}
+
+#[cfg(windows)]
+#[tauri::command]
+#[specta::specta]
+pub fn play_system_sound(event: String) -> Result<(), String> {
+    imp::play(&event)
+}
+
+#[cfg(not(windows))]
+#[tauri::command]
+#[specta::specta]
+pub fn get_sound_schemes() -> Result<SoundSchemes, String> {
+    Err(crate::i18n::text(crate::i18n::Text::SoundSchemesUnsupported).to_string())
+}
+
+#[cfg(not(windows))]
+#[tauri::command]
+#[specta::specta]
+pub fn set_sound_scheme(_id: String) -> Result<(), String> {
+    Err(crate::i18n::text(crate::i18n::Text::SoundSchemesUnsupported).to_string())
+}
+
+#[cfg(not(windows))]
+#[tauri::command]
+#[specta::specta]
+pub fn play_system_sound(_event: String) -> Result<(), String> {
+    Err(crate::i18n::text(crate::i18n::Text::SoundSchemesUnsupported).to_string())
+}
@@ -0,0 +1,49 @@
+//! このアプリでは変えられない設定のために、Windows のサウンド設定の該当ページを直接開きます。
+
+use tauri::AppHandle;
+
+/// 開くページ。前半は設定アプリ、後半はコントロールパネルのサウンド (mmsys.cpl) のタブ
+#[derive(Debug, Clone, Copy, serde::Deserialize, specta::Type)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub enum SoundSettingsPage {
+    Sound,
+    /// デバイスの有効・無効の管理
+    SoundDevices,
+    /// アプリごとの音量と出力先
+    AppVolume,
+    Playback,
+    Recording,
+    Sounds,
+    Communications,
+}
+
+#[cfg(windows)]
+#[tauri::command]
+#[specta::specta]
+pub fn open_sound_settings(app: AppHandle, page: SoundSettingsPage) -> Result<(), String> {
+    use tauri_plugin_opener::OpenerExt;
+
+    let open = |uri: &str| app.opener().open_url(uri, None::<&str>).map_err(|e| e.to_string());
+    let tab = match page {
+        SoundSettingsPage::Sound => return open("ms-settings:sound"),
+        SoundSettingsPage::SoundDevices => return open("ms-settings:sound-devices"),
+        SoundSettingsPage::AppVolume => return open("ms-settings:apps-volume"),
+        SoundSettingsPage::Playback => 0,
+        SoundSettingsPage::Recording => 1,
+        SoundSettingsPage::Sounds => 2,
+        SoundSettingsPage::Communications => 3,
+    };
+    // タブは "mmsys.cpl,,<番号>" で選ぶ
+    std::process::Command::new("control.exe")
+        .arg(format!("mmsys.cpl,,{}", tab))
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(windows))]
+#[tauri::command]
+#[specta::specta]
+pub fn open_sound_settings(_app: AppHandle, _page: SoundSettingsPage) -> Result<(), String> {
+    Err(crate::i18n::text(crate::i18n::Text::SoundSettingsUnsupported).to_string())
+}
@@ -0,0 +1,53 @@
+//! オーディオの状態の短い要約。トレイのツールチップや、動いているかだけ知りたい連携先が、
+//! アイコンを含む一覧を受け取らずに済むようにします。
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::audio::{AudioDeviceInfo, AudioWorker, SessionState};
+use crate::i18n;
+
+/// いちばん大きな音を出しているアプリ
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct LoudestApp {
+    pub pid: u32,
+    pub process_name: String,
+    pub peak: f32,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct AudioSummary {
+    pub session_count: usize,
+    /// 再生中 (`Active`) のセッションの数
+    pub active_sessions: usize,
+    /// マスター出力かいずれかのセッションがミュートされている
+    pub any_muted: bool,
+    /// 音を出しているセッションがなければ `None`
+    pub loudest: Option<LoudestApp>,
+    pub default_device: Option<AudioDeviceInfo>,
+}
+
+pub async fn summarize(worker: &AudioWorker) -> Result<AudioSummary, String> {
+    worker.call(|m| {
+        let sessions = m.get_sessions_lite().map_err(i18n::audio_error)?;
+        let devices = m.get_audio_devices().map_err(i18n::audio_error)?;
+        let master_muted = m.get_master_volume().is_ok_and(|master| master.is_muted);
+        let loudest = sessions.iter()
+            .filter(|s| s.peak_level > 0.0)
+            .max_by(|a, b| a.peak_level.total_cmp(&b.peak_level))
+            .map(|s| LoudestApp { pid: s.process_id, process_name: s.process_name.clone(), peak: s.peak_level });
+        Ok(AudioSummary {
+            session_count: sessions.len(),
+            active_sessions: sessions.iter().filter(|s| s.state == SessionState::Active).count(),
+            any_muted: master_muted || sessions.iter().any(|s| s.is_muted),
+            loudest,
+            default_device: devices.into_iter().find(|d| d.is_default),
+        })
+    }).await
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn get_audio_summary(worker: State<'_, AudioWorker>) -> Result<AudioSummary, String> {
+    summarize(&worker).await
+}
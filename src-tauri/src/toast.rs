@@ -0,0 +1,124 @@
+//! 既定の出力デバイスが変わったときのトースト通知。
+//! このアプリからの切り替えでも、OS のサウンド設定など外部からの切り替えでも通知します。
+
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Manager};
+use tokio::sync::broadcast;
+
+use crate::audio::{AudioWorker, MixerEvent};
+use crate::events::EventBus;
+use crate::i18n::{self, Text};
+use crate::settings::{DeviceChangeToast, SettingsManager};
+
+/// 既定のデバイス (ID, 名前)
+async fn default_device(app: &AppHandle) -> Option<(String, String)> {
+    let worker = app.state::<AudioWorker>();
+    let devices = worker.call(|m| m.get_audio_devices().map_err(i18n::audio_error)).await.ok()?;
+    devices.into_iter().find(|d| d.is_default).map(|d| (d.id, d.name))
+}
+
+/// デバイスの変化を購読し、既定のデバイスが変わっていたら通知します。
+pub fn spawn_device_change_notifier(app: AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        let mut current = default_device(&app).await;
+        loop {
+            match rx.recv().await {
+                Ok(MixerEvent::DevicesChanged | MixerEvent::BackendReconnected) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+            let Some(next) = default_device(&app).await else { continue };
+            if current.as_ref().is_some_and(|(id, _)| *id == next.0) {
+                continue;
+            }
+            let previous = current.replace(next.clone());
+            // 起動直後に既定のデバイスが取れなかった場合は、変化とは見なさない
+            let Some((previous_id, _)) = previous else { continue };
+            let mode = {
+                let settings = app.state::<Mutex<SettingsManager>>();
+                let mode = settings.lock().map(|s| s.settings().device_change_toast);
+                mode.unwrap_or_default()
+            };
+            let undo = match mode {
+                DeviceChangeToast::Off => continue,
+                DeviceChangeToast::On => None,
+                DeviceChangeToast::WithUndo => Some(previous_id),
+            };
+            show(&app, &i18n::format(Text::NowPlayingThrough, &next.1), undo);
+        }
+    });
+}
+
+/// 「元に戻す」が押されたら `undo_device_id` を既定のデバイスに戻します。
+#[cfg(windows)]
+fn show(app: &AppHandle, message: &str, undo_device_id: Option<String>) {
+    let app = app.clone();
+    let message = message.to_string();
+    // WinRT の呼び出しは COM を初期化済みのメインスレッドで行う
+    let _ = app.clone().run_on_main_thread(move || {
+        if let Err(e) = win::show(&app, &message, undo_device_id) {
+            eprintln!("Failed to show toast: {}", e);
+        }
+    });
+}
+
+#[cfg(not(windows))]
+fn show(_app: &AppHandle, _message: &str, _undo_device_id: Option<String>) {}
+
+#[cfg(windows)]
+mod win {
+    use tauri::AppHandle;
+    use windows::core::{IInspectable, Interface, HSTRING};
+    use windows::Data::Xml::Dom::XmlDocument;
+    use windows::Foundation::TypedEventHandler;
+    use windows::UI::Notifications::{ToastActivatedEventArgs, ToastNotification, ToastNotificationManager};
+
+    use crate::default_device;
+    use crate::i18n::{self, Text};
+
+    const UNDO_ARGUMENT: &str = "undo";
+
+    pub fn show(app: &AppHandle, message: &str, undo_device_id: Option<String>) -> windows::core::Result<()> {
+        let actions = if undo_device_id.is_some() {
+            format!(r#"<actions><action content="{}" arguments="{}"/></actions>"#, escape(i18n::text(Text::Undo)), UNDO_ARGUMENT)
+        } else {
+            String::new()
+        };
+        let xml = format!(
+            r#"<toast><visual><binding template="ToastGeneric"><text>{}</text></binding></visual>{}</toast>"#,
+            escape(message),
+            actions,
+        );
+        let document = XmlDocument::new()?;
+        document.LoadXml(&HSTRING::from(xml))?;
+        let toast = ToastNotification::CreateToastNotification(&document)?;
+
+        if let Some(device_id) = undo_device_id {
+            let app = app.clone();
+            toast.Activated(&TypedEventHandler::<ToastNotification, IInspectable>::new(move |_, args| {
+                let Some(args) = args.as_ref() else { return Ok(()) };
+                if args.cast::<ToastActivatedEventArgs>()?.Arguments()? == UNDO_ARGUMENT {
+                    let app = app.clone();
+                    let device_id = device_id.clone();
+                    tauri::async_runtime::spawn(async move {
+                        let _ = default_device::switch(&app, device_id).await;
+                    });
+                }
+                Ok(())
+            }))?;
+        }
+
+        // インストーラーがスタートメニューのショートカットに登録した AppUserModelID で送る
+        let notifier = ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(&app.config().identifier))?;
+        notifier.Show(&toast)
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+}
@@ -0,0 +1,239 @@
+//! トレイアイコンとその右クリックメニュー。
+//! メニューには出力デバイスの一覧を並べ、既定のデバイスにチェックを付けます。デバイスが変わるたびに作り直します。
+//! マスター出力かピン留めしたアプリがミュートされている間は、アイコンに赤いバッジを重ねます。
+//! ツールチップには再生中のアプリの数と既定の出力デバイスを添えます。
+
+use std::sync::Mutex;
+
+use tauri::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::image::Image;
+use tauri::{App, AppHandle, Emitter, Manager, Wry};
+use tokio::sync::broadcast;
+
+use crate::audio::{AudioDeviceInfo, AudioWorker, MixerEvent};
+use crate::default_device;
+use crate::events::EventBus;
+use crate::i18n::{self, Text};
+use crate::settings::{normalize_exe_name, SettingsManager};
+use crate::summary;
+use crate::updater;
+use crate::window::WindowManager;
+
+const TRAY_ID: &str = "main";
+/// デバイスを表すメニュー項目の ID の接頭辞。続けてデバイス ID を置く
+const DEVICE_ITEM_PREFIX: &str = "device:";
+
+const TOOLTIP: &str = "Antigravity Pulse";
+
+/// バッジの色 (RGBA)
+const BADGE_COLOR: [u8; 4] = [0xe5, 0x39, 0x35, 0xff];
+
+pub fn create(app: &App) -> tauri::Result<()> {
+    TrayIconBuilder::with_id(TRAY_ID)
+        .icon(app.default_window_icon().unwrap().clone())
+        .tooltip(TOOLTIP)
+        .menu(&build_menu(app.handle(), &[])?)
+        .show_menu_on_left_click(false)
+        .on_menu_event(on_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click { position, button, button_state, .. } = event {
+                if button == MouseButton::Left && button_state == MouseButtonState::Up {
+                    toggle_at(tray.app_handle(), (position.x as i32, position.y as i32));
+                }
+            }
+        })
+        .build(app)?;
+    Ok(())
+}
+
+fn toggle_at(app: &AppHandle, position: (i32, i32)) {
+    let wm_state = app.state::<Mutex<WindowManager>>();
+    let mut wm = wm_state.lock().unwrap();
+    wm.toggle(app, position);
+}
+
+/// トレイアイコンをクリックしたときと同じ位置にミキサーを開閉します。
+/// アイコンの位置が分からない場合はカーソルの位置を使います。
+pub fn toggle_flyout(app: &AppHandle) {
+    let rect = app.tray_by_id(TRAY_ID).and_then(|tray| tray.rect().ok().flatten());
+    let position = match rect {
+        Some(rect) => {
+            let position = rect.position.to_physical::<i32>(1.0);
+            let size = rect.size.to_physical::<i32>(1.0);
+            (position.x + size.width / 2, position.y + size.height / 2)
+        }
+        None => app.cursor_position()
+            .map(|p| (p.x as i32, p.y as i32))
+            .unwrap_or((0, 0)),
+    };
+    toggle_at(app, position);
+}
+
+fn build_menu(app: &AppHandle, devices: &[AudioDeviceInfo]) -> tauri::Result<Menu<Wry>> {
+    let menu = Menu::new(app)?;
+    for device in devices {
+        let id = format!("{}{}", DEVICE_ITEM_PREFIX, device.id);
+        // 何もつながっていない端子には切り替えられないようにする
+        let enabled = device.jack_connected != Some(false);
+        menu.append(&CheckMenuItem::with_id(app, id, &device.name, enabled, device.is_default, None::<&str>)?)?;
+    }
+    if !devices.is_empty() {
+        menu.append(&PredefinedMenuItem::separator(app)?)?;
+    }
+    menu.append(&MenuItem::with_id(app, "open-mixer", i18n::text(Text::OpenMixerWindow), true, None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(app, "check-updates", i18n::text(Text::CheckForUpdates), updater::enabled(app), None::<&str>)?)?;
+    Ok(menu)
+}
+
+/// デバイスの一覧を取り直してメニューを作り直します。
+pub async fn refresh_menu(app: &AppHandle) {
+    let worker = app.state::<AudioWorker>();
+    let devices = worker.call(|m| m.get_audio_devices().map_err(i18n::audio_error)).await.unwrap_or_default();
+    let Some(tray) = app.tray_by_id(TRAY_ID) else { return };
+    if let Ok(menu) = build_menu(app, &devices) {
+        let _ = tray.set_menu(Some(menu));
+    }
+}
+
+/// デバイスの変化を購読し、そのたびにメニューを作り直します。起動直後にも一度作ります。
+pub fn spawn_menu_updater(app: AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        refresh_menu(&app).await;
+        loop {
+            match rx.recv().await {
+                Ok(MixerEvent::DevicesChanged | MixerEvent::BackendReconnected) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                    refresh_menu(&app).await;
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// ミュートに気付くべき状態なら、その説明を返します。
+async fn mute_attention(app: &AppHandle) -> Option<String> {
+    let worker = app.state::<AudioWorker>();
+    let master = worker.call(|m| m.get_master_volume().map_err(i18n::audio_error)).await;
+    if master.is_ok_and(|m| m.is_muted) {
+        return Some(i18n::text(Text::OutputMuted).to_string());
+    }
+    let pinned = {
+        let settings = app.state::<Mutex<SettingsManager>>();
+        let settings = settings.lock().ok()?;
+        settings.settings().pinned_apps.clone()
+    };
+    if pinned.is_empty() {
+        return None;
+    }
+    let sessions = worker.call(|m| m.get_sessions_lite().map_err(i18n::audio_error)).await.ok()?;
+    let mut muted: Vec<String> = sessions.into_iter()
+        .filter(|s| s.is_muted && pinned.contains(&normalize_exe_name(&s.exe_name)))
+        .map(|s| s.process_name)
+        .collect();
+    muted.sort();
+    muted.dedup();
+    (!muted.is_empty()).then(|| i18n::format(Text::MutedApps, &muted.join(", ")))
+}
+
+/// 右下に赤い丸を重ねたアイコンを作ります。
+fn badged_icon(icon: &Image<'_>) -> Image<'static> {
+    let (width, height) = (icon.width(), icon.height());
+    let mut rgba = icon.rgba().to_vec();
+    let radius = width.min(height) as f32 / 4.0;
+    let (cx, cy) = (width as f32 - radius, height as f32 - radius);
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            if dx * dx + dy * dy <= radius * radius {
+                let i = ((y * width + x) * 4) as usize;
+                rgba[i..i + 4].copy_from_slice(&BADGE_COLOR);
+            }
+        }
+    }
+    Image::new_owned(rgba, width, height)
+}
+
+/// 再生中のアプリの数と既定の出力デバイスを添えたツールチップ
+async fn tooltip(app: &AppHandle) -> String {
+    let Ok(summary) = summary::summarize(&app.state::<AudioWorker>()).await else { return TOOLTIP.to_string() };
+    let mut tooltip = format!("{}\n{}", TOOLTIP, i18n::format(Text::PlayingCount, &summary.active_sessions.to_string()));
+    if let Some(device) = summary.default_device {
+        tooltip.push_str(&format!(" · {}", device.name));
+    }
+    tooltip
+}
+
+/// ミュートの状態を調べ直し、アイコンとツールチップを更新します。
+pub async fn refresh_badge(app: &AppHandle) {
+    let attention = mute_attention(app).await;
+    let tooltip = tooltip(app).await;
+    let (Some(tray), Some(icon)) = (app.tray_by_id(TRAY_ID), app.default_window_icon()) else { return };
+    match &attention {
+        Some(reason) => {
+            let _ = tray.set_icon(Some(badged_icon(icon)));
+            let _ = tray.set_tooltip(Some(format!("{}\n{}", tooltip, reason)));
+        }
+        None => {
+            let _ = tray.set_icon(Some(icon.clone()));
+            let _ = tray.set_tooltip(Some(tooltip));
+        }
+    }
+}
+
+/// ミュートに関わるイベントを購読し、そのたびにバッジを付け直します。
+pub fn spawn_badge_updater(app: AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        refresh_badge(&app).await;
+        loop {
+            match rx.recv().await {
+                Ok(MixerEvent::MasterVolumeChanged(..)
+                    | MixerEvent::VolumeChanged { .. }
+                    | MixerEvent::SessionsChanged(_)
+                    | MixerEvent::DevicesChanged
+                    | MixerEvent::BackendReconnected)
+                | Err(broadcast::error::RecvError::Lagged(_)) => {
+                    refresh_badge(&app).await;
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    let id = event.id().as_ref();
+    if let Some(device_id) = id.strip_prefix(DEVICE_ITEM_PREFIX) {
+        let app = app.clone();
+        let device_id = device_id.to_string();
+        tauri::async_runtime::spawn(async move {
+            if default_device::switch(&app, device_id).await.is_err() {
+                // クリックでチェックの付いた項目を元に戻す
+                refresh_menu(&app).await;
+            }
+        });
+    } else if id == "open-mixer" {
+        // Windows ではイベントループのスレッドでウィンドウを作ると止まることがあるので、別のタスクで作る
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let wm_state = app.state::<Mutex<WindowManager>>();
+            let mut wm = wm_state.lock().unwrap();
+            if let Err(e) = wm.open_mixer(&app) {
+                eprintln!("Failed to open mixer window: {}", e);
+            }
+        });
+    } else if id == "check-updates" {
+        // 結果を表示するためにミキサーを開き、確認はフロントエンドに任せる
+        let visible = app.get_webview_window("main")
+            .and_then(|w| w.is_visible().ok())
+            .unwrap_or(false);
+        if !visible {
+            toggle_flyout(app);
+        }
+        let _ = app.emit("check-for-updates", ());
+    }
+}
@@ -0,0 +1,226 @@
+//! ミキサーから行った操作（音量、ミュート、出力先、既定のデバイス）の取り消しとやり直し。
+//! 操作の前後の値を覚えておき、`undo` / `redo` で前の値・後の値を設定し直します。
+//! スライダーのドラッグのように同じ対象を続けて変えた操作は、1 回の操作としてまとめます。
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio::{AudioBackend, AudioError, AudioSessionInfo, AudioWorker};
+use crate::i18n::{self, Text};
+use crate::{default_device, elevated};
+
+/// 覚えておく操作の数。超えたら古いものから捨てる
+const MAX_OPERATIONS: usize = 50;
+/// 同じ対象への変更をこの間隔以内に続けたら、1 回の操作にまとめる
+const COALESCE_WINDOW: Duration = Duration::from_secs(1);
+
+/// 操作の対象
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// `instance_id` を省略するとプロセスのすべてのセッション
+    SessionVolume { pid: u32, instance_id: Option<String> },
+    SessionMute { pid: u32, instance_id: Option<String> },
+    MasterVolume,
+    MasterMute,
+    Routing { pid: u32 },
+    DefaultDevice,
+}
+
+/// 対象の値。`Target` の種類ごとに決まった 1 つを使う
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Volume(f32),
+    Muted(bool),
+    Device(String),
+    /// プロセスに永続化した出力先。`None` なら既定のデバイスに従う
+    Route(Option<String>),
+}
+
+#[derive(Debug, Clone)]
+struct Operation {
+    target: Target,
+    before: Value,
+    after: Value,
+}
+
+#[derive(Debug, Default)]
+struct History {
+    undo: VecDeque<Operation>,
+    redo: Vec<Operation>,
+    /// 最後に記録した時刻。取り消し・やり直しの後は、次の操作を前の操作とまとめないよう `None` にする
+    last_recorded: Option<Instant>,
+}
+
+/// 取り消し・やり直しできる操作。`app.manage` で登録します。
+#[derive(Debug, Default)]
+pub struct UndoStack(Mutex<History>);
+
+#[derive(Debug, Clone, serde::Serialize, specta::Type)]
+pub struct UndoStatus {
+    pub can_undo: bool,
+    pub can_redo: bool,
+}
+
+fn status(app: &AppHandle) -> UndoStatus {
+    app.state::<UndoStack>().0.lock()
+        .map(|h| UndoStatus { can_undo: !h.undo.is_empty(), can_redo: !h.redo.is_empty() })
+        .unwrap_or(UndoStatus { can_undo: false, can_redo: false })
+}
+
+fn notify(app: &AppHandle) {
+    let _ = app.emit("undo-changed", status(app));
+}
+
+fn session(m: &mut dyn AudioBackend, pid: u32, instance_id: Option<&str>) -> Result<AudioSessionInfo, AudioError> {
+    match instance_id {
+        Some(id) => m.get_sessions_lite()?.into_iter()
+            .find(|s| s.instance_id == id)
+            .ok_or_else(|| AudioError::InstanceNotFound(id.to_string())),
+        None => m.get_session(pid),
+    }
+}
+
+fn read_value(m: &mut dyn AudioBackend, target: &Target) -> Result<Value, AudioError> {
+    Ok(match target {
+        Target::SessionVolume { pid, instance_id } => Value::Volume(session(m, *pid, instance_id.as_deref())?.volume),
+        Target::SessionMute { pid, instance_id } => Value::Muted(session(m, *pid, instance_id.as_deref())?.is_muted),
+        Target::MasterVolume => Value::Volume(m.get_master_volume()?.volume),
+        Target::MasterMute => Value::Muted(m.get_master_volume()?.is_muted),
+        Target::Routing { pid } => Value::Route(m.get_session(*pid)?.persisted_device_id),
+        Target::DefaultDevice => Value::Device(m.get_master_volume()?.device_id),
+    })
+}
+
+/// 対象のいまの値を読み取ります。
+async fn read(app: &AppHandle, target: &Target) -> Result<Value, String> {
+    let target = target.clone();
+    app.state::<AudioWorker>().call(move |m| read_value(m, &target).map_err(i18n::audio_error)).await
+}
+
+/// 対象に値を設定します。管理者として動いているアプリなどで失敗したら、昇格ヘルパーが動いていればそちらで設定し直します。
+async fn write(app: &AppHandle, target: &Target, value: Value) -> Result<(), String> {
+    let worker = app.state::<AudioWorker>();
+    match (target.clone(), value) {
+        (Target::SessionVolume { instance_id: Some(id), .. }, Value::Volume(volume)) => worker.set_instance_volume(id, volume).await,
+        (Target::SessionVolume { pid, instance_id: None }, Value::Volume(volume)) => match worker.set_session_volume(pid, volume).await {
+            Err(e) => elevated::set_session_volume(app, pid, volume).await.unwrap_or(Err(e)),
+            result => result,
+        },
+        (Target::SessionMute { pid, instance_id }, Value::Muted(mute)) => {
+            let by_pid = instance_id.is_none();
+            match worker.call(move |m| match instance_id {
+                Some(id) => m.set_instance_mute(&id, mute),
+                None => m.set_session_mute(pid, mute),
+            }.map_err(i18n::audio_error)).await {
+                Err(e) if by_pid => elevated::set_session_mute(app, pid, mute).await.unwrap_or(Err(e)),
+                result => result,
+            }
+        }
+        (Target::MasterVolume, Value::Volume(volume)) => worker.call(move |m| m.set_master_volume(volume).map_err(i18n::audio_error)).await,
+        (Target::MasterMute, Value::Muted(mute)) => worker.call(move |m| m.set_master_mute(mute).map_err(i18n::audio_error)).await,
+        (Target::Routing { pid }, Value::Route(route)) => worker.call(move |m| match route {
+            Some(device_id) => m.set_audio_routing(pid, &device_id),
+            None => m.clear_audio_routing(pid),
+        }.map_err(i18n::audio_error)).await,
+        (Target::DefaultDevice, Value::Device(device_id)) => default_device::switch(app, device_id).await,
+        _ => Err(i18n::text(Text::CannotUndo).to_string()),
+    }
+}
+
+fn record(app: &AppHandle, target: Target, before: Value, after: Value) {
+    if before == after {
+        return;
+    }
+    let stack = app.state::<UndoStack>();
+    let Ok(mut history) = stack.0.lock() else { return };
+    let now = Instant::now();
+    let recent = history.last_recorded.is_some_and(|at| now.duration_since(at) < COALESCE_WINDOW);
+    history.redo.clear();
+    history.last_recorded = Some(now);
+    match history.undo.back_mut() {
+        Some(last) if recent && last.target == target => last.after = after,
+        _ => {
+            history.undo.push_back(Operation { target, before, after });
+            if history.undo.len() > MAX_OPERATIONS {
+                history.undo.pop_front();
+            }
+        }
+    }
+    drop(history);
+    notify(app);
+}
+
+/// `operation` の前後で対象の値を読み取り、変わっていれば取り消せる操作として記録します。
+/// 前の値を読み取れない場合（セッションが見つからないなど）は記録せずに操作だけ行います。
+/// 変えた後の値が分かっていれば `change` を使ってください。
+pub async fn track<F>(app: &AppHandle, target: Target, operation: F) -> Result<(), String>
+where
+    F: Future<Output = Result<(), String>>,
+{
+    let before = read(app, &target).await.ok();
+    operation.await?;
+    if let Some(before) = before {
+        if let Ok(after) = read(app, &target).await {
+            record(app, target, before, after);
+        }
+    }
+    Ok(())
+}
+
+/// 対象に値を設定し、取り消せる操作として記録します。
+pub async fn change(app: &AppHandle, target: Target, value: Value) -> Result<(), String> {
+    // セッションの値は通知で更新されるキャッシュから読むため、設定した直後は読み直さず設定した値を記録する
+    let before = read(app, &target).await.ok();
+    write(app, &target, value.clone()).await?;
+    if let Some(before) = before {
+        record(app, target, before, value);
+    }
+    Ok(())
+}
+
+fn take(app: &AppHandle, redo: bool) -> Result<Option<Operation>, String> {
+    let stack = app.state::<UndoStack>();
+    let mut history = stack.0.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    history.last_recorded = None;
+    Ok(if redo { history.redo.pop() } else { history.undo.pop_back() })
+}
+
+/// 直前の操作を取り消します。取り消せなかった操作（アプリが終了したなど）は捨てます。
+#[tauri::command]
+#[specta::specta]
+pub async fn undo(app: AppHandle) -> Result<UndoStatus, String> {
+    if let Some(operation) = take(&app, false)? {
+        let result = write(&app, &operation.target, operation.before.clone()).await;
+        if result.is_ok() {
+            app.state::<UndoStack>().0.lock().map_err(|_| i18n::text(Text::LockFailed))?.redo.push(operation);
+        }
+        notify(&app);
+        result?;
+    }
+    Ok(status(&app))
+}
+
+/// 取り消した操作をやり直します。
+#[tauri::command]
+#[specta::specta]
+pub async fn redo(app: AppHandle) -> Result<UndoStatus, String> {
+    if let Some(operation) = take(&app, true)? {
+        let result = write(&app, &operation.target, operation.after.clone()).await;
+        if result.is_ok() {
+            app.state::<UndoStack>().0.lock().map_err(|_| i18n::text(Text::LockFailed))?.undo.push_back(operation);
+        }
+        notify(&app);
+        result?;
+    }
+    Ok(status(&app))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_undo_status(app: AppHandle) -> UndoStatus {
+    status(&app)
+}
@@ -0,0 +1,101 @@
+//! 既定の出力デバイスが外れたときの保護。ヘッドホンを抜いたり Bluetooth が切れたりすると、Windows は別のデバイス
+//! （多くはノート PC のスピーカー）に切り替えるので、設定で選べば再生中のメディアを一時停止し、
+//! 切り替わった先のデバイスを少しの間ミュートして、音がそのまま流れ出さないようにします。
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::broadcast;
+
+use crate::audio::{AudioDeviceInfo, AudioWorker, MediaCommand, MixerEvent, PlaybackStatus};
+use crate::events::EventBus;
+use crate::i18n::{self, Text};
+use crate::settings::SettingsManager;
+
+/// 切り替わった先のデバイスをミュートしておく時間。一時停止が効くまでの音を抑える
+const FALLBACK_MUTE: Duration = Duration::from_secs(3);
+
+fn enabled(app: &AppHandle) -> bool {
+    app.state::<Mutex<SettingsManager>>().lock().is_ok_and(|s| s.settings().pause_on_unplug)
+}
+
+async fn devices(app: &AppHandle) -> Option<Vec<AudioDeviceInfo>> {
+    app.state::<AudioWorker>().call(|m| m.get_audio_devices().map_err(i18n::audio_error)).await.ok()
+}
+
+/// 一覧から消えたか、端子から抜かれた
+fn is_gone(devices: &[AudioDeviceInfo], device_id: &str) -> bool {
+    devices.iter().find(|d| d.id == device_id).is_none_or(|d| d.jack_connected == Some(false))
+}
+
+/// 切り替わった先のデバイスをミュートし、再生中のメディアを一時停止して、しばらくしたらミュートを戻します。
+async fn protect(app: &AppHandle, fallback_id: String) {
+    let worker = app.state::<AudioWorker>();
+    let target = fallback_id.clone();
+    // 一時停止が効くまでの間も鳴らないよう、先にミュートする。元からミュートなら後で戻さない
+    let muted = worker.call(move |m| {
+        if m.get_device_volume(&target).map_err(i18n::audio_error)?.is_muted {
+            return Ok(false);
+        }
+        m.set_device_mute(&target, true).map_err(i18n::audio_error)?;
+        Ok(true)
+    }).await.unwrap_or(false);
+    let paused = worker.call(|m| {
+        let sessions = m.get_sessions_lite().map_err(i18n::audio_error)?;
+        let mut pids: Vec<u32> = sessions.iter()
+            .filter(|s| s.now_playing.as_ref().is_some_and(|n| n.status == PlaybackStatus::Playing))
+            .map(|s| s.process_id)
+            .collect();
+        pids.sort_unstable();
+        pids.dedup();
+        for pid in pids {
+            if let Err(e) = m.send_media_command(pid, MediaCommand::Pause) {
+                eprintln!("Failed to pause media in {}: {}", pid, e);
+            }
+        }
+        Ok(())
+    }).await;
+    if let Err(e) = paused {
+        eprintln!("Failed to pause media: {}", e);
+    }
+    if muted {
+        tokio::time::sleep(FALLBACK_MUTE).await;
+        let _ = worker.call(move |m| m.set_device_mute(&fallback_id, false).map_err(i18n::audio_error)).await;
+    }
+}
+
+/// デバイスの変化を購読し、既定のデバイスが外れて別のデバイスに切り替わったら保護します。
+pub fn spawn_guard(app: AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        let mut current = devices(&app).await.and_then(|d| d.into_iter().find(|d| d.is_default)).map(|d| d.id);
+        loop {
+            match rx.recv().await {
+                Ok(MixerEvent::DevicesChanged | MixerEvent::BackendReconnected) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+            let Some(devices) = devices(&app).await else { continue };
+            let Some(next) = devices.iter().find(|d| d.is_default).map(|d| d.id.clone()) else { continue };
+            let Some(previous) = current.replace(next.clone()) else { continue };
+            if previous != next && is_gone(&devices, &previous) && enabled(&app) {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move { protect(&app, next).await });
+            }
+        }
+    });
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_pause_on_unplug(settings: State<'_, Mutex<SettingsManager>>) -> Result<bool, String> {
+    let settings = settings.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(settings.settings().pause_on_unplug)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_pause_on_unplug(settings: State<'_, Mutex<SettingsManager>>, enabled: bool) -> Result<(), String> {
+    settings.lock().map_err(|_| i18n::text(Text::LockFailed))?.set_pause_on_unplug(enabled)
+}
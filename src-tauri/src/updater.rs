@@ -0,0 +1,78 @@
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::i18n::{self, Text};
+
+/// `check_for_update` で見つかり、まだインストールしていない更新
+#[derive(Default)]
+pub struct PendingUpdate(Mutex<Option<Update>>);
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct UpdateProgress {
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+/// 更新の署名を検証する公開鍵が `tauri.conf.json` に設定されているか。
+/// 設定されるまでは更新を確認しません。
+pub fn enabled(app: &AppHandle) -> bool {
+    app.config().plugins.0.get("updater")
+        .and_then(|updater| updater.get("pubkey"))
+        .and_then(|pubkey| pubkey.as_str())
+        .is_some_and(|pubkey| !pubkey.is_empty())
+}
+
+/// 新しいリリースを確認します。見つかった更新は `install_update` のために保持します。
+#[tauri::command]
+#[specta::specta]
+pub async fn check_for_update(app: AppHandle, pending: State<'_, PendingUpdate>) -> Result<Option<UpdateInfo>, String> {
+    if !enabled(&app) {
+        return Err(i18n::text(Text::UpdaterDisabled).to_string());
+    }
+    let update = app.updater().map_err(|e| e.to_string())?
+        .check().await
+        .map_err(|e| e.to_string())?;
+    let info = update.as_ref().map(|u| UpdateInfo {
+        version: u.version.clone(),
+        current_version: u.current_version.clone(),
+        notes: u.body.clone(),
+        date: u.date.map(|d| d.to_string()),
+    });
+    *pending.0.lock().map_err(|_| i18n::text(Text::LockFailed))? = update;
+    Ok(info)
+}
+
+/// 保持している更新をダウンロードしてインストールし、アプリを再起動します。
+/// ダウンロード中は `update-progress` イベントで進捗を通知します。
+#[tauri::command]
+#[specta::specta]
+pub async fn install_update(app: AppHandle, pending: State<'_, PendingUpdate>) -> Result<(), String> {
+    let update = pending.0.lock().map_err(|_| i18n::text(Text::LockFailed))?.take().ok_or(i18n::text(Text::NoUpdateAvailable))?;
+    let mut downloaded: u64 = 0;
+    let progress_handle = app.clone();
+    let finished_handle = app.clone();
+    update
+        .download_and_install(
+            move |chunk, total| {
+                downloaded += chunk as u64;
+                let _ = progress_handle.emit("update-progress", UpdateProgress { downloaded, total });
+            },
+            move || {
+                let _ = finished_handle.emit("update-downloaded", ());
+            },
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    app.restart();
+}
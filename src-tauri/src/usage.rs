@@ -0,0 +1,180 @@
+//! アプリごとの再生時間と平均音量の統計。一定間隔で再生中のセッションを数え、1 時間ごとに集計して
+//! アプリのデータディレクトリに保存します。どのアプリがいつ音を出しているかを見るためのものです。
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::audio::{AudioWorker, SessionState};
+use crate::i18n::{self, Text};
+use crate::settings::normalize_exe_name;
+
+const USAGE_FILE: &str = "usage.json";
+/// 再生中のセッションを数える間隔
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+/// この回数だけ数えるごとに保存する
+const SAVE_EVERY: u32 = 12;
+/// これより古い集計は捨てる
+const RETENTION_HOURS: u64 = 24 * 90;
+
+/// 1 時間・1 アプリ分の集計
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct Bucket {
+    active_secs: u64,
+    /// 音量 × 秒の合計。`active_secs` で割ると平均音量になる
+    volume_secs: f64,
+}
+
+/// 集計する期間。いまから遡ります
+#[derive(Debug, Clone, Copy, Deserialize, specta::Type)]
+pub enum UsageRange {
+    Day,
+    Week,
+    Month,
+}
+
+impl UsageRange {
+    fn hours(self) -> u64 {
+        match self {
+            UsageRange::Day => 24,
+            UsageRange::Week => 24 * 7,
+            UsageRange::Month => 24 * 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct AppUsage {
+    pub exe_name: String,
+    pub active_secs: u64,
+    pub average_volume: f32,
+}
+
+/// 1 時間ごとの全アプリの再生時間
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct HourlyUsage {
+    /// その時間の始まり（UNIX 時刻、秒）
+    pub start: u64,
+    pub active_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct UsageStats {
+    /// 再生時間の長い順
+    pub apps: Vec<AppUsage>,
+    pub hours: Vec<HourlyUsage>,
+}
+
+/// 集計の保存先と中身。`app.manage` で `Mutex` に入れて登録します。
+pub struct UsageStore {
+    path: Option<PathBuf>,
+    /// UNIX 時刻の時間数 → 実行ファイル名（大文字） → 集計
+    hours: BTreeMap<u64, HashMap<String, Bucket>>,
+}
+
+fn current_hour() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() / 3600).unwrap_or(0)
+}
+
+impl UsageStore {
+    pub fn load(app: &AppHandle) -> Self {
+        let path = app.path().app_data_dir().ok().map(|dir| dir.join(USAGE_FILE));
+        let hours = path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path, hours }
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        let oldest = current_hour().saturating_sub(RETENTION_HOURS);
+        self.hours = self.hours.split_off(&oldest);
+        let path = self.path.as_ref().ok_or(i18n::text(Text::SettingsPathUnavailable))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string(&self.hours).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    /// 再生中だったアプリと音量を `secs` 秒分だけ加えます。
+    fn record(&mut self, playing: HashMap<String, f32>, secs: u64) {
+        let hour = self.hours.entry(current_hour()).or_default();
+        for (exe_name, volume) in playing {
+            let bucket = hour.entry(exe_name).or_default();
+            bucket.active_secs += secs;
+            bucket.volume_secs += volume as f64 * secs as f64;
+        }
+    }
+
+    fn stats(&self, range: UsageRange) -> UsageStats {
+        let since = current_hour().saturating_sub(range.hours() - 1);
+        let mut apps: HashMap<&str, Bucket> = HashMap::new();
+        let mut hours = Vec::new();
+        for (hour, buckets) in self.hours.range(since..) {
+            for (exe_name, bucket) in buckets {
+                let total = apps.entry(exe_name).or_default();
+                total.active_secs += bucket.active_secs;
+                total.volume_secs += bucket.volume_secs;
+            }
+            hours.push(HourlyUsage { start: hour * 3600, active_secs: buckets.values().map(|b| b.active_secs).sum() });
+        }
+        let mut apps: Vec<AppUsage> = apps.into_iter()
+            .filter(|(_, b)| b.active_secs > 0)
+            .map(|(exe_name, b)| AppUsage {
+                exe_name: exe_name.to_string(),
+                active_secs: b.active_secs,
+                average_volume: (b.volume_secs / b.active_secs as f64) as f32,
+            })
+            .collect();
+        apps.sort_by(|a, b| b.active_secs.cmp(&a.active_secs).then_with(|| a.exe_name.cmp(&b.exe_name)));
+        UsageStats { apps, hours }
+    }
+}
+
+/// いま音を出しているアプリと、そのセッションの平均音量（ミュート中は 0）
+async fn playing_apps(app: &AppHandle) -> HashMap<String, f32> {
+    let worker = app.state::<AudioWorker>();
+    let sessions = worker.call(|m| m.get_sessions_lite().map_err(i18n::audio_error)).await.unwrap_or_default();
+    let mut volumes: HashMap<String, Vec<f32>> = HashMap::new();
+    for session in sessions.iter().filter(|s| s.state == SessionState::Active) {
+        let volume = if session.is_muted { 0.0 } else { session.volume };
+        volumes.entry(normalize_exe_name(&session.exe_name)).or_default().push(volume);
+    }
+    volumes.into_iter()
+        .map(|(exe_name, v)| (exe_name, v.iter().sum::<f32>() / v.len() as f32))
+        .collect()
+}
+
+/// 再生中のアプリを定期的に数えて集計に加え、ときどき保存します。
+pub fn spawn_recorder(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut samples = 0;
+        loop {
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+            let playing = playing_apps(&app).await;
+            let store = app.state::<Mutex<UsageStore>>();
+            let Ok(mut store) = store.lock() else { continue };
+            store.record(playing, SAMPLE_INTERVAL.as_secs());
+            samples += 1;
+            if samples % SAVE_EVERY == 0 {
+                if let Err(e) = store.save() {
+                    eprintln!("Failed to save usage stats: {}", e);
+                }
+            }
+        }
+    });
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_usage_stats(store: State<'_, Mutex<UsageStore>>, range: UsageRange) -> Result<UsageStats, String> {
+    let store = store.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    Ok(store.stats(range))
+}
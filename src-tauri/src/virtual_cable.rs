@@ -0,0 +1,61 @@
+//! 仮想オーディオケーブル（VB-Cable・VoiceMeeter）を使うための手順をまとめたもの。
+//! 「アプリの出力先をケーブルにし、ケーブルに流れる音をスピーカーでも聴く」には、出力先の切り替えと
+//! 録音側のデバイスの「このデバイスを聴く」の設定が要り、手で行うと取り違えやすいので 1 回の操作にします。
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::audio::{self, AudioWorker, VirtualCable};
+use crate::i18n::{self, Text};
+use crate::undo;
+
+async fn detect(worker: &AudioWorker) -> Result<Vec<VirtualCable>, String> {
+    worker.call(|m| {
+        let playback = m.get_audio_devices().map_err(i18n::audio_error)?;
+        // 入力デバイスを列挙できないバックエンドでは、録音側のないケーブルとして返す
+        let recording = m.get_capture_devices().unwrap_or_default();
+        Ok(audio::virtual_cable::detect(&playback, &recording))
+    }).await
+}
+
+async fn find(worker: &AudioWorker, cable_device_id: &str) -> Result<VirtualCable, String> {
+    detect(worker).await?
+        .into_iter()
+        .find(|c| c.playback_device_id == cable_device_id)
+        .ok_or_else(|| i18n::format(Text::VirtualCableNotFound, cable_device_id))
+}
+
+/// ケーブルの録音側を `monitor_device_id` で聴くようにします。`None` ならやめます。
+async fn monitor(worker: &AudioWorker, cable: &VirtualCable, monitor_device_id: Option<String>) -> Result<(), String> {
+    let recording_device_id = cable.recording_device_id.clone()
+        .ok_or_else(|| i18n::format(Text::VirtualCableNoRecordingDevice, &cable.name))?;
+    worker.call(move |m| m.set_input_monitor(&recording_device_id, monitor_device_id.as_deref()).map_err(i18n::audio_error)).await
+}
+
+/// 有効になっている仮想ケーブル
+#[tauri::command]
+#[specta::specta]
+pub async fn get_virtual_cables(worker: State<'_, AudioWorker>) -> Result<Vec<VirtualCable>, String> {
+    detect(&worker).await
+}
+
+/// アプリの出力先を仮想ケーブルにし、`monitor_device_id` を指定すればケーブルに流れる音をその出力デバイスでも聴けるようにします。
+/// 聴く設定を先に行うので、失敗したときは出力先は変わりません。出力先の切り替えは取り消せる操作として記録します。
+#[tauri::command]
+#[specta::specta]
+pub async fn route_app_through_cable(app: AppHandle, pid: u32, cable_device_id: String, monitor_device_id: Option<String>) -> Result<VirtualCable, String> {
+    let worker = app.state::<AudioWorker>();
+    let cable = find(&worker, &cable_device_id).await?;
+    if monitor_device_id.is_some() {
+        monitor(&worker, &cable, monitor_device_id).await?;
+    }
+    undo::change(&app, undo::Target::Routing { pid }, undo::Value::Route(Some(cable.playback_device_id.clone()))).await?;
+    Ok(cable)
+}
+
+/// ケーブルに流れる音を `monitor_device_id` の出力デバイスで聴きます。`None` ならやめます。
+#[tauri::command]
+#[specta::specta]
+pub async fn monitor_virtual_cable(worker: State<'_, AudioWorker>, cable_device_id: String, monitor_device_id: Option<String>) -> Result<(), String> {
+    let cable = find(&worker, &cable_device_id).await?;
+    monitor(&worker, &cable, monitor_device_id).await
+}
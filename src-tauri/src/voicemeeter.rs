@@ -0,0 +1,376 @@
+//! VoiceMeeter の Remote API によるストリップ・バスの操作。設定で有効にすると、VoiceMeeter と一緒に入る
+//! VoicemeeterRemote64.dll を読み込んでログインし、Windows のセッションと同じミキサーから音量とミュートを変えられるようにします。
+//! API に変更の通知はないので、変更があったか（IsParametersDirty）を定期的に調べて "voicemeeter-changed" を送ります。
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::i18n::{self, Text};
+use crate::settings::SettingsManager;
+
+/// 変更があったかを調べる間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// ストリップ・バスのゲインの範囲 (dB)
+const MIN_GAIN_DB: f32 = -60.0;
+const MAX_GAIN_DB: f32 = 12.0;
+
+/// VoiceMeeter の種類。種類ごとにストリップとバスの数が違う
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, specta::Type)]
+pub enum Edition {
+    Standard,
+    Banana,
+    Potato,
+}
+
+impl Edition {
+    /// VBVMR_GetVoicemeeterType の値から
+    #[cfg_attr(not(windows), allow(dead_code))]
+    fn from_type(value: i32) -> Option<Self> {
+        match value {
+            1 => Some(Self::Standard),
+            2 => Some(Self::Banana),
+            3 => Some(Self::Potato),
+            _ => None,
+        }
+    }
+
+    /// (ハードウェア, 仮想) の数
+    fn count(self, kind: ChannelKind) -> (u32, u32) {
+        match (self, kind) {
+            (Self::Standard, ChannelKind::Strip) => (2, 1),
+            (Self::Standard, ChannelKind::Bus) => (1, 1),
+            (Self::Banana, _) => (3, 2),
+            (Self::Potato, _) => (5, 3),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, specta::Type)]
+pub enum ChannelKind {
+    /// 入力
+    Strip,
+    /// 出力
+    Bus,
+}
+
+impl ChannelKind {
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::Strip => "Strip",
+            Self::Bus => "Bus",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, specta::Type)]
+pub struct Channel {
+    pub kind: ChannelKind,
+    pub index: u32,
+    /// VoiceMeeter で付けた名前。付けていなければ空
+    pub label: String,
+    /// VAIO・AUX などの仮想の入出力
+    pub is_virtual: bool,
+    pub gain_db: f32,
+    pub muted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, specta::Type)]
+pub struct VoiceMeeterState {
+    pub edition: Edition,
+    /// ストリップ、バスの順
+    pub channels: Vec<Channel>,
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::ffi::CString;
+
+    use windows::core::{s, HSTRING};
+    use windows::Win32::Foundation::{FreeLibrary, HMODULE};
+    use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryW};
+    use winreg::enums::HKEY_LOCAL_MACHINE;
+    use winreg::RegKey;
+
+    use super::Edition;
+    use crate::i18n::{self, Text};
+
+    const UNINSTALL_KEYS: [&str; 2] = [
+        "SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\VB:Voicemeeter {17359A74-1236-5467}",
+        "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\VB:Voicemeeter {17359A74-1236-5467}",
+    ];
+    #[cfg(target_pointer_width = "64")]
+    const DLL_NAME: &str = "VoicemeeterRemote64.dll";
+    #[cfg(not(target_pointer_width = "64"))]
+    const DLL_NAME: &str = "VoicemeeterRemote.dll";
+    /// VBVMR_GetParameterStringW が書き込む最大の長さ
+    const STRING_LEN: usize = 512;
+
+    type Login = unsafe extern "system" fn() -> i32;
+    type GetType = unsafe extern "system" fn(*mut i32) -> i32;
+    type GetFloat = unsafe extern "system" fn(*const u8, *mut f32) -> i32;
+    type GetString = unsafe extern "system" fn(*const u8, *mut u16) -> i32;
+    type SetFloat = unsafe extern "system" fn(*const u8, f32) -> i32;
+
+    /// ログインしている Remote API。Drop でログアウトし、DLL を解放します。
+    pub struct Remote {
+        module: HMODULE,
+        logout: Login,
+        get_type: GetType,
+        is_dirty: Login,
+        get_float: GetFloat,
+        get_string: GetString,
+        set_float: SetFloat,
+    }
+
+    // API はプロセスで 1 つのログインを共有するもので、呼び出しは Mutex の内側で 1 つずつ行う
+    unsafe impl Send for Remote {}
+
+    impl Drop for Remote {
+        fn drop(&mut self) {
+            unsafe {
+                (self.logout)();
+                let _ = FreeLibrary(self.module);
+            }
+        }
+    }
+
+    /// アンインストール情報にあるインストール先の DLL
+    fn dll_path() -> Option<String> {
+        let root = RegKey::predef(HKEY_LOCAL_MACHINE);
+        UNINSTALL_KEYS.iter().find_map(|key| {
+            let uninstaller: String = root.open_subkey(key).ok()?.get_value("UninstallString").ok()?;
+            let dir = std::path::Path::new(uninstaller.trim_matches('"')).parent()?.to_path_buf();
+            Some(dir.join(DLL_NAME).to_string_lossy().into_owned())
+        })
+    }
+
+    impl Remote {
+        pub fn connect() -> Result<Self, String> {
+            let path = dll_path().ok_or_else(|| i18n::text(Text::VoiceMeeterNotInstalled).to_string())?;
+            unsafe {
+                let module = LoadLibraryW(&HSTRING::from(path)).map_err(|e| e.to_string())?;
+                macro_rules! function {
+                    ($name:expr, $type:ty) => {
+                        match GetProcAddress(module, $name) {
+                            Some(f) => std::mem::transmute::<unsafe extern "system" fn() -> isize, $type>(f),
+                            None => {
+                                let _ = FreeLibrary(module);
+                                return Err(i18n::text(Text::VoiceMeeterNotInstalled).to_string());
+                            }
+                        }
+                    };
+                }
+                let login = function!(s!("VBVMR_Login"), Login);
+                let remote = Self {
+                    module,
+                    logout: function!(s!("VBVMR_Logout"), Login),
+                    get_type: function!(s!("VBVMR_GetVoicemeeterType"), GetType),
+                    is_dirty: function!(s!("VBVMR_IsParametersDirty"), Login),
+                    get_float: function!(s!("VBVMR_GetParameterFloat"), GetFloat),
+                    get_string: function!(s!("VBVMR_GetParameterStringW"), GetString),
+                    set_float: function!(s!("VBVMR_SetParameterFloat"), SetFloat),
+                };
+                // 1 は VoiceMeeter が起動していないだけで、起動すればそのまま使える
+                match login() {
+                    result if result < 0 => {
+                        // ログインできていないので、Drop でログアウトさせずに DLL だけ解放する
+                        let _ = FreeLibrary(std::mem::ManuallyDrop::new(remote).module);
+                        Err(i18n::format(Text::VoiceMeeterLoginFailed, &result.to_string()))
+                    }
+                    _ => Ok(remote),
+                }
+            }
+        }
+
+        /// 起動している VoiceMeeter の種類。起動していなければ `None`
+        pub fn edition(&self) -> Option<Edition> {
+            let mut value = 0;
+            let result = unsafe { (self.get_type)(&mut value) };
+            if result == 0 { Edition::from_type(value) } else { None }
+        }
+
+        /// 前に調べてから値が変わったか。値を読む前に呼ぶと、読む値も最新になる
+        pub fn is_dirty(&self) -> bool {
+            unsafe { (self.is_dirty)() == 1 }
+        }
+
+        pub fn float(&self, name: &str) -> Result<f32, String> {
+            let parameter = CString::new(name).map_err(|e| e.to_string())?;
+            let mut value = 0.0;
+            match unsafe { (self.get_float)(parameter.as_ptr().cast(), &mut value) } {
+                0 => Ok(value),
+                result => Err(i18n::format(Text::VoiceMeeterReadFailed, &format!("{} ({})", name, result))),
+            }
+        }
+
+        pub fn string(&self, name: &str) -> Result<String, String> {
+            let parameter = CString::new(name).map_err(|e| e.to_string())?;
+            let mut buffer = [0u16; STRING_LEN];
+            match unsafe { (self.get_string)(parameter.as_ptr().cast(), buffer.as_mut_ptr()) } {
+                0 => Ok(String::from_utf16_lossy(&buffer[..buffer.iter().position(|&c| c == 0).unwrap_or(STRING_LEN)])),
+                result => Err(i18n::format(Text::VoiceMeeterReadFailed, &format!("{} ({})", name, result))),
+            }
+        }
+
+        pub fn set_float(&self, name: &str, value: f32) -> Result<(), String> {
+            let parameter = CString::new(name).map_err(|e| e.to_string())?;
+            match unsafe { (self.set_float)(parameter.as_ptr().cast(), value) } {
+                0 => Ok(()),
+                result => Err(i18n::format(Text::VoiceMeeterWriteFailed, &format!("{} ({})", name, result))),
+            }
+        }
+    }
+}
+
+/// Windows 以外では作れない
+#[cfg(not(windows))]
+mod imp {
+    use super::Edition;
+    use crate::i18n::{self, Text};
+
+    pub enum Remote {}
+
+    impl Remote {
+        pub fn connect() -> Result<Self, String> {
+            Err(i18n::text(Text::VoiceMeeterUnsupported).to_string())
+        }
+
+        pub fn edition(&self) -> Option<Edition> {
+            match *self {}
+        }
+
+        pub fn is_dirty(&self) -> bool {
+            match *self {}
+        }
+
+        pub fn float(&self, _name: &str) -> Result<f32, String> {
+            match *self {}
+        }
+
+        pub fn string(&self, _name: &str) -> Result<String, String> {
+            match *self {}
+        }
+
+        pub fn set_float(&self, _name: &str, _value: f32) -> Result<(), String> {
+            match *self {}
+        }
+    }
+}
+
+/// ログインしている Remote API。有効にしていなければ `None`。`app.manage` で登録します。
+#[derive(Default)]
+pub struct VoiceMeeter(Mutex<Option<imp::Remote>>);
+
+fn read_state(remote: &imp::Remote) -> Result<Option<VoiceMeeterState>, String> {
+    let Some(edition) = remote.edition() else { return Ok(None) };
+    let mut channels = Vec::new();
+    for kind in [ChannelKind::Strip, ChannelKind::Bus] {
+        let (hardware, virtual_count) = edition.count(kind);
+        for index in 0..hardware + virtual_count {
+            let name = format!("{}[{}]", kind.prefix(), index);
+            channels.push(Channel {
+                kind,
+                index,
+                label: remote.string(&format!("{}.Label", name))?,
+                is_virtual: index >= hardware,
+                gain_db: remote.float(&format!("{}.Gain", name))?,
+                muted: remote.float(&format!("{}.Mute", name))? != 0.0,
+            });
+        }
+    }
+    Ok(Some(VoiceMeeterState { edition, channels }))
+}
+
+/// ログインしている Remote API で `f` を呼びます。有効にしていないか VoiceMeeter が起動していなければエラーにします。
+fn with_remote<T>(voicemeeter: &VoiceMeeter, f: impl FnOnce(&imp::Remote, Edition) -> Result<T, String>) -> Result<T, String> {
+    let remote = voicemeeter.0.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    let remote = remote.as_ref().ok_or_else(|| i18n::text(Text::VoiceMeeterNotConnected).to_string())?;
+    let edition = remote.edition().ok_or_else(|| i18n::text(Text::VoiceMeeterNotConnected).to_string())?;
+    f(remote, edition)
+}
+
+/// `kind` の `index` 番目の、`field` のパラメーター名。VoiceMeeter の種類にないものはエラーにします。
+fn parameter(edition: Edition, kind: ChannelKind, index: u32, field: &str) -> Result<String, String> {
+    let (hardware, virtual_count) = edition.count(kind);
+    let name = format!("{}[{}]", kind.prefix(), index);
+    if index >= hardware + virtual_count {
+        return Err(i18n::format(Text::VoiceMeeterChannelNotFound, &name));
+    }
+    Ok(format!("{}.{}", name, field))
+}
+
+/// 設定で有効になっていれば起動時にログインし、値の変化を見張ります。
+pub fn spawn_watcher(app: AppHandle) {
+    let enabled = app.state::<Mutex<SettingsManager>>().lock().is_ok_and(|s| s.settings().voicemeeter);
+    if enabled {
+        match imp::Remote::connect() {
+            Ok(remote) => *app.state::<VoiceMeeter>().0.lock().unwrap_or_else(|e| e.into_inner()) = Some(remote),
+            Err(e) => eprintln!("Failed to connect to VoiceMeeter: {}", e),
+        }
+    }
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let changed = {
+                let state = app.state::<VoiceMeeter>();
+                let remote = state.0.lock().unwrap_or_else(|e| e.into_inner());
+                match remote.as_ref() {
+                    Some(remote) if remote.is_dirty() => Some(read_state(remote)),
+                    _ => None,
+                }
+            };
+            match changed {
+                Some(Ok(state)) => { let _ = app.emit("voicemeeter-changed", state); }
+                Some(Err(e)) => eprintln!("Failed to read VoiceMeeter: {}", e),
+                None => {}
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+/// VoiceMeeter のストリップとバス。有効にしていないか、VoiceMeeter が起動していなければ `None`
+#[tauri::command]
+#[specta::specta]
+pub fn get_voicemeeter(voicemeeter: State<'_, VoiceMeeter>) -> Result<Option<VoiceMeeterState>, String> {
+    let remote = voicemeeter.0.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    match remote.as_ref() {
+        Some(remote) => {
+            remote.is_dirty();
+            read_state(remote)
+        }
+        None => Ok(None),
+    }
+}
+
+/// VoiceMeeter の操作を有効・無効にします。有効にするときは Remote API にログインできたときだけ設定を保存します。
+#[tauri::command]
+#[specta::specta]
+pub fn set_voicemeeter_enabled(app: AppHandle, enabled: bool) -> Result<(), String> {
+    let state = app.state::<VoiceMeeter>();
+    let mut remote = state.0.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+    if enabled && remote.is_none() {
+        *remote = Some(imp::Remote::connect()?);
+    } else if !enabled {
+        *remote = None;
+    }
+    app.state::<Mutex<SettingsManager>>().lock().map_err(|_| i18n::text(Text::LockFailed))?.set_voicemeeter(enabled)
+}
+
+/// ストリップかバスのゲインを dB で設定します。範囲 (-60〜+12 dB) の外は丸めます。
+#[tauri::command]
+#[specta::specta]
+pub fn set_voicemeeter_gain(voicemeeter: State<'_, VoiceMeeter>, kind: ChannelKind, index: u32, gain_db: f32) -> Result<(), String> {
+    with_remote(&voicemeeter, |remote, edition| {
+        remote.set_float(&parameter(edition, kind, index, "Gain")?, gain_db.clamp(MIN_GAIN_DB, MAX_GAIN_DB))
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_voicemeeter_mute(voicemeeter: State<'_, VoiceMeeter>, kind: ChannelKind, index: u32, muted: bool) -> Result<(), String> {
+    with_remote(&voicemeeter, |remote, edition| {
+        remote.set_float(&parameter(edition, kind, index, "Mute")?, if muted { 1.0 } else { 0.0 })
+    })
+}
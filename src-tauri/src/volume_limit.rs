@@ -0,0 +1,171 @@
+//! 聴覚保護のためのマスター音量の上限。既定の出力デバイスの音量がどこから上限を超えて変えられても
+//! （音量キー、ほかのアプリ、このミキサー）、音量の変更通知を受けて上限まで戻します。
+//! 一時的に上限を外すには、`request_volume_limit_override` で受け取った確認コードを
+//! `confirm_volume_limit_override` に渡す 2 段階の操作が必要です。
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::broadcast;
+
+use crate::audio::{AudioWorker, MixerEvent};
+use crate::events::EventBus;
+use crate::i18n::{self, Text};
+use crate::settings::SettingsManager;
+
+/// 一時的に上限を外しておく時間。過ぎたら音量を上限まで戻す
+const OVERRIDE_DURATION: Duration = Duration::from_secs(15 * 60);
+/// 確認コードの有効期限
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+/// 浮動小数点の丸めで戻し続けないための許容差
+const TOLERANCE: f32 = 0.005;
+
+#[derive(Debug, Default)]
+struct LimitState {
+    override_until: Option<Instant>,
+    pending_code: Option<(u32, Instant)>,
+}
+
+/// 上限の一時解除の状態。`app.manage` で登録します。上限そのものは設定に保存します。
+#[derive(Debug, Default)]
+pub struct VolumeLimit(Mutex<LimitState>);
+
+#[derive(Debug, Clone, serde::Serialize, specta::Type)]
+pub struct VolumeLimitStatus {
+    /// 0.0〜1.0。`None` なら上限なし
+    pub limit: Option<f32>,
+    /// 一時解除中なら残り秒数
+    pub override_remaining_secs: Option<u64>,
+}
+
+fn status(app: &AppHandle) -> VolumeLimitStatus {
+    let limit = app.state::<Mutex<SettingsManager>>().lock().ok().and_then(|s| s.settings().volume_limit);
+    let remaining = app.state::<VolumeLimit>().0.lock().ok()
+        .and_then(|s| s.override_until)
+        .and_then(|until| until.checked_duration_since(Instant::now()))
+        .map(|d| d.as_secs());
+    VolumeLimitStatus { limit, override_remaining_secs: remaining }
+}
+
+/// いま守らせる上限。一時解除中は `None`
+fn effective_limit(app: &AppHandle) -> Option<f32> {
+    let status = status(app);
+    if status.override_remaining_secs.is_some() { None } else { status.limit }
+}
+
+/// マスター音量が上限を超えていれば上限まで下げます。
+async fn enforce(app: &AppHandle, volume: Option<f32>) {
+    let Some(limit) = effective_limit(app) else { return };
+    let worker = app.state::<AudioWorker>();
+    let volume = match volume {
+        Some(volume) => volume,
+        None => match worker.call(|m| m.get_master_volume().map_err(i18n::audio_error)).await {
+            Ok(master) => master.volume,
+            Err(_) => return,
+        },
+    };
+    if volume > limit + TOLERANCE {
+        if let Err(e) = worker.call(move |m| m.set_master_volume(limit).map_err(i18n::audio_error)).await {
+            eprintln!("Failed to enforce the volume limit: {}", e);
+        }
+    }
+}
+
+fn notify(app: &AppHandle) {
+    let _ = app.emit("volume-limit-changed", status(app));
+}
+
+/// 設定ファイルが外から書き換えられたときに、新しい上限を適用して知らせます。
+pub async fn reapply(app: &AppHandle) {
+    enforce(app, None).await;
+    notify(app);
+}
+
+/// マスター音量の変更を見張り、上限を超えたら戻します。既定のデバイスが変わったときも同じ通知で確かめます。
+pub fn spawn_enforcer(app: AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        enforce(&app, None).await;
+        loop {
+            match rx.recv().await {
+                Ok(MixerEvent::MasterVolumeChanged(master, _)) => enforce(&app, Some(master.volume)).await,
+                Ok(MixerEvent::BackendReconnected) => enforce(&app, None).await,
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_volume_limit(app: AppHandle) -> VolumeLimitStatus {
+    status(&app)
+}
+
+/// 上限を設定します（`None` で解除）。一時解除中であれば終わらせ、すぐに上限を適用します。
+#[tauri::command]
+#[specta::specta]
+pub async fn set_volume_limit(app: AppHandle, limit: Option<f32>) -> Result<(), String> {
+    let limit = limit.map(|l| l.clamp(0.0, 1.0));
+    app.state::<Mutex<SettingsManager>>().lock().map_err(|_| i18n::text(Text::LockFailed))?.set_volume_limit(limit)?;
+    app.state::<VolumeLimit>().0.lock().map_err(|_| i18n::text(Text::LockFailed))?.override_until = None;
+    enforce(&app, None).await;
+    notify(&app);
+    Ok(())
+}
+
+/// 一時解除の確認コードを発行します。UI で確認を取ってから `confirm_volume_limit_override` に渡してください。
+#[tauri::command]
+#[specta::specta]
+pub fn request_volume_limit_override(limit: State<'_, VolumeLimit>) -> Result<u32, String> {
+    let code = RandomState::new().hash_one(Instant::now()) as u32;
+    limit.0.lock().map_err(|_| i18n::text(Text::LockFailed))?.pending_code = Some((code, Instant::now() + CONFIRM_TIMEOUT));
+    Ok(code)
+}
+
+/// 確認コードが正しければ、一定時間だけ上限を外します。時間が過ぎたら音量を上限まで戻します。
+#[tauri::command]
+#[specta::specta]
+pub fn confirm_volume_limit_override(app: AppHandle, code: u32) -> Result<(), String> {
+    let until = {
+        let state = app.state::<VolumeLimit>();
+        let mut state = state.0.lock().map_err(|_| i18n::text(Text::LockFailed))?;
+        match state.pending_code.take() {
+            Some((pending, expires)) if pending == code && Instant::now() < expires => {}
+            _ => return Err(i18n::text(Text::VolumeLimitConfirmationInvalid).to_string()),
+        }
+        let until = Instant::now() + OVERRIDE_DURATION;
+        state.override_until = Some(until);
+        until
+    };
+    notify(&app);
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep_until(until.into()).await;
+        // 途中で解除し直されていれば何もしない
+        let expired = app.state::<VolumeLimit>().0.lock().map(|s| s.override_until == Some(until)).unwrap_or(false);
+        if expired {
+            end_override(&app).await;
+        }
+    });
+    Ok(())
+}
+
+async fn end_override(app: &AppHandle) {
+    if let Ok(mut state) = app.state::<VolumeLimit>().0.lock() {
+        state.override_until = None;
+    }
+    enforce(app, None).await;
+    notify(app);
+}
+
+/// 一時解除を終わらせ、すぐに上限を適用します。
+#[tauri::command]
+#[specta::specta]
+pub async fn end_volume_limit_override(app: AppHandle) -> Result<(), String> {
+    end_override(&app).await;
+    Ok(())
+}
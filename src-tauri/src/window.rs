@@ -1,63 +1,273 @@
-use tauri::{AppHandle, Manager, PhysicalPosition, WebviewWindow};
-use window_vibrancy::{apply_acrylic, apply_mica};
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, WebviewUrl, WebviewWindow, WebviewWindowBuilder, WindowEvent};
+
+use crate::settings::{ScreenCorner, SettingsManager, WindowGeometry};
+
+/// サイズを変えられる通常のウィンドウで開くミキサー
+const MIXER_LABEL: &str = "mixer";
+
+/// Acrylic・Mica を使う最初のビルド (Windows 11)。
+/// Windows 10 の Acrylic はウィンドウの移動やリサイズで大きく遅れ、表示が崩れるため使わない
+#[cfg(windows)]
+const WINDOWS_11_BUILD: u32 = 22000;
+
+/// Acrylic・ぼかしに重ねる色 (RGBA)
+#[cfg(windows)]
+const BACKDROP_TINT: (u8, u8, u8, u8) = (20, 20, 20, 10);
+
+/// ウィンドウの背景に使っている効果。どれも使えなければ、フロントエンドが不透明な下地を描きます。
+// プラットフォームごとに使わない効果がある
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, specta::Type)]
+#[serde(rename_all = "lowercase")]
+pub enum Backdrop {
+    Mica,
+    Acrylic,
+    /// Windows 10 のぼかし (blur-behind)
+    Blur,
+    /// macOS の NSVisualEffectView
+    Vibrancy,
+    #[default]
+    Solid,
+}
 
 #[derive(Debug, Default)]
-pub struct WindowManager {}
+pub struct WindowManager {
+    backdrop: Backdrop,
+}
+
+/// レジストリから Windows のビルド番号を読みます。
+#[cfg(windows)]
+fn windows_build() -> Option<u32> {
+    use winreg::enums::HKEY_LOCAL_MACHINE;
+    use winreg::RegKey;
+    let key = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey("SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion")
+        .ok()?;
+    let build: String = key.get_value("CurrentBuildNumber").ok()?;
+    build.parse().ok()
+}
 
 impl WindowManager {
-    pub fn apply_visual_effects(&self, window: &WebviewWindow) {
-        if let Err(_) = apply_mica(window, None) {
-            let _ = apply_acrylic(window, Some((20, 20, 20, 10)));
-        }
+    pub fn backdrop(&self) -> Backdrop {
+        self.backdrop
     }
 
+    /// Windows 11 では Mica（使えなければ Acrylic）、それより前ではぼかしを使います。
+    #[cfg(windows)]
+    pub fn apply_visual_effects(&mut self, window: &WebviewWindow) {
+        use window_vibrancy::{apply_acrylic, apply_blur, apply_mica};
+        let windows_11 = windows_build().is_some_and(|build| build >= WINDOWS_11_BUILD);
+        self.backdrop = if windows_11 && apply_mica(window, None).is_ok() {
+            Backdrop::Mica
+        } else if windows_11 && apply_acrylic(window, Some(BACKDROP_TINT)).is_ok() {
+            Backdrop::Acrylic
+        } else if apply_blur(window, Some(BACKDROP_TINT)).is_ok() {
+            Backdrop::Blur
+        } else {
+            Backdrop::Solid
+        };
+    }
+
+    #[cfg(target_os = "macos")]
+    pub fn apply_visual_effects(&mut self, window: &WebviewWindow) {
+        use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial};
+        self.backdrop = match apply_vibrancy(window, NSVisualEffectMaterial::HudWindow, None, Some(12.0)) {
+            Ok(()) => Backdrop::Vibrancy,
+            Err(_) => Backdrop::Solid,
+        };
+    }
+
+    #[cfg(not(any(windows, target_os = "macos")))]
+    pub fn apply_visual_effects(&mut self, _window: &WebviewWindow) {}
+
     pub fn toggle(&mut self, app: &AppHandle, tray_pos: (i32, i32)) {
         let window = match app.get_webview_window("main") {
             Some(w) => w,
-            None => return,
+            // ヘッドレスで起動したときはフライアウトがないので、ミキサーウィンドウを開く
+            None => {
+                if let Err(e) = self.open_mixer(app) {
+                    eprintln!("Failed to open mixer window: {}", e);
+                }
+                return;
+            }
         };
 
         if window.is_visible().unwrap_or(false) {
             let _ = window.hide();
         } else {
-            let (x, y) = self.calculate_position(&window, tray_pos);
+            let ((x, y), edge) = self.calculate_position(&window, tray_pos);
             let _ = window.set_position(PhysicalPosition::new(x, y));
             let _ = window.show();
             let _ = window.unminimize();
             let _ = window.set_focus();
             let _ = window.set_always_on_top(true);
             
+            // フロントエンドはタスクバーの側から滑り込ませる
             use tauri::Emitter;
-            let _ = app.emit("window-visible", ());
+            let _ = app.emit("window-visible", edge);
         }
     }
 
-    fn calculate_position(&self, window: &WebviewWindow, (tx, ty): (i32, i32)) -> (i32, i32) {
+    fn calculate_position(&self, window: &WebviewWindow, (tx, ty): (i32, i32)) -> ((i32, i32), TaskbarEdge) {
         let size = window.outer_size().unwrap_or_default();
         let w = size.width as i32;
         let h = size.height as i32;
 
-        // モニター情報を取得して境界チェック
-        let monitor = window.current_monitor().ok().flatten().unwrap_or_else(|| {
-            window.primary_monitor().ok().flatten().unwrap()
-        });
-        let m_size = monitor.size();
-        let m_pos = monitor.position();
+        // トレイアイコンのあるモニターを基準にする
+        let monitor = window.monitor_from_point(tx as f64, ty as f64).ok().flatten()
+            .or_else(|| window.current_monitor().ok().flatten())
+            .unwrap_or_else(|| window.primary_monitor().ok().flatten().unwrap());
+        let work = monitor.work_area();
+        let left = work.position.x;
+        let top = work.position.y;
+        let right = left + work.size.width as i32;
+        let bottom = top + work.size.height as i32;
+        let placement = window.state::<Mutex<SettingsManager>>().lock()
+            .map(|s| s.settings().flyout_placement)
+            .unwrap_or_default();
 
-        let mut target_x = tx - (w / 2);
-        let mut target_y = ty - h - 10;
+        let (target_x, target_y, edge) = match placement.corner {
+            // 角を指定されていれば、タスクバーを判定しない。上の角なら上から滑り込ませる
+            Some(ScreenCorner::TopLeft) => (left + EDGE_MARGIN, top + EDGE_MARGIN, TaskbarEdge::Top),
+            Some(ScreenCorner::TopRight) => (right - w - EDGE_MARGIN, top + EDGE_MARGIN, TaskbarEdge::Top),
+            Some(ScreenCorner::BottomLeft) => (left + EDGE_MARGIN, bottom - h - EDGE_MARGIN, TaskbarEdge::Bottom),
+            Some(ScreenCorner::BottomRight) => (right - w - EDGE_MARGIN, bottom - h - EDGE_MARGIN, TaskbarEdge::Bottom),
+            // タスクバーに接する辺に沿って、アイコンの位置に合わせる
+            None => match TaskbarEdge::detect(&monitor, (tx, ty)) {
+                TaskbarEdge::Bottom => (tx - w / 2, bottom - h - EDGE_MARGIN, TaskbarEdge::Bottom),
+                TaskbarEdge::Top => (tx - w / 2, top + EDGE_MARGIN, TaskbarEdge::Top),
+                TaskbarEdge::Left => (left + EDGE_MARGIN, ty - h / 2, TaskbarEdge::Left),
+                TaskbarEdge::Right => (right - w - EDGE_MARGIN, ty - h / 2, TaskbarEdge::Right),
+            },
+        };
+        let (target_x, target_y) = (target_x + placement.offset_x, target_y + placement.offset_y);
 
         // 画面端の補正
-        if target_x < m_pos.x { target_x = m_pos.x + 10; }
-        if target_x + w > m_pos.x + m_size.width as i32 {
-            target_x = m_pos.x + m_size.width as i32 - w - 10;
+        let target_x = target_x.min(right - w - EDGE_MARGIN).max(left + EDGE_MARGIN);
+        let target_y = target_y.min(bottom - h - EDGE_MARGIN).max(top + EDGE_MARGIN);
+
+        ((target_x, target_y), edge)
+    }
+
+    /// 設定ファイル (`tauri.conf.json`) にあるウィンドウ（フライアウトと HUD）を作ります。
+    /// ヘッドレスで起動したときは呼ばないので、設定側では `create: false` にしてある
+    pub fn create_configured_windows(&mut self, app: &AppHandle) -> tauri::Result<()> {
+        for config in &app.config().app.windows {
+            let window = WebviewWindowBuilder::from_config(app, config)?.build()?;
+            if window.label() == "main" {
+                self.apply_visual_effects(&window);
+            }
         }
+        Ok(())
+    }
 
-        // 上部召喚の場合（タスクバーが上の場合など）
-        if target_y < m_pos.y {
-            target_y = ty + 10;
+    /// ミキサーウィンドウを開きます。前回閉じたときの位置と大きさを復元します。
+    pub fn open_mixer(&mut self, app: &AppHandle) -> tauri::Result<()> {
+        if let Some(window) = app.get_webview_window(MIXER_LABEL) {
+            window.unminimize()?;
+            return window.set_focus();
+        }
+        let window = WebviewWindowBuilder::new(app, MIXER_LABEL, WebviewUrl::App("index.html".into()))
+            .title("Antigravity Pulse")
+            .inner_size(720.0, 640.0)
+            .min_inner_size(380.0, 400.0)
+            .visible(false)
+            .build()?;
+        self.apply_visual_effects(&window);
+        let geometry = app.state::<Mutex<SettingsManager>>().lock().ok()
+            .and_then(|s| s.settings().mixer_geometry.clone());
+        if let Some(geometry) = geometry {
+            let (x, y, width, height) = restore_geometry(&window, &geometry);
+            window.set_size(PhysicalSize::new(width, height))?;
+            window.set_position(PhysicalPosition::new(x, y))?;
+        } else {
+            window.center()?;
         }
+        let handle = window.clone();
+        window.on_window_event(move |event| {
+            if let WindowEvent::CloseRequested { .. } = event {
+                save_mixer_geometry(&handle);
+            }
+        });
+        window.show()?;
+        window.set_focus()
+    }
+}
+
+fn save_mixer_geometry(window: &WebviewWindow) {
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else { return };
+    if window.is_minimized().unwrap_or(false) {
+        return;
+    }
+    let geometry = WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        monitor: window.current_monitor().ok().flatten().and_then(|m| m.name().cloned()),
+    };
+    if let Ok(mut settings) = window.state::<Mutex<SettingsManager>>().lock() {
+        let _ = settings.set_mixer_geometry(geometry);
+    }
+}
+
+/// 保存した位置と大きさを返します。保存したときのモニターがなくなっていれば、
+/// 位置に最も近いモニター（なければプライマリモニター）の作業領域に収まるように補正します。
+fn restore_geometry(window: &WebviewWindow, geometry: &WindowGeometry) -> (i32, i32, u32, u32) {
+    let monitors = window.available_monitors().unwrap_or_default();
+    let same_monitor = geometry.monitor.as_ref()
+        .is_some_and(|name| monitors.iter().any(|m| m.name() == Some(name)));
+    if same_monitor {
+        return (geometry.x, geometry.y, geometry.width, geometry.height);
+    }
+    let monitor = window.monitor_from_point(geometry.x as f64, geometry.y as f64).ok().flatten()
+        .or_else(|| window.primary_monitor().ok().flatten());
+    let Some(monitor) = monitor else {
+        return (geometry.x, geometry.y, geometry.width, geometry.height);
+    };
+    let work = monitor.work_area();
+    let width = geometry.width.min(work.size.width);
+    let height = geometry.height.min(work.size.height);
+    let x = geometry.x.clamp(work.position.x, work.position.x + (work.size.width - width) as i32);
+    let y = geometry.y.clamp(work.position.y, work.position.y + (work.size.height - height) as i32);
+    (x, y, width, height)
+}
+
+/// 作業領域との間隔 (px)
+const EDGE_MARGIN: i32 = 10;
 
-        (target_x, target_y)
+/// タスクバー（macOS ではメニューバー）のある画面の辺
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskbarEdge {
+    Bottom,
+    Top,
+    Left,
+    Right,
+}
+
+impl TaskbarEdge {
+    /// モニター全体と作業領域の差から判定します。
+    /// タスクバーを自動的に隠す設定で差がない場合は、アイコンに最も近い辺とします。
+    fn detect(monitor: &Monitor, (x, y): (i32, i32)) -> Self {
+        let (m_pos, m_size, work) = (monitor.position(), monitor.size(), monitor.work_area());
+        let insets = [
+            (TaskbarEdge::Bottom, (m_pos.y + m_size.height as i32) - (work.position.y + work.size.height as i32)),
+            (TaskbarEdge::Top, work.position.y - m_pos.y),
+            (TaskbarEdge::Left, work.position.x - m_pos.x),
+            (TaskbarEdge::Right, (m_pos.x + m_size.width as i32) - (work.position.x + work.size.width as i32)),
+        ];
+        if let Some((edge, _)) = insets.iter().filter(|(_, inset)| *inset > 0).max_by_key(|(_, inset)| *inset) {
+            return *edge;
+        }
+        let distances = [
+            (TaskbarEdge::Bottom, m_pos.y + m_size.height as i32 - y),
+            (TaskbarEdge::Top, y - m_pos.y),
+            (TaskbarEdge::Left, x - m_pos.x),
+            (TaskbarEdge::Right, m_pos.x + m_size.width as i32 - x),
+        ];
+        distances.iter().min_by_key(|(_, d)| *d).map(|(edge, _)| *edge).unwrap_or(TaskbarEdge::Bottom)
     }
 }
@@ -1,70 +1,410 @@
 import { useEffect, useState, useRef } from "react";
-import { invoke } from "@tauri-apps/api/core";
+import { Channel, invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import type {
+  AudioSessionInfo as AudioSession,
+  MasterVolume,
+  BluetoothProfile,
+  DeviceChangeToast,
+  HudMode,
+  RouteMigration,
+  ScreenCorner,
+  FlyoutPlacement,
+  AudioDeviceInfo as AudioDevice,
+  SessionsDiff,
+  SessionStreamMessage,
+  PeakLevel as PeakData,
+  UpdateInfo,
+  InputPeak,
+  InputGain,
+  CaptureEffectKind,
+  CaptureEffect,
+  CallPanel,
+  LegacyStream,
+  MicrophoneUser,
+  SoundSchemes,
+  UsageRange,
+  UsageStats,
+  HistoryEntry,
+  AudioState,
+  UndoStatus,
+  DevicePair,
+  AppRule,
+  Profile,
+  ProfileList,
+  BackupConfig,
+  BackupEntry,
+  MirrorRequest,
+  VolumeLimitStatus,
+} from "./bindings";
 
-interface AudioSession {
-  process_id: number;
-  process_name: string;
-  volume: number;
-  is_muted: boolean;
-  peak_level: number;
-  icon_base64: string | null;
-  device_id: string;
+const DEVICE_TOAST_LABELS: Record<DeviceChangeToast, string> = { Off: "OFF", On: "ON", WithUndo: "ON + UNDO" };
+const NEXT_DEVICE_TOAST: Record<DeviceChangeToast, DeviceChangeToast> = { Off: "On", On: "WithUndo", WithUndo: "Off" };
+
+type TaskbarEdge = "bottom" | "top" | "left" | "right";
+
+/// 表示時に、タスクバーのある側からどれだけずらした位置から滑り込ませるか
+const SLIDE_FROM: Record<TaskbarEdge, string> = {
+  bottom: "translateY(24px)",
+  top: "translateY(-24px)",
+  left: "translateX(-24px)",
+  right: "translateX(24px)",
+};
+
+/// settings-changed で届く設定のうち、画面に出しているもの
+interface SettingsSnapshot {
+  pinned_apps: string[];
+  device_change_toast: DeviceChangeToast;
+  hud_mode: HudMode;
+  flyout_placement: FlyoutPlacement;
+  device_pairs: DevicePair[];
+  app_rules: AppRule[];
+  profiles: Profile[];
+  startup_profile: string | null;
+  backup: BackupConfig | null;
 }
 
-interface AudioDevice {
-  id: string;
-  name: string;
-  is_default: boolean;
+const CORNER_LABELS: Record<ScreenCorner, string> = { TopLeft: "TOP LEFT", TopRight: "TOP RIGHT", BottomLeft: "BOTTOM LEFT", BottomRight: "BOTTOM RIGHT" };
+// 自動 (null) → 各角 → 自動
+const CORNER_ORDER: (ScreenCorner | null)[] = [null, "BottomRight", "BottomLeft", "TopRight", "TopLeft"];
+
+const HUD_MODE_LABELS: Record<HudMode, string> = { Off: "OFF", On: "ON", ReplaceNative: "REPLACE WINDOWS" };
+const NEXT_HUD_MODE: Record<HudMode, HudMode> = { Off: "On", On: "ReplaceNative", ReplaceNative: "Off" };
+
+/// キー入力をグローバルショートカットの表記 ("Ctrl+Shift+KeyV") に変換する。修飾キーだけの場合は null
+function shortcutFromEvent(e: KeyboardEvent): string | null {
+  if (["Control", "Shift", "Alt", "Meta"].includes(e.key)) return null;
+  const parts = [];
+  if (e.ctrlKey) parts.push("Ctrl");
+  if (e.altKey) parts.push("Alt");
+  if (e.shiftKey) parts.push("Shift");
+  if (e.metaKey) parts.push("Super");
+  parts.push(e.code);
+  return parts.join("+");
 }
 
-interface PeakData {
-  pid: number;
-  peak: number;
+const isSameSession = (a: { instance_id: string }, b: { instance_id: string }) => a.instance_id === b.instance_id;
+
+const applySessionsDiff = (prev: AudioSession[], diff: SessionsDiff) => {
+  const next = prev.filter((s) => !diff.removed.some((r) => isSameSession(r, s)));
+  for (const session of [...diff.added, ...diff.updated]) {
+    const index = next.findIndex((s) => isSameSession(s, session));
+    if (index >= 0) next[index] = session;
+    else next.push(session);
+  }
+  return next;
+};
+
+const EFFECT_LABEL: Record<CaptureEffectKind, string> = {
+  EchoCancellation: "AEC",
+  NoiseSuppression: "NS",
+  AutomaticGainControl: "AGC",
+};
+
+const NEXT_USAGE_RANGE: Record<UsageRange, UsageRange | null> = { Day: "Week", Week: "Month", Month: null };
+
+const formatDuration = (secs: number) => {
+  const hours = Math.floor(secs / 3600);
+  const minutes = Math.floor((secs % 3600) / 60);
+  return hours > 0 ? `${hours}h ${minutes}m` : `${minutes}m`;
+};
+
+// 上限なし → 50% → 60% → 70% → 80% → 上限なし
+const VOLUME_LIMIT_STEPS = [null, 0.5, 0.6, 0.7, 0.8];
+
+interface UpdateProgress {
+  downloaded: number;
+  total: number | null;
 }
 
 function App() {
   const [sessions, setSessions] = useState<AudioSession[]>([]);
   const [devices, setDevices] = useState<AudioDevice[]>([]);
+  const [inputs, setInputs] = useState<AudioDevice[]>([]);
+  // レベルを計測中のマイク。"default" は既定のデバイスを追いかける
+  const [micTest, setMicTest] = useState<string | null>(null);
+  const [inputPeak, setInputPeak] = useState<InputPeak | null>(null);
+  const [inputGain, setInputGain] = useState<InputGain | null>(null);
+  const [micUsers, setMicUsers] = useState<MicrophoneUser[]>([]);
+  const [legacyStreams, setLegacyStreams] = useState<LegacyStream[]>([]);
+  const [captureEffects, setCaptureEffects] = useState<CaptureEffect[]>([]);
+  const [callPanel, setCallPanel] = useState<CallPanel | null>(null);
   const [draggedPid, setDraggedPid] = useState<number | null>(null);
+  // 出力先を移すよう促しても移らなかった（再起動が必要な）アプリ
+  const [restartRequired, setRestartRequired] = useState<number[]>([]);
   const [tacticalMode, setTacticalMode] = useState(false);
+  const [update, setUpdate] = useState<UpdateInfo | null>(null);
+  const [updateStatus, setUpdateStatus] = useState<string | null>(null);
+  const [updateProgress, setUpdateProgress] = useState<UpdateProgress | null>(null);
+  const [crashReport, setCrashReport] = useState<string | null>(null);
+  const [master, setMaster] = useState<MasterVolume | null>(null);
+  const [pinnedApps, setPinnedApps] = useState<string[]>([]);
+  const [deviceToast, setDeviceToast] = useState<DeviceChangeToast>("WithUndo");
+  const [hudMode, setHudMode] = useState<HudMode>("On");
+  const [placement, setPlacement] = useState<FlyoutPlacement>({ offset_x: 0, offset_y: 0, corner: null });
+  const [flyoutShortcut, setFlyoutShortcut] = useState("");
+  const [recordingShortcut, setRecordingShortcut] = useState(false);
+  const [elevatedHelper, setElevatedHelper] = useState(false);
+  const [soundSchemes, setSoundSchemes] = useState<SoundSchemes | null>(null);
+  const [usageRange, setUsageRange] = useState<UsageRange | null>(null);
+  const [usage, setUsage] = useState<UsageStats | null>(null);
+  const [history, setHistory] = useState<HistoryEntry[] | null>(null);
+  const [volumeLimit, setVolumeLimit] = useState<VolumeLimitStatus>({ limit: null, override_remaining_secs: null });
+  const [mirrors, setMirrors] = useState<MirrorRequest[]>([]);
+  const [devicePairs, setDevicePairs] = useState<DevicePair[]>([]);
+  const [appRules, setAppRules] = useState<AppRule[]>([]);
+  const [profiles, setProfiles] = useState<ProfileList>({ profiles: [], startup_profile: null });
+  const [backupConfig, setBackupConfig] = useState<BackupConfig | null>(null);
+  const [backups, setBackups] = useState<BackupEntry[] | null>(null);
+  // 組にする 1 台目として選んだデバイス
+  const [pairingFrom, setPairingFrom] = useState<string | null>(null);
+  const [exportedPath, setExportedPath] = useState<string | null>(null);
+  const [undoStatus, setUndoStatus] = useState<UndoStatus>({ can_undo: false, can_redo: false });
   
   const canvasRefs = useRef<Record<number, HTMLCanvasElement | null>>({});
+  const mainRef = useRef<HTMLElement | null>(null);
 
   useEffect(() => {
     refreshData();
+    invoke<string | null>("get_pending_crash_report").then(setCrashReport);
+    invoke<MasterVolume>("get_master_volume").then(setMaster).catch(() => setMaster(null));
+    invoke<string[]>("get_pinned_apps").then(setPinnedApps);
+    invoke<DeviceChangeToast>("get_device_change_toast").then(setDeviceToast);
+    invoke<string>("get_locale").then((locale) => {
+      document.documentElement.lang = locale;
+    });
+    invoke<HudMode>("get_hud_mode").then(setHudMode);
+    invoke<FlyoutPlacement>("get_flyout_placement").then(setPlacement);
+    // 背景効果が使えない場合は index.css で不透明な下地にする
+    invoke<string>("get_backdrop").then((backdrop) => {
+      document.documentElement.dataset.backdrop = backdrop;
+    });
+    invoke<string>("get_flyout_shortcut").then(setFlyoutShortcut);
+    invoke<boolean>("is_elevated_helper_running").then(setElevatedHelper);
+    invoke<SoundSchemes>("get_sound_schemes").then(setSoundSchemes).catch(() => setSoundSchemes(null));
+    invoke<VolumeLimitStatus>("get_volume_limit").then(setVolumeLimit);
+    invoke<MirrorRequest[]>("get_mirrors").then(setMirrors);
+    invoke<DevicePair[]>("get_device_pairs").then(setDevicePairs);
+    invoke<AppRule[]>("get_app_rules").then(setAppRules);
+    invoke<ProfileList>("get_profiles").then(setProfiles);
+    invoke<BackupConfig | null>("get_backup_config").then(setBackupConfig);
+    invoke<UndoStatus>("get_undo_status").then(setUndoStatus);
 
     const unlistenPulse = listen<PeakData[]>("audio-pulse", (event) => {
       event.payload.forEach((p) => drawPeak(p.pid, p.peak));
     });
 
-    const unlistenVolume = listen<any>("volume-change", () => refreshData());
+    const unlistenInputPulse = listen<InputPeak>("input-pulse", (event) => setInputPeak(event.payload));
+    invoke<MicrophoneUser[]>("get_microphone_users").then(setMicUsers);
+    const unlistenMicAccess = listen("microphone-access", () => {
+      invoke<MicrophoneUser[]>("get_microphone_users").then(setMicUsers);
+      // 録音を始めたアプリは通話アプリとして扱う
+      invoke<CallPanel>("get_call_panel").then(setCallPanel).catch(() => setCallPanel(null));
+    });
+
     const unlistenRefresh = listen("refresh-trigger", () => refreshData());
-    const unlistenAutoRefresh = listen<AudioSession[]>("refresh-sessions", (event) => {
-      setSessions(event.payload);
+    const unlistenReconnect = listen("backend-reconnected", () => {
+      // 作り直したバックエンドはマイクを計測していない
+      setMicTest(null);
+      refreshData();
+    });
+    // セッションの差分はウィンドウが表示されている間だけチャネルで受け取る。全体向けの sessions-changed は送らせない
+    const watchSessions = () => {
+      const channel = new Channel<SessionStreamMessage>();
+      channel.onmessage = (message) => {
+        if (message.kind === "Snapshot") setSessions(message.data);
+        else setSessions((prev) => applySessionsDiff(prev, message.data));
+      };
+      invoke("watch_sessions", { channel }).catch((e) => console.error("Failed to watch sessions", e));
+    };
+    invoke("unsubscribe_events", { categories: ["Sessions"] }).catch((e) => console.error("Failed to unsubscribe from session events", e));
+    watchSessions();
+    const unlistenMaster = listen<MasterVolume>("master-volume-change", (event) => setMaster(event.payload));
+    const unlistenVisible = listen<TaskbarEdge>("window-visible", (event) => {
+      watchSessions();
+      mainRef.current?.animate(
+        [{ transform: SLIDE_FROM[event.payload], opacity: 0 }, { transform: "none", opacity: 1 }],
+        { duration: 180, easing: "ease-out" },
+      );
+    });
+    const unlistenUpdateCheck = listen("check-for-updates", () => checkForUpdates());
+    const unlistenHistory = listen<HistoryEntry>("volume-history", (event) => {
+      const entry = event.payload;
+      setHistory((prev) => {
+        if (!prev) return prev;
+        // バックエンドは 1 秒以内の同じ変更を 1 件にまとめるので、先頭を置き換える
+        const [head, ...rest] = prev;
+        const merged = head !== undefined && entry.time - head.time < 1000 && head.origin === entry.origin
+          && head.muted === entry.muted && JSON.stringify(head.target) === JSON.stringify(entry.target);
+        return [entry, ...(merged ? rest : prev)].slice(0, 20);
+      });
+    });
+    const unlistenUndo = listen<UndoStatus>("undo-changed", (event) => setUndoStatus(event.payload));
+    const unlistenAudioState = listen<AudioState>("audio-state-refreshed", (event) => {
+      setSessions(event.payload.sessions);
+      setDevices(event.payload.devices);
+      setInputs(event.payload.capture_devices);
+      setMaster(event.payload.master);
+    });
+    const unlistenMirror = listen<{ pid: number; error: string | null }>("mirror-stopped", (event) => {
+      if (event.payload.error) console.error("Mirror stopped", event.payload.error);
+      setMirrors((prev) => prev.filter((m) => m.pid !== event.payload.pid));
+    });
+    // 一覧はアイコンを待たずに届くので、抽出できたものから埋める
+    const unlistenIcon = listen<{ pid: number; icon_base64: string; accent_color: string | null }>("session-icon-ready", (event) => {
+      const { pid, icon_base64, accent_color } = event.payload;
+      setSessions((prev) => prev.map((s) => (s.process_id === pid && !s.icon_base64 ? { ...s, icon_base64, accent_color } : s)));
+    });
+    // 設定ファイルが手で書き換えられた
+    const unlistenSettings = listen<SettingsSnapshot>("settings-changed", (event) => {
+      const settings = event.payload;
+      setPinnedApps(settings.pinned_apps);
+      setDeviceToast(settings.device_change_toast);
+      setHudMode(settings.hud_mode);
+      setPlacement(settings.flyout_placement);
+      setDevicePairs(settings.device_pairs);
+      setAppRules(settings.app_rules);
+      setProfiles({ profiles: settings.profiles, startup_profile: settings.startup_profile });
+      setBackupConfig(settings.backup);
+      // 未設定なら既定のショートカットになるので、バックエンドに聞き直す
+      invoke<string>("get_flyout_shortcut").then(setFlyoutShortcut);
+      refreshData();
+    });
+    const unlistenVolumeLimit = listen<VolumeLimitStatus>("volume-limit-changed", (event) => setVolumeLimit(event.payload));
+    const unlistenElevated = listen<boolean>("elevated-helper-changed", (event) => setElevatedHelper(event.payload));
+    const unlistenUpdateProgress = listen<UpdateProgress>("update-progress", (event) => {
+      setUpdateProgress(event.payload);
     });
 
     return () => {
       unlistenPulse.then((f) => f());
-      unlistenVolume.then((f) => f());
+      unlistenInputPulse.then((f) => f());
+      unlistenMicAccess.then((f) => f());
       unlistenRefresh.then((f) => f());
-      unlistenAutoRefresh.then((f) => f());
+      unlistenReconnect.then((f) => f());
+      unlistenMaster.then((f) => f());
+      unlistenVisible.then((f) => f());
+      unlistenUpdateCheck.then((f) => f());
+      unlistenElevated.then((f) => f());
+      unlistenHistory.then((f) => f());
+      unlistenVolumeLimit.then((f) => f());
+      unlistenMirror.then((f) => f());
+      unlistenIcon.then((f) => f());
+      unlistenSettings.then((f) => f());
+      unlistenUndo.then((f) => f());
+      unlistenAudioState.then((f) => f());
+      unlistenUpdateProgress.then((f) => f());
     };
   }, []);
 
   const refreshData = async () => {
     try {
-      const [sessionData, deviceData] = await Promise.all([
+      const [sessionData, deviceData, inputData] = await Promise.all([
         invoke<AudioSession[]>("get_audio_sessions"),
-        invoke<AudioDevice[]>("get_audio_devices")
+        invoke<AudioDevice[]>("get_audio_devices"),
+        // 入力デバイスを列挙できない環境では空にする
+        invoke<AudioDevice[]>("get_capture_devices").catch(() => [])
       ]);
       setSessions(sessionData);
       setDevices(deviceData);
+      setInputs(inputData);
+      setCallPanel(await invoke<CallPanel>("get_call_panel").catch(() => null));
+      const defaultInput = inputData.find((d) => d.is_default);
+      setInputGain(defaultInput ? await invoke<InputGain>("get_input_gain", { deviceId: defaultInput.id }).catch(() => null) : null);
+      // ドライバーが公開していなければ空
+      setCaptureEffects(defaultInput ? await invoke<CaptureEffect[]>("get_capture_effects", { deviceId: defaultInput.id }).catch(() => []) : []);
+      // Windows 以外では調べられないので空
+      setLegacyStreams(await invoke<LegacyStream[]>("get_legacy_streams").catch(() => []));
     } catch (e) {
       console.error("Failed to fetch data", e);
     }
   };
 
+  const changeInputGain = async (kind: "volume" | "boost", db: number) => {
+    if (!inputGain) return;
+    const level = inputGain[kind];
+    if (!level) return;
+    setInputGain({ ...inputGain, [kind]: { ...level, db } });
+    try {
+      await invoke(kind === "volume" ? "set_input_gain" : "set_input_boost", { deviceId: inputGain.device_id, db });
+    } catch (e) {
+      console.error("Failed to change the input gain", e);
+    }
+  };
+
+  const changeCallVolume = async (volume: number) => {
+    setCallPanel((prev) => prev && { ...prev, volume });
+    try {
+      await invoke("set_call_volume", { volume });
+    } catch (e) {
+      console.error("Failed to change the call volume", e);
+    }
+  };
+
+  const toggleCaptureEffect = async (effect: CaptureEffect) => {
+    const input = inputs.find((d) => d.is_default);
+    if (!input || !effect.can_toggle) return;
+    try {
+      await invoke("set_capture_effect", { deviceId: input.id, kind: effect.kind, enabled: !effect.enabled });
+      setCaptureEffects(await invoke<CaptureEffect[]>("get_capture_effects", { deviceId: input.id }));
+    } catch (e) {
+      console.error("Failed to switch the capture effect", e);
+    }
+  };
+
+  // 計測中はマイクを開いているので、OS の「マイク使用中」表示が出る
+  const toggleMicTest = async (input: AudioDevice) => {
+    const target = input.is_default ? "default" : input.id;
+    try {
+      if (micTest === target || (micTest === "default" && input.is_default)) {
+        await invoke("stop_input_meter");
+        setMicTest(null);
+      } else {
+        await invoke("start_input_meter", { deviceId: target === "default" ? null : target });
+        setMicTest(target);
+      }
+    } catch (e) {
+      console.error("Mic level meter failed", e);
+      setMicTest(null);
+    }
+    setInputPeak(null);
+  };
+
+  const checkForUpdates = async () => {
+    setUpdateStatus("CHECKING FOR UPDATES");
+    try {
+      const info = await invoke<UpdateInfo | null>("check_for_update");
+      setUpdate(info);
+      setUpdateStatus(info ? null : "UP TO DATE");
+    } catch (e) {
+      console.error("Update check failed", e);
+      setUpdateStatus("UPDATE CHECK FAILED");
+    }
+  };
+
+  const installUpdate = async () => {
+    setUpdateProgress({ downloaded: 0, total: null });
+    try {
+      // 成功するとアプリが再起動するので、ここには戻らない
+      await invoke("install_update");
+    } catch (e) {
+      console.error("Update failed", e);
+      setUpdate(null);
+      setUpdateProgress(null);
+      setUpdateStatus("UPDATE FAILED");
+    }
+  };
+
+  const openCrashReport = async () => {
+    setCrashReport(null);
+    await invoke("open_crash_report");
+  };
+
+  const dismissCrashReport = async () => {
+    setCrashReport(null);
+    await invoke("dismiss_crash_report");
+  };
+
   const drawPeak = (pid: number, peak: number) => {
     const canvas = canvasRefs.current[pid];
     if (!canvas) return;
@@ -75,20 +415,22 @@ function App() {
     const { width, height } = canvas;
     ctx.clearRect(0, 0, width, height);
 
+    // アプリのアイコンの色 (#rrggbb) があればその色で描く
+    const color = canvas.dataset.accent ?? "#00f2ff";
     const gradient = ctx.createLinearGradient(0, 0, width, 0);
-    gradient.addColorStop(0, "rgba(0, 242, 255, 0.1)");
-    gradient.addColorStop(0.5, "rgba(0, 242, 255, 0.8)");
-    gradient.addColorStop(1, "rgba(0, 242, 255, 0.1)");
+    gradient.addColorStop(0, `${color}1a`);
+    gradient.addColorStop(0.5, `${color}cc`);
+    gradient.addColorStop(1, `${color}1a`);
 
     ctx.fillStyle = gradient;
     ctx.shadowBlur = 12;
-    ctx.shadowColor = "#00f2ff";
+    ctx.shadowColor = color;
     ctx.fillRect(0, 0, width * peak, height);
   };
 
-  const updateVolume = async (pid: number, volume: number) => {
-    await invoke("set_session_volume", { pid, volume });
-    setSessions(prev => prev.map(s => s.process_id === pid ? { ...s, volume } : s));
+  const updateVolume = async (session: AudioSession, volume: number) => {
+    await invoke("set_session_volume", { pid: session.process_id, volume, instanceId: session.instance_id });
+    setSessions(prev => prev.map(s => isSameSession(s, session) ? { ...s, volume } : s));
   };
 
   const handleRoute = async (deviceId: string) => {
@@ -103,6 +445,356 @@ function App() {
     }
   };
 
+  const forceRoute = async (session: AudioSession) => {
+    if (!session.persisted_device_id) return;
+    try {
+      const result = await invoke<RouteMigration>("force_audio_routing", { pid: session.process_id, deviceId: session.persisted_device_id });
+      setRestartRequired((prev) => result === "RestartRequired"
+        ? [...prev.filter((pid) => pid !== session.process_id), session.process_id]
+        : prev.filter((pid) => pid !== session.process_id));
+      refreshData();
+    } catch (e) {
+      console.error("Failed to migrate the route", e);
+    }
+  };
+
+  const pairDevice = async (deviceId: string) => {
+    if (pairingFrom === null || pairingFrom === deviceId) {
+      setPairingFrom(pairingFrom === null ? deviceId : null);
+      return;
+    }
+    try {
+      await invoke("add_device_pair", { first: pairingFrom, second: deviceId });
+      setDevicePairs(await invoke<DevicePair[]>("get_device_pairs"));
+    } catch (e) {
+      console.error("Failed to pair devices", e);
+    }
+    setPairingFrom(null);
+  };
+
+  const unpairDevices = async (pair: DevicePair) => {
+    try {
+      await invoke("remove_device_pair", { first: pair.first, second: pair.second });
+      setDevicePairs(await invoke<DevicePair[]>("get_device_pairs"));
+    } catch (e) {
+      console.error("Failed to unpair devices", e);
+    }
+  };
+
+  // いまの出力先・音量・ミュートを、ウィンドウのタイトル（/正規表現/）かコマンドラインの一部で選ぶルールとして保存する
+  const addAppRule = async (session: AudioSession) => {
+    const pattern = window.prompt(`Apply this output and volume to ${session.exe_name} when its command line contains this text, or its window title matches /regex/:`);
+    if (!pattern?.trim()) return;
+    const regex = pattern.match(/^\/(.+)\/$/);
+    const rule: AppRule = {
+      matches: regex ? { WindowTitle: regex[1] } : { CommandLine: pattern.trim() },
+      exe_name: session.exe_name,
+      device_id: session.device_id,
+      volume: session.volume,
+      muted: session.is_muted,
+    };
+    try {
+      setAppRules(await invoke<AppRule[]>("add_app_rule", { rule }));
+    } catch (e) {
+      window.alert(String(e));
+    }
+  };
+
+  const removeAppRule = async (index: number) => {
+    try {
+      setAppRules(await invoke<AppRule[]>("remove_app_rule", { index }));
+    } catch (e) {
+      console.error("Failed to remove the rule", e);
+    }
+  };
+
+  const saveProfile = async () => {
+    const name = window.prompt("Save the current volumes and outputs as a profile named:");
+    if (!name?.trim()) return;
+    try {
+      setProfiles(await invoke<ProfileList>("save_profile", { name }));
+    } catch (e) {
+      window.alert(String(e));
+    }
+  };
+
+  const applyProfile = async (name: string) => {
+    try {
+      await invoke("apply_profile", { name });
+      refreshData();
+    } catch (e) {
+      console.error("Failed to apply the profile", e);
+    }
+  };
+
+  const toggleStartupProfile = async (name: string) => {
+    try {
+      setProfiles(await invoke<ProfileList>("set_startup_profile", { name: profiles.startup_profile === name ? null : name }));
+    } catch (e) {
+      console.error("Failed to set the startup profile", e);
+    }
+  };
+
+  const removeProfile = async (name: string) => {
+    try {
+      setProfiles(await invoke<ProfileList>("remove_profile", { name }));
+    } catch (e) {
+      console.error("Failed to remove the profile", e);
+    }
+  };
+
+  const cycleMirror = async (session: AudioSession) => {
+    // オフ → 元の出力先以外の各デバイス → オフ
+    const targets = devices.filter((d) => d.id !== session.device_id);
+    const current = mirrors.find((m) => m.pid === session.process_id);
+    const index = current ? targets.findIndex((d) => d.id === current.device_id) : -1;
+    const next = targets[index + 1];
+    try {
+      if (next) {
+        const request = { pid: session.process_id, device_id: next.id, delay_ms: current?.delay_ms ?? 0 };
+        await invoke("start_mirror", { pid: request.pid, deviceId: request.device_id, delayMs: request.delay_ms });
+        setMirrors((prev) => [...prev.filter((m) => m.pid !== request.pid), request]);
+      } else {
+        await invoke("stop_mirror", { pid: session.process_id });
+        setMirrors((prev) => prev.filter((m) => m.pid !== session.process_id));
+      }
+    } catch (e) {
+      console.error("Failed to mirror the app", e);
+    }
+  };
+
+  const changeMirrorDelay = async (pid: number, delayMs: number) => {
+    setMirrors((prev) => prev.map((m) => m.pid === pid ? { ...m, delay_ms: delayMs } : m));
+    try {
+      await invoke("set_mirror_delay", { pid, delayMs });
+    } catch (e) {
+      console.error("Failed to change the mirror delay", e);
+    }
+  };
+
+  const togglePin = async (exeName: string) => {
+    const key = exeName.trim().toUpperCase();
+    const pinned = pinnedApps.includes(key);
+    try {
+      await invoke(pinned ? "unpin_app" : "pin_app", { exeName });
+      setPinnedApps((prev) => pinned ? prev.filter((n) => n !== key) : [...prev, key]);
+    } catch (e) {
+      console.error("Pin failed", e);
+    }
+  };
+
+  const switchBluetoothProfile = async (deviceId: string, profile: BluetoothProfile) => {
+    try {
+      await invoke("set_bluetooth_profile", { deviceId, profile });
+      setTimeout(refreshData, 500);
+    } catch (e) {
+      console.error("Profile switch failed", e);
+    }
+  };
+
+  const cycleDeviceToast = async () => {
+    const next = NEXT_DEVICE_TOAST[deviceToast];
+    try {
+      await invoke("set_device_change_toast", { mode: next });
+      setDeviceToast(next);
+    } catch (e) {
+      console.error("Failed to save toast setting", e);
+    }
+  };
+
+  // 記録中は次に押されたキーの組み合わせをショートカットにする。Esc で中止、Backspace で無効化
+  useEffect(() => {
+    if (!recordingShortcut) return;
+    const onKeyDown = async (e: KeyboardEvent) => {
+      e.preventDefault();
+      let shortcut: string | null;
+      if (e.key === "Escape") {
+        setRecordingShortcut(false);
+        return;
+      } else if (e.key === "Backspace" && !e.ctrlKey && !e.altKey && !e.shiftKey && !e.metaKey) {
+        shortcut = "";
+      } else {
+        shortcut = shortcutFromEvent(e);
+        if (shortcut === null) return;
+      }
+      setRecordingShortcut(false);
+      try {
+        await invoke("set_flyout_shortcut", { shortcut });
+        setFlyoutShortcut(shortcut);
+      } catch (err) {
+        console.error("Failed to register shortcut", err);
+        setUpdateStatus("SHORTCUT UNAVAILABLE");
+      }
+    };
+    window.addEventListener("keydown", onKeyDown);
+    return () => window.removeEventListener("keydown", onKeyDown);
+  }, [recordingShortcut]);
+
+  const cycleHudMode = async () => {
+    const next = NEXT_HUD_MODE[hudMode];
+    try {
+      await invoke("set_hud_mode", { mode: next });
+      setHudMode(next);
+    } catch (e) {
+      console.error("Failed to save HUD setting", e);
+    }
+  };
+
+  const savePlacement = async (next: FlyoutPlacement) => {
+    try {
+      await invoke("set_flyout_placement", { placement: next });
+      setPlacement(next);
+    } catch (e) {
+      console.error("Failed to save the window placement", e);
+    }
+  };
+
+  const cycleCorner = () => {
+    const next = CORNER_ORDER[(CORNER_ORDER.indexOf(placement.corner) + 1) % CORNER_ORDER.length];
+    savePlacement({ ...placement, corner: next });
+  };
+
+  const editOffset = () => {
+    const input = window.prompt("Move the mixer by X, Y pixels (e.g. 0, -40)", `${placement.offset_x}, ${placement.offset_y}`);
+    if (input === null) return;
+    const [x, y] = input.split(",").map((v) => parseInt(v.trim(), 10));
+    if (Number.isNaN(x) || Number.isNaN(y)) return;
+    savePlacement({ ...placement, offset_x: x, offset_y: y });
+  };
+
+  const toggleElevatedHelper = async () => {
+    try {
+      // 起動時は UAC の確認が出る。断られたらそのまま
+      await invoke(elevatedHelper ? "stop_elevated_helper" : "start_elevated_helper");
+    } catch (e) {
+      console.error("Failed to switch the admin helper", e);
+    }
+  };
+
+  const cycleSoundScheme = async () => {
+    if (!soundSchemes || soundSchemes.schemes.length === 0) return;
+    const index = soundSchemes.schemes.findIndex((s) => s.id === soundSchemes.current);
+    const next = soundSchemes.schemes[(index + 1) % soundSchemes.schemes.length];
+    try {
+      await invoke("set_sound_scheme", { id: next.id });
+      setSoundSchemes({ ...soundSchemes, current: next.id });
+      // 新しいテーマの音を試しに鳴らす。サウンドなしなら何も鳴らない
+      invoke("play_system_sound", { event: "SystemAsterisk" }).catch(() => {});
+    } catch (e) {
+      console.error("Failed to switch the sound scheme", e);
+    }
+  };
+
+  const cycleUsageRange = async () => {
+    // 非表示 → 1 日 → 1 週間 → 30 日 → 非表示
+    const next = usageRange === null ? "Day" : NEXT_USAGE_RANGE[usageRange];
+    setUsageRange(next);
+    setUsage(next ? await invoke<UsageStats>("get_usage_stats", { range: next }).catch(() => null) : null);
+  };
+
+  const toggleHistory = async () => {
+    setHistory(history ? null : await invoke<HistoryEntry[]>("get_volume_history", { limit: 20 }).catch(() => []));
+  };
+
+  // 空にするとバックアップを止める
+  const editBackupFolder = async () => {
+    const dir = window.prompt("Back up the settings every day to this folder (e.g. a OneDrive folder). Leave empty to stop.", backupConfig?.dir ?? "");
+    if (dir === null) return;
+    const config = dir.trim() ? { dir: dir.trim(), interval_hours: backupConfig?.interval_hours ?? 24, keep: backupConfig?.keep ?? 10 } : null;
+    try {
+      await invoke("set_backup_config", { config });
+      setBackupConfig(config);
+      if (config) await invoke("back_up_settings");
+    } catch (e) {
+      window.alert(String(e));
+    }
+  };
+
+  const toggleBackups = async () => {
+    setBackups(backups ? null : await invoke<BackupEntry[]>("get_settings_backups").catch(() => []));
+  };
+
+  const restoreBackup = async (backup: BackupEntry) => {
+    if (!window.confirm(`Replace the current settings with the backup from ${new Date(backup.created_at * 1000).toLocaleString()}?`)) return;
+    try {
+      await invoke("restore_settings_backup", { fileName: backup.file_name });
+      setBackups(null);
+    } catch (e) {
+      window.alert(String(e));
+    }
+  };
+
+  const cycleVolumeLimit = async () => {
+    // 上限は f32 で保存されるので、近い段階を探す
+    const current = volumeLimit.limit;
+    const index = VOLUME_LIMIT_STEPS.findIndex((step) => step === current || (step !== null && current !== null && Math.abs(step - current) < 0.001));
+    const next = VOLUME_LIMIT_STEPS[(index + 1) % VOLUME_LIMIT_STEPS.length];
+    try {
+      await invoke("set_volume_limit", { limit: next });
+    } catch (e) {
+      console.error("Failed to set the volume limit", e);
+    }
+  };
+
+  const toggleVolumeLimitOverride = async () => {
+    try {
+      if (volumeLimit.override_remaining_secs !== null) {
+        await invoke("end_volume_limit_override");
+        return;
+      }
+      const code = await invoke<number>("request_volume_limit_override");
+      if (window.confirm("Listening at high volume for a long time can damage your hearing. Allow volume above the limit for 15 minutes?")) {
+        await invoke("confirm_volume_limit_override", { code });
+      }
+    } catch (e) {
+      console.error("Failed to override the volume limit", e);
+    }
+  };
+
+  // キャッシュを捨てて数え直す。結果は audio-state-refreshed で届く
+  const refreshAudioState = async () => {
+    try {
+      await invoke("refresh_audio_state");
+    } catch (e) {
+      console.error("Failed to refresh the audio state", e);
+    }
+  };
+
+  const undoOrRedo = async (command: "undo" | "redo") => {
+    try {
+      setUndoStatus(await invoke<UndoStatus>(command));
+    } catch (e) {
+      console.error(`Failed to ${command}`, e);
+    }
+  };
+
+  // Ctrl+Z で取り消し、Ctrl+Y または Ctrl+Shift+Z でやり直し。ショートカットの記録中と入力欄では使わない
+  useEffect(() => {
+    if (recordingShortcut) return;
+    const onKeyDown = (e: KeyboardEvent) => {
+      if (!e.ctrlKey || e.altKey || e.metaKey) return;
+      if (e.target instanceof HTMLInputElement && e.target.type !== "range") return;
+      const key = e.key.toLowerCase();
+      if (key === "z" && !e.shiftKey) {
+        e.preventDefault();
+        undoOrRedo("undo");
+      } else if (key === "y" || (key === "z" && e.shiftKey)) {
+        e.preventDefault();
+        undoOrRedo("redo");
+      }
+    };
+    window.addEventListener("keydown", onKeyDown);
+    return () => window.removeEventListener("keydown", onKeyDown);
+  }, [recordingShortcut]);
+
+  const exportMixerState = async (format: "Csv" | "Json") => {
+    try {
+      setExportedPath(await invoke<string>("export_mixer_state", { format, fileName: null, overwrite: false }));
+    } catch (e) {
+      console.error("Failed to export mixer state", e);
+    }
+  };
+
   const toggleTactical = async (enabled: boolean) => {
     setTacticalMode(enabled);
     await invoke("set_tactical_mode", { enabled });
@@ -110,6 +802,7 @@ function App() {
 
   return (
     <main 
+      ref={mainRef}
       onContextMenu={(e) => e.preventDefault()}
       className={`flex flex-col h-screen overflow-hidden p-4 space-y-4 select-none transition-all duration-500 ${tacticalMode ? 'bg-black/40' : 'bg-transparent'}`}
     >
@@ -143,40 +836,247 @@ function App() {
 
       {/* Devices Grid */}
       <div className="space-y-2">
-        <h2 className="text-[10px] font-bold opacity-30 uppercase tracking-[0.2em] px-1">Active Endpoints</h2>
+        <div className="flex justify-between items-center px-1">
+          <h2 className="text-[10px] font-bold opacity-30 uppercase tracking-[0.2em]">Active Endpoints</h2>
+          {master && (
+            <button
+              onClick={() => invoke("set_master_mute", { mute: !master.is_muted })}
+              onWheel={(e) => invoke("step_master_volume", { delta: e.deltaY < 0 ? 0.05 : -0.05 })}
+              title={`${master.is_muted ? "Output is muted. Click to unmute." : "Mute output"} Scroll to change the volume.`}
+              className={`p-1 rounded border transition-all ${master.is_muted ? 'bg-red-500/20 border-red-500/40 text-red-400' : 'border-white/10 text-white/40 hover:text-pulse-neon'}`}
+            >
+              <MuteIcon isMuted={master.is_muted} />
+            </button>
+          )}
+        </div>
         <div className="flex gap-2 overflow-x-auto pb-2 custom-scrollbar">
           {devices.map(device => (
             <div 
               key={device.id}
               onDragOver={(e) => e.preventDefault()}
               onDrop={() => handleRoute(device.id)}
-              className={`flex-shrink-0 w-36 p-3 rounded-lg border transition-all duration-300 ${device.is_default ? 'border-pulse-neon/50 bg-pulse-neon/10' : 'border-white/10 bg-white/5'} hover:bg-white/10 hover:border-white/20`}
+              className={`flex-shrink-0 w-36 p-3 rounded-lg border transition-all duration-300 ${device.is_default ? 'border-pulse-neon/50 bg-pulse-neon/10' : 'border-white/10 bg-white/5'} ${device.jack_connected === false ? 'opacity-40' : ''} hover:bg-white/10 hover:border-white/20`}
+              title={device.jack_connected === false ? "Nothing is plugged into this jack" : undefined}
             >
               <div className="text-[10px] font-black truncate text-white/90 mb-1">{device.name}</div>
               <div className="flex justify-between items-center">
                 <span className={`text-[8px] px-1.5 py-0.5 rounded ${device.is_default ? 'bg-pulse-neon text-black' : 'bg-white/10 text-white/40'}`}>
-                  {device.is_default ? 'PRIMARY' : 'ACTIVE'}
+                  {device.is_default ? 'PRIMARY' : device.jack_connected === false ? 'UNPLUGGED' : 'ACTIVE'}
                 </span>
+                <button
+                  onClick={() => pairDevice(device.id)}
+                  title={pairingFrom === null ? "Keep this device's volume locked to another device" : pairingFrom === device.id ? "Cancel pairing" : "Lock this device's volume to the selected device"}
+                  className={`text-[8px] px-1 rounded border ${pairingFrom === device.id ? 'border-pulse-neon/60 text-pulse-neon' : 'border-white/20 text-white/40 hover:text-pulse-neon'}`}
+                >
+                  {pairingFrom === null ? 'LINK' : pairingFrom === device.id ? 'CANCEL' : 'PAIR'}
+                </button>
                 <div className="w-8 h-1 bg-white/10 rounded-full overflow-hidden">
                   <div className={`h-full ${device.is_default ? 'bg-pulse-neon' : 'bg-white/30'} w-2/3 shadow-[0_0_5px_currentColor]`} />
                 </div>
               </div>
+              {device.bluetooth && (
+                <div className="flex justify-between items-center mt-2 text-[8px] font-mono">
+                  <button
+                    onClick={() => switchBluetoothProfile(device.id, device.bluetooth!.profile === "HandsFree" ? "A2dp" : "HandsFree")}
+                    title={device.bluetooth.profile === "HandsFree" ? "Hands-free mode lowers audio quality. Click to switch to A2DP." : "Switch to hands-free mode (enables the microphone)"}
+                    className={`px-1 rounded border ${device.bluetooth.profile === "HandsFree" ? 'border-yellow-400/60 text-yellow-300' : 'border-white/20 text-white/40'}`}
+                  >
+                    {device.bluetooth.profile === "HandsFree" ? "HFP" : device.bluetooth.profile === "A2dp" ? "A2DP" : "BT"}
+                  </button>
+                  {device.bluetooth.battery_percent !== null && (
+                    <span className="text-white/40">{device.bluetooth.battery_percent}%</span>
+                  )}
+                </div>
+              )}
             </div>
           ))}
         </div>
+        {devicePairs.length > 0 && (
+          <div className="flex flex-wrap gap-2 px-1 text-[8px] font-mono text-white/40">
+            {devicePairs.map(pair => {
+              const name = (id: string) => devices.find(d => d.id === id)?.name ?? "Disconnected";
+              return (
+                <button
+                  key={`${pair.first}|${pair.second}`}
+                  onClick={() => unpairDevices(pair)}
+                  title="These devices keep the same volume. Click to unlink."
+                  className="px-1 rounded border border-white/10 hover:text-red-400"
+                >
+                  {name(pair.first)} ⇄ {name(pair.second)} ×
+                </button>
+              );
+            })}
+          </div>
+        )}
+        {micUsers.length > 0 && (
+          <div
+            className="flex items-center gap-1 px-1 text-[8px] font-mono text-red-300"
+            title={micUsers.map(u => u.pid !== null ? `${u.process_name} (PID ${u.pid})` : u.process_name).join("\n")}
+          >
+            <span className="w-1.5 h-1.5 rounded-full bg-red-500 animate-pulse" />
+            <span className="truncate">MIC IN USE · {[...new Set(micUsers.map(u => u.process_name))].join(", ")}</span>
+          </div>
+        )}
+        {inputs.length > 0 && (
+          <div className="flex gap-2 overflow-x-auto pb-2 custom-scrollbar">
+            {inputs.map(input => {
+              const testing = micTest === input.id || (micTest === "default" && input.is_default);
+              const level = testing && inputPeak?.device_id === input.id ? inputPeak.peak : 0;
+              return (
+                <div key={input.id} className={`flex-shrink-0 max-w-36 rounded border transition-all ${input.is_default ? 'border-pulse-neon/50 bg-pulse-neon/10' : 'border-white/10'}`}>
+                  <div className="flex items-center">
+                    <button
+                      onClick={() => !input.is_default && invoke("set_default_capture_device", { deviceId: input.id }).then(refreshData)}
+                      title={input.is_default ? "Default microphone" : "Make this the default microphone"}
+                      className={`flex-1 min-w-0 px-2 py-1 text-left text-[9px] font-mono truncate ${input.is_default ? 'text-white/90' : 'text-white/40 hover:text-white/80'}`}
+                    >
+                      MIC · {input.name}
+                    </button>
+                    <button
+                      onClick={() => toggleMicTest(input)}
+                      title={testing ? "Stop the level meter" : "Show this microphone's input level"}
+                      className={`px-1 text-[8px] font-mono ${testing ? 'text-pulse-neon' : 'text-white/30 hover:text-white/70'}`}
+                    >
+                      TEST
+                    </button>
+                  </div>
+                  {testing && (
+                    <div className="h-0.5 bg-white/10 overflow-hidden">
+                      <div className="h-full bg-pulse-neon transition-[width] duration-75" style={{ width: `${Math.min(level, 1) * 100}%` }} />
+                    </div>
+                  )}
+                </div>
+              );
+            })}
+          </div>
+        )}
+        {inputGain && (
+          <div className="flex items-center gap-2 px-1 text-[8px] font-mono text-white/40">
+            <span>GAIN</span>
+            <input
+              type="range"
+              min={inputGain.volume.min_db}
+              max={inputGain.volume.max_db}
+              step={inputGain.volume.step_db || 0.5}
+              value={inputGain.volume.db}
+              onChange={(e) => changeInputGain("volume", Number(e.target.value))}
+              className="flex-1 accent-pulse-neon"
+              title="Microphone level"
+            />
+            <span className="w-12 text-right">{inputGain.volume.db.toFixed(1)} dB</span>
+            {inputGain.boost && (
+              <button
+                onClick={() => {
+                  const boost = inputGain.boost!;
+                  const next = boost.db + (boost.step_db || 10);
+                  changeInputGain("boost", next > boost.max_db + 0.01 ? boost.min_db : next);
+                }}
+                title="Hardware microphone boost. Click to step through the available levels."
+                className={`px-1 rounded border ${inputGain.boost.db > inputGain.boost.min_db ? 'border-yellow-400/60 text-yellow-300' : 'border-white/20'}`}
+              >
+                BOOST +{inputGain.boost.db.toFixed(0)} dB
+              </button>
+            )}
+            {captureEffects.map(effect => (
+              <button
+                key={effect.kind}
+                onClick={() => toggleCaptureEffect(effect)}
+                disabled={!effect.can_toggle}
+                title={effect.can_toggle ? `${effect.kind} is ${effect.enabled ? "on" : "off"}. Click to switch.` : `${effect.kind} is ${effect.enabled ? "on" : "off"} and the driver does not allow switching it.`}
+                className={`px-1 rounded border ${effect.enabled ? 'border-pulse-neon/50 text-pulse-neon' : 'border-white/20'} ${effect.can_toggle ? '' : 'opacity-40'}`}
+              >
+                {EFFECT_LABEL[effect.kind]}
+              </button>
+            ))}
+          </div>
+        )}
       </div>
 
+      {callPanel && callPanel.sessions.length > 0 && (
+        <div className="p-2 rounded-lg border border-pulse-neon/30 bg-pulse-neon/5 space-y-1 text-[8px] font-mono">
+          <div className="flex justify-between text-white/60">
+            <span className="font-black text-pulse-neon">CALL · {[...new Set(callPanel.sessions.map(s => s.process_name))].join(", ")}</span>
+            <span>{callPanel.volume !== null ? `${Math.round(callPanel.volume * 100)}%` : ""}</span>
+          </div>
+          <input
+            type="range"
+            min={0}
+            max={1}
+            step={0.01}
+            value={callPanel.volume ?? 1}
+            onChange={(e) => changeCallVolume(Number(e.target.value))}
+            className="w-full accent-pulse-neon"
+            title="Volume of every call app"
+          />
+          <div className="flex justify-between text-white/40">
+            <span className="truncate">OUT · {callPanel.output?.name ?? "NONE"}</span>
+            <span className="truncate">MIC · {callPanel.input?.name ?? "NONE"}</span>
+          </div>
+        </div>
+      )}
+
       {/* Sessions List */}
       <div className="flex-1 flex flex-col space-y-2 overflow-hidden">
         <h2 className="text-[10px] font-bold opacity-30 uppercase tracking-[0.2em] px-1">Signal Sessions</h2>
+        {legacyStreams.length > 0 && (
+          <div
+            className="px-1 text-[8px] font-mono text-yellow-300/70 truncate"
+            title={legacyStreams.some(s => s.system_sounds_active)
+              ? "These apps use an older audio API. Their sound may be mixed into System Sounds, so use that slider to change their volume."
+              : "These apps use an older audio API and may play sound without showing up as their own session."}
+          >
+            LEGACY AUDIO · {legacyStreams.map(s => `${s.exe_name} (${s.api === "Mme" ? "MME" : "DirectSound"})`).join(", ")}
+            {legacyStreams.some(s => s.system_sounds_active) && " → SYSTEM SOUNDS"}
+          </div>
+        )}
+        {appRules.length > 0 && (
+          <div className="flex flex-wrap gap-2 px-1 text-[8px] font-mono text-white/40">
+            {appRules.map((rule, index) => {
+              const condition = "WindowTitle" in rule.matches ? `/${rule.matches.WindowTitle}/` : `"${rule.matches.CommandLine}"`;
+              const output = devices.find(d => d.id === rule.device_id)?.name;
+              return (
+                <button
+                  key={index}
+                  onClick={() => removeAppRule(index)}
+                  title="Applied when a matching app starts playing. Click to delete."
+                  className="px-1 rounded border border-white/10 hover:text-red-400"
+                >
+                  {rule.exe_name ?? "ANY"} {condition}{output ? ` → ${output}` : ""}{rule.volume !== null ? ` ${Math.round(rule.volume * 100)}%` : ""}{rule.muted ? " MUTED" : ""} ×
+                </button>
+              );
+            })}
+          </div>
+        )}
+        <div className="flex flex-wrap gap-2 px-1 text-[8px] font-mono text-white/40">
+          {profiles.profiles.map(profile => (
+            <span key={profile.name} className="flex items-center gap-1 px-1 rounded border border-white/10">
+              <button
+                onClick={() => toggleStartupProfile(profile.name)}
+                title="Apply this profile when the app starts"
+                className={profiles.startup_profile === profile.name ? "text-pulse-neon" : "hover:text-white"}
+              >
+                ★
+              </button>
+              <button onClick={() => applyProfile(profile.name)} title="Apply this profile" className="hover:text-pulse-neon">
+                {profile.name}
+              </button>
+              <button onClick={() => removeProfile(profile.name)} title="Delete this profile" className="hover:text-red-400">
+                ×
+              </button>
+            </span>
+          ))}
+          <button onClick={saveProfile} title="Save the current state as a profile" className="px-1 rounded border border-white/10 hover:text-pulse-neon">
+            + PROFILE
+          </button>
+        </div>
         <section className="flex-1 overflow-y-auto space-y-2 pr-2 custom-scrollbar">
           {sessions.map((session) => (
             <div 
-              key={session.process_id}
+              key={session.instance_id}
               draggable
               onDragStart={() => setDraggedPid(session.process_id)}
               onDragEnd={() => setDraggedPid(null)}
-              className={`group bg-gradient-to-r from-white/5 to-transparent border border-white/10 rounded-xl p-4 transition-all duration-200 cursor-grab active:cursor-grabbing ${draggedPid === session.process_id ? 'opacity-30 scale-95 blur-sm' : 'hover:border-pulse-neon/30 hover:from-white/10'}`}
+              className={`group bg-gradient-to-r from-white/5 to-transparent border border-white/10 rounded-xl p-4 transition-all duration-200 cursor-grab active:cursor-grabbing ${draggedPid === session.process_id ? 'opacity-30 scale-95 blur-sm' : 'hover:border-pulse-neon/30 hover:from-white/10'} ${session.state === 'Inactive' && draggedPid !== session.process_id ? 'opacity-50' : ''}`}
             >
               <div className="flex items-center space-x-4 mb-4">
                 <div className="relative w-12 h-12 flex-shrink-0">
@@ -195,17 +1095,51 @@ function App() {
                     <div className="text-[13px] font-black truncate text-white/80 group-hover:text-white transition-colors uppercase tracking-tight">
                       {session.process_name}
                     </div>
-                    <button 
-                      onClick={(e) => { e.stopPropagation(); invoke("set_session_mute", { pid: session.process_id, mute: !session.is_muted }); }}
-                      className={`p-1.5 rounded-lg border transition-all ${session.is_muted ? 'bg-red-500/20 border-red-500/40 text-red-400' : 'bg-white/5 border-white/10 text-white/40 hover:text-pulse-neon hover:border-pulse-neon/40'}`}
-                    >
-                      <MuteIcon isMuted={session.is_muted} />
-                    </button>
+                    <div className="flex items-center space-x-1">
+                      <button
+                        onClick={(e) => { e.stopPropagation(); togglePin(session.exe_name); }}
+                        title={pinnedApps.includes(session.exe_name.toUpperCase()) ? "Unpin: stop flagging this app on the tray icon when muted" : "Pin: flag this app on the tray icon when muted"}
+                        className={`px-1.5 py-1 rounded-lg border text-[8px] font-mono transition-all ${pinnedApps.includes(session.exe_name.toUpperCase()) ? 'border-pulse-neon/50 text-pulse-neon' : 'border-white/10 text-white/30 opacity-0 group-hover:opacity-100'}`}
+                      >
+                        PIN
+                      </button>
+                      <button
+                        onClick={(e) => { e.stopPropagation(); cycleMirror(session); }}
+                        title="Also play this app on another output. Click to pick the next device or turn it off."
+                        className={`px-1.5 py-1 rounded-lg border text-[8px] font-mono transition-all ${mirrors.some((m) => m.pid === session.process_id) ? 'border-pulse-neon/50 text-pulse-neon' : 'border-white/10 text-white/30 opacity-0 group-hover:opacity-100'}`}
+                      >
+                        MIRROR
+                      </button>
+                      <button
+                        onClick={(e) => { e.stopPropagation(); addAppRule(session); }}
+                        title="Remember this output and volume for this app when its window title or command line matches"
+                        className="px-1.5 py-1 rounded-lg border text-[8px] font-mono transition-all border-white/10 text-white/30 opacity-0 group-hover:opacity-100"
+                      >
+                        RULE
+                      </button>
+                      <button 
+                        onClick={(e) => { e.stopPropagation(); invoke("set_session_mute", { pid: session.process_id, mute: !session.is_muted, instanceId: session.instance_id }); }}
+                        className={`p-1.5 rounded-lg border transition-all ${session.is_muted ? 'bg-red-500/20 border-red-500/40 text-red-400' : 'bg-white/5 border-white/10 text-white/40 hover:text-pulse-neon hover:border-pulse-neon/40'}`}
+                      >
+                        <MuteIcon isMuted={session.is_muted} />
+                      </button>
+                    </div>
                   </div>
                   <div className="text-[9px] font-mono opacity-30 mt-1 flex items-center space-x-2">
                     <span>PID:{session.process_id}</span>
                     <span className="opacity-20">•</span>
                     <span className="truncate">{devices.find(d => d.id === session.device_id)?.name || "SYSTEM DEFAULT"}</span>
+                    {session.route_pending && (
+                      <button
+                        onClick={() => forceRoute(session)}
+                        className="shrink-0 px-1 rounded border border-yellow-400/60 text-yellow-300 hover:bg-yellow-400/10"
+                        title={restartRequired.includes(session.process_id)
+                          ? "This app does not switch outputs while it is running. Restart it to use the new device."
+                          : "The output was changed, but this app keeps using the old device. Click to move its audio now."}
+                      >
+                        {restartRequired.includes(session.process_id) ? "RESTART APP" : "ROUTE PENDING"} → {devices.find(d => d.id === session.persisted_device_id)?.name || session.persisted_device_id}
+                      </button>
+                    )}
                   </div>
                 </div>
               </div>
@@ -214,6 +1148,7 @@ function App() {
                 <div className="relative h-2 bg-black/40 rounded-full overflow-hidden border border-white/5">
                   <canvas 
                     ref={(el) => { canvasRefs.current[session.process_id] = el; }}
+                    data-accent={session.accent_color ?? undefined}
                     width={340}
                     height={8}
                     className="absolute inset-0 w-full h-full"
@@ -224,22 +1159,244 @@ function App() {
                     type="range" 
                     min="0" max="1" step="0.01"
                     value={session.volume}
-                    onChange={(e) => updateVolume(session.process_id, parseFloat(e.target.value))}
+                    onChange={(e) => updateVolume(session, parseFloat(e.target.value))}
                     onDragStart={(e) => { e.preventDefault(); e.stopPropagation(); }}
                     onMouseDown={(e) => e.stopPropagation()}
                     onTouchStart={(e) => e.stopPropagation()}
                     className="flex-1 h-1.5"
+                    style={session.accent_color ? { accentColor: session.accent_color } : undefined}
                   />
                   <span className="text-[10px] font-mono opacity-50 w-8 text-right">{(session.volume * 100).toFixed(0)}%</span>
                 </div>
+                {mirrors.filter((m) => m.pid === session.process_id).map((mirror) => (
+                  <div key={mirror.device_id} className="flex items-center space-x-3 text-[9px] font-mono">
+                    <span className="opacity-50 truncate w-28" title="The copy of this app's audio plays here">
+                      → {devices.find((d) => d.id === mirror.device_id)?.name ?? mirror.device_id}
+                    </span>
+                    <input
+                      type="range"
+                      min="0" max="1000" step="10"
+                      value={mirror.delay_ms}
+                      title="Delay the copy so both outputs line up"
+                      onChange={(e) => changeMirrorDelay(mirror.pid, parseInt(e.target.value))}
+                      onMouseDown={(e) => e.stopPropagation()}
+                      onTouchStart={(e) => e.stopPropagation()}
+                      className="flex-1 h-1"
+                    />
+                    <span className="opacity-50 w-12 text-right">{mirror.delay_ms} ms</span>
+                  </div>
+                ))}
               </div>
             </div>
           ))}
         </section>
       </div>
 
+      {crashReport && (
+        <div className="flex items-center justify-between px-3 py-2 rounded-lg border border-red-500/40 bg-red-500/10 text-[10px] font-mono uppercase tracking-widest">
+          <span className="text-red-400 truncate">Pulse crashed last time</span>
+          <div className="flex gap-2">
+            <button onClick={openCrashReport} className="px-2 py-0.5 rounded bg-red-500/80 text-black font-bold">Open report</button>
+            <button onClick={dismissCrashReport} className="opacity-40 hover:opacity-100">×</button>
+          </div>
+        </div>
+      )}
+
+      {(update || updateStatus) && (
+        <div className="flex items-center justify-between px-3 py-2 rounded-lg border border-pulse-neon/30 bg-pulse-neon/5 text-[10px] font-mono uppercase tracking-widest">
+          {update ? (
+            <>
+              <span className="text-pulse-neon truncate">
+                v{update.version} available
+                {updateProgress && updateProgress.total ? ` // ${Math.floor((updateProgress.downloaded / updateProgress.total) * 100)}%` : ""}
+              </span>
+              <button
+                onClick={installUpdate}
+                disabled={updateProgress !== null}
+                className="px-2 py-0.5 rounded bg-pulse-neon text-black font-bold disabled:opacity-40"
+              >
+                {updateProgress ? "Installing" : "Install"}
+              </button>
+            </>
+          ) : (
+            <>
+              <span className="opacity-60">{updateStatus}</span>
+              <button onClick={() => setUpdateStatus(null)} className="opacity-40 hover:opacity-100">×</button>
+            </>
+          )}
+        </div>
+      )}
+
+      {history && (
+        <div className="flex flex-col gap-0.5 px-1 pb-2 max-h-32 overflow-y-auto custom-scrollbar text-[8px] font-mono text-white/50">
+          {history.length === 0 && <span>NO VOLUME CHANGES YET</span>}
+          {history.map((entry) => (
+            <span key={`${entry.time}-${JSON.stringify(entry.target)}`} className={entry.origin === "Mixer" ? "" : "text-amber-300/70"}>
+              {new Date(entry.time).toLocaleTimeString()} · {entry.target.kind === "Master" ? "MASTER" : entry.target.exe_name || `PID ${entry.target.pid}`} →{" "}
+              {entry.muted ? "MUTED" : `${Math.round(entry.volume * 100)}%`} · {entry.origin === "Mixer" ? "THIS MIXER" : entry.origin === "External" ? "OTHER APP" : "UNKNOWN"}
+            </span>
+          ))}
+        </div>
+      )}
+      {backups && (
+        <div className="flex flex-col gap-0.5 px-1 pb-2 max-h-32 overflow-y-auto custom-scrollbar text-[8px] font-mono text-white/50">
+          {backups.length === 0 && <span>NO BACKUPS YET</span>}
+          {backups.map((backup) => (
+            <button key={backup.file_name} onClick={() => restoreBackup(backup)} title="Restore these settings" className="text-left hover:text-pulse-neon">
+              {new Date(backup.created_at * 1000).toLocaleString()} · {Math.ceil(backup.size / 1024)} KB
+            </button>
+          ))}
+        </div>
+      )}
+      {usageRange && usage && (
+        <div className="flex gap-3 px-1 pb-2 text-[8px] font-mono text-white/50 overflow-x-auto custom-scrollbar">
+          {usage.apps.length === 0 && <span>NO PLAYBACK RECORDED</span>}
+          {usage.apps.slice(0, 5).map((app) => (
+            <span key={app.exe_name} className="flex-shrink-0" title={`Average volume ${Math.round(app.average_volume * 100)}%`}>
+              {app.exe_name} · {formatDuration(app.active_secs)}
+            </span>
+          ))}
+        </div>
+      )}
       <footer className="pt-3 border-t border-white/5 flex justify-between items-center text-[8px] font-mono opacity-20 uppercase tracking-[0.3em]">
         <span>Build v4.0.0 Stable</span>
+        <button
+          onClick={cycleDeviceToast}
+          title="Notify when the default output device changes"
+          className="uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon"
+        >
+          Device toast: {DEVICE_TOAST_LABELS[deviceToast]}
+        </button>
+        <button
+          onClick={cycleHudMode}
+          title="Volume overlay. REPLACE WINDOWS hides the built-in Windows volume flyout and shows this one instead."
+          className="uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon"
+        >
+          HUD: {HUD_MODE_LABELS[hudMode]}
+        </button>
+        <button
+          onClick={() => setRecordingShortcut(true)}
+          title="Shortcut to open the mixer. Click, then press the new keys (Esc to cancel, Backspace to disable)."
+          className={`uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon ${recordingShortcut ? 'text-pulse-neon' : ''}`}
+        >
+          Hotkey: {recordingShortcut ? "PRESS KEYS…" : flyoutShortcut || "NONE"}
+        </button>
+        <button
+          onClick={cycleCorner}
+          title="Where the mixer opens from the tray. AUTO follows the taskbar; pick a corner if it opens in the wrong place."
+          className={`uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon ${placement.corner ? 'text-pulse-neon' : ''}`}
+        >
+          Placement: {placement.corner ? CORNER_LABELS[placement.corner] : "AUTO"}
+        </button>
+        <button
+          onClick={editOffset}
+          title="Shift the mixer from where it would open, for taskbars that are not detected correctly."
+          className={`uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon ${placement.offset_x || placement.offset_y ? 'text-pulse-neon' : ''}`}
+        >
+          Offset: {placement.offset_x}, {placement.offset_y}
+        </button>
+        <button
+          onClick={toggleElevatedHelper}
+          title="Start a helper with administrator rights so apps running as administrator show their names and respond to volume changes."
+          className={`uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon ${elevatedHelper ? 'text-pulse-neon' : ''}`}
+        >
+          Admin apps: {elevatedHelper ? "ON" : "OFF"}
+        </button>
+        {soundSchemes && (
+          <button
+            onClick={cycleSoundScheme}
+            title="Windows sound scheme for system sounds. Choose No Sounds to silence system dings."
+            className="uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon"
+          >
+            System sounds: {soundSchemes.schemes.find((s) => s.id === soundSchemes.current)?.name ?? soundSchemes.current}
+          </button>
+        )}
+        <button
+          onClick={cycleVolumeLimit}
+          title="Hearing protection: the output volume is pulled back to this level whenever anything raises it higher"
+          className={`uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon ${volumeLimit.limit !== null ? 'text-pulse-neon' : ''}`}
+        >
+          Limit: {volumeLimit.limit === null ? "OFF" : `${Math.round(volumeLimit.limit * 100)}%`}
+        </button>
+        {volumeLimit.limit !== null && (
+          <button
+            onClick={toggleVolumeLimitOverride}
+            title="Allow volume above the limit for 15 minutes"
+            className={`uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon ${volumeLimit.override_remaining_secs !== null ? 'text-pulse-neon' : ''}`}
+          >
+            {volumeLimit.override_remaining_secs !== null ? "End override" : "Override"}
+          </button>
+        )}
+        <button
+          onClick={toggleHistory}
+          title="Recent volume and mute changes, and whether this mixer or another app made them"
+          className={`uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon ${history ? 'text-pulse-neon' : ''}`}
+        >
+          History
+        </button>
+        <button
+          onClick={cycleUsageRange}
+          title="Which apps played audio the longest. Click to switch between the last day, week and 30 days."
+          className={`uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon ${usageRange ? 'text-pulse-neon' : ''}`}
+        >
+          Usage: {usageRange ?? "HIDDEN"}
+        </button>
+        <button
+          onClick={editBackupFolder}
+          title={backupConfig ? `Settings are backed up to ${backupConfig.dir}` : "Back up the settings to a folder, such as a OneDrive folder, so a reinstall doesn't lose them"}
+          className={`uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon ${backupConfig ? 'text-pulse-neon' : ''}`}
+        >
+          Backup: {backupConfig ? "ON" : "OFF"}
+        </button>
+        {backupConfig && (
+          <button
+            onClick={toggleBackups}
+            title="Pick a backup to restore"
+            className={`uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon ${backups ? 'text-pulse-neon' : ''}`}
+          >
+            Restore
+          </button>
+        )}
+        <button
+          onClick={() => undoOrRedo("undo")}
+          disabled={!undoStatus.can_undo}
+          title="Undo the last volume, mute, route or default device change (Ctrl+Z)"
+          className="uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon disabled:opacity-30"
+        >
+          Undo
+        </button>
+        <button
+          onClick={() => undoOrRedo("redo")}
+          disabled={!undoStatus.can_redo}
+          title="Redo the change you undid (Ctrl+Y)"
+          className="uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon disabled:opacity-30"
+        >
+          Redo
+        </button>
+        <button
+          onClick={refreshAudioState}
+          title="Reload every app, device and icon if something looks out of date"
+          className="uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon"
+        >
+          Refresh
+        </button>
+        {(["Csv", "Json"] as const).map((format) => (
+          <button
+            key={format}
+            onClick={() => exportMixerState(format)}
+            title={exportedPath ? `Saved to ${exportedPath}` : "Save the apps, volumes, routes and devices to the Downloads folder"}
+            className="uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon"
+          >
+            Export {format}
+          </button>
+        ))}
+        <button
+          onClick={() => invoke("open_sound_settings", { page: "Sound" }).catch((e) => console.error("Failed to open sound settings", e))}
+          title="Open the Windows sound settings for anything this mixer can't change"
+          className="uppercase tracking-[0.3em] hover:opacity-100 hover:text-pulse-neon"
+        >
+          Windows settings
+        </button>
         <span>Antigravity Engine // Pulse v2</span>
       </footer>
     </main>
@@ -0,0 +1,53 @@
+import { Component, ErrorInfo, ReactNode } from "react";
+import { invoke } from "@tauri-apps/api/core";
+
+interface State {
+  error: Error | null;
+  reportSaved: boolean;
+}
+
+// 描画中の例外でウィンドウが真っ白になる代わりに、クラッシュレポートを書き出して案内する
+class ErrorBoundary extends Component<{ children: ReactNode }, State> {
+  state: State = { error: null, reportSaved: false };
+
+  static getDerivedStateFromError(error: Error) {
+    return { error };
+  }
+
+  componentDidCatch(error: Error, info: ErrorInfo) {
+    const stack = [error.stack, info.componentStack].filter(Boolean).join("\n\ncomponent stack:");
+    invoke("report_frontend_crash", { message: error.message, stack })
+      .then(() => this.setState({ reportSaved: true }))
+      .catch((e) => console.error("Failed to save crash report", e));
+  }
+
+  render() {
+    const { error, reportSaved } = this.state;
+    if (!error) return this.props.children;
+
+    return (
+      <main className="flex flex-col h-screen p-4 space-y-3 select-none font-mono text-[10px] uppercase tracking-widest">
+        <h1 className="text-sm font-black text-red-400">Pulse crashed</h1>
+        <p className="opacity-60 normal-case tracking-normal break-words">{error.message}</p>
+        <div className="flex gap-2">
+          {reportSaved && (
+            <button
+              onClick={() => invoke("open_crash_report")}
+              className="px-2 py-1 rounded border border-white/20 hover:border-pulse-neon/60"
+            >
+              Open report
+            </button>
+          )}
+          <button
+            onClick={() => window.location.reload()}
+            className="px-2 py-1 rounded bg-pulse-neon text-black font-bold"
+          >
+            Reload
+          </button>
+        </div>
+      </main>
+    );
+  }
+}
+
+export default ErrorBoundary;
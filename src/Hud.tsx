@@ -0,0 +1,38 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import type { MasterVolume } from "./bindings";
+
+/// 音量 HUD ウィンドウの中身。Rust 側の "hud-update" イベントで更新する
+function Hud() {
+  const [master, setMaster] = useState<MasterVolume | null>(null);
+
+  useEffect(() => {
+    // HUD はミキサーのイベントを使わないので、ピーク値などを送らせない
+    invoke("subscribe_events", { categories: [] }).catch((e) => console.error("Failed to unsubscribe from mixer events", e));
+    const unlisten = listen<MasterVolume>("hud-update", (event) => setMaster(event.payload));
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  if (!master) return null;
+  const percent = Math.round(master.volume * 100);
+
+  return (
+    <div className="h-screen flex items-center px-4 space-x-3 rounded-xl border border-pulse-neon/30 bg-black/70 select-none">
+      <span className={`text-[10px] font-black font-mono w-10 ${master.is_muted ? 'text-red-400' : 'text-pulse-neon'}`}>
+        {master.is_muted ? "MUTE" : "VOL"}
+      </span>
+      <div className="flex-1 h-1.5 rounded-full bg-white/10 overflow-hidden">
+        <div
+          className={`h-full transition-all duration-100 ${master.is_muted ? 'bg-red-500/40' : 'bg-pulse-neon shadow-[0_0_8px_#00f2ff]'}`}
+          style={{ width: `${percent}%` }}
+        />
+      </div>
+      <span className="text-[11px] font-mono text-white/70 w-8 text-right">{percent}</span>
+    </div>
+  );
+}
+
+export default Hud;
@@ -0,0 +1,1673 @@
+// tauri-specta が生成したファイルです。編集しないでください。
+// This file was generated by [tauri-specta](https://github.com/oscartbeaumont/tauri-specta). Do not edit this file manually.
+
+/** user-defined commands **/
+
+
+export const commands = {
+async getAudioSessions() : Promise<AudioSessionInfo[]> {
+    return await TAURI_INVOKE("get_audio_sessions");
+},
+async getSessionsLite() : Promise<AudioSessionInfo[]> {
+    return await TAURI_INVOKE("get_sessions_lite");
+},
+async getSession(pid: number) : Promise<AudioSessionInfo> {
+    return await TAURI_INVOKE("get_session", { pid });
+},
+async getSessionIcon(pid: number) : Promise<string | null> {
+    return await TAURI_INVOKE("get_session_icon", { pid });
+},
+/**
+ * `instance_id` を指定した場合はそのセッションだけ、省略した場合はプロセスのすべてのセッションを変更します。
+ * 管理者として動いているアプリなどで失敗したら、昇格ヘルパーが動いていればそちらで設定し直します。
+ */
+async setSessionVolume(pid: number, volume: number, instanceId: string | null) : Promise<null> {
+    return await TAURI_INVOKE("set_session_volume", { pid, volume, instanceId });
+},
+async setSessionMute(pid: number, mute: boolean, instanceId: string | null) : Promise<null> {
+    return await TAURI_INVOKE("set_session_mute", { pid, mute, instanceId });
+},
+/**
+ * アプリに再生・一時停止・曲送りなどを送ります。セッションに `now_playing` のあるアプリだけが受け付けます。
+ */
+async sendMediaCommand(pid: number, command: MediaCommand) : Promise<null> {
+    return await TAURI_INVOKE("send_media_command", { pid, command });
+},
+async setAudioRouting(pid: number, deviceId: string) : Promise<null> {
+    return await TAURI_INVOKE("set_audio_routing", { pid, deviceId });
+},
+/**
+ * `set_audio_routing` と同じく出力先を切り替え（取り消せる操作として記録し）、反映されなければストリームを移すよう促します。
+ */
+async forceAudioRouting(pid: number, deviceId: string) : Promise<RouteMigration> {
+    return await TAURI_INVOKE("force_audio_routing", { pid, deviceId });
+},
+async getAudioDevices() : Promise<AudioDeviceInfo[]> {
+    return await TAURI_INVOKE("get_audio_devices");
+},
+async setDefaultDevice(deviceId: string) : Promise<null> {
+    return await TAURI_INVOKE("set_default_device", { deviceId });
+},
+async getCaptureDevices() : Promise<AudioDeviceInfo[]> {
+    return await TAURI_INVOKE("get_capture_devices");
+},
+/**
+ * セッションを持たない古い API で再生しているらしいアプリ。ウィンドウを持つプロセスのモジュールを調べるので、一覧を開いたときだけ呼びます。
+ */
+async getLegacyStreams() : Promise<LegacyStream[]> {
+    return await TAURI_INVOKE("get_legacy_streams");
+},
+/**
+ * 既定のマイクを切り替えます。`role` を省略するとすべての役割で既定にします。
+ */
+async setDefaultCaptureDevice(deviceId: string, role: DeviceRole | null) : Promise<null> {
+    return await TAURI_INVOKE("set_default_capture_device", { deviceId, role });
+},
+/**
+ * 有効になっている仮想ケーブル
+ */
+async getVirtualCables() : Promise<VirtualCable[]> {
+    return await TAURI_INVOKE("get_virtual_cables");
+},
+/**
+ * アプリの出力先を仮想ケーブルにし、`monitor_device_id` を指定すればケーブルに流れる音をその出力デバイスでも聴けるようにします。
+ * 聴く設定を先に行うので、失敗したときは出力先は変わりません。出力先の切り替えは取り消せる操作として記録します。
+ */
+async routeAppThroughCable(pid: number, cableDeviceId: string, monitorDeviceId: string | null) : Promise<VirtualCable> {
+    return await TAURI_INVOKE("route_app_through_cable", { pid, cableDeviceId, monitorDeviceId });
+},
+/**
+ * ケーブルに流れる音を `monitor_device_id` の出力デバイスで聴きます。`None` ならやめます。
+ */
+async monitorVirtualCable(cableDeviceId: string, monitorDeviceId: string | null) : Promise<null> {
+    return await TAURI_INVOKE("monitor_virtual_cable", { cableDeviceId, monitorDeviceId });
+},
+/**
+ * VoiceMeeter のストリップとバス。有効にしていないか、VoiceMeeter が起動していなければ `None`
+ */
+async getVoicemeeter() : Promise<VoiceMeeterState | null> {
+    return await TAURI_INVOKE("get_voicemeeter");
+},
+/**
+ * VoiceMeeter の操作を有効・無効にします。有効にするときは Remote API にログインできたときだけ設定を保存します。
+ */
+async setVoicemeeterEnabled(enabled: boolean) : Promise<null> {
+    return await TAURI_INVOKE("set_voicemeeter_enabled", { enabled });
+},
+/**
+ * ストリップかバスのゲインを dB で設定します。範囲 (-60〜+12 dB) の外は丸めます。
+ */
+async setVoicemeeterGain(kind: ChannelKind, index: number, gainDb: number) : Promise<null> {
+    return await TAURI_INVOKE("set_voicemeeter_gain", { kind, index, gainDb });
+},
+async setVoicemeeterMute(kind: ChannelKind, index: number, muted: boolean) : Promise<null> {
+    return await TAURI_INVOKE("set_voicemeeter_mute", { kind, index, muted });
+},
+/**
+ * マイクの入力レベルの計測を始めます。結果は `input-pulse` イベントで届きます。
+ */
+async startInputMeter(deviceId: string | null) : Promise<null> {
+    return await TAURI_INVOKE("start_input_meter", { deviceId });
+},
+async stopInputMeter() : Promise<null> {
+    return await TAURI_INVOKE("stop_input_meter");
+},
+async getInputGain(deviceId: string) : Promise<InputGain> {
+    return await TAURI_INVOKE("get_input_gain", { deviceId });
+},
+async setInputGain(deviceId: string, db: number) : Promise<null> {
+    return await TAURI_INVOKE("set_input_gain", { deviceId, db });
+},
+async setInputBoost(deviceId: string, db: number) : Promise<null> {
+    return await TAURI_INVOKE("set_input_boost", { deviceId, db });
+},
+async getCaptureEffects(deviceId: string) : Promise<CaptureEffect[]> {
+    return await TAURI_INVOKE("get_capture_effects", { deviceId });
+},
+async setCaptureEffect(deviceId: string, kind: CaptureEffectKind, enabled: boolean) : Promise<null> {
+    return await TAURI_INVOKE("set_capture_effect", { deviceId, kind, enabled });
+},
+/**
+ * いまマイクを使っているアプリ
+ */
+async getMicrophoneUsers() : Promise<MicrophoneUser[]> {
+    return await TAURI_INVOKE("get_microphone_users");
+},
+async getCallPanel() : Promise<CallPanel> {
+    return await TAURI_INVOKE("get_call_panel");
+},
+/**
+ * 通話アプリのセッションすべてと、通信用の既定の出力デバイス・マイクの音量をまとめて設定します。
+ * セッションは `set_session_volume` と同じく取り消せ、管理者として動いているアプリには昇格ヘルパーを使います。
+ */
+async setCallVolume(volume: number) : Promise<null> {
+    return await TAURI_INVOKE("set_call_volume", { volume });
+},
+async isElevatedHelperRunning() : Promise<boolean> {
+    return await TAURI_INVOKE("is_elevated_helper_running");
+},
+/**
+ * ヘルパーを管理者として起動します。UAC の確認が出ます。すでに動いていれば何もしません。
+ */
+async startElevatedHelper() : Promise<null> {
+    return await TAURI_INVOKE("start_elevated_helper");
+},
+/**
+ * パイプを閉じてヘルパーを終了させます。
+ */
+async stopElevatedHelper() : Promise<null> {
+    return await TAURI_INVOKE("stop_elevated_helper");
+},
+async getSoundSchemes() : Promise<SoundSchemes> {
+    return await TAURI_INVOKE("get_sound_schemes");
+},
+async setSoundScheme(id: string) : Promise<null> {
+    return await TAURI_INVOKE("set_sound_scheme", { id });
+},
+async playSystemSound(event: string) : Promise<null> {
+    return await TAURI_INVOKE("play_system_sound", { event });
+},
+async openSoundSettings(page: SoundSettingsPage) : Promise<null> {
+    return await TAURI_INVOKE("open_sound_settings", { page });
+},
+async getUsageStats(range: UsageRange) : Promise<UsageStats> {
+    return await TAURI_INVOKE("get_usage_stats", { range });
+},
+/**
+ * 新しい順に最大 `limit` 件（省略時はすべて）
+ */
+async getVolumeHistory(limit: number | null) : Promise<HistoryEntry[]> {
+    return await TAURI_INVOKE("get_volume_history", { limit });
+},
+async clearVolumeHistory() : Promise<null> {
+    return await TAURI_INVOKE("clear_volume_history");
+},
+/**
+ * いまの状態をダウンロードフォルダ（なければドキュメントフォルダ）に書き出し、書き出したファイルのパスを返します。
+ * `file_name` はフォルダの外を指せないファイル名だけを受け付け、省略すると時刻から付けます。
+ * 同じ名前のファイルがあれば、`overwrite` のときだけ上書きします。
+ */
+async exportMixerState(format: ExportFormat, fileName: string | null, overwrite: boolean) : Promise<string> {
+    return await TAURI_INVOKE("export_mixer_state", { format, fileName, overwrite });
+},
+async getVolumeLimit() : Promise<VolumeLimitStatus> {
+    return await TAURI_INVOKE("get_volume_limit");
+},
+/**
+ * 上限を設定します（`None` で解除）。一時解除中であれば終わらせ、すぐに上限を適用します。
+ */
+async setVolumeLimit(limit: number | null) : Promise<null> {
+    return await TAURI_INVOKE("set_volume_limit", { limit });
+},
+/**
+ * 一時解除の確認コードを発行します。UI で確認を取ってから `confirm_volume_limit_override` に渡してください。
+ */
+async requestVolumeLimitOverride() : Promise<number> {
+    return await TAURI_INVOKE("request_volume_limit_override");
+},
+/**
+ * 確認コードが正しければ、一定時間だけ上限を外します。時間が過ぎたら音量を上限まで戻します。
+ */
+async confirmVolumeLimitOverride(code: number) : Promise<null> {
+    return await TAURI_INVOKE("confirm_volume_limit_override", { code });
+},
+/**
+ * 一時解除を終わらせ、すぐに上限を適用します。
+ */
+async endVolumeLimitOverride() : Promise<null> {
+    return await TAURI_INVOKE("end_volume_limit_override");
+},
+async getDevicePairs() : Promise<DevicePair[]> {
+    return await TAURI_INVOKE("get_device_pairs");
+},
+/**
+ * 2 つのデバイスを組にし、`second` の音量とミュートを `first` に合わせます。
+ */
+async addDevicePair(first: string, second: string) : Promise<null> {
+    return await TAURI_INVOKE("add_device_pair", { first, second });
+},
+async removeDevicePair(first: string, second: string) : Promise<null> {
+    return await TAURI_INVOKE("remove_device_pair", { first, second });
+},
+async getAppRules() : Promise<AppRule[]> {
+    return await TAURI_INVOKE("get_app_rules");
+},
+/**
+ * ルールを最後に加えます。すでに動いているアプリには適用せず、次にセッションが現れたときから使います。
+ */
+async addAppRule(rule: AppRule) : Promise<AppRule[]> {
+    return await TAURI_INVOKE("add_app_rule", { rule });
+},
+async removeAppRule(index: number) : Promise<AppRule[]> {
+    return await TAURI_INVOKE("remove_app_rule", { index });
+},
+async getProfiles() : Promise<ProfileList> {
+    return await TAURI_INVOKE("get_profiles");
+},
+/**
+ * 今の状態を名前を付けて保存します。同じ名前のプロファイルは上書きします。
+ */
+async saveProfile(name: string) : Promise<ProfileList> {
+    return await TAURI_INVOKE("save_profile", { name });
+},
+async applyProfile(name: string) : Promise<null> {
+    return await TAURI_INVOKE("apply_profile", { name });
+},
+async removeProfile(name: string) : Promise<ProfileList> {
+    return await TAURI_INVOKE("remove_profile", { name });
+},
+/**
+ * 起動時に適用するプロファイルを選びます（`None` で適用しない）。
+ */
+async setStartupProfile(name: string | null) : Promise<ProfileList> {
+    return await TAURI_INVOKE("set_startup_profile", { name });
+},
+async getBackupConfig() : Promise<BackupConfig | null> {
+    return await TAURI_INVOKE("get_backup_config");
+},
+/**
+ * バックアップ先を選びます（`None` で止める）。フォルダがなければ作ります。
+ */
+async setBackupConfig(config: BackupConfig | null) : Promise<null> {
+    return await TAURI_INVOKE("set_backup_config", { config });
+},
+/**
+ * 今すぐ写します。前回と同じ中身なら何もせず `None` を返します。
+ */
+async backUpSettings() : Promise<BackupEntry | null> {
+    return await TAURI_INVOKE("back_up_settings");
+},
+async getSettingsBackups() : Promise<BackupEntry[]> {
+    return await TAURI_INVOKE("get_settings_backups");
+},
+/**
+ * バックアップ先のフォルダにあるバックアップで設定を置き換え、すぐに適用します。
+ * 戻す前の設定も、バックアップとして残します。
+ */
+async restoreSettingsBackup(fileName: string) : Promise<null> {
+    return await TAURI_INVOKE("restore_settings_backup", { fileName });
+},
+/**
+ * 直前の操作を取り消します。取り消せなかった操作（アプリが終了したなど）は捨てます。
+ */
+async undo() : Promise<UndoStatus> {
+    return await TAURI_INVOKE("undo");
+},
+/**
+ * 取り消した操作をやり直します。
+ */
+async redo() : Promise<UndoStatus> {
+    return await TAURI_INVOKE("redo");
+},
+async getUndoStatus() : Promise<UndoStatus> {
+    return await TAURI_INVOKE("get_undo_status");
+},
+/**
+ * 呼び出した webview が受け取るイベントに `categories` と `pids` を加えます。初めて絞り込むと、
+ * 加えたもの以外は届かなくなります。省略した方はこれまでどおりです。
+ */
+async subscribeEvents(categories: EventCategory[] | null, pids: number[] | null) : Promise<EventFilter> {
+    return await TAURI_INVOKE("subscribe_events", { categories, pids });
+},
+/**
+ * 呼び出した webview の購読から `categories` と `pids` を外します。どちらも省略すると絞り込みをやめ、すべてを受け取ります。
+ */
+async unsubscribeEvents(categories: EventCategory[] | null, pids: number[] | null) : Promise<EventFilter> {
+    return await TAURI_INVOKE("unsubscribe_events", { categories, pids });
+},
+/**
+ * 呼び出した webview の購読内容。`None` はすべてを受け取っていることを表します。
+ */
+async getEventSubscriptions() : Promise<EventFilter> {
+    return await TAURI_INVOKE("get_event_subscriptions");
+},
+async getApiVersion() : Promise<ApiVersion> {
+    return await TAURI_INVOKE("get_api_version");
+},
+/**
+ * 呼び出した webview に送るイベントの形式の版を選びます。連携側が扱える最も新しい版を渡してください。
+ */
+async setEventSchema(version: number) : Promise<ApiVersion> {
+    return await TAURI_INVOKE("set_event_schema", { version });
+},
+/**
+ * セッション一覧の全体を送ってから、差分を送り続けます。ウィンドウが隠されるか閉じられると止まるので、
+ * 開き直したら呼び直してください。同じウィンドウで呼び直すと、前のストリームは止まります。
+ */
+async watchSessions(channel: TAURI_CHANNEL<SessionStreamMessage>) : Promise<null> {
+    return await TAURI_INVOKE("watch_sessions", { channel });
+},
+async setBluetoothProfile(deviceId: string, profile: BluetoothProfile) : Promise<null> {
+    return await TAURI_INVOKE("set_bluetooth_profile", { deviceId, profile });
+},
+async probeDeviceFormats(deviceId: string) : Promise<DeviceFormat[]> {
+    return await TAURI_INVOKE("probe_device_formats", { deviceId });
+},
+/**
+ * テストトーンを再生します。`output` でサンプルレート・ビット深度・バッファの長さと、排他モードにするかを選べます。
+ * 省略するとデバイスのミックスフォーマットで共有モードに出力します。
+ */
+async playTone(deviceId: string | null, tone: ToneRequest, output: OutputFormat | null) : Promise<null> {
+    return await TAURI_INVOKE("play_tone", { deviceId, tone, output });
+},
+/**
+ * 和音や DTMF の数字の並びを再生します。
+ */
+async playSignal(deviceId: string | null, signal: Signal, output: OutputFormat | null) : Promise<null> {
+    return await TAURI_INVOKE("play_signal", { deviceId, signal, output });
+},
+/**
+ * メトロノームを始めます。止めるときは `stop_playback` を使います。
+ */
+async startMetronome(deviceId: string | null, metronome: Metronome, output: OutputFormat | null) : Promise<null> {
+    return await TAURI_INVOKE("start_metronome", { deviceId, metronome, output });
+},
+async setMetronomeTempo(bpm: number) : Promise<null> {
+    return await TAURI_INVOKE("set_metronome_tempo", { bpm });
+},
+/**
+ * 音声ファイル（WAV / FLAC / Ogg Vorbis / MP3）を再生します。再生位置は `playback-progress` イベントで届きます。
+ */
+async playFile(deviceId: string | null, path: string, output: OutputFormat | null) : Promise<null> {
+    return await TAURI_INVOKE("play_file", { deviceId, path, output });
+},
+async seekPlayback(positionMs: number) : Promise<null> {
+    return await TAURI_INVOKE("seek_playback", { positionMs });
+},
+/**
+ * 複数のデバイスで同じ信号を同時に鳴らします。デバイスごとの遅れは `set_playback_delay` で鳴らしながら合わせられます。
+ */
+async playSynced(signal: Signal, outputs: SyncedOutput[], output: OutputFormat | null) : Promise<null> {
+    return await TAURI_INVOKE("play_synced", { signal, outputs, output });
+},
+async setPlaybackDelay(deviceId: string, delayMs: number) : Promise<null> {
+    return await TAURI_INVOKE("set_playback_delay", { deviceId, delayMs });
+},
+/**
+ * エージングを始めます。振幅は [`audio::MAX_BURN_IN_AMPLITUDE`] を超えられません。
+ */
+async startBurnIn(deviceId: string | null, burnIn: BurnIn, output: OutputFormat | null) : Promise<null> {
+    return await TAURI_INVOKE("start_burn_in", { deviceId, burnIn, output });
+},
+/**
+ * 実行中のエージングの進み具合。動いていなければ `None`
+ */
+async getBurnIn() : Promise<BurnInProgress | null> {
+    return await TAURI_INVOKE("get_burn_in");
+},
+async stopBurnIn() : Promise<null> {
+    return await TAURI_INVOKE("stop_burn_in");
+},
+/**
+ * 測定の間（スイープの長さ + 1 秒ほど）待ってから結果を返します。ジェネレーターの再生中のものは止めます。
+ */
+async measureFrequencyResponse(request: MeasurementRequest) : Promise<FrequencyResponse> {
+    return await TAURI_INVOKE("measure_frequency_response", { request });
+},
+async stopPlayback() : Promise<null> {
+    return await TAURI_INVOKE("stop_playback");
+},
+/**
+ * アプリの音を `device_id` にも流します。止まると `mirror-stopped` イベントが届きます。
+ */
+async startMirror(pid: number, deviceId: string, delayMs: number) : Promise<null> {
+    return await TAURI_INVOKE("start_mirror", { pid, deviceId, delayMs });
+},
+async setMirrorDelay(pid: number, delayMs: number) : Promise<null> {
+    return await TAURI_INVOKE("set_mirror_delay", { pid, delayMs });
+},
+async stopMirror(pid: number) : Promise<null> {
+    return await TAURI_INVOKE("stop_mirror", { pid });
+},
+async getMirrors() : Promise<MirrorRequest[]> {
+    return await TAURI_INVOKE("get_mirrors");
+},
+/**
+ * アプリの音を `gain_db` だけ大きくします。アプリが終わるか止めるまで続き、止まると `boost-stopped` イベントが届きます。
+ */
+async startBoost(pid: number, gainDb: number) : Promise<null> {
+    return await TAURI_INVOKE("start_boost", { pid, gainDb });
+},
+async setBoostGain(pid: number, gainDb: number) : Promise<null> {
+    return await TAURI_INVOKE("set_boost_gain", { pid, gainDb });
+},
+async stopBoost(pid: number) : Promise<null> {
+    return await TAURI_INVOKE("stop_boost", { pid });
+},
+async getBoosts() : Promise<BoostRequest[]> {
+    return await TAURI_INVOKE("get_boosts");
+},
+/**
+ * システムの音をファイルに録り始めます。止まると `file-capture-stopped` イベントが届きます。
+ */
+async startFileCapture(request: FileCaptureRequest) : Promise<null> {
+    return await TAURI_INVOKE("start_file_capture", { request });
+},
+/**
+ * ファイルへの録音を止めて、作ったファイルのパスを返します。
+ */
+async stopFileCapture() : Promise<string[]> {
+    return await TAURI_INVOKE("stop_file_capture");
+},
+/**
+ * 取り込んだ音を HTTP で配信し始めます。止まると `network-stream-stopped` イベントが届きます。
+ */
+async startNetworkStream(request: NetworkStreamRequest) : Promise<NetworkStream> {
+    return await TAURI_INVOKE("start_network_stream", { request });
+},
+async stopNetworkStream() : Promise<null> {
+    return await TAURI_INVOKE("stop_network_stream");
+},
+async getNetworkStream() : Promise<NetworkStream | null> {
+    return await TAURI_INVOKE("get_network_stream");
+},
+async getMasterVolume() : Promise<MasterVolume> {
+    return await TAURI_INVOKE("get_master_volume");
+},
+async refreshAudioState() : Promise<AudioState> {
+    return await TAURI_INVOKE("refresh_audio_state");
+},
+async getAudioSummary() : Promise<AudioSummary> {
+    return await TAURI_INVOKE("get_audio_summary");
+},
+async setMasterVolume(volume: number) : Promise<null> {
+    return await TAURI_INVOKE("set_master_volume", { volume });
+},
+/**
+ * マスター音量を dB で設定します。設定できる範囲は `get_master_volume` の `level` にあります。
+ */
+async setMasterVolumeDb(db: number) : Promise<null> {
+    return await TAURI_INVOKE("set_master_volume_db", { db });
+},
+async setMasterMute(mute: boolean) : Promise<null> {
+    return await TAURI_INVOKE("set_master_mute", { mute });
+},
+/**
+ * マスター音量を `delta` の向きに設定の刻みで 1 段変えて HUD を表示します。ミキサー上のホイール操作用
+ */
+async stepMasterVolume(delta: number) : Promise<null> {
+    return await TAURI_INVOKE("step_master_volume", { delta });
+},
+async getHiddenApps() : Promise<string[]> {
+    return await TAURI_INVOKE("get_hidden_apps");
+},
+async hideApp(exeName: string) : Promise<null> {
+    return await TAURI_INVOKE("hide_app", { exeName });
+},
+async unhideApp(exeName: string) : Promise<null> {
+    return await TAURI_INVOKE("unhide_app", { exeName });
+},
+async getPinnedApps() : Promise<string[]> {
+    return await TAURI_INVOKE("get_pinned_apps");
+},
+/**
+ * ピン留めしたアプリはミュートされるとトレイアイコンにバッジが付きます。
+ */
+async pinApp(exeName: string) : Promise<null> {
+    return await TAURI_INVOKE("pin_app", { exeName });
+},
+async unpinApp(exeName: string) : Promise<null> {
+    return await TAURI_INVOKE("unpin_app", { exeName });
+},
+async getDeviceChangeToast() : Promise<DeviceChangeToast> {
+    return await TAURI_INVOKE("get_device_change_toast");
+},
+async setDeviceChangeToast(mode: DeviceChangeToast) : Promise<null> {
+    return await TAURI_INVOKE("set_device_change_toast", { mode });
+},
+async getPauseOnUnplug() : Promise<boolean> {
+    return await TAURI_INVOKE("get_pause_on_unplug");
+},
+async setPauseOnUnplug(enabled: boolean) : Promise<null> {
+    return await TAURI_INVOKE("set_pause_on_unplug", { enabled });
+},
+async getFlyoutPlacement() : Promise<FlyoutPlacement> {
+    return await TAURI_INVOKE("get_flyout_placement");
+},
+/**
+ * 次にトレイから開いたときから使います。
+ */
+async setFlyoutPlacement(placement: FlyoutPlacement) : Promise<null> {
+    return await TAURI_INVOKE("set_flyout_placement", { placement });
+},
+async getAppOverrides() : Promise<Partial<{ [key in string]: AppOverride }>> {
+    return await TAURI_INVOKE("get_app_overrides");
+},
+async setAppOverride(exeName: string, displayName: string | null, iconPath: string | null) : Promise<null> {
+    return await TAURI_INVOKE("set_app_override", { exeName, displayName, iconPath });
+},
+async isAutoLaunchEnabled() : Promise<boolean> {
+    return await TAURI_INVOKE("is_auto_launch_enabled");
+},
+async toggleAutoLaunch(enable: boolean) : Promise<null> {
+    return await TAURI_INVOKE("toggle_auto_launch", { enable });
+},
+async setTacticalMode(enabled: boolean) : Promise<null> {
+    return await TAURI_INVOKE("set_tactical_mode", { enabled });
+},
+/**
+ * ウィンドウの背景に使っている効果。フロントエンドはこれに合わせて下地の色を変えます。
+ */
+async getBackdrop() : Promise<Backdrop> {
+    return await TAURI_INVOKE("get_backdrop");
+},
+async getLocale() : Promise<Locale> {
+    return await TAURI_INVOKE("get_locale");
+},
+/**
+ * 新しいリリースを確認します。見つかった更新は `install_update` のために保持します。
+ */
+async checkForUpdate() : Promise<UpdateInfo | null> {
+    return await TAURI_INVOKE("check_for_update");
+},
+/**
+ * 保持している更新をダウンロードしてインストールし、アプリを再起動します。
+ * ダウンロード中は `update-progress` イベントで進捗を通知します。
+ */
+async installUpdate() : Promise<null> {
+    return await TAURI_INVOKE("install_update");
+},
+async getHudMode() : Promise<HudMode> {
+    return await TAURI_INVOKE("get_hud_mode");
+},
+async setHudMode(mode: HudMode) : Promise<null> {
+    return await TAURI_INVOKE("set_hud_mode", { mode });
+},
+async getVolumeStepping() : Promise<VolumeStepping> {
+    return await TAURI_INVOKE("get_volume_stepping");
+},
+async setVolumeStepping(stepping: VolumeStepping) : Promise<null> {
+    return await TAURI_INVOKE("set_volume_stepping", { stepping });
+},
+async getFlyoutShortcut() : Promise<string> {
+    return await TAURI_INVOKE("get_flyout_shortcut");
+},
+async setFlyoutShortcut(shortcut: string) : Promise<null> {
+    return await TAURI_INVOKE("set_flyout_shortcut", { shortcut });
+},
+async getHotkeys() : Promise<HotkeyBinding[]> {
+    return await TAURI_INVOKE("get_hotkeys");
+},
+/**
+ * ショートカットを割り当てます。ミキサーのショートカットや割り当て済みのもの、ほかのアプリが使っているものとは重ねられません。
+ */
+async addHotkey(binding: HotkeyBinding) : Promise<null> {
+    return await TAURI_INVOKE("add_hotkey", { binding });
+},
+async removeHotkey(index: number) : Promise<null> {
+    return await TAURI_INVOKE("remove_hotkey", { index });
+},
+/**
+ * ショートカットと重なっているもの。Windows のショートカットと重なっていても割り当てはできます。
+ */
+async checkHotkeyConflicts(shortcut: string, doubleTap: boolean) : Promise<HotkeyConflict[]> {
+    return await TAURI_INVOKE("check_hotkey_conflicts", { shortcut, doubleTap });
+},
+async getMediaKeyTarget() : Promise<MediaKeyTarget> {
+    return await TAURI_INVOKE("get_media_key_target");
+},
+async setMediaKeyTarget(target: MediaKeyTarget) : Promise<null> {
+    return await TAURI_INVOKE("set_media_key_target", { target });
+},
+/**
+ * 前回の実行で書き出されたクラッシュレポートのパスを返します。
+ */
+async getPendingCrashReport() : Promise<string | null> {
+    return await TAURI_INVOKE("get_pending_crash_report");
+},
+/**
+ * 案内中のクラッシュレポートを既定のアプリで開き、案内を終えます。
+ */
+async openCrashReport() : Promise<null> {
+    return await TAURI_INVOKE("open_crash_report");
+},
+async dismissCrashReport() : Promise<null> {
+    return await TAURI_INVOKE("dismiss_crash_report");
+},
+/**
+ * フロントエンドのエラーバウンダリで捕捉したエラーをレポートとして書き出します。
+ */
+async reportFrontendCrash(message: string, stack: string | null) : Promise<string> {
+    return await TAURI_INVOKE("report_frontend_crash", { message, stack });
+}
+}
+
+/** user-defined events **/
+
+
+export const events = __makeEvents__<{
+audioPulse: AudioPulse,
+backendReconnected: BackendReconnected,
+boostStopped: BoostStopped,
+deviceVolumeChange: DeviceVolumeChange,
+fileCaptureStopped: FileCaptureStopped,
+inputPulse: InputPulse,
+masterVolumeChange: MasterVolumeChange,
+mirrorStopped: MirrorStopped,
+networkStreamStopped: NetworkStreamStopped,
+playbackProgress: PlaybackProgress,
+playbackStopped: PlaybackStopped,
+processExited: ProcessExited,
+processStarted: ProcessStarted,
+refreshTrigger: RefreshTrigger,
+sessionIconReady: SessionIconReady,
+sessionStateChange: SessionStateChange,
+sessionsChanged: SessionsChanged,
+volumeChange: VolumeChange
+}>({
+audioPulse: "audio-pulse",
+backendReconnected: "backend-reconnected",
+boostStopped: "boost-stopped",
+deviceVolumeChange: "device-volume-change",
+fileCaptureStopped: "file-capture-stopped",
+inputPulse: "input-pulse",
+masterVolumeChange: "master-volume-change",
+mirrorStopped: "mirror-stopped",
+networkStreamStopped: "network-stream-stopped",
+playbackProgress: "playback-progress",
+playbackStopped: "playback-stopped",
+processExited: "process-exited",
+processStarted: "process-started",
+refreshTrigger: "refresh-trigger",
+sessionIconReady: "session-icon-ready",
+sessionStateChange: "session-state-change",
+sessionsChanged: "sessions-changed",
+volumeChange: "volume-change"
+})
+
+/** user-defined constants **/
+
+
+
+/** user-defined types **/
+
+export type ApiVersion = { version: number; 
+/**
+ * `set_event_schema` で選べる最も古い版
+ */
+min_supported: number; app_version: string }
+export type AppLevel = { volume: number; muted: boolean }
+/**
+ * 実行ファイルごとの表示名・アイコンの上書き
+ */
+export type AppOverride = { display_name: string | null; icon_path: string | null }
+/**
+ * 条件に合うセッションが現れたときに適用する出力先・音量・ミュート。`None` の項目は変えない
+ */
+export type AppRule = { matches: RuleMatch; 
+/**
+ * 実行ファイル名（大文字）で絞り込む。`None` ならすべてのアプリ
+ */
+exe_name: string | null; device_id: string | null; volume: number | null; muted: boolean | null }
+export type AppUsage = { exe_name: string; active_secs: number; average_volume: number }
+export type AudioDeviceInfo = { id: string; name: string; is_default: boolean; 
+/**
+ * 端子に何かが接続されているか。端子の状態を報告しないデバイスでは `None`
+ */
+jack_connected: boolean | null; 
+/**
+ * Bluetooth 機器の場合だけ `Some`
+ */
+bluetooth: BluetoothInfo | null }
+export type AudioPulse = PeakLevel[]
+export type AudioSessionInfo = { process_id: number; process_name: string; 
+/**
+ * 上書き設定のキーとなる実行ファイル名（表示名の変更に影響されない）
+ */
+exe_name: string; volume: number; is_muted: boolean; peak_level: number; 
+/**
+ * `ICON_SIZE` 四方の PNG (base64)
+ */
+icon_base64: string | null; 
+/**
+ * アイコンで最も多く使われている色 (`#rrggbb`)。アイコンがないか、色のないアイコンなら `None`
+ */
+accent_color: string | null; device_id: string; 
+/**
+ * セッションの識別子。同じプロセス・同じ出力先に複数のセッションがあっても区別できます
+ */
+instance_id: string; 
+/**
+ * 音を出している間は `Active`。セッションがあっても再生していなければ `Inactive` です
+ */
+state: SessionState; 
+/**
+ * アプリに永続的に設定された出力先。未設定なら `None`
+ */
+persisted_device_id: string | null; 
+/**
+ * 出力先を設定したが、まだ `device_id` に反映されていない（多くはアプリの再起動が必要）
+ */
+route_pending: boolean; 
+/**
+ * OS のメディアコントロールに出しているアプリの再生中の曲。対応していないアプリやプラットフォームでは `None`
+ */
+now_playing: NowPlaying | null }
+/**
+ * オーディオの状態の全体
+ */
+export type AudioState = { 
+/**
+ * セッション一覧。まだ抽出していないアイコンは後から `session-icon-ready` で届く
+ */
+sessions: AudioSessionInfo[]; devices: AudioDeviceInfo[]; capture_devices: AudioDeviceInfo[]; 
+/**
+ * 既定の出力デバイスがない場合は `None`
+ */
+master: MasterVolume | null }
+export type AudioSummary = { session_count: number; 
+/**
+ * 再生中 (`Active`) のセッションの数
+ */
+active_sessions: number; 
+/**
+ * マスター出力かいずれかのセッションがミュートされている
+ */
+any_muted: boolean; 
+/**
+ * 音を出しているセッションがなければ `None`
+ */
+loudest: LoudestApp | null; default_device: AudioDeviceInfo | null }
+/**
+ * ウィンドウの背景に使っている効果。どれも使えなければ、フロントエンドが不透明な下地を描きます。
+ */
+export type Backdrop = "mica" | "acrylic" | 
+/**
+ * Windows 10 のぼかし (blur-behind)
+ */
+"blur" | 
+/**
+ * macOS の NSVisualEffectView
+ */
+"vibrancy" | "solid"
+/**
+ * オーディオサービスにつなぎ直した。ペイロードはない
+ */
+export type BackendReconnected = null
+/**
+ * 設定の自動バックアップ。OneDrive などの同期フォルダを選べば、入れ直しても設定を戻せる
+ */
+export type BackupConfig = { dir: string; 
+/**
+ * 前回のバックアップからこの時間がたったら写す
+ */
+interval_hours: number; 
+/**
+ * 残しておくバックアップの数。古いものから消す
+ */
+keep: number }
+export type BackupEntry = { file_name: string; 
+/**
+ * UNIX 時刻（秒）
+ */
+created_at: number; size: number }
+export type BandLevel = { 
+/**
+ * 帯域の中心周波数 (Hz)
+ */
+frequency: number; 
+/**
+ * 鳴らしたスイープに対するレベル (dB)。そのまま返ってくれば 0
+ */
+level_db: number }
+export type BluetoothInfo = { 
+/**
+ * バッテリー残量 (0〜100)。機器や OS が報告しなければ `None`
+ */
+battery_percent: number | null; 
+/**
+ * 現在のプロファイル。判別できなければ `None`
+ */
+profile: BluetoothProfile | null }
+/**
+ * Bluetooth のオーディオプロファイル
+ */
+export type BluetoothProfile = 
+/**
+ * 高音質な再生専用のプロファイル
+ */
+"A2dp" | 
+/**
+ * マイクが使える通話用のプロファイル (HFP/HSP)。再生音質は大きく下がります
+ */
+"HandsFree"
+/**
+ * アプリ（子プロセスを含む）の音を 100% より大きくするブースト。声の小さい通話相手や音の小さい動画向け
+ */
+export type BoostRequest = { pid: number; 
+/**
+ * 上げる量 (dB)。0 より大きく [`MAX_BOOST_DB`] 以下
+ */
+gain_db: number }
+export type BoostStopped = { pid: number; error: string | null }
+/**
+ * スピーカーのエージング。ノイズを決まった時間鳴らして自動で止めます
+ */
+export type BurnIn = { 
+/**
+ * `WhiteNoise` か `PinkNoise`
+ */
+waveform: Waveform; 
+/**
+ * 0.0〜[`MAX_BURN_IN_AMPLITUDE`] の振幅
+ */
+amplitude: number; duration_ms: number; 
+/**
+ * 始めと終わりに音量を徐々に上げ下げする時間（ミリ秒）。0 なら上げ下げしない
+ */
+ramp_ms: number }
+export type BurnInProgress = { device_id: string | null; elapsed_ms: number; duration_ms: number; 
+/**
+ * いまの振幅
+ */
+level: number; 
+/**
+ * 時間が来たか、止められた
+ */
+finished: boolean }
+export type CallPanel = { 
+/**
+ * 通信用の既定の出力デバイス
+ */
+output: AudioDeviceInfo | null; 
+/**
+ * 通信用の既定のマイク
+ */
+input: AudioDeviceInfo | null; sessions: AudioSessionInfo[]; 
+/**
+ * 通話アプリの音量の平均。通話アプリがなければ `None`
+ */
+volume: number | null }
+/**
+ * ドライバー（APO）が公開している音声処理とその状態
+ */
+export type CaptureEffect = { kind: CaptureEffectKind; enabled: boolean; 
+/**
+ * ドライバーが切り替えを許している
+ */
+can_toggle: boolean }
+/**
+ * 入力デバイスの音声処理のうち、切り替えを扱うもの
+ */
+export type CaptureEffectKind = "EchoCancellation" | "NoiseSuppression" | "AutomaticGainControl"
+/**
+ * 録る音
+ */
+export type CaptureSource = 
+/**
+ * 出力デバイスに流れているすべての音。`None` なら既定の出力デバイス
+ */
+{ Device: string | null } | 
+/**
+ * アプリ（子プロセスを含む）の音だけ
+ */
+{ App: number }
+/**
+ * 音量・ミュートを変えたのが誰か
+ */
+export type ChangeOrigin = 
+/**
+ * このミキサー（を通したユーザーの操作）
+ */
+"Mixer" | 
+/**
+ * アプリ自身、OS の音量ミキサー、音量キーなど、ほかのプロセス
+ */
+"External" | 
+/**
+ * バックエンドが区別できない
+ */
+"Unknown"
+export type Channel = { kind: ChannelKind; index: number; 
+/**
+ * VoiceMeeter で付けた名前。付けていなければ空
+ */
+label: string; 
+/**
+ * VAIO・AUX などの仮想の入出力
+ */
+is_virtual: boolean; gain_db: number; muted: boolean }
+export type ChannelKind = 
+/**
+ * 入力
+ */
+"Strip" | 
+/**
+ * 出力
+ */
+"Bus"
+/**
+ * 和音の 1 つの音
+ */
+export type ChordTone = { 
+/**
+ * 周波数 (Hz)
+ */
+frequency: number; 
+/**
+ * 0.0〜1.0 の振幅。和音全体の合計も 1.0 以下にします
+ */
+amplitude: number }
+export type DeviceChangeToast = "Off" | "On" | 
+/**
+ * 元のデバイスへ戻す「元に戻す」ボタンを付ける
+ */
+"WithUndo"
+/**
+ * 出力のサンプルフォーマット
+ */
+export type DeviceFormat = { sample_rate: number; 
+/**
+ * 1 サンプルの有効ビット数
+ */
+bits_per_sample: number; 
+/**
+ * 1 サンプルが占めるビット数。24bit を 32bit に詰める場合は 32
+ */
+container_bits: number; 
+/**
+ * 浮動小数点形式か
+ */
+float: boolean; channels: number }
+/**
+ * 音量を揃えておく 2 つの出力デバイスの ID。どちらを変えても、もう一方が同じ音量になる
+ */
+export type DevicePair = { first: string; second: string }
+/**
+ * 既定のデバイスの役割。Windows では役割ごとに別のデバイスを既定にできます
+ */
+export type DeviceRole = 
+/**
+ * ゲームやシステム音など一般の用途
+ */
+"Console" | 
+/**
+ * 音楽・動画の再生や録音
+ */
+"Multimedia" | 
+/**
+ * 通話アプリ
+ */
+"Communications"
+export type DeviceVolumeChange = MasterVolume
+/**
+ * VoiceMeeter の種類。種類ごとにストリップとバスの数が違う
+ */
+export type Edition = "Standard" | "Banana" | "Potato"
+/**
+ * webview へ送るイベントの種類。送出するイベント名と 1 対 1 に対応します。
+ */
+export type EventCategory = 
+/**
+ * `volume-change`
+ */
+"Volume" | 
+/**
+ * `session-state-change`
+ */
+"SessionState" | 
+/**
+ * `sessions-changed`
+ */
+"Sessions" | 
+/**
+ * `session-icon-ready`
+ */
+"SessionIcon" | 
+/**
+ * `refresh-trigger`
+ */
+"Devices" | 
+/**
+ * `master-volume-change`
+ */
+"MasterVolume" | 
+/**
+ * `device-volume-change`
+ */
+"DeviceVolume" | 
+/**
+ * `audio-pulse`
+ */
+"Peaks" | 
+/**
+ * `input-pulse`
+ */
+"InputPeak" | 
+/**
+ * `backend-reconnected`
+ */
+"Backend" | 
+/**
+ * `playback-stopped`, `playback-progress`
+ */
+"Playback" | 
+/**
+ * `mirror-stopped`
+ */
+"Mirror" | 
+/**
+ * `boost-stopped`
+ */
+"Boost" | 
+/**
+ * `file-capture-stopped`
+ */
+"FileCapture" | 
+/**
+ * `network-stream-stopped`
+ */
+"NetworkStream" | 
+/**
+ * `process-started`, `process-exited`
+ */
+"Process"
+/**
+ * webview ごとの購読内容。`None` はすべてを表します。
+ */
+export type EventFilter = { categories: EventCategory[] | null; 
+/**
+ * PID を含むイベント（音量、状態、ピーク値、セッションの差分、ミラー、プロセスの起動と終了）だけに効きます
+ */
+pids: number[] | null; 
+/**
+ * 送る形式の版。`None` なら旧形式
+ */
+schema_version: number | null }
+export type ExportFormat = "Csv" | "Json"
+export type FileCaptureRequest = { source: CaptureSource; 
+/**
+ * 書き出すフォルダー。なければ作ります
+ */
+directory: string; 
+/**
+ * ファイル名の頭。`{file_prefix}-001.flac` のように番号を付けます
+ */
+file_prefix: string; encoding: FileEncoding; 
+/**
+ * `None` なら [`DEFAULT_CAPTURE_SAMPLE_RATE`]。取り込んだ音は OS がこのレートに変換します
+ */
+sample_rate: number | null; 
+/**
+ * 1 か 2
+ */
+channels: number; rotation?: Rotation }
+export type FileCaptureStopped = { files: string[]; error: string | null }
+export type FileEncoding = 
+/**
+ * 16 / 24bit の整数か、32bit の float
+ */
+{ Wav: { bits_per_sample: number } } | 
+/**
+ * 16 / 24bit
+ */
+{ Flac: { bits_per_sample: number } } | { OggOpus: { bitrate_kbps: number } }
+/**
+ * トレイから開くミキサーの位置の上書き（物理ピクセル）
+ */
+export type FlyoutPlacement = { 
+/**
+ * 決めた位置からずらす量。正の値で右・下へ動く
+ */
+offset_x: number; offset_y: number; 
+/**
+ * `Some` ならタスクバーの位置を判定せず、常にこの角に置く
+ */
+corner: ScreenCorner | null }
+export type FrequencyResponse = { bands: BandLevel[]; 
+/**
+ * 出力から録音までの遅れ (ms)
+ */
+latency_ms: number; 
+/**
+ * 録音の最大値 (dBFS)
+ */
+peak_db: number }
+/**
+ * dB で表したゲインと、設定できる範囲・刻み
+ */
+export type GainLevel = { db: number; min_db: number; max_db: number; step_db: number }
+export type HistoryEntry = { 
+/**
+ * UNIX 時刻（ミリ秒）
+ */
+time: number; target: HistoryTarget; volume: number; muted: boolean; origin: ChangeOrigin }
+export type HistoryTarget = { kind: "Session"; pid: number; exe_name: string } | { kind: "Master"; device_id: string }
+/**
+ * ショートカットで行う操作
+ */
+export type HotkeyAction = "VolumeUp" | "VolumeDown" | "ToggleMute" | 
+/**
+ * デバイスを既定にする、プロファイルを適用する
+ */
+"Activate"
+/**
+ * ショートカットと、それで行う操作
+ */
+export type HotkeyBinding = { 
+/**
+ * "Ctrl+Alt+KeyM" のような、修飾キー（いくつでも）とキーの組み合わせ
+ */
+shortcut: string; 
+/**
+ * 素早く 2 回押したときだけ行う。同じショートカットの 1 回押しの割り当ては、2 回目を待ってから行う
+ */
+double_tap?: boolean; action: HotkeyAction; target: HotkeyTarget }
+/**
+ * ショートカットが重なっているもの
+ */
+export type HotkeyConflict = 
+/**
+ * Windows のショートカット。割り当てられても Windows の機能と重なる
+ */
+{ kind: "Windows"; value: string } | 
+/**
+ * ミキサーを開くショートカット
+ */
+{ kind: "Flyout" } | 
+/**
+ * マスター音量の固定のショートカット
+ */
+{ kind: "MasterVolume" } | 
+/**
+ * 割り当て済みのショートカット（`hotkeys` の番号）
+ */
+{ kind: "Binding"; value: number } | 
+/**
+ * ほかのアプリが登録している
+ */
+{ kind: "OtherApp" }
+/**
+ * ショートカットで操作する対象
+ */
+export type HotkeyTarget = "Master" | 
+/**
+ * 前面のウィンドウのアプリ。セッションがなければマスター
+ */
+"FocusedApp" | 
+/**
+ * 実行ファイル名（大文字）で選んだアプリ。再生していなければマスター
+ */
+{ App: string } | 
+/**
+ * 出力デバイスの ID
+ */
+{ Device: string } | 
+/**
+ * プロファイルの名前。`Activate` だけに使える
+ */
+{ Profile: string }
+/**
+ * 1 時間ごとの全アプリの再生時間
+ */
+export type HourlyUsage = { 
+/**
+ * その時間の始まり（UNIX 時刻、秒）
+ */
+start: number; active_secs: number }
+export type HudMode = "Off" | 
+/**
+ * このアプリのホットキー・ホイールで音量を変えたときだけ表示する
+ */
+"On" | 
+/**
+ * Windows の音量フライアウトを隠し、外部からの音量の変更でも HUD を表示する
+ */
+"ReplaceNative"
+/**
+ * 入力デバイスのゲイン。`boost` はハードウェアのマイクブーストで、持たないデバイスでは `None` です
+ */
+export type InputGain = { device_id: string; volume: GainLevel; boost: GainLevel | null }
+export type InputPeak = { device_id: string; peak: number }
+export type InputPulse = InputPeak
+/**
+ * セッションを持たないことがある古い再生 API
+ */
+export type LegacyAudioApi = "DirectSound" | 
+/**
+ * waveOut など Windows のマルチメディア API
+ */
+"Mme"
+/**
+ * 自分のセッションが見当たらないのに、古い API で音を出しているらしいプロセス。
+ * 読み込んでいるモジュールから推測するだけなので、実際には鳴っていないこともあります
+ */
+export type LegacyStream = { pid: number; exe_name: string; api: LegacyAudioApi; 
+/**
+ * システム音のセッションが鳴っている。このプロセスの音がそちらにまとめられている可能性がある
+ */
+system_sounds_active: boolean }
+export type Locale = "en" | "ja"
+/**
+ * いちばん大きな音を出しているアプリ
+ */
+export type LoudestApp = { pid: number; process_name: string; peak: number }
+/**
+ * 既定の出力デバイス全体の音量
+ */
+export type MasterVolume = { device_id: string; volume: number; is_muted: boolean; 
+/**
+ * dB で表した音量と範囲。dB で扱えないバックエンドでは `None` です
+ */
+level: GainLevel | null }
+export type MasterVolumeChange = MasterVolume
+export type MeasurementRequest = { 
+/**
+ * スイープを鳴らすデバイス。`None` なら既定の出力デバイス
+ */
+output_device_id: string | null; 
+/**
+ * 録音するマイク。`None` なら既定の入力デバイス
+ */
+input_device_id: string | null; sweep: Sweep; 
+/**
+ * 1 オクターブをいくつの帯域に分けるか
+ */
+bands_per_octave: number }
+/**
+ * アプリへ送るメディアの操作
+ */
+export type MediaCommand = "Play" | "Pause" | "TogglePlayPause" | "Next" | "Previous"
+/**
+ * 音量キー（音量アップ・ダウン・ミュート）で変える対象。`System` 以外では OS に届く前にキーを横取りする
+ */
+export type MediaKeyTarget = 
+/**
+ * 横取りせず、OS がマスター音量を変える
+ */
+"System" | 
+/**
+ * このアプリがマスター音量を変え、HUD を表示する
+ */
+"Master" | 
+/**
+ * 前面のウィンドウのアプリ。セッションがなければマスター
+ */
+"FocusedApp" | 
+/**
+ * 実行ファイル名（大文字）で選んだアプリ。再生していなければマスター
+ */
+{ App: string }
+export type Metronome = { bpm: number; beats_per_bar: number; 
+/**
+ * 小節の頭の拍を高く大きな音にする
+ */
+accent: boolean; 
+/**
+ * 0.0〜1.0 の振幅
+ */
+amplitude: number }
+/**
+ * マイクを使っているアプリ
+ */
+export type MicrophoneUser = { 
+/**
+ * OS の同意ストアからしか分からない場合は `None`
+ */
+pid: number | null; exe_name: string; process_name: string; 
+/**
+ * 録音しているデバイス。同意ストアからは分からない
+ */
+device_id: string | null }
+/**
+ * アプリ（子プロセスを含む）の音を、元の出力先とは別のデバイスにも流すミラー
+ */
+export type MirrorRequest = { pid: number; 
+/**
+ * 複製を流す出力デバイス
+ */
+device_id: string; 
+/**
+ * 複製を遅らせる時間 (ms)。出力先ごとの遅れの違いを揃えるために使います
+ */
+delay_ms: number }
+export type MirrorStopped = { pid: number; error: string | null }
+/**
+ * 動いている配信
+ */
+export type NetworkStream = { request: NetworkStreamRequest; 
+/**
+ * 聴くための URL。LAN に公開しているときは LAN 側のアドレス
+ */
+url: string; 
+/**
+ * いま聴いている数
+ */
+listeners: number }
+export type NetworkStreamRequest = { source: CaptureSource; 
+/**
+ * 待ち受けるポート。0 なら空いているポートを使います
+ */
+port: number; 
+/**
+ * LAN のほかの機器からの接続を受け付ける。`false` ならこの PC からだけ
+ */
+allow_lan: boolean; 
+/**
+ * `None` なら [`DEFAULT_CAPTURE_SAMPLE_RATE`]
+ */
+sample_rate: number | null; 
+/**
+ * 1 か 2
+ */
+channels: number }
+export type NetworkStreamStopped = { error: string | null }
+/**
+ * アプリが OS のメディアコントロールに出している再生中の曲と、受け付ける操作
+ */
+export type NowPlaying = { title: string; artist: string; album: string; status: PlaybackStatus; can_play: boolean; can_pause: boolean; can_next: boolean; can_previous: boolean }
+/**
+ * ジェネレーターの出力のしかた
+ */
+export type OutputFormat = { 
+/**
+ * 出力するフォーマット。`None` ならデバイスのミックスフォーマット。
+ * 共有モードでは OS がミックスフォーマットに変換します
+ */
+format: DeviceFormat | null; 
+/**
+ * `format` のまま排他モードで出力し、OS のミキサーとリサンプラーを通しません
+ */
+exclusive: boolean; 
+/**
+ * バッファの長さ (ms)。`None` なら共有モードでは 50ms、排他モードではデバイスの既定の周期
+ */
+buffer_ms: number | null }
+export type PeakLevel = { pid: number; peak: number }
+export type PlaybackProgress = { position_ms: number; duration_ms: number | null }
+/**
+ * メディアの再生状態
+ */
+export type PlaybackStatus = "Playing" | "Paused" | "Stopped"
+export type PlaybackStopped = { error: string | null }
+export type ProcessExited = { pid: number; exe_name: string }
+export type ProcessStarted = { pid: number; exe_name: string }
+/**
+ * 名前を付けて保存したミキサーの状態。`None` の項目と、含まれていないアプリは変えない
+ */
+export type Profile = { name: string; 
+/**
+ * マスター音量とミュート
+ */
+master: AppLevel | null; default_device: string | null; 
+/**
+ * 実行ファイル名（大文字）ごとの音量とミュート
+ */
+apps: Partial<{ [key in string]: AppLevel }>; 
+/**
+ * 実行ファイル名（大文字）ごとの出力先
+ */
+routes: Partial<{ [key in string]: string }> }
+export type ProfileList = { profiles: Profile[]; startup_profile: string | null }
+/**
+ * デバイスの一覧が変わった。ペイロードはない
+ */
+export type RefreshTrigger = null
+export type RemovedSession = { process_id: number; device_id: string; instance_id: string }
+/**
+ * ファイルを分ける条件。どちらかに達したら次のファイルに移ります
+ */
+export type Rotation = { max_bytes: number | null; max_ms: number | null }
+/**
+ * 出力先の切り替えの結果
+ */
+export type RouteMigration = 
+/**
+ * 永続化しただけで反映された
+ */
+"Applied" | 
+/**
+ * ストリームを移すよう促して反映された
+ */
+"Migrated" | 
+/**
+ * 設定は保存されたが、アプリを再起動するまで反映されない
+ */
+"RestartRequired"
+/**
+ * ルールが当てはまるプロセスの条件
+ */
+export type RuleMatch = 
+/**
+ * いずれかのウィンドウのタイトルに合う正規表現
+ */
+{ WindowTitle: string } | 
+/**
+ * コマンドラインに含まれる文字列。大文字と小文字は区別しない
+ */
+{ CommandLine: string }
+/**
+ * トレイから開くミキサーを置く作業領域の角
+ */
+export type ScreenCorner = "TopLeft" | "TopRight" | "BottomLeft" | "BottomRight"
+export type SessionIconReady = { pid: number; icon_base64: string; accent_color: string | null }
+/**
+ * セッションの状態。各プラットフォームの状態をこの 3 つに揃えます。
+ */
+export type SessionState = "Active" | "Inactive" | "Expired"
+export type SessionStateChange = { pid: number; state: string }
+export type SessionStreamMessage = 
+/**
+ * アイコンを含むセッション一覧の全体。これまでの一覧を置き換える
+ */
+{ kind: "Snapshot"; data: AudioSessionInfo[] } | 
+/**
+ * 前回のメッセージからの差分
+ */
+{ kind: "Diff"; data: SessionsDiff }
+export type SessionsChanged = SessionsDiff
+/**
+ * `sessions-changed` イベントのペイロード。前回の通知からの差分だけを含みます。
+ */
+export type SessionsDiff = { added: AudioSessionInfo[]; updated: AudioSessionInfo[]; removed: RemovedSession[] }
+/**
+ * 再生する信号
+ */
+export type Signal = { Tone: ToneRequest } | 
+/**
+ * 同じ波形の複数の音を同時に鳴らします
+ */
+{ Chord: { waveform: Waveform; tones: ChordTone[]; 
+/**
+ * 再生時間（ミリ秒）。`None` なら止めるまで再生します
+ */
+duration_ms: number | null } } | 
+/**
+ * DTMF の数字を順に鳴らします。各数字は 2 つの正弦波を `amplitude` の半分ずつで重ねたもの
+ */
+{ Dtmf: { digits: string; amplitude: number; 
+/**
+ * 1 つの数字を鳴らす時間（ミリ秒）
+ */
+tone_ms: number; 
+/**
+ * 数字の間の無音（ミリ秒）
+ */
+gap_ms: number } } | 
+/**
+ * 周波数を上げていく正弦波。周波数特性の測定に使います
+ */
+{ Sweep: Sweep } | 
+/**
+ * 止めるまで拍を刻みます。テンポは再生中に変えられます
+ */
+{ Metronome: Metronome } | 
+/**
+ * 決まった時間ノイズを鳴らし、終わったら止まります
+ */
+{ BurnIn: BurnIn } | 
+/**
+ * 音声ファイル（WAV / FLAC / Ogg Vorbis / MP3）を鳴らします。再生中にシークできます
+ */
+{ File: { path: string } }
+/**
+ * サウンドのテーマ。`id` は ".Default" や ".None" のようなレジストリ上の名前
+ */
+export type SoundScheme = { id: string; name: string }
+export type SoundSchemes = { 
+/**
+ * 使用中のテーマの ID
+ */
+current: string; schemes: SoundScheme[] }
+/**
+ * 開くページ。前半は設定アプリ、後半はコントロールパネルのサウンド (mmsys.cpl) のタブ
+ */
+export type SoundSettingsPage = "Sound" | 
+/**
+ * デバイスの有効・無効の管理
+ */
+"SoundDevices" | 
+/**
+ * アプリごとの音量と出力先
+ */
+"AppVolume" | "Playback" | "Recording" | "Sounds" | "Communications"
+/**
+ * `start_hz` から `end_hz` まで、周波数を指数的に（1 オクターブあたり同じ時間で）上げていく正弦波
+ */
+export type Sweep = { start_hz: number; end_hz: number; 
+/**
+ * 0.0〜1.0 の振幅
+ */
+amplitude: number; duration_ms: number }
+/**
+ * 同期再生の出力先の 1 つ
+ */
+export type SyncedOutput = { device_id: string; 
+/**
+ * このデバイスだけ遅らせる時間 (ms)。Bluetooth のスピーカーとの遅れの違いを揃えるために使います
+ */
+delay_ms: number }
+export type TAURI_CHANNEL<TSend> = null
+export type ToneRequest = { waveform: Waveform; 
+/**
+ * 周波数 (Hz)。ノイズでは使いません
+ */
+frequency: number; 
+/**
+ * 0.0〜1.0 の振幅
+ */
+amplitude: number; 
+/**
+ * 再生時間（ミリ秒）。`None` なら止めるまで再生します
+ */
+duration_ms: number | null }
+export type UndoStatus = { can_undo: boolean; can_redo: boolean }
+export type UpdateInfo = { version: string; current_version: string; notes: string | null; date: string | null }
+/**
+ * 集計する期間。いまから遡ります
+ */
+export type UsageRange = "Day" | "Week" | "Month"
+export type UsageStats = { 
+/**
+ * 再生時間の長い順
+ */
+apps: AppUsage[]; hours: HourlyUsage[] }
+/**
+ * 見つかった仮想ケーブル
+ */
+export type VirtualCable = { kind: VirtualCableKind; 
+/**
+ * 再生デバイスの名前
+ */
+name: string; 
+/**
+ * アプリの出力先にする再生デバイス
+ */
+playback_device_id: string; 
+/**
+ * 流した音が出てくる録音デバイス。無効にされているなどで見つからなければ `None`
+ */
+recording_device_id: string | null }
+export type VirtualCableKind = 
+/**
+ * VB-Cable（A+B、Hi-Fi Cable を含む）
+ */
+"VbCable" | "VoiceMeeter"
+export type VoiceMeeterState = { edition: Edition; 
+/**
+ * ストリップ、バスの順
+ */
+channels: Channel[] }
+export type VolumeChange = { pid: number; volume: number; muted: boolean; origin: ChangeOrigin }
+export type VolumeLimitStatus = { 
+/**
+ * 0.0〜1.0。`None` なら上限なし
+ */
+limit: number | null; 
+/**
+ * 一時解除中なら残り秒数
+ */
+override_remaining_secs: number | null }
+/**
+ * ホットキー・ホイールで 1 回に変える音量
+ */
+export type VolumeStep = "Percent1" | "Percent2" | "Percent5"
+export type VolumeStepping = { step: VolumeStep; 
+/**
+ * 25・50・75・100% をまたぐときは、いったんそこで止める
+ */
+snap: boolean }
+/**
+ * トーンの波形
+ */
+export type Waveform = "Sine" | "Square" | "Triangle" | "Sawtooth" | "WhiteNoise" | "PinkNoise"
+
+/** tauri-specta globals **/
+
+import {
+	invoke as TAURI_INVOKE,
+	Channel as TAURI_CHANNEL,
+} from "@tauri-apps/api/core";
+import * as TAURI_API_EVENT from "@tauri-apps/api/event";
+import { type WebviewWindow as __WebviewWindow__ } from "@tauri-apps/api/webviewWindow";
+
+type __EventObj__<T> = {
+	listen: (
+		cb: TAURI_API_EVENT.EventCallback<T>,
+	) => ReturnType<typeof TAURI_API_EVENT.listen<T>>;
+	once: (
+		cb: TAURI_API_EVENT.EventCallback<T>,
+	) => ReturnType<typeof TAURI_API_EVENT.once<T>>;
+	emit: null extends T
+		? (payload?: T) => ReturnType<typeof TAURI_API_EVENT.emit>
+		: (payload: T) => ReturnType<typeof TAURI_API_EVENT.emit>;
+};
+
+export type Result<T, E> =
+	| { status: "ok"; data: T }
+	| { status: "error"; error: E };
+
+function __makeEvents__<T extends Record<string, any>>(
+	mappings: Record<keyof T, string>,
+) {
+	return new Proxy(
+		{} as unknown as {
+			[K in keyof T]: __EventObj__<T[K]> & {
+				(handle: __WebviewWindow__): __EventObj__<T[K]>;
+			};
+		},
+		{
+			get: (_, event) => {
+				const name = mappings[event as keyof T];
+
+				return new Proxy((() => {}) as any, {
+					apply: (_, __, [window]: [__WebviewWindow__]) => ({
+						listen: (arg: any) => window.listen(name, arg),
+						once: (arg: any) => window.once(name, arg),
+						emit: (arg: any) => window.emit(name, arg),
+					}),
+					get: (_, command: keyof __EventObj__<any>) => {
+						switch (command) {
+							case "listen":
+								return (arg: any) => TAURI_API_EVENT.listen(name, arg);
+							case "once":
+								return (arg: any) => TAURI_API_EVENT.once(name, arg);
+							case "emit":
+								return (arg: any) => TAURI_API_EVENT.emit(name, arg);
+						}
+					},
+				});
+			},
+		},
+	);
+}
@@ -1,10 +1,15 @@
 import React from "react";
 import ReactDOM from "react-dom/client";
+import { getCurrentWindow } from "@tauri-apps/api/window";
 import App from "./App";
+import Hud from "./Hud";
+import ErrorBoundary from "./ErrorBoundary";
 import "./index.css";
 
 ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
   <React.StrictMode>
-    <App />
+    <ErrorBoundary>
+      {getCurrentWindow().label === "hud" ? <Hud /> : <App />}
+    </ErrorBoundary>
   </React.StrictMode>,
 );